@@ -21,11 +21,17 @@ fn vert(@location(0) in_pos: vec3<f32>,
         @location(4) in_tangent: vec4<f32>,
         @location(5) in_instance_pos: vec3<f32>,
         @location(6) in_instance_dir: vec3<f32>,
-        @location(7) in_instance_tint: vec4<f32>) -> VertexOutput {
+        @location(7) in_instance_tint: vec4<f32>,
+        @location(8) in_instance_roll: f32) -> VertexOutput {
     let s: f32 = length(in_instance_dir);
     let x: vec3<f32> = in_instance_dir / s;
-    let y: vec3<f32> = normalize(vec3(-x.y, x.x, 0.0)); // Z up
-    let z: vec3<f32> = normalize(cross(x, y));
+    let y0: vec3<f32> = normalize(vec3(-x.y, x.x, 0.0)); // Z up
+    let z0: vec3<f32> = normalize(cross(x, y0));
+
+    let c: f32 = cos(in_instance_roll);
+    let sn: f32 = sin(in_instance_roll);
+    let y: vec3<f32> = c * y0 + sn * z0;
+    let z: vec3<f32> = c * z0 - sn * y0;
 
     let off: vec3<f32> = s * (in_pos.x * x + in_pos.y * y + in_pos.z * z) + in_instance_pos;
     let normal: vec3<f32> = in_normal.x * x + in_normal.y * y + in_normal.z * z;
@@ -170,6 +170,7 @@ fn create_shown(gfx: &mut GfxContext, state: &State, inspected: Inspected) -> Sh
                             pos: Vec3::x(i as f32 * size * 2.0),
                             dir: Vec3::X,
                             tint: LinearColor::WHITE,
+                            roll: 0.0,
                         });
 
                         meshes.push(unwrap_cont!(b.build(gfx)));
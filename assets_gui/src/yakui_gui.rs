@@ -4,7 +4,7 @@ use yakui::{
     Constraints, CrossAxisAlignment, MainAxisAlignment, MainAxisSize, Response, Vec2,
 };
 
-use common::descriptions::{BuildingGen, CompanyKind};
+use common::descriptions::{BuildingGen, CompanyKind, DoorLayout};
 use engine::meshload::CPUMesh;
 use engine::wgpu::RenderPass;
 use engine::{set_cursor_icon, CursorIcon, Drawable, GfxContext, InstancedMesh, Mesh, SpriteBatch};
@@ -309,7 +309,7 @@ impl State {
 
                     label("Building generator");
                     let mut selected = match comp.bgen {
-                        BuildingGen::House => unreachable!(),
+                        BuildingGen::House | BuildingGen::StyledHouse(_) => unreachable!(),
                         BuildingGen::Farm => 0,
                         BuildingGen::CenteredDoor { .. } => 1,
                         BuildingGen::NoWalkway { .. } => 2,
@@ -325,11 +325,13 @@ impl State {
                             1 => {
                                 comp.bgen = BuildingGen::CenteredDoor {
                                     vertical_factor: 1.0,
+                                    doors: DoorLayout::DRIVEWAY,
                                 }
                             }
                             2 => {
                                 comp.bgen = BuildingGen::NoWalkway {
                                     door_pos: geom::Vec2::ZERO,
+                                    doors: DoorLayout::NONE,
                                 }
                             }
                             _ => unreachable!(),
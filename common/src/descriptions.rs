@@ -1,5 +1,6 @@
+use crate::expr::Expr;
 use egui_inspect::debug_inspect_impl;
-use geom::Vec2;
+use geom::{Color, Vec2};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -27,15 +28,104 @@ debug_inspect_impl!(CompanyKind);
 #[serde(tag = "kind", rename_all = "snake_case")]
 pub enum BuildingGen {
     House,
+    /// A house with the parameters of a building archetype
+    StyledHouse(HouseStyle),
     Farm,
     CenteredDoor {
         vertical_factor: f32, // 1.0 means that the door is at the bottom, just on the street
+        #[serde(default = "DoorLayout::driveway")]
+        doors: DoorLayout,
     },
     NoWalkway {
         door_pos: Vec2, // door_pos is relative to the center of the building
+        #[serde(default)]
+        doors: DoorLayout,
     },
 }
 
+impl BuildingGen {
+    /// Where the vehicle doors of the generated building are
+    pub fn doors(&self) -> DoorLayout {
+        match *self {
+            BuildingGen::House | BuildingGen::StyledHouse(_) => DoorLayout::DRIVEWAY,
+            BuildingGen::Farm => DoorLayout::FREIGHT,
+            BuildingGen::CenteredDoor { doors, .. } | BuildingGen::NoWalkway { doors, .. } => doors,
+        }
+    }
+}
+
+/// Vehicle doors on the front of a building, as positions along the front relative to its size,
+/// -0.5 being the left corner and 0.5 the right one
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct DoorLayout {
+    /// Where trucks load and unload goods, away from the pedestrian entrance
+    #[serde(default)]
+    pub freight_dock: Option<f32>,
+    /// Where vehicles kept inside the building come out onto the road
+    #[serde(default)]
+    pub driveway: Option<f32>,
+}
+
+impl DoorLayout {
+    pub const NONE: DoorLayout = DoorLayout {
+        freight_dock: None,
+        driveway: None,
+    };
+
+    pub const DRIVEWAY: DoorLayout = DoorLayout {
+        freight_dock: None,
+        driveway: Some(-0.35),
+    };
+
+    /// The freight dock is on the other side of the front from the driveway
+    pub const FREIGHT: DoorLayout = DoorLayout {
+        freight_dock: Some(0.35),
+        driveway: Some(-0.35),
+    };
+
+    fn driveway() -> Self {
+        Self::DRIVEWAY
+    }
+}
+
+/// Procedural parameters of a house, building archetypes use them so that houses don't look identical
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct HouseStyle {
+    /// Size of the house relative to the default one
+    #[serde(default = "one")]
+    pub footprint: f32,
+    #[serde(default = "one_u32")]
+    pub min_floors: u32,
+    #[serde(default = "one_u32")]
+    pub max_floors: u32,
+    /// Uses the house color of the config if not set
+    #[serde(default)]
+    pub wall_col: Option<Color>,
+    /// Uses the roof color of the config if not set
+    #[serde(default)]
+    pub roof_col: Option<Color>,
+}
+
+impl Default for HouseStyle {
+    fn default() -> Self {
+        Self {
+            footprint: 1.0,
+            min_floors: 1,
+            max_floors: 1,
+            wall_col: None,
+            roof_col: None,
+        }
+    }
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn one_u32() -> u32 {
+    1
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct GoodsCompanyDescriptionJSON {
     pub name: String,
@@ -49,6 +139,95 @@ pub struct GoodsCompanyDescriptionJSON {
     pub price: i64,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub zone: Option<Box<ZoneDescription>>,
+    /// Always open if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opening_hours: Option<OpeningHours>,
+    /// Wether some of the workers work at night
+    #[serde(default)]
+    pub night_shift: bool,
+    /// Education the workers need to be fully productive
+    #[serde(default)]
+    pub qualification: EducationLevel,
+    /// Schools raise the education of the people living nearby up to this level
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub teaches: Option<EducationLevel>,
+    /// Public service provided to the buildings nearby, lowering their risk of incidents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<ServiceKind>,
+    /// Restaurants, bars and cinemas where people spend their evenings out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leisure: Option<LeisureDescription>,
+    /// Formula of the productivity, 1 when producing at full speed, of `workers` (unqualified
+    /// ones count partially), `max_workers` and `zone` (the zone area relative to the maximum).
+    /// `workers / max_workers * zone` if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub productivity: Option<Expr>,
+    /// Formula of the wage of the workers relative to the base wage, of `qualification`
+    /// (0 for basic, 1 for secondary and 2 for higher education). 1 if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wage: Option<Expr>,
+}
+
+/// Public services covering the buildings around them
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceKind {
+    Fire,
+    Police,
+    Health,
+}
+
+debug_inspect_impl!(ServiceKind);
+
+impl ServiceKind {
+    pub const ALL: [ServiceKind; 3] = [ServiceKind::Fire, ServiceKind::Police, ServiceKind::Health];
+}
+
+/// Highest schooling completed by a person, also used as the qualification required by jobs
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum EducationLevel {
+    #[default]
+    Basic,
+    Secondary,
+    Higher,
+}
+
+debug_inspect_impl!(EducationLevel);
+
+impl EducationLevel {
+    pub const ALL: [EducationLevel; 3] = [
+        EducationLevel::Basic,
+        EducationLevel::Secondary,
+        EducationLevel::Higher,
+    ];
+
+    pub fn next(self) -> Option<EducationLevel> {
+        match self {
+            EducationLevel::Basic => Some(EducationLevel::Secondary),
+            EducationLevel::Secondary => Some(EducationLevel::Higher),
+            EducationLevel::Higher => None,
+        }
+    }
+}
+
+/// Capacity and price of a leisure venue
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct LeisureDescription {
+    /// How many people fit at once, the others queue at the door
+    pub capacity: u32,
+    /// What an evening costs to the visitors, in bucks
+    pub price: i64,
+}
+
+/// Hours between which a company is open, it closes the next day if `close` is before `open`
+/// (e.g. a bar open from 18 to 2)
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct OpeningHours {
+    pub open: i32,
+    pub close: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -0,0 +1,379 @@
+//! Small arithmetic expressions read from the data files, so that mods can change how the
+//! economy behaves and not only its constants.
+//!
+//! Expressions are parsed when the files are loaded and evaluated with named variables, e.g.
+//! `"min(workers, max_workers) / max_workers"`.
+//! They support numbers, variables, `+ - * / ^`, parentheses and the functions
+//! `min(a, b)`, `max(a, b)`, `clamp(x, lo, hi)`, `abs(x)`, `sqrt(x)`, `exp(x)` and `ln(x)`.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Display, Formatter};
+
+/// A parsed expression, serialized as its source text
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Expr {
+    source: String,
+    node: Node,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Num(f32),
+    Var(String),
+    Neg(Box<Node>),
+    Bin(Op, Box<Node>, Box<Node>),
+    Call(Func, Vec<Node>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Func {
+    Min,
+    Max,
+    Clamp,
+    Abs,
+    Sqrt,
+    Exp,
+    Ln,
+}
+
+impl Func {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "min" => Func::Min,
+            "max" => Func::Max,
+            "clamp" => Func::Clamp,
+            "abs" => Func::Abs,
+            "sqrt" => Func::Sqrt,
+            "exp" => Func::Exp,
+            "ln" => Func::Ln,
+            _ => return None,
+        })
+    }
+
+    fn n_args(self) -> usize {
+        match self {
+            Func::Min | Func::Max => 2,
+            Func::Clamp => 3,
+            Func::Abs | Func::Sqrt | Func::Exp | Func::Ln => 1,
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+        };
+        let node = parser.expr()?;
+        if let Some(t) = parser.peek() {
+            return Err(format!("unexpected {t:?} in \"{source}\""));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            node,
+        })
+    }
+
+    /// Evaluates the expression, variables missing from `vars` are 0
+    pub fn eval(&self, vars: &[(&str, f32)]) -> f32 {
+        self.node.eval(vars)
+    }
+
+    /// Checks that the expression only uses the given variables
+    pub fn check_vars(&self, vars: &[&str]) -> Result<(), String> {
+        let mut unknown = vec![];
+        self.node.visit_vars(&mut |v| {
+            if !vars.contains(&v) && !unknown.contains(&v.to_string()) {
+                unknown.push(v.to_string());
+            }
+        });
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        Err(format!(
+            "unknown variables {} in \"{}\", expected some of {}",
+            unknown.join(", "),
+            self.source,
+            vars.join(", ")
+        ))
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+impl Node {
+    fn eval(&self, vars: &[(&str, f32)]) -> f32 {
+        match self {
+            Node::Num(x) => *x,
+            Node::Var(name) => vars
+                .iter()
+                .find(|(v, _)| v == name)
+                .map_or(0.0, |&(_, x)| x),
+            Node::Neg(x) => -x.eval(vars),
+            Node::Bin(op, a, b) => {
+                let (a, b) = (a.eval(vars), b.eval(vars));
+                match op {
+                    Op::Add => a + b,
+                    Op::Sub => a - b,
+                    Op::Mul => a * b,
+                    Op::Div => a / b,
+                    Op::Pow => a.powf(b),
+                }
+            }
+            Node::Call(f, args) => {
+                let arg = |i: usize| args[i].eval(vars);
+                match f {
+                    Func::Min => arg(0).min(arg(1)),
+                    Func::Max => arg(0).max(arg(1)),
+                    Func::Clamp => arg(0).max(arg(1)).min(arg(2)),
+                    Func::Abs => arg(0).abs(),
+                    Func::Sqrt => arg(0).sqrt(),
+                    Func::Exp => arg(0).exp(),
+                    Func::Ln => arg(0).ln(),
+                }
+            }
+        }
+    }
+
+    fn visit_vars<'a>(&'a self, f: &mut impl FnMut(&'a str)) {
+        match self {
+            Node::Num(_) => {}
+            Node::Var(name) => f(name),
+            Node::Neg(x) => x.visit_vars(f),
+            Node::Bin(_, a, b) => {
+                a.visit_vars(f);
+                b.visit_vars(f);
+            }
+            Node::Call(_, args) => {
+                for arg in args {
+                    arg.visit_vars(f);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(f32),
+    Ident(String),
+    Sym(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let num = &source[start..end];
+            let num = num
+                .parse()
+                .map_err(|_| format!("invalid number {num} in \"{source}\""))?;
+            tokens.push(Token::Num(num));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Ident(source[start..end].to_string()));
+        } else if "+-*/^(),".contains(c) {
+            tokens.push(Token::Sym(c));
+            chars.next();
+        } else {
+            return Err(format!("unexpected character {c:?} in \"{source}\""));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser, from the lowest precedence to the highest
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn eat(&mut self, sym: char) -> bool {
+        if self.peek() == Some(&Token::Sym(sym)) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, sym: char) -> Result<(), String> {
+        if self.eat(sym) {
+            return Ok(());
+        }
+        Err(format!("expected '{sym}', found {:?}", self.peek()))
+    }
+
+    fn expr(&mut self) -> Result<Node, String> {
+        let mut node = self.term()?;
+        loop {
+            let op = if self.eat('+') {
+                Op::Add
+            } else if self.eat('-') {
+                Op::Sub
+            } else {
+                return Ok(node);
+            };
+            node = Node::Bin(op, Box::new(node), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                Op::Mul
+            } else if self.eat('/') {
+                Op::Div
+            } else {
+                return Ok(node);
+            };
+            node = Node::Bin(op, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.eat('-') {
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+        let node = self.atom()?;
+        // right associative: 2^3^2 = 2^(3^2)
+        if self.eat('^') {
+            return Ok(Node::Bin(Op::Pow, Box::new(node), Box::new(self.unary()?)));
+        }
+        Ok(node)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Num(x)) => Ok(Node::Num(x)),
+            Some(Token::Ident(name)) => {
+                if !self.eat('(') {
+                    return Ok(Node::Var(name));
+                }
+                let f = Func::parse(&name).ok_or_else(|| format!("unknown function {name}"))?;
+                let mut args = vec![];
+                if !self.eat(')') {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(')') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                if args.len() != f.n_args() {
+                    return Err(format!(
+                        "{name} takes {} arguments, got {}",
+                        f.n_args(),
+                        args.len()
+                    ));
+                }
+                Ok(Node::Call(f, args))
+            }
+            Some(Token::Sym('(')) => {
+                let node = self.expr()?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            t => Err(format!("unexpected {t:?}")),
+        }
+    }
+}
+
+impl TryFrom<String> for Expr {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Self::parse(&source)
+    }
+}
+
+impl From<Expr> for String {
+    fn from(expr: Expr) -> Self {
+        expr.source
+    }
+}
+
+impl Debug for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Expr({:?})", self.source)
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Expr;
+
+    #[test]
+    fn parse_and_eval() {
+        let eval = |s: &str, vars: &[(&str, f32)]| Expr::parse(s).unwrap().eval(vars);
+
+        assert_eq!(eval("1 + 2 * 3", &[]), 7.0);
+        assert_eq!(eval("(1 + 2) * 3", &[]), 9.0);
+        assert_eq!(eval("-2^2", &[]), -4.0);
+        assert_eq!(eval("2^3^2", &[]), 512.0);
+        assert_eq!(eval("10 - 4 - 3", &[]), 3.0);
+        assert_eq!(eval("min(x, 3) + max(x, 3)", &[("x", 5.0)]), 8.0);
+        assert_eq!(eval("clamp(x / 2, 0, 1)", &[("x", 5.0)]), 1.0);
+        assert_eq!(eval("sqrt(abs(-16))", &[]), 4.0);
+        assert_eq!(eval("unknown", &[]), 0.0);
+
+        assert!(Expr::parse("1 +").is_err());
+        assert!(Expr::parse("min(1)").is_err());
+        assert!(Expr::parse("foo(1)").is_err());
+        assert!(Expr::parse("1 $ 2").is_err());
+        assert!(Expr::parse("(1").is_err());
+
+        let e = Expr::parse("workers / max_workers").unwrap();
+        assert!(e.check_vars(&["workers", "max_workers"]).is_ok());
+        assert!(e.check_vars(&["workers"]).is_err());
+    }
+}
@@ -0,0 +1,400 @@
+//! Flattens any serializable value into a list of `(path, value)` leaves, e.g.
+//! `("trans.position.x", "12.5")`.
+//! Used to compare values field by field without them implementing `Debug` or `PartialEq`.
+
+use serde::ser::{
+    self, Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+    SerializeTuple, SerializeTupleStruct, SerializeTupleVariant, Serializer,
+};
+use std::fmt::{Debug, Display, Formatter};
+
+pub fn flatten<T: Serialize + ?Sized>(v: &T) -> Vec<(String, String)> {
+    let mut f = Flattener::default();
+    if let Err(e) = v.serialize(&mut f) {
+        f.out.push((f.path.clone(), format!("<error: {}>", e)));
+    }
+    f.out
+}
+
+/// Finds the first leaf that differs between two flattened values, with the values on both sides.
+/// A missing leaf is shown as `<none>`.
+pub fn first_difference(
+    a: &[(String, String)],
+    b: &[(String, String)],
+) -> Option<(String, String, String)> {
+    let none = || "<none>".to_string();
+    for i in 0..a.len().max(b.len()) {
+        match (a.get(i), b.get(i)) {
+            (Some(x), Some(y)) if x == y => continue,
+            (Some((pa, va)), Some((pb, vb))) if pa == pb => {
+                return Some((pa.clone(), va.clone(), vb.clone()))
+            }
+            (Some((pa, va)), Some((pb, vb))) => {
+                return Some((format!("{} / {}", pa, pb), va.clone(), vb.clone()))
+            }
+            (Some((pa, va)), None) => return Some((pa.clone(), va.clone(), none())),
+            (None, Some((pb, vb))) => return Some((pb.clone(), none(), vb.clone())),
+            (None, None) => break,
+        }
+    }
+    None
+}
+
+#[derive(Default)]
+struct Flattener {
+    out: Vec<(String, String)>,
+    path: String,
+}
+
+#[derive(Debug)]
+pub struct FlattenError(String);
+
+impl Display for FlattenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for FlattenError {}
+
+impl ser::Error for FlattenError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl Flattener {
+    fn leaf(&mut self, v: impl Debug) -> Result<(), FlattenError> {
+        self.out.push((self.path.clone(), format!("{:?}", v)));
+        Ok(())
+    }
+
+    /// Serializes `v` with `segment` appended to the path
+    fn field<T: Serialize + ?Sized>(&mut self, segment: &str, v: &T) -> Result<(), FlattenError> {
+        let len = self.path.len();
+        if !self.path.is_empty() && !segment.starts_with('[') {
+            self.path.push('.');
+        }
+        self.path.push_str(segment);
+        let r = v.serialize(&mut *self);
+        self.path.truncate(len);
+        r
+    }
+
+    fn compound(&mut self) -> Compound<'_> {
+        Compound {
+            f: self,
+            idx: 0,
+            key: None,
+        }
+    }
+}
+
+struct Compound<'a> {
+    f: &'a mut Flattener,
+    idx: usize,
+    key: Option<String>,
+}
+
+impl Compound<'_> {
+    fn element<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), FlattenError> {
+        let segment = format!("[{}]", self.idx);
+        self.idx += 1;
+        self.f.field(&segment, v)
+    }
+}
+
+impl<'a> Serializer for &'a mut Flattener {
+    type Ok = ();
+    type Error = FlattenError;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), FlattenError> {
+        self.leaf(v)
+    }
+    fn serialize_i8(self, v: i8) -> Result<(), FlattenError> {
+        self.leaf(v)
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), FlattenError> {
+        self.leaf(v)
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), FlattenError> {
+        self.leaf(v)
+    }
+    fn serialize_i64(self, v: i64) -> Result<(), FlattenError> {
+        self.leaf(v)
+    }
+    fn serialize_u8(self, v: u8) -> Result<(), FlattenError> {
+        self.leaf(v)
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), FlattenError> {
+        self.leaf(v)
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), FlattenError> {
+        self.leaf(v)
+    }
+    fn serialize_u64(self, v: u64) -> Result<(), FlattenError> {
+        self.leaf(v)
+    }
+    fn serialize_f32(self, v: f32) -> Result<(), FlattenError> {
+        self.leaf(v)
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), FlattenError> {
+        self.leaf(v)
+    }
+    fn serialize_char(self, v: char) -> Result<(), FlattenError> {
+        self.leaf(v)
+    }
+    fn serialize_str(self, v: &str) -> Result<(), FlattenError> {
+        self.leaf(v)
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), FlattenError> {
+        self.leaf(v)
+    }
+    fn serialize_none(self) -> Result<(), FlattenError> {
+        self.out.push((self.path.clone(), "None".to_string()));
+        Ok(())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, v: &T) -> Result<(), FlattenError> {
+        v.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), FlattenError> {
+        self.out.push((self.path.clone(), "()".to_string()));
+        Ok(())
+    }
+    fn serialize_unit_struct(self, name: &'static str) -> Result<(), FlattenError> {
+        self.out.push((self.path.clone(), name.to_string()));
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<(), FlattenError> {
+        self.out.push((self.path.clone(), variant.to_string()));
+        Ok(())
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        v: &T,
+    ) -> Result<(), FlattenError> {
+        v.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        v: &T,
+    ) -> Result<(), FlattenError> {
+        self.out.push((self.path.clone(), variant.to_string()));
+        self.field(variant, v)
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Compound<'a>, FlattenError> {
+        Ok(self.compound())
+    }
+    fn serialize_tuple(self, _: usize) -> Result<Compound<'a>, FlattenError> {
+        Ok(self.compound())
+    }
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Compound<'a>, FlattenError> {
+        Ok(self.compound())
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Compound<'a>, FlattenError> {
+        self.out.push((self.path.clone(), variant.to_string()));
+        Ok(self.compound())
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<Compound<'a>, FlattenError> {
+        Ok(self.compound())
+    }
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Compound<'a>, FlattenError> {
+        Ok(self.compound())
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Compound<'a>, FlattenError> {
+        self.out.push((self.path.clone(), variant.to_string()));
+        Ok(self.compound())
+    }
+}
+
+impl SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = FlattenError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), FlattenError> {
+        self.element(v)
+    }
+    fn end(self) -> Result<(), FlattenError> {
+        Ok(())
+    }
+}
+
+impl SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = FlattenError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), FlattenError> {
+        self.element(v)
+    }
+    fn end(self) -> Result<(), FlattenError> {
+        Ok(())
+    }
+}
+
+impl SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = FlattenError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), FlattenError> {
+        self.element(v)
+    }
+    fn end(self) -> Result<(), FlattenError> {
+        Ok(())
+    }
+}
+
+impl SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = FlattenError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), FlattenError> {
+        self.element(v)
+    }
+    fn end(self) -> Result<(), FlattenError> {
+        Ok(())
+    }
+}
+
+impl SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = FlattenError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), FlattenError> {
+        let leaves = flatten(key);
+        let key = leaves
+            .into_iter()
+            .map(|(_, v)| v)
+            .collect::<Vec<_>>()
+            .join(",");
+        self.key = Some(format!("[{}]", key));
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), FlattenError> {
+        let key = self.key.take().unwrap_or_default();
+        self.f.field(&key, v)
+    }
+    fn end(self) -> Result<(), FlattenError> {
+        Ok(())
+    }
+}
+
+impl SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = FlattenError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        v: &T,
+    ) -> Result<(), FlattenError> {
+        self.f.field(key, v)
+    }
+    fn end(self) -> Result<(), FlattenError> {
+        Ok(())
+    }
+}
+
+impl SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = FlattenError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        v: &T,
+    ) -> Result<(), FlattenError> {
+        self.f.field(key, v)
+    }
+    fn end(self) -> Result<(), FlattenError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    enum Kind {
+        Walking { speed: f32 },
+        Idle,
+    }
+
+    #[derive(Serialize)]
+    struct Ent {
+        pos: (f32, f32),
+        kind: Kind,
+        tags: BTreeMap<u32, String>,
+        target: Option<u32>,
+    }
+
+    #[test]
+    fn test_flatten_and_diff() {
+        let a = Ent {
+            pos: (1.0, 2.0),
+            kind: Kind::Walking { speed: 1.5 },
+            tags: [(3, "a".to_string())].into_iter().collect(),
+            target: None,
+        };
+        let flat = flatten(&a);
+        assert_eq!(
+            flat,
+            vec![
+                ("pos[0]".to_string(), "1.0".to_string()),
+                ("pos[1]".to_string(), "2.0".to_string()),
+                ("kind".to_string(), "Walking".to_string()),
+                ("kind.speed".to_string(), "1.5".to_string()),
+                ("tags[3]".to_string(), "\"a\"".to_string()),
+                ("target".to_string(), "None".to_string()),
+            ]
+        );
+
+        let b = Ent {
+            kind: Kind::Idle,
+            ..a
+        };
+        assert_eq!(
+            first_difference(&flat, &flatten(&b)),
+            Some((
+                "kind".to_string(),
+                "Walking".to_string(),
+                "Idle".to_string()
+            ))
+        );
+        assert_eq!(first_difference(&flat, &flat), None);
+    }
+}
@@ -0,0 +1,297 @@
+//! gettext-style translations of the texts shown to the player.
+//!
+//! The texts are written in english in the code and used as the message ids of the translation
+//! files, so untranslated texts are simply shown in english.
+//! Translations are `.po` files in `assets/i18n`, named after their language (`fr.po`).
+//! Mods add their own translations with files named `<language>.<anything>.po`, anywhere in
+//! the folder.
+//! Message ids can contain `{}` placeholders to translate already formatted texts, such as the
+//! messages sent by the simulation. The translation uses `{}` in the same order, or `{0}`, `{1}`
+//! to reorder them.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+pub const I18N_PATH: &str = "assets/i18n";
+/// The language the texts are written in, it needs no translation file
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+static CATALOG: RwLock<Option<Catalog>> = RwLock::new(None);
+
+/// Translates the text to the current language, returns it as is if it has no translation
+pub fn tr(text: &str) -> String {
+    match *CATALOG.read().unwrap() {
+        Some(ref catalog) => catalog.translate(text).into_owned(),
+        None => text.to_string(),
+    }
+}
+
+/// Switches the translations to the given language, does nothing if it is already in use
+pub fn set_language(language: &str) {
+    let current = CATALOG.read().unwrap().as_ref().map(|c| c.language.clone());
+    if current.as_deref().unwrap_or(DEFAULT_LANGUAGE) == language {
+        return;
+    }
+    log::info!("using language {}", language);
+    *CATALOG.write().unwrap() = Some(Catalog::load(Path::new(I18N_PATH), language));
+}
+
+/// The available languages with their name, starting with the default one
+pub fn languages() -> Vec<(String, String)> {
+    let mut languages = vec![(DEFAULT_LANGUAGE.to_string(), "English".to_string())];
+    for path in po_files(Path::new(I18N_PATH)) {
+        let language = language_of(&path);
+        if languages.iter().any(|(l, _)| *l == language) {
+            continue;
+        }
+        let catalog = Catalog::load(Path::new(I18N_PATH), &language);
+        let name = catalog.name.clone().unwrap_or_else(|| language.clone());
+        languages.push((language, name));
+    }
+    languages
+}
+
+fn po_files(dir: &Path) -> Vec<PathBuf> {
+    if !dir.exists() {
+        return vec![];
+    }
+    let mut paths: Vec<_> = crate::saveload::walkdir(dir)
+        .filter(|p| p.extension().map_or(false, |ext| ext == "po"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn language_of(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    stem.split('.').next().unwrap_or_default().to_string()
+}
+
+/// The translations of a language
+#[derive(Default)]
+pub struct Catalog {
+    pub language: String,
+    /// Name of the language in itself, from the `Language-Name` header
+    pub name: Option<String>,
+    exact: BTreeMap<String, String>,
+    /// Message ids with placeholders, split around them
+    templates: Vec<(Vec<String>, String)>,
+}
+
+impl Catalog {
+    /// Loads the translation files of the language, the game's file first so that mods can
+    /// override it
+    pub fn load(dir: &Path, language: &str) -> Self {
+        let mut catalog = Self {
+            language: language.to_string(),
+            ..Default::default()
+        };
+
+        let mut paths: Vec<_> = po_files(dir)
+            .into_iter()
+            .filter(|p| language_of(p) == language)
+            .collect();
+        paths.sort_by_key(|p| (p.file_stem() != Some(OsStr::new(language)), p.clone()));
+
+        for path in paths {
+            let source = match std::fs::read_to_string(&path) {
+                Ok(x) => x,
+                Err(e) => {
+                    log::error!("couldn't read {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            if let Err(e) = catalog.parse(&source) {
+                log::error!("couldn't parse {}: {}", path.display(), e);
+            }
+        }
+
+        catalog
+    }
+
+    /// Adds the entries of a `.po` file, only `msgid` and `msgstr` are supported
+    pub fn parse(&mut self, source: &str) -> Result<(), String> {
+        let mut msgid: Option<String> = None;
+        let mut msgstr: Option<String> = None;
+
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = || format!("line {}: {}", i + 1, line);
+
+            if let Some(rest) = line.strip_prefix("msgid ") {
+                if let (Some(id), Some(s)) = (msgid.take(), msgstr.take()) {
+                    self.add(id, s);
+                }
+                msgid = Some(unquote(rest).ok_or_else(err)?);
+            } else if let Some(rest) = line.strip_prefix("msgstr ") {
+                if msgid.is_none() {
+                    return Err(err());
+                }
+                msgstr = Some(unquote(rest).ok_or_else(err)?);
+            } else if line.starts_with('"') {
+                // continuation of a multiline string
+                let cont = unquote(line).ok_or_else(err)?;
+                match msgstr.as_mut().or(msgid.as_mut()) {
+                    Some(s) => s.push_str(&cont),
+                    None => return Err(err()),
+                }
+            } else {
+                return Err(err());
+            }
+        }
+        if let (Some(id), Some(s)) = (msgid, msgstr) {
+            self.add(id, s);
+        }
+        Ok(())
+    }
+
+    pub fn add(&mut self, msgid: String, msgstr: String) {
+        if msgid.is_empty() {
+            // header
+            for line in msgstr.lines() {
+                if let Some(name) = line.strip_prefix("Language-Name:") {
+                    self.name = Some(name.trim().to_string());
+                }
+            }
+            return;
+        }
+        // empty translations are untranslated
+        if msgstr.is_empty() {
+            return;
+        }
+        if msgid.contains("{}") {
+            let parts = msgid.split("{}").map(str::to_string).collect();
+            self.templates.retain(|(p, _)| *p != parts);
+            self.templates.push((parts, msgstr));
+            return;
+        }
+        self.exact.insert(msgid, msgstr);
+    }
+
+    pub fn translate<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if let Some(t) = self.exact.get(text) {
+            return Cow::Owned(t.clone());
+        }
+        for (parts, msgstr) in &self.templates {
+            if let Some(args) = match_template(parts, text) {
+                return Cow::Owned(fill(msgstr, &args));
+            }
+        }
+        Cow::Borrowed(text)
+    }
+}
+
+/// Finds the values of the placeholders if the text matches the message id
+fn match_template<'a>(parts: &[String], text: &'a str) -> Option<Vec<&'a str>> {
+    let (first, rest) = parts.split_first()?;
+    let mut s = text.strip_prefix(first.as_str())?;
+    let mut args = Vec::with_capacity(rest.len());
+    for (i, part) in rest.iter().enumerate() {
+        let end = if i + 1 == rest.len() {
+            s.strip_suffix(part.as_str())?.len()
+        } else {
+            s.find(part.as_str())?
+        };
+        args.push(&s[..end]);
+        s = &s[end + part.len()..];
+    }
+    Some(args)
+}
+
+fn fill(msgstr: &str, args: &[&str]) -> String {
+    let mut out = String::with_capacity(msgstr.len());
+    let mut next = 0;
+    let mut rest = msgstr;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let inner = &rest[1..end];
+        let arg = if inner.is_empty() {
+            next += 1;
+            args.get(next - 1)
+        } else {
+            inner.parse::<usize>().ok().and_then(|i| args.get(i))
+        };
+        match arg {
+            Some(arg) => out.push_str(arg),
+            None => out.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn unquote(s: &str) -> Option<String> {
+    let inner = s.trim().strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => out.push('\n'),
+            't' => out.push('\t'),
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        let mut catalog = Catalog::default();
+        catalog
+            .parse(
+                r#"
+# comment
+msgid ""
+msgstr ""
+"Language-Name: Français\n"
+
+msgid "Economy"
+msgstr "Économie"
+
+msgid "{} cars are heading to the event!"
+msgstr "{} voitures se rendent à l'événement !"
+
+msgid "{} sold {}"
+msgstr "{1} vendu par {0}"
+
+msgid "Untranslated"
+msgstr ""
+"#,
+            )
+            .unwrap();
+
+        assert_eq!(catalog.name.as_deref(), Some("Français"));
+        assert_eq!(catalog.translate("Economy"), "Économie");
+        assert_eq!(
+            catalog.translate("12 cars are heading to the event!"),
+            "12 voitures se rendent à l'événement !"
+        );
+        assert_eq!(catalog.translate("Farm sold bread"), "bread vendu par Farm");
+        assert_eq!(catalog.translate("Untranslated"), "Untranslated");
+        assert_eq!(catalog.translate("Unknown"), "Unknown");
+
+        assert!(catalog.parse("msgstr \"orphan\"").is_err());
+    }
+}
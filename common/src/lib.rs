@@ -202,7 +202,10 @@ where
 
 mod chunkid;
 pub mod descriptions;
+pub mod expr;
+pub mod flatten;
 pub mod history;
+pub mod i18n;
 pub mod logger;
 pub mod rand;
 pub mod saveload;
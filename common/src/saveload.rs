@@ -4,6 +4,26 @@ use serde::Serialize;
 use std::fs::File;
 use std::io;
 use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::sync::RwLock;
+
+/// Directory used when no other is set
+pub const DEFAULT_SAVE_DIR: &str = "world";
+
+/// Directory where everything is saved, empty means [`DEFAULT_SAVE_DIR`]
+static SAVE_DIR: RwLock<String> = RwLock::new(String::new());
+
+pub fn save_dir() -> String {
+    let dir = SAVE_DIR.read().unwrap();
+    if dir.is_empty() {
+        return DEFAULT_SAVE_DIR.to_string();
+    }
+    dir.clone()
+}
+
+/// Changes where everything is saved and loaded from, used to keep player profiles apart
+pub fn set_save_dir(dir: impl Into<String>) {
+    *SAVE_DIR.write().unwrap() = dir.into();
+}
 
 fn create_file(path: &str) -> Option<File> {
     File::create(path).map_err(|e| log::error!("{}", e)).ok()
@@ -47,7 +67,7 @@ pub trait Encoder {
     }
 
     fn filename(name: &str) -> String {
-        format!("world/{}.{}", name, Self::EXTENSION)
+        format!("{}/{}.{}", save_dir(), name, Self::EXTENSION)
     }
 
     fn load_reader(name: &str) -> Result<BufReader<File>> {
@@ -62,7 +82,7 @@ pub trait Encoder {
     }
 
     fn save_silent(x: &impl Serialize, name: &str) -> Option<()> {
-        let _ = std::fs::create_dir("world");
+        let _ = std::fs::create_dir_all(save_dir());
 
         let file = create_file(&Self::filename(name))?;
 
@@ -74,6 +94,18 @@ pub trait Encoder {
         Some(())
     }
 
+    /// Saves data that was already encoded with this encoder
+    fn save_encoded(buf: &[u8], name: &str) -> Option<()> {
+        let _ = std::fs::create_dir_all(save_dir());
+
+        let mut file = create_file(&Self::filename(name))?;
+        file.write_all(buf)
+            .map_err(|e| log::error!("failed writing {}: {}", name, e))
+            .ok()?;
+        log::info!("successfully saved {}", name);
+        Some(())
+    }
+
     fn load<T: DeserializeOwned>(name: &str) -> Result<T> {
         Self::decode_reader(Self::load_reader(name)?)
             .map_err(|err| {
@@ -127,19 +159,60 @@ impl Encoder for Bincode {
 
 pub struct CompressedBincode;
 
+impl CompressedBincode {
+    /// Compresses data encoded with [`Bincode`]
+    pub fn compress(encoded: &[u8]) -> Vec<u8> {
+        miniz_oxide::deflate::compress_to_vec_zlib(encoded, 1) // bigger level values take far too long and only compress a bit better (about 5%)
+    }
+
+    /// Inverse of [`CompressedBincode::compress`]
+    pub fn decompress(compressed: &[u8]) -> Result<Vec<u8>> {
+        miniz_oxide::inflate::decompress_to_vec_zlib(compressed)
+            .map_err(|_| std::io::Error::new(ErrorKind::Other, "could not decode zipped file"))
+    }
+
+    /// Same as [`CompressedBincode::decompress`], but stops inflating past `max_size` bytes
+    /// so that a small malicious input cannot exhaust the memory
+    pub fn decompress_with_limit(compressed: &[u8], max_size: usize) -> Result<Vec<u8>> {
+        miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(compressed, max_size)
+            .map_err(|_| std::io::Error::new(ErrorKind::Other, "could not decode zipped file"))
+    }
+}
+
 impl Encoder for CompressedBincode {
     const EXTENSION: &'static str = "zip";
 
     fn encode(x: &impl Serialize) -> Result<Vec<u8>> {
         let encoded = &*Bincode::encode(x)?;
-        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(encoded, 1); // bigger level values take far too long and only compress a bit better (about 5%)
-        Ok(compressed)
+        Ok(Self::compress(encoded))
     }
 
     fn decode<T: DeserializeOwned>(x: &[u8]) -> Result<T> {
-        let v = &miniz_oxide::inflate::decompress_to_vec_zlib(x)
-            .map_err(|_| std::io::Error::new(ErrorKind::Other, "could not decode zipped file"))?;
-        Bincode::decode(v)
+        Bincode::decode(&Self::decompress(x)?)
+    }
+}
+
+/// Bincode for data made of chunks that were each compressed on their own,
+/// so that they can be compressed and decompressed in parallel
+pub struct Chunked;
+
+impl Encoder for Chunked {
+    const EXTENSION: &'static str = "chunks";
+
+    fn encode(x: &impl Serialize) -> Result<Vec<u8>> {
+        Bincode::encode(x)
+    }
+
+    fn decode<T: DeserializeOwned>(x: &[u8]) -> Result<T> {
+        Bincode::decode(x)
+    }
+
+    fn encode_writer(x: &impl Serialize, w: impl Write) -> Result<()> {
+        Bincode::encode_writer(x, w)
+    }
+
+    fn decode_reader<T: DeserializeOwned>(r: impl Read) -> Result<T> {
+        Bincode::decode_reader(r)
     }
 }
 
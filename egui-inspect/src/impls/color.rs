@@ -0,0 +1,79 @@
+use crate::impls::Inspect;
+use crate::impls::InspectArgs;
+use egui::color_picker::Alpha;
+use egui::Color32;
+use geom::{Color, LinearColor};
+
+impl Inspect<Color32> for Color32 {
+    fn render(c: &Self, label: &'static str, ui: &mut egui::Ui, _: &InspectArgs) {
+        let mut cp = *c;
+        ui.horizontal(|ui| {
+            ui.label(label);
+            egui::color_picker::color_picker_color32(ui, &mut cp, Alpha::OnlyBlend);
+        });
+    }
+
+    fn render_mut(c: &mut Self, label: &'static str, ui: &mut egui::Ui, _: &InspectArgs) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(label);
+            changed = egui::color_picker::color_picker_color32(ui, c, Alpha::OnlyBlend);
+        });
+        changed
+    }
+}
+
+fn to_color32(c: &Color) -> Color32 {
+    Color32::from_rgba_unmultiplied(
+        (c.r * 255.0) as u8,
+        (c.g * 255.0) as u8,
+        (c.b * 255.0) as u8,
+        (c.a * 255.0) as u8,
+    )
+}
+
+impl Inspect<Color> for Color {
+    fn render(c: &Self, label: &'static str, ui: &mut egui::Ui, args: &InspectArgs) {
+        <Color32 as Inspect<Color32>>::render(&to_color32(c), label, ui, args);
+    }
+
+    fn render_mut(
+        c: &mut Self,
+        label: &'static str,
+        ui: &mut egui::Ui,
+        args: &InspectArgs,
+    ) -> bool {
+        let mut color_arr = to_color32(c);
+        if !<Color32 as Inspect<Color32>>::render_mut(&mut color_arr, label, ui, args) {
+            return false;
+        }
+        let arr = color_arr.to_srgba_unmultiplied();
+        c.r = arr[0] as f32 / 255.0;
+        c.g = arr[1] as f32 / 255.0;
+        c.b = arr[2] as f32 / 255.0;
+        c.a = arr[3] as f32 / 255.0;
+        true
+    }
+}
+
+impl Inspect<LinearColor> for LinearColor {
+    fn render(lc: &Self, label: &'static str, ui: &mut egui::Ui, _: &InspectArgs) {
+        let c: Color = (*lc).into();
+        <Color as Inspect<Color>>::render(&c, label, ui, &InspectArgs::default());
+    }
+
+    fn render_mut(
+        lc: &mut Self,
+        label: &'static str,
+        ui: &mut egui::Ui,
+        _args: &InspectArgs,
+    ) -> bool {
+        let mut c: Color = (*lc).into();
+        if <Color as Inspect<Color>>::render_mut(&mut c, label, ui, &InspectArgs::default()) {
+            *lc = c.into();
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::impls::Inspect;
+use crate::impls::InspectArgs;
+use egui::emath::RectTransform;
+use egui::{pos2, vec2, Color32, Pos2, Rect, Sense, Shape, Stroke};
+use geom::Curve;
+
+const POINT_RADIUS: f32 = 4.0;
+
+impl Inspect<Curve> for Curve {
+    fn render(c: &Self, label: &'static str, ui: &mut egui::Ui, args: &InspectArgs) {
+        let mut cp = c.clone();
+        curve_editor(&mut cp, label, ui, args, false);
+    }
+
+    /// Points can be dragged, double click adds a point and right click removes one
+    fn render_mut(
+        c: &mut Self,
+        label: &'static str,
+        ui: &mut egui::Ui,
+        args: &InspectArgs,
+    ) -> bool {
+        curve_editor(c, label, ui, args, true)
+    }
+}
+
+fn curve_editor(
+    c: &mut Curve,
+    label: &'static str,
+    ui: &mut egui::Ui,
+    args: &InspectArgs,
+    editable: bool,
+) -> bool {
+    let (min_x, max_x) = c.x_range();
+    let (min_y, max_y) = c.y_range();
+    let min_y = args.min_value.unwrap_or(min_y.min(0.0));
+    let max_y = args.max_value.unwrap_or(max_y.max(min_y + 1.0));
+
+    ui.label(label);
+    let (resp, painter) =
+        ui.allocate_painter(vec2(ui.available_width().min(300.0), 100.0), Sense::click());
+    let rect = resp.rect;
+    // y goes up in the curve but down on the screen
+    let to_screen = RectTransform::from_to(
+        Rect::from_x_y_ranges(min_x..=max_x.max(min_x + 1.0), max_y..=min_y),
+        rect.shrink(POINT_RADIUS),
+    );
+    let from_screen = to_screen.inverse();
+
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+    let axis_stroke = Stroke::new(1.0, visuals.weak_text_color());
+    if min_y < 0.0 && max_y > 0.0 {
+        let zero = to_screen * pos2(min_x, 0.0);
+        painter.hline(rect.x_range(), zero.y, axis_stroke);
+    }
+
+    let mut changed = false;
+    let mut remove = None;
+    let points = if editable {
+        c.points().to_vec()
+    } else {
+        vec![]
+    };
+    for (i, (x, y)) in points.into_iter().enumerate() {
+        let p = to_screen * pos2(x, y);
+        let point_rect = Rect::from_center_size(p, vec2(POINT_RADIUS * 3.0, POINT_RADIUS * 3.0));
+        let point_resp = ui.interact(point_rect, resp.id.with(i), Sense::click_and_drag());
+        if point_resp.secondary_clicked() {
+            remove = Some(i);
+        }
+        if point_resp.dragged() {
+            let moved = (p + point_resp.drag_delta()).clamp(rect.min, rect.max);
+            let Pos2 { x, y } = from_screen * moved;
+            c.set_point(i, x, y);
+            changed = true;
+        }
+    }
+    if let Some(i) = remove {
+        c.remove(i);
+        changed = true;
+    }
+    if editable && resp.double_clicked() {
+        if let Some(pos) = resp.interact_pointer_pos() {
+            let Pos2 { x, .. } = from_screen * pos;
+            c.insert(x, c.eval(x));
+            changed = true;
+        }
+    }
+
+    let line: Vec<Pos2> = c
+        .points()
+        .iter()
+        .map(|&(x, y)| to_screen * pos2(x, y))
+        .collect();
+    painter.add(Shape::line(
+        line.clone(),
+        Stroke::new(2.0, visuals.text_color()),
+    ));
+    for p in line {
+        painter.circle_filled(p, POINT_RADIUS, Color32::from_rgb(100, 160, 255));
+    }
+
+    resp.on_hover_text(format!(
+        "x: {:.2} .. {:.2}, y: {:.2} .. {:.2}",
+        min_x, max_x, min_y, max_y
+    ));
+    changed
+}
@@ -1,73 +1,7 @@
 use crate::impls::f64::InspectF64Deg;
 use crate::impls::Inspect;
 use crate::impls::InspectArgs;
-use egui::color_picker::Alpha;
-use egui::Color32;
-use geom::{Color, LinearColor, PolyLine, Transform, Vec2, Vec3};
-
-impl Inspect<Color> for Color {
-    fn render(c: &Self, label: &'static str, ui: &mut egui::Ui, _args: &InspectArgs) {
-        let mut color_arr = Color32::from_rgba_unmultiplied(
-            (c.r * 255.0) as u8,
-            (c.g * 255.0) as u8,
-            (c.b * 255.0) as u8,
-            (c.a * 255.0) as u8,
-        );
-        ui.horizontal(|ui| {
-            ui.label(label);
-            egui::color_picker::color_picker_color32(ui, &mut color_arr, Alpha::OnlyBlend);
-        });
-    }
-
-    fn render_mut(
-        c: &mut Self,
-        label: &'static str,
-        ui: &mut egui::Ui,
-        _args: &InspectArgs,
-    ) -> bool {
-        let mut color_arr = Color32::from_rgba_unmultiplied(
-            (c.r * 255.0) as u8,
-            (c.g * 255.0) as u8,
-            (c.b * 255.0) as u8,
-            (c.a * 255.0) as u8,
-        );
-        let mut changed = false;
-        ui.horizontal(|ui| {
-            ui.label(label);
-            if egui::color_picker::color_picker_color32(ui, &mut color_arr, Alpha::OnlyBlend) {
-                let arr = color_arr.to_srgba_unmultiplied();
-                c.r = arr[0] as f32 / 255.0;
-                c.g = arr[1] as f32 / 255.0;
-                c.b = arr[2] as f32 / 255.0;
-                c.a = arr[3] as f32 / 255.0;
-                changed = true;
-            }
-        });
-        changed
-    }
-}
-
-impl Inspect<LinearColor> for LinearColor {
-    fn render(lc: &Self, label: &'static str, ui: &mut egui::Ui, _: &InspectArgs) {
-        let c: Color = (*lc).into();
-        <Color as Inspect<Color>>::render(&c, label, ui, &InspectArgs::default());
-    }
-
-    fn render_mut(
-        lc: &mut Self,
-        label: &'static str,
-        ui: &mut egui::Ui,
-        _args: &InspectArgs,
-    ) -> bool {
-        let mut c: Color = (*lc).into();
-        if <Color as Inspect<Color>>::render_mut(&mut c, label, ui, &InspectArgs::default()) {
-            *lc = c.into();
-            true
-        } else {
-            false
-        }
-    }
-}
+use geom::{PolyLine, Transform, Vec2, Vec3};
 
 impl Inspect<Transform> for Transform {
     fn render(t: &Self, _: &'static str, ui: &mut egui::Ui, _: &InspectArgs) {
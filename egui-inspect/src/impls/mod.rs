@@ -1,5 +1,7 @@
 mod bool;
 mod btrees;
+mod color;
+mod curve;
 mod f32;
 mod f64;
 pub mod geometry;
@@ -118,6 +118,9 @@ impl AudioContext {
         let buf = match common::saveload::load_raw(&p) {
             Ok(x) => x,
             Err(e) => {
+                if let Ok(wav) = common::saveload::load_raw(format!("assets/sounds/{name}.wav")) {
+                    return Self::decode_wav(name, &wav);
+                }
                 log::error!("Could not load sound {}: {}", name, e);
                 return None;
             }
@@ -163,6 +166,57 @@ impl AudioContext {
         Some(frames)
     }
 
+    /// Decodes an uncompressed 16-bit PCM wav file, used for short effects
+    fn decode_wav(name: &str, buf: &[u8]) -> Option<StoredAudio> {
+        if buf.len() < 12 || &buf[0..4] != b"RIFF" || &buf[8..12] != b"WAVE" {
+            log::error!("Could not load sound {}: not a wav file", name);
+            return None;
+        }
+        let u16_at = |i: usize| u16::from_le_bytes([buf[i], buf[i + 1]]);
+        let u32_at = |i: usize| u32::from_le_bytes(buf[i..i + 4].try_into().unwrap());
+
+        let mut fmt = None;
+        let mut data = None;
+        let mut i = 12;
+        while i + 8 <= buf.len() {
+            let len = u32_at(i + 4) as usize;
+            let body = buf.get(i + 8..i + 8 + len)?;
+            match &buf[i..i + 4] {
+                b"fmt " if len >= 16 => {
+                    fmt = Some((
+                        u16_at(i + 8),
+                        u16_at(i + 10),
+                        u32_at(i + 12),
+                        u16_at(i + 22),
+                    ))
+                }
+                b"data" => data = Some(body),
+                _ => {}
+            }
+            i += 8 + len + (len & 1);
+        }
+
+        let (format, channels, rate, bits) = fmt?;
+        if format != 1 || bits != 16 || !(channels == 1 || channels == 2) {
+            log::error!(
+                "Could not load sound {}: only 16-bit PCM wav is supported",
+                name
+            );
+            return None;
+        }
+        let samples: Vec<[Sample; 2]> = data?
+            .chunks_exact(2 * channels as usize)
+            .map(|frame| {
+                let v = |j: usize| {
+                    i16::from_le_bytes([frame[2 * j], frame[2 * j + 1]]) as f32 / (i16::MAX as f32)
+                };
+                [v(0), v(channels as usize - 1)]
+            })
+            .collect();
+
+        Some(Frames::from_slice(rate, &samples))
+    }
+
     fn get(
         preloading: &FastSet<String>,
         cache: &RwLock<FastMap<String, StoredAudio>>,
@@ -12,12 +12,14 @@ pub struct MeshInstance {
     pub pos: Vec3,
     pub dir: Vec3,
     pub tint: LinearColor,
+    /// Rotation around the direction, in radians
+    pub roll: f32,
 }
 
 u8slice_impl!(MeshInstance);
 
 const ATTRS: &[VertexAttribute] =
-    &wgpu::vertex_attr_array![5 => Float32x3, 6 => Float32x3, 7 => Float32x4];
+    &wgpu::vertex_attr_array![5 => Float32x3, 6 => Float32x3, 7 => Float32x4, 8 => Float32];
 
 impl MeshInstance {
     pub(crate) const fn desc() -> VertexBufferLayout<'static> {
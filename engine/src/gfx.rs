@@ -130,6 +130,9 @@ pub struct GfxSettings {
     pub terrain_grid: bool,
     pub shader_debug: bool,
     pub pbr_enabled: bool,
+    /// Low-spec preset for integrated GPUs: no shadows and simpler mesh LODs
+    #[serde(default)]
+    pub performance_mode: bool,
 }
 
 impl Default for GfxSettings {
@@ -143,6 +146,7 @@ impl Default for GfxSettings {
             terrain_grid: true,
             shader_debug: false,
             pbr_enabled: true,
+            performance_mode: false,
         }
     }
 }
@@ -520,10 +524,16 @@ impl GfxContext {
             self.update_sc = true;
         }
 
+        let shadows = if settings.performance_mode {
+            ShadowQuality::NoShadows
+        } else {
+            settings.shadows
+        };
+
         let params = self.render_params.value_mut();
-        params.shadow_mapping_resolution = settings.shadows.size().unwrap_or(0) as i32;
+        params.shadow_mapping_resolution = shadows.size().unwrap_or(0) as i32;
 
-        if let Some(v) = settings.shadows.size() {
+        if let Some(v) = shadows.size() {
             if self.sun_shadowmap.extent.width != v {
                 self.sun_shadowmap = GfxContext::mk_shadowmap(&self.device, v);
                 self.update_simplelit_bg();
@@ -539,6 +549,15 @@ impl GfxContext {
         self.settings = Some(settings);
     }
 
+    /// Multiplier of the screen coverage of meshes when selecting their LOD,
+    /// below 1 the simpler LODs are picked sooner
+    pub fn lod_bias(&self) -> f32 {
+        match self.settings {
+            Some(s) if s.performance_mode => 0.25,
+            _ => 1.0,
+        }
+    }
+
     pub fn set_time(&mut self, time: f32) {
         self.render_params.value_mut().time = time;
     }
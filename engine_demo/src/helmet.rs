@@ -24,6 +24,7 @@ impl DemoElement for Helmet {
             pos: vec3(0.0, 10.0, 0.0),
             dir: Vec3::X * 3.0,
             tint: LinearColor::WHITE,
+            roll: 0.0,
         });
         let mesh = i.build(gfx).unwrap();
 
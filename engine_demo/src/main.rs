@@ -53,6 +53,7 @@ impl engine::framework::State for State {
                 pos: vec3(50.0, 00.0, 0.0),
                 dir: Vec3::X,
                 tint: LinearColor::WHITE,
+                roll: 0.0,
             });
             meshes.push(i.build(gfx).unwrap());
         }
@@ -47,6 +47,7 @@ impl DemoElement for Spheres {
                     pos: 2.3 * vec3(x as f32, 0.0, z as f32),
                     dir: Vec3::X,
                     tint: LinearColor::WHITE,
+                    roll: 0.0,
                 });
                 meshes.push(i.build(gfx).unwrap());
             }
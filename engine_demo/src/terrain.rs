@@ -105,6 +105,7 @@ impl DemoElement for Terrain {
                 pos,
                 dir: Vec3::X * 20.0,
                 tint: LinearColor::WHITE,
+                roll: 0.0,
             });
         }
         if let Some(pos) = self.plane_hitpos {
@@ -112,6 +113,7 @@ impl DemoElement for Terrain {
                 pos,
                 dir: Vec3::X * 10.0,
                 tint: LinearColor::RED,
+                roll: 0.0,
             });
         }
 
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+/// Piecewise-linear curve defined by (x, y) points sorted by x.
+/// Useful for tweakable profiles such as a value over the time of day.
+/// Outside of the points range, the curve is extended by the closest point.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Curve {
+    points: Vec<(f32, f32)>,
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Self::constant(0.0, 1.0, 1.0)
+    }
+}
+
+impl Curve {
+    /// Creates a curve from points, they are sorted by x
+    pub fn new(mut points: Vec<(f32, f32)>) -> Self {
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { points }
+    }
+
+    /// Flat curve going from `start` to `end`
+    pub fn constant(start: f32, end: f32, y: f32) -> Self {
+        Self {
+            points: vec![(start, y), (end, y)],
+        }
+    }
+
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+
+    /// Range of x covered by the points
+    pub fn x_range(&self) -> (f32, f32) {
+        match (self.points.first(), self.points.last()) {
+            (Some(a), Some(b)) => (a.0, b.0),
+            _ => (0.0, 0.0),
+        }
+    }
+
+    /// Range of y taken by the points
+    pub fn y_range(&self) -> (f32, f32) {
+        self.points
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &(_, y)| {
+                (lo.min(y), hi.max(y))
+            })
+    }
+
+    pub fn eval(&self, x: f32) -> f32 {
+        let Some(&(first_x, first_y)) = self.points.first() else {
+            return 0.0;
+        };
+        if x <= first_x {
+            return first_y;
+        }
+        for w in self.points.windows(2) {
+            let (x0, y0) = w[0];
+            let (x1, y1) = w[1];
+            if x <= x1 {
+                if x1 - x0 <= f32::EPSILON {
+                    return y1;
+                }
+                return y0 + (y1 - y0) * (x - x0) / (x1 - x0);
+            }
+        }
+        self.points.last().unwrap().1
+    }
+
+    /// Moves the i-th point, its x stays between its neighbours so the points stay sorted
+    pub fn set_point(&mut self, i: usize, x: f32, y: f32) {
+        let lo = if i > 0 {
+            self.points[i - 1].0
+        } else {
+            f32::NEG_INFINITY
+        };
+        let hi = self.points.get(i + 1).map_or(f32::INFINITY, |p| p.0);
+        self.points[i] = (x.clamp(lo, hi), y);
+    }
+
+    /// Inserts a point, keeping the points sorted
+    pub fn insert(&mut self, x: f32, y: f32) {
+        let i = self.points.partition_point(|p| p.0 < x);
+        self.points.insert(i, (x, y));
+    }
+
+    /// Removes the i-th point, a curve always keeps at least two points
+    pub fn remove(&mut self, i: usize) {
+        if self.points.len() <= 2 {
+            return;
+        }
+        self.points.remove(i);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eval_interpolates() {
+        let mut c = Curve::new(vec![(10.0, 0.0), (0.0, 1.0), (20.0, 1.0)]);
+        assert_eq!(c.eval(-5.0), 1.0);
+        assert_eq!(c.eval(5.0), 0.5);
+        assert_eq!(c.eval(15.0), 0.5);
+        assert_eq!(c.eval(25.0), 1.0);
+
+        c.set_point(1, 30.0, 2.0);
+        assert_eq!(c.points()[1], (20.0, 2.0));
+        c.insert(5.0, 0.0);
+        assert_eq!(c.eval(5.0), 0.0);
+    }
+}
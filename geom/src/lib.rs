@@ -30,6 +30,7 @@ mod boldline;
 mod boldspline;
 mod circle;
 mod color;
+mod curve;
 mod frustrum;
 mod heightmap;
 mod infinite_frustrum;
@@ -65,6 +66,7 @@ pub use boldline::*;
 pub use boldspline::*;
 pub use circle::*;
 pub use color::*;
+pub use curve::*;
 pub use frustrum::*;
 pub use heightmap::*;
 pub use infinite_frustrum::*;
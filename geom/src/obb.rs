@@ -28,6 +28,28 @@ impl OBB {
         Self { corners }
     }
 
+    /// Mirrors the box along its first axis, the corners are then in clockwise order
+    pub fn mirrored(&self) -> Self {
+        let [a, b, c, d] = self.corners;
+        Self {
+            corners: [b, a, d, c],
+        }
+    }
+
+    /// Whether the corners are in clockwise order, as produced by [`OBB::mirrored`]
+    pub fn is_mirrored(&self) -> bool {
+        let [a, b] = self.axis();
+        a.perpendicular().dot(b) > 0.0
+    }
+
+    /// The same box with its corners in counter-clockwise order
+    pub fn unmirrored(&self) -> Self {
+        if self.is_mirrored() {
+            return self.mirrored();
+        }
+        *self
+    }
+
     #[inline]
     pub fn axis(&self) -> [Vec2; 2] {
         [
@@ -90,6 +90,18 @@ impl Camera {
         })
     }
 
+    /// Position in pixels on the viewport of a point of the world, None if it is behind the camera
+    pub fn project(&self, pos: Vec3) -> Option<Vec2> {
+        let v = self.build_view_projection_matrix() * vec4(pos.x, pos.y, pos.z, 1.0);
+        if v.w <= 0.0 {
+            return None;
+        }
+        Some(vec2(
+            (v.x / v.w + 1.0) * 0.5 * self.viewport_w,
+            (1.0 - v.y / v.w) * 0.5 * self.viewport_h,
+        ))
+    }
+
     pub fn build_view_projection_matrix(&self) -> Matrix4 {
         let eye = self.eye();
         let view = look_to_rh(eye, -self.dir(), self.up);
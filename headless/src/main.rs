@@ -1,6 +1,13 @@
 use common::logger::MyLog;
+use common::saveload::{load_string, Encoder, JSONPretty};
 use common::unwrap_or;
 use networking::{Frame, Server, ServerConfiguration, ServerPollResult};
+use simulation::utils::analytics::AnalyticsExport;
+use simulation::utils::layout_import::LayoutImport;
+use simulation::utils::scheduler::SeqSchedule;
+use simulation::utils::sim_snapshot::SimSnapshot;
+use simulation::utils::snapshot_diff::{diff_simulations, diff_with_replay, Divergence};
+use simulation::utils::sumo::SumoExport;
 use simulation::world_command::WorldCommands;
 use simulation::Simulation;
 use std::time::{Duration, Instant};
@@ -27,6 +34,35 @@ struct Opt {
     /// i.e. 20ms = 50FPS
     #[structopt(long, default_value = "20")]
     timestep: u64,
+
+    /// Compare two saves, print where they diverge and exit
+    #[structopt(long, number_of_values = 2)]
+    diff: Option<Vec<String>>,
+
+    /// Replay the commands of a save, print where the replay diverges from the save and exit
+    #[structopt(long)]
+    check_replay: Option<String>,
+
+    /// Print the statistics snapshot of a save as json and exit
+    #[structopt(long)]
+    stats: Option<String>,
+
+    /// Export a statistics snapshot every n ticks in the save folder, 0 to disable
+    #[structopt(long, default_value = "0")]
+    stats_every: u64,
+
+    /// Export anonymized statistics of a save as csv files in the save folder and exit
+    #[structopt(long)]
+    export_analytics: Option<String>,
+
+    /// Export the road network and the vehicle routes of a save as SUMO xml files in the save
+    /// folder and exit
+    #[structopt(long)]
+    export_sumo: Option<String>,
+
+    /// Place the buildings of a csv or json layout file before starting the server
+    #[structopt(long)]
+    import_layout: Option<String>,
 }
 
 fn main() {
@@ -34,6 +70,27 @@ fn main() {
     MyLog::init();
     simulation::init::init();
 
+    if let Some(ref saves) = opt.diff {
+        diff_saves(&saves[0], &saves[1]);
+        return;
+    }
+    if let Some(ref save) = opt.check_replay {
+        check_replay(save);
+        return;
+    }
+    if let Some(ref save) = opt.stats {
+        print_stats(save);
+        return;
+    }
+    if let Some(ref save) = opt.export_analytics {
+        export_analytics(save);
+        return;
+    }
+    if let Some(ref save) = opt.export_sumo {
+        export_sumo(save);
+        return;
+    }
+
     log::info!("starting server with version: {}", VERSION);
 
     let mut w = unwrap_or!(Simulation::load_from_disk("world"), {
@@ -43,6 +100,10 @@ fn main() {
 
     let mut sched = Simulation::schedule();
 
+    if let Some(ref path) = opt.import_layout {
+        import_layout(&mut w, &mut sched, path);
+    }
+
     let mut server: Server<Simulation, WorldCommands> = match Server::start(ServerConfiguration {
         start_frame: Frame(w.get_tick()),
         period: Duration::from_millis(opt.timestep),
@@ -67,6 +128,10 @@ fn main() {
                 assert_eq!(frame.frame.0, w.get_tick() + 1);
                 let merged: WorldCommands = frame.inputs.into_iter().map(|x| x.inp).collect();
                 w.tick(&mut sched, merged.as_ref());
+
+                if opt.stats_every > 0 && w.get_tick() % opt.stats_every == 0 {
+                    SimSnapshot::take(&w).save(&format!("stats_{}", w.get_tick()));
+                }
             }
         }
 
@@ -78,3 +143,95 @@ fn main() {
         std::thread::sleep(Duration::from_millis(1));
     }
 }
+
+fn import_layout(w: &mut Simulation, sched: &mut SeqSchedule, path: &str) {
+    let text = match load_string(path) {
+        Ok(x) => x,
+        Err(e) => {
+            log::error!("could not read layout {}: {}", path, e);
+            return;
+        }
+    };
+    let import = LayoutImport::new(w, &text);
+    for failure in &import.failures {
+        log::warn!("{}: row {}: {}", path, failure.row, failure.error);
+    }
+    log::info!(
+        "imported {} buildings from {}, {} rows failed",
+        import.commands.len(),
+        path,
+        import.failures.len()
+    );
+    w.tick(sched, import.commands.iter());
+}
+
+fn diff_saves(a: &str, b: &str) {
+    let (Some(sim_a), Some(sim_b)) = (Simulation::load_from_disk(a), Simulation::load_from_disk(b))
+    else {
+        log::error!("could not load {} or {}", a, b);
+        return;
+    };
+    if sim_a.get_tick() != sim_b.get_tick() {
+        log::warn!(
+            "the saves are at different ticks: {} and {}",
+            sim_a.get_tick(),
+            sim_b.get_tick()
+        );
+    }
+    report(&diff_simulations(&sim_a, &sim_b));
+}
+
+fn check_replay(save: &str) {
+    let Some(sim) = Simulation::load_from_disk(save) else {
+        log::error!("could not load {}", save);
+        return;
+    };
+    let Some(replay) = Simulation::load_replay_from_disk(save) else {
+        log::error!("could not load the replay of {}", save);
+        return;
+    };
+    log::info!("replaying {} ticks", sim.get_tick());
+    report(&diff_with_replay(&sim, replay));
+}
+
+fn print_stats(save: &str) {
+    let Some(sim) = Simulation::load_from_disk(save) else {
+        log::error!("could not load {}", save);
+        return;
+    };
+    match JSONPretty::encode(&SimSnapshot::take(&sim)) {
+        Ok(json) => println!("{}", String::from_utf8_lossy(&json)),
+        Err(e) => log::error!("could not encode the snapshot: {}", e),
+    }
+}
+
+fn export_analytics(save: &str) {
+    let Some(sim) = Simulation::load_from_disk(save) else {
+        log::error!("could not load {}", save);
+        return;
+    };
+    for path in AnalyticsExport::new(&sim).save(save) {
+        println!("{}", path);
+    }
+}
+
+fn export_sumo(save: &str) {
+    let Some(sim) = Simulation::load_from_disk(save) else {
+        log::error!("could not load {}", save);
+        return;
+    };
+    for path in SumoExport::new(&sim).save(save) {
+        println!("{}", path);
+    }
+}
+
+fn report(divergences: &[Divergence]) {
+    if divergences.is_empty() {
+        println!("no divergence");
+        return;
+    }
+    println!("{} diverging entities or resources:", divergences.len());
+    for d in divergences {
+        println!("{}", d);
+    }
+}
@@ -1,21 +1,68 @@
+use crate::gui::windows::settings::Settings;
 use crate::uiworld::UiWorld;
 use common::AudioKind;
 use engine::{AudioContext, Gain, GainControl};
 use flat_spatial::grid::GridHandle;
 use geom::{Camera, AABB};
 use oddio::{Cycle, Mixed, Seek, Speed, SpeedControl};
-use simulation::physics::CollisionWorld;
+use ordered_float::OrderedFloat;
+use simulation::physics::{CollisionWorld, PhysicsGroup, PhysicsObject};
 use simulation::Simulation;
 use slotmapd::SecondaryMap;
 
+const HEAR_RADIUS: f32 = 200.0;
+/// Vehicles engines are pitched up or down by up to this ratio so they don't all sound the same
+const PITCH_VARIATION: f32 = 0.1;
+/// How much louder (or quieter) vehicles driving towards (or away from) the camera are
+const APPROACH_GAIN: f32 = 0.3;
+/// Engine volume of a stopped vehicle, relative to the volume at 1m/s
+const IDLE_VOLUME: f32 = 0.3;
+
+/// EngineProfile is the engine loop of a kind of vehicle and how it reacts to speed
+#[derive(Copy, Clone)]
+struct EngineProfile {
+    sound: &'static str,
+    base_pitch: f32,
+    pitch_per_speed: f32,
+    volume: f32,
+}
+
+impl EngineProfile {
+    const CAR: EngineProfile = EngineProfile {
+        sound: "car_engine",
+        base_pitch: 0.9,
+        pitch_per_speed: 0.01,
+        volume: 1.0,
+    };
+
+    /// Trucks and buses have their own diesel loop, louder than cars
+    const HEAVY: EngineProfile = EngineProfile {
+        sound: "heavy_engine",
+        base_pitch: 0.9,
+        pitch_per_speed: 0.006,
+        volume: 1.6,
+    };
+
+    /// Vehicles are only known by their collider here, heavy vehicles are the bigger ones
+    fn of(obj: &PhysicsObject) -> Self {
+        if obj.radius > 2.5 {
+            Self::HEAVY
+        } else {
+            Self::CAR
+        }
+    }
+}
+
 /// CarSound is the sound of a single car
 pub struct CarSound {
     road: Option<(SpeedControl, GainControl, Mixed)>,
     engine: Option<(SpeedControl, GainControl, Mixed)>,
+    profile: EngineProfile,
+    pitch: f32,
 }
 
 /// CarSounds are sounds that are played when cars are near the player
-/// They are tied to a car entity
+/// They are tied to a car entity, only the closest cars within the audio budget are heard individually
 pub struct CarSounds {
     sounds: SecondaryMap<GridHandle, CarSound>,
     generic_car_sound: Option<GainControl>,
@@ -40,23 +87,43 @@ impl CarSounds {
         let campos = uiworld.read::<Camera>().eye();
         let cambox = AABB::new(campos.xy(), campos.xy()).expand(100.0);
 
-        const HEAR_RADIUS: f32 = 200.0;
+        // Individual car sounds get chaotic when the game goes fast, and they should stop when paused
+        let warp = uiworld.read::<Settings>().time_warp;
+        let warp_attenuation = if warp == 0 {
+            0.0
+        } else {
+            1.0 / (warp as f32).sqrt()
+        };
+
+        let mut budget = uiworld.read::<Settings>().vehicle_sounds_budget as usize;
+        if cfg!(debug_assertions) {
+            budget = budget.min(1);
+        }
 
-        #[cfg(not(debug_assertions))]
-        const MAX_SOUNDS: usize = 30;
-        #[cfg(debug_assertions)]
-        const MAX_SOUNDS: usize = 1;
+        // Only the closest vehicles get their own sound
+        let mut heard: Vec<(GridHandle, f32)> = coworld
+            .query_around(
+                campos.xy(),
+                (HEAR_RADIUS * HEAR_RADIUS - campos.z * campos.z)
+                    .max(0.0)
+                    .sqrt(),
+            )
+            .filter_map(|(h, _)| {
+                let (pos, obj) = coworld.get(h)?;
+                if !matches!(obj.group, PhysicsGroup::Vehicles) {
+                    return None;
+                }
+                Some((h, pos.z0().distance(campos)))
+            })
+            .collect();
+        heard.sort_by_key(|&(_, d)| OrderedFloat(d));
+        heard.truncate(budget);
 
         let mut to_remove = vec![];
-
         for (h, _) in &self.sounds {
-            if let Some((pos, _)) = coworld.get(h) {
-                if pos.z0().is_close(campos, HEAR_RADIUS) {
-                    continue;
-                }
+            if !heard.iter().any(|&(h2, _)| h2 == h) {
+                to_remove.push(h);
             }
-
-            to_remove.push(h);
         }
 
         for h in to_remove {
@@ -70,52 +137,52 @@ impl CarSounds {
         }
 
         // Gather
-        for (h, _) in coworld.query_around(
-            campos.xy(),
-            (HEAR_RADIUS * HEAR_RADIUS - campos.z * campos.z)
-                .max(0.0)
-                .sqrt(),
-        ) {
-            let (pos, obj) = coworld.get(h).unwrap();
-            if !matches!(obj.group, simulation::physics::PhysicsGroup::Vehicles) {
+        for &(h, _) in &heard {
+            if self.sounds.contains_key(h) {
                 continue;
             }
+            let (pos, obj) = coworld.get(h).unwrap(); // Unwrap ok: just queried
+            let profile = EngineProfile::of(obj);
+            let variation =
+                1.0 - PITCH_VARIATION + 2.0 * PITCH_VARIATION * common::rand::rand2(pos.y, pos.x);
 
-            if self.sounds.len() >= MAX_SOUNDS {
-                break;
-            }
+            let engine = ctx
+                .play_with_control(
+                    profile.sound,
+                    |x| {
+                        let mut cycle = Cycle::new(x);
+                        cycle.seek(common::rand::rand2(pos.x, pos.y));
+                        let (g_control, signal) = Gain::new(cycle, 0.0);
+                        let (speed_control, signal) = Speed::new(signal);
+                        ((speed_control, g_control), signal)
+                    },
+                    AudioKind::Effect,
+                )
+                .map(|((a, b), c)| (a, b, c));
 
-            if !self.sounds.contains_key(h) {
-                let engine = ctx
-                    .play_with_control(
-                        "car_engine",
-                        |x| {
-                            let mut cycle = Cycle::new(x);
-                            cycle.seek(common::rand::rand2(pos.x, pos.y));
-                            let (g_control, signal) = Gain::new(cycle, 0.0);
-                            let (speed_control, signal) = Speed::new(signal);
-                            ((speed_control, g_control), signal)
-                        },
-                        AudioKind::Effect,
-                    )
-                    .map(|((a, b), c)| (a, b, c));
-
-                let road = ctx
-                    .play_with_control(
-                        "car_loop",
-                        |x| {
-                            let mut cycle = Cycle::new(x);
-                            cycle.seek(common::rand::rand2(pos.x, pos.y));
-                            let (g_control, signal) = Gain::new(cycle, 0.0);
-                            let (speed_control, signal) = Speed::new(signal);
-                            ((speed_control, g_control), signal)
-                        },
-                        AudioKind::Effect,
-                    )
-                    .map(|((a, b), c)| (a, b, c));
-
-                self.sounds.insert(h, CarSound { road, engine });
-            }
+            let road = ctx
+                .play_with_control(
+                    "car_loop",
+                    |x| {
+                        let mut cycle = Cycle::new(x);
+                        cycle.seek(common::rand::rand2(pos.x, pos.y));
+                        let (g_control, signal) = Gain::new(cycle, 0.0);
+                        let (speed_control, signal) = Speed::new(signal);
+                        ((speed_control, g_control), signal)
+                    },
+                    AudioKind::Effect,
+                )
+                .map(|((a, b), c)| (a, b, c));
+
+            self.sounds.insert(
+                h,
+                CarSound {
+                    road,
+                    engine,
+                    profile,
+                    pitch: variation * profile.base_pitch,
+                },
+            );
         }
 
         // Update
@@ -126,16 +193,21 @@ impl CarSounds {
             let dir_to_me = (campos - pos.z(campos.z * 0.5)).normalize();
 
             let speed_to_me = his_speed.dot(dir_to_me);
-            let boost = 300.0 / (300.0 - speed_to_me);
+            let doppler = 300.0 / (300.0 - speed_to_me);
+            // Approaching vehicles are louder than the ones driving away
+            let approach = 1.0 + APPROACH_GAIN * (speed_to_me / 30.0).clamp(-1.0, 1.0);
+            let attenuation = warp_attenuation * approach / pos.z0().distance(campos);
 
             if let Some((ref mut speed, ref mut gain, _)) = cs.road {
-                gain.set_amplitude_ratio(obj.speed.sqrt() * 3.0 / pos.z0().distance(campos));
-                speed.set_speed(boost)
+                gain.set_amplitude_ratio(attenuation * obj.speed.sqrt() * 3.0);
+                speed.set_speed(doppler)
             }
 
             if let Some((ref mut speed, ref mut gain, _)) = cs.engine {
-                gain.set_amplitude_ratio(obj.speed.sqrt() / pos.z0().distance(campos));
-                speed.set_speed(boost)
+                gain.set_amplitude_ratio(
+                    attenuation * cs.profile.volume * (IDLE_VOLUME + obj.speed.sqrt()),
+                );
+                speed.set_speed(doppler * (cs.pitch + cs.profile.pitch_per_speed * obj.speed))
             }
         }
 
@@ -143,7 +215,7 @@ impl CarSounds {
             let cars_on_screen = coworld
                 .query_aabb(cambox.ll, cambox.ur)
                 .filter_map(|(h, _)| coworld.get(h))
-                .filter(|(_, obj)| matches!(obj.group, simulation::physics::PhysicsGroup::Vehicles))
+                .filter(|(_, obj)| matches!(obj.group, PhysicsGroup::Vehicles))
                 .count();
             if let Some(ref mut s) = self.generic_car_sound {
                 s.set_amplitude_ratio(
@@ -0,0 +1,87 @@
+use crate::gui::subtitles::Subtitles;
+use crate::gui::windows::settings::Settings;
+use crate::uiworld::UiWorld;
+use common::AudioKind;
+use engine::AudioContext;
+use geom::Camera;
+use simulation::utils::events::{SimEventKind, SimEvents};
+use simulation::utils::time::Tick;
+use simulation::Simulation;
+use std::time::{Duration, Instant};
+
+/// Events older than this (in ticks) are not worth a sound anymore
+const MAX_EVENT_AGE: u64 = 10;
+const HEAR_RADIUS: f32 = 300.0;
+/// Minimum real time between two sounds of the same kind, so a burst of events
+/// (e.g. the city building houses on its own) does not play the same sound over and over
+const MIN_SOUND_INTERVAL: Duration = Duration::from_secs(3);
+
+/// EventSounds plays the sounds of the simulation events that happened since the last frame.
+/// At high time warp many ticks happen every frame, so events of the same kind are aggregated
+/// into a single sound and stale events are skipped.
+/// The events heard are also shown as subtitles when enabled in the settings.
+pub struct EventSounds {
+    last_tick: Tick,
+    last_played: Vec<(SimEventKind, Instant)>,
+}
+
+impl EventSounds {
+    pub fn new() -> Self {
+        Self {
+            last_tick: Tick::default(),
+            last_played: vec![],
+        }
+    }
+
+    fn sound(kind: SimEventKind) -> Option<&'static str> {
+        match kind {
+            SimEventKind::Construction => Some("construction"),
+            SimEventKind::TrainArrived => None,
+            SimEventKind::Incident => None,
+        }
+    }
+
+    pub fn update(&mut self, sim: &Simulation, uiworld: &mut UiWorld, ctx: &mut AudioContext) {
+        let now = Tick(sim.get_tick());
+        let campos = uiworld.read::<Camera>().eye();
+        let events = sim.read::<SimEvents>();
+        let show_subtitles = uiworld.read::<Settings>().subtitles;
+
+        let mut played = vec![];
+        for e in events.since(self.last_tick) {
+            if e.tick.0 + MAX_EVENT_AGE < now.0
+                || played.contains(&e.kind)
+                || !e.pos.is_close(campos, HEAR_RADIUS)
+            {
+                continue;
+            }
+            played.push(e.kind);
+
+            if let Some(sound) = Self::sound(e.kind) {
+                if self.can_play(e.kind) {
+                    ctx.play(sound, AudioKind::Effect);
+                }
+            }
+            if show_subtitles {
+                uiworld.write::<Subtitles>().push(e.kind, e.pos);
+            }
+        }
+
+        self.last_tick = now;
+    }
+
+    fn can_play(&mut self, kind: SimEventKind) -> bool {
+        let now = Instant::now();
+        match self.last_played.iter_mut().find(|(k, _)| *k == kind) {
+            Some((_, last)) if now.duration_since(*last) < MIN_SOUND_INTERVAL => false,
+            Some((_, last)) => {
+                *last = now;
+                true
+            }
+            None => {
+                self.last_played.push((kind, now));
+                true
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use simulation::Simulation;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock, TryLockError};
+
+/// Emergency saves go to their own slot: the simulation might be in a broken state
+/// so it must not overwrite the last good save.
+pub const CRASH_SAVE_NAME: &str = "world_crash";
+
+static SIM: Mutex<Option<Arc<RwLock<Simulation>>>> = Mutex::new(None);
+static SAVED: AtomicBool = AtomicBool::new(false);
+
+/// Installs a panic hook that attempts an emergency save.
+/// Must be called after the logger is initialized (by the engine) as it chains the previous hook.
+pub fn install_hook() {
+    let prev = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        prev(info);
+        emergency_save(false);
+    }));
+}
+
+/// Sets the simulation to save on crash
+pub fn register(sim: Arc<RwLock<Simulation>>) {
+    if let Ok(mut s) = SIM.lock() {
+        *s = Some(sim);
+    }
+}
+
+/// Saves the simulation to the crash slot, at most once.
+/// During the panic hook, the panicking thread might be holding the simulation (it usually is when
+/// the crash happens during a tick), so we don't wait for it and the save is retried after unwinding.
+pub fn emergency_save(after_unwind: bool) {
+    if SAVED.load(Ordering::SeqCst) {
+        return;
+    }
+    let Ok(sim) = SIM.try_lock() else {
+        return;
+    };
+    let Some(ref sim) = *sim else {
+        return;
+    };
+
+    let sim = match sim.try_read() {
+        Ok(sim) => sim,
+        Err(TryLockError::Poisoned(p)) => p.into_inner(),
+        Err(TryLockError::WouldBlock) if after_unwind => {
+            sim.read().unwrap_or_else(|p| p.into_inner())
+        }
+        Err(TryLockError::WouldBlock) => {
+            log::warn!("simulation is busy, emergency save postponed after unwinding");
+            return;
+        }
+    };
+
+    SAVED.store(true, Ordering::SeqCst);
+    log::error!("game crashed, emergency save to {}", CRASH_SAVE_NAME);
+    sim.save_to_disk(CRASH_SAVE_NAME);
+}
@@ -6,6 +6,7 @@ use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
 use common::History;
 use engine::{Context, FrameContext, Tesselator};
 use geom::{vec2, vec3, Camera, LinearColor};
+use simulation::utils::render_snapshot::SnapshotBuffer;
 use simulation::utils::time::GameTime;
 use simulation::Simulation;
 
@@ -38,13 +39,15 @@ pub struct State {
 
 impl engine::framework::State for State {
     fn new(ctx: &mut Context) -> Self {
+        crate::profiles::select_at_startup();
         let camera = OrbitCamera::load((ctx.gfx.size.0, ctx.gfx.size.1));
 
         Gui::set_style(ctx.egui.platform.egui_ctx());
         log::info!("loaded egui_render");
 
-        let sim: Simulation =
-            Simulation::load_from_disk("world").unwrap_or_else(|| Simulation::new(true));
+        let loaded = Simulation::load_from_disk("world");
+        let resume_session = loaded.is_some();
+        let sim: Simulation = loaded.unwrap_or_else(|| Simulation::new(true));
         let game_schedule = Simulation::schedule();
         let mut uiworld = UiWorld::init();
 
@@ -64,6 +67,9 @@ impl engine::framework::State for State {
         let gui: Gui = common::saveload::JSON::load("gui").unwrap_or_default();
         uiworld.insert(camera.camera);
         uiworld.insert(camera);
+        if resume_session {
+            gui.session.restore(&mut uiworld, &sim);
+        }
 
         log::info!("version is {}", VERSION);
 
@@ -85,6 +91,8 @@ impl engine::framework::State for State {
             immtess: Tesselator::new(&mut ctx.gfx, None, 1.0),
         };
         me.sim.write().unwrap().map().dispatch_all();
+        crate::crash_save::install_hook();
+        crate::crash_save::register(me.sim.clone());
         me
     }
 
@@ -97,15 +105,28 @@ impl engine::framework::State for State {
             let cpy = self.sim.clone();
             slstate.saving_status.store(true, Ordering::SeqCst);
             let status = slstate.saving_status.clone();
+            let pause = self.uiw.read::<Settings>().pause_during_saves;
             std::thread::spawn(move || {
                 profiling::scope!("game_loop::update::save");
-                cpy.read().unwrap().save_to_disk("world");
+                if pause {
+                    cpy.read().unwrap().save_to_disk("world");
+                } else {
+                    // only hold the simulation while encoding so it keeps running during the rest of the save
+                    let snapshot = cpy.read().unwrap().snapshot();
+                    if let Some(snapshot) = snapshot {
+                        snapshot.write_to_disk("world");
+                    }
+                }
                 status.store(false, Ordering::SeqCst);
             });
         }
         drop(slstate);
 
         crate::network::sim_update(self);
+        // the ticks of this frame are done, the rendering and the GUI read their result from now on
+        self.uiw
+            .read::<SnapshotBuffer>()
+            .publish(&self.sim.read().unwrap());
 
         if std::mem::take(&mut self.uiw.write::<SaveLoadState>().render_reset) {
             self.reset(ctx);
@@ -150,8 +171,8 @@ impl engine::framework::State for State {
 
         self.map_renderer.update(&self.sim.read().unwrap(), ctx);
 
-        ctx.gfx
-            .set_time(self.sim.read().unwrap().read::<GameTime>().timestamp as f32);
+        let time = self.uiw.read::<SnapshotBuffer>().latest().time;
+        ctx.gfx.set_time(time.timestamp as f32);
 
         for (sound, kind) in self.uiw.write::<ImmediateSound>().orders.drain(..) {
             ctx.audio.play(sound, kind);
@@ -173,7 +194,9 @@ impl engine::framework::State for State {
         let camera = self.uiw.read::<OrbitCamera>();
         camera.cull_tess(&mut self.immtess);
 
-        let time: GameTime = *self.sim.read().unwrap().read::<GameTime>();
+        let snapshot = self.uiw.read::<SnapshotBuffer>().latest();
+        let time: GameTime = snapshot.time;
+        let performance_mode = self.uiw.read::<Settings>().gfx.performance_mode;
 
         self.map_renderer.render(
             &sim.map(),
@@ -182,13 +205,14 @@ impl engine::framework::State for State {
             MapRenderOptions {
                 show_arrows: self.uiw.read::<Tool>().show_arrows(),
                 show_lots: self.uiw.read::<Tool>().show_lots(),
+                performance_mode,
             },
             &mut self.uiw.write::<ImmediateDraw>(),
             ctx,
         );
 
         self.instanced_renderer
-            .render(&self.sim.read().unwrap(), ctx);
+            .render(&snapshot, &camera.camera, performance_mode, ctx);
 
         drop(sim);
         drop(camera);
@@ -248,8 +272,10 @@ impl State {
         self.uiw.insert(ctx.gfx.perf.as_static());
 
         let params = ctx.gfx.render_params.value_mut();
+        let hour = (params.time % GameTime::DAY as f32) / GameTime::HOUR as f32;
         params.time_always = (params.time_always + ctx.delta) % 3600.0;
         params.sun_col = 4.0
+            * simulation::config().sun_intensity.eval(hour)
             * sun.z.max(0.0).sqrt().sqrt()
             * LinearColor::new(1.0, 0.95 + sun.z * 0.05, 0.95 + sun.z * 0.05, 1.0);
         let camera = self.uiw.read::<OrbitCamera>();
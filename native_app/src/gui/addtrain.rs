@@ -5,13 +5,20 @@ use crate::rendering::immediate::ImmediateDraw;
 use crate::uiworld::UiWorld;
 use geom::{Color, OBB};
 use simulation::map::LaneKind;
-use simulation::transportation::train::{train_length, wagons_positions_for_render};
+use simulation::transportation::train::{
+    check_train_placement, wagons_positions_for_render, LocomotiveKind,
+};
 use simulation::world_command::WorldCommand;
 use simulation::Simulation;
 use std::option::Option::None;
 
+#[derive(Default)]
+pub struct AddTrainResource {
+    pub loco: LocomotiveKind,
+}
+
 /// Addtrain handles the "Adding a train" tool
-/// It allows to add a train to any rail lane
+/// It allows to add a train to any rail lane, electric trains need electrified rails
 pub fn addtrain(sim: &Simulation, uiworld: &mut UiWorld) {
     profiling::scope!("gui::addtrain");
     let tool = *uiworld.read::<Tool>();
@@ -25,6 +32,7 @@ pub fn addtrain(sim: &Simulation, uiworld: &mut UiWorld) {
     let mut draw = uiworld.write::<ImmediateDraw>();
     let map = sim.map();
     let commands = &mut *uiworld.commands();
+    let loco = uiworld.read::<AddTrainResource>().loco;
 
     let mpos = unwrap_ret!(inp.unprojected);
 
@@ -43,7 +51,6 @@ pub fn addtrain(sim: &Simulation, uiworld: &mut UiWorld) {
     let dist = nearbylane.points.length_at_proj(proj);
 
     let n_wagons = 7;
-    let trainlength = train_length(n_wagons);
 
     let mut drawtrain = |col: Color| {
         for (p, dir) in wagons_positions_for_render(&nearbylane.points, dist, n_wagons) {
@@ -52,7 +59,16 @@ pub fn addtrain(sim: &Simulation, uiworld: &mut UiWorld) {
         }
     };
 
-    if dist <= trainlength {
+    if check_train_placement(
+        &map,
+        &sim.world().trains,
+        nearbylane.id,
+        dist,
+        n_wagons,
+        loco,
+    )
+    .is_err()
+    {
         drawtrain(simulation::config().gui_danger);
         return;
     }
@@ -63,6 +79,7 @@ pub fn addtrain(sim: &Simulation, uiworld: &mut UiWorld) {
         dist,
         n_wagons,
         lane: nearbylane.id,
+        loco,
     };
     if inp.just_act.contains(&InputAction::Select) {
         commands.push(cmd);
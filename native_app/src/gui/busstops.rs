@@ -0,0 +1,114 @@
+use super::Tool;
+use crate::gui::windows::buslines::BusLinesState;
+use crate::inputmap::{InputAction, InputMap};
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+use geom::{Color, Vec2};
+use simulation::map::{BusStop, BusStopID, Map, MIN_BUS_STOP_SPACING};
+use simulation::transportation::bus::BusLines;
+use simulation::Simulation;
+
+/// Height above the sidewalk at which the shelters are drawn
+const SHELTER_Z: f32 = 0.3;
+/// Height above the ground at which the lines are drawn
+const LINE_Z: f32 = 0.5;
+
+/// Bus stop tool
+/// Places bus stops on the sidewalks and adds them to the line selected in the bus lines window.
+pub fn busstops(sim: &Simulation, uiworld: &mut UiWorld) {
+    profiling::scope!("gui::busstops");
+    let tool = *uiworld.read::<Tool>();
+    if !matches!(tool, Tool::BusStops) {
+        return;
+    }
+    let inp = uiworld.read::<InputMap>();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let map = sim.map();
+
+    let mpos = unwrap_ret!(inp.unprojected);
+    let hovered = hovered_stop(&map, mpos.xy());
+
+    if inp.just_act.contains(&InputAction::SecondarySelect) {
+        if let Some(id) = hovered {
+            uiworld.commands().map_remove_bus_stop(id);
+        }
+        return;
+    }
+
+    if let Some(id) = hovered {
+        let stop = &map.bus_stops()[id];
+        draw.obb(stop.obb(), stop.pos.z + SHELTER_Z + 0.05)
+            .color(simulation::config().gui_primary.a(0.5));
+        if inp.just_act.contains(&InputAction::Select) {
+            let selected = uiworld.read::<BusLinesState>().selected;
+            let lines = sim.read::<BusLines>();
+            if let Some((id_line, line)) = selected.and_then(|l| Some((l, lines.get(l)?))) {
+                let mut line = line.clone();
+                line.stops.push(id);
+                uiworld.commands().update_bus_line(id_line, line);
+            }
+        }
+        return;
+    }
+
+    let name = format!("Stop {}", map.bus_stops().len() + 1);
+    let Some(stop) = map.snap_bus_stop(mpos, name) else {
+        return;
+    };
+    let mut col = simulation::config().gui_primary;
+    let too_close = map
+        .bus_stops()
+        .values()
+        .any(|s| s.pos.is_close(stop.pos, MIN_BUS_STOP_SPACING));
+    if stop.check().is_err() || too_close {
+        col = simulation::config().gui_danger;
+    }
+    draw.obb(stop.obb(), stop.pos.z + SHELTER_Z)
+        .color(col.a(0.5));
+
+    if inp.just_act.contains(&InputAction::Select) {
+        uiworld.commands().map_add_bus_stop(stop);
+    }
+}
+
+/// Draws the bus stops and the routes of the lines, they are shown whatever the tool
+pub fn draw_buslines(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::draw_buslines");
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let map = sim.map();
+    let stops = map.bus_stops();
+    for stop in stops.values() {
+        draw_stop(&mut draw, stop);
+    }
+
+    let selected = uiworld.read::<BusLinesState>().selected;
+    for (id, line) in sim.read::<BusLines>().iter() {
+        let thickness = if selected == Some(id) { 2.0 } else { 1.0 };
+        let points: Vec<_> = line
+            .stops
+            .iter()
+            .filter_map(|&s| stops.get(s))
+            .map(|s| s.pos.up(LINE_Z))
+            .collect();
+        for w in points.windows(2) {
+            draw.line(w[0], w[1], thickness).color(line.color);
+        }
+        // the buses come back to the first stop after the last one
+        if points.len() > 2 {
+            draw.line(points[points.len() - 1], points[0], thickness)
+                .color(line.color);
+        }
+    }
+}
+
+fn draw_stop(draw: &mut ImmediateDraw, stop: &BusStop) {
+    draw.obb(stop.obb(), stop.pos.z + SHELTER_Z)
+        .color(Color::new(0.2, 0.4, 0.8, 1.0));
+}
+
+fn hovered_stop(map: &Map, pos: Vec2) -> Option<BusStopID> {
+    map.bus_stops()
+        .iter()
+        .find(|(_, stop)| stop.obb().contains(pos))
+        .map(|(id, _)| id)
+}
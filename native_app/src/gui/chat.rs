@@ -1,3 +1,4 @@
+use common::i18n::tr;
 use egui::panel::TopBottomSide;
 use egui::{Align2, Color32, Frame, RichText, ScrollArea, TextBuffer, TopBottomPanel};
 
@@ -66,7 +67,8 @@ pub fn chat(ui: &egui::Context, uiw: &mut UiWorld, sim: &Simulation) {
                 for message in msgs.iter().rev() {
                     let color = message.color;
 
-                    let text = RichText::new(message.text.clone());
+                    // messages sent by the simulation are translated like the rest of the interface
+                    let text = RichText::new(tr(&message.text));
 
                     ui.horizontal_wrapped(|ui| {
                         ui.add_space(5.0);
@@ -0,0 +1,169 @@
+use super::Tool;
+use crate::gui::PotentialCommands;
+use crate::inputmap::{InputAction, InputMap};
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+use geom::{Color, Vec3};
+use simulation::map::{
+    CurbKind, CurbZone, LaneDirection, Map, ProjectFilter, ProjectKind, Road, RoadID,
+};
+use simulation::world_command::WorldCommand;
+use simulation::Simulation;
+
+pub struct CurbToolResource {
+    pub kind: CurbKind,
+    /// Where the section being designated starts: its road, side and distance along the road
+    start: Option<(RoadID, LaneDirection, f32)>,
+}
+
+impl Default for CurbToolResource {
+    fn default() -> Self {
+        Self {
+            kind: CurbKind::LoadingZone,
+            start: None,
+        }
+    }
+}
+
+pub fn curb_color(kind: CurbKind) -> Color {
+    match kind {
+        CurbKind::Parking => Color::new(0.3, 0.5, 1.0, 0.8),
+        CurbKind::BusStop => Color::new(0.9, 0.2, 0.2, 0.8),
+        CurbKind::LoadingZone => Color::new(1.0, 0.8, 0.0, 0.8),
+        CurbKind::NoParking => Color::new(0.6, 0.6, 0.6, 0.8),
+    }
+}
+
+/// Curb tool
+/// Designates sections of the curbs as loading zones or no parking by dragging along a side of a
+/// road. Designating as parking clears the designations. The bus bays are shown, they are placed
+/// with the bus stops.
+pub fn curbs(sim: &Simulation, uiworld: &mut UiWorld) {
+    profiling::scope!("gui::curbs");
+    let tool = *uiworld.read::<Tool>();
+    let mut state = uiworld.write::<CurbToolResource>();
+
+    if !matches!(tool, Tool::Curbs) {
+        state.start = None;
+        return;
+    }
+
+    let inp = uiworld.read::<InputMap>();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let mut potential = uiworld.write::<PotentialCommands>();
+    let map = sim.map();
+
+    for road in map.roads().values() {
+        for z in road.curbs.zones() {
+            draw_curb(
+                &mut draw,
+                &map,
+                road,
+                z.side,
+                z.start,
+                z.end,
+                curb_color(z.kind),
+            );
+        }
+    }
+
+    let mpos = unwrap_ret!(inp.unprojected);
+
+    let Some((road, side, start)) = state.start else {
+        let ProjectKind::Road(id) = map.project(mpos, 10.0, ProjectFilter::ROAD).kind else {
+            return;
+        };
+        let Some(road) = map.roads().get(id).filter(|r| !r.is_rail()) else {
+            return;
+        };
+        let side = closest_side(&map, road, mpos);
+        let dist = dist_along(road, mpos);
+        draw_curb(
+            &mut draw,
+            &map,
+            road,
+            side,
+            dist - 1.0,
+            dist + 1.0,
+            simulation::config().gui_primary,
+        );
+        if inp.just_act.contains(&InputAction::Select) {
+            state.start = Some((id, side, dist));
+        }
+        return;
+    };
+
+    let Some(r) = map.roads().get(road) else {
+        state.start = None;
+        return;
+    };
+    let end = dist_along(r, mpos);
+    let zone = CurbZone {
+        side,
+        start: start.min(end),
+        end: start.max(end),
+        kind: state.kind,
+    };
+    let mut col = curb_color(state.kind);
+    col.a = 0.5;
+    draw_curb(&mut draw, &map, r, side, zone.start, zone.end, col);
+
+    let cmd = WorldCommand::MapSetRoadCurb { road, zone };
+    if inp.act.contains(&InputAction::Select) {
+        potential.set(cmd);
+        return;
+    }
+    state.start = None;
+    if zone.length() > 0.0 {
+        uiworld.commands().push(cmd);
+    }
+}
+
+pub fn dist_along(road: &Road, pos: Vec3) -> f32 {
+    let points = road.points();
+    points
+        .length_at_proj(points.project(pos))
+        .clamp(0.0, road.length())
+}
+
+pub fn closest_side(map: &Map, road: &Road, pos: Vec3) -> LaneDirection {
+    let dist = |side| {
+        road.curb_lane(side)
+            .and_then(|l| map.lanes().get(l))
+            .map_or(f32::INFINITY, |l| l.points.project_dist2(pos))
+    };
+    if dist(LaneDirection::Backward) < dist(LaneDirection::Forward) {
+        return LaneDirection::Backward;
+    }
+    LaneDirection::Forward
+}
+
+/// Draws the section of the curb along the lane vehicles stop on
+fn draw_curb(
+    draw: &mut ImmediateDraw,
+    map: &Map,
+    road: &Road,
+    side: LaneDirection,
+    start: f32,
+    end: f32,
+    col: Color,
+) {
+    let Some(lane) = road.curb_lane(side).and_then(|l| map.lanes().get(l)) else {
+        return;
+    };
+    let start = start.max(0.0);
+    let end = end.min(road.length());
+    if end <= start {
+        return;
+    }
+    let n = ((end - start) / 2.0).ceil().max(1.0) as usize;
+    let points: Vec<Vec3> = (0..=n)
+        .map(|i| {
+            let p = road
+                .points()
+                .point_along(start + (end - start) * i as f32 / n as f32);
+            lane.points.project(p).up(0.2)
+        })
+        .collect();
+    draw.polyline(points, 1.0, false).color(col);
+}
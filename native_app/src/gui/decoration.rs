@@ -0,0 +1,221 @@
+use super::Tool;
+use crate::inputmap::{InputAction, InputMap};
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::OrbitCamera;
+use crate::uiworld::UiWorld;
+use egui::{Align2, Color32, Context, FontId, LayerId};
+use geom::{Color, Degrees, Vec2, OBB};
+use simulation::map::{
+    DecalPattern, Decoration, DecorationKind, DecorationSupport, ProjectFilter, ProjectKind,
+};
+use simulation::Simulation;
+
+/// Camera distance above which the sign texts are not shown
+const SIGN_TEXT_MAX_DIST: f32 = 400.0;
+/// Height above the ground at which decals are drawn
+const DECAL_Z: f32 = 0.15;
+/// Height above its support at which a sign panel is drawn
+const SIGN_Z: f32 = 3.0;
+
+pub struct DecorationToolResource {
+    pub pattern: DecalPattern,
+    /// Whether a sign is placed instead of a decal
+    pub sign: bool,
+    pub text: String,
+    pub size: f32,
+    pub color: Color,
+    pub rotation: Degrees,
+}
+
+impl Default for DecorationToolResource {
+    fn default() -> Self {
+        Self {
+            pattern: DecalPattern::Stripes,
+            sign: false,
+            text: String::new(),
+            size: 6.0,
+            color: Color::WHITE,
+            rotation: Degrees(0.0),
+        }
+    }
+}
+
+impl DecorationToolResource {
+    fn kind(&self) -> DecorationKind {
+        if self.sign {
+            DecorationKind::Sign(self.text.clone())
+        } else {
+            DecorationKind::Decal(self.pattern)
+        }
+    }
+}
+
+/// Decoration tool
+/// Places decals on the ground and roads and text signs on buildings and roads, purely cosmetic.
+pub fn decoration(sim: &Simulation, uiworld: &mut UiWorld) {
+    profiling::scope!("gui::decoration");
+    let tool = *uiworld.read::<Tool>();
+    if !matches!(tool, Tool::Decoration) {
+        return;
+    }
+    let mut state = uiworld.write::<DecorationToolResource>();
+    let inp = uiworld.read::<InputMap>();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let map = sim.map();
+
+    if inp.act.contains(&InputAction::Rotate) {
+        state.rotation += Degrees(inp.wheel);
+        state.rotation.normalize();
+    }
+    if inp.just_act.contains(&InputAction::RotateLeft) {
+        state.rotation += Degrees(15.0);
+        state.rotation.normalize();
+    }
+    if inp.just_act.contains(&InputAction::RotateRight) {
+        state.rotation -= Degrees(15.0);
+        state.rotation.normalize();
+    }
+
+    let mpos = unwrap_ret!(inp.unprojected);
+
+    if inp.just_act.contains(&InputAction::SecondarySelect) {
+        let hovered = map
+            .decorations()
+            .iter()
+            .filter(|(_, d)| d.obb().contains(mpos.xy()))
+            .min_by_key(|(_, d)| ordered_float::OrderedFloat(d.pos.xy().distance(mpos.xy())));
+        if let Some((id, _)) = hovered {
+            uiworld.commands().map_remove_decoration(id);
+        }
+        return;
+    }
+
+    let proj = map.project(mpos, 5.0, ProjectFilter::ROAD | ProjectFilter::BUILDING);
+    let on = match proj.kind {
+        ProjectKind::Road(id) => DecorationSupport::Road(id),
+        ProjectKind::Building(id) => DecorationSupport::Building(id),
+        _ => DecorationSupport::Ground,
+    };
+
+    let d = Decoration {
+        kind: state.kind(),
+        pos: mpos,
+        dir: state.rotation.vec2(),
+        size: state.size,
+        color: state.color,
+        on,
+    };
+
+    let mut col = simulation::config().gui_primary;
+    if d.check().is_err() {
+        col = simulation::config().gui_danger;
+    }
+    draw.obb(d.obb(), mpos.z + DECAL_Z + 0.05).color(col.a(0.3));
+    draw_decoration(&mut draw, &d);
+
+    if inp.just_act.contains(&InputAction::Select) {
+        uiworld.commands().map_add_decoration(d);
+    }
+}
+
+/// Draws the decorations of the map, they are shown whatever the tool
+pub fn draw_decorations(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::draw_decorations");
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    for d in sim.map().decorations().values() {
+        draw_decoration(&mut draw, d);
+    }
+}
+
+fn draw_decoration(draw: &mut ImmediateDraw, d: &Decoration) {
+    let z = d.pos.z + DECAL_Z;
+    let dir = d.dir.normalize();
+    let perp = dir.perpendicular();
+    let center = d.pos.xy();
+    let half = d.size * 0.5;
+    let pattern = match d.kind {
+        DecorationKind::Decal(pattern) => pattern,
+        DecorationKind::Sign(_) => {
+            draw.obb(d.obb(), d.pos.z + SIGN_Z).color(d.color);
+            return;
+        }
+    };
+
+    match pattern {
+        DecalPattern::Stripes => {
+            const N: i32 = 5;
+            let w = d.size / (N * 2 - 1) as f32;
+            for i in 0..N {
+                let off = -half + w * (0.5 + 2.0 * i as f32);
+                let c = center + perp * off;
+                draw.obb(OBB::new(c, dir, d.size, w), z).color(d.color);
+            }
+        }
+        DecalPattern::Arrow => {
+            let thickness = d.size * 0.08;
+            let tail = center - dir * half;
+            let tip = center + dir * half;
+            let head = dir * d.size * 0.3;
+            let side = perp * d.size * 0.25;
+            draw.line(tail.z(z), tip.z(z), thickness).color(d.color);
+            draw.line(tip.z(z), (tip - head + side).z(z), thickness)
+                .color(d.color);
+            draw.line(tip.z(z), (tip - head - side).z(z), thickness)
+                .color(d.color);
+        }
+        DecalPattern::Checker => {
+            const N: i32 = 4;
+            let w = d.size / N as f32;
+            for i in 0..N {
+                for j in 0..N {
+                    if (i + j) % 2 == 1 {
+                        continue;
+                    }
+                    let c = center
+                        + dir * (-half + w * (i as f32 + 0.5))
+                        + perp * (-half + w * (j as f32 + 0.5));
+                    draw.obb(OBB::new(c, dir, w, w), z).color(d.color);
+                }
+            }
+        }
+        DecalPattern::Rings => {
+            const N: i32 = 3;
+            let thickness = half / (N * 2) as f32;
+            for i in 1..=N {
+                draw.stroke_circle(d.pos.up(DECAL_Z), half * i as f32 / N as f32, thickness)
+                    .color(d.color);
+            }
+        }
+    }
+}
+
+/// Writes the text of the signs over them when the camera is close enough
+pub fn sign_texts(ui: &Context, uiworld: &UiWorld, sim: &Simulation) {
+    profiling::scope!("gui::sign_texts");
+    let camera = uiworld.read::<OrbitCamera>().camera;
+    if camera.dist > SIGN_TEXT_MAX_DIST {
+        return;
+    }
+    let painter = ui.layer_painter(LayerId::background());
+    let ppp = ui.pixels_per_point();
+    for d in sim.map().decorations().values() {
+        let DecorationKind::Sign(ref text) = d.kind else {
+            continue;
+        };
+        let Some(screen) = camera.project(d.pos.up(SIGN_Z)) else {
+            continue;
+        };
+        let screen: Vec2 = screen / ppp;
+        painter.text(
+            [screen.x, screen.y].into(),
+            Align2::CENTER_BOTTOM,
+            text,
+            FontId::proportional(14.0),
+            Color32::from_rgb(
+                (d.color.r * 255.0) as u8,
+                (d.color.g * 255.0) as u8,
+                (d.color.b * 255.0) as u8,
+            ),
+        );
+    }
+}
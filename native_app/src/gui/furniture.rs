@@ -0,0 +1,103 @@
+use super::Tool;
+use crate::gui::curbs::{closest_side, dist_along};
+use crate::inputmap::{InputAction, InputMap};
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+use geom::Color;
+use simulation::map::{
+    FurnitureKind, Map, ProjectFilter, ProjectKind, Road, RoadFurniture, FURNITURE_MIN_SPACING,
+};
+use simulation::Simulation;
+
+pub struct FurnitureToolResource {
+    pub kind: FurnitureKind,
+}
+
+impl Default for FurnitureToolResource {
+    fn default() -> Self {
+        Self {
+            kind: FurnitureKind::SpeedBump,
+        }
+    }
+}
+
+pub fn furniture_color(kind: FurnitureKind) -> Color {
+    match kind {
+        FurnitureKind::SpeedBump => Color::new(1.0, 0.8, 0.0, 0.9),
+        FurnitureKind::StopSign => Color::new(0.9, 0.1, 0.1, 0.9),
+        FurnitureKind::YieldSign => Color::new(1.0, 1.0, 1.0, 0.9),
+    }
+}
+
+/// Road furniture tool
+/// Places speed bumps, stop and yield signs along roads, signs apply to the closest side.
+pub fn furniture(sim: &Simulation, uiworld: &mut UiWorld) {
+    profiling::scope!("gui::furniture");
+    let tool = *uiworld.read::<Tool>();
+    if !matches!(tool, Tool::RoadFurniture) {
+        return;
+    }
+    let state = uiworld.read::<FurnitureToolResource>();
+    let inp = uiworld.read::<InputMap>();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let map = sim.map();
+
+    for road in map.roads().values() {
+        for f in road.furniture.items() {
+            draw_furniture(&mut draw, &map, road, f, furniture_color(f.kind));
+        }
+    }
+
+    let mpos = unwrap_ret!(inp.unprojected);
+    let ProjectKind::Road(id) = map.project(mpos, 10.0, ProjectFilter::ROAD).kind else {
+        return;
+    };
+    let Some(road) = map
+        .roads()
+        .get(id)
+        .filter(|r| !r.is_rail() && !r.is_canal())
+    else {
+        return;
+    };
+    let f = RoadFurniture {
+        kind: state.kind,
+        side: closest_side(&map, road, mpos),
+        dist: dist_along(road, mpos),
+    };
+
+    if inp.just_act.contains(&InputAction::SecondarySelect) {
+        uiworld.commands().map_remove_road_furniture(id, f.dist);
+        return;
+    }
+
+    let mut col = simulation::config().gui_primary;
+    if road
+        .furniture
+        .items()
+        .iter()
+        .any(|x| (x.dist - f.dist).abs() < FURNITURE_MIN_SPACING)
+    {
+        col = simulation::config().gui_danger;
+    }
+    draw_furniture(&mut draw, &map, road, &f, col);
+
+    if inp.just_act.contains(&InputAction::Select) {
+        uiworld.commands().map_add_road_furniture(id, f);
+    }
+}
+
+/// Speed bumps are drawn across the road, signs next to the lanes they apply to
+fn draw_furniture(draw: &mut ImmediateDraw, map: &Map, road: &Road, f: &RoadFurniture, col: Color) {
+    let (pos, dir) = road.points().point_dir_along(f.dist);
+    if f.kind == FurnitureKind::SpeedBump {
+        let across = dir.xy().perpendicular().z0() * road.width * 0.5;
+        draw.line((pos - across).up(0.3), (pos + across).up(0.3), 1.0)
+            .color(col);
+        return;
+    }
+    let Some(lane) = road.curb_lane(f.side).and_then(|l| map.lanes().get(l)) else {
+        return;
+    };
+    draw.circle(lane.points.project(pos).up(0.3), 1.5)
+        .color(col);
+}
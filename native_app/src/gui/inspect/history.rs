@@ -0,0 +1,195 @@
+use crate::gui::{FollowEntity, InspectedBuilding, InspectedEntity, InspectedIntersection};
+use crate::uiworld::UiWorld;
+use egui::Ui;
+use simulation::map::{BuildingID, BuildingKind, IntersectionID};
+use simulation::souls::goods_company::GoodsCompanyRegistry;
+use simulation::{AnyEntity, Simulation};
+
+/// Number of inspected things remembered
+const MAX_HISTORY: usize = 50;
+/// Number of previous steps shown in the breadcrumbs
+const MAX_CRUMBS: usize = 4;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InspectTarget {
+    Entity(AnyEntity),
+    Building(BuildingID),
+    Intersection(IntersectionID),
+}
+
+/// InspectHistory remembers what was inspected so that the player can go back and forth
+/// between them like in a browser
+#[derive(Default)]
+pub struct InspectHistory {
+    targets: Vec<InspectTarget>,
+    /// Index in targets of what is currently inspected
+    cur: usize,
+    last_entity: Option<AnyEntity>,
+    last_building: Option<BuildingID>,
+    last_intersection: Option<IntersectionID>,
+}
+
+impl InspectHistory {
+    /// Records whatever started being inspected since the last frame
+    fn observe(
+        &mut self,
+        entity: Option<AnyEntity>,
+        building: Option<BuildingID>,
+        intersection: Option<IntersectionID>,
+    ) {
+        if entity != self.last_entity {
+            if let Some(e) = entity {
+                self.visit(InspectTarget::Entity(e));
+            }
+        }
+        if building != self.last_building {
+            if let Some(b) = building {
+                self.visit(InspectTarget::Building(b));
+            }
+        }
+        if intersection != self.last_intersection {
+            if let Some(i) = intersection {
+                self.visit(InspectTarget::Intersection(i));
+            }
+        }
+        self.last_entity = entity;
+        self.last_building = building;
+        self.last_intersection = intersection;
+    }
+
+    fn visit(&mut self, target: InspectTarget) {
+        if self.targets.get(self.cur) == Some(&target) {
+            return;
+        }
+        if !self.targets.is_empty() {
+            self.targets.truncate(self.cur + 1);
+        }
+        self.targets.push(target);
+        if self.targets.len() > MAX_HISTORY {
+            self.targets.remove(0);
+        }
+        self.cur = self.targets.len() - 1;
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.cur > 0
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.cur + 1 < self.targets.len()
+    }
+
+    /// The targets before the current one, from the oldest to the most recent, with their index
+    fn crumbs(&self) -> impl Iterator<Item = (usize, InspectTarget)> + '_ {
+        let start = self.cur.saturating_sub(MAX_CRUMBS);
+        self.targets[start..self.cur.min(self.targets.len())]
+            .iter()
+            .enumerate()
+            .map(move |(i, &t)| (start + i, t))
+    }
+}
+
+/// Keeps the history in sync with what is inspected, must be called every frame
+pub fn track_history(uiworld: &mut UiWorld) {
+    let entity = uiworld.read::<InspectedEntity>().e;
+    let building = uiworld.read::<InspectedBuilding>().e;
+    let intersection = uiworld.read::<InspectedIntersection>().e;
+    uiworld
+        .write::<InspectHistory>()
+        .observe(entity, building, intersection);
+}
+
+/// Back and forward buttons followed by the breadcrumbs of the last inspected things
+pub fn history_bar(uiworld: &mut UiWorld, sim: &Simulation, ui: &mut Ui) {
+    let mut goto = None;
+    {
+        let history = uiworld.read::<InspectHistory>();
+        if history.targets.len() <= 1 {
+            return;
+        }
+        ui.horizontal_wrapped(|ui| {
+            if ui
+                .add_enabled(history.can_go_back(), egui::Button::new("<").small())
+                .clicked()
+            {
+                goto = Some(history.cur - 1);
+            }
+            if ui
+                .add_enabled(history.can_go_forward(), egui::Button::new(">").small())
+                .clicked()
+            {
+                goto = Some(history.cur + 1);
+            }
+            for (i, target) in history.crumbs() {
+                if ui.link(target_name(sim, target)).clicked() {
+                    goto = Some(i);
+                }
+                ui.label("›");
+            }
+            if let Some(&cur) = history.targets.get(history.cur) {
+                ui.label(target_name(sim, cur));
+            }
+        });
+    }
+    ui.separator();
+
+    if let Some(i) = goto {
+        navigate(uiworld, sim, i);
+    }
+}
+
+fn navigate(uiworld: &mut UiWorld, sim: &Simulation, i: usize) {
+    let mut history = uiworld.write::<InspectHistory>();
+    let Some(&target) = history.targets.get(i) else {
+        return;
+    };
+    history.cur = i;
+    drop(history);
+
+    match target {
+        InspectTarget::Entity(e) => {
+            uiworld.write::<InspectedEntity>().e = Some(e);
+            if sim.pos_any(e).is_some() {
+                uiworld.write::<FollowEntity>().0 = Some(e);
+            }
+        }
+        InspectTarget::Building(b) => {
+            uiworld.write::<InspectedBuilding>().e = Some(b);
+            if let Some(b) = sim.map().buildings().get(b) {
+                uiworld.camera_mut().targetpos = b.door_pos;
+            }
+        }
+        InspectTarget::Intersection(i) => {
+            uiworld.write::<InspectedIntersection>().e = Some(i);
+            if let Some(i) = sim.map().intersections().get(i) {
+                uiworld.camera_mut().targetpos = i.pos;
+            }
+        }
+    }
+}
+
+fn target_name(sim: &Simulation, target: InspectTarget) -> String {
+    match target {
+        InspectTarget::Entity(AnyEntity::HumanID(id)) => sim
+            .world()
+            .humans
+            .get(id)
+            .map_or_else(|| "???".to_string(), |h| h.personal_info.name.to_string()),
+        InspectTarget::Entity(e) => format!("{}", e),
+        InspectTarget::Building(b) => {
+            let map = sim.map();
+            let Some(b) = map.buildings().get(b) else {
+                return "???".to_string();
+            };
+            match b.kind {
+                BuildingKind::GoodsCompany(id) => sim
+                    .read::<GoodsCompanyRegistry>()
+                    .descriptions
+                    .get(id)
+                    .map_or_else(|| "???".to_string(), |d| d.name.clone()),
+                kind => format!("{:?}", kind),
+            }
+        }
+        InspectTarget::Intersection(_) => "Intersection".to_string(),
+    }
+}
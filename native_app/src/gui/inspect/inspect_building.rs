@@ -1,16 +1,31 @@
 use crate::uiworld::UiWorld;
-use egui::{Context, Ui, Widget};
+use common::descriptions::{CompanyKind, EducationLevel, ServiceKind};
+use egui::{Color32, Context, Ui, Widget};
 use simulation::economy::{ItemRegistry, Market};
+use simulation::utils::time::{GameInstant, GameTime, SECONDS_PER_HOUR};
 use simulation::world_command::WorldCommand;
-use simulation::{Simulation, SoulID};
+use simulation::{CompanyEnt, CompanyID, Simulation, SoulID};
 
-use crate::gui::inspect::entity_link;
+use crate::gui::inspect::{brand_label, entity_link, history::history_bar};
 use crate::gui::item_icon;
 use egui_inspect::{Inspect, InspectArgs, InspectVec2Rotation};
 use simulation::map::{Building, BuildingID, BuildingKind, Zone, MAX_ZONE_AREA};
-use simulation::map_dynamic::BuildingInfos;
-use simulation::souls::freight_station::FreightTrainState;
-use simulation::souls::goods_company::{GoodsCompanyRegistry, Recipe};
+use simulation::map_dynamic::{
+    BuildingInfos, BuildingRisks, LeisureVenues, ParkingManagement, VenueEvents, SERVICE_RADIUS,
+    VENUE_CAPACITY,
+};
+use simulation::souls::education::SCHOOL_RADIUS;
+use simulation::souls::freight_station::{FreightTrainState, MAX_TRAINS_PER_STATION};
+use simulation::souls::goods_company::{
+    FreightMode, GoodsCompanyRegistry, Recipe, FLEET_BUSY, MAX_FLEET_SIZE, TRUCK_PRICE,
+};
+use simulation::souls::health::hospital_beds;
+use simulation::transportation::airport::{Airports, FlightKind, PlaneState};
+use simulation::transportation::fuel::{GasStations, PUMPS_PER_STATION};
+use simulation::transportation::metro::{metro_platform, MetroLines};
+use simulation::transportation::road_maintenance::{PlowState, RoadMaintenance};
+use simulation::transportation::waterway::{BargeState, Harbors};
+use simulation::transportation::Location;
 
 /// Inspect a specific building, showing useful information about it
 pub fn inspect_building(uiworld: &mut UiWorld, sim: &Simulation, ui: &Context, id: BuildingID) {
@@ -26,12 +41,19 @@ pub fn inspect_building(uiworld: &mut UiWorld, sim: &Simulation, ui: &Context, i
         BuildingKind::RailFreightStation => "Rail Freight Station",
         BuildingKind::TrainStation => "Train Station",
         BuildingKind::ExternalTrading => "External Trading",
+        BuildingKind::SnowPlowDepot => "Snow Plow Depot",
+        BuildingKind::Airport => "Airport",
+        BuildingKind::Harbor => "Harbor",
+        BuildingKind::MetroStation => "Metro Station",
+        BuildingKind::ParkingLot => "Parking Lot",
+        BuildingKind::EventVenue => "Event Venue",
     };
 
     egui::Window::new(title)
         .resizable(false)
         .auto_sized()
         .show(ui, |ui| {
+            history_bar(uiworld, sim, ui);
             if cfg!(debug_assertions) {
                 ui.label(format!("{:?}", building.id));
             }
@@ -46,8 +68,16 @@ pub fn inspect_building(uiworld: &mut UiWorld, sim: &Simulation, ui: &Context, i
                 }
                 BuildingKind::TrainStation => {}
                 BuildingKind::ExternalTrading => {}
+                BuildingKind::SnowPlowDepot => render_snowplowdepot(ui, uiworld, sim, building),
+                BuildingKind::Airport => render_airport(ui, sim, building),
+                BuildingKind::Harbor => render_harbor(ui, sim, building),
+                BuildingKind::MetroStation => render_metro_station(ui, sim, building),
+                BuildingKind::ParkingLot => render_parking_lot(ui, sim, building),
+                BuildingKind::EventVenue => render_event_venue(ui, sim, building),
             };
 
+            render_risks(ui, sim, building);
+
             if let Some(ref zone) = building.zone {
                 let mut cpy = zone.filldir;
                 if InspectVec2Rotation::render_mut(
@@ -72,6 +102,71 @@ pub fn inspect_building(uiworld: &mut UiWorld, sim: &Simulation, ui: &Context, i
         });
 }
 
+/// Risks of incidents, with what makes them high or low
+fn render_risks(ui: &mut Ui, sim: &Simulation, b: &Building) {
+    let risks = sim.read::<BuildingRisks>();
+    let Some(risk) = risks.get(b.id) else {
+        return;
+    };
+
+    ui.separator();
+    ui.label(format!("Risks (density {:.0}%)", risk.density * 100.0));
+    egui::Grid::new("risks").show(ui, |ui| {
+        for service in ServiceKind::ALL {
+            let covered_by = match service {
+                ServiceKind::Fire => "fire station",
+                ServiceKind::Police => "police station",
+                ServiceKind::Health => "clinic",
+            };
+            let value = risk.risk(service);
+            let color = if value > 0.5 {
+                Color32::from_rgb(255, 100, 80)
+            } else if value > 0.25 {
+                Color32::from_rgb(255, 200, 80)
+            } else {
+                Color32::from_rgb(120, 220, 120)
+            };
+            ui.label(risk_name(service));
+            ui.colored_label(color, format!("{:.0}%", value * 100.0));
+            let coverage = risk.coverage(service);
+            if coverage > 0.0 {
+                ui.label(format!(
+                    "{:.0}% covered by a {}",
+                    coverage * 100.0,
+                    covered_by
+                ));
+            } else {
+                ui.label(format!("no {} within {:.0}m", covered_by, SERVICE_RADIUS));
+            }
+            ui.label(format!("exposure {:.0}%", risk.exposure(service) * 100.0));
+            ui.end_row();
+        }
+    });
+
+    let last = risks
+        .incidents()
+        .iter()
+        .rev()
+        .find(|incident| incident.building == b.id);
+    if let Some(incident) = last {
+        let hours = incident.at.elapsed(&sim.read::<GameTime>()) / SECONDS_PER_HOUR as f64;
+        ui.label(format!(
+            "Last incident: {}, {:.0}h ago, cost {}",
+            risk_name(incident.kind).to_lowercase(),
+            hours,
+            incident.cost
+        ));
+    }
+}
+
+fn risk_name(service: ServiceKind) -> &'static str {
+    match service {
+        ServiceKind::Fire => "Fire",
+        ServiceKind::Police => "Crime",
+        ServiceKind::Health => "Health",
+    }
+}
+
 fn render_house(ui: &mut Ui, uiworld: &mut UiWorld, sim: &Simulation, b: &Building) {
     let binfos = sim.read::<BuildingInfos>();
     let Some(info) = binfos.get(b.id) else {
@@ -81,6 +176,18 @@ fn render_house(ui: &mut Ui, uiworld: &mut UiWorld, sim: &Simulation, b: &Buildi
         return;
     };
 
+    if let Some(ref archetype) = b.archetype {
+        ui.label(format!("Archetype: {archetype}"));
+    }
+    ui.label(format!(
+        "Residents: {}/{}",
+        info.n_residents(),
+        info.capacity.residents
+    ));
+    if info.overcrowding() > 0.0 {
+        ui.colored_label(Color32::from_rgb(255, 100, 75), "Overcrowded");
+    }
+
     ui.horizontal(|ui| {
         ui.label("Owner");
         entity_link(uiworld, sim, ui, owner);
@@ -106,6 +213,45 @@ fn render_freightstation(ui: &mut Ui, uiworld: &mut UiWorld, sim: &Simulation, b
     ui.label(format!("Waiting cargo: {}", freight.f.waiting_cargo));
     ui.label(format!("Wanted cargo: {}", freight.f.wanted_cargo));
 
+    ui.add_space(10.0);
+    ui.label("Service")
+        .on_hover_text("Trains are added as cargo piles up, within these bounds");
+    let mut min = freight.f.min_trains;
+    let mut max = freight.f.max_trains;
+    ui.horizontal(|ui| {
+        ui.label(format!("min {min}"));
+        if ui.add_enabled(min > 0, egui::Button::new("-")).clicked() {
+            min -= 1;
+        }
+        if ui.add_enabled(min < max, egui::Button::new("+")).clicked() {
+            min += 1;
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label(format!("max {max}"));
+        if ui
+            .add_enabled(max > min.max(1), egui::Button::new("-"))
+            .clicked()
+        {
+            max -= 1;
+        }
+        if ui
+            .add_enabled(max < MAX_TRAINS_PER_STATION, egui::Button::new("+"))
+            .clicked()
+        {
+            max += 1;
+        }
+    });
+    if min != freight.f.min_trains || max != freight.f.max_trains {
+        uiworld.commands().set_freight_service(owner, min, max);
+    }
+    ui.label(format!(
+        "{}/{} trains wanted, {} added automatically",
+        freight.f.serving_trains(),
+        freight.f.wanted_trains(),
+        freight.f.auto_trains.len()
+    ));
+
     ui.add_space(10.0);
     ui.label("Trains:");
     for (tid, state) in &freight.f.trains {
@@ -126,6 +272,192 @@ fn render_freightstation(ui: &mut Ui, uiworld: &mut UiWorld, sim: &Simulation, b
     }
 }
 
+fn render_airport(ui: &mut Ui, sim: &Simulation, b: &Building) {
+    let airports = sim.read::<Airports>();
+    let Some(airport) = airports.airports.get(&b.id) else {
+        return;
+    };
+
+    ui.label(format!("Flights: {}", airport.n_flights));
+    ui.label(format!("Tourists welcomed: {}", airport.tourists));
+    ui.label(format!("Cargo exported: {}", airport.exported_cargo));
+
+    ui.add_space(10.0);
+    match airport.plane {
+        Some(plane) => {
+            let kind = match plane.kind {
+                FlightKind::Passenger => "Passenger",
+                FlightKind::Freight => "Freight",
+            };
+            let state = match plane.state {
+                PlaneState::Landing { .. } => "landing",
+                PlaneState::Boarding { .. } => "boarding",
+                PlaneState::TakingOff { .. } => "taking off",
+            };
+            ui.label(format!("{} plane {}", kind, state));
+        }
+        None => {
+            let time = sim.read::<GameTime>().timestamp;
+            let minutes = ((airport.next_flight - time) / 60.0).max(0.0);
+            ui.label(format!("Next flight in {:.0} minutes", minutes));
+        }
+    }
+
+    if let Some(SoulID::FreightStation(owner)) = sim.read::<BuildingInfos>().owner(b.id) {
+        if let Some(freight) = sim.world().get(owner) {
+            ui.label(format!(
+                "Cargo waiting for a plane: {}",
+                freight.f.waiting_cargo
+            ));
+        }
+    }
+}
+
+fn render_harbor(ui: &mut Ui, sim: &Simulation, b: &Building) {
+    let harbors = sim.read::<Harbors>();
+    let Some(harbor) = harbors.harbors.get(&b.id) else {
+        return;
+    };
+
+    ui.label(format!("Barges: {}", harbor.n_barges));
+    ui.label(format!("Cargo exported: {}", harbor.exported_cargo));
+    ui.label(format!("Cargo imported: {}", harbor.imported_cargo));
+
+    ui.add_space(10.0);
+    match harbor.barge {
+        Some(BargeState::Docking { .. }) => {
+            ui.label("Barge docking");
+        }
+        Some(BargeState::Loading { .. }) => {
+            ui.label("Barge loading");
+        }
+        None => {
+            let time = sim.read::<GameTime>().timestamp;
+            let minutes = ((harbor.next_barge - time) / 60.0).max(0.0);
+            ui.label(format!("Next barge in {:.0} minutes", minutes));
+        }
+    }
+
+    if let Some(SoulID::FreightStation(owner)) = sim.read::<BuildingInfos>().owner(b.id) {
+        if let Some(freight) = sim.world().get(owner) {
+            ui.label(format!(
+                "Cargo waiting for a barge: {}",
+                freight.f.waiting_cargo
+            ));
+        }
+    }
+}
+
+fn render_metro_station(ui: &mut Ui, sim: &Simulation, b: &Building) {
+    if metro_platform(&sim.map(), b.id).is_none() {
+        ui.label("No metro tunnel under the station");
+        return;
+    }
+
+    let lines = sim.read::<MetroLines>();
+    let mut served = false;
+    for (id, line) in lines.iter() {
+        if !line.stations.contains(&b.id) {
+            continue;
+        }
+        served = true;
+        if line.is_running() {
+            let next = GameInstant {
+                timestamp: lines.next_departure(id),
+            };
+            ui.label(format!("{}: next departure {}", line.name, next));
+        } else {
+            ui.label(format!("{}: not running", line.name));
+        }
+    }
+    if !served {
+        ui.label("No metro line serves the station");
+        return;
+    }
+    let waiting = sim
+        .world()
+        .humans
+        .values()
+        .filter(|h| {
+            h.router
+                .waiting_for_metro()
+                .map_or(false, |t| t.from == b.id)
+        })
+        .count();
+    ui.label(format!("Waiting at the entrance: {}", waiting));
+}
+
+fn render_parking_lot(ui: &mut Ui, sim: &Simulation, b: &Building) {
+    let map = sim.map();
+    let spots = map.parking.lot_spots(b.id);
+    let parking = sim.read::<ParkingManagement>();
+    let taken = spots.iter().filter(|&&s| !parking.is_spot_free(s)).count();
+    ui.label(format!("Spots taken: {}/{}", taken, spots.len()));
+}
+
+fn render_event_venue(ui: &mut Ui, sim: &Simulation, b: &Building) {
+    let events = sim.read::<VenueEvents>();
+    let Some(event) = events.event(b.id) else {
+        ui.label("No event planned");
+        return;
+    };
+    let time = sim.read::<GameTime>();
+    if event.is_ongoing(&time) {
+        ui.label(format!("{} until {}", event.kind, event.end));
+    } else {
+        ui.label(format!("Next: {} at {}", event.kind, event.start));
+    }
+    if !event.announced {
+        return;
+    }
+    let present = sim
+        .world()
+        .humans
+        .values()
+        .filter(|h| h.location == Location::Building(b.id))
+        .count();
+    ui.label(format!(
+        "Tickets sold: {}/{}",
+        event.tickets, VENUE_CAPACITY
+    ));
+    ui.label(format!("In the venue: {}", present));
+}
+
+fn render_snowplowdepot(ui: &mut Ui, uiworld: &mut UiWorld, sim: &Simulation, b: &Building) {
+    let maintenance = sim.read::<RoadMaintenance>();
+
+    ui.label("Trucks:");
+    for (vid, plow) in &maintenance.plows {
+        if plow.depot != b.id {
+            continue;
+        }
+        ui.horizontal(|ui| {
+            entity_link(uiworld, sim, ui, *vid);
+            match plow.state {
+                PlowState::Parked => {
+                    ui.label("Parked");
+                }
+                PlowState::Plowing(_) => {
+                    ui.label("Plowing");
+                }
+                PlowState::Repairing(r) => {
+                    if maintenance.is_closed(r) {
+                        ui.label("Repairing");
+                    } else {
+                        ui.label("Going to repair");
+                    }
+                }
+                PlowState::Idle => {
+                    ui.label("Idle");
+                }
+                PlowState::Returning => {
+                    ui.label("Returning");
+                }
+            }
+        });
+    }
+}
+
 fn render_goodscompany(ui: &mut Ui, uiworld: &mut UiWorld, sim: &Simulation, b: &Building) {
     let owner = sim.read::<BuildingInfos>().owner(b.id);
 
@@ -138,6 +470,10 @@ fn render_goodscompany(ui: &mut Ui, uiworld: &mut UiWorld, sim: &Simulation, b:
     let goods = &c.comp;
     let workers = &c.workers;
 
+    if goods.brand.is_set() {
+        brand_label(ui, &goods.brand);
+    }
+
     let market = sim.read::<Market>();
     let itemregistry = sim.read::<ItemRegistry>();
     let max_workers = goods.max_workers;
@@ -145,13 +481,89 @@ fn render_goodscompany(ui: &mut Ui, uiworld: &mut UiWorld, sim: &Simulation, b:
         .text(format!("workers: {}/{}", workers.0.len(), max_workers))
         .desired_width(200.0)
         .ui(ui);
-    if let Some(driver) = goods.driver {
-        ui.horizontal(|ui| {
-            ui.label("Driver is");
-            entity_link(uiworld, sim, ui, driver);
-        });
+    let gregistry = sim.read::<GoodsCompanyRegistry>();
+    if let Some(descr) = b
+        .kind
+        .as_goods_company()
+        .and_then(|id| gregistry.descriptions.get(id))
+    {
+        if let Some(h) = descr.opening_hours {
+            let open = descr.is_open(sim.read::<GameTime>().daytime);
+            ui.label(format!(
+                "Open from {}h to {}h, currently {}",
+                h.open,
+                h.close,
+                if open { "open" } else { "closed" }
+            ));
+        } else if descr.night_shift {
+            ui.label("Runs day and night");
+        }
+        if let Some(level) = descr.teaches {
+            ui.label(format!(
+                "Teaches up to {:?} education within {:.0}m",
+                level, SCHOOL_RADIUS
+            ));
+        }
+        if let Some(service) = descr.service {
+            ui.label(format!(
+                "Lowers the {} risk of the buildings within {:.0}m",
+                risk_name(service).to_lowercase(),
+                SERVICE_RADIUS
+            ));
+        }
+        if descr.service == Some(ServiceKind::Health) {
+            let patients = sim
+                .world()
+                .humans
+                .values()
+                .filter(|h| h.health.hospital == Some(b.id))
+                .count();
+            ui.label(format!(
+                "{}/{} patients",
+                patients,
+                hospital_beds(goods, workers.0.len())
+            ));
+        }
+    }
+    if let Some(station) = sim.read::<GasStations>().get(b.id) {
+        ui.label(format!(
+            "{}/{} pumps busy, {} waiting, {} served",
+            station.busy_pumps(),
+            PUMPS_PER_STATION,
+            station.queue_len(),
+            station.served
+        ));
+    }
+    if let Some(venue) = sim.read::<LeisureVenues>().venue(b.id) {
+        ui.label(format!(
+            "{}/{} visitors, {} queuing, {} an evening",
+            venue.visitors.len(),
+            venue.capacity,
+            venue.queue.len(),
+            venue.price
+        ));
+        ui.label(format!("Income: {}", goods.money));
+    }
+    let levels: Vec<_> = workers
+        .0
+        .iter()
+        .filter_map(|&w| sim.world().humans.get(w))
+        .map(|h| h.education.level)
+        .collect();
+    if goods.qualification != EducationLevel::Basic {
+        let qualified = levels.iter().filter(|&&l| l >= goods.qualification).count();
+        ui.label(format!(
+            "Needs {:?} education, {}/{} workers qualified",
+            goods.qualification,
+            qualified,
+            levels.len()
+        ));
+    }
+    if matches!(goods.kind, CompanyKind::Factory { .. }) {
+        render_fleet(ui, uiworld, sim, c_id, c);
     }
-    let productivity = goods.productivity(workers.0.len(), b.zone.as_ref());
+    let productivity =
+        goods.productivity(goods.effective_workers(levels.into_iter()), b.zone.as_ref());
     let productivity = (productivity * 100.0).round();
     if productivity < 100.0 {
         egui::ProgressBar::new(productivity)
@@ -186,6 +598,84 @@ fn render_goodscompany(ui: &mut Ui, uiworld: &mut UiWorld, sim: &Simulation, b:
     }
 }
 
+/// Delivery fleet of a company, its size and freight mode can be changed
+fn render_fleet(
+    ui: &mut Ui,
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+    id: CompanyID,
+    c: &CompanyEnt,
+) {
+    let goods = &c.comp;
+    ui.add_space(10.0);
+    ui.label("Delivery fleet");
+
+    let mut size = goods.fleet_size;
+    let mut mode = goods.freight_mode;
+    ui.horizontal(|ui| {
+        ui.label(format!("{}/{} trucks", goods.fleet.len(), goods.fleet_size));
+        if ui.add_enabled(size > 0, egui::Button::new("-")).clicked() {
+            size -= 1;
+        }
+        if ui
+            .add_enabled(size < MAX_FLEET_SIZE, egui::Button::new("+"))
+            .on_hover_text(format!("Buy a truck for {}", TRUCK_PRICE))
+            .clicked()
+        {
+            size += 1;
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Ship by");
+        ui.radio_value(&mut mode, FreightMode::Truck, "Truck");
+        ui.radio_value(&mut mode, FreightMode::Rail, "Rail");
+    });
+    if size != goods.fleet_size || mode != goods.freight_mode {
+        uiworld.commands().set_company_logistics(id, size, mode);
+    }
+
+    egui::ProgressBar::new(goods.fleet_utilization)
+        .text(format!(
+            "utilization: {:.0}%",
+            goods.fleet_utilization * 100.0
+        ))
+        .desired_width(200.0)
+        .ui(ui);
+    ui.label(format!("{} deliveries waiting", c.sold.0.len()));
+
+    let has_stations = sim
+        .map()
+        .buildings()
+        .values()
+        .any(|b| b.kind == BuildingKind::RailFreightStation);
+    if goods.freight_mode == FreightMode::Rail && !has_stations {
+        ui.colored_label(
+            Color32::YELLOW,
+            "No freight station yet, goods are delivered by truck",
+        );
+    } else if goods.fleet_utilization > FLEET_BUSY {
+        ui.colored_label(
+            Color32::YELLOW,
+            "The fleet is always busy, buy trucks or ship by rail",
+        );
+    }
+
+    for t in &goods.fleet {
+        ui.horizontal(|ui| {
+            entity_link(uiworld, sim, ui, t.truck);
+            match t.driver {
+                Some(driver) => {
+                    ui.label("driven by");
+                    entity_link(uiworld, sim, ui, driver);
+                }
+                None => {
+                    ui.label("no driver");
+                }
+            }
+        });
+    }
+}
+
 fn render_recipe(ui: &mut Ui, uiworld: &UiWorld, sim: &Simulation, recipe: &Recipe) {
     let registry = sim.read::<ItemRegistry>();
 
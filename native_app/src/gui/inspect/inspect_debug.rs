@@ -1,5 +1,6 @@
 use crate::gui::follow::FollowEntity;
 use crate::uiworld::UiWorld;
+use common::i18n::tr;
 use egui::Ui;
 use egui_inspect::{Inspect, InspectArgs};
 use simulation::economy::{ItemRegistry, Market};
@@ -119,7 +120,7 @@ impl InspectRenderer {
                 egui::CollapsingHeader::new("Capital").show(ui, |ui| {
                     ui.columns(2, |ui| {
                         for (kind, cap) in capitals {
-                            ui[0].label(&registry[*kind].label);
+                            ui[0].label(tr(&registry[*kind].label));
                             ui[1].label(format!("{cap}"));
                         }
                     });
@@ -130,7 +131,7 @@ impl InspectRenderer {
                 egui::CollapsingHeader::new("Buy orders").show(ui, |ui| {
                     ui.columns(2, |ui| {
                         for (kind, b) in borders {
-                            ui[0].label(&registry[*kind].label);
+                            ui[0].label(tr(&registry[*kind].label));
                             ui[1].label(format!("{b:#?}"));
                         }
                     });
@@ -141,7 +142,7 @@ impl InspectRenderer {
                 egui::CollapsingHeader::new("Sell orders").show(ui, |ui| {
                     ui.columns(2, |ui| {
                         for (kind, b) in sellorders {
-                            ui[0].label(&registry[*kind].label);
+                            ui[0].label(tr(&registry[*kind].label));
                             ui[1].label(format!("{b:#?}"));
                         }
                     });
@@ -1,12 +1,12 @@
 use egui::{Context, Widget};
 
 use simulation::economy::{ItemRegistry, Market};
-use simulation::map_dynamic::Destination;
+use simulation::map_dynamic::{Destination, LegMode};
 use simulation::souls::desire::WorkKind;
 use simulation::transportation::Location;
 use simulation::{HumanID, Simulation};
 
-use crate::gui::inspect::{building_link, follow_button};
+use crate::gui::inspect::{building_link, follow_button, history::history_bar};
 use crate::gui::item_icon;
 use crate::uiworld::UiWorld;
 
@@ -22,17 +22,50 @@ pub fn inspect_human(uiworld: &mut UiWorld, sim: &Simulation, ui: &Context, id:
         .auto_sized()
         .open(&mut is_open)
         .show(ui, |ui| {
+            history_bar(uiworld, sim, ui);
             if cfg!(debug_assertions) {
                 ui.label(format!("{:?}", id));
             }
             let pinfo = &human.personal_info;
             ui.label(format!("{}{:?} • {}", pinfo.age, pinfo.gender, pinfo.name));
+            if human.education.level.next().is_some() && human.education.progress > 0.0 {
+                ui.label(format!(
+                    "{:?} education, studying ({:.0}%)",
+                    human.education.level,
+                    human.education.progress * 100.0
+                ));
+            } else {
+                ui.label(format!("{:?} education", human.education.level));
+            }
+            ui.label(format!(
+                "Health: {:.0}%{}",
+                human.health.value * 100.0,
+                if human.health.sick { ", sick" } else { "" }
+            ));
+            if let Some(hospital) = human.health.hospital {
+                ui.horizontal(|ui| {
+                    ui.label("Treated at");
+                    building_link(uiworld, sim, ui, hospital);
+                });
+            }
+
+            let state = &human.decision.activity;
+            ui.label(format!("{} since {}", state.activity, state.since));
+            if state.is_commuting() {
+                ui.label(format!("Commuting to: {}", state.goal));
+            }
+            if let Some(next) = state.next_transition {
+                ui.label(format!("Until {}", next));
+            }
 
             match human.location {
                 Location::Outside => {}
                 Location::Vehicle(_) => {
                     ui.label("In a vehicle");
                 }
+                Location::Train(_) => {
+                    ui.label("In the metro");
+                }
                 Location::Building(x) => {
                     ui.horizontal(|ui| {
                         ui.label("In a building:");
@@ -55,12 +88,35 @@ pub fn inspect_human(uiworld: &mut UiWorld, sim: &Simulation, ui: &Context, id:
                 }
             }
 
+            for leg in human.router.tour() {
+                let mode = match leg.mode {
+                    LegMode::Walk => "walk",
+                    LegMode::Drive => "drive",
+                    LegMode::Bus => "take the bus",
+                };
+                match leg.dest {
+                    Destination::Outside(pos) => {
+                        ui.label(format!("Then {} to {}", mode, pos));
+                    }
+                    Destination::Building(b) => {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Then {} to", mode));
+                            building_link(uiworld, sim, ui, b);
+                        });
+                    }
+                }
+            }
+
             ui.horizontal(|ui| {
                 ui.label("House is");
                 building_link(uiworld, sim, ui, human.home.house);
             });
 
             ui.label(format!("Last ate: {}", human.food.last_ate));
+            ui.label(format!(
+                "Last went out: {}, spends up to {}, {} left",
+                human.go_out.last_out, human.go_out.budget, human.go_out.wallet
+            ));
 
             if let Some(ref x) = human.work {
                 ui.horizontal(|ui| {
@@ -94,6 +150,25 @@ pub fn inspect_human(uiworld: &mut UiWorld, sim: &Simulation, ui: &Context, id:
                 egui::DragValue::new(&mut score).ui(ui);
                 ui.label("Work");
             });
+            ui.horizontal(|ui| {
+                let mut score = human.go_out.last_score;
+                egui::DragValue::new(&mut score).ui(ui);
+                ui.label("Go out");
+            });
+            if human.event.ticket.is_some() {
+                ui.horizontal(|ui| {
+                    let mut score = human.event.last_score;
+                    egui::DragValue::new(&mut score).ui(ui);
+                    ui.label("Event");
+                });
+            }
+            if human.router.personal_car.is_some() {
+                ui.horizontal(|ui| {
+                    let mut score = human.refuel.last_score;
+                    egui::DragValue::new(&mut score).ui(ui);
+                    ui.label("Refuel");
+                });
+            }
 
             let market = sim.read::<Market>();
             let itemregistry = sim.read::<ItemRegistry>();
@@ -0,0 +1,105 @@
+use crate::gui::inspect::history::history_bar;
+use crate::gui::roadeditor::{IntersectionComponent, RoadEditorResource};
+use crate::gui::{Tab, Tool};
+use crate::uiworld::UiWorld;
+use common::i18n::tr;
+use egui::{Context, Grid, RichText};
+use simulation::map::{Intersection, IntersectionID, LaneID, Map};
+use simulation::map_dynamic::IntersectionStats;
+use simulation::Simulation;
+
+/// Inspect an intersection, showing its roads, the traffic going through it and its signals
+pub fn inspect_intersection(
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+    ui: &Context,
+    id: IntersectionID,
+) {
+    let map = sim.map();
+    let Some(inter) = map.intersections().get(id) else {
+        return;
+    };
+    let stats = sim.read::<IntersectionStats>();
+    let mut edit_signals = false;
+
+    egui::Window::new(tr("Intersection"))
+        .resizable(false)
+        .auto_sized()
+        .show(ui, |ui| {
+            history_bar(uiworld, sim, ui);
+            if cfg!(debug_assertions) {
+                ui.label(format!("{:?}", id));
+            }
+
+            ui.label(RichText::new(tr("Roads")).strong());
+            for (i, &road) in inter.roads.iter().enumerate() {
+                let Some(r) = map.roads().get(road) else {
+                    continue;
+                };
+                let text = format!(
+                    "{} {}: {} lanes, {:.0}m",
+                    tr("Road"),
+                    i + 1,
+                    r.n_lanes(),
+                    r.length()
+                );
+                if ui.link(text).clicked() {
+                    uiworld.camera_mut().targetpos = r.points().point_along(r.length() * 0.5);
+                }
+            }
+
+            ui.separator();
+            ui.label(format!("{}: {:?}", tr("Light policy"), inter.light_policy));
+            if ui.button(tr("Edit signals")).clicked() {
+                edit_signals = true;
+            }
+
+            ui.separator();
+            ui.label(RichText::new(tr("Traffic during the last hour")).strong());
+            let Some(stat) = stats.get(id).filter(|s| s.volume() > 0) else {
+                ui.label(tr("No vehicle went through yet"));
+                return;
+            };
+            ui.label(format!("{} vehicles/h", stat.volume()));
+            if let Some(delay) = stat.avg_delay() {
+                ui.label(format!("{}: {:.1}s", tr("Average delay"), delay));
+            }
+
+            Grid::new("turn_volumes").striped(true).show(ui, |ui| {
+                ui.label(tr("From"));
+                ui.label(tr("To"));
+                ui.label(tr("Vehicles/h"));
+                ui.end_row();
+
+                for (turn, volume) in stat.turn_volumes() {
+                    ui.label(road_name(&map, inter, turn.src));
+                    ui.label(road_name(&map, inter, turn.dst));
+                    ui.label(volume.to_string());
+                    ui.end_row();
+                }
+            });
+        });
+
+    if edit_signals {
+        let component = IntersectionComponent {
+            id,
+            turn_policy: inter.turn_policy,
+            light_policy: inter.light_policy,
+        };
+        let mut state = uiworld.write::<RoadEditorResource>();
+        state.inspect = Some(component);
+        state.inspect_road = None;
+        state.dirty = false;
+        drop(state);
+        uiworld.insert(Tool::RoadEditor);
+        uiworld.insert(Tab::Roadeditor);
+    }
+}
+
+/// Name of the road of the lane, numbered like in the list of the roads of the intersection
+fn road_name(map: &Map, inter: &Intersection, lane: LaneID) -> String {
+    map.lanes()
+        .get(lane)
+        .and_then(|l| inter.roads.iter().position(|&r| r == l.parent))
+        .map_or_else(|| "?".to_string(), |i| format!("{} {}", tr("Road"), i + 1))
+}
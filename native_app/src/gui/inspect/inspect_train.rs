@@ -1,4 +1,4 @@
-use crate::gui::inspect::follow_button;
+use crate::gui::inspect::{follow_button, history::history_bar};
 use crate::uiworld::UiWorld;
 use egui::Context;
 use simulation::{Simulation, TrainID};
@@ -14,6 +14,7 @@ pub fn inspect_train(uiworld: &mut UiWorld, sim: &Simulation, ui: &Context, id:
         .auto_sized()
         .open(&mut is_open)
         .show(ui, |ui| {
+            history_bar(uiworld, sim, ui);
             if cfg!(debug_assertions) {
                 ui.label(format!("{:?}", id));
             }
@@ -1,6 +1,9 @@
-use crate::gui::inspect::{entity_link, follow_button};
+use crate::gui::inspect::{
+    brand_label, building_link, entity_link, follow_button, history::history_bar,
+};
 use crate::uiworld::UiWorld;
-use egui::Context;
+use egui::{Context, Widget};
+use simulation::transportation::fuel::EnergyKind;
 use simulation::transportation::VehicleState;
 use simulation::{Simulation, VehicleID};
 
@@ -22,6 +25,7 @@ pub fn inspect_vehicle(
         .auto_sized()
         .open(&mut is_open)
         .show(ui, |ui| {
+            history_bar(uiworld, sim, ui);
             if cfg!(debug_assertions) {
                 ui.label(format!("{:?}", id));
             }
@@ -39,8 +43,23 @@ pub fn inspect_vehicle(
                 VehicleState::RoadToPark(_, _, _) => {
                     ui.label("Parking");
                 }
+                VehicleState::Merging(_, _) => {
+                    ui.label("Merging into traffic");
+                }
+                VehicleState::DoubleParked(_) => {
+                    ui.label("Double parked");
+                }
             }
 
+            let tank = match v.vehicle.energy {
+                EnergyKind::Fuel => "Fuel",
+                EnergyKind::Electric => "Battery",
+            };
+            egui::ProgressBar::new(v.vehicle.fuel)
+                .text(format!("{}: {:.0}%", tank, v.vehicle.fuel * 100.0))
+                .desired_width(200.0)
+                .ui(ui);
+
             for (human_id, human) in &sim.world().humans {
                 if human.router.personal_car == Some(id) {
                     ui.horizontal(|ui| {
@@ -50,6 +69,18 @@ pub fn inspect_vehicle(
                 }
             }
 
+            for c in sim.world().companies.values() {
+                if c.comp.fleet.iter().any(|t| t.truck == id) {
+                    ui.horizontal(|ui| {
+                        ui.label("Delivery truck of");
+                        if c.comp.brand.is_set() {
+                            brand_label(ui, &c.comp.brand);
+                        }
+                        building_link(uiworld, sim, ui, c.comp.building);
+                    });
+                }
+            }
+
             follow_button(uiworld, ui, id);
         });
 
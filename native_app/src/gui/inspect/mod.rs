@@ -1,29 +1,41 @@
+use crate::gui::inspect::history::{history_bar, track_history};
 use crate::gui::inspect::inspect_train::inspect_train;
 use crate::gui::windows::debug::DebugState;
-use crate::gui::{FollowEntity, InspectedBuilding, InspectedEntity};
+use crate::gui::{FollowEntity, InspectedBuilding, InspectedEntity, InspectedIntersection};
 use crate::uiworld::UiWorld;
-use egui::{Context, Ui, Window};
+use egui::{Align2, Color32, Context, FontId, Sense, Stroke, Ui, Window};
 use inspect_building::inspect_building;
 use inspect_debug::InspectRenderer;
 use inspect_human::inspect_human;
+use inspect_intersection::inspect_intersection;
 use inspect_vehicle::inspect_vehicle;
 use simulation::map::BuildingID;
+use simulation::souls::branding::{Brand, LogoShape};
 use simulation::{AnyEntity, Simulation};
 use slotmapd::Key;
 
+pub mod history;
 mod inspect_building;
 mod inspect_debug;
 mod inspect_human;
+mod inspect_intersection;
 mod inspect_train;
 mod inspect_vehicle;
 
 pub fn inspector(ui: &Context, uiworld: &mut UiWorld, sim: &Simulation) {
     profiling::scope!("topgui::inspector");
+    track_history(uiworld);
+
     let inspected_building = *uiworld.read::<InspectedBuilding>();
     if let Some(b) = inspected_building.e {
         inspect_building(uiworld, sim, ui, b);
     }
 
+    let inspected_intersection = *uiworld.read::<InspectedIntersection>();
+    if let Some(i) = inspected_intersection.e {
+        inspect_intersection(uiworld, sim, ui, i);
+    }
+
     let e = unwrap_or!(uiworld.read::<InspectedEntity>().e, return);
 
     let force_debug_inspect = uiworld.read::<DebugState>().debug_inspector;
@@ -53,6 +65,7 @@ pub fn inspector(ui: &Context, uiworld: &mut UiWorld, sim: &Simulation) {
                 .resizable(true)
                 .open(&mut is_open)
                 .show(ui, |ui| {
+                    history_bar(uiworld, sim, ui);
                     let mut ins = InspectRenderer { entity: e };
                     ins.render(uiworld, sim, ui);
                     uiworld.write::<InspectedEntity>().e = Some(ins.entity);
@@ -98,6 +111,60 @@ fn entity_link_inner(uiworld: &mut UiWorld, sim: &Simulation, ui: &mut Ui, e: An
     }
 }
 
+/// The logo of a company followed by its name
+pub fn brand_label(ui: &mut Ui, brand: &Brand) -> egui::Response {
+    ui.horizontal(|ui| {
+        brand_logo(ui, brand);
+        ui.label(&brand.name);
+    })
+    .response
+}
+
+pub fn brand_logo(ui: &mut Ui, brand: &Brand) {
+    const SIZE: f32 = 18.0;
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(SIZE, SIZE), Sense::hover());
+    let c = brand.color;
+    let col = Color32::from_rgb(
+        (c.r * 255.0) as u8,
+        (c.g * 255.0) as u8,
+        (c.b * 255.0) as u8,
+    );
+    let painter = ui.painter();
+    let (center, r) = (rect.center(), SIZE * 0.5);
+    match brand.shape {
+        LogoShape::Circle => {
+            painter.circle_filled(center, r, col);
+        }
+        LogoShape::Square => {
+            painter.rect_filled(rect, 2.0, col);
+        }
+        LogoShape::Diamond => {
+            let points = vec![
+                center + egui::vec2(0.0, -r),
+                center + egui::vec2(r, 0.0),
+                center + egui::vec2(0.0, r),
+                center + egui::vec2(-r, 0.0),
+            ];
+            painter.add(egui::Shape::convex_polygon(points, col, Stroke::NONE));
+        }
+        LogoShape::Triangle => {
+            let points = vec![
+                center + egui::vec2(0.0, -r),
+                center + egui::vec2(r, r),
+                center + egui::vec2(-r, r),
+            ];
+            painter.add(egui::Shape::convex_polygon(points, col, Stroke::NONE));
+        }
+    }
+    painter.text(
+        center,
+        Align2::CENTER_CENTER,
+        brand.initial(),
+        FontId::proportional(11.0),
+        Color32::WHITE,
+    );
+}
+
 pub fn follow_button(uiworld: &UiWorld, ui: &mut Ui, id: impl Into<AnyEntity>) {
     follow_button_inner(uiworld, ui, id.into())
 }
@@ -1,5 +1,5 @@
 use crate::gui::selectable::select_radius;
-use crate::gui::{InspectedBuilding, InspectedEntity};
+use crate::gui::{InspectedBuilding, InspectedEntity, InspectedIntersection};
 use crate::rendering::immediate::ImmediateDraw;
 use crate::uiworld::UiWorld;
 use geom::Color;
@@ -11,6 +11,7 @@ pub fn inspected_aura(sim: &Simulation, uiworld: &mut UiWorld) {
     profiling::scope!("gui::inspected_aura");
     let inspected = uiworld.write::<InspectedEntity>();
     let inspected_b = uiworld.write::<InspectedBuilding>();
+    let inspected_i = uiworld.write::<InspectedIntersection>();
     let map = sim.map();
     let mut draw = uiworld.write::<ImmediateDraw>();
 
@@ -23,6 +24,7 @@ pub fn inspected_aura(sim: &Simulation, uiworld: &mut UiWorld) {
                 Location::Outside => {}
                 Location::Vehicle(v) => pos = sim.pos(v),
                 Location::Building(b) => pos = map.buildings().get(b).map(|b| b.door_pos),
+                Location::Train(t) => pos = sim.pos(t),
             }
         }
 
@@ -36,6 +38,12 @@ pub fn inspected_aura(sim: &Simulation, uiworld: &mut UiWorld) {
         }
     }
 
+    if let Some(inter) = inspected_i.e.and_then(|id| map.intersections().get(id)) {
+        let circle = inter.bcircle(map.roads());
+        draw.stroke_circle(inter.pos.up(0.25), circle.radius, 0.5)
+            .color(simulation::config().gui_primary);
+    }
+
     if let Some(sel) = inspected_b.e {
         let b = map.buildings().get(sel).unwrap();
 
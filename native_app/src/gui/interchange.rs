@@ -0,0 +1,103 @@
+use super::Tool;
+use crate::gui::roadbuild::RoadBuildResource;
+use crate::gui::{ErrorTooltip, PotentialCommands};
+use crate::inputmap::{InputAction, InputMap};
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+use geom::Degrees;
+use simulation::map::{
+    InterchangeKind, InterchangeParams, LaneKind, LanePattern, LanePatternBuilder,
+};
+use simulation::world_command::WorldCommand;
+use simulation::Simulation;
+use std::borrow::Cow;
+
+pub struct InterchangeResource {
+    pub kind: InterchangeKind,
+    pub rotation: Degrees,
+    /// Distance from the center to the end of each arm, in meters
+    pub size: f32,
+    /// Height of the bridge above the main road, in meters
+    pub height: f32,
+    /// The main road uses the road tool pattern, this is the pattern of the crossing road
+    pub crossing: LanePatternBuilder,
+}
+
+impl Default for InterchangeResource {
+    fn default() -> Self {
+        Self {
+            kind: InterchangeKind::Diamond,
+            rotation: Degrees(0.0),
+            size: 200.0,
+            height: 8.0,
+            crossing: LanePatternBuilder::new().n_lanes(2).speed_limit(13.0),
+        }
+    }
+}
+
+/// Interchange tool
+/// Places a grade-separated interchange template centered on the mouse, the main road follows the
+/// rotation and the crossing road bridges over it. Everything is built in one action.
+pub fn interchange(sim: &Simulation, uiworld: &mut UiWorld) {
+    profiling::scope!("gui::interchange");
+    let tool = *uiworld.read::<Tool>();
+    if !matches!(tool, Tool::Interchange) {
+        return;
+    }
+
+    let inp = uiworld.read::<InputMap>();
+    let mut state = uiworld.write::<InterchangeResource>();
+    let mut potential = uiworld.write::<PotentialCommands>();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let map = sim.map();
+
+    if inp.act.contains(&InputAction::Rotate) {
+        state.rotation += Degrees(inp.wheel);
+        state.rotation.normalize();
+    }
+
+    let mpos = unwrap_ret!(inp.unprojected);
+
+    let params = InterchangeParams {
+        kind: state.kind,
+        center: mpos.xy(),
+        dir: state.rotation.vec2(),
+        size: state.size,
+        height: state.height,
+        main: uiworld.read::<RoadBuildResource>().pattern_builder.build(),
+        crossing: state.crossing.build(),
+    };
+
+    let has_rail = |p: &LanePattern| p.lanes().any(|(k, _, _)| k == LaneKind::Rail);
+    let rail = has_rail(&params.main) || has_rail(&params.crossing);
+
+    let inter = params.generate(&map.environment);
+
+    let invalid = rail && params.kind != InterchangeKind::Overpass;
+    let col = if invalid {
+        *uiworld.write::<ErrorTooltip>() =
+            ErrorTooltip::new(Cow::Borrowed("Rails can only cross with an overpass"));
+        simulation::config().gui_danger
+    } else {
+        simulation::config().gui_primary
+    };
+
+    for (from, elbow, to) in inter.preview() {
+        let points = match elbow {
+            Some(e) => vec![from.pos, e.z(0.5 * (from.pos.z + to.pos.z)), to.pos],
+            None => vec![from.pos, to.pos],
+        };
+        draw.polyline(points, 4.0, false).color(col);
+    }
+
+    if invalid {
+        return;
+    }
+
+    let cmd = WorldCommand::MapMakeMultipleConnections(inter.projects, inter.links);
+    if inp.just_act.contains(&InputAction::Select) {
+        uiworld.commands().push(cmd);
+    } else {
+        potential.set(cmd);
+    }
+}
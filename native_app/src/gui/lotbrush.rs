@@ -3,20 +3,37 @@ use crate::inputmap::{InputAction, InputMap};
 use crate::rendering::immediate::ImmediateDraw;
 use crate::uiworld::UiWorld;
 use serde::{Deserialize, Serialize};
-use simulation::map::{LotKind, ProjectFilter, ProjectKind};
+use simulation::map::{BuildingID, BuildingKind, LotKind, ProjectFilter, ProjectKind};
+use simulation::map_dynamic::Rezoning;
 use simulation::Simulation;
 
+#[derive(Default, Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum LotBrushMode {
+    /// Builds houses on the lots
+    #[default]
+    Build,
+    /// Clears the lots and demolishes the houses
+    Erase,
+    /// Changes the kind of the lots, houses are replaced over time
+    Rezone,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct LotBrushResource {
     pub kind: LotKind,
     pub radius: f32,
+    #[serde(default)]
+    pub mode: LotBrushMode,
+    /// Houses selected by the eraser, waiting for confirmation before being demolished
+    #[serde(skip)]
+    pub to_erase: Vec<BuildingID>,
 }
 
 /// Lot brush tool
-/// Allows to build houses on lots
+/// Allows to build houses on lots, erase them or rezone them
 pub fn lotbrush(sim: &Simulation, uiworld: &mut UiWorld) {
     profiling::scope!("gui::lotbrush");
-    let res = uiworld.read::<LotBrushResource>();
+    let mut res = uiworld.write::<LotBrushResource>();
     let tool = *uiworld.read::<Tool>();
     let inp = uiworld.read::<InputMap>();
     let mut draw = uiworld.write::<ImmediateDraw>();
@@ -24,28 +41,100 @@ pub fn lotbrush(sim: &Simulation, uiworld: &mut UiWorld) {
     let commands = &mut *uiworld.commands();
 
     if !matches!(tool, Tool::LotBrush) {
+        res.to_erase.clear();
         return;
     }
 
-    let kind = res.kind;
+    let kind = match res.mode {
+        LotBrushMode::Build => LotKind::Residential,
+        LotBrushMode::Erase => LotKind::Unassigned,
+        LotBrushMode::Rezone => res.kind,
+    };
 
-    let mut col = match kind {
-        LotKind::Unassigned => simulation::config().lot_unassigned_col,
-        LotKind::Residential => simulation::config().lot_residential_col,
+    let mut col = match res.mode {
+        LotBrushMode::Erase => simulation::config().gui_danger,
+        _ => match kind {
+            LotKind::Unassigned => simulation::config().lot_unassigned_col,
+            LotKind::Residential => simulation::config().lot_residential_col,
+            LotKind::Commercial => simulation::config().lot_commercial_col,
+        },
     };
 
     col.a = 0.2;
 
+    let mut danger = simulation::config().gui_danger;
+    danger.a = 0.5;
+    for b in res
+        .to_erase
+        .iter()
+        .filter_map(|&id| map.buildings().get(id))
+    {
+        draw.obb(b.obb, b.height + 0.5).color(danger);
+    }
+
     let mpos = unwrap_ret!(inp.unprojected);
     draw.circle(mpos.up(0.8), res.radius).color(col);
 
-    if inp.act.contains(&InputAction::Select) {
-        for v in map
-            .spatial_map()
-            .query_around(mpos.xy(), res.radius, ProjectFilter::LOT)
-        {
-            if let ProjectKind::Lot(id) = v {
-                commands.map_build_house(id);
+    if !inp.act.contains(&InputAction::Select) {
+        return;
+    }
+
+    let filter = match res.mode {
+        LotBrushMode::Build => ProjectFilter::LOT,
+        LotBrushMode::Erase | LotBrushMode::Rezone => ProjectFilter::LOT | ProjectFilter::BUILDING,
+    };
+
+    let mut lots = vec![];
+    let mut houses = vec![];
+    for v in map
+        .spatial_map()
+        .query_around(mpos.xy(), res.radius, filter)
+    {
+        match v {
+            ProjectKind::Lot(id) => {
+                let Some(lot) = map.lots().get(id) else {
+                    continue;
+                };
+                if lot.kind != kind {
+                    lots.push(id);
+                }
+                if res.mode == LotBrushMode::Build && lot.kind != LotKind::Commercial {
+                    commands.map_build_house(id);
+                }
+            }
+            ProjectKind::Building(id) => {
+                if map
+                    .buildings()
+                    .get(id)
+                    .map_or(false, |b| matches!(b.kind, BuildingKind::House))
+                {
+                    houses.push(id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match res.mode {
+        LotBrushMode::Build => {}
+        LotBrushMode::Erase => {
+            if !lots.is_empty() {
+                commands.map_set_lot_kind(lots, kind);
+            }
+            for id in houses {
+                if !res.to_erase.contains(&id) {
+                    res.to_erase.push(id);
+                }
+            }
+        }
+        LotBrushMode::Rezone => {
+            if !lots.is_empty() {
+                commands.map_set_lot_kind(lots, kind);
+            }
+            let rezoning = sim.read::<Rezoning>();
+            houses.retain(|&id| !rezoning.is_pending(id));
+            if !houses.is_empty() && kind != LotKind::Residential {
+                commands.map_rezone_buildings(houses, kind, false);
             }
         }
     }
@@ -56,6 +145,8 @@ impl Default for LotBrushResource {
         Self {
             kind: LotKind::Residential,
             radius: 25.0,
+            mode: LotBrushMode::Build,
+            to_erase: vec![],
         }
     }
 }
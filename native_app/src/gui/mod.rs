@@ -2,29 +2,44 @@ use common::FastMap;
 use std::borrow::Cow;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use egui::{ColorImage, ImageData, TextureHandle, TextureId, TextureOptions};
 use serde::{Deserialize, Serialize};
 
 use crate::uiworld::UiWorld;
 use roadbuild::RoadBuildResource;
-use simulation::map::BuildingID;
-use simulation::world_command::WorldCommand;
+use simulation::map::{BuildingID, IntersectionID};
+use simulation::utils::time::Tick;
+use simulation::world_command::{RejectedCommands, WorldCommand};
 use simulation::{AnyEntity, Simulation};
 
 pub mod addtrain;
 pub mod bulldozer;
+pub mod busstops;
 pub mod chat;
+pub mod curbs;
+pub mod decoration;
 pub mod follow;
+pub mod furniture;
 pub mod inspect;
 pub mod inspected_aura;
+pub mod interchange;
 pub mod lotbrush;
+pub mod modpanels;
+pub mod palette;
 pub mod roadbuild;
 pub mod roadeditor;
 pub mod selectable;
+pub mod session;
+pub mod slow_ticks;
 pub mod specialbuilding;
+pub mod subtitles;
 pub mod terraforming;
 pub mod topgui;
+pub mod tutorial;
+pub mod underground;
+pub mod undo;
 pub mod windows;
 pub mod zoneedit;
 
@@ -38,10 +53,23 @@ pub fn run_ui_systems(sim: &Simulation, uiworld: &mut UiWorld) {
     lotbrush::lotbrush(sim, uiworld);
     roadbuild::roadbuild(sim, uiworld);
     roadeditor::roadeditor(sim, uiworld);
+    curbs::curbs(sim, uiworld);
+    furniture::furniture(sim, uiworld);
+    decoration::decoration(sim, uiworld);
+    decoration::draw_decorations(sim, uiworld);
+    busstops::busstops(sim, uiworld);
+    busstops::draw_buslines(sim, uiworld);
+    underground::draw_underground(sim, uiworld);
+    interchange::interchange(sim, uiworld);
     specialbuilding::specialbuilding(sim, uiworld);
     addtrain::addtrain(sim, uiworld);
     zoneedit::zoneedit(sim, uiworld);
     terraforming::terraforming(sim, uiworld);
+    windows::markers::draw_markers(sim, uiworld);
+    tutorial::draw_tutorial_markers(sim, uiworld);
+    windows::scenario::export_scenario_result(sim, uiworld);
+    undo::undo(sim, uiworld);
+    rejected_commands(sim, uiworld);
 
     // run last so other systems can have the chance to cancel select
     selectable::selectable(sim, uiworld);
@@ -63,7 +91,6 @@ impl ErrorTooltip {
         }
     }
 
-    #[allow(unused)]
     pub fn new_ui(msg: impl Into<Cow<'static, str>>) -> Self {
         Self {
             msg: Some(msg.into()),
@@ -72,6 +99,33 @@ impl ErrorTooltip {
     }
 }
 
+/// How long the reason of a rejected command stays on screen
+const REJECTION_DISPLAY_TIME: Duration = Duration::from_secs(3);
+
+#[derive(Default)]
+pub struct RejectedCommandsState {
+    next_tick: Tick,
+    shown: Option<(String, Instant)>,
+}
+
+/// Tells the player why their last command was rejected by the simulation
+fn rejected_commands(sim: &Simulation, uiworld: &mut UiWorld) {
+    let mut state = uiworld.write::<RejectedCommandsState>();
+    if let Some((_, err)) = sim.read::<RejectedCommands>().since(state.next_tick).last() {
+        state.shown = Some((err.to_string(), Instant::now()));
+    }
+    state.next_tick = Tick(sim.get_tick());
+
+    let Some((ref msg, at)) = state.shown else {
+        return;
+    };
+    if at.elapsed() > REJECTION_DISPLAY_TIME {
+        state.shown = None;
+        return;
+    }
+    *uiworld.write::<ErrorTooltip>() = ErrorTooltip::new_ui(msg.clone());
+}
+
 #[derive(Default, Clone, Debug)]
 pub struct PotentialCommands(pub Vec<WorldCommand>);
 
@@ -88,6 +142,12 @@ pub struct InspectedBuilding {
     pub dontclear: bool,
 }
 
+#[derive(Copy, Clone, Debug, Default)]
+pub struct InspectedIntersection {
+    pub e: Option<IntersectionID>,
+    pub dontclear: bool,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct InspectedEntity {
     pub e: Option<AnyEntity>,
@@ -117,9 +177,33 @@ pub enum Tool {
     SpecialBuilding,
     Train,
     Terraforming,
+    Interchange,
+    Curbs,
+    RoadFurniture,
+    Decoration,
+    BusStops,
 }
 
 impl Tool {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tool::Hand => "Hand",
+            Tool::RoadbuildStraight => "Road",
+            Tool::RoadbuildCurved => "Curved road",
+            Tool::RoadEditor => "Road editor",
+            Tool::Bulldozer => "Bulldozer",
+            Tool::LotBrush => "House brush",
+            Tool::SpecialBuilding => "Buildings",
+            Tool::Train => "Trains",
+            Tool::Terraforming => "Terraforming",
+            Tool::Interchange => "Interchange",
+            Tool::Curbs => "Curbs",
+            Tool::RoadFurniture => "Road furniture",
+            Tool::Decoration => "Decorations",
+            Tool::BusStops => "Bus stops",
+        }
+    }
+
     pub fn is_roadbuild(&self) -> bool {
         matches!(self, Tool::RoadbuildStraight | Tool::RoadbuildCurved)
     }
@@ -0,0 +1,189 @@
+use std::path::Path;
+
+use common::saveload::Encoder;
+use egui::{Context, ProgressBar, Ui, Window};
+use serde::Deserialize;
+
+use simulation::economy::{Government, ItemRegistry, Market};
+use simulation::world_command::WorldCommand;
+use simulation::Simulation;
+
+use crate::uiworld::UiWorld;
+
+const PANELS_PATH: &str = "assets/panels";
+
+/// PanelManifest is the description of a mod panel, as read from a json file in assets/panels
+#[derive(Deserialize)]
+pub struct PanelManifest {
+    pub name: String,
+    #[serde(default)]
+    pub elements: Vec<PanelElement>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PanelElement {
+    Label {
+        text: String,
+    },
+    Separator,
+    /// Shows the current value of a statistic
+    Stat {
+        label: String,
+        stat: String,
+    },
+    /// Shows a statistic as a fraction of `max`
+    Bar {
+        label: String,
+        stat: String,
+        max: f64,
+    },
+    /// Sends the commands when clicked
+    Button {
+        label: String,
+        commands: Vec<WorldCommand>,
+    },
+}
+
+struct ModPanel {
+    manifest: PanelManifest,
+    opened: bool,
+}
+
+/// ModPanels holds the panels contributed by data-only mods.
+/// They are interpreted every frame so that mods don't need any rust code to expose their mechanics.
+pub struct ModPanels {
+    panels: Vec<ModPanel>,
+}
+
+impl Default for ModPanels {
+    fn default() -> Self {
+        Self::load(Path::new(PANELS_PATH))
+    }
+}
+
+impl ModPanels {
+    pub fn load(dir: &Path) -> Self {
+        let mut panels = vec![];
+        if !dir.exists() {
+            return Self { panels };
+        }
+
+        let mut paths: Vec<_> = common::saveload::walkdir(dir)
+            .filter(|p| p.extension().map_or(false, |ext| ext == "json"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let manifest = match common::saveload::load_raw(&path)
+                .and_then(|data| common::saveload::JSON::decode::<PanelManifest>(&data))
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    log::error!("could not load mod panel {:?}: {}", path, e);
+                    continue;
+                }
+            };
+            log::info!("loaded mod panel {}", manifest.name);
+            panels.push(ModPanel {
+                manifest,
+                opened: false,
+            });
+        }
+
+        Self { panels }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.panels.is_empty()
+    }
+
+    pub fn menu(&mut self, ui: &mut Ui) {
+        if self.is_empty() {
+            return;
+        }
+        ui.menu_button("Mods", |ui| {
+            for panel in &mut self.panels {
+                panel.opened ^= ui
+                    .selectable_label(panel.opened, &panel.manifest.name)
+                    .clicked();
+            }
+        });
+    }
+
+    pub fn render(&mut self, ui: &Context, uiworld: &UiWorld, sim: &Simulation) {
+        profiling::scope!("modpanels::render");
+        for (i, panel) in self.panels.iter_mut().enumerate() {
+            if !panel.opened {
+                continue;
+            }
+            let manifest = &panel.manifest;
+            Window::new(&manifest.name)
+                .id(egui::Id::new(("modpanel", i)))
+                .open(&mut panel.opened)
+                .auto_sized()
+                .show(ui, |ui| {
+                    for element in &manifest.elements {
+                        render_element(ui, element, uiworld, sim);
+                    }
+                });
+        }
+    }
+}
+
+fn render_element(ui: &mut Ui, element: &PanelElement, uiworld: &UiWorld, sim: &Simulation) {
+    match element {
+        PanelElement::Label { text } => {
+            ui.label(text);
+        }
+        PanelElement::Separator => {
+            ui.separator();
+        }
+        PanelElement::Stat { label, stat } => {
+            let v = stat_value(sim, stat);
+            ui.label(format!(
+                "{}: {}",
+                label,
+                v.map_or_else(|| "?".to_string(), |v| format!("{v:.0}"))
+            ));
+        }
+        PanelElement::Bar { label, stat, max } => {
+            let v = stat_value(sim, stat).unwrap_or(0.0);
+            let progress = if *max > 0.0 { v / max } else { 0.0 };
+            ui.add(
+                ProgressBar::new(progress.clamp(0.0, 1.0) as f32)
+                    .text(format!("{label}: {v:.0}/{max:.0}")),
+            );
+        }
+        PanelElement::Button { label, commands } => {
+            if ui.button(label).clicked() {
+                uiworld.commands().extend(commands.iter().cloned());
+            }
+        }
+    }
+}
+
+/// Resolves a statistic name to its current value.
+/// Item prices can be accessed using `price:<item name>`.
+pub fn stat_value(sim: &Simulation, stat: &str) -> Option<f64> {
+    if let Some(item) = stat.strip_prefix("price:") {
+        let id = sim.read::<ItemRegistry>().try_id(item)?;
+        let market = sim.read::<Market>();
+        return market
+            .inner()
+            .get(&id)
+            .map(|m| m.ext_value.bucks() as f64);
+    }
+
+    let world = sim.world();
+    Some(match stat {
+        "money" => sim.read::<Government>().money.bucks() as f64,
+        "population" => world.humans.len() as f64,
+        "vehicles" => world.vehicles.len() as f64,
+        "trains" => world.trains.len() as f64,
+        "companies" => world.companies.len() as f64,
+        "buildings" => sim.map().buildings().len() as f64,
+        "roads" => sim.map().roads().len() as f64,
+        _ => return None,
+    })
+}
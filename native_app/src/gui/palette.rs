@@ -0,0 +1,229 @@
+use crate::gui::Tool;
+use crate::inputmap::{Bindings, InputAction};
+use common::i18n::tr;
+use egui::{Align, Align2, Context, Grid, Layout, RichText};
+use std::cmp::Reverse;
+
+/// An entry of the command palette, the action is run by the caller when picked
+pub struct PaletteEntry<A> {
+    pub name: String,
+    pub category: &'static str,
+    pub hotkey: Option<String>,
+    pub action: A,
+}
+
+#[derive(Default)]
+pub struct CommandPalette {
+    pub open: bool,
+    /// Whether the controls of the current tool are shown
+    pub help: bool,
+    query: String,
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// Shows the palette if it is opened, returns the action of the picked entry
+    pub fn show<A: Clone>(&mut self, ui: &Context, entries: &[PaletteEntry<A>]) -> Option<A> {
+        if !self.open {
+            return None;
+        }
+
+        let mut matches: Vec<(i32, usize)> = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| Some((fuzzy_score(&self.query, &e.name)?, i)))
+            .collect();
+        matches.sort_by_key(|&(score, i)| (Reverse(score), i));
+
+        let (up, down, enter, escape) = ui.input(|i| {
+            (
+                i.key_pressed(egui::Key::ArrowUp),
+                i.key_pressed(egui::Key::ArrowDown),
+                i.key_pressed(egui::Key::Enter),
+                i.key_pressed(egui::Key::Escape),
+            )
+        });
+        if escape {
+            self.toggle();
+            return None;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if down {
+            self.selected += 1;
+        }
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let mut picked = None;
+        egui::Window::new("Command palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .fixed_size([400.0, 300.0])
+            .anchor(Align2::CENTER_TOP, [0.0, 100.0])
+            .show(ui, |ui| {
+                let resp = egui::TextEdit::singleline(&mut self.query)
+                    .hint_text(tr("Search tools, windows and actions"))
+                    .desired_width(f32::INFINITY)
+                    .show(ui)
+                    .response;
+                resp.request_focus();
+                if resp.changed() {
+                    self.selected = 0;
+                }
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (rank, &(_, i)) in matches.iter().enumerate() {
+                        let e = &entries[i];
+                        ui.horizontal(|ui| {
+                            let resp = ui.selectable_label(rank == self.selected, &e.name);
+                            if rank == self.selected && (up || down) {
+                                resp.scroll_to_me(None);
+                            }
+                            if resp.clicked() {
+                                picked = Some(e.action.clone());
+                            }
+                            ui.weak(e.category);
+                            if let Some(ref hotkey) = e.hotkey {
+                                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                    ui.label(RichText::new(hotkey).monospace());
+                                });
+                            }
+                        });
+                    }
+                });
+            });
+
+        if enter {
+            picked = matches
+                .get(self.selected)
+                .map(|&(_, i)| entries[i].action.clone());
+        }
+        if picked.is_some() {
+            self.toggle();
+        }
+        picked
+    }
+}
+
+/// Case insensitive subsequence matching, the higher the score the better the match.
+/// Consecutive letters and letters starting a word score higher, skipped letters lower.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut start = 0;
+    let mut last = None;
+
+    for qc in query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+    {
+        let pos = start + text[start..].iter().position(|&c| c == qc)?;
+        if pos == 0 || !text[pos - 1].is_alphanumeric() {
+            score += 8;
+        }
+        if last.map_or(false, |l| l + 1 == pos) {
+            score += 5;
+        }
+        score -= (pos - start) as i32;
+        last = Some(pos);
+        start = pos + 1;
+    }
+
+    Some(score)
+}
+
+/// The controls of each tool, on top of the camera controls
+fn tool_controls(tool: Tool) -> &'static [(InputAction, &'static str)] {
+    use InputAction::*;
+    match tool {
+        Tool::Hand => &[(Select, "Inspect"), (Close, "Stop inspecting")],
+        Tool::RoadbuildStraight | Tool::RoadbuildCurved => &[
+            (Select, "Place the next point"),
+            (UpElevation, "Raise the road"),
+            (DownElevation, "Lower the road"),
+            (NoSnapping, "Hold to disable snapping"),
+            (Close, "Cancel the road"),
+        ],
+        Tool::RoadEditor => &[(Select, "Edit an intersection")],
+        Tool::Bulldozer => &[(Select, "Remove a road, intersection or building")],
+        Tool::LotBrush => &[(Select, "Paint the lots")],
+        Tool::SpecialBuilding => &[
+            (Select, "Place the building"),
+            (Rotate, "Rotate with the wheel"),
+            (RotateLeft, "Quarter turn left"),
+            (RotateRight, "Quarter turn right"),
+            (Mirror, "Mirror the footprint"),
+        ],
+        Tool::Train => &[(Select, "Place the train")],
+        Tool::Terraforming => &[
+            (Select, "Apply"),
+            (SecondarySelect, "Apply in reverse"),
+            (SizeUp, "Bigger radius"),
+            (SizeDown, "Smaller radius"),
+        ],
+        Tool::Interchange => &[
+            (Select, "Place the interchange"),
+            (Rotate, "Rotate with the wheel"),
+        ],
+        Tool::Curbs => &[
+            (Select, "Drag along a curb to designate it"),
+            (Close, "Stop designating curbs"),
+        ],
+        Tool::RoadFurniture => &[
+            (Select, "Place along the road"),
+            (SecondarySelect, "Remove"),
+        ],
+        Tool::Decoration => &[
+            (Select, "Place the decoration"),
+            (SecondarySelect, "Remove the hovered decoration"),
+            (Rotate, "Rotate with the wheel"),
+            (RotateLeft, "Turn left"),
+            (RotateRight, "Turn right"),
+        ],
+        Tool::BusStops => &[
+            (
+                Select,
+                "Place a stop, or add the hovered one to the selected line",
+            ),
+            (SecondarySelect, "Remove the hovered stop"),
+        ],
+    }
+}
+
+/// Help overlay listing the controls of the current tool with their bound hotkeys
+pub fn help_overlay(ui: &Context, tool: Tool, bindings: &Bindings) {
+    const COMMON: &[(InputAction, &str)] = &[
+        (InputAction::CommandPalette, "Search tools and windows"),
+        (InputAction::ToggleHelp, "Hide this help"),
+    ];
+
+    egui::Window::new("Controls")
+        .title_bar(false)
+        .collapsible(false)
+        .resizable(false)
+        .auto_sized()
+        .anchor(Align2::LEFT_BOTTOM, [10.0, -10.0])
+        .show(ui, |ui| {
+            ui.label(RichText::new(tr(tool.name())).strong());
+            Grid::new("controls_help").striped(true).show(ui, |ui| {
+                for (act, what) in tool_controls(tool).iter().chain(COMMON) {
+                    let hotkey = bindings
+                        .0
+                        .get(act)
+                        .map_or_else(|| "<unbound>".to_string(), |comb| comb.to_string());
+                    ui.label(RichText::new(hotkey).monospace());
+                    ui.label(tr(what));
+                    ui.end_row();
+                }
+            });
+        });
+}
@@ -6,7 +6,8 @@ use common::AudioKind;
 use geom::{BoldLine, BoldSpline, Camera, PolyLine, ShapeEnum, Spline};
 use geom::{PolyLine3, Spline3, Vec2, Vec3};
 use simulation::map::{
-    Intersection, LanePatternBuilder, Map, MapProject, ProjectFilter, ProjectKind, PylonPosition,
+    Intersection, LanePatternBuilder, Map, MapLayer, MapProject, ProjectFilter, ProjectKind,
+    PylonPosition,
 };
 use simulation::world_command::{WorldCommand, WorldCommands};
 use simulation::Simulation;
@@ -27,6 +28,33 @@ pub struct RoadBuildResource {
     pub pattern_builder: LanePatternBuilder,
     pub snap_to_grid: bool,
     pub height_offset: f32,
+    /// Allows negative height offsets to dig tunnels under the terrain
+    pub tunnel: bool,
+}
+
+/// Deepest a tunnel can be dug under the terrain
+pub const MAX_TUNNEL_DEPTH: f32 = 50.0;
+
+impl RoadBuildResource {
+    /// Lowest height offset allowed in the current mode
+    pub fn min_height_offset(&self) -> f32 {
+        if self.tunnel {
+            -MAX_TUNNEL_DEPTH
+        } else {
+            0.0
+        }
+    }
+
+    /// The pattern built in the layer, the underground layer only has metro tracks
+    pub fn layer_pattern(&self, layer: MapLayer) -> LanePatternBuilder {
+        match layer {
+            MapLayer::Surface => self.pattern_builder,
+            MapLayer::Underground => LanePatternBuilder::new()
+                .rail(true)
+                .electrified(true)
+                .one_way(self.pattern_builder.one_way),
+        }
+    }
 }
 
 /// Road building tool
@@ -42,6 +70,7 @@ pub fn roadbuild(sim: &Simulation, uiworld: &mut UiWorld) {
     let map = &*sim.map();
     let commands: &mut WorldCommands = &mut uiworld.commands();
     let cam = &*uiworld.read::<Camera>();
+    let layer = *uiworld.read::<MapLayer>();
 
     if !tool.is_roadbuild() {
         state.build_state = Hover;
@@ -50,15 +79,31 @@ pub fn roadbuild(sim: &Simulation, uiworld: &mut UiWorld) {
     }
 
     let nosnapping = inp.act.contains(&InputAction::NoSnapping);
+    let pattern_builder = state.layer_pattern(layer);
 
     // Prepare mousepos depending on snap to grid
     let unproj = unwrap_ret!(inp.unprojected);
     let grid_size = 20.0;
+    // roads are built over water at sea level, canals follow the bottom
+    let min_height = if pattern_builder.canal {
+        f32::NEG_INFINITY
+    } else {
+        0.0
+    };
     let mousepos = if state.snap_to_grid {
         let v = unproj.xy().snap(grid_size, grid_size);
-        v.z(unwrap_ret!(map.environment.height(v)) + 0.3 + state.height_offset)
+        v.z(unwrap_ret!(map.environment.height(v)).max(min_height) + 0.3 + state.height_offset)
     } else {
-        unproj.up(0.3 + state.height_offset)
+        unproj
+            .xy()
+            .z(unproj.z.max(min_height) + 0.3 + state.height_offset)
+    };
+    // the metro tunnels are all dug at the same depth
+    let mousepos = match layer {
+        MapLayer::Surface => mousepos,
+        MapLayer::Underground => mousepos.xy().z(unwrap_ret!(
+            MapLayer::Underground.height(mousepos, &map.environment)
+        )),
     };
 
     let log_camheight = cam.eye().z.log10();
@@ -115,16 +160,17 @@ pub fn roadbuild(sim: &Simulation, uiworld: &mut UiWorld) {
 
     if inp.just_act.contains(&InputAction::DownElevation) {
         state.height_offset -= 5.0;
-        state.height_offset = state.height_offset.max(0.0);
     }
+    state.height_offset = state.height_offset.max(state.min_height_offset());
 
-    let mut cur_proj = map.project(
+    let mut cur_proj = map.project_layer(
         mousepos,
         (log_camheight * 5.0).clamp(1.0, 10.0),
         ProjectFilter::INTER | ProjectFilter::ROAD,
+        layer,
     );
 
-    let patwidth = state.pattern_builder.width();
+    let patwidth = pattern_builder.width();
 
     if let Road(r_id) = cur_proj.kind {
         let r = &map.roads()[r_id];
@@ -155,7 +201,8 @@ pub fn roadbuild(sim: &Simulation, uiworld: &mut UiWorld) {
         }
     }
 
-    let is_rail = state.pattern_builder.rail;
+    // barges can't take sharp turns either
+    let is_rail = pattern_builder.rail || pattern_builder.canal;
 
     let is_valid = match (state.build_state, cur_proj.kind) {
         (Hover, Building(_)) => false,
@@ -171,7 +218,8 @@ pub fn roadbuild(sim: &Simulation, uiworld: &mut UiWorld) {
                 && !check_intersect(
                     map,
                     &ShapeEnum::BoldLine(sp),
-                    (selected_proj.pos.z + cur_proj.pos.z) / 2.0,
+                    &PolyLine3::new(vec![selected_proj.pos, cur_proj.pos]),
+                    patwidth,
                     cur_proj.kind,
                     selected_proj.kind,
                 )
@@ -185,14 +233,22 @@ pub fn roadbuild(sim: &Simulation, uiworld: &mut UiWorld) {
                 to_derivative: (cur_proj.pos.xy() - interpoint) * std::f32::consts::FRAC_1_SQRT_2,
             };
 
+            let sp3 = Spline3 {
+                from: selected_proj.pos,
+                to: cur_proj.pos,
+                from_derivative: sp.from_derivative.z0(),
+                to_derivative: sp.to_derivative.z0(),
+            };
+
             compatible(map, cur_proj, selected_proj)
                 && check_angle(map, selected_proj, interpoint, is_rail)
                 && check_angle(map, cur_proj, interpoint, is_rail)
-                && !sp.is_steep(state.pattern_builder.width())
+                && !sp.is_steep(pattern_builder.width())
                 && !check_intersect(
                     map,
                     &ShapeEnum::BoldSpline(BoldSpline::new(sp, patwidth * 0.5)),
-                    (selected_proj.pos.z + cur_proj.pos.z) / 2.0,
+                    &PolyLine3::new(sp3.smart_points(1.0, 0.0, 1.0).collect()),
+                    patwidth,
                     selected_proj.kind,
                     cur_proj.kind,
                 )
@@ -208,14 +264,14 @@ pub fn roadbuild(sim: &Simulation, uiworld: &mut UiWorld) {
             from: selected_proj,
             to: cur_proj,
             inter: None,
-            pat: state.pattern_builder.build(),
+            pat: pattern_builder.build(),
         }),
         Interpolation(interpoint, selected_proj) => {
             potential_command.set(WorldCommand::MapMakeConnection {
                 from: selected_proj,
                 to: cur_proj,
                 inter: Some(interpoint),
-                pat: state.pattern_builder.build(),
+                pat: pattern_builder.build(),
             })
         }
     }
@@ -318,32 +374,54 @@ fn compatible(map: &Map, x: MapProject, y: MapProject) -> bool {
     }
 }
 
-/// Check if the given shape intersects with any existing road or intersection
+/// Check if the given shape intersects with any existing road or intersection.
+/// Roads and intersections at another height don't intersect, the new road bridges over them.
 fn check_intersect(
     map: &Map,
     obj: &ShapeEnum,
-    z: f32,
+    points: &PolyLine3,
+    width: f32,
     start: ProjectKind,
     end: ProjectKind,
 ) -> bool {
     map.spatial_map()
         .query(obj, ProjectFilter::ROAD | ProjectFilter::INTER)
         .any(move |x| {
-            if let Road(rid) = x {
-                let r = &map.roads()[rid];
-                if (r.points.first().z - z).abs() > 0.1 || (r.points.last().z - z).abs() > 0.1 {
-                    return false;
-                }
-                if let Inter(id) = start {
-                    if r.src == id || r.dst == id {
+            match x {
+                Road(rid) => {
+                    let r = &map.roads()[rid];
+                    if let Inter(id) = start {
+                        if r.src == id || r.dst == id {
+                            return false;
+                        }
+                    }
+                    if let Inter(id) = end {
+                        if r.src == id || r.dst == id {
+                            return false;
+                        }
+                    }
+                    if !r.overlaps(points, width) {
                         return false;
                     }
                 }
-                if let Inter(id) = end {
-                    if r.src == id || r.dst == id {
-                        return false;
+                Inter(id) => {
+                    let pos = map.intersections()[id].pos;
+                    let closest =
+                        points
+                            .equipoints_dir(1.0, true)
+                            .map(|(p, _)| p)
+                            .min_by(|a, b| {
+                                a.xy()
+                                    .distance2(pos.xy())
+                                    .total_cmp(&b.xy().distance2(pos.xy()))
+                            });
+                    if let Some(closest) = closest {
+                        if (closest.z - pos.z).abs() >= simulation::map::Road::CLEARANCE {
+                            return false;
+                        }
                     }
                 }
+                _ => {}
             }
             x != start && x != end
         })
@@ -3,25 +3,58 @@ use crate::inputmap::{InputAction, InputMap};
 use crate::rendering::immediate::ImmediateDraw;
 use crate::uiworld::UiWorld;
 use geom::Color;
-use simulation::map::{IntersectionID, LightPolicy, TurnPolicy};
-use simulation::map::{ProjectFilter, ProjectKind};
+use simulation::map::{arterials_through, Arterial, ProjectFilter, ProjectKind};
+use simulation::map::{
+    ApproachSign, IntersectionID, LightPolicy, LightProgram, RoadID, TurnPolicy,
+};
+use simulation::transportation::road_maintenance::RoadMaintenance;
 use simulation::Simulation;
+use std::collections::BTreeMap;
 
 #[derive(Clone)]
 pub struct IntersectionComponent {
     pub id: IntersectionID,
     pub turn_policy: TurnPolicy,
     pub light_policy: LightPolicy,
+    pub light_program: Option<LightProgram>,
+    pub light_offset: Option<u16>,
+    pub approach_signs: BTreeMap<RoadID, ApproachSign>,
+}
+
+/// Color of the i-th approach of the inspected intersection, on the map and in the editor
+pub fn approach_color(i: usize) -> Color {
+    Color::hsv(i as f32 * 70.0, 0.7, 0.9, 1.0)
 }
 
-#[derive(Default)]
 pub struct RoadEditorResource {
     pub inspect: Option<IntersectionComponent>,
+    pub inspect_road: Option<RoadID>,
     pub dirty: bool,
+    /// The light program or the light offset of the inspected intersection changed
+    pub timing_dirty: bool,
+    /// Corridors of signalized intersections going through the inspected one
+    pub corridors: Vec<Arterial>,
+    /// Corridor whose lights the green wave coordinates
+    pub corridor: Option<usize>,
+}
+
+impl Default for RoadEditorResource {
+    fn default() -> Self {
+        Self {
+            inspect: None,
+            inspect_road: None,
+            dirty: false,
+            timing_dirty: false,
+            corridors: vec![],
+            corridor: None,
+        }
+    }
 }
 
 /// RoadEditor tool
-/// Allows to edit intersections properties like turns and signals
+/// Allows to edit intersections properties like turns and signals, and roads maintenance
+/// Shows the condition of the worn roads, the phases of custom light programs and the corridor
+/// of the green wave
 pub fn roadeditor(sim: &Simulation, uiworld: &mut UiWorld) {
     profiling::scope!("gui::roadeditor");
     let tool = uiworld.read::<Tool>();
@@ -33,9 +66,94 @@ pub fn roadeditor(sim: &Simulation, uiworld: &mut UiWorld) {
 
     if !matches!(*tool, Tool::RoadEditor) {
         state.inspect = None;
+        state.inspect_road = None;
         return;
     }
 
+    // condition overlay, worn roads go from yellow to red
+    let maintenance = sim.read::<RoadMaintenance>();
+    for road in map.roads().values() {
+        let condition = maintenance.condition(road.id);
+        if condition >= 1.0 {
+            continue;
+        }
+        let col = if maintenance.is_closed(road.id) {
+            Color::new(1.0, 0.55, 0.0, 0.5)
+        } else {
+            Color::new(1.0, condition, 0.0, 0.4 * (1.0 - condition))
+        };
+        imm_draw
+            .polyline(
+                road.points().iter().map(|p| p.up(0.05)).collect::<Vec<_>>(),
+                road.width,
+                false,
+            )
+            .color(col);
+    }
+    drop(maintenance);
+
+    if let Some(id) = state.inspect_road {
+        if let Some(road) = map.roads().get(id) {
+            imm_draw
+                .polyline(
+                    road.points().iter().map(|p| p.up(0.1)).collect::<Vec<_>>(),
+                    road.width,
+                    false,
+                )
+                .color(simulation::config().gui_primary.a(0.3));
+        } else {
+            state.inspect_road = None;
+        }
+    }
+
+    if let Some(interc) = &state.inspect {
+        if let Some(inter) = map
+            .intersections()
+            .get(interc.id)
+            .filter(|i| !interc.light_policy.has_lights(i, map.roads()))
+        {
+            for (i, road) in LightPolicy::approaches(inter, map.roads())
+                .into_iter()
+                .enumerate()
+            {
+                let road = unwrap_cont!(map.roads().get(road));
+                imm_draw
+                    .polyline(
+                        road.points().iter().map(|p| p.up(0.15)).collect::<Vec<_>>(),
+                        road.width * 0.5,
+                        false,
+                    )
+                    .color(approach_color(i).a(0.4));
+            }
+        }
+        if let (Some(program), Some(inter)) =
+            (&interc.light_program, map.intersections().get(interc.id))
+        {
+            for (road, phase) in program.road_phases(inter, map.roads()) {
+                let road = unwrap_cont!(map.roads().get(road));
+                let col = Color::hsv(phase as f32 * 90.0, 0.8, 0.8, 0.4);
+                imm_draw
+                    .polyline(
+                        road.points().iter().map(|p| p.up(0.15)).collect::<Vec<_>>(),
+                        road.width * 0.5,
+                        false,
+                    )
+                    .color(col);
+            }
+        }
+    }
+
+    let corridor = state.corridor.filter(|_| state.inspect.is_some());
+    if let Some(arterial) = corridor.and_then(|i| state.corridors.get(i)) {
+        let col = simulation::config().gui_primary;
+        for road in arterial.roads.iter().filter_map(|&r| map.roads().get(r)) {
+            let points: Vec<_> = road.points().iter().map(|p| p.up(0.5)).collect();
+            imm_draw
+                .polyline(points, road.width * 0.5, false)
+                .color(col.a(0.7));
+        }
+    }
+
     if let Some(id) = state.inspect.as_ref().map(|x| x.id) {
         if let Some(inter) = map.intersections().get(id) {
             let lanes = map.lanes();
@@ -82,8 +200,21 @@ pub fn roadeditor(sim: &Simulation, uiworld: &mut UiWorld) {
                 id,
                 turn_policy: inter.turn_policy,
                 light_policy: inter.light_policy,
+                light_program: inter
+                    .light_program
+                    .clone()
+                    .filter(|p| p.fits(inter, map.roads())),
+                light_offset: inter.light_offset,
+                approach_signs: inter.approach_signs.clone(),
             });
+            state.inspect_road = None;
             state.dirty = false;
+            state.timing_dirty = false;
+            state.corridors = arterials_through(&map, id);
+            state.corridor = None;
+        } else if let ProjectKind::Road(id) = map.project(proj_pos, 0.0, ProjectFilter::ROAD).kind {
+            state.inspect = None;
+            state.inspect_road = Some(id);
         }
     }
 
@@ -96,7 +227,16 @@ pub fn roadeditor(sim: &Simulation, uiworld: &mut UiWorld) {
                 interc.turn_policy,
                 interc.light_policy,
             );
+            commands.map_set_approach_signs(interc.id, interc.approach_signs.clone());
         }
         state.dirty = false;
     }
+
+    if state.timing_dirty {
+        if let Some(interc) = &state.inspect {
+            commands.map_set_light_program(interc.id, interc.light_program.clone());
+            commands.map_set_light_offsets(vec![(interc.id, interc.light_offset)]);
+        }
+        state.timing_dirty = false;
+    }
 }
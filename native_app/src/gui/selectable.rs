@@ -1,8 +1,8 @@
-use crate::gui::{InspectedBuilding, InspectedEntity, Tool};
+use crate::gui::{InspectedBuilding, InspectedEntity, InspectedIntersection, Tool};
 use crate::inputmap::{InputAction, InputMap};
 use crate::uiworld::UiWorld;
 use geom::Vec2;
-use simulation::map::ProjectFilter;
+use simulation::map::{ProjectFilter, ProjectKind};
 use simulation::{AnyEntity, Simulation};
 
 pub fn select_radius(id: AnyEntity) -> f32 {
@@ -18,11 +18,15 @@ pub fn select_radius(id: AnyEntity) -> f32 {
     }
 }
 
-/// Selectable allows to select entities by clicking on them
+/// Intersections are selected when clicking within this distance of their center, in meters
+const INTERSECTION_SELECT_RADIUS: f32 = 5.0;
+
+/// Selectable allows to select entities, buildings and intersections by clicking on them
 pub fn selectable(sim: &Simulation, uiworld: &mut UiWorld) {
     profiling::scope!("gui::selectable");
     let mut inspected = uiworld.write::<InspectedEntity>();
     let mut inspected_b = uiworld.write::<InspectedBuilding>();
+    let mut inspected_i = uiworld.write::<InspectedIntersection>();
     let inp = uiworld.read::<InputMap>();
     let tool = uiworld.read::<Tool>();
 
@@ -63,8 +67,24 @@ pub fn selectable(sim: &Simulation, uiworld: &mut UiWorld) {
                 .find_map(|x| x.as_building());
         }
     }
+    if inp.just_act.contains(&InputAction::Select)
+        && matches!(*tool, Tool::Hand)
+        && !inspected_i.dontclear
+    {
+        inspected_i.e = None;
+        if inspected.e.is_none() && inspected_b.e.is_none() {
+            let unproj = unwrap_ret!(inp.unprojected);
+            let proj = sim
+                .map()
+                .project(unproj, INTERSECTION_SELECT_RADIUS, ProjectFilter::INTER);
+            if let ProjectKind::Inter(id) = proj.kind {
+                inspected_i.e = Some(id);
+            }
+        }
+    }
     inspected.dontclear = false;
     inspected_b.dontclear = false;
+    inspected_i.dontclear = false;
 
     if let Some(e) = inspected.e {
         if !sim.world().contains(e) {
@@ -78,8 +98,15 @@ pub fn selectable(sim: &Simulation, uiworld: &mut UiWorld) {
         }
     }
 
+    if let Some(i) = inspected_i.e {
+        if !sim.map().intersections().contains_key(i) {
+            inspected_i.e = None;
+        }
+    }
+
     if inp.just_act.contains(&InputAction::Close) || matches!(*tool, Tool::Bulldozer) {
         inspected.e = None;
         inspected_b.e = None;
+        inspected_i.e = None;
     }
 }
@@ -0,0 +1,62 @@
+use crate::gui::windows::debug::DebugObjs;
+use crate::gui::{InspectedBuilding, InspectedEntity, Tool};
+use crate::rendering::OrbitCamera;
+use crate::uiworld::UiWorld;
+use geom::Camera;
+use serde::{Deserialize, Serialize};
+use simulation::map::BuildingID;
+use simulation::{AnyEntity, Simulation};
+
+/// UiSession is the UI state saved alongside the gui, so that loading a save
+/// resumes the session exactly where it was left off
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiSession {
+    pub camera: Option<Camera>,
+    pub tool: Tool,
+    /// Names of the enabled debug overlays
+    pub overlays: Vec<String>,
+    pub inspected: Option<AnyEntity>,
+    pub inspected_building: Option<BuildingID>,
+}
+
+impl UiSession {
+    pub fn capture(uiworld: &UiWorld) -> Self {
+        Self {
+            camera: Some(uiworld.read::<OrbitCamera>().camera),
+            tool: *uiworld.read::<Tool>(),
+            overlays: uiworld
+                .read::<DebugObjs>()
+                .0
+                .iter()
+                .filter(|(enabled, _, _)| *enabled)
+                .map(|(_, name, _)| name.to_string())
+                .collect(),
+            inspected: uiworld.read::<InspectedEntity>().e,
+            inspected_building: uiworld.read::<InspectedBuilding>().e,
+        }
+    }
+
+    /// Restores the session, entities that do not exist anymore are not inspected
+    pub fn restore(&self, uiworld: &mut UiWorld, sim: &Simulation) {
+        if let Some(camera) = self.camera {
+            let mut orbit = uiworld.camera_mut();
+            orbit.camera = camera;
+            orbit.targetpos = camera.pos;
+            orbit.targetyaw = camera.yaw;
+            orbit.targetpitch = camera.pitch;
+            orbit.targetdist = camera.dist;
+        }
+
+        *uiworld.write::<Tool>() = self.tool;
+
+        for (enabled, name, _) in &mut uiworld.write::<DebugObjs>().0 {
+            *enabled = self.overlays.iter().any(|x| x == name);
+        }
+
+        uiworld.write::<InspectedEntity>().e = self.inspected.filter(|&e| sim.world().contains(e));
+        uiworld.write::<InspectedBuilding>().e = self
+            .inspected_building
+            .filter(|&b| sim.map().buildings().contains_key(b));
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use common::i18n::tr;
+use egui::{Align2, Color32, RichText};
+
+use simulation::utils::scheduler::{SeqSchedule, SlowTick};
+
+use crate::gui::windows::settings::Settings;
+use crate::gui::windows::GUIWindows;
+use crate::uiworld::UiWorld;
+
+/// Slow ticks are reported at most this often, a struggling simulation is slow every tick
+const REPORT_COOLDOWN: Duration = Duration::from_secs(10);
+const TOAST_DURATION: Duration = Duration::from_secs(8);
+const MAX_REPORTS: usize = 20;
+
+/// SlowTicks keeps the reports of the ticks that went over the budget set in the settings,
+/// shown in the profiler window
+#[derive(Default)]
+pub struct SlowTicks {
+    pub reports: VecDeque<SlowTick>,
+    last_report: Option<Instant>,
+    toast: bool,
+}
+
+impl SlowTicks {
+    fn push(&mut self, report: SlowTick) {
+        if self
+            .last_report
+            .map_or(false, |t| t.elapsed() < REPORT_COOLDOWN)
+        {
+            return;
+        }
+        log::warn!("slow tick: {}", report.summary());
+        if self.reports.len() >= MAX_REPORTS {
+            self.reports.pop_front();
+        }
+        self.reports.push_back(report);
+        self.last_report = Some(Instant::now());
+        self.toast = true;
+    }
+}
+
+/// Called after each tick, reports it if its systems took longer than the budget
+pub fn check_slow_tick(uiw: &UiWorld, sched: &SeqSchedule) {
+    let budget = uiw.read::<Settings>().slow_tick_budget_ms;
+    if budget <= 0.0 {
+        return;
+    }
+    if let Some(report) = sched.slow_tick(budget) {
+        uiw.write::<SlowTicks>().push(report);
+    }
+}
+
+/// Slow tick toast
+/// Tells which systems made the last tick slow, with a shortcut to the profiler window
+pub fn slow_tick_toast(ui: &egui::Context, uiw: &mut UiWorld, windows: &mut GUIWindows) {
+    let mut slow = uiw.write::<SlowTicks>();
+    if !slow.toast
+        || slow
+            .last_report
+            .map_or(true, |t| t.elapsed() > TOAST_DURATION)
+    {
+        slow.toast = false;
+        return;
+    }
+    let Some(report) = slow.reports.back() else {
+        return;
+    };
+
+    let mut close = false;
+    egui::Window::new("slow_tick_toast")
+        .title_bar(false)
+        .resizable(false)
+        .anchor(Align2::RIGHT_TOP, [-10.0, 40.0])
+        .show(ui, |ui| {
+            ui.label(
+                RichText::new(format!(
+                    "{} {:.0}ms / {:.0}ms",
+                    tr("Slow tick:"),
+                    report.total_ms,
+                    report.budget_ms
+                ))
+                .color(Color32::from_rgb(230, 150, 60)),
+            );
+            if let Some(s) = report.systems.first() {
+                ui.label(format!("{} {:.1}ms", s.name, s.ms));
+            }
+            ui.horizontal(|ui| {
+                if ui.button(tr("Open profiler")).clicked() {
+                    windows.open("Profiler");
+                    close = true;
+                }
+                if ui.small_button("x").clicked() {
+                    close = true;
+                }
+            });
+        });
+    if close {
+        slow.toast = false;
+    }
+}
@@ -6,7 +6,7 @@ use crate::uiworld::UiWorld;
 use common::AudioKind;
 use geom::{Degrees, Intersect, Vec3, OBB};
 use ordered_float::OrderedFloat;
-use simulation::map::{ProjectFilter, ProjectKind};
+use simulation::map::{Map, ProjectFilter, ProjectKind};
 use simulation::world_command::WorldCommand;
 use simulation::Simulation;
 use std::borrow::Cow;
@@ -22,6 +22,8 @@ pub struct SpecialBuildKind {
     pub h: f32,
     pub asset: String,
     pub road_snap: bool,
+    /// Extra placement constraint, returns why the building cannot be placed here
+    pub check: Option<fn(&Map, &OBB) -> Result<(), &'static str>>,
 }
 
 #[derive(Default)]
@@ -29,6 +31,25 @@ pub struct SpecialBuildingResource {
     pub opt: Option<SpecialBuildKind>,
     pub last_obb: Option<OBB>,
     pub rotation: Degrees,
+    /// Mirrors the footprint (walkways, docks, rails), the building asset itself keeps its orientation
+    pub mirrored: bool,
+    /// Keep placing the same building until Escape instead of going back to the hand tool
+    pub repeat: bool,
+    /// Rotation and mirroring of the last placed building
+    pub last_placement: Option<(Degrees, bool)>,
+}
+
+impl SpecialBuildingResource {
+    /// Rotates by a quarter turn, snapping the rotation to quarter turns
+    pub fn rotate_quarter(&mut self, clockwise: bool) {
+        let quarter = (self.rotation.0 / 90.0).round() + if clockwise { -1.0 } else { 1.0 };
+        self.rotation = Degrees((quarter * 90.0).rem_euclid(360.0));
+    }
+
+    /// Number of quarter turns of the rotation, used to orient buildings snapped to roads
+    fn quarter_turns(&self) -> i32 {
+        (self.rotation.0 / 90.0).round() as i32
+    }
 }
 
 /// SpecialBuilding tool
@@ -69,6 +90,15 @@ pub fn specialbuilding(sim: &Simulation, uiworld: &mut UiWorld) {
         state.rotation += Degrees(inp.wheel);
         state.rotation.normalize();
     }
+    if inp.just_act.contains(&InputAction::RotateLeft) {
+        state.rotate_quarter(false);
+    }
+    if inp.just_act.contains(&InputAction::RotateRight) {
+        state.rotate_quarter(true);
+    }
+    if inp.just_act.contains(&InputAction::Mirror) {
+        state.mirrored = !state.mirrored;
+    }
 
     let SpecialBuildKind {
         w,
@@ -76,13 +106,17 @@ pub fn specialbuilding(sim: &Simulation, uiworld: &mut UiWorld) {
         ref asset,
         ref make,
         road_snap,
+        check,
     } = *unwrap_or!(&state.opt, return);
 
     let mpos = unwrap_ret!(inp.unprojected);
     let roads = map.roads();
 
     let diag = 0.5 * w.hypot(h);
-    let hover_obb = OBB::new(mpos.xy(), state.rotation.vec2(), w, h);
+    let mut hover_obb = OBB::new(mpos.xy(), state.rotation.vec2(), w, h);
+    if state.mirrored {
+        hover_obb = hover_obb.mirrored();
+    }
 
     let mut draw = |obb, red| {
         let p = asset.to_string();
@@ -95,6 +129,7 @@ pub fn specialbuilding(sim: &Simulation, uiworld: &mut UiWorld) {
         if p.ends_with(".png") || p.ends_with(".jpg") {
             draw.textured_obb(obb, p, mpos.z + 0.1).color(col);
         } else if p.ends_with(".glb") {
+            let obb = obb.unmirrored();
             draw.mesh(p, obb.center().z(mpos.z), obb.axis()[0].normalize().z0())
                 .color(col);
         }
@@ -134,12 +169,18 @@ pub fn specialbuilding(sim: &Simulation, uiworld: &mut UiWorld) {
         let first = closest_road.points().first();
         let last = closest_road.points().last();
 
+        // buildings along roads can only be rotated by quarter turns
+        let quarter = state.quarter_turns();
+        let depth = if quarter % 2 == 0 { w } else { h };
         obb = OBB::new(
-            proj.xy() + side * (h + closest_road.width + 0.5) * 0.5,
-            side,
+            proj.xy() + side * (depth + closest_road.width + 0.5) * 0.5,
+            side.rotated_by(Degrees(quarter as f32 * 90.0).vec2()),
             w,
             h,
         );
+        if state.mirrored {
+            obb = obb.mirrored();
+        }
 
         if proj.distance(first) < diag || proj.distance(last) < diag {
             *uiworld.write::<ErrorTooltip>() =
@@ -179,13 +220,24 @@ pub fn specialbuilding(sim: &Simulation, uiworld: &mut UiWorld) {
         return;
     }
 
+    if let Some(Err(reason)) = check.map(|check| check(&map, &obb)) {
+        *uiworld.write::<ErrorTooltip>() = ErrorTooltip::new(Cow::Borrowed(reason));
+        draw(obb, true);
+        return;
+    }
+
     draw(obb, false);
 
     let cmds: Vec<WorldCommand> = make(&SpecialBuildArgs { obb, mpos });
     if inp.act.contains(&InputAction::Select) {
-        commands.extend(cmds);
+        // e.g. the freight station and its tracks are built together or not at all
+        commands.push(WorldCommand::batch(cmds));
         sound.play("road_lay", AudioKind::Ui);
         state.last_obb = Some(obb);
+        state.last_placement = Some((state.rotation, state.mirrored));
+        if !state.repeat {
+            *uiworld.write::<Tool>() = Tool::Hand;
+        }
     } else if let Some(last) = cmds.last() {
         uiworld.write::<PotentialCommands>().set(last.clone());
     }
@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use common::i18n::tr;
+use egui::load::SizedTexture;
+use egui::{Align2, Color32, Frame, Label, RichText, Sense, Widget};
+
+use geom::Vec3;
+use simulation::utils::events::SimEventKind;
+
+use crate::gui::windows::settings::Settings;
+use crate::gui::UiTextures;
+use crate::uiworld::UiWorld;
+
+/// How long a subtitle stays on screen after the last time its sound was heard
+const SUBTITLE_DURATION: Duration = Duration::from_secs(6);
+const MAX_SUBTITLES: usize = 5;
+
+struct Subtitle {
+    kind: SimEventKind,
+    pos: Vec3,
+    count: u32,
+    last_heard: Instant,
+}
+
+/// Subtitles mirrors the sounds of the simulation events into a ticker so that they are not
+/// missed when playing muted. Filled by the event sounds whenever one is heard.
+#[derive(Default)]
+pub struct Subtitles {
+    entries: VecDeque<Subtitle>,
+}
+
+impl Subtitles {
+    /// Sounds of the same kind heard while their subtitle is on screen are aggregated
+    pub fn push(&mut self, kind: SimEventKind, pos: Vec3) {
+        if let Some(s) = self.entries.iter_mut().find(|s| s.kind == kind) {
+            s.pos = pos;
+            s.count += 1;
+            s.last_heard = Instant::now();
+            return;
+        }
+        if self.entries.len() >= MAX_SUBTITLES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Subtitle {
+            kind,
+            pos,
+            count: 1,
+            last_heard: Instant::now(),
+        });
+    }
+}
+
+fn caption(kind: SimEventKind) -> &'static str {
+    match kind {
+        SimEventKind::Construction => "Construction completed",
+        SimEventKind::TrainArrived => "Train horn",
+        SimEventKind::Incident => "Sirens",
+    }
+}
+
+fn icon(kind: SimEventKind) -> Option<&'static str> {
+    match kind {
+        SimEventKind::Construction => Some("buildings"),
+        SimEventKind::TrainArrived => Some("traintool"),
+        SimEventKind::Incident => None,
+    }
+}
+
+/// Subtitles ticker
+/// Shows the sounds heard recently with an icon, clicking one moves the camera to where it
+/// came from. Sits above the chat where the incidents are detailed.
+pub fn subtitles(ui: &egui::Context, uiw: &mut UiWorld) {
+    let mut subtitles = uiw.write::<Subtitles>();
+    subtitles
+        .entries
+        .retain(|s| s.last_heard.elapsed() < SUBTITLE_DURATION);
+
+    if !uiw.read::<Settings>().subtitles || subtitles.entries.is_empty() {
+        return;
+    }
+
+    let textures = uiw.read::<UiTextures>();
+    let mut look_at = None;
+
+    egui::Window::new("Subtitles")
+        .title_bar(false)
+        .resizable(false)
+        .auto_sized()
+        .frame(Frame::default().fill(Color32::from_black_alpha(128)))
+        .anchor(Align2::LEFT_BOTTOM, (0.0, -370.0))
+        .show(ui, |ui| {
+            for s in &subtitles.entries {
+                ui.horizontal(|ui| {
+                    ui.add_space(5.0);
+                    match icon(s.kind).and_then(|name| textures.try_get(name)) {
+                        Some(tex) => {
+                            ui.image(SizedTexture::new(tex, [20.0, 20.0]));
+                        }
+                        None => {
+                            ui.colored_label(Color32::from_rgb(255, 100, 75), "⚠");
+                        }
+                    }
+
+                    let mut text = format!("[{}]", tr(caption(s.kind)));
+                    if s.count > 1 {
+                        text += &format!(" x{}", s.count);
+                    }
+                    if Label::new(RichText::new(text).color(Color32::WHITE))
+                        .sense(Sense::click())
+                        .ui(ui)
+                        .on_hover_text(tr("Click to look"))
+                        .clicked()
+                    {
+                        look_at = Some(s.pos);
+                    }
+                    ui.add_space(5.0);
+                });
+            }
+        });
+
+    drop(textures);
+    drop(subtitles);
+    if let Some(pos) = look_at {
+        uiw.camera_mut().targetpos = pos;
+    }
+}
@@ -3,8 +3,8 @@ use crate::inputmap::{InputAction, InputMap};
 use crate::rendering::immediate::ImmediateDraw;
 use crate::uiworld::UiWorld;
 use egui_inspect::Inspect;
-use geom::{Vec2, Vec3, OBB};
-use simulation::map::TerraformKind;
+use geom::{Vec2, Vec3, AABB, OBB};
+use simulation::map::{ProjectKind, TerraformKind, WATER_DEPTH};
 use simulation::world_command::WorldCommand;
 use simulation::Simulation;
 
@@ -30,7 +30,7 @@ pub fn terraforming(sim: &Simulation, uiworld: &mut UiWorld) {
     let tool = *uiworld.read::<Tool>();
     let inp = uiworld.read::<InputMap>();
     let mut draw = uiworld.write::<ImmediateDraw>();
-    let _map = sim.map();
+    let map = sim.map();
     let commands = &mut *uiworld.commands();
 
     if !matches!(tool, Tool::Terraforming) {
@@ -90,6 +90,7 @@ pub fn terraforming(sim: &Simulation, uiworld: &mut UiWorld) {
             }
         }
         TerraformKind::Erode => {}
+        TerraformKind::Water => {}
     }
 
     if inp.act.contains(&InputAction::Select) || inp.act.contains(&InputAction::SecondarySelect) {
@@ -138,6 +139,32 @@ pub fn terraforming(sim: &Simulation, uiworld: &mut UiWorld) {
             .color(simulation::config().gui_primary.a(0.2));
         }
         TerraformKind::Erode => {}
+        TerraformKind::Water => {
+            draw.circle(mpos.xy().z(WATER_DEPTH), res.radius * 0.7)
+                .color(simulation::config().sea_col.a(0.3));
+        }
+    }
+
+    // Roads and buildings around the brush that the terrain doesn't fit anymore
+    let area = AABB::centered(mpos.xy(), Vec2::splat(res.radius * 4.0));
+    let col = simulation::config().gui_danger.a(0.5);
+    for obj in map.terrain_conflicts(area) {
+        match obj {
+            ProjectKind::Road(id) => {
+                let Some(road) = map.roads().get(id) else {
+                    continue;
+                };
+                let points: Vec<_> = road.points().iter().map(|p| p.up(0.5)).collect();
+                draw.polyline(points, road.width, false).color(col);
+            }
+            ProjectKind::Building(id) => {
+                let Some(b) = map.buildings().get(id) else {
+                    continue;
+                };
+                draw.obb(b.obb, b.door_pos.z + 0.5).color(col);
+            }
+            _ => {}
+        }
     }
 }
 
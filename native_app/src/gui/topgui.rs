@@ -1,16 +1,29 @@
+use crate::gui::addtrain::AddTrainResource;
 use crate::gui::bulldozer::BulldozerState;
 use crate::gui::chat::chat;
+use crate::gui::curbs::{curb_color, CurbToolResource};
+use crate::gui::decoration::{sign_texts, DecorationToolResource};
+use crate::gui::furniture::{furniture_color, FurnitureToolResource};
 use crate::gui::inspect::inspector;
-use crate::gui::lotbrush::LotBrushResource;
-use crate::gui::roadeditor::RoadEditorResource;
+use crate::gui::interchange::InterchangeResource;
+use crate::gui::lotbrush::{LotBrushMode, LotBrushResource};
+use crate::gui::modpanels::ModPanels;
+use crate::gui::palette::{help_overlay, CommandPalette, PaletteEntry};
+use crate::gui::roadeditor::{approach_color, RoadEditorResource};
+use crate::gui::session::UiSession;
+use crate::gui::slow_ticks::slow_tick_toast;
 use crate::gui::specialbuilding::{SpecialBuildKind, SpecialBuildingResource};
+use crate::gui::subtitles::subtitles;
 use crate::gui::terraforming::TerraformingResource;
+use crate::gui::tutorial::{tutorial, TutorialHighlight};
+use crate::gui::windows::arterials::GreenWaveSpeed;
 use crate::gui::windows::settings::Settings;
 use crate::gui::windows::GUIWindows;
 use crate::gui::{ErrorTooltip, PotentialCommands, RoadBuildResource, Tool, UiTextures};
-use crate::inputmap::{InputAction, InputMap};
+use crate::inputmap::{Bindings, InputAction, InputMap};
 use crate::uiworld::{SaveLoadState, UiWorld};
-use common::descriptions::BuildingGen;
+use common::descriptions::{BuildingGen, DoorLayout};
+use common::i18n::tr;
 use common::saveload::Encoder;
 use egui::load::SizedTexture;
 use egui::{
@@ -18,23 +31,77 @@ use egui::{
     Widget, Window,
 };
 use egui_inspect::{Inspect, InspectArgs};
-use geom::{Polygon, Vec2};
+use geom::{Color, Polygon, Vec2};
 use serde::{Deserialize, Serialize};
-use simulation::economy::{Government, Item, ItemRegistry, Money};
+use simulation::economy::{Government, Item, ItemRegistry, Milestone, Milestones, Money};
 use simulation::map::{
-    BuildingKind, LanePatternBuilder, LightPolicy, MapProject, TerraformKind, TurnPolicy, Zone,
+    ApproachSign, BuildingKind, CurbKind, DecalPattern, FurnitureKind, InterchangeKind,
+    LanePatternBuilder, LightPolicy, LightProgram, LotKind, MapLayer, MapProject, RoundaboutPolicy,
+    TerraformKind, TurnPolicy, Zone, MAX_DECORATION_SIZE, MAX_PHASE_LENGTH, MAX_SIGN_TEXT,
+    MIN_PHASE_LENGTH,
 };
+use simulation::map_dynamic::Rezoning;
 use simulation::souls::goods_company::GoodsCompanyRegistry;
-use simulation::utils::time::{GameTime, SECONDS_PER_HOUR};
+use simulation::transportation::airport::{check_airport_footprint, AIRPORT_LENGTH, AIRPORT_WIDTH};
+use simulation::transportation::metro::check_metro_station_footprint;
+use simulation::transportation::road_maintenance::{PlowPriority, RepairFunding, RoadMaintenance};
+use simulation::transportation::train::LocomotiveKind;
+use simulation::transportation::waterway::{check_harbor_footprint, HARBOR_LENGTH, HARBOR_WIDTH};
+use simulation::utils::render_snapshot::SnapshotBuffer;
+use simulation::utils::time::SECONDS_PER_HOUR;
 use simulation::world_command::WorldCommand;
 use simulation::Simulation;
 use std::sync::atomic::Ordering;
 use std::time::{Duration, Instant};
 
+#[derive(Copy, Clone)]
+pub enum Tab {
+    Hand,
+    Roadbuild,
+    Roadcurved,
+    Roadeditor,
+    Housebrush,
+    Roadbuilding,
+    Bulldozer,
+    Train,
+    Terraforming,
+}
+
+const TOOLS: [(&str, Tab, Tool); 8] = [
+    ("road", Tab::Roadbuild, Tool::RoadbuildStraight),
+    ("curved", Tab::Roadcurved, Tool::RoadbuildCurved),
+    ("road_edit", Tab::Roadeditor, Tool::RoadEditor),
+    ("housebrush", Tab::Housebrush, Tool::LotBrush),
+    ("buildings", Tab::Roadbuilding, Tool::SpecialBuilding),
+    ("bulldozer", Tab::Bulldozer, Tool::Bulldozer),
+    ("traintool", Tab::Train, Tool::Train),
+    ("terraform", Tab::Terraforming, Tool::Terraforming),
+];
+
+/// Tools without a button of their own, with the tab they are chosen from
+const SUB_TOOLS: [(Tab, Tool); 6] = [
+    (Tab::Hand, Tool::Hand),
+    (Tab::Roadbuild, Tool::Interchange),
+    (Tab::Roadeditor, Tool::Curbs),
+    (Tab::Roadeditor, Tool::RoadFurniture),
+    (Tab::Roadeditor, Tool::Decoration),
+    (Tab::Roadeditor, Tool::BusStops),
+];
+
+#[derive(Clone)]
+enum PaletteAction {
+    Tool(Tab, Tool),
+    Window(&'static str),
+    Input(InputAction),
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(default)]
 pub struct Gui {
     pub windows: GUIWindows,
+    pub session: UiSession,
+    #[serde(skip)]
+    pub mod_panels: ModPanels,
     #[serde(skip)]
     pub last_save: Instant,
     #[serde(skip)]
@@ -47,18 +114,23 @@ pub struct Gui {
     pub depause_warp: u32,
     #[serde(skip)]
     pub hidden: bool,
+    #[serde(skip)]
+    pub palette: CommandPalette,
 }
 
 impl Default for Gui {
     fn default() -> Self {
         Self {
             windows: GUIWindows::default(),
+            session: UiSession::default(),
+            mod_panels: ModPanels::default(),
             last_save: Instant::now(),
             last_gui_save: Instant::now(),
             n_cars: 100,
             n_pedestrians: 100,
             depause_warp: 1,
             hidden: false,
+            palette: CommandPalette::default(),
         }
     }
 }
@@ -79,7 +151,9 @@ impl Gui {
             return;
         }
 
-        self.time_controls(ui, uiworld, sim);
+        sign_texts(ui, uiworld, sim);
+
+        self.time_controls(ui, uiworld);
 
         self.menu_bar(ui, uiworld, sim);
 
@@ -87,13 +161,96 @@ impl Gui {
 
         chat(ui, uiworld, sim);
 
+        subtitles(ui, uiworld);
+
+        slow_tick_toast(ui, uiworld, &mut self.windows);
+
         self.windows.render(ui, uiworld, sim);
 
+        self.mod_panels.render(ui, uiworld, sim);
+
         Self::toolbox(ui, uiworld, sim);
 
+        tutorial(ui, uiworld, sim);
+
+        self.command_palette(ui, uiworld);
+
         self.tooltip(ui, uiworld, sim);
     }
 
+    /// Command palette listing the tools, windows and actions with their hotkeys, and the help
+    /// overlay showing the controls of the current tool
+    pub fn command_palette(&mut self, ui: &Context, uiworld: &mut UiWorld) {
+        profiling::scope!("topgui::command_palette");
+        let inp = uiworld.read::<InputMap>();
+        if inp.just_act.contains(&InputAction::CommandPalette) {
+            self.palette.toggle();
+        }
+        if inp.just_act.contains(&InputAction::ToggleHelp) {
+            self.palette.help = !self.palette.help;
+        }
+        drop(inp);
+
+        let bindings = uiworld.read::<Bindings>();
+        if self.palette.help {
+            help_overlay(ui, *uiworld.read::<Tool>(), &bindings);
+        }
+        if !self.palette.open {
+            return;
+        }
+
+        let hotkey = |act: &InputAction| bindings.0.get(act).map(ToString::to_string);
+        let mut entries: Vec<PaletteEntry<PaletteAction>> = TOOLS
+            .iter()
+            .map(|&(_, tab, tool)| (tab, tool))
+            .chain(SUB_TOOLS)
+            .map(|(tab, tool)| PaletteEntry {
+                name: tr(tool.name()),
+                category: "Tool",
+                hotkey: None,
+                action: PaletteAction::Tool(tab, tool),
+            })
+            .collect();
+        entries.extend(self.windows.names().map(|name| PaletteEntry {
+            name: tr(name),
+            category: "Window",
+            hotkey: if name == "Economy" {
+                hotkey(&InputAction::OpenEconomyMenu)
+            } else {
+                None
+            },
+            action: PaletteAction::Window(name),
+        }));
+        entries.extend(
+            [
+                InputAction::PausePlay,
+                InputAction::OpenChat,
+                InputAction::HideInterface,
+                InputAction::ToggleHelp,
+                InputAction::Undo,
+                InputAction::Redo,
+            ]
+            .into_iter()
+            .map(|act| PaletteEntry {
+                name: act.to_string(),
+                category: "Action",
+                hotkey: hotkey(&act),
+                action: PaletteAction::Input(act),
+            }),
+        );
+        drop(bindings);
+
+        match self.palette.show(ui, &entries) {
+            Some(PaletteAction::Tool(tab, tool)) => {
+                uiworld.insert(tool);
+                uiworld.insert(tab);
+            }
+            Some(PaletteAction::Window(name)) => self.windows.open(name),
+            Some(PaletteAction::Input(act)) => uiworld.write::<InputMap>().inject(act),
+            None => {}
+        }
+    }
+
     pub fn tooltip(&mut self, ui: &Context, uiworld: &mut UiWorld, sim: &Simulation) {
         profiling::scope!("gui::tooltip");
         let tooltip = std::mem::take(&mut *uiworld.write::<ErrorTooltip>());
@@ -142,6 +299,7 @@ impl Gui {
         }
 
         if self.last_gui_save.elapsed() > Duration::from_secs(1) {
+            self.session = UiSession::capture(uiworld);
             if let Ok(data) = common::saveload::JSONPretty::encode(self) {
                 rayon::spawn(move || {
                     let _ = std::fs::write(common::saveload::JSONPretty::filename("gui"), data);
@@ -153,19 +311,16 @@ impl Gui {
 
     pub fn toolbox(ui: &Context, uiworld: &mut UiWorld, sim: &Simulation) {
         profiling::scope!("topgui::toolbox");
-        #[derive(Copy, Clone)]
-        pub enum Tab {
-            Hand,
-            Roadbuild,
-            Roadcurved,
-            Roadeditor,
-            Housebrush,
-            Roadbuilding,
-            Bulldozer,
-            Train,
-            Terraforming,
-        }
-        uiworld.check_present(|| Tab::Hand);
+        // the tool might have been restored from a save, open its tab
+        let cur_tool = *uiworld.read::<Tool>();
+        uiworld.check_present(|| {
+            TOOLS
+                .iter()
+                .map(|&(_, tab, tool)| (tab, tool))
+                .chain(SUB_TOOLS)
+                .find(|&(_, tool)| tool == cur_tool)
+                .map_or(Tab::Hand, |(tab, _)| tab)
+        });
 
         if uiworld
             .read::<InputMap>()
@@ -184,18 +339,7 @@ impl Gui {
 
         let toolbox_w = 85.0;
 
-        let tools = [
-            ("road", Tab::Roadbuild, Tool::RoadbuildStraight),
-            ("curved", Tab::Roadcurved, Tool::RoadbuildCurved),
-            ("road_edit", Tab::Roadeditor, Tool::RoadEditor),
-            ("housebrush", Tab::Housebrush, Tool::LotBrush),
-            ("buildings", Tab::Roadbuilding, Tool::SpecialBuilding),
-            ("bulldozer", Tab::Bulldozer, Tool::Bulldozer),
-            ("traintool", Tab::Train, Tool::Train),
-            ("terraform", Tab::Terraforming, Tool::Terraforming),
-        ];
-
-        Window::new("Toolbox")
+        Window::new(tr("Toolbox"))
             .min_width(toolbox_w)
             .fixed_pos([w, h * 0.5])
             .vscroll(false)
@@ -207,35 +351,177 @@ impl Gui {
             .auto_sized()
             .show(ui, |ui| {
                 let cur_tab = *uiworld.read::<Tab>();
+                uiworld.write::<TutorialHighlight>().buttons.clear();
 
-                for (name, tab, default_tool) in &tools {
-                    if egui::ImageButton::new(SizedTexture::new(
+                for (name, tab, default_tool) in &TOOLS {
+                    let resp = egui::ImageButton::new(SizedTexture::new(
                         uiworld.read::<UiTextures>().get(name),
                         [toolbox_w, 30.0],
                     ))
                     .selected(std::mem::discriminant(tab) == std::mem::discriminant(&cur_tab))
-                    .ui(ui)
-                    .clicked()
-                    {
+                    .ui(ui);
+                    uiworld
+                        .write::<TutorialHighlight>()
+                        .buttons
+                        .push((*name, resp.rect));
+                    if resp.clicked() {
                         uiworld.insert::<Tool>(*default_tool);
                         uiworld.insert(*tab);
                     }
                 }
+
+                ui.separator();
+                let mut layer = uiworld.write::<MapLayer>();
+                for (l, name) in [
+                    (MapLayer::Surface, "Surface"),
+                    (MapLayer::Underground, "Underground"),
+                ] {
+                    if ui.selectable_label(*layer == l, tr(name)).clicked() {
+                        *layer = l;
+                    }
+                }
             });
 
         if matches!(*uiworld.read::<Tab>(), Tab::Roadeditor) {
+            Window::new(tr("Curbs"))
+                .fixed_size([150.0, 100.0])
+                .fixed_pos([w - 150.0 - toolbox_w, h * 0.5 - 170.0])
+                .vscroll(false)
+                .title_bar(true)
+                .collapsible(false)
+                .resizable(false)
+                .show(ui, |ui| {
+                    let mut curbs = uiworld.write::<CurbToolResource>();
+                    let is_tool = *uiworld.read::<Tool>() == Tool::Curbs;
+                    for kind in CurbKind::DESIGNATIONS {
+                        let c = curb_color(kind);
+                        let text = RichText::new(tr(kind.name())).color(Color32::from_rgb(
+                            (c.r * 255.0) as u8,
+                            (c.g * 255.0) as u8,
+                            (c.b * 255.0) as u8,
+                        ));
+                        if ui
+                            .selectable_label(is_tool && curbs.kind == kind, text)
+                            .clicked()
+                        {
+                            curbs.kind = kind;
+                            *uiworld.write::<Tool>() = Tool::Curbs;
+                        }
+                    }
+                });
+
+            Window::new(tr("Road furniture"))
+                .fixed_size([150.0, 80.0])
+                .fixed_pos([w - 150.0 - toolbox_w, h * 0.5 - 280.0])
+                .vscroll(false)
+                .title_bar(true)
+                .collapsible(false)
+                .resizable(false)
+                .show(ui, |ui| {
+                    let mut furniture = uiworld.write::<FurnitureToolResource>();
+                    let is_tool = *uiworld.read::<Tool>() == Tool::RoadFurniture;
+                    for kind in FurnitureKind::ALL {
+                        let c = furniture_color(kind);
+                        let text = RichText::new(tr(kind.name())).color(Color32::from_rgb(
+                            (c.r * 255.0) as u8,
+                            (c.g * 255.0) as u8,
+                            (c.b * 255.0) as u8,
+                        ));
+                        if ui
+                            .selectable_label(is_tool && furniture.kind == kind, text)
+                            .clicked()
+                        {
+                            furniture.kind = kind;
+                            *uiworld.write::<Tool>() = Tool::RoadFurniture;
+                        }
+                    }
+                });
+
+            Window::new(tr("Decorations"))
+                .fixed_size([150.0, 150.0])
+                .fixed_pos([w - 150.0 - toolbox_w, h * 0.5 - 480.0])
+                .vscroll(false)
+                .title_bar(true)
+                .collapsible(false)
+                .resizable(false)
+                .show(ui, |ui| {
+                    let mut deco = uiworld.write::<DecorationToolResource>();
+                    let is_tool = *uiworld.read::<Tool>() == Tool::Decoration;
+                    for pattern in DecalPattern::ALL {
+                        if ui
+                            .selectable_label(
+                                is_tool && !deco.sign && deco.pattern == pattern,
+                                tr(pattern.name()),
+                            )
+                            .clicked()
+                        {
+                            deco.pattern = pattern;
+                            deco.sign = false;
+                            *uiworld.write::<Tool>() = Tool::Decoration;
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        if ui
+                            .selectable_label(is_tool && deco.sign, tr("Sign"))
+                            .clicked()
+                        {
+                            deco.sign = true;
+                            *uiworld.write::<Tool>() = Tool::Decoration;
+                        }
+                        egui::TextEdit::singleline(&mut deco.text)
+                            .char_limit(MAX_SIGN_TEXT)
+                            .ui(ui);
+                    });
+                    ui.horizontal(|ui| {
+                        egui::DragValue::new(&mut deco.size)
+                            .clamp_range(0.5..=MAX_DECORATION_SIZE)
+                            .speed(0.1)
+                            .ui(ui);
+                        ui.label(tr("size"));
+                    });
+                    <Color as Inspect<Color>>::render_mut(
+                        &mut deco.color,
+                        "",
+                        ui,
+                        &InspectArgs::default(),
+                    );
+                });
+
+            Window::new(tr("Transit"))
+                .fixed_size([150.0, 50.0])
+                .fixed_pos([w - 150.0 - toolbox_w, h * 0.5 - 560.0])
+                .vscroll(false)
+                .title_bar(true)
+                .collapsible(false)
+                .resizable(false)
+                .show(ui, |ui| {
+                    let is_tool = *uiworld.read::<Tool>() == Tool::BusStops;
+                    if ui
+                        .selectable_label(is_tool, tr("Bus stops"))
+                        .on_hover_text(tr("The lines are edited in the Bus lines window"))
+                        .clicked()
+                    {
+                        *uiworld.write::<Tool>() = Tool::BusStops;
+                    }
+                });
+
             let state = &mut *uiworld.write::<RoadEditorResource>();
             if let Some(ref mut v) = state.inspect {
                 let dirty = &mut state.dirty;
-                Window::new("Editor")
+                let timing_dirty = &mut state.timing_dirty;
+                let corridors = &state.corridors;
+                let corridor = &mut state.corridor;
+                let mut green_wave = uiworld.write::<GreenWaveSpeed>();
+                let wave_speed = &mut green_wave.0;
+                Window::new(tr("Editor"))
                     .fixed_size([150.0, 200.0])
                     .fixed_pos([w - 150.0 - toolbox_w, h * 0.5 - 30.0])
-                    .vscroll(false)
+                    .vscroll(true)
                     .title_bar(true)
                     .collapsible(false)
                     .resizable(false)
                     .show(ui, |ui| {
-                        ui.label("Light policy");
+                        ui.label(tr("Light policy"));
                         *dirty |= <LightPolicy as Inspect<LightPolicy>>::render_mut(
                             &mut v.light_policy,
                             "",
@@ -246,9 +532,173 @@ impl Gui {
                                 ..Default::default()
                             },
                         );
+
+                        let map = sim.map();
+                        if let Some(inter) = map
+                            .intersections()
+                            .get(v.id)
+                            .filter(|i| v.light_policy.has_lights(i, map.roads()))
+                        {
+                            ui.add_space(10.0);
+                            ui.label(tr("Light timing"));
+                            let mut custom = v.light_program.is_some();
+                            if ui.checkbox(&mut custom, tr("Custom program")).changed() {
+                                v.light_program =
+                                    custom.then(|| LightProgram::new(inter, map.roads()));
+                                *timing_dirty = true;
+                            }
+                            let cycle = match v.light_program {
+                                Some(ref mut program) => {
+                                    let n = program.phases.len() as u16;
+                                    let mut cycle = program.cycle_length();
+                                    let range = MIN_PHASE_LENGTH * n..=MAX_PHASE_LENGTH * n;
+                                    if ui
+                                        .add(
+                                            egui::Slider::new(&mut cycle, range)
+                                                .text(tr("Cycle"))
+                                                .suffix("s"),
+                                        )
+                                        .changed()
+                                    {
+                                        program.set_cycle_length(cycle);
+                                        *timing_dirty = true;
+                                    }
+                                    for (i, phase) in program.phases.iter_mut().enumerate() {
+                                        let slider = egui::Slider::new(
+                                            phase,
+                                            MIN_PHASE_LENGTH..=MAX_PHASE_LENGTH,
+                                        )
+                                        .text(format!("{} {}", tr("Phase"), i + 1))
+                                        .suffix("s");
+                                        *timing_dirty |= ui.add(slider).changed();
+                                    }
+                                    program.cycle_length()
+                                }
+                                None => LightProgram::new(inter, map.roads()).cycle_length(),
+                            };
+                            let mut auto_offset = v.light_offset.is_none();
+                            if ui
+                                .checkbox(&mut auto_offset, tr("Automatic offset"))
+                                .changed()
+                            {
+                                v.light_offset = (!auto_offset).then_some(0);
+                                *timing_dirty = true;
+                            }
+                            if let Some(ref mut offset) = v.light_offset {
+                                let slider = egui::Slider::new(offset, 0..=cycle)
+                                    .text(tr("Offset"))
+                                    .suffix("s");
+                                *timing_dirty |= ui.add(slider).changed();
+                            }
+                        }
+
+                        if let Some(inter) = map
+                            .intersections()
+                            .get(v.id)
+                            .filter(|i| !v.light_policy.has_lights(i, map.roads()))
+                        {
+                            let approaches = LightPolicy::approaches(inter, map.roads());
+                            if !approaches.is_empty() {
+                                ui.add_space(10.0);
+                                ui.label(tr("Approach signs"));
+                            }
+                            let name = |s: Option<ApproachSign>| {
+                                s.map_or(tr("Policy"), |s| tr(&format!("{s:?}")))
+                            };
+                            for (i, road) in approaches.into_iter().enumerate() {
+                                let cur = v.approach_signs.get(&road).copied();
+                                let mut sign = cur;
+                                let c = approach_color(i);
+                                let col = Color32::from_rgb(
+                                    (c.r * 255.0) as u8,
+                                    (c.g * 255.0) as u8,
+                                    (c.b * 255.0) as u8,
+                                );
+                                ui.horizontal(|ui| {
+                                    egui::ComboBox::from_id_source(("approach_sign", i))
+                                        .selected_text(name(sign))
+                                        .show_ui(ui, |ui| {
+                                            ui.selectable_value(&mut sign, None, name(None));
+                                            for s in ApproachSign::ALL {
+                                                ui.selectable_value(
+                                                    &mut sign,
+                                                    Some(s),
+                                                    name(Some(s)),
+                                                );
+                                            }
+                                        });
+                                    let label = format!("{} {}", tr("Approach"), i + 1);
+                                    ui.label(RichText::new(label).color(col));
+                                });
+                                if sign != cur {
+                                    match sign {
+                                        Some(s) => v.approach_signs.insert(road, s),
+                                        None => v.approach_signs.remove(&road),
+                                    };
+                                    *dirty = true;
+                                }
+                            }
+                        }
+
+                        if !corridors.is_empty() {
+                            ui.add_space(10.0);
+                            ui.label(tr("Green wave"));
+                            for (i, arterial) in corridors.iter().enumerate() {
+                                let text = format!(
+                                    "{} signals, {:.0}m",
+                                    arterial.signals.len(),
+                                    arterial.length()
+                                );
+                                if ui.selectable_label(*corridor == Some(i), text).clicked() {
+                                    *corridor = Some(i);
+                                }
+                            }
+                            ui.add(
+                                egui::Slider::new(wave_speed, 30.0..=90.0)
+                                    .text(tr("Cruise speed (km/h)")),
+                            );
+                            if let Some(arterial) = corridor.and_then(|i| corridors.get(i)) {
+                                if ui
+                                    .button(tr("Apply green wave"))
+                                    .on_hover_text(tr(
+                                        "Lights with another cycle length than the first one \
+                                         of the corridor are left as is",
+                                    ))
+                                    .clicked()
+                                {
+                                    let offsets = arterial.green_wave(&map, *wave_speed / 3.6);
+                                    if let Some(&(_, offset)) =
+                                        offsets.iter().find(|(id, _)| *id == v.id)
+                                    {
+                                        v.light_offset = Some(offset);
+                                    }
+                                    uiworld.commands().map_set_light_offsets(
+                                        offsets
+                                            .into_iter()
+                                            .map(|(id, offset)| (id, Some(offset)))
+                                            .collect(),
+                                    );
+                                }
+                            }
+                        }
+                        drop(map);
+
                         ui.add_space(10.0);
-                        ui.label("Turn policy");
                         let had_roundabout = v.turn_policy.roundabout.is_some();
+                        let toggle = if had_roundabout {
+                            tr("Remove roundabout")
+                        } else {
+                            tr("Convert to roundabout")
+                        };
+                        if ui.button(toggle).clicked() {
+                            v.turn_policy.roundabout = match v.turn_policy.roundabout {
+                                Some(_) => None,
+                                None => Some(RoundaboutPolicy::default()),
+                            };
+                            *dirty = true;
+                        }
+                        ui.add_space(10.0);
+                        ui.label(tr("Turn policy"));
                         *dirty |= <TurnPolicy as Inspect<TurnPolicy>>::render_mut(
                             &mut v.turn_policy,
                             "Turn policy",
@@ -260,7 +710,66 @@ impl Gui {
                             },
                         );
                         if !had_roundabout && v.turn_policy.roundabout.is_some() {
-                            v.light_policy = LightPolicy::StopSigns;
+                            // entries of roundabouts yield under the auto policy
+                            v.light_policy = LightPolicy::Auto;
+                        }
+                    });
+            }
+
+            if let Some(road) = state.inspect_road {
+                Window::new(tr("Road"))
+                    .fixed_size([150.0, 350.0])
+                    .fixed_pos([w - 150.0 - toolbox_w, h * 0.5 - 30.0])
+                    .vscroll(false)
+                    .title_bar(true)
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(ui, |ui| {
+                        let maintenance = sim.read::<RoadMaintenance>();
+                        ui.label(format!("Snow: {:.0}%", maintenance.snow(road) * 100.0));
+                        ui.label(format!(
+                            "Condition: {:.0}%",
+                            maintenance.condition(road) * 100.0
+                        ));
+                        if maintenance.is_closed(road) {
+                            ui.label(tr("Lane closed for repairs"));
+                        }
+                        ui.add_space(10.0);
+                        ui.label(tr("Plowing priority"));
+                        let cur = maintenance.priority(road);
+                        for priority in PlowPriority::ALL {
+                            if ui
+                                .selectable_label(cur == priority, format!("{priority:?}"))
+                                .clicked()
+                                && cur != priority
+                            {
+                                uiworld.commands().set_road_plow_priority(road, priority);
+                            }
+                        }
+                        let map = sim.map();
+                        if let Some(r) = map.roads().get(road).filter(|r| r.is_rail()) {
+                            ui.add_space(10.0);
+                            let mut electrified = r.electrified;
+                            if ui.checkbox(&mut electrified, tr("Electrified")).changed() {
+                                uiworld
+                                    .commands()
+                                    .map_set_road_electrified(road, electrified);
+                            }
+                        }
+                        drop(map);
+                        ui.add_space(10.0);
+                        ui.label(tr("Repair funding (all roads)"));
+                        for funding in RepairFunding::ALL {
+                            if ui
+                                .selectable_label(
+                                    maintenance.funding == funding,
+                                    format!("{funding:?}"),
+                                )
+                                .clicked()
+                                && maintenance.funding != funding
+                            {
+                                uiworld.commands().set_road_repair_funding(funding);
+                            }
                         }
                     });
             }
@@ -268,8 +777,8 @@ impl Gui {
 
         if matches!(*uiworld.read::<Tab>(), Tab::Train) {
             let rbw = 150.0;
-            Window::new("Trains")
-                .fixed_size([rbw, 83.0])
+            Window::new(tr("Trains"))
+                .fixed_size([rbw, 150.0])
                 .fixed_pos([w - rbw - toolbox_w, h * 0.5 - 30.0])
                 .hscroll(false)
                 .title_bar(true)
@@ -286,6 +795,17 @@ impl Gui {
                         *uiworld.write::<Tool>() = Tool::Train;
                     }
 
+                    let mut state = uiworld.write::<AddTrainResource>();
+                    for loco in LocomotiveKind::ALL {
+                        if ui
+                            .selectable_label(state.loco == loco, loco.name())
+                            .clicked()
+                        {
+                            state.loco = loco;
+                        }
+                    }
+                    drop(state);
+
                     /*
                     if ui.button_with_size("Trainstation", [rbw, 30.0]) {
                         *uiworld.write::<Tool>() = Tool::SpecialBuilding;
@@ -312,7 +832,11 @@ impl Gui {
                     if *uiworld.read::<Tool>() == Tool::SpecialBuilding {
                         freightstation = freightstation.strong();
                     };
-                    if ui.button(freightstation).clicked() {
+                    let locked = sim.read::<Milestones>().locked_by(
+                        BuildingKind::RailFreightStation,
+                        &sim.read::<GoodsCompanyRegistry>(),
+                    );
+                    if locked_button(ui, freightstation, locked).clicked() {
                         *uiworld.write::<Tool>() = Tool::SpecialBuilding;
 
                         uiworld.write::<SpecialBuildingResource>().opt = Some(SpecialBuildKind {
@@ -339,6 +863,7 @@ impl Gui {
                                     kind: BuildingKind::RailFreightStation,
                                     gen: BuildingGen::NoWalkway {
                                         door_pos: Vec2::ZERO,
+                                        doors: DoorLayout::NONE,
                                     },
                                     zone: None,
                                 });
@@ -348,6 +873,7 @@ impl Gui {
                             h: 200.0,
                             asset: "rail_freight_station.glb".to_string(),
                             road_snap: false,
+                            check: None,
                         });
                     }
                 });
@@ -355,25 +881,28 @@ impl Gui {
 
         if matches!(*uiworld.read::<Tab>(), Tab::Roadbuild | Tab::Roadcurved) {
             let rbw = 220.0;
-            Window::new("Road Properties")
-                .fixed_size([rbw, 380.0])
+            Window::new(tr("Road Properties"))
+                .fixed_size([rbw, 440.0])
                 .fixed_pos([w - rbw - toolbox_w + tweak!(40.0), h * 0.5 - tweak!(125.0)])
                 .title_bar(true)
                 .collapsible(false)
                 .resizable(false)
                 .show(ui, |ui| {
                     let mut roadbuild = uiworld.write::<RoadBuildResource>();
-                    ui.checkbox(&mut roadbuild.snap_to_grid, "snap to grid");
+                    ui.checkbox(&mut roadbuild.snap_to_grid, tr("snap to grid"));
                     ui.horizontal(|ui| {
-                        if ui.button("zero").clicked() {
+                        if ui.button(tr("zero")).clicked() {
                             roadbuild.height_offset = 0.0;
                         }
+                        let min = roadbuild.min_height_offset();
                         egui::DragValue::new(&mut roadbuild.height_offset)
-                            .clamp_range(0.0..=100.0f32)
+                            .clamp_range(min..=100.0f32)
                             .speed(1.0)
                             .ui(ui);
-                        ui.label("height off");
+                        ui.label(tr("height off"));
                     });
+                    ui.checkbox(&mut roadbuild.tunnel, tr("tunnel"))
+                        .on_hover_text(tr("Allows negative heights to dig under the terrain"));
                     let pat = &mut roadbuild.pattern_builder;
 
                     static BUILDERS: &[(&str, LanePatternBuilder)] = &[
@@ -382,6 +911,22 @@ impl Gui {
                             "Rail one-way",
                             LanePatternBuilder::new().rail(true).one_way(true),
                         ),
+                        (
+                            "Electrified rail",
+                            LanePatternBuilder::new().rail(true).electrified(true),
+                        ),
+                        (
+                            "Electrified rail one-way",
+                            LanePatternBuilder::new()
+                                .rail(true)
+                                .electrified(true)
+                                .one_way(true),
+                        ),
+                        ("Canal", LanePatternBuilder::new().canal(true)),
+                        (
+                            "Canal one-way",
+                            LanePatternBuilder::new().canal(true).one_way(true),
+                        ),
                         ("Street", LanePatternBuilder::new()),
                         ("Street one-way", LanePatternBuilder::new().one_way(true)),
                         (
@@ -395,6 +940,21 @@ impl Gui {
                                 .one_way(true)
                                 .speed_limit(13.0),
                         ),
+                        (
+                            "Avenue bus lanes",
+                            LanePatternBuilder::new()
+                                .n_lanes(2)
+                                .bus_lanes(true)
+                                .speed_limit(13.0),
+                        ),
+                        ("Street tram", LanePatternBuilder::new().tram(true)),
+                        (
+                            "Avenue tram",
+                            LanePatternBuilder::new()
+                                .n_lanes(2)
+                                .tram(true)
+                                .speed_limit(13.0),
+                        ),
                         (
                             "Drive",
                             LanePatternBuilder::new()
@@ -465,13 +1025,53 @@ impl Gui {
                             pat.n_lanes = 10;
                         }
                     });
+                    drop(roadbuild);
+
+                    egui::CollapsingHeader::new("interchanges").show(ui, |ui| {
+                        let mut inter = uiworld.write::<InterchangeResource>();
+                        let is_tool = *uiworld.read::<Tool>() == Tool::Interchange;
+                        for kind in InterchangeKind::ALL {
+                            let mut text = RichText::new(kind.name());
+                            if is_tool && inter.kind == kind {
+                                text = text.strong();
+                            }
+                            if ui.button(text).clicked() {
+                                inter.kind = kind;
+                                *uiworld.write::<Tool>() = Tool::Interchange;
+                            }
+                        }
+                        ui.horizontal(|ui| {
+                            egui::DragValue::new(&mut inter.size)
+                                .clamp_range(100.0..=400.0f32)
+                                .speed(1.0)
+                                .ui(ui);
+                            ui.label(tr("size"));
+                        });
+                        ui.horizontal(|ui| {
+                            egui::DragValue::new(&mut inter.height)
+                                .clamp_range(6.0..=30.0f32)
+                                .speed(0.5)
+                                .ui(ui);
+                            ui.label(tr("bridge height"));
+                        });
+                        ui.label(tr("Crossing road"));
+                        for (name, lpat) in BUILDERS {
+                            if ui
+                                .selectable_label(inter.crossing == *lpat, *name)
+                                .clicked()
+                            {
+                                inter.crossing = *lpat;
+                            }
+                        }
+                    });
                 });
         }
 
         if matches!(*uiworld.read::<Tab>(), Tab::Housebrush) {
             let lbw = 120.0;
-            Window::new("House Brush")
-                .fixed_size([lbw, 50.0])
+            Window::new(tr("House Brush"))
+                .min_width(lbw)
+                .auto_sized()
                 .fixed_pos([w - toolbox_w - lbw - 10.0, h * 0.5 - 30.0])
                 .hscroll(false)
                 .title_bar(true)
@@ -484,14 +1084,64 @@ impl Gui {
                         egui::DragValue::new(&mut cur_brush.radius)
                             .clamp_range(10.0..=300.0f32)
                             .ui(ui);
-                        ui.label("radius");
-                    })
+                        ui.label(tr("radius"));
+                    });
+
+                    ui.horizontal(|ui| {
+                        for mode in [
+                            LotBrushMode::Build,
+                            LotBrushMode::Erase,
+                            LotBrushMode::Rezone,
+                        ] {
+                            if ui
+                                .selectable_label(cur_brush.mode == mode, format!("{mode:?}"))
+                                .clicked()
+                            {
+                                cur_brush.mode = mode;
+                                cur_brush.to_erase.clear();
+                            }
+                        }
+                    });
+
+                    if cur_brush.mode == LotBrushMode::Rezone {
+                        ui.horizontal(|ui| {
+                            for kind in [LotKind::Residential, LotKind::Commercial] {
+                                if ui
+                                    .selectable_label(cur_brush.kind == kind, format!("{kind:?}"))
+                                    .clicked()
+                                {
+                                    cur_brush.kind = kind;
+                                }
+                            }
+                        });
+                        let pending = sim.read::<Rezoning>().n_pending();
+                        if pending > 0 {
+                            ui.label(format!("{pending} houses waiting to be replaced"));
+                        }
+                    }
+
+                    if !cur_brush.to_erase.is_empty() {
+                        ui.label(format!("Demolish {} houses?", cur_brush.to_erase.len()));
+                        ui.horizontal(|ui| {
+                            if ui.button(tr("Confirm")).clicked() {
+                                let to_erase = std::mem::take(&mut cur_brush.to_erase);
+                                uiworld.commands().map_rezone_buildings(
+                                    to_erase,
+                                    LotKind::Unassigned,
+                                    true,
+                                );
+                            }
+                            if ui.button(tr("Cancel")).clicked() {
+                                cur_brush.to_erase.clear();
+                            }
+                        });
+                    }
                 });
         }
 
         if matches!(*uiworld.read::<Tab>(), Tab::Bulldozer) {
             let lbw = 120.0;
-            Window::new("Bulldozer")
+            Window::new(tr("Bulldozer"))
                 .min_width(lbw)
                 .auto_sized()
                 .fixed_pos([w - toolbox_w - lbw, h * 0.5 - 30.0])
@@ -516,7 +1166,7 @@ impl Gui {
 
         if matches!(*uiworld.read::<Tab>(), Tab::Terraforming) {
             let lbw = 150.0;
-            Window::new("Terraforming")
+            Window::new(tr("Terraforming"))
                 .min_width(lbw)
                 .auto_sized()
                 .fixed_pos([w - toolbox_w - lbw, h * 0.5 - 30.0])
@@ -537,11 +1187,17 @@ impl Gui {
                         },
                     );
 
-                    ui.radio_value(&mut state.kind, TerraformKind::Elevation, "Raise/Lower");
-                    ui.radio_value(&mut state.kind, TerraformKind::Smooth, "Smooth");
-                    ui.radio_value(&mut state.kind, TerraformKind::Level, "Level");
-                    ui.radio_value(&mut state.kind, TerraformKind::Slope, "Slope");
-                    ui.radio_value(&mut state.kind, TerraformKind::Erode, "Erode");
+                    ui.radio_value(&mut state.kind, TerraformKind::Elevation, tr("Raise/Lower"));
+                    ui.radio_value(&mut state.kind, TerraformKind::Smooth, tr("Smooth"));
+                    ui.radio_value(&mut state.kind, TerraformKind::Level, tr("Level"));
+                    ui.radio_value(&mut state.kind, TerraformKind::Slope, tr("Slope"));
+                    ui.radio_value(&mut state.kind, TerraformKind::Erode, tr("Erode"));
+                    ui.radio_value(&mut state.kind, TerraformKind::Water, tr("Water"));
+                    ui.label(format!(
+                        "{} {}",
+                        Government::terraform_cost(1000.0),
+                        tr("per 1000 m³ of earth moved")
+                    ));
                 });
         }
 
@@ -550,9 +1206,10 @@ impl Gui {
         let gbuildings = registry.descriptions.values().peekable();
 
         let iregistry = sim.read::<ItemRegistry>();
+        let milestones = sim.read::<Milestones>();
 
         if matches!(*uiworld.read::<Tab>(), Tab::Roadbuilding) {
-            Window::new("Buildings")
+            Window::new(tr("Buildings"))
                 .min_width(building_select_w)
                 .default_height(500.0f32.min(h * 0.5))
                 .vscroll(true)
@@ -563,19 +1220,41 @@ impl Gui {
                 .show(ui, |ui| {
                     let mut cur_build = uiworld.write::<SpecialBuildingResource>();
 
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut cur_build.repeat, tr("Repeat"));
+                        ui.checkbox(&mut cur_build.mirrored, tr("Mirror"));
+                    });
+                    ui.label(format!(
+                        "Rotation: {:.0}°",
+                        cur_build.rotation.0.rem_euclid(360.0)
+                    ));
+                    if let Some((rotation, mirrored)) = cur_build.last_placement {
+                        ui.label(format!(
+                            "Last placed: {:.0}°{}",
+                            rotation.0.rem_euclid(360.0),
+                            if mirrored { ", mirrored" } else { "" }
+                        ));
+                    }
+                    ui.separator();
+
                     let mut picked_descr = None;
                     ui.style_mut().spacing.interact_size = [building_select_w - 5.0, 35.0].into();
 
                     for descr in gbuildings {
                         let cur_kind = cur_build.opt.as_ref().map(|x| &*x.asset).unwrap_or("");
+                        let bkind = BuildingKind::GoodsCompany(descr.id);
+                        let locked = milestones.locked_by(bkind, &registry);
 
                         let mut name = RichText::new(&descr.name);
                         if descr.asset_location == cur_kind {
                             picked_descr = Some(descr);
                             name = name.strong();
                         };
-                        if ui.button(name).clicked() || cur_build.opt.is_none() {
-                            let bkind = BuildingKind::GoodsCompany(descr.id);
+                        let button = locked_button(ui, name, locked);
+                        if locked.is_none() && (button.clicked() || cur_build.opt.is_none()) {
+                            if button.clicked() {
+                                *uiworld.write::<Tool>() = Tool::SpecialBuilding;
+                            }
                             let bgen = descr.bgen;
                             let has_zone = descr.zone.is_some();
                             cur_build.opt = Some(SpecialBuildKind {
@@ -587,7 +1266,9 @@ impl Gui {
                                         gen: bgen,
                                         zone: has_zone.then(|| {
                                             Zone::new(
-                                                Polygon::from(args.obb.corners.as_slice()),
+                                                Polygon::from(
+                                                    args.obb.unmirrored().corners.as_slice(),
+                                                ),
                                                 Vec2::X,
                                             )
                                         }),
@@ -596,14 +1277,151 @@ impl Gui {
                                 w: descr.size,
                                 h: descr.size,
                                 asset: descr.asset_location.to_string(),
+                                check: None,
                             });
                         }
                     }
 
+                    if ui.button(tr("Snow Plow Depot")).clicked() {
+                        *uiworld.write::<Tool>() = Tool::SpecialBuilding;
+                        cur_build.opt = Some(SpecialBuildKind {
+                            road_snap: true,
+                            make: Box::new(move |args| {
+                                vec![WorldCommand::MapBuildSpecialBuilding {
+                                    pos: args.obb,
+                                    kind: BuildingKind::SnowPlowDepot,
+                                    gen: BuildingGen::CenteredDoor {
+                                        vertical_factor: 1.0,
+                                        doors: DoorLayout::DRIVEWAY,
+                                    },
+                                    zone: None,
+                                }]
+                            }),
+                            w: 40.0,
+                            h: 40.0,
+                            asset: "assets/sprites/cement.jpg".to_string(),
+                            check: None,
+                        });
+                    }
+
+                    if ui.button(tr("Parking lot")).clicked() {
+                        *uiworld.write::<Tool>() = Tool::SpecialBuilding;
+                        cur_build.opt = Some(SpecialBuildKind {
+                            road_snap: true,
+                            make: Box::new(move |args| {
+                                vec![WorldCommand::MapBuildSpecialBuilding {
+                                    pos: args.obb,
+                                    kind: BuildingKind::ParkingLot,
+                                    gen: BuildingGen::CenteredDoor {
+                                        vertical_factor: 1.0,
+                                        doors: DoorLayout::DRIVEWAY,
+                                    },
+                                    zone: None,
+                                }]
+                            }),
+                            w: 40.0,
+                            h: 40.0,
+                            asset: "assets/sprites/cement.jpg".to_string(),
+                            check: None,
+                        });
+                    }
+
+                    let locked = milestones.locked_by(BuildingKind::Airport, &registry);
+                    if locked_button(ui, RichText::new("Airport"), locked).clicked() {
+                        *uiworld.write::<Tool>() = Tool::SpecialBuilding;
+                        cur_build.opt = Some(SpecialBuildKind {
+                            road_snap: true,
+                            make: Box::new(move |args| {
+                                vec![WorldCommand::MapBuildSpecialBuilding {
+                                    pos: args.obb,
+                                    kind: BuildingKind::Airport,
+                                    gen: BuildingGen::CenteredDoor {
+                                        vertical_factor: 1.0,
+                                        doors: DoorLayout::DRIVEWAY,
+                                    },
+                                    zone: None,
+                                }]
+                            }),
+                            w: AIRPORT_WIDTH,
+                            h: AIRPORT_LENGTH,
+                            asset: "assets/sprites/cement.jpg".to_string(),
+                            check: Some(check_airport_footprint),
+                        });
+                    }
+
+                    let locked = milestones.locked_by(BuildingKind::Harbor, &registry);
+                    if locked_button(ui, RichText::new(tr("Harbor")), locked).clicked() {
+                        *uiworld.write::<Tool>() = Tool::SpecialBuilding;
+                        cur_build.opt = Some(SpecialBuildKind {
+                            road_snap: true,
+                            make: Box::new(move |args| {
+                                vec![WorldCommand::MapBuildSpecialBuilding {
+                                    pos: args.obb,
+                                    kind: BuildingKind::Harbor,
+                                    gen: BuildingGen::CenteredDoor {
+                                        vertical_factor: 1.0,
+                                        doors: DoorLayout::DRIVEWAY,
+                                    },
+                                    zone: None,
+                                }]
+                            }),
+                            w: HARBOR_WIDTH,
+                            h: HARBOR_LENGTH,
+                            asset: "assets/sprites/cement.jpg".to_string(),
+                            check: Some(check_harbor_footprint),
+                        });
+                    }
+
+                    let locked = milestones.locked_by(BuildingKind::MetroStation, &registry);
+                    if locked_button(ui, RichText::new(tr("Metro station")), locked).clicked() {
+                        *uiworld.write::<Tool>() = Tool::SpecialBuilding;
+                        cur_build.opt = Some(SpecialBuildKind {
+                            road_snap: true,
+                            make: Box::new(move |args| {
+                                vec![WorldCommand::MapBuildSpecialBuilding {
+                                    pos: args.obb,
+                                    kind: BuildingKind::MetroStation,
+                                    gen: BuildingGen::CenteredDoor {
+                                        vertical_factor: 1.0,
+                                        doors: DoorLayout::DRIVEWAY,
+                                    },
+                                    zone: None,
+                                }]
+                            }),
+                            w: 30.0,
+                            h: 60.0,
+                            asset: "assets/sprites/cement.jpg".to_string(),
+                            check: Some(check_metro_station_footprint),
+                        });
+                    }
+
+                    let locked = milestones.locked_by(BuildingKind::EventVenue, &registry);
+                    if locked_button(ui, RichText::new(tr("Event venue")), locked).clicked() {
+                        *uiworld.write::<Tool>() = Tool::SpecialBuilding;
+                        cur_build.opt = Some(SpecialBuildKind {
+                            road_snap: true,
+                            make: Box::new(move |args| {
+                                vec![WorldCommand::MapBuildSpecialBuilding {
+                                    pos: args.obb,
+                                    kind: BuildingKind::EventVenue,
+                                    gen: BuildingGen::CenteredDoor {
+                                        vertical_factor: 1.0,
+                                        doors: DoorLayout::DRIVEWAY,
+                                    },
+                                    zone: None,
+                                }]
+                            }),
+                            w: 80.0,
+                            h: 80.0,
+                            asset: "assets/sprites/cement.jpg".to_string(),
+                            check: None,
+                        });
+                    }
+
                     let bdescrpt_w = 180.0;
 
                     if let Some(descr) = picked_descr {
-                        Window::new("Building description")
+                        Window::new(tr("Building description"))
                             .default_width(bdescrpt_w)
                             .auto_sized()
                             .fixed_pos([
@@ -618,14 +1436,14 @@ impl Gui {
                                 ui.label(format!("workers: {}", descr.n_workers));
                                 ui.add_space(10.0);
                                 if !descr.recipe.consumption.is_empty() {
-                                    ui.label("consumption:");
+                                    ui.label(tr("consumption:"));
                                     for (kind, n) in &descr.recipe.consumption {
                                         item_icon(ui, uiworld, &iregistry[*kind], *n);
                                     }
                                     ui.add_space(10.0);
                                 }
                                 if !descr.recipe.production.is_empty() {
-                                    ui.label("production:");
+                                    ui.label(tr("production:"));
                                     for (kind, n) in &descr.recipe.production {
                                         item_icon(ui, uiworld, &iregistry[*kind], *n);
                                     }
@@ -642,9 +1460,9 @@ impl Gui {
         }
     }
 
-    pub fn time_controls(&mut self, ui: &Context, uiworld: &mut UiWorld, sim: &Simulation) {
+    pub fn time_controls(&mut self, ui: &Context, uiworld: &mut UiWorld) {
         profiling::scope!("topgui::time_controls");
-        let time = sim.read::<GameTime>().daytime;
+        let time = uiworld.read::<SnapshotBuffer>().latest().time.daytime;
         let warp = &mut uiworld.write::<Settings>().time_warp;
         let depause_warp = &mut self.depause_warp;
         if uiworld
@@ -677,7 +1495,7 @@ impl Gui {
 
         //let _tok1 = ui.push_style_var(StyleVar::WindowRounding(0.0));
         //let _tok2 = ui.push_style_var(StyleVar::ItemSpacing([10.0, 7.0]));
-        Window::new("Time controls")
+        Window::new(tr("Time controls"))
             .fixed_size([165.0, 55.0])
             .fixed_pos([-1.0, h])
             .title_bar(false)
@@ -727,6 +1545,7 @@ impl Gui {
         egui::TopBottomPanel::top("top_menu").show(ui, |ui| {
             egui::menu::bar(ui, |ui| {
                 self.windows.menu(ui);
+                self.mod_panels.menu(ui);
 
                 let mut name = "Save";
                 let mut enabled = true;
@@ -742,7 +1561,9 @@ impl Gui {
                     uiworld.save_to_disk();
                 }
 
-                ui.label(format!("Money: {}", sim.read::<Government>().money));
+                let money = uiworld.read::<SnapshotBuffer>().latest().money;
+                ui.label(format!("{} {}", tr("Money:"), money.compact()))
+                    .on_hover_text(money.to_string());
 
                 let mut estate = uiworld.write::<ExitState>();
 
@@ -751,7 +1572,7 @@ impl Gui {
                     ExitState::ExitAsk | ExitState::Saving => {
                         let [w, h]: [f32; 2] = ui.available_size().into();
                         let mut opened = true;
-                        Window::new("Exit Menu")
+                        Window::new(tr("Exit Menu"))
                             .default_pos([w * 0.5, h * 0.5])
                             .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
                             .auto_sized()
@@ -759,7 +1580,7 @@ impl Gui {
                             .show(ui.ctx(), |ui| {
                                 //let _tok = ui.push_style_var(StyleVar::ItemSpacing([2.0, 5.0]));
                                 if let ExitState::Saving = *estate {
-                                    ui.label("Saving...");
+                                    ui.label(tr("Saving..."));
                                     if !slstate.please_save
                                         && !slstate.saving_status.load(Ordering::SeqCst)
                                     {
@@ -767,16 +1588,16 @@ impl Gui {
                                     }
                                     return;
                                 }
-                                if ui.button("Save and exit").clicked() {
+                                if ui.button(tr("Save and exit")).clicked() {
                                     if let ExitState::ExitAsk = *estate {
                                         slstate.please_save = true;
                                         *estate = ExitState::Saving;
                                     }
                                 }
-                                if ui.button("Exit without saving").clicked() {
+                                if ui.button(tr("Exit without saving")).clicked() {
                                     std::process::exit(0);
                                 }
-                                if ui.button("Cancel").clicked() {
+                                if ui.button(tr("Cancel")).clicked() {
                                     *estate = ExitState::NoExit;
                                 }
                             });
@@ -793,12 +1614,12 @@ impl Gui {
 
                 match *estate {
                     ExitState::NoExit => {
-                        if ui.button("Exit").clicked() {
+                        if ui.button(tr("Exit")).clicked() {
                             *estate = ExitState::ExitAsk;
                         }
                     }
                     ExitState::ExitAsk => {
-                        if ui.button("Save and exit").clicked() {
+                        if ui.button(tr("Save and exit")).clicked() {
                             if let ExitState::ExitAsk = *estate {
                                 slstate.please_save = true;
                                 *estate = ExitState::Saving;
@@ -806,7 +1627,7 @@ impl Gui {
                         }
                     }
                     ExitState::Saving => {
-                        ui.label("Saving...");
+                        ui.label(tr("Saving..."));
                     }
                 }
             });
@@ -814,6 +1635,18 @@ impl Gui {
     }
 }
 
+/// A button that is grayed out with its requirements shown when the building is locked
+fn locked_button(ui: &mut Ui, text: RichText, locked: Option<&Milestone>) -> Response {
+    let button = ui.add_enabled(locked.is_none(), egui::Button::new(text));
+    match locked {
+        Some(m) => button.on_disabled_hover_text(format!(
+            "Requires the {} milestone: {} population and {} in the bank",
+            m.name, m.population, m.money
+        )),
+        None => button,
+    }
+}
+
 pub fn item_icon(ui: &mut Ui, uiworld: &UiWorld, item: &Item, multiplier: i32) -> Response {
     ui.horizontal(move |ui| {
         if let Some(id) = uiworld
@@ -823,11 +1656,11 @@ pub fn item_icon(ui: &mut Ui, uiworld: &UiWorld, item: &Item, multiplier: i32) -
             if ui.image(SizedTexture::new(id, (32.0, 32.0))).hovered() {
                 egui::show_tooltip(ui.ctx(), ui.make_persistent_id("icon tooltip"), |ui| {
                     ui.image(SizedTexture::new(id, (64.0, 64.0)));
-                    ui.label(format!("{} x{}", item.name, multiplier));
+                    ui.label(format!("{} x{}", tr(&item.label), multiplier));
                 });
             }
         } else {
-            ui.label(format!("- {} ", &item.label));
+            ui.label(format!("- {} ", tr(&item.label)));
         }
         ui.label(format!("x{multiplier}"))
     })
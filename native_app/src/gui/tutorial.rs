@@ -0,0 +1,90 @@
+use common::i18n::tr;
+use egui::{Align2, Color32, Context, Id, LayerId, Order, Rect, RichText, Stroke};
+
+use geom::Color;
+use simulation::utils::scenario::Scenario;
+use simulation::utils::tutorial::MARKER_RADIUS;
+use simulation::Simulation;
+
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+
+/// TutorialHighlight keeps where the toolbox buttons were drawn this frame, so that the tutorial
+/// can point at the one to use
+#[derive(Default)]
+pub struct TutorialHighlight {
+    pub buttons: Vec<(&'static str, Rect)>,
+}
+
+/// Tutorial panel
+/// Shows the current step of the tutorial and points an arrow at the tool it asks for
+pub fn tutorial(ui: &Context, uiworld: &mut UiWorld, sim: &Simulation) {
+    let scenario = sim.read::<Scenario>();
+    let Some(step) = scenario.current_step() else {
+        return;
+    };
+
+    egui::Window::new(tr("Tutorial"))
+        .id(Id::new("tutorial"))
+        .collapsible(false)
+        .resizable(false)
+        .default_width(300.0)
+        .anchor(Align2::CENTER_TOP, [0.0, 40.0])
+        .show(ui, |ui| {
+            ui.label(
+                RichText::new(format!(
+                    "{} {}/{}",
+                    tr("Step"),
+                    scenario.step + 1,
+                    scenario.tutorial.len()
+                ))
+                .strong(),
+            );
+            ui.label(tr(&step.text));
+        });
+
+    let Some(ref highlight) = step.highlight else {
+        return;
+    };
+    let highlights = uiworld.read::<TutorialHighlight>();
+    let Some(&(_, rect)) = highlights
+        .buttons
+        .iter()
+        .find(|(name, _)| name == highlight)
+    else {
+        return;
+    };
+
+    // the toolbox is on the right of the screen, the arrow comes from the left and bobs
+    let bob = (ui.input(|i| i.time) * 4.0).sin() as f32 * 8.0;
+    let tip = rect.left_center() - egui::vec2(8.0 + bob, 0.0);
+    let painter = ui.layer_painter(LayerId::new(Order::Foreground, Id::new("tutorial_arrow")));
+    painter.arrow(
+        tip - egui::vec2(60.0, 0.0),
+        egui::vec2(60.0, 0.0),
+        Stroke::new(4.0, Color32::from_rgb(255, 200, 50)),
+    );
+    painter.rect_stroke(
+        rect.expand(2.0),
+        3.0,
+        Stroke::new(2.0, Color32::from_rgb(255, 200, 50)),
+    );
+    ui.request_repaint();
+}
+
+/// Marks on the map the points the current step asks to connect
+pub fn draw_tutorial_markers(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("tutorial::draw_tutorial_markers");
+    let scenario = sim.read::<Scenario>();
+    let Some(step) = scenario.current_step() else {
+        return;
+    };
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    for pos in step.objective.markers() {
+        let pos = pos.up(0.5);
+        draw.circle(pos, MARKER_RADIUS)
+            .color(Color::new(1.0, 0.8, 0.2, 0.3));
+        draw.stroke_circle(pos, MARKER_RADIUS, 1.5)
+            .color(Color::new(1.0, 0.8, 0.2, 1.0));
+    }
+}
@@ -0,0 +1,60 @@
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+use geom::{Color, Vec3};
+use simulation::map::{BuildingKind, MapLayer, ProjectKind};
+use simulation::transportation::metro::MetroLines;
+use simulation::Simulation;
+
+/// Height above the terrain at which the underground network is drawn
+const CUTAWAY_Z: f32 = 0.5;
+const TUNNEL_COLOR: Color = Color::new(0.15, 0.15, 0.2, 0.8);
+const STATION_COLOR: Color = Color::new(0.2, 0.4, 0.8, 0.8);
+
+/// Underground view
+/// Draws the metro tunnels, stations and trains over the terrain while the underground layer is
+/// edited, as if the surface was cut away.
+pub fn draw_underground(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::draw_underground");
+    if *uiworld.read::<MapLayer>() != MapLayer::Underground {
+        return;
+    }
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let map = sim.map();
+    let env = &map.environment;
+    let lift = |p: Vec3| p.xy().z(env.height(p.xy()).unwrap_or(p.z) + CUTAWAY_Z);
+
+    for road in map.roads().values() {
+        if !MapLayer::Underground.contains(&map, ProjectKind::Road(road.id)) {
+            continue;
+        }
+        let points: Vec<Vec3> = road
+            .points()
+            .equipoints_dir(10.0, true)
+            .map(|(p, _)| lift(p))
+            .collect();
+        draw.polyline(points, road.width, false).color(TUNNEL_COLOR);
+    }
+
+    for inter in map.intersections().values() {
+        if MapLayer::Underground.contains(&map, ProjectKind::Inter(inter.id)) {
+            draw.circle(lift(inter.pos), 5.0).color(TUNNEL_COLOR);
+        }
+    }
+
+    for b in map.buildings().values() {
+        if b.kind == BuildingKind::MetroStation {
+            draw.obb(b.obb, lift(b.door_pos).z + 0.1)
+                .color(STATION_COLOR);
+        }
+    }
+
+    let lines = sim.read::<MetroLines>();
+    for train in sim.world().trains.values() {
+        let Some(ref metro) = train.metro else {
+            continue;
+        };
+        let col = lines.get(metro.line).map_or(Color::WHITE, |l| l.color);
+        draw.circle(lift(train.trans.position).up(0.2), 4.0)
+            .color(col);
+    }
+}
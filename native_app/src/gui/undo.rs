@@ -0,0 +1,92 @@
+use crate::inputmap::{InputAction, InputMap};
+use crate::network::NetworkState;
+use crate::uiworld::UiWorld;
+use simulation::world_command::{Inverse, UndoableEdits, WorldCommands};
+use simulation::Simulation;
+
+/// Number of map edits that can be undone
+const MAX_UNDO: usize = 64;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Sent {
+    Undo,
+    Redo,
+}
+
+#[derive(Default)]
+pub struct UndoState {
+    /// Index of the next edit recorded by the simulation that wasn't looked at
+    next_edit: u64,
+    /// Inverses of the last map edits, the last one undoes the last edit
+    undo: Vec<Inverse>,
+    /// Inverses redoing the edits that were undone
+    redo: Vec<Inverse>,
+    /// The undo or redo command waiting to be applied
+    sent: Option<Sent>,
+}
+
+impl UndoState {
+    fn record(&mut self, inverse: Inverse) {
+        match self.sent {
+            Some(Sent::Undo) => self.redo.push(inverse),
+            Some(Sent::Redo) => self.undo.push(inverse),
+            None => {
+                self.undo.push(inverse);
+                self.redo.clear();
+            }
+        }
+        if self.undo.len() > MAX_UNDO {
+            self.undo.remove(0);
+        }
+    }
+}
+
+/// Undo/Redo of the map edits: building and bulldozing roads and buildings, and zone updates.
+/// Disabled in multiplayer as the edits of the other players would be undone too.
+pub fn undo(sim: &Simulation, uiworld: &mut UiWorld) {
+    profiling::scope!("gui::undo");
+    #[allow(irrefutable_let_patterns)]
+    let NetworkState::Singleplayer(_) = *uiworld.read::<NetworkState>() else {
+        return;
+    };
+
+    let mut state = uiworld.write::<UndoState>();
+    let mut commands = uiworld.commands();
+
+    // Wait for the undo or redo command to be applied to know which stack its inverse goes to
+    if state.sent.is_some() && !commands.is_empty() {
+        return;
+    }
+
+    let edits = sim.read::<UndoableEdits>();
+    if edits.next() < state.next_edit {
+        // another simulation was loaded, its map has nothing to do with the recorded edits
+        *state = UndoState::default();
+    }
+    let next_edit = state.next_edit;
+    for (_, inverse) in edits.since(next_edit) {
+        state.record(inverse.clone());
+    }
+    state.next_edit = edits.next();
+    state.sent = None;
+
+    let inp = uiworld.read::<InputMap>();
+    if inp.just_act.contains(&InputAction::Undo) {
+        send(sim, &mut state, &mut commands, Sent::Undo);
+    } else if inp.just_act.contains(&InputAction::Redo) {
+        send(sim, &mut state, &mut commands, Sent::Redo);
+    }
+}
+
+fn send(sim: &Simulation, state: &mut UndoState, commands: &mut WorldCommands, sent: Sent) {
+    let stack = match sent {
+        Sent::Undo => &mut state.undo,
+        Sent::Redo => &mut state.redo,
+    };
+    let Some(inverse) = stack.pop() else {
+        return;
+    };
+    // the roads and buildings may have been replaced since the edit
+    commands.push(inverse.resolve(&sim.map()));
+    state.sent = Some(sent);
+}
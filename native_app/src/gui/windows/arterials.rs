@@ -0,0 +1,120 @@
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+use egui::{Grid, RichText};
+use simulation::map::{detect_arterials, Arterial};
+use simulation::Simulation;
+
+#[derive(Default)]
+pub struct ArterialsState {
+    arterials: Vec<Arterial>,
+    selected: Option<usize>,
+}
+
+/// Cruise speed of the green waves in km/h, shared by this window and the road editor
+pub struct GreenWaveSpeed(pub f32);
+
+impl Default for GreenWaveSpeed {
+    fn default() -> Self {
+        Self(50.0)
+    }
+}
+
+/// Arterials window
+/// Detects the corridors of signalized intersections along main roads and coordinates their
+/// lights so that vehicles driving at the cruise speed get a green wave
+pub fn arterials(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+) {
+    window.default_width(300.0).show(ui, |ui| {
+        let mut state = uiworld.write::<ArterialsState>();
+        ui.label(
+            "Arterials are main roads going straight through several signalized intersections.",
+        );
+        if ui.button("Detect arterials").clicked() {
+            state.arterials = detect_arterials(&sim.map());
+            state.selected = None;
+        }
+        ui.separator();
+
+        if state.arterials.is_empty() {
+            ui.label("No arterial detected.");
+            return;
+        }
+
+        let mut selected = state.selected;
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                Grid::new("arterials").striped(true).show(ui, |ui| {
+                    for (i, arterial) in state.arterials.iter().enumerate() {
+                        let text = format!(
+                            "{} signals, {:.0}m",
+                            arterial.signals.len(),
+                            arterial.length()
+                        );
+                        if ui.selectable_label(selected == Some(i), text).clicked() {
+                            selected = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        state.selected = selected;
+
+        let Some(arterial) = selected.and_then(|i| state.arterials.get(i)) else {
+            ui.label("Select an arterial to coordinate its lights.");
+            return;
+        };
+        draw_arterial(uiworld, sim, arterial);
+
+        ui.separator();
+        ui.label(RichText::new("Signal coordination").strong());
+        let mut speed = uiworld.read::<GreenWaveSpeed>().0;
+        ui.add(egui::Slider::new(&mut speed, 30.0..=90.0).text("Cruise speed (km/h)"));
+
+        let mut commands = None;
+        if ui
+            .button("Apply green wave")
+            .on_hover_text("Vehicles leaving the first light on green get green at the next ones")
+            .clicked()
+        {
+            let offsets = arterial
+                .green_wave(&sim.map(), speed / 3.6)
+                .into_iter()
+                .map(|(id, offset)| (id, Some(offset)))
+                .collect();
+            commands = Some(offsets);
+        }
+        if ui.button("Reset offsets").clicked() {
+            commands = Some(arterial.signals.iter().map(|s| (s.inter, None)).collect());
+        }
+        uiworld.write::<GreenWaveSpeed>().0 = speed;
+        drop(state);
+
+        if let Some(offsets) = commands {
+            uiworld.commands().map_set_light_offsets(offsets);
+        }
+    });
+}
+
+fn draw_arterial(uiworld: &UiWorld, sim: &Simulation, arterial: &Arterial) {
+    let map = sim.map();
+    let col = simulation::config().gui_primary;
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    for road in arterial.roads.iter().filter_map(|&r| map.roads().get(r)) {
+        let points: Vec<_> = road.points().iter().map(|p| p.up(0.5)).collect();
+        draw.polyline(points, road.width * 0.5, false)
+            .color(col.a(0.7));
+    }
+    for inter in arterial
+        .signals
+        .iter()
+        .filter_map(|s| map.intersections().get(s.inter))
+    {
+        draw.stroke_circle(inter.pos.up(0.5), inter.bcircle(map.roads()).radius, 2.0)
+            .color(col);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::gui::Tool;
+use crate::uiworld::UiWorld;
+use egui::{DragValue, Grid};
+use egui_inspect::{Inspect, InspectArgs};
+use geom::Color;
+use simulation::transportation::bus::{BusLine, BusLineID, BusLines, MAX_BUSES_PER_LINE};
+use simulation::Simulation;
+
+pub struct BusLinesState {
+    /// The line the bus stop tool adds stops to
+    pub selected: Option<BusLineID>,
+    name: String,
+    color: Color,
+}
+
+impl Default for BusLinesState {
+    fn default() -> Self {
+        Self {
+            selected: None,
+            name: String::new(),
+            color: Color::ORANGE,
+        }
+    }
+}
+
+/// Bus lines window
+/// Creates bus lines, picks their stops and how many buses run on them
+pub fn buslines(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+) {
+    let lines = sim.read::<BusLines>();
+    let map = sim.map();
+
+    window.default_width(350.0).show(ui, |ui| {
+        let mut state = uiworld.write::<BusLinesState>();
+        if state.selected.map_or(false, |id| lines.get(id).is_none()) {
+            state.selected = None;
+        }
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut state.name);
+            <Color as Inspect<Color>>::render_mut(
+                &mut state.color,
+                "",
+                ui,
+                &InspectArgs::default(),
+            );
+        });
+        if ui
+            .button("New line")
+            .on_hover_text("Pick its stops with the bus stop tool")
+            .clicked()
+        {
+            let name = if state.name.is_empty() {
+                format!("Line {}", lines.len() + 1)
+            } else {
+                std::mem::take(&mut state.name)
+            };
+            uiworld.commands().add_bus_line(BusLine {
+                name,
+                color: state.color,
+                stops: vec![],
+                n_buses: 1,
+                tram: false,
+            });
+        }
+        let mut selected = state.selected;
+        drop(state);
+        ui.separator();
+
+        if lines.is_empty() {
+            ui.label("No bus line yet.");
+            return;
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (id, line) in lines.iter() {
+                ui.push_id(id.0, |ui| {
+                    let mut updated = line.clone();
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut updated.name);
+                        <Color as Inspect<Color>>::render_mut(
+                            &mut updated.color,
+                            "",
+                            ui,
+                            &InspectArgs::default(),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Buses");
+                        ui.add(
+                            DragValue::new(&mut updated.n_buses)
+                                .clamp_range(0..=MAX_BUSES_PER_LINE),
+                        )
+                        .on_hover_text("Half of them keep running at night, from 23h to 5h");
+                        ui.checkbox(&mut updated.tram, "Trams")
+                            .on_hover_text("Trams run on the streets with tram tracks");
+                        if ui
+                            .selectable_label(selected == Some(id), "Edit stops")
+                            .on_hover_text("Click on the sidewalks to add stops to this line")
+                            .clicked()
+                        {
+                            if selected == Some(id) {
+                                selected = None;
+                            } else {
+                                selected = Some(id);
+                                *uiworld.write::<Tool>() = Tool::BusStops;
+                            }
+                        }
+                        if ui.small_button("Remove").clicked() {
+                            uiworld.commands().remove_bus_line(id);
+                        }
+                    });
+                    if !line.is_running() {
+                        ui.label("Not running: it needs a vehicle and two stops");
+                    }
+                    Grid::new("stops").striped(true).show(ui, |ui| {
+                        let mut removed = None;
+                        for (i, &stop) in line.stops.iter().enumerate() {
+                            let name = map
+                                .bus_stops()
+                                .get(stop)
+                                .map_or("?", |stop| stop.name.as_str());
+                            ui.label(format!("{}. {}", i + 1, name));
+                            if lines.is_unreachable(id, stop) {
+                                ui.colored_label(egui::Color32::RED, "Unreachable")
+                                    .on_hover_text("The vehicles of the line find no way there");
+                            } else {
+                                ui.label("");
+                            }
+                            if ui.small_button("Remove").clicked() {
+                                removed = Some(i);
+                            }
+                            ui.end_row();
+                        }
+                        if let Some(i) = removed {
+                            updated.stops.remove(i);
+                        }
+                    });
+                    if updated != *line {
+                        uiworld.commands().update_bus_line(id, updated);
+                    }
+                });
+                ui.separator();
+            }
+        });
+        uiworld.write::<BusLinesState>().selected = selected;
+    });
+}
@@ -0,0 +1,81 @@
+use crate::uiworld::UiWorld;
+use egui::{Button, DragValue, Grid, ProgressBar, RichText};
+use simulation::economy::{ConstructionQueue, Money};
+use simulation::Simulation;
+
+/// Constructions window
+/// Spreads the public constructions over time with a daily budget, and reorders the queue
+pub fn constructions(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+) {
+    let queue = sim.read::<ConstructionQueue>();
+
+    window.default_width(300.0).show(ui, |ui| {
+        let mut enabled = queue.enabled;
+        let mut limit = queue.daily_limit.bucks();
+        let mut changed = ui
+            .checkbox(&mut enabled, "Queue constructions")
+            .on_hover_text("New constructions are paid over time instead of at once")
+            .changed();
+        ui.horizontal(|ui| {
+            changed |= ui
+                .add(
+                    DragValue::new(&mut limit)
+                        .clamp_range(0..=1_000_000)
+                        .speed(100.0),
+                )
+                .changed();
+            ui.label("$ spent per day at most");
+        });
+        if changed {
+            uiworld
+                .commands()
+                .set_construction_budget(enabled, Money::new_bucks(limit));
+        }
+        ui.separator();
+
+        ui.label(RichText::new("Queued constructions").strong());
+        if queue.is_empty() {
+            ui.label("Nothing waiting to be built.");
+            return;
+        }
+        let last = queue.len() - 1;
+        Grid::new("constructions").striped(true).show(ui, |ui| {
+            for (i, c) in queue.iter().enumerate() {
+                ui.label(format!("{}.", i + 1));
+                ui.label(c.command.construction_name().unwrap_or("Construction"));
+                ui.add(
+                    ProgressBar::new(c.progress())
+                        .desired_width(100.0)
+                        .text(format!("{} / {}", c.paid, c.cost)),
+                );
+                if ui
+                    .add_enabled(i > 0, Button::new("Up").small())
+                    .on_hover_text("Fund earlier")
+                    .clicked()
+                {
+                    uiworld.commands().move_construction(c.id, i - 1);
+                }
+                if ui
+                    .add_enabled(i < last, Button::new("Down").small())
+                    .on_hover_text("Fund later")
+                    .clicked()
+                {
+                    uiworld.commands().move_construction(c.id, i + 1);
+                }
+                if ui
+                    .small_button("Cancel")
+                    .on_hover_text("What was already paid is refunded")
+                    .clicked()
+                {
+                    uiworld.commands().cancel_construction(c.id);
+                }
+                ui.end_row();
+            }
+        });
+        ui.label(format!("Still to pay: {}", queue.remaining()));
+    });
+}
@@ -3,25 +3,32 @@
 use crate::game_loop::Timings;
 use crate::gui::InspectedEntity;
 use crate::uiworld::UiWorld;
-use simulation::map_dynamic::ParkingManagement;
+use simulation::map_dynamic::{ParkingManagement, TrafficFlow, CONGESTION_FACTOR};
 use simulation::physics::CollisionWorld;
 use simulation::utils::time::{GameTime, Tick, SECONDS_PER_DAY};
-use simulation::{Simulation, TrainID};
+use simulation::{AnyEntity, Simulation, TrainID};
 
 use crate::inputmap::InputMap;
 use egui::Widget;
 use engine::{PerfCountersStatic, Tesselator};
 use geom::{Camera, Color, LinearColor, Spline3, Vec2};
 use simulation::map::{
-    IntersectionID, Map, MapSubscriber, RoadSegmentKind, TraverseKind, UpdateType,
+    Accessibility, IntersectionID, Map, MapSubscriber, PathDebug, RoadSegmentKind, TraverseKind,
+    UpdateType,
 };
 use simulation::transportation::train::TrainReservations;
+use simulation::utils::snapshot_diff::{diff_simulations, diff_with_replay, Divergence};
 use simulation::world_command::WorldCommand;
 
 #[derive(Default)]
 pub struct DebugState {
     pub connectivity: (Option<MapSubscriber>, Vec<Vec<IntersectionID>>),
     pub debug_inspector: bool,
+    /// Last replayed pathfinding query, keyed by the entity and the tick it was made at
+    pub path_search: Option<(AnyEntity, Tick, PathDebug)>,
+    /// Save compared with the current simulation to find determinism bugs
+    pub desync_save: String,
+    pub desync_report: Vec<String>,
 }
 
 pub struct DebugObjs(
@@ -36,6 +43,7 @@ impl Default for DebugObjs {
     fn default() -> Self {
         DebugObjs(vec![
             (true, "Debug pathfinder", debug_pathfinder),
+            (false, "Debug pathfinder search", debug_pathfinder_search),
             (false, "Debug train reservations", debug_trainreservations),
             (false, "Debug connectivity", debug_connectivity),
             (false, "Debug spatialmap", debug_spatialmap),
@@ -44,10 +52,57 @@ impl Default for DebugObjs {
             (false, "Debug lots", debug_lots),
             (false, "Debug road points", debug_road_points),
             (false, "Debug parking", debug_parking),
+            (false, "Debug stairs", debug_stairs),
+            (false, "Debug lane travel times", debug_traffic_flow),
         ])
     }
 }
 
+/// Compares the simulation with a save or with its own replay, and lists where they diverge
+fn desync(ui: &mut egui::Ui, uiworld: &UiWorld, sim: &Simulation) {
+    let mut state = uiworld.write::<DebugState>();
+    ui.horizontal(|ui| {
+        ui.label("Desync check with save");
+        ui.text_edit_singleline(&mut state.desync_save);
+    });
+    ui.horizontal(|ui| {
+        if ui.small_button("Diff with save").clicked() {
+            state.desync_report = match Simulation::load_from_disk(&state.desync_save) {
+                Some(other) => report(&diff_simulations(sim, &other)),
+                None => vec![format!("could not load {}", state.desync_save)],
+            };
+        }
+        if ui.small_button("Diff with replay").clicked() {
+            state.desync_report = match Simulation::load_replay_from_disk(&state.desync_save) {
+                Some(replay) => report(&diff_with_replay(sim, replay)),
+                None => vec![format!(
+                    "could not load the replay of {}",
+                    state.desync_save
+                )],
+            };
+        }
+    });
+    for line in &state.desync_report {
+        ui.label(line);
+    }
+}
+
+fn report(divergences: &[Divergence]) -> Vec<String> {
+    const SHOWN: usize = 10;
+    if divergences.is_empty() {
+        return vec!["no divergence".to_string()];
+    }
+    let mut lines: Vec<_> = divergences
+        .iter()
+        .take(SHOWN)
+        .map(|d| d.to_string())
+        .collect();
+    if divergences.len() > SHOWN {
+        lines.push(format!("and {} more", divergences.len() - SHOWN));
+    }
+    lines
+}
+
 #[derive(Clone)]
 pub struct TestFieldProperties {
     size: u32,
@@ -175,6 +230,9 @@ pub fn debug(
         ui.label(format!("Cam center:      {:.1} {:.1}", cam.x, cam.y));
         ui.separator();
 
+        desync(ui, uiworld, sim);
+        ui.separator();
+
         if ui.small_button("load Paris map").clicked() {
             uiworld.commands().map_load_paris();
         }
@@ -445,6 +503,46 @@ pub fn debug_parking(tess: &mut Tesselator<true>, sim: &Simulation, _: &UiWorld)
     Some(())
 }
 
+pub fn debug_stairs(tess: &mut Tesselator<true>, sim: &Simulation, _: &UiWorld) -> Option<()> {
+    let map: &Map = &sim.map();
+
+    tess.set_color(LinearColor::RED);
+    for inter in map.intersections().values() {
+        for turn in inter.turns() {
+            if turn.accessibility != Accessibility::Stairs {
+                continue;
+            }
+            let points: Vec<_> = turn.points.iter().map(|x| x.up(0.1)).collect();
+            tess.draw_polyline(&points, 1.0, false);
+        }
+    }
+
+    Some(())
+}
+
+/// Lanes vehicles drove through recently, from green at their free flow time to red when congested
+pub fn debug_traffic_flow(
+    tess: &mut Tesselator<true>,
+    sim: &Simulation,
+    _: &UiWorld,
+) -> Option<()> {
+    let map: &Map = &sim.map();
+    let flow = sim.read::<TrafficFlow>();
+
+    for lane in map.lanes().values() {
+        if flow.travel_time(lane.id).is_none() {
+            continue;
+        }
+        let congestion = flow.congestion(map, lane.id);
+        let t = ((congestion - 1.0) / (CONGESTION_FACTOR - 1.0)).clamp(0.0, 1.0);
+        tess.set_color(LinearColor::new(t, 1.0 - t, 0.0, 1.0));
+        let points: Vec<_> = lane.points.iter().map(|x| x.up(0.1)).collect();
+        tess.draw_polyline(&points, 1.0, false);
+    }
+
+    Some(())
+}
+
 pub fn debug_trainreservations(
     tess: &mut Tesselator<true>,
     sim: &Simulation,
@@ -518,6 +616,76 @@ pub fn debug_trainreservations(
     Some(())
 }
 
+/// Shows the nodes explored by the pathfinding query of the selected entity.
+/// Nodes go from green to red as their cost increases, the frontier is yellow.
+pub fn debug_pathfinder_search(
+    tess: &mut Tesselator<true>,
+    sim: &Simulation,
+    uiworld: &UiWorld,
+) -> Option<()> {
+    let map: &Map = &sim.map();
+    let selected = uiworld.read::<InspectedEntity>().e?;
+    let query = sim.world().it_any(selected)?.get_route()?.query;
+
+    let mut state = uiworld.write::<DebugState>();
+    let search = &mut state.path_search;
+    if !matches!(search, Some((e, tick, _)) if *e == selected && *tick == query.tick) {
+        *search = Some((selected, query.tick, query.debug(map)));
+    }
+    let (_, _, debug) = search.as_ref()?;
+
+    let max_cost = debug
+        .explored
+        .iter()
+        .chain(&debug.path)
+        .map(|(_, c)| *c)
+        .filter(|c| c.is_finite())
+        .fold(0.0f32, f32::max)
+        .max(0.001);
+    let cost_col = |c: f32| {
+        let t = (c / max_cost).clamp(0.0, 1.0);
+        LinearColor::new(t, 1.0 - t, 0.0, 0.5)
+    };
+
+    for (t, cost) in &debug.explored {
+        let Some(p) = t.raw_points(map) else {
+            continue;
+        };
+        tess.set_color(cost_col(*cost));
+        tess.draw_polyline(
+            &p.as_slice().iter().map(|x| x.up(0.2)).collect::<Vec<_>>(),
+            1.0,
+            false,
+        );
+    }
+
+    tess.set_color(LinearColor::YELLOW.a(0.5));
+    for (t, _) in &debug.frontier {
+        let Some(p) = t.raw_points(map) else {
+            continue;
+        };
+        tess.draw_polyline(
+            &p.as_slice().iter().map(|x| x.up(0.2)).collect::<Vec<_>>(),
+            1.0,
+            false,
+        );
+    }
+
+    for (t, cost) in &debug.path {
+        let Some(p) = t.raw_points(map) else {
+            continue;
+        };
+        tess.set_color(cost_col(*cost).a(1.0));
+        tess.draw_polyline(
+            &p.as_slice().iter().map(|x| x.up(0.25)).collect::<Vec<_>>(),
+            2.5,
+            false,
+        );
+        tess.draw_circle(p.last().up(0.3), 1.0);
+    }
+    Some(())
+}
+
 pub fn debug_pathfinder(
     tess: &mut Tesselator<true>,
     sim: &Simulation,
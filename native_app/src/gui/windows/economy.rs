@@ -1,11 +1,17 @@
+use crate::gui::inspect::brand_logo;
+use crate::gui::InspectedBuilding;
 use crate::uiworld::UiWorld;
+use common::i18n::tr;
 use common::timestep::UP_DT;
 use egui::{Align2, Color32, Ui};
-use egui_plot::{Line, PlotPoints};
+use egui_plot::{Line, PlotPoint, PlotPoints, Text, VLine};
 use geom::Color;
 use simulation::economy::{
-    EcoStats, ItemHistories, ItemRegistry, Market, HISTORY_SIZE, LEVEL_FREQS, LEVEL_NAMES,
+    CrisisFrequency, CrisisKind, EcoStats, EconomyCrises, Government, ItemHistories, ItemRegistry,
+    Market, Money, HISTORY_SIZE, LEVEL_FREQS, LEVEL_NAMES,
 };
+use simulation::souls::goods_company::GoodsCompanyRegistry;
+use simulation::utils::time::{GameInstant, GameTime, SECONDS_PER_DAY, SECONDS_PER_HOUR};
 use simulation::Simulation;
 use slotmapd::Key;
 use std::cmp::Reverse;
@@ -15,6 +21,8 @@ enum EconomyTab {
     ImportExports,
     InternalTrade,
     MarketPrices,
+    Companies,
+    Treasury,
 }
 
 #[derive(Copy, Clone, Default)]
@@ -38,6 +46,7 @@ pub fn economy(window: egui::Window<'_>, ui: &egui::Context, uiw: &mut UiWorld,
         tab: EconomyTab::ImportExports,
         hist_type: Default::default(),
     });
+    let uiw: &UiWorld = uiw;
     let mut state = uiw.write::<EconomyState>();
     let ecostats = sim.read::<EcoStats>();
     let registry = sim.read::<ItemRegistry>();
@@ -74,6 +83,18 @@ pub fn economy(window: egui::Window<'_>, ui: &egui::Context, uiw: &mut UiWorld,
                 {
                     state.tab = EconomyTab::MarketPrices;
                 }
+                if ui
+                    .selectable_label(matches!(state.tab, EconomyTab::Companies), "Companies")
+                    .clicked()
+                {
+                    state.tab = EconomyTab::Companies;
+                }
+                if ui
+                    .selectable_label(matches!(state.tab, EconomyTab::Treasury), tr("Treasury"))
+                    .clicked()
+                {
+                    state.tab = EconomyTab::Treasury;
+                }
             });
 
             ui.horizontal(|ui| {
@@ -170,7 +191,7 @@ pub fn economy(window: egui::Window<'_>, ui: &egui::Context, uiw: &mut UiWorld,
                                     first_zeros
                                 });
 
-                            let iname = &registry[id].name;
+                            let iname = tr(&registry[id].label);
 
                             ui.line(
                                 Line::new(PlotPoints::from_iter(heights))
@@ -180,7 +201,7 @@ pub fn economy(window: egui::Window<'_>, ui: &egui::Context, uiw: &mut UiWorld,
                                         (random_col.b * 255.0) as u8,
                                         (random_col.a * 255.0) as u8,
                                     ))
-                                    .name(iname),
+                                    .name(&iname),
                             );
                         }
                         ui.line(
@@ -230,27 +251,28 @@ pub fn economy(window: egui::Window<'_>, ui: &egui::Context, uiw: &mut UiWorld,
                             histories.sort_by_key(|(_, sum)| Reverse(*sum));
 
                             for (id, sum) in histories {
-                                let iname = &registry[id].name;
+                                let iname = tr(&registry[id].label);
                                 let mut enabled = filter.contains(&id);
-                                if ui.checkbox(&mut enabled, iname).changed() {
+                                if ui.checkbox(&mut enabled, &iname).changed() {
                                     if enabled {
                                         filter.insert(id);
                                     } else {
                                         filter.remove(&id);
                                     }
                                 }
-                                let suffix = match hist_type {
-                                    HistoryType::Items => "",
-                                    HistoryType::Money => "$",
+                                match hist_type {
+                                    HistoryType::Items => ui.label(sum.to_string()),
+                                    HistoryType::Money => {
+                                        ui.label(Money::new_bucks(sum).to_string())
+                                    }
                                 };
-                                ui.label(format!("{}{}", sum, suffix));
                                 ui.end_row();
                                 overall_total += sum;
                             }
                         });
                         if matches!(hist_type, HistoryType::Money) {
                             ui.separator();
-                            ui.label(format!("Total: {}$", overall_total));
+                            ui.label(format!("Total: {}", Money::new_bucks(overall_total)));
                         }
                     });
                 ui.data_mut(move |d| {
@@ -282,7 +304,17 @@ pub fn economy(window: egui::Window<'_>, ui: &egui::Context, uiw: &mut UiWorld,
                 }
                 EconomyTab::MarketPrices => {
                     ui.push_id(3, |ui| {
-                        render_market_prices(sim, ui);
+                        render_market_prices(sim, uiw, ui);
+                    });
+                }
+                EconomyTab::Companies => {
+                    ui.push_id(4, |ui| {
+                        render_companies(sim, uiw, ui);
+                    });
+                }
+                EconomyTab::Treasury => {
+                    ui.push_id(5, |ui| {
+                        render_treasury(sim, ui);
                     });
                 }
             }
@@ -290,12 +322,156 @@ pub fn economy(window: egui::Window<'_>, ui: &egui::Context, uiw: &mut UiWorld,
         });
 }
 
-fn render_market_prices(sim: &Simulation, ui: &mut Ui) {
+/// The money of the city over the last days, with the big expenses marked on the graph
+fn render_treasury(sim: &Simulation, ui: &mut Ui) {
+    let gov = sim.read::<Government>();
+    let now = sim.read::<GameTime>().timestamp;
+    let day = |timestamp: f64| timestamp / SECONDS_PER_DAY as f64;
+
+    ui.label(format!("{} {}", tr("Money:"), gov.money));
+
+    let points: Vec<[f64; 2]> = gov
+        .history()
+        .chain([(now, gov.money)])
+        .map(|(t, money)| [day(t), money.bucks() as f64])
+        .collect();
+    let top = points.iter().map(|[_, y]| *y).fold(0.0, f64::max);
+    let start = points[0][0];
+
+    egui_plot::Plot::new("treasury")
+        .height(250.0)
+        .include_y(0.0)
+        .allow_drag(false)
+        .allow_scroll(false)
+        .allow_zoom(false)
+        .allow_boxed_zoom(false)
+        .x_axis_formatter(|x, _, _| format!("{} {}", tr("Day"), x.floor()))
+        .label_formatter(|_, p| Money::new_bucks(p.y as i64).to_string())
+        .show(ui, |ui| {
+            ui.line(Line::new(PlotPoints::new(points)).color(Color32::from_rgb(90, 200, 90)));
+            for e in gov.big_expenses().filter(|e| day(e.timestamp) >= start) {
+                let x = day(e.timestamp);
+                ui.vline(VLine::new(x).color(Color32::from_rgb(230, 90, 90)));
+                ui.text(
+                    Text::new(
+                        PlotPoint::new(x, top),
+                        format!("{} {}", tr(&e.label), e.amount.compact()),
+                    )
+                    .anchor(Align2::LEFT_TOP)
+                    .color(Color32::from_rgb(230, 90, 90)),
+                );
+            }
+        });
+
+    ui.label(tr("Big expenses"));
+    egui::ScrollArea::vertical()
+        .max_height(150.0)
+        .show(ui, |ui| {
+            egui::Grid::new("big_expenses")
+                .striped(true)
+                .show(ui, |ui| {
+                    for e in gov.big_expenses().collect::<Vec<_>>().into_iter().rev() {
+                        ui.label(
+                            GameInstant {
+                                timestamp: e.timestamp,
+                            }
+                            .to_string(),
+                        );
+                        ui.label(tr(&e.label));
+                        ui.label(e.amount.to_string());
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+/// Every company of the city with its brand, clicking one inspects its building
+fn render_companies(sim: &Simulation, uiw: &UiWorld, ui: &mut Ui) {
+    let world = sim.world();
+    let map = sim.map();
+    let gregistry = sim.read::<GoodsCompanyRegistry>();
+
+    let mut companies: Vec<_> = world.companies.values().collect();
+    companies.sort_by(|a, b| a.comp.brand.name.cmp(&b.comp.brand.name));
+
+    egui::ScrollArea::vertical()
+        .max_height(420.0)
+        .show(ui, |ui| {
+            egui::Grid::new("companies").striped(true).show(ui, |ui| {
+                ui.label("Company");
+                ui.label("Trade");
+                ui.label("Workers");
+                ui.label("Trucks");
+                ui.end_row();
+
+                for c in companies {
+                    let Some(b) = map.buildings().get(c.comp.building) else {
+                        continue;
+                    };
+                    let trade = b
+                        .kind
+                        .as_goods_company()
+                        .and_then(|id| gregistry.descriptions.get(id))
+                        .map_or("???", |d| &d.name);
+
+                    ui.horizontal(|ui| {
+                        brand_logo(ui, &c.comp.brand);
+                        let name = if c.comp.brand.is_set() {
+                            c.comp.brand.name.as_str()
+                        } else {
+                            trade
+                        };
+                        if ui.link(name).clicked() {
+                            uiw.write::<InspectedBuilding>().e = Some(b.id);
+                            uiw.camera_mut().targetpos = b.door_pos;
+                        }
+                    });
+                    ui.label(tr(trade));
+                    ui.label(format!("{}/{}", c.workers.0.len(), c.comp.max_workers));
+                    ui.label(c.comp.fleet.len().to_string());
+                    ui.end_row();
+                }
+            });
+        });
+}
+
+fn render_market_prices(sim: &Simulation, uiw: &UiWorld, ui: &mut Ui) {
     let registry = sim.read::<ItemRegistry>();
     let market = sim.read::<Market>();
+    let crises = sim.read::<EconomyCrises>();
+    let time = *sim.read::<GameTime>();
+
+    ui.horizontal(|ui| {
+        ui.label("World crises");
+        for frequency in CrisisFrequency::ALL {
+            if ui
+                .selectable_label(crises.frequency == frequency, format!("{frequency:?}"))
+                .clicked()
+                && crises.frequency != frequency
+            {
+                uiw.commands().set_crisis_frequency(frequency);
+            }
+        }
+    });
+
+    for crisis in crises.active() {
+        let remaining = -crisis.ends_at.elapsed(&time) / SECONDS_PER_HOUR as f64;
+        let text = match crisis.kind {
+            CrisisKind::PriceShock { item, factor } => {
+                format!("{} price x{:.1}", tr(&registry[item].label), factor)
+            }
+            CrisisKind::FuelCrisis { factor } => format!("Fuel crisis x{:.1}", factor),
+        };
+        ui.colored_label(
+            Color32::from_rgb(255, 128, 50),
+            format!("{text} ({remaining:.0}h left)"),
+        );
+    }
+    ui.separator();
+
     egui::Grid::new("marketprices").show(ui, |ui| {
         for (id, market) in market.iter() {
-            ui.label(&registry[*id].name);
+            ui.label(tr(&registry[*id].label));
             ui.label(market.ext_value.to_string());
             ui.end_row();
         }
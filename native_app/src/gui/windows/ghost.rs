@@ -0,0 +1,250 @@
+use crate::gui::roadeditor::RoadEditorResource;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+use egui::{Color32, Grid, RichText};
+use geom::Vec3;
+use simulation::map::RoadID;
+use simulation::utils::ghost::{GhostComparison, GhostRun, TrafficCapture};
+use simulation::Simulation;
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Ticks of the ghost runs simulated between two updates of the window
+const GHOST_TICKS_PER_UPDATE: usize = 50;
+
+#[derive(Default)]
+pub struct GhostState {
+    corridor: BTreeSet<RoadID>,
+    capture: Option<TrafficCapture>,
+    comparison: Option<RunningComparison>,
+    error: Option<&'static str>,
+}
+
+/// What the window shows of a ghost run
+#[derive(Default, Clone)]
+struct RunProgress {
+    travel_times: Vec<Option<f32>>,
+    elapsed: f64,
+    finished: bool,
+    ghosts: Vec<Vec3>,
+}
+
+impl RunProgress {
+    fn new(run: &GhostRun) -> Self {
+        Self {
+            travel_times: run.travel_times().to_vec(),
+            elapsed: run.elapsed(),
+            finished: run.is_finished(),
+            ghosts: run.ghost_positions().collect(),
+        }
+    }
+}
+
+/// The comparison being run on its own thread, so the game keeps its frame rate
+struct RunningComparison {
+    /// The progress of the runs on the corridor before and after it was rebuilt
+    progress: Arc<Mutex<[RunProgress; 2]>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl RunningComparison {
+    fn start(mut comparison: GhostComparison) -> Self {
+        let progress = Arc::new(Mutex::new([
+            RunProgress::new(&comparison.before),
+            RunProgress::new(&comparison.after),
+        ]));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_progress = progress.clone();
+        let thread_stop = stop.clone();
+        std::thread::spawn(move || {
+            let mut schedule = Simulation::schedule();
+            while !thread_stop.load(Ordering::SeqCst) {
+                let finished = comparison.advance(&mut schedule, GHOST_TICKS_PER_UPDATE);
+                *thread_progress.lock().unwrap() = [
+                    RunProgress::new(&comparison.before),
+                    RunProgress::new(&comparison.after),
+                ];
+                if finished {
+                    break;
+                }
+            }
+        });
+
+        Self { progress, stop }
+    }
+}
+
+impl Drop for RunningComparison {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Ghost comparison window
+/// Captures the trips going through a corridor, then drives them again with ghost cars in a
+/// fork of the simulation before and after the corridor is rebuilt to compare travel times
+pub fn ghost(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+) {
+    let selected = uiworld.read::<RoadEditorResource>().inspect_road;
+
+    window.default_width(350.0).show(ui, |ui| {
+        let mut state = uiworld.write::<GhostState>();
+        let state = &mut *state;
+        ui.label(
+            "Capture the traffic of a corridor, rebuild it differently, \
+            then drive the same trips again to compare travel times.",
+        );
+        ui.separator();
+
+        ui.label(RichText::new("Corridor").strong());
+        ui.label(format!("{} roads", state.corridor.len()));
+        ui.horizontal(|ui| {
+            let add = ui.add_enabled(selected.is_some(), egui::Button::new("Add selected road"));
+            if add
+                .on_disabled_hover_text("Select a road with the road editor")
+                .clicked()
+            {
+                state.corridor.extend(selected);
+            }
+            if ui.button("Clear").clicked() {
+                state.corridor.clear();
+            }
+        });
+
+        let capture = ui.add_enabled(
+            !state.corridor.is_empty(),
+            egui::Button::new("Capture traffic"),
+        );
+        if capture.clicked() {
+            state.capture = Some(TrafficCapture::capture(sim, state.corridor.clone()));
+            state.comparison = None;
+            state.error = None;
+        }
+        draw_corridor(uiworld, sim, &state.corridor);
+
+        let Some(ref capture) = state.capture else {
+            return;
+        };
+        ui.label(format!("{} trips captured", capture.trips.len()));
+        if capture.trips.is_empty() {
+            ui.label("No car was going through the corridor.");
+            return;
+        }
+        ui.separator();
+
+        ui.label(
+            "Rebuild the corridor, then drive the captured trips on the corridor \
+            as it was and as it is now.",
+        );
+        if ui.button("Run comparison").clicked() {
+            state.comparison = None;
+            match GhostComparison::new(sim, capture) {
+                Ok(comparison) => {
+                    state.comparison = Some(RunningComparison::start(comparison));
+                    state.error = None;
+                }
+                Err(e) => state.error = Some(e),
+            }
+        }
+        if let Some(error) = state.error {
+            ui.colored_label(Color32::from_rgb(255, 100, 100), error);
+        }
+
+        let Some(ref comparison) = state.comparison else {
+            return;
+        };
+        let [before, after] = comparison.progress.lock().unwrap().clone();
+        for (name, run) in [("Before", &before), ("After", &after)] {
+            if !run.finished {
+                draw_ghosts(uiworld, run);
+            }
+            let arrived = run.travel_times.iter().flatten().count();
+            let status = if run.finished { "done" } else { "running" };
+            ui.label(format!(
+                "{name}: {arrived}/{} arrived after {:.0} min, {status}",
+                run.travel_times.len(),
+                run.elapsed / 60.0
+            ));
+        }
+
+        ui.separator();
+        results(ui, &before, &after);
+    });
+}
+
+fn results(ui: &mut egui::Ui, before: &RunProgress, after: &RunProgress) {
+    let trips: Vec<(Option<f32>, Option<f32>)> = before
+        .travel_times
+        .iter()
+        .copied()
+        .zip(after.travel_times.iter().copied())
+        .collect();
+
+    let deltas: Vec<f32> = trips.iter().filter_map(|&(b, a)| Some(a? - b?)).collect();
+    if !deltas.is_empty() {
+        let avg = deltas.iter().sum::<f32>() / deltas.len() as f32;
+        ui.label(RichText::new(format!("Average difference: {}", fmt_delta(avg))).strong());
+    }
+
+    let fmt = |x: Option<f32>| x.map_or("-".to_string(), |x| format!("{:.1} min", x / 60.0));
+    egui::ScrollArea::vertical()
+        .max_height(300.0)
+        .show(ui, |ui| {
+            Grid::new("ghost_results").striped(true).show(ui, |ui| {
+                ui.label("Trip");
+                ui.label("Before");
+                ui.label("After");
+                ui.label("Difference");
+                ui.end_row();
+
+                for (i, &(b, a)) in trips.iter().enumerate() {
+                    ui.label(format!("#{}", i + 1));
+                    ui.label(fmt(b));
+                    ui.label(fmt(a));
+                    match (b, a) {
+                        (Some(b), Some(a)) => {
+                            let col = if a <= b {
+                                Color32::from_rgb(100, 230, 100)
+                            } else {
+                                Color32::from_rgb(255, 100, 100)
+                            };
+                            ui.colored_label(col, fmt_delta(a - b));
+                        }
+                        _ => {
+                            ui.label("-");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+}
+
+/// Formats a difference of travel time in seconds, faster trips are negative
+fn fmt_delta(seconds: f32) -> String {
+    format!("{:+.1} min", seconds / 60.0)
+}
+
+fn draw_corridor(uiworld: &UiWorld, sim: &Simulation, corridor: &BTreeSet<RoadID>) {
+    let map = sim.map();
+    let col = simulation::config().gui_primary.a(0.7);
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    for road in corridor.iter().filter_map(|&r| map.roads().get(r)) {
+        let points: Vec<_> = road.points().iter().map(|p| p.up(0.5)).collect();
+        draw.polyline(points, road.width * 0.5, false).color(col);
+    }
+}
+
+fn draw_ghosts(uiworld: &UiWorld, run: &RunProgress) {
+    let col = simulation::config().gui_primary.a(0.5);
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    for &pos in &run.ghosts {
+        draw.circle(pos.up(1.0), 3.0).color(col);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::gui::inspect::building_link;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+use egui::{Grid, RichText};
+use geom::{lerp, Color};
+use simulation::souls::health::HealthStats;
+use simulation::Simulation;
+use std::collections::BTreeMap;
+
+#[derive(Default)]
+pub struct HealthWindowState {
+    show_health: bool,
+}
+
+/// Health window
+/// Shows how healthy the population is, how full the hospitals are and who died recently,
+/// so that the effects of pollution and noise on the people can be followed
+pub fn health(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+) {
+    let stats = sim.read::<HealthStats>();
+
+    window.default_width(300.0).show(ui, |ui| {
+        Grid::new("health_stats").striped(true).show(ui, |ui| {
+            ui.label("Average health");
+            ui.label(format!("{:.0}%", stats.average * 100.0));
+            ui.end_row();
+            ui.label("Sick");
+            ui.label(stats.sick.to_string());
+            ui.end_row();
+            ui.label("Hospitalized");
+            ui.label(format!("{}/{} beds", stats.hospitalized, stats.beds));
+            ui.end_row();
+            ui.label("Deaths");
+            ui.label(stats.deaths.to_string());
+            ui.end_row();
+        });
+
+        if stats.sick > stats.hospitalized {
+            ui.colored_label(
+                egui::Color32::from_rgb(230, 100, 80),
+                format!(
+                    "{} sick people found no hospital bed",
+                    stats.sick - stats.hospitalized
+                ),
+            );
+        }
+
+        ui.separator();
+        let mut state = uiworld.write::<HealthWindowState>();
+        ui.checkbox(&mut state.show_health, "Show health of the homes");
+        if state.show_health {
+            show_health(uiworld, sim);
+        }
+        drop(state);
+
+        if stats.recent_deaths.is_empty() {
+            return;
+        }
+        ui.separator();
+        ui.label(RichText::new("Recent deaths").strong());
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            Grid::new("health_deaths").striped(true).show(ui, |ui| {
+                for &(house, at) in stats.recent_deaths.iter().rev() {
+                    building_link(uiworld, sim, ui, house);
+                    ui.label(at.to_string());
+                    ui.end_row();
+                }
+            });
+        });
+    });
+}
+
+/// Colors the homes by the average health of their residents, from red to green
+fn show_health(uiworld: &UiWorld, sim: &Simulation) {
+    let mut homes: BTreeMap<_, (f32, u32)> = BTreeMap::new();
+    for h in sim.world().humans.values() {
+        let v = homes.entry(h.home.house).or_default();
+        v.0 += h.health.value;
+        v.1 += 1;
+    }
+
+    let map = sim.map();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    for (house, (total, n)) in homes {
+        let Some(b) = map.buildings().get(house) else {
+            continue;
+        };
+        let health = total / n as f32;
+        draw.obb(b.obb, b.height + 0.5).color(Color::new(
+            lerp(1.0, 0.2, health),
+            lerp(0.2, 0.9, health),
+            0.2,
+            0.7,
+        ));
+    }
+}
@@ -0,0 +1,158 @@
+use crate::gui::inspect::building_link;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+use common::descriptions::EducationLevel;
+use egui::{Grid, RichText};
+use geom::{lerp, Color};
+use simulation::map_dynamic::BuildingInfos;
+use simulation::Simulation;
+
+#[derive(Default)]
+pub struct LaborWindowState {
+    show_occupancy: bool,
+}
+
+#[derive(Default, Clone, Copy)]
+struct LevelStats {
+    people: usize,
+    studying: usize,
+    jobs: i32,
+    filled: usize,
+    qualified: usize,
+}
+
+/// Labor market window
+/// Compares the education of the population to the qualifications required by the companies,
+/// so that advanced industries slowed down by a lack of educated workers can be spotted
+pub fn labor_market(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+) {
+    let world = sim.world();
+    let mut stats = [LevelStats::default(); EducationLevel::ALL.len()];
+
+    for h in world.humans.values() {
+        let s = &mut stats[h.education.level as usize];
+        s.people += 1;
+        if h.education.progress > 0.0 {
+            s.studying += 1;
+        }
+    }
+
+    // companies missing qualified workers, the worst first
+    let mut short = vec![];
+    for c in world.companies.values() {
+        let levels = c
+            .workers
+            .0
+            .iter()
+            .filter_map(|&w| world.humans.get(w))
+            .map(|h| h.education.level);
+        let qualified = levels
+            .clone()
+            .filter(|&l| l >= c.comp.qualification)
+            .count();
+        let filled = levels.count();
+
+        let s = &mut stats[c.comp.qualification as usize];
+        s.jobs += c.comp.max_workers;
+        s.filled += filled;
+        s.qualified += qualified;
+
+        if qualified < filled {
+            short.push((c.comp.building, c.comp.qualification, qualified, filled));
+        }
+    }
+    short.sort_by_key(|&(_, _, qualified, filled)| std::cmp::Reverse(filled - qualified));
+
+    window.default_width(400.0).show(ui, |ui| {
+        Grid::new("labor_market").striped(true).show(ui, |ui| {
+            ui.label("Education");
+            ui.label("People");
+            ui.label("Studying");
+            ui.label("Jobs");
+            ui.label("Filled");
+            ui.label("Qualified");
+            ui.end_row();
+
+            for (level, s) in EducationLevel::ALL.iter().zip(stats.iter()) {
+                ui.label(format!("{:?}", level));
+                ui.label(s.people.to_string());
+                ui.label(s.studying.to_string());
+                ui.label(s.jobs.to_string());
+                ui.label(s.filled.to_string());
+                ui.label(s.qualified.to_string());
+                ui.end_row();
+            }
+        });
+
+        ui.separator();
+        occupancy(ui, uiworld, sim);
+        ui.separator();
+
+        if short.is_empty() {
+            ui.label("Every company has the workers it needs.");
+            return;
+        }
+        ui.label(RichText::new("Lacking qualified workers").strong());
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            Grid::new("labor_short").striped(true).show(ui, |ui| {
+                for &(building, qualification, qualified, filled) in &short {
+                    building_link(uiworld, sim, ui, building);
+                    ui.label(format!("{:?}", qualification));
+                    ui.label(format!("{}/{} qualified", qualified, filled));
+                    ui.end_row();
+                }
+            });
+        });
+    });
+}
+
+/// Shows how full the homes and workplaces are, and colors them on the map
+fn occupancy(ui: &mut egui::Ui, uiworld: &UiWorld, sim: &Simulation) {
+    let binfos = sim.read::<BuildingInfos>();
+    let map = sim.map();
+    let (mut people, mut capacity, mut overcrowded) = (0, 0, 0);
+    for b in map.buildings().values() {
+        let Some(info) = binfos.get(b.id) else {
+            continue;
+        };
+        people += info.headcount();
+        capacity += info.capacity.total();
+        if info.overcrowding() > 0.0 {
+            overcrowded += 1;
+        }
+    }
+    ui.label(format!(
+        "{}/{} homes and jobs taken, {} overcrowded buildings",
+        people, capacity, overcrowded
+    ));
+
+    let mut state = uiworld.write::<LaborWindowState>();
+    ui.checkbox(&mut state.show_occupancy, "Show occupancy");
+    if !state.show_occupancy {
+        return;
+    }
+    drop(state);
+
+    // empty buildings are blue, full ones green and overcrowded ones red
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    for b in map.buildings().values() {
+        let Some(occupancy) = binfos.get(b.id).and_then(|i| i.occupancy()) else {
+            continue;
+        };
+        let col = if occupancy > 1.0 {
+            Color::new(1.0, 0.2, 0.1, 0.7)
+        } else {
+            Color::new(
+                lerp(0.1, 0.2, occupancy),
+                lerp(0.4, 0.9, occupancy),
+                lerp(1.0, 0.3, occupancy),
+                0.7,
+            )
+        };
+        draw.obb(b.obb, b.height + 0.5).color(col);
+    }
+}
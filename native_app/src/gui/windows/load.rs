@@ -1,13 +1,37 @@
 #![allow(unused)]
 use crate::uiworld::{SaveLoadState, UiWorld};
+use common::saveload;
 use egui::{Color32, DroppedFile, Widget};
-use simulation::Simulation;
+use simulation::map::procgen::{sumo_demand_files, EdgeConnections, OsmNetwork};
+use simulation::utils::scenario::Scenario;
+use simulation::utils::time::GameTime;
+use simulation::world_command::WorldCommand;
+use simulation::{Simulation, SimulationOptions};
 use std::path::PathBuf;
 
-#[derive(Default)]
 pub struct LoadState {
     curpath: Option<PathBuf>,
     load_fail: String,
+    edge_connections: EdgeConnections,
+    /// Path of the OpenStreetMap extract to start from
+    osm_path: String,
+    /// Path of the SUMO network to start from
+    sumo_path: String,
+    /// Path of a SUMO route file whose demand is replayed on the network, optional
+    sumo_routes_path: String,
+}
+
+impl Default for LoadState {
+    fn default() -> Self {
+        Self {
+            curpath: None,
+            load_fail: String::new(),
+            edge_connections: SimulationOptions::default().edge_connections,
+            osm_path: String::new(),
+            sumo_path: String::new(),
+            sumo_routes_path: String::new(),
+        }
+    }
 }
 
 /// Load window
@@ -15,19 +39,100 @@ pub struct LoadState {
 pub fn load(window: egui::Window<'_>, ui: &egui::Context, uiw: &mut UiWorld, _: &Simulation) {
     window.show(ui, |ui| {
         let mut lstate = uiw.write::<LoadState>();
+        let replay_path = format!("{}/world_replay.json", saveload::save_dir());
 
         let has_save = ui.data_mut(|d| {
             *d.get_persisted_mut_or_insert_with(ui.make_persistent_id("has_save"), || {
-                std::fs::metadata("world/world_replay.json").is_ok()
+                std::fs::metadata(&replay_path).is_ok()
             })
         });
 
-        if ui.button("New Game").clicked() {
-            uiw.write::<SaveLoadState>().please_load_sim = Some(Simulation::new(true));
-        }
+        ui.horizontal(|ui| {
+            if ui.button("New Game").clicked() {
+                uiw.write::<SaveLoadState>().please_load_sim =
+                    Some(Simulation::new_with_options(SimulationOptions {
+                        edge_connections: lstate.edge_connections,
+                        ..Default::default()
+                    }));
+            }
+            egui::ComboBox::from_label("Map edge connections")
+                .selected_text(lstate.edge_connections.name())
+                .show_ui(ui, |ui| {
+                    for e in EdgeConnections::ALL {
+                        ui.selectable_value(&mut lstate.edge_connections, e, e.name());
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("Import OSM")
+                .on_hover_text(
+                    "Starts a new game on the roads of an OpenStreetMap extract (.osm.pbf)",
+                )
+                .clicked()
+            {
+                match OsmNetwork::from_file(&lstate.osm_path) {
+                    Ok(network) => {
+                        let mut sim = Simulation::new_with_options(SimulationOptions {
+                            edge_connections: EdgeConnections::None,
+                            ..Default::default()
+                        });
+                        WorldCommand::MapLoadOsm(Box::new(network)).apply(&mut sim);
+                        uiw.write::<SaveLoadState>().please_load_sim = Some(sim);
+                        lstate.load_fail.clear();
+                    }
+                    Err(e) => lstate.load_fail = format!("{}: {e}", lstate.osm_path),
+                }
+            }
+            ui.text_edit_singleline(&mut lstate.osm_path);
+        });
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("Import SUMO")
+                .on_hover_text("Starts a new game on the roads of a SUMO network (.net.xml)")
+                .clicked()
+            {
+                let imported = OsmNetwork::from_sumo_file(&lstate.sumo_path).and_then(|network| {
+                    if lstate.sumo_routes_path.is_empty() {
+                        return Ok((network, None));
+                    }
+                    let trips = sumo_demand_files(&lstate.sumo_path, &lstate.sumo_routes_path)?;
+                    Ok((network, Some(trips)))
+                });
+                match imported {
+                    Ok((network, demand)) => {
+                        let mut sim = Simulation::new_with_options(SimulationOptions {
+                            edge_connections: EdgeConnections::None,
+                            ..Default::default()
+                        });
+                        WorldCommand::MapLoadOsm(Box::new(network)).apply(&mut sim);
+                        if let Some(trips) = demand {
+                            let start = sim.read::<GameTime>().daytime;
+                            let scenario = Scenario::from_sumo_demand(
+                                lstate.sumo_routes_path.clone(),
+                                &trips,
+                                start,
+                            );
+                            WorldCommand::StartScenario(Box::new(scenario)).apply(&mut sim);
+                        }
+                        uiw.write::<SaveLoadState>().please_load_sim = Some(sim);
+                        lstate.load_fail.clear();
+                    }
+                    Err(e) => lstate.load_fail = format!("{}: {e}", lstate.sumo_path),
+                }
+            }
+            ui.text_edit_singleline(&mut lstate.sumo_path);
+        });
+        ui.horizontal(|ui| {
+            ui.label("SUMO routes (.rou.xml, optional)")
+                .on_hover_text("The trips of the route file are replayed on the imported network");
+            ui.text_edit_singleline(&mut lstate.sumo_routes_path);
+        });
 
         if has_save {
-            if ui.button("Load world/world_replay.json").clicked() {
+            if ui.button(format!("Load {replay_path}")).clicked() {
                 let replay = Simulation::load_replay_from_disk("world");
 
                 if let Some(replay) = replay {
@@ -39,7 +144,20 @@ pub fn load(window: egui::Window<'_>, ui: &egui::Context, uiw: &mut UiWorld, _:
                 }
             }
         } else {
-            ui.label("No replay found in world/world_replay.json");
+            ui.label(format!("No replay found in {replay_path}"));
+        }
+
+        let crash_save = crate::crash_save::CRASH_SAVE_NAME;
+        if Simulation::save_exists(crash_save)
+            && ui
+                .button(format!("Load emergency save {crash_save}"))
+                .on_hover_text("Saved when the game crashed, it might be broken")
+                .clicked()
+        {
+            match Simulation::load_from_disk(crate::crash_save::CRASH_SAVE_NAME) {
+                Some(sim) => uiw.write::<SaveLoadState>().please_load_sim = Some(sim),
+                None => lstate.load_fail = "Failed to load emergency save".to_string(),
+            }
         }
 
         if let Some(ref mut loading) = uiw.write::<SaveLoadState>().please_load {
@@ -0,0 +1,168 @@
+use crate::gui::inspect::building_link;
+use crate::uiworld::UiWorld;
+use egui::{Color32, Grid, RichText, Ui};
+use egui_plot::{Line, Plot, PlotPoints};
+use simulation::map::BuildingKind;
+use simulation::souls::freight_station::{FreightDayStats, STATS_DAYS};
+use simulation::souls::goods_company::{FreightMode, FLEET_BUSY};
+use simulation::Simulation;
+
+/// Logistics window
+/// Aggregates the delivery fleets of the companies and the daily statistics of every freight
+/// station so the stations where cargo piles up can be found at a glance
+pub fn logistics(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+) {
+    let map = sim.map();
+    let mut stations: Vec<_> = sim
+        .world()
+        .freight_stations
+        .values()
+        .filter(|f| {
+            map.buildings()
+                .get(f.f.building)
+                .map_or(false, |b| b.kind == BuildingKind::RailFreightStation)
+        })
+        .map(|f| &f.f)
+        .collect();
+    // the most congested stations first
+    stations.sort_by_key(|f| std::cmp::Reverse(f.waiting_cargo));
+
+    window.default_width(500.0).show(ui, |ui| {
+        fleets(ui, sim);
+        ui.separator();
+
+        if stations.is_empty() {
+            ui.label("No freight station built yet.");
+            return;
+        }
+
+        let mut total = vec![FreightDayStats::default(); STATS_DAYS];
+        for f in &stations {
+            let days = f.stats.days();
+            let off = STATS_DAYS - days.len();
+            for (i, d) in days.enumerate() {
+                total[off + i].tons_in += d.tons_in;
+                total[off + i].tons_out += d.tons_out;
+            }
+        }
+        ui.label(RichText::new(format!("City total, last {} days", STATS_DAYS)).strong());
+        ui.horizontal(|ui| {
+            ui.label("tons in/day");
+            sparkline(ui, "total_in", total.iter().map(|d| d.tons_in as f64));
+            ui.label("tons out/day");
+            sparkline(ui, "total_out", total.iter().map(|d| d.tons_out as f64));
+        });
+        ui.separator();
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            Grid::new("logistics").striped(true).show(ui, |ui| {
+                ui.label("Station");
+                ui.label("Tons in today");
+                ui.label("Tons out today");
+                ui.label("Turnaround");
+                ui.label("Yard");
+                ui.label("Tons out/day");
+                ui.label("Yard/day");
+                ui.end_row();
+
+                for f in &stations {
+                    let today = f.stats.today();
+                    building_link(uiworld, sim, ui, f.building);
+                    ui.label(today.tons_in.to_string());
+                    ui.label(today.tons_out.to_string());
+                    match today.avg_turnaround() {
+                        Some(t) => ui.label(format!("{:.0} min", t / 60.0)),
+                        None => ui.label("-"),
+                    };
+                    ui.label(format!("{} t", f.waiting_cargo));
+                    let id = f.building;
+                    sparkline(ui, ("out", id), f.stats.days().map(|d| d.tons_out as f64));
+                    sparkline(
+                        ui,
+                        ("yard", id),
+                        f.stats.days().map(|d| d.max_yard_cargo as f64),
+                    );
+                    ui.end_row();
+                }
+            });
+        });
+    });
+}
+
+/// Delivery fleets of the companies, busy fleets tell that the city needs more trucks or more
+/// freight rail
+fn fleets(ui: &mut Ui, sim: &Simulation) {
+    let mut n_fleets = 0;
+    let mut n_trucks = 0;
+    let mut n_busy = 0;
+    let mut n_rail = 0;
+    let mut utilization = 0.0;
+    let mut waiting = 0;
+    for c in sim.world().companies.values() {
+        if c.comp.fleet.is_empty() {
+            continue;
+        }
+        n_fleets += 1;
+        n_trucks += c.comp.fleet.len();
+        utilization += c.comp.fleet_utilization;
+        waiting += c.sold.0.len();
+        if c.comp.fleet_utilization > FLEET_BUSY {
+            n_busy += 1;
+        }
+        if c.comp.freight_mode == FreightMode::Rail {
+            n_rail += 1;
+        }
+    }
+
+    ui.label(RichText::new("Company fleets").strong());
+    if n_fleets == 0 {
+        ui.label("No company has delivery trucks yet.");
+        return;
+    }
+    Grid::new("fleets").show(ui, |ui| {
+        ui.label("Trucks");
+        ui.label(n_trucks.to_string());
+        ui.end_row();
+        ui.label("Average utilization");
+        ui.label(format!("{:.0}%", utilization / n_fleets as f32 * 100.0));
+        ui.end_row();
+        ui.label("Deliveries waiting");
+        ui.label(waiting.to_string());
+        ui.end_row();
+        ui.label("Shipping by rail");
+        ui.label(format!("{n_rail}/{n_fleets} companies"));
+        ui.end_row();
+    });
+    if n_busy * 2 > n_fleets {
+        ui.colored_label(
+            Color32::YELLOW,
+            format!(
+                "{n_busy}/{n_fleets} fleets are always busy, the city needs more trucks \
+                or more freight rail"
+            ),
+        );
+    }
+}
+
+/// Small plot without axes showing the trend of the values
+fn sparkline(ui: &mut Ui, id: impl std::hash::Hash, values: impl Iterator<Item = f64>) {
+    let points: PlotPoints = values.enumerate().map(|(i, v)| [i as f64, v]).collect();
+    Plot::new(ui.id().with(id))
+        .width(80.0)
+        .height(20.0)
+        .show_axes(false)
+        .show_grid(false)
+        .show_background(false)
+        .show_x(false)
+        .show_y(false)
+        .include_y(0.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .show(ui, |ui| ui.line(Line::new(points)));
+}
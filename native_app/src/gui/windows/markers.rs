@@ -0,0 +1,125 @@
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::OrbitCamera;
+use crate::uiworld::UiWorld;
+use egui::{Color32, Grid, RichText};
+use egui_inspect::{Inspect, InspectArgs};
+use geom::Color;
+use simulation::map_dynamic::{MapMarker, MapMarkers, MarkerIcon};
+use simulation::Simulation;
+
+pub struct MarkersState {
+    name: String,
+    icon: MarkerIcon,
+    color: Color,
+}
+
+impl Default for MarkersState {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            icon: MarkerIcon::Pin,
+            color: Color::ORANGE,
+        }
+    }
+}
+
+/// Markers window
+/// Places named markers on the map and jumps to them
+pub fn markers(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+) {
+    let markers = sim.read::<MapMarkers>();
+
+    window.default_width(300.0).show(ui, |ui| {
+        let mut state = uiworld.write::<MarkersState>();
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut state.name);
+            egui::ComboBox::from_id_source("marker_icon")
+                .width(40.0)
+                .selected_text(state.icon.symbol())
+                .show_ui(ui, |ui| {
+                    for icon in MarkerIcon::ALL {
+                        ui.selectable_value(&mut state.icon, icon, icon.symbol());
+                    }
+                });
+            <Color as Inspect<Color>>::render_mut(
+                &mut state.color,
+                "",
+                ui,
+                &InspectArgs::default(),
+            );
+        });
+        if ui
+            .button("Add marker at camera")
+            .on_hover_text("The marker is placed at the center of the screen")
+            .clicked()
+        {
+            let name = if state.name.is_empty() {
+                format!("Marker {}", markers.len() + 1)
+            } else {
+                std::mem::take(&mut state.name)
+            };
+            let marker = MapMarker {
+                name,
+                pos: uiworld.read::<OrbitCamera>().targetpos,
+                icon: state.icon,
+                color: state.color,
+            };
+            uiworld.commands().add_map_marker(marker);
+        }
+        drop(state);
+        ui.separator();
+
+        if markers.is_empty() {
+            ui.label("No marker placed yet.");
+            return;
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            Grid::new("markers").striped(true).show(ui, |ui| {
+                for (id, marker) in markers.iter() {
+                    ui.label(RichText::new(marker.icon.symbol()).color(color32(marker.color)));
+                    let mut name = marker.name.clone();
+                    if ui.text_edit_singleline(&mut name).changed() {
+                        uiworld.commands().update_map_marker(
+                            id,
+                            MapMarker {
+                                name,
+                                ..marker.clone()
+                            },
+                        );
+                    }
+                    if ui.small_button("Go").clicked() {
+                        uiworld.camera_mut().targetpos = marker.pos;
+                    }
+                    if ui.small_button("Remove").clicked() {
+                        uiworld.commands().remove_map_marker(id);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    });
+}
+
+/// Draws the markers on the map
+pub fn draw_markers(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("markers::draw_markers");
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    for (_, marker) in sim.read::<MapMarkers>().iter() {
+        let pos = marker.pos.up(0.5);
+        draw.circle(pos, 6.0).color(marker.color);
+        draw.stroke_circle(pos, 6.0, 1.5).color(Color::WHITE);
+    }
+}
+
+fn color32(c: Color) -> Color32 {
+    Color32::from_rgba_unmultiplied(
+        (c.r * 255.0) as u8,
+        (c.g * 255.0) as u8,
+        (c.b * 255.0) as u8,
+        255,
+    )
+}
@@ -0,0 +1,169 @@
+use crate::uiworld::UiWorld;
+use egui::{ComboBox, DragValue, Grid};
+use egui_inspect::{Inspect, InspectArgs};
+use geom::Color;
+use simulation::map::{BuildingID, BuildingKind};
+use simulation::transportation::metro::{
+    MetroLine, MetroLines, MAX_METRO_TRAINS_PER_LINE, MIN_METRO_HEADWAY,
+};
+use simulation::utils::time::GameInstant;
+use simulation::Simulation;
+
+pub struct MetroLinesState {
+    name: String,
+    color: Color,
+}
+
+impl Default for MetroLinesState {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            color: Color::new(0.2, 0.4, 0.8, 1.0),
+        }
+    }
+}
+
+/// Metro lines window
+/// Creates metro lines, picks the stations they serve and the schedule of their trains
+pub fn metrolines(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+) {
+    let lines = sim.read::<MetroLines>();
+    let map = sim.map();
+    let stations: Vec<BuildingID> = map
+        .buildings()
+        .values()
+        .filter(|b| b.kind == BuildingKind::MetroStation)
+        .map(|b| b.id)
+        .collect();
+    let station_name = |id: BuildingID| match stations.iter().position(|&s| s == id) {
+        Some(i) => format!("Station {}", i + 1),
+        None => "?".to_string(),
+    };
+
+    window.default_width(350.0).show(ui, |ui| {
+        let mut state = uiworld.write::<MetroLinesState>();
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut state.name);
+            <Color as Inspect<Color>>::render_mut(
+                &mut state.color,
+                "",
+                ui,
+                &InspectArgs::default(),
+            );
+        });
+        if ui
+            .button("New line")
+            .on_hover_text("Metro stations are built above the tunnels of the underground layer")
+            .clicked()
+        {
+            let name = if state.name.is_empty() {
+                format!("Metro {}", lines.len() + 1)
+            } else {
+                std::mem::take(&mut state.name)
+            };
+            uiworld.commands().add_metro_line(MetroLine {
+                name,
+                color: state.color,
+                stations: vec![],
+                n_trains: 1,
+                headway: 5.0 * 60.0,
+            });
+        }
+        drop(state);
+        ui.separator();
+
+        if lines.is_empty() {
+            ui.label("No metro line yet.");
+            return;
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (id, line) in lines.iter() {
+                ui.push_id(id.0, |ui| {
+                    let mut updated = line.clone();
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut updated.name);
+                        <Color as Inspect<Color>>::render_mut(
+                            &mut updated.color,
+                            "",
+                            ui,
+                            &InspectArgs::default(),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Trains");
+                        ui.add(
+                            DragValue::new(&mut updated.n_trains)
+                                .clamp_range(0..=MAX_METRO_TRAINS_PER_LINE),
+                        );
+                        let mut minutes = updated.headway / 60.0;
+                        ui.label("every");
+                        if ui
+                            .add(
+                                DragValue::new(&mut minutes)
+                                    .clamp_range(MIN_METRO_HEADWAY / 60.0..=120.0)
+                                    .speed(0.5)
+                                    .suffix(" min"),
+                            )
+                            .changed()
+                        {
+                            updated.headway = minutes * 60.0;
+                        }
+                        if ui.small_button("Remove").clicked() {
+                            uiworld.commands().remove_metro_line(id);
+                        }
+                    });
+                    if line.is_running() {
+                        let next = GameInstant {
+                            timestamp: lines.next_departure(id),
+                        };
+                        ui.label(format!("Next departure: {}", next));
+                        let riders: usize = sim
+                            .world()
+                            .trains
+                            .values()
+                            .filter_map(|t| t.metro.as_ref())
+                            .filter(|m| m.line == id)
+                            .map(|m| m.passengers.len())
+                            .sum();
+                        ui.label(format!("Passengers: {}", riders));
+                    } else {
+                        ui.label("Not running: it needs a train and two stations");
+                    }
+                    Grid::new("stations").striped(true).show(ui, |ui| {
+                        let mut removed = None;
+                        for (i, &station) in line.stations.iter().enumerate() {
+                            ui.label(format!("{}. {}", i + 1, station_name(station)));
+                            if ui.small_button("Remove").clicked() {
+                                removed = Some(i);
+                            }
+                            ui.end_row();
+                        }
+                        if let Some(i) = removed {
+                            updated.stations.remove(i);
+                        }
+                    });
+                    ComboBox::from_id_source("add_station")
+                        .selected_text("Add station")
+                        .show_ui(ui, |ui| {
+                            for &station in &stations {
+                                if line.stations.last() == Some(&station) {
+                                    continue;
+                                }
+                                if ui.selectable_label(false, station_name(station)).clicked() {
+                                    updated.stations.push(station);
+                                }
+                            }
+                        });
+                    if updated != *line {
+                        uiworld.commands().update_metro_line(id, updated);
+                    }
+                });
+                ui.separator();
+            }
+        });
+    });
+}
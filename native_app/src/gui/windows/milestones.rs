@@ -0,0 +1,96 @@
+use crate::uiworld::UiWorld;
+use egui::{Color32, ProgressBar};
+use simulation::economy::{Government, Milestones, MILESTONES};
+use simulation::map::BuildingKind;
+use simulation::souls::goods_company::GoodsCompanyRegistry;
+use simulation::Simulation;
+
+/// Milestones window
+/// Shows the progression of the city and the buildings unlocked by each milestone
+pub fn milestones(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+) {
+    let milestones = sim.read::<Milestones>();
+    let companies = sim.read::<GoodsCompanyRegistry>();
+    let population = sim.world().humans.len();
+    let money = sim.read::<Government>().money;
+
+    window.default_width(300.0).show(ui, |ui| {
+        let mut sandbox = !milestones.enabled;
+        if ui
+            .checkbox(&mut sandbox, "Sandbox (everything unlocked)")
+            .changed()
+        {
+            uiworld.commands().set_milestones_enabled(!sandbox);
+        }
+        if sandbox {
+            return;
+        }
+
+        if let Some(next) = milestones.next() {
+            ui.label(format!("Next milestone: {}", next.name));
+            if next.population > 0 {
+                ui.add(
+                    ProgressBar::new(population as f32 / next.population as f32)
+                        .text(format!("population {}/{}", population, next.population)),
+                );
+            }
+            if next.money.bucks() > 0 {
+                ui.add(
+                    ProgressBar::new((money.bucks() as f32 / next.money.bucks() as f32).max(0.0))
+                        .text(format!("money {}/{}", money, next.money)),
+                );
+            }
+        }
+        ui.separator();
+
+        for (i, m) in MILESTONES.iter().enumerate() {
+            let reached = i <= milestones.reached();
+            let col = if reached {
+                Color32::from_rgb(100, 230, 100)
+            } else {
+                Color32::GRAY
+            };
+            ui.colored_label(col, m.name);
+
+            let unlocks: Vec<String> = [
+                BuildingKind::RailFreightStation,
+                BuildingKind::TrainStation,
+                BuildingKind::SnowPlowDepot,
+                BuildingKind::Airport,
+                BuildingKind::Harbor,
+                BuildingKind::MetroStation,
+                BuildingKind::ParkingLot,
+                BuildingKind::EventVenue,
+            ]
+            .into_iter()
+            .filter(|&kind| Milestones::required(kind, &companies) == i)
+            .map(|kind| format!("{kind:?}"))
+            .chain(
+                companies
+                    .descriptions
+                    .iter()
+                    .filter(|(id, _)| {
+                        Milestones::required(BuildingKind::GoodsCompany(*id), &companies) == i
+                    })
+                    .map(|(_, descr)| descr.name.clone()),
+            )
+            .collect();
+
+            ui.indent(("milestone", i), |ui| {
+                if i > 0 {
+                    ui.label(format!("requires {} population", m.population));
+                    if m.money.bucks() > 0 {
+                        ui.label(format!("requires {} in the bank", m.money));
+                    }
+                }
+                if !unlocks.is_empty() && i > 0 {
+                    ui.label(format!("unlocks: {}", unlocks.join(", ")));
+                }
+            });
+        }
+    });
+}
@@ -1,3 +1,4 @@
+use common::i18n::tr;
 use egui::{Context, Ui};
 use serde::{Deserialize, Serialize};
 
@@ -5,13 +6,28 @@ use crate::inputmap::{InputAction, InputMap};
 use crate::uiworld::UiWorld;
 use simulation::Simulation;
 
+pub mod arterials;
+pub mod buslines;
 mod config;
+mod constructions;
 pub mod debug;
 mod economy;
+pub mod ghost;
+pub mod health;
+pub mod labor;
 pub mod load;
+mod logistics;
+pub mod markers;
+pub mod metrolines;
+mod milestones;
 #[cfg(feature = "multiplayer")]
 pub mod network;
+mod policies;
+mod profiler;
+pub mod scenario;
 pub mod settings;
+mod timelapse;
+pub mod traffic;
 
 pub trait GUIWindow: Send + Sync {
     fn render_window(
@@ -58,12 +74,27 @@ impl Default for GUIWindows {
             opened: vec![],
         };
         s.insert("Economy", economy::economy, false);
+        s.insert("Constructions", constructions::constructions, false);
+        s.insert("Milestones", milestones::milestones, false);
+        s.insert("Traffic", traffic::traffic, false);
+        s.insert("Logistics", logistics::logistics, false);
+        s.insert("Labor market", labor::labor_market, false);
+        s.insert("Health", health::health, false);
+        s.insert("Policies", policies::policies, false);
+        s.insert("Markers", markers::markers, false);
+        s.insert("Bus lines", buslines::buslines, false);
+        s.insert("Metro lines", metrolines::metrolines, false);
+        s.insert("Time-lapse", timelapse::timelapse, false);
         s.insert("Config", config::config, false);
         s.insert("Debug", debug::debug, false);
+        s.insert("Profiler", profiler::profiler, false);
         s.insert("Settings", settings::settings, false);
         #[cfg(feature = "multiplayer")]
         s.insert("Network", network::network, false);
         s.insert("Load", load::load, false);
+        s.insert("Arterials", arterials::arterials, false);
+        s.insert("Ghost comparison", ghost::ghost, false);
+        s.insert("Scenario results", scenario::scenario, false);
         s
     }
 }
@@ -79,13 +110,28 @@ impl GUIWindows {
         }
     }
 
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.windows.iter().map(|w| w.name)
+    }
+
+    pub fn open(&mut self, name: &str) {
+        if self.opened.len() < self.windows.len() {
+            self.opened.resize(self.windows.len(), false);
+        }
+        for (opened, w) in self.opened.iter_mut().zip(self.windows.iter()) {
+            if w.name == name {
+                *opened = true;
+            }
+        }
+    }
+
     pub fn menu(&mut self, ui: &mut Ui) {
         if self.opened.len() < self.windows.len() {
             self.opened
                 .extend(std::iter::repeat(false).take(self.windows.len() - self.opened.len()))
         }
         for (opened, w) in self.opened.iter_mut().zip(self.windows.iter()) {
-            *opened ^= ui.selectable_label(*opened, w.name).clicked();
+            *opened ^= ui.selectable_label(*opened, tr(w.name)).clicked();
         }
     }
 
@@ -104,7 +150,11 @@ impl GUIWindows {
         }
         for (ws, opened) in self.windows.iter_mut().zip(self.opened.iter_mut()) {
             if *opened {
-                ws.w.render_window(egui::Window::new(ws.name).open(opened), ui, uiworld, sim);
+                // the id doesn't depend on the language so the window keeps its place
+                let window = egui::Window::new(tr(ws.name))
+                    .id(egui::Id::new(ws.name))
+                    .open(opened);
+                ws.w.render_window(window, ui, uiworld, sim);
             }
         }
     }
@@ -0,0 +1,99 @@
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::OrbitCamera;
+use crate::uiworld::UiWorld;
+use egui::{Grid, RichText};
+use simulation::map::procgen::BuildingArchetypes;
+use simulation::map_dynamic::{District, Policies, Policy, DISTRICT_SIZE};
+use simulation::Simulation;
+
+/// Policies window
+/// Enacts policies and sets the building theme of the district under the camera, and lists the
+/// policies enacted in the city
+pub fn policies(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+) {
+    let policies = sim.read::<Policies>();
+    let campos = uiworld.read::<OrbitCamera>().targetpos;
+    let district = District::of(campos.xy());
+
+    window.default_width(300.0).show(ui, |ui| {
+        ui.label(RichText::new(format!("District {} (under the camera)", district)).strong());
+        for policy in Policy::ALL {
+            let mut enacted = policies.is_enacted(district, policy);
+            if ui
+                .checkbox(
+                    &mut enacted,
+                    format!("{} ({}/day)", policy.name(), policy.daily_cost()),
+                )
+                .on_hover_text(policy.description())
+                .changed()
+            {
+                uiworld
+                    .commands()
+                    .set_district_policy(district, policy, enacted);
+            }
+        }
+        ui.horizontal(|ui| {
+            let archetypes = sim.read::<BuildingArchetypes>();
+            let current = policies.theme(district);
+            let mut theme = current.map(str::to_string);
+            egui::ComboBox::from_id_source("district_theme")
+                .selected_text(current.unwrap_or("No theme"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut theme, None, "No theme");
+                    for t in archetypes.themes() {
+                        ui.selectable_value(&mut theme, Some(t.name.clone()), &t.name)
+                            .on_hover_text(&t.description);
+                    }
+                });
+            ui.label("Building theme");
+            if theme.as_deref() != current {
+                uiworld.commands().set_district_theme(district, theme);
+            }
+        });
+        ui.separator();
+
+        ui.label(RichText::new("Enacted policies").strong());
+        if policies.iter().next().is_none() {
+            ui.label("No policy enacted yet.");
+            return;
+        }
+        Grid::new("policies").striped(true).show(ui, |ui| {
+            for (d, policy) in policies.iter() {
+                ui.label(d.to_string());
+                ui.label(policy.name());
+                ui.label(format!("{}/day", policy.daily_cost()));
+                if ui.small_button("Repeal").clicked() {
+                    uiworld.commands().set_district_policy(d, policy, false);
+                }
+                ui.end_row();
+            }
+        });
+        ui.label(format!("Total: {}/day", policies.daily_cost()));
+    });
+
+    draw_district(uiworld, district, campos.z);
+}
+
+/// Outlines the district the policies apply to
+fn draw_district(uiworld: &UiWorld, district: District, z: f32) {
+    let c = district.center();
+    let h = DISTRICT_SIZE * 0.5;
+    let corners = [
+        c + geom::vec2(-h, -h),
+        c + geom::vec2(h, -h),
+        c + geom::vec2(h, h),
+        c + geom::vec2(-h, h),
+    ];
+    uiworld
+        .write::<ImmediateDraw>()
+        .polyline(
+            corners.iter().map(|p| p.z(z + 1.0)).collect::<Vec<_>>(),
+            5.0,
+            true,
+        )
+        .color(simulation::config().gui_primary.a(0.6));
+}
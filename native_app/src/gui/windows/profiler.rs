@@ -0,0 +1,72 @@
+use common::i18n::tr;
+use egui::{Context, Widget};
+
+use simulation::Simulation;
+
+use crate::game_loop::Timings;
+use crate::gui::slow_ticks::SlowTicks;
+use crate::gui::windows::settings::Settings;
+use crate::uiworld::UiWorld;
+
+/// Profiler window
+/// Shows the time taken by each system of the simulation and the last slow ticks reported
+pub fn profiler(window: egui::Window<'_>, ui: &Context, uiworld: &mut UiWorld, _: &Simulation) {
+    window.default_size([350.0, 500.0]).show(ui, |ui| {
+        {
+            let mut settings = uiworld.write::<Settings>();
+            ui.horizontal(|ui| {
+                egui::DragValue::new(&mut settings.slow_tick_budget_ms)
+                    .clamp_range(0.0..=1000.0)
+                    .suffix("ms")
+                    .ui(ui);
+                ui.label(tr("Slow tick budget"))
+                    .on_hover_text(tr("Ticks taking longer are reported, 0 to disable"));
+            });
+        }
+
+        let timings = uiworld.read::<Timings>();
+        ui.label(format!(
+            "{} {:.1}ms",
+            tr("World update time:"),
+            timings.world_update.avg() * 1000.0
+        ));
+
+        ui.separator();
+        let max = timings
+            .per_game_system
+            .first()
+            .map_or(1.0, |&(_, t)| t.max(0.001));
+        for &(ref name, time) in &timings.per_game_system {
+            egui::ProgressBar::new(time / max)
+                .text(format!("{name}: {time:.3}ms"))
+                .ui(ui);
+        }
+        drop(timings);
+
+        ui.separator();
+        let slow = uiworld.read::<SlowTicks>();
+        if slow.reports.is_empty() {
+            ui.label(tr("No slow tick reported"));
+        }
+        for report in slow.reports.iter().rev() {
+            egui::CollapsingHeader::new(format!(
+                "{} {}: {:.1}ms",
+                tr("Tick"),
+                report.tick,
+                report.total_ms
+            ))
+            .id_source(report.tick)
+            .show(ui, |ui| {
+                for s in &report.systems {
+                    ui.label(format!(
+                        "{}: {:.2}ms ({} {:.2}ms)",
+                        s.name,
+                        s.ms,
+                        tr("avg"),
+                        s.avg_ms
+                    ));
+                }
+            });
+        }
+    });
+}
@@ -0,0 +1,218 @@
+use common::i18n::tr;
+use common::saveload::{Encoder, JSONPretty, JSON};
+use egui::{Color32, Grid, RichText};
+
+use simulation::utils::scenario::Scenario;
+use simulation::utils::scenario_result::ScenarioResult;
+use simulation::utils::time::Tick;
+use simulation::Simulation;
+
+use crate::rendering::OrbitCamera;
+use crate::uiworld::UiWorld;
+
+#[derive(Default)]
+pub struct ScenarioResults {
+    /// Name written in the exported results
+    pub player: String,
+    /// The finished scenario whose result was last exported
+    exported: Option<(u64, Tick)>,
+    exported_file: String,
+    import_path: String,
+    /// Results of other players for the same scenario
+    imported: Vec<ScenarioResult>,
+    error: Option<String>,
+}
+
+impl ScenarioResults {
+    fn export(&mut self, result: &ScenarioResult) {
+        let mut result = result.clone();
+        result.player = self.player_name();
+        result.update_checksum();
+        JSONPretty::save(&result, &result.file_name());
+        self.exported_file = JSONPretty::filename(&result.file_name());
+        self.exported = Some((result.scenario_id, result.finished_at));
+    }
+
+    fn player_name(&self) -> String {
+        if self.player.trim().is_empty() {
+            return "Player".to_string();
+        }
+        self.player.trim().to_string()
+    }
+
+    fn import(&mut self, own: &ScenarioResult, path: &str) {
+        let result = common::saveload::load_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| {
+                JSON::decode::<ScenarioResult>(text.as_bytes()).map_err(|e| e.to_string())
+            });
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => {
+                self.error = Some(format!("{path}: {e}"));
+                return;
+            }
+        };
+        if !result.checksum_matches() {
+            self.error = Some(format!("{path}: {}", tr("the result was edited")));
+            return;
+        }
+        if !own.comparable(&result) {
+            self.error = Some(format!(
+                "{path}: {}",
+                tr("the result is from another scenario")
+            ));
+            return;
+        }
+        self.imported.retain(|r| r.player != result.player);
+        self.imported.push(result);
+        self.error = None;
+    }
+
+    /// Imports the results of the same scenario found in the save folder
+    fn scan_save_dir(&mut self, own: &ScenarioResult) {
+        let prefix = format!("scenario_result_{:016x}_", own.scenario_id);
+        let Ok(entries) = std::fs::read_dir(common::saveload::save_dir()) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(&prefix) || !name.ends_with(".json") {
+                continue;
+            }
+            let path = entry.path().to_string_lossy().to_string();
+            if path == self.exported_file {
+                continue;
+            }
+            self.import(own, &path);
+        }
+    }
+}
+
+/// Exports the result of the scenario once it is finished
+pub fn export_scenario_result(sim: &Simulation, uiworld: &mut UiWorld) {
+    let scenario = sim.read::<Scenario>();
+    let Some(ref result) = scenario.result else {
+        return;
+    };
+    let mut state = uiworld.write::<ScenarioResults>();
+    if state.exported == Some((result.scenario_id, result.finished_at)) {
+        return;
+    }
+    state.export(result);
+}
+
+/// Scenario results window
+/// Shows the result of the finished scenario and compares it with the results of friends
+pub fn scenario(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+) {
+    window.default_width(400.0).show(ui, |ui| {
+        let scenario = sim.read::<Scenario>();
+        if scenario.events.is_empty() && scenario.tutorial.is_empty() {
+            ui.label(tr("No scenario is running."));
+            if ui
+                .button(tr("Start tutorial"))
+                .on_hover_text(tr("Learn the basics around the camera"))
+                .clicked()
+            {
+                let center = uiworld.read::<OrbitCamera>().targetpos;
+                uiworld
+                    .commands()
+                    .start_scenario(Scenario::tutorial(center));
+            }
+            return;
+        }
+        ui.label(RichText::new(&scenario.name).strong());
+        let Some(ref own) = scenario.result else {
+            if scenario.tutorial.is_empty() {
+                ui.label(format!(
+                    "{} {}/{}",
+                    tr("Events played:"),
+                    scenario.next,
+                    scenario.events.len()
+                ));
+            } else {
+                ui.label(format!(
+                    "{} {}/{}",
+                    tr("Step"),
+                    scenario.step.min(scenario.tutorial.len()),
+                    scenario.tutorial.len()
+                ));
+            }
+            ui.label(tr("The result is exported when the scenario is finished."));
+            return;
+        };
+
+        let mut state = uiworld.write::<ScenarioResults>();
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut state.player);
+            ui.label(tr("Player name"));
+        });
+        if ui.button(tr("Export again")).clicked() {
+            state.export(own);
+        }
+        ui.label(format!("{} {}", tr("Exported to"), state.exported_file));
+
+        ui.separator();
+        ui.label(RichText::new(tr("Compare with friends")).strong());
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut state.import_path);
+            if ui.button(tr("Import")).clicked() {
+                let path = state.import_path.clone();
+                state.import(own, &path);
+            }
+        });
+        if ui
+            .button(tr("Find in save folder"))
+            .on_hover_text(tr(
+                "Imports the result files of this scenario put in the save folder",
+            ))
+            .clicked()
+        {
+            state.scan_save_dir(own);
+        }
+        if let Some(ref error) = state.error {
+            ui.label(RichText::new(error).color(Color32::from_rgb(230, 90, 90)));
+        }
+
+        let mut own = own.clone();
+        own.player = state.player_name();
+        let mut rows: Vec<(&ScenarioResult, bool)> = state
+            .imported
+            .iter()
+            .map(|r| (r, false))
+            .chain([(&own, true)])
+            .collect();
+        rows.sort_by_key(|(r, _)| std::cmp::Reverse(r.stats.money));
+
+        ui.separator();
+        Grid::new("scenario_results").striped(true).show(ui, |ui| {
+            ui.label(tr("Player"));
+            ui.label(tr("Money"));
+            ui.label(tr("Population"));
+            ui.label(tr("Trips"));
+            ui.label(tr("Commands"));
+            ui.end_row();
+            for (r, mine) in rows {
+                let text = |s: String| {
+                    let text = RichText::new(s);
+                    if mine {
+                        text.strong()
+                    } else {
+                        text
+                    }
+                };
+                ui.label(text(r.player.clone()));
+                ui.label(text(r.stats.money.to_string()));
+                ui.label(text(r.stats.population.to_string()));
+                ui.label(text(r.stats.n_trips.to_string()));
+                ui.label(text(r.n_commands.to_string()));
+                ui.end_row();
+            }
+        });
+    });
+}
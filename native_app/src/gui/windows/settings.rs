@@ -1,6 +1,8 @@
 use crate::game_loop::Timings;
 use crate::inputmap::{Bindings, InputMap};
+use crate::profiles::Profiles;
 use crate::uiworld::UiWorld;
+use common::i18n::tr;
 use common::saveload::Encoder;
 use egui::{Align2, Context, Widget};
 use egui_extras::Column;
@@ -11,7 +13,7 @@ use std::time::{Duration, Instant};
 
 const SETTINGS_SAVE_NAME: &str = "settings";
 
-#[derive(Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
 #[serde(default)]
 pub struct Settings {
     pub camera_border_move: bool,
@@ -27,10 +29,22 @@ pub struct Settings {
     pub music_volume_percent: f32,
     pub effects_volume_percent: f32,
     pub ui_volume_percent: f32,
+    /// Maximum number of vehicles heard individually
+    pub vehicle_sounds_budget: u32,
+    /// Show the sounds of the simulation events as subtitles, to play muted or hard of hearing
+    pub subtitles: bool,
+    /// Ticks taking longer than this are reported in the profiler, 0 to disable
+    pub slow_tick_budget_ms: f32,
 
     #[serde(skip)]
     pub time_warp: u32,
     pub auto_save_every: AutoSaveEvery,
+    /// Stop the simulation until the save is written, otherwise it only stops while encoding
+    pub pause_during_saves: bool,
+    /// Code of the language the texts are shown in, see [`common::i18n`]
+    pub language: String,
+    /// Symbol the money is shown with, such as `$` or `€`
+    pub currency_symbol: String,
 }
 
 impl Default for Settings {
@@ -42,12 +56,18 @@ impl Default for Settings {
             music_volume_percent: 100.0,
             effects_volume_percent: 100.0,
             ui_volume_percent: 100.0,
+            vehicle_sounds_budget: 30,
+            subtitles: false,
+            slow_tick_budget_ms: 50.0,
             time_warp: 1,
             auto_save_every: AutoSaveEvery::FiveMinutes,
+            pause_during_saves: true,
             camera_smooth_tightness: 1.0,
             camera_fov: 60.0,
             gui_scale: 1.0,
             gfx: GfxSettings::default(),
+            language: common::i18n::DEFAULT_LANGUAGE.to_string(),
+            currency_symbol: "$".to_string(),
         }
     }
 }
@@ -103,27 +123,46 @@ pub fn settings(window: egui::Window<'_>, ui: &Context, uiworld: &mut UiWorld, _
         .vscroll(true)
         .collapsible(false)
         .show(ui, |ui| {
-            let before = *settings;
-            ui.label("Gameplay");
+            let before = settings.clone();
+            ui.label(tr("Gameplay"));
+
+            language(ui, &mut settings.language);
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut settings.currency_symbol).desired_width(30.0),
+                );
+                ui.label(tr("Currency symbol"));
+            });
 
             let mut id = settings.auto_save_every as u8 as usize;
-            egui::ComboBox::from_label("Autosave").show_index(ui, &mut id, 3, |i| {
-                AutoSaveEvery::from(i as u8).as_ref().to_string()
+            egui::ComboBox::from_label(tr("Autosave")).show_index(ui, &mut id, 3, |i| {
+                tr(AutoSaveEvery::from(i as u8).as_ref())
             });
             settings.auto_save_every = AutoSaveEvery::from(id as u8);
+            ui.checkbox(
+                &mut settings.pause_during_saves,
+                tr("Pause simulation during saves"),
+            )
+            .on_hover_text(tr(
+                "Otherwise the simulation only stops while the world is encoded",
+            ));
+
+            ui.label(tr("Profile"));
+            profiles(ui);
 
-            ui.label("Input");
+            ui.label(tr("Input"));
 
             ui.checkbox(
                 &mut settings.camera_border_move,
-                "Border screen camera movement",
+                tr("Border screen camera movement"),
             );
-            ui.checkbox(&mut settings.camera_smooth, "Camera smooth");
+            ui.checkbox(&mut settings.camera_smooth, tr("Camera smooth"));
 
             if settings.camera_smooth {
                 ui.horizontal(|ui| {
                     ui.add(egui::DragValue::new(&mut settings.camera_smooth_tightness).speed(0.01));
-                    ui.label("Camera smoothing tightness");
+                    ui.label(tr("Camera smoothing tightness"));
                 });
             }
             ui.horizontal(|ui| {
@@ -131,7 +170,7 @@ pub fn settings(window: egui::Window<'_>, ui: &Context, uiworld: &mut UiWorld, _
                     .clamp_range(1.0..=179.0f32)
                     .speed(0.1)
                     .ui(ui);
-                ui.label("Camera Field of View (FOV)");
+                ui.label(tr("Camera Field of View (FOV)"));
             });
 
             let mut fps_to_show = 0.0;
@@ -159,26 +198,41 @@ pub fn settings(window: egui::Window<'_>, ui: &Context, uiworld: &mut UiWorld, _
                 "shouldn't be looking at FPS in debug mode! use --release",
             );
             ui.label(format!(
-                "Graphics - {fps_to_show:.1}FPS - {:.1}ms",
+                "{} - {fps_to_show:.1}FPS - {:.1}ms",
+                tr("Graphics"),
                 1000.0 * ms_to_show
             ));
 
-            ui.checkbox(&mut settings.gfx.fullscreen, "Fullscreen");
-            ui.checkbox(&mut settings.gfx.terrain_grid, "Terrain Grid");
-            ui.checkbox(&mut settings.gfx.fog, "Fog");
-            ui.checkbox(&mut settings.gfx.ssao, "Ambient Occlusion (SSAO)");
+            // the preset also turns off the effects the integrated GPUs struggle with
+            if ui
+                .checkbox(&mut settings.gfx.performance_mode, tr("Performance mode"))
+                .on_hover_text(tr(
+                    "Simplified roads without markings, no shadows nor props and fewer agents drawn",
+                ))
+                .changed()
+                && settings.gfx.performance_mode
+            {
+                settings.gfx.fog = false;
+                settings.gfx.ssao = false;
+            }
+            ui.checkbox(&mut settings.gfx.fullscreen, tr("Fullscreen"));
+            ui.checkbox(&mut settings.gfx.terrain_grid, tr("Terrain Grid"));
+            ui.checkbox(&mut settings.gfx.fog, tr("Fog"));
+            ui.checkbox(&mut settings.gfx.ssao, tr("Ambient Occlusion (SSAO)"));
 
             // shadow quality combobox
             let mut id = settings.gfx.shadows as u8 as usize;
-            egui::ComboBox::from_label("Shadow Quality").show_index(ui, &mut id, 5, |i| {
-                ShadowQuality::from(i as u8).as_ref().to_string()
+            ui.add_enabled_ui(!settings.gfx.performance_mode, |ui| {
+                egui::ComboBox::from_label(tr("Shadow Quality")).show_index(ui, &mut id, 5, |i| {
+                    tr(ShadowQuality::from(i as u8).as_ref())
+                });
             });
             settings.gfx.shadows = ShadowQuality::from(id as u8);
 
-            ui.checkbox(&mut settings.gfx.vsync, "VSync");
+            ui.checkbox(&mut settings.gfx.vsync, tr("VSync"));
 
             ui.separator();
-            ui.label("GUI");
+            ui.label(tr("GUI"));
             ui.horizontal(|ui| {
                 // we only change gui_scale at end of interaction to avoid feedback loops
                 let mut gui_scale = settings.gui_scale;
@@ -186,47 +240,58 @@ pub fn settings(window: egui::Window<'_>, ui: &Context, uiworld: &mut UiWorld, _
                 if res.drag_released() {
                     settings.gui_scale = gui_scale;
                 }
-                ui.label("GUI Scale");
+                ui.label(tr("GUI Scale"));
             });
 
             ui.separator();
-            ui.label("Audio");
+            ui.label(tr("Audio"));
 
             ui.horizontal(|ui| {
                 ui.add(
                     egui::Slider::new(&mut settings.master_volume_percent, 0.0..=100.0)
                         .custom_formatter(|x, _| format!("{x:.0}%")),
                 );
-                ui.label("Master volume");
+                ui.label(tr("Master volume"));
             });
             ui.horizontal(|ui| {
                 ui.add(
                     egui::Slider::new(&mut settings.music_volume_percent, 0.0..=100.0)
                         .custom_formatter(|x, _| format!("{x:.0}%")),
                 );
-                ui.label("Music volume");
+                ui.label(tr("Music volume"));
             });
             ui.horizontal(|ui| {
                 ui.add(
                     egui::Slider::new(&mut settings.effects_volume_percent, 0.0..=100.0)
                         .custom_formatter(|x, _| format!("{x:.0}%")),
                 );
-                ui.label("Effects volume");
+                ui.label(tr("Effects volume"));
             });
             ui.horizontal(|ui| {
                 ui.add(
                     egui::Slider::new(&mut settings.ui_volume_percent, 0.0..=100.0)
                         .custom_formatter(|x, _| format!("{x:.0}%")),
                 );
-                ui.label("Ui volume");
+                ui.label(tr("Ui volume"));
+            });
+            ui.horizontal(|ui| {
+                ui.add(egui::Slider::new(
+                    &mut settings.vehicle_sounds_budget,
+                    0..=60,
+                ));
+                ui.label(tr("Vehicle sounds"));
             });
+            ui.checkbox(&mut settings.subtitles, tr("Sound subtitles"))
+                .on_hover_text(tr(
+                    "Shows the train horns, constructions and incidents heard as subtitles",
+                ));
 
             ui.separator();
             let mut bindings = uiworld.write::<Bindings>();
             ui.horizontal(|ui| {
-                ui.label("Keybinds");
+                ui.label(tr("Keybinds"));
 
-                if ui.button("Reset").clicked() {
+                if ui.button(tr("Reset")).clicked() {
                     *bindings = Bindings::default();
                     uiworld.write::<InputMap>().build_input_tree(&mut bindings);
                 }
@@ -242,13 +307,13 @@ pub fn settings(window: egui::Window<'_>, ui: &Context, uiworld: &mut UiWorld, _
                 .column(Column::initial(50.0))
                 .header(30.0, |mut header| {
                     header.col(|ui| {
-                        ui.label("Action");
+                        ui.label(tr("Action"));
                     });
                     header.col(|ui| {
-                        ui.label("Primary");
+                        ui.label(tr("Primary"));
                     });
                     header.col(|ui| {
-                        ui.label("Secondary");
+                        ui.label(tr("Secondary"));
                     });
                 })
                 .body(|body| {
@@ -304,6 +369,9 @@ pub fn settings(window: egui::Window<'_>, ui: &Context, uiworld: &mut UiWorld, _
 }
 
 pub fn manage_settings(ctx: &mut engine::Context, settings: &Settings) {
+    common::i18n::set_language(&settings.language);
+    simulation::economy::set_currency_symbol(&settings.currency_symbol);
+
     ctx.gfx.update_settings(settings.gfx);
 
     ctx.egui.zoom_factor = settings.gui_scale;
@@ -315,3 +383,59 @@ pub fn manage_settings(ctx: &mut engine::Context, settings: &Settings) {
         settings.effects_volume_percent,
     );
 }
+
+/// Picks the language among the ones with a translation file
+fn language(ui: &mut egui::Ui, language: &mut String) {
+    let id = ui.make_persistent_id("languages");
+    let languages = ui.data_mut(|d| {
+        d.get_temp_mut_or_insert_with(id, common::i18n::languages)
+            .clone()
+    });
+    let selected = languages
+        .iter()
+        .find(|(code, _)| code == language)
+        .map_or(language.clone(), |(_, name)| name.clone());
+
+    egui::ComboBox::from_label(tr("Language"))
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for (code, name) in languages {
+                ui.selectable_value(language, code, name);
+            }
+        });
+}
+
+/// Lists the profiles, the selected one is used at the next startup
+fn profiles(ui: &mut egui::Ui) {
+    let id = ui.make_persistent_id("profiles");
+    let mut profiles = ui.data_mut(|d| d.get_temp_mut_or_insert_with(id, Profiles::load).clone());
+    let mut new_name = ui.data_mut(|d| d.get_temp::<String>(id.with("new")).unwrap_or_default());
+    let before = profiles.selected.clone();
+
+    ui.label(format!(
+        "{}: {}",
+        tr("Current profile"),
+        crate::profiles::current()
+    ));
+    egui::ComboBox::from_label(tr("Profile at next startup"))
+        .selected_text(&profiles.selected)
+        .show_ui(ui, |ui| {
+            for name in profiles.list.clone() {
+                ui.selectable_value(&mut profiles.selected, name.clone(), name);
+            }
+        });
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut new_name);
+        if ui.button(tr("Create profile")).clicked() && profiles.add(&new_name) {
+            profiles.selected = std::mem::take(&mut new_name);
+        }
+    });
+
+    if profiles.selected != before {
+        profiles.save();
+    }
+    ui.data_mut(|d| {
+        d.insert_temp(id, profiles);
+        d.insert_temp(id.with("new"), new_name);
+    });
+}
@@ -0,0 +1,183 @@
+use crate::uiworld::UiWorld;
+use egui::load::SizedTexture;
+use egui::{Color32, ColorImage, TextureHandle, TextureOptions};
+use geom::{Vec2, AABB};
+use simulation::utils::time::DayTime;
+use simulation::utils::timelapse::{SilhouetteCell, Timelapse, TimelapseSnapshot};
+use simulation::Simulation;
+use std::path::Path;
+
+/// Side of the played back and exported frames, in pixels
+const FRAME_SIZE: usize = 256;
+/// Folder the image sequence is exported to
+const EXPORT_DIR: &str = "timelapse";
+
+struct TimelapseState {
+    frame: usize,
+    playing: bool,
+    /// Snapshots shown per second
+    speed: f32,
+    elapsed: f32,
+    /// The frame and the number of snapshots the texture was drawn for
+    texture: Option<((usize, usize), TextureHandle)>,
+    export_status: Option<String>,
+}
+
+/// Time-lapse window
+/// Plays back the growth of the city from the periodic snapshots, and exports it as an image
+/// sequence
+pub fn timelapse(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiw: &mut UiWorld,
+    sim: &Simulation,
+) {
+    uiw.check_present(|| TimelapseState {
+        frame: 0,
+        playing: false,
+        speed: 8.0,
+        elapsed: 0.0,
+        texture: None,
+        export_status: None,
+    });
+    let uiw: &UiWorld = uiw;
+    let mut state = uiw.write::<TimelapseState>();
+    let state = &mut *state;
+    let timelapse = sim.read::<Timelapse>();
+    let snaps = timelapse.snapshots();
+
+    window.resizable(false).show(ui, |ui| {
+        let Some(last) = snaps.len().checked_sub(1) else {
+            ui.label("No snapshot yet, the first one is taken after a few in-game hours.");
+            return;
+        };
+
+        if state.playing {
+            state.elapsed += ui.input(|i| i.unstable_dt) * state.speed;
+            let advance = state.elapsed as usize;
+            state.elapsed -= advance as f32;
+            state.frame += advance;
+            if state.frame >= last {
+                state.playing = false;
+            }
+            ui.ctx().request_repaint();
+        }
+        state.frame = state.frame.min(last);
+        let frame = state.frame;
+
+        let key = (frame, snaps.len());
+        if state.texture.as_ref().map_or(true, |(k, _)| *k != key) {
+            let img = frame_image(snaps, frame);
+            match state.texture {
+                Some((ref mut k, ref mut tex)) => {
+                    tex.set(img, TextureOptions::NEAREST);
+                    *k = key;
+                }
+                None => {
+                    let tex = ui
+                        .ctx()
+                        .load_texture("timelapse", img, TextureOptions::NEAREST);
+                    state.texture = Some((key, tex));
+                }
+            }
+        }
+        if let Some((_, ref tex)) = state.texture {
+            ui.image(SizedTexture::new(
+                tex.id(),
+                (FRAME_SIZE as f32, FRAME_SIZE as f32),
+            ));
+        }
+
+        let snap = &snaps[frame];
+        let date = DayTime::new(snap.instant.timestamp as i32);
+        ui.label(format!("Day {} ({:?})", date.day, date.season()));
+        ui.label(format!(
+            "population {}   buildings {}   roads {:.1}km   money {}",
+            snap.population,
+            snap.buildings,
+            snap.road_length / 1000.0,
+            snap.money
+        ));
+
+        ui.horizontal(|ui| {
+            let label = if state.playing { "Pause" } else { "Play" };
+            if ui.button(label).clicked() {
+                if !state.playing && state.frame == last {
+                    state.frame = 0;
+                }
+                state.playing = !state.playing;
+                state.elapsed = 0.0;
+            }
+            ui.add(egui::Slider::new(&mut state.frame, 0..=last).show_value(false));
+            ui.add(
+                egui::DragValue::new(&mut state.speed)
+                    .clamp_range(1.0..=60.0)
+                    .suffix(" fps"),
+            );
+        });
+
+        if ui.button("Export image sequence").clicked() {
+            state.export_status = Some(match export(snaps, Path::new(EXPORT_DIR)) {
+                Ok(()) => format!("Exported {} frames to {}/", snaps.len(), EXPORT_DIR),
+                Err(e) => format!("Could not export: {}", e),
+            });
+        }
+        if let Some(ref status) = state.export_status {
+            ui.label(status);
+        }
+    });
+}
+
+fn cell_color(c: SilhouetteCell) -> Color32 {
+    match c {
+        SilhouetteCell::Empty => Color32::from_rgb(24, 28, 34),
+        SilhouetteCell::Road => Color32::from_rgb(210, 210, 210),
+        SilhouetteCell::Rail => Color32::from_rgb(160, 110, 80),
+        SilhouetteCell::House => Color32::from_rgb(110, 190, 110),
+        SilhouetteCell::Company => Color32::from_rgb(230, 180, 70),
+        SilhouetteCell::Other => Color32::from_rgb(100, 150, 230),
+    }
+}
+
+/// Draws a snapshot in a view covering every snapshot, so the city grows inside a fixed frame
+fn frame_image(snaps: &[TimelapseSnapshot], frame: usize) -> ColorImage {
+    let view = snaps
+        .iter()
+        .map(|s| s.bounds)
+        .reduce(AABB::union)
+        .unwrap_or(AABB::zero());
+    let side = view.w().max(view.h());
+    let view = AABB::centered(view.center(), Vec2::splat(side));
+
+    let snap = &snaps[frame];
+    let cells = snap.silhouette();
+    let mut img = ColorImage::new([FRAME_SIZE, FRAME_SIZE], cell_color(SilhouetteCell::Empty));
+    for y in 0..FRAME_SIZE {
+        for x in 0..FRAME_SIZE {
+            // rows go from the top of the image, the silhouette from the bottom of the map
+            let p = view.ll
+                + Vec2::new(x as f32 + 0.5, (FRAME_SIZE - y) as f32 - 0.5) * side
+                    / FRAME_SIZE as f32;
+            if let Some(&c) = snap.cell_index(p).and_then(|i| cells.get(i)) {
+                img.pixels[y * FRAME_SIZE + x] = cell_color(c);
+            }
+        }
+    }
+    img
+}
+
+fn export(snaps: &[TimelapseSnapshot], dir: &Path) -> image::ImageResult<()> {
+    std::fs::create_dir_all(dir)?;
+    for i in 0..snaps.len() {
+        let img = frame_image(snaps, i);
+        let bytes: Vec<u8> = img.pixels.iter().flat_map(|c| c.to_array()).collect();
+        image::save_buffer(
+            dir.join(format!("frame_{:04}.png", i)),
+            &bytes,
+            FRAME_SIZE as u32,
+            FRAME_SIZE as u32,
+            image::ColorType::Rgba8,
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,277 @@
+use crate::gui::roadeditor::RoadEditorResource;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+use egui::{Color32, Grid, RichText};
+use geom::Color;
+use simulation::map::RoadID;
+use simulation::map_dynamic::{
+    District, NightActivity, ParkingEnforcement, ParkingPressure, TripStats, NIGHT_ACTIVITY_HOUR,
+};
+use simulation::transportation::Mobility;
+use simulation::utils::analytics::AnalyticsExport;
+use simulation::Simulation;
+use std::collections::BTreeMap;
+
+/// Maximum number of districts shown in the matrix, the busiest ones are kept
+const MAX_DISTRICTS: usize = 12;
+
+#[derive(Default)]
+pub struct TrafficWindowState {
+    show_night_activity: bool,
+    /// Files written by the last analytics export
+    exported: Vec<String>,
+}
+
+/// Traffic analysis window
+/// Shows the origin-destination matrix of the completed trips and, when a road is selected
+/// in the road editor, where the traffic of that road comes from and goes to
+pub fn traffic(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiworld: &mut UiWorld,
+    sim: &Simulation,
+) {
+    let trips = sim.read::<TripStats>();
+    let selected = uiworld.read::<RoadEditorResource>().inspect_road;
+
+    window.default_width(400.0).show(ui, |ui| {
+        ui.label(format!(
+            "{} trips completed, {} sampled paths",
+            trips.n_trips(),
+            trips.n_samples()
+        ));
+        ui.label("Districts are named by their x:y position on a 1km grid.");
+        export_analytics(ui, uiworld, sim);
+        ui.separator();
+
+        ui.label(RichText::new("Access time").strong());
+        access_times(ui, &trips);
+        ui.separator();
+
+        ui.label(RichText::new("Parking pressure").strong());
+        parking_pressure(ui, sim);
+        ui.separator();
+
+        ui.label(RichText::new("Parking enforcement").strong());
+        parking_enforcement(ui, sim);
+        ui.separator();
+
+        ui.label(RichText::new("Night activity").strong());
+        night_activity(ui, uiworld, sim);
+        ui.separator();
+
+        ui.label(RichText::new("Origin-destination matrix").strong());
+        od_table(ui, "od_matrix", trips.od_matrix());
+        ui.separator();
+
+        ui.label(RichText::new("Select-link analysis").strong());
+        let Some(road) = selected else {
+            ui.label("Select a road with the road editor to see where its traffic comes from.");
+            return;
+        };
+        let od = trips.select_link(road);
+        if od.is_empty() {
+            ui.label("No sampled trip went through this road yet.");
+            return;
+        }
+        od_table(ui, "select_link", &od);
+        draw_desire_lines(uiworld, sim, road, &od);
+    });
+}
+
+/// Exports the commute times, mode share, wages and trip lengths as csv files, only when asked
+fn export_analytics(ui: &mut egui::Ui, uiworld: &UiWorld, sim: &Simulation) {
+    let mut state = uiworld.write::<TrafficWindowState>();
+    if ui
+        .button("Export analytics (csv)")
+        .on_hover_text("Writes anonymized histograms of the city in the save folder")
+        .clicked()
+    {
+        state.exported = AnalyticsExport::new(sim).save("analytics");
+    }
+    for path in &state.exported {
+        ui.label(path);
+    }
+}
+
+fn access_times(ui: &mut egui::Ui, trips: &TripStats) {
+    let Some(avg) = trips.avg_access_time(None) else {
+        ui.label("Nobody walked to a vehicle yet.");
+        return;
+    };
+    let fmt = |x: Option<f32>| x.map_or("-".to_string(), |x| format!("{:.1} min", x / 60.0));
+    Grid::new("access_times").show(ui, |ui| {
+        ui.label("Average walk to a vehicle");
+        ui.label(fmt(Some(avg)));
+        ui.end_row();
+        ui.label("Regular mobility");
+        ui.label(fmt(trips.avg_access_time(Some(Mobility::Regular))));
+        ui.end_row();
+        ui.label("Reduced mobility");
+        ui.label(fmt(trips.avg_access_time(Some(Mobility::Reduced))));
+        ui.end_row();
+    });
+}
+
+fn parking_pressure(ui: &mut egui::Ui, sim: &Simulation) {
+    let cruising = sim
+        .world()
+        .humans
+        .values()
+        .filter(|h| h.router.is_cruising())
+        .count();
+    ui.label(format!(
+        "{} cars are cruising around the block looking for a spot.",
+        cruising
+    ));
+
+    let pressure = sim.read::<ParkingPressure>();
+    let mut districts: Vec<_> = pressure.iter().collect();
+    if districts.is_empty() {
+        ui.label("Nobody looked for a spot yet.");
+        return;
+    }
+    districts.sort_by(|a, b| b.1.total_cmp(&a.1));
+    Grid::new("parking_pressure").show(ui, |ui| {
+        ui.label("District");
+        ui.label("Searches without a spot");
+        ui.end_row();
+        for (district, p) in districts.into_iter().take(MAX_DISTRICTS) {
+            ui.label(district.to_string());
+            let col = if p > 0.5 {
+                Color32::from_rgb(230, 100, 100)
+            } else {
+                Color32::GRAY
+            };
+            ui.colored_label(col, format!("{:.0}%", p * 100.0));
+            ui.end_row();
+        }
+    });
+}
+
+fn parking_enforcement(ui: &mut egui::Ui, sim: &Simulation) {
+    let enforcement = sim.read::<ParkingEnforcement>();
+    ui.label(format!(
+        "{} cars are double parked because they found no spot.",
+        enforcement.n_double_parked
+    ));
+    ui.label("Police stations fine them, and tow the ones staying too long.");
+    Grid::new("parking_enforcement").show(ui, |ui| {
+        ui.label("Fines");
+        ui.label(enforcement.n_fines.to_string());
+        ui.end_row();
+        ui.label("Cars towed");
+        ui.label(enforcement.n_towed.to_string());
+        ui.end_row();
+        ui.label("Collected");
+        ui.label(enforcement.income.to_string());
+        ui.end_row();
+    });
+}
+
+fn night_activity(ui: &mut egui::Ui, uiworld: &UiWorld, sim: &Simulation) {
+    let activity = sim.read::<NightActivity>();
+    if activity.day().is_none() {
+        ui.label(format!(
+            "Nobody was counted yet, people out are counted at {NIGHT_ACTIVITY_HOUR}am."
+        ));
+        return;
+    }
+    ui.label(format!(
+        "{} people were out at {NIGHT_ACTIVITY_HOUR}am last night.",
+        activity.n_people()
+    ));
+
+    let mut state = uiworld.write::<TrafficWindowState>();
+    ui.checkbox(&mut state.show_night_activity, "Show where");
+    if !state.show_night_activity {
+        return;
+    }
+    drop(state);
+
+    let map = sim.map();
+    let max = activity.cells().map(|(_, n)| n).max().unwrap_or(1).max(1);
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    for (cell, n) in activity.cells() {
+        let aabb = NightActivity::cell_bounds(cell);
+        let z = map.environment.height(aabb.center()).unwrap_or(0.0) + 5.0;
+        let intensity = (n as f32 / max as f32).sqrt();
+        draw.aabb(aabb, z)
+            .color(Color::new(1.0, 0.6, 0.1, 0.2 + 0.5 * intensity));
+    }
+}
+
+fn od_table(ui: &mut egui::Ui, id: &str, od: &BTreeMap<(District, District), u32>) {
+    if od.is_empty() {
+        ui.label("No trips recorded yet.");
+        return;
+    }
+
+    let mut volume: BTreeMap<District, u32> = BTreeMap::new();
+    for (&(from, to), &n) in od {
+        *volume.entry(from).or_default() += n;
+        *volume.entry(to).or_default() += n;
+    }
+    let mut districts: Vec<_> = volume.into_iter().collect();
+    districts.sort_by_key(|&(d, n)| (std::cmp::Reverse(n), d));
+    districts.truncate(MAX_DISTRICTS);
+    districts.sort();
+
+    let max = od.values().copied().max().unwrap_or(1).max(1);
+
+    egui::ScrollArea::horizontal().id_source(id).show(ui, |ui| {
+        Grid::new(id).striped(true).show(ui, |ui| {
+            ui.label("from \\ to");
+            for (to, _) in &districts {
+                ui.label(to.to_string());
+            }
+            ui.end_row();
+
+            for (from, _) in &districts {
+                ui.label(from.to_string());
+                for (to, _) in &districts {
+                    let n = od.get(&(*from, *to)).copied().unwrap_or(0);
+                    if n == 0 {
+                        ui.label("");
+                        continue;
+                    }
+                    let intensity = (n as f32 / max as f32).sqrt();
+                    let col = Color32::from_rgb(
+                        (100.0 + 155.0 * intensity) as u8,
+                        (200.0 - 100.0 * intensity) as u8,
+                        100,
+                    );
+                    ui.colored_label(col, n.to_string());
+                }
+                ui.end_row();
+            }
+        });
+    });
+}
+
+/// Draws a line between the districts of each origin-destination pair going through the road
+fn draw_desire_lines(
+    uiworld: &UiWorld,
+    sim: &Simulation,
+    road: RoadID,
+    od: &BTreeMap<(District, District), u32>,
+) {
+    let map = sim.map();
+    let Some(road) = map.roads().get(road) else {
+        return;
+    };
+    let z = road.points().first().z + 5.0;
+    let max = od.values().copied().max().unwrap_or(1).max(1);
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    for (&(from, to), &n) in od {
+        let thickness = 5.0 + 25.0 * n as f32 / max as f32;
+        let col = simulation::config().gui_primary.a(0.6);
+        if from == to {
+            draw.stroke_circle(from.center().z(z), 100.0, thickness)
+                .color(col);
+            continue;
+        }
+        draw.line(from.center().z(z), to.center().z(z), thickness)
+            .color(col);
+    }
+}
@@ -1,17 +1,26 @@
 use crate::game_loop::Timings;
+use crate::gui::addtrain::AddTrainResource;
 use crate::gui::bulldozer::BulldozerState;
 use crate::gui::chat::GUIChatState;
+use crate::gui::curbs::CurbToolResource;
+use crate::gui::decoration::DecorationToolResource;
+use crate::gui::furniture::FurnitureToolResource;
+use crate::gui::inspect::history::InspectHistory;
+use crate::gui::interchange::InterchangeResource;
 use crate::gui::lotbrush::LotBrushResource;
 use crate::gui::roadbuild::RoadBuildResource;
 use crate::gui::roadeditor::RoadEditorResource;
+use crate::gui::slow_ticks::SlowTicks;
 use crate::gui::specialbuilding::SpecialBuildingResource;
+use crate::gui::subtitles::Subtitles;
 use crate::gui::terraforming::TerraformingResource;
+use crate::gui::undo::UndoState;
 use crate::gui::windows::debug::{DebugObjs, DebugState, TestFieldProperties};
 use crate::gui::windows::settings::Settings;
 use crate::gui::zoneedit::ZoneEditState;
 use crate::gui::{
-    ErrorTooltip, ExitState, FollowEntity, InspectedBuilding, InspectedEntity, PotentialCommands,
-    Tool,
+    ErrorTooltip, ExitState, FollowEntity, InspectedBuilding, InspectedEntity,
+    InspectedIntersection, PotentialCommands, RejectedCommandsState, Tool,
 };
 use crate::inputmap::{Bindings, InputMap};
 use crate::network::NetworkState;
@@ -32,6 +41,11 @@ pub fn init() {
     register_resource::<LotBrushResource>("lot_brush");
     register_resource::<Bindings>("bindings");
 
+    register_resource_noserialize::<AddTrainResource>();
+    register_resource_noserialize::<CurbToolResource>();
+    register_resource_noserialize::<FurnitureToolResource>();
+    register_resource_noserialize::<DecorationToolResource>();
+    register_resource_noserialize::<InterchangeResource>();
     register_resource_noserialize::<TerraformingResource>();
     register_resource_noserialize::<BulldozerState>();
     register_resource_noserialize::<DebugObjs>();
@@ -45,18 +59,37 @@ pub fn init() {
     register_resource_noserialize::<InputMap>();
     register_resource_noserialize::<InspectedEntity>();
     register_resource_noserialize::<InspectedBuilding>();
+    register_resource_noserialize::<InspectedIntersection>();
+    register_resource_noserialize::<InspectHistory>();
     register_resource_noserialize::<NetworkState>();
     register_resource_noserialize::<PotentialCommands>();
+    register_resource_noserialize::<RejectedCommandsState>();
+    register_resource_noserialize::<UndoState>();
     register_resource_noserialize::<ZoneEditState>();
     register_resource_noserialize::<TestFieldProperties>();
     register_resource_noserialize::<ReceivedCommands>();
     register_resource_noserialize::<RoadBuildResource>();
     register_resource_noserialize::<RoadEditorResource>();
     register_resource_noserialize::<SpecialBuildingResource>();
+    register_resource_noserialize::<Subtitles>();
+    register_resource_noserialize::<SlowTicks>();
     register_resource_noserialize::<Timings>();
     register_resource_noserialize::<Tool>();
+    register_resource_noserialize::<crate::gui::tutorial::TutorialHighlight>();
     register_resource_noserialize::<WorldCommands>();
+    register_resource_noserialize::<crate::gui::windows::arterials::ArterialsState>();
+    register_resource_noserialize::<crate::gui::windows::arterials::GreenWaveSpeed>();
+    register_resource_noserialize::<crate::gui::windows::ghost::GhostState>();
+    register_resource_noserialize::<crate::gui::windows::scenario::ScenarioResults>();
+    register_resource_noserialize::<crate::gui::windows::health::HealthWindowState>();
+    register_resource_noserialize::<crate::gui::windows::labor::LaborWindowState>();
     register_resource_noserialize::<crate::gui::windows::load::LoadState>();
+    register_resource_noserialize::<crate::gui::windows::markers::MarkersState>();
+    register_resource_noserialize::<crate::gui::windows::buslines::BusLinesState>();
+    register_resource_noserialize::<crate::gui::windows::metrolines::MetroLinesState>();
+    register_resource_noserialize::<simulation::map::MapLayer>();
+    register_resource_noserialize::<simulation::utils::render_snapshot::SnapshotBuffer>();
+    register_resource_noserialize::<crate::gui::windows::traffic::TrafficWindowState>();
     register_resource_noserialize::<crate::uiworld::SaveLoadState>();
 }
 
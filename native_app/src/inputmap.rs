@@ -29,6 +29,9 @@ pub enum InputAction {
     Zoom,
     Dezoom,
     Rotate,
+    RotateLeft,
+    RotateRight,
+    Mirror,
     SizeUp,
     SizeDown,
     Close,
@@ -41,6 +44,10 @@ pub enum InputAction {
     OpenEconomyMenu,
     PausePlay,
     OpenChat,
+    CommandPalette,
+    ToggleHelp,
+    Undo,
+    Redo,
 }
 
 // All unit inputs need to match
@@ -70,6 +77,8 @@ pub struct InputMap {
     /// Mouse position in screen space
     pub screen: Vec2,
     input_tree: InputTree,
+    /// Actions triggered from the GUI, added to just_act on the next frame
+    injected: Vec<InputAction>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -92,6 +101,9 @@ const DEFAULT_BINDINGS: &[(InputAction, &[&[UnitInput]])] = &[
     (Zoom,            &[&[Key(K::c("+"))], &[WheelUp]]),
     (Dezoom,          &[&[Key(K::c("-"))], &[WheelDown]]),
     (Rotate,          &[&[Key(K::Control), WheelUp], &[Key(K::Control), WheelDown]]),
+    (RotateLeft,      &[&[Key(K::c("X"))]]),
+    (RotateRight,     &[&[Key(K::c("R"))]]),
+    (Mirror,          &[&[Key(K::c("F"))]]),
     (SizeUp,          &[&[Key(K::Control), WheelUp]]),
     (SizeDown,        &[&[Key(K::Control), WheelDown]]),
     (Close,           &[&[Key(K::Escape)]]),
@@ -104,6 +116,10 @@ const DEFAULT_BINDINGS: &[(InputAction, &[&[UnitInput]])] = &[
     (OpenEconomyMenu, &[&[Key(K::c("E"))]]),
     (PausePlay,       &[&[Key(K::Space)]]),
     (OpenChat,        &[&[Key(K::c("T"))]]),
+    (CommandPalette,  &[&[Key(K::Control), Key(K::c("P"))]]),
+    (ToggleHelp,      &[&[Key(K::F1)]]),
+    (Undo,            &[&[Key(K::Control), Key(K::c("Z"))]]),
+    (Redo,            &[&[Key(K::Control), Key(K::c("Y"))]]),
 ];
 
 impl Default for Bindings {
@@ -135,6 +151,11 @@ impl InputMap {
         self.input_tree = InputTree::new(bindings);
     }
 
+    /// Triggers the action on the next frame as if its binding was just pressed
+    pub fn inject(&mut self, act: InputAction) {
+        self.injected.push(act);
+    }
+
     pub fn prepare_frame(&mut self, input: &InputContext, kb: bool, mouse: bool) {
         self.just_act.clear();
         let empty1 = FastSet::default();
@@ -159,6 +180,7 @@ impl InputMap {
                 self.just_act.insert(v.clone());
             }
         }
+        self.just_act.extend(self.injected.drain(..));
         self.screen = input.mouse.screen;
         self.wheel = input.mouse.wheel_delta;
     }
@@ -327,6 +349,9 @@ impl Display for InputAction {
                 Zoom => "Zoom",
                 Dezoom => "Dezoom",
                 Rotate => "Rotate",
+                RotateLeft => "Rotate Left",
+                RotateRight => "Rotate Right",
+                Mirror => "Mirror",
                 Close => "Close",
                 Select => "Select",
                 SecondarySelect => "Secondary Select",
@@ -339,6 +364,10 @@ impl Display for InputAction {
                 OpenChat => "Interact with Chat",
                 SizeUp => "Size Up",
                 SizeDown => "Size Down",
+                CommandPalette => "Command Palette",
+                ToggleHelp => "Controls Help",
+                Undo => "Undo",
+                Redo => "Redo",
             }
         )
     }
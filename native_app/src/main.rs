@@ -14,18 +14,45 @@ extern crate inline_tweak;
 mod uiworld;
 
 mod audio;
+mod crash_save;
 mod game_loop;
 mod gui;
 mod init;
 mod inputmap;
 mod network;
+mod profiles;
 mod rendering;
 
 fn main() {
     #[cfg(feature = "profile")]
     profiling::tracy_client::Client::start();
+    #[cfg(feature = "profile-tracing")]
+    start_tracing();
     profiling::register_thread!("Main Thread");
 
     init::init();
-    engine::framework::start::<game_loop::State>();
+    if std::panic::catch_unwind(engine::framework::start::<game_loop::State>).is_err() {
+        // the panic hook couldn't save if the simulation was being ticked, now it's released
+        crash_save::emergency_save(true);
+        std::process::exit(1);
+    }
+}
+
+/// Writes the profiling spans with their duration to `tracing.log` when they close
+#[cfg(feature = "profile-tracing")]
+fn start_tracing() {
+    use tracing_subscriber::fmt::format::FmtSpan;
+
+    let file = match std::fs::File::create("tracing.log") {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("could not create tracing.log: {e}");
+            return;
+        }
+    };
+    tracing_subscriber::fmt()
+        .with_writer(std::sync::Mutex::new(file))
+        .with_span_events(FmtSpan::CLOSE)
+        .with_ansi(false)
+        .init();
 }
@@ -1,5 +1,6 @@
 pub use self::inner::*;
 use crate::game_loop::{State, Timings};
+use crate::gui::slow_ticks::check_slow_tick;
 use crate::gui::windows::settings::Settings;
 use crate::uiworld::{ReceivedCommands, SaveLoadState};
 use common::timestep::Timestep;
@@ -69,6 +70,7 @@ fn handle_singleplayer(state: &mut State) {
     while step.tick() || (has_commands && commands_once.is_some()) {
         let t = sim.tick(sched, commands_once.take().unwrap_or_default().as_ref());
         timings.world_update.add_value(t.as_secs_f32());
+        check_slow_tick(&state.uiw, sched);
     }
 
     if commands_once.is_none() {
@@ -101,6 +103,7 @@ fn handle_replay(
 #[cfg(feature = "multiplayer")]
 mod inner {
     use crate::game_loop::{State, Timings, VERSION};
+    use crate::gui::slow_ticks::check_slow_tick;
     use crate::gui::windows::network::NetworkConnectionInfo;
     use crate::network::handle_replay;
     use crate::uiworld::{ReceivedCommands, SaveLoadState};
@@ -206,6 +209,7 @@ mod inner {
                     .write::<Timings>()
                     .world_update
                     .add_value(t.as_secs_f32());
+                check_slow_tick(&state.uiw, &state.game_schedule);
                 merged.merge(
                     &frame_commands
                         .inputs
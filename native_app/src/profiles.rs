@@ -0,0 +1,102 @@
+use common::saveload;
+use common::saveload::{Encoder, JSONPretty, JSON};
+use serde::{Deserialize, Serialize};
+
+/// The default profile uses the historical save directory so existing saves are kept
+pub const DEFAULT_PROFILE: &str = "default";
+const PROFILES_PATH: &str = "profiles.json";
+
+/// Profiles keep separate settings, gui layouts and saves (including the campaign progress)
+/// by giving each profile its own save directory.
+/// The profile is chosen at startup with `--profile <name>`, otherwise the last selected one is used.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profiles {
+    /// Profile used at the next startup
+    pub selected: String,
+    pub list: Vec<String>,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Self {
+            selected: DEFAULT_PROFILE.to_string(),
+            list: vec![DEFAULT_PROFILE.to_string()],
+        }
+    }
+}
+
+impl Profiles {
+    pub fn load() -> Self {
+        saveload::load_raw(PROFILES_PATH)
+            .ok()
+            .and_then(|x| JSON::decode(&x).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let Ok(x) = JSONPretty::encode(self) else {
+            return;
+        };
+        if let Err(e) = std::fs::write(PROFILES_PATH, x) {
+            log::error!("couldn't save profiles: {}", e);
+        }
+    }
+
+    /// Adds a profile, returns false if the name cannot be used as a directory
+    pub fn add(&mut self, name: &str) -> bool {
+        if !is_valid_name(name) {
+            return false;
+        }
+        if !self.list.iter().any(|p| p == name) {
+            self.list.push(name.to_string());
+        }
+        true
+    }
+
+    pub fn dir(name: &str) -> String {
+        if name == DEFAULT_PROFILE {
+            return saveload::DEFAULT_SAVE_DIR.to_string();
+        }
+        format!("profiles/{name}")
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Selects the profile to use, must be called before anything is loaded
+pub fn select_at_startup() {
+    let mut profiles = Profiles::load();
+
+    let mut args = std::env::args().skip_while(|a| a != "--profile").skip(1);
+    if let Some(name) = args.next() {
+        if profiles.add(&name) {
+            profiles.selected = name;
+        } else {
+            log::error!("invalid profile name: {}", name);
+        }
+    }
+    if !profiles.list.contains(&profiles.selected) {
+        profiles.selected = DEFAULT_PROFILE.to_string();
+    }
+    profiles.save();
+
+    log::info!("using profile {}", profiles.selected);
+    saveload::set_save_dir(Profiles::dir(&profiles.selected));
+}
+
+/// The profile in use, it only changes when restarting
+pub fn current() -> String {
+    let dir = saveload::save_dir();
+    Profiles::load()
+        .list
+        .into_iter()
+        .find(|p| Profiles::dir(p) == dir)
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
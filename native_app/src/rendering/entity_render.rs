@@ -1,9 +1,14 @@
 use engine::meshload::load_mesh;
 use engine::{FrameContext, GfxContext, InstancedMeshBuilder, MeshInstance, SpriteBatchBuilder};
-use geom::{LinearColor, Vec3, V3};
+use geom::{Camera, LinearColor, Vec3, V3};
 use simulation::transportation::train::RailWagonKind;
-use simulation::transportation::{Location, VehicleKind};
-use simulation::Simulation;
+use simulation::transportation::VehicleKind;
+use simulation::utils::render_snapshot::RenderSnapshot;
+
+/// Agents further than this from the camera are not drawn in performance mode
+const PERFORMANCE_AGENT_DIST: f32 = 1000.0;
+/// Maximum number of instances drawn per kind of agent in performance mode
+const PERFORMANCE_MAX_AGENTS: usize = 1000;
 
 /// Render all entities using instanced rendering for performance
 pub struct InstancedRender {
@@ -15,6 +20,7 @@ pub struct InstancedRender {
     pub trucks: InstancedMeshBuilder<true>,
     pub pedestrians: InstancedMeshBuilder<true>,
     pub birds: InstancedMeshBuilder<true>,
+    pub planes: InstancedMeshBuilder<true>,
 }
 
 impl InstancedRender {
@@ -34,42 +40,51 @@ impl InstancedRender {
             trucks: InstancedMeshBuilder::new(load_mesh(gfx, "truck.glb").unwrap()),
             pedestrians: InstancedMeshBuilder::new(load_mesh(gfx, "pedestrian.glb").unwrap()),
             birds: InstancedMeshBuilder::new(load_mesh(gfx, "bird.glb").unwrap()),
+            // there is no dedicated plane model yet
+            planes: InstancedMeshBuilder::new(load_mesh(gfx, "train.glb").unwrap()),
         }
     }
 
-    pub fn render(&mut self, sim: &Simulation, fctx: &mut FrameContext<'_>) {
+    pub fn render(
+        &mut self,
+        snapshot: &RenderSnapshot,
+        cam: &Camera,
+        performance_mode: bool,
+        fctx: &mut FrameContext<'_>,
+    ) {
         profiling::scope!("entity_render::render");
         self.cars.instances.clear();
         self.trucks.instances.clear();
         self.pedestrians.instances.clear();
         self.birds.instances.clear();
-        for v in sim.world().vehicles.values() {
-            let trans = &v.trans;
+        self.wagons_passenger.instances.clear();
+        for v in &snapshot.vehicles {
             let instance = MeshInstance {
-                pos: trans.position,
-                dir: trans.dir,
-                tint: v.vehicle.tint.into(),
+                pos: v.pose.pos,
+                dir: v.pose.dir,
+                tint: v.tint.into(),
+                roll: v.roll,
             };
 
-            match v.vehicle.kind {
+            match v.kind {
                 VehicleKind::Car => self.cars.instances.push(instance),
                 VehicleKind::Truck => self.trucks.instances.push(instance),
+                VehicleKind::Tram => self.wagons_passenger.instances.push(instance),
                 _ => {}
             }
         }
 
         self.locomotives.instances.clear();
-        self.wagons_passenger.instances.clear();
         self.wagons_freight.instances.clear();
-        for wagon in sim.world().wagons.values() {
-            let trans = &wagon.trans;
+        for (pose, kind) in &snapshot.wagons {
             let instance = MeshInstance {
-                pos: trans.position,
-                dir: trans.dir,
+                pos: pose.pos,
+                dir: pose.dir,
                 tint: LinearColor::WHITE,
+                roll: 0.0,
             };
 
-            match wagon.wagon.kind {
+            match kind {
                 RailWagonKind::Passenger => {
                     self.wagons_passenger.instances.push(instance);
                 }
@@ -82,32 +97,44 @@ impl InstancedRender {
             }
         }
 
-        for p in sim.world().humans.values() {
-            if matches!(p.location, Location::Outside) {
-                self.pedestrians.instances.push(MeshInstance {
-                    pos: p
-                        .trans
-                        .position
-                        .up(0.5 + 0.4 * p.pedestrian.walk_anim.cos()),
-                    dir: p.trans.dir.xy().z0(),
-                    tint: LinearColor::WHITE,
-                });
-            }
+        for p in &snapshot.pedestrians {
+            self.pedestrians.instances.push(MeshInstance {
+                pos: p.pos,
+                dir: p.dir,
+                tint: LinearColor::WHITE,
+                roll: 0.0,
+            });
         }
 
-        for bird_ent in sim.world().birds.values() {
+        for b in &snapshot.birds {
             self.birds.instances.push(MeshInstance {
-                pos: bird_ent.trans.position,
-                dir: bird_ent.trans.dir,
+                pos: b.pos,
+                dir: b.dir,
+                tint: LinearColor::WHITE,
+                roll: 0.0,
+            });
+        }
+
+        self.planes.instances.clear();
+        for p in &snapshot.planes {
+            self.planes.instances.push(MeshInstance {
+                pos: p.pos,
+                dir: p.dir,
                 tint: LinearColor::WHITE,
+                roll: 0.0,
             });
         }
 
+        if performance_mode {
+            let eye = cam.eye();
+            cull_agents(&mut self.cars.instances, eye);
+            cull_agents(&mut self.trucks.instances, eye);
+            cull_agents(&mut self.pedestrians.instances, eye);
+            cull_agents(&mut self.birds.instances, eye);
+        }
+
         self.path_not_found.clear();
-        for (_, (trans, itin)) in sim.world().query_trans_itin() {
-            let Some(wait) = itin.is_wait_for_reroute() else {
-                continue;
-            };
+        for &(pos, wait) in &snapshot.waiting_reroute {
             if wait == 0 {
                 continue;
             }
@@ -117,7 +144,7 @@ impl InstancedRender {
 
             let s = 7.0;
             self.path_not_found.push(
-                trans.position + off * 3.0 * V3::Y + 3.0 * V3::Z,
+                pos + off * 3.0 * V3::Y + 3.0 * V3::Z,
                 Vec3::X,
                 LinearColor::RED.a(r),
                 (s, s),
@@ -148,5 +175,20 @@ impl InstancedRender {
         if let Some(x) = self.wagons_freight.build(fctx.gfx) {
             fctx.objs.push(Box::new(x));
         }
+        if let Some(x) = self.planes.build(fctx.gfx) {
+            fctx.objs.push(Box::new(x));
+        }
+    }
+}
+
+/// Keeps the agents closest to the camera, within the performance mode distance and budget
+fn cull_agents(instances: &mut Vec<MeshInstance>, eye: Vec3) {
+    instances.retain(|x| x.pos.distance2(eye) < PERFORMANCE_AGENT_DIST * PERFORMANCE_AGENT_DIST);
+    if instances.len() <= PERFORMANCE_MAX_AGENTS {
+        return;
     }
+    instances.select_nth_unstable_by(PERFORMANCE_MAX_AGENTS, |a, b| {
+        a.pos.distance2(eye).total_cmp(&b.pos.distance2(eye))
+    });
+    instances.truncate(PERFORMANCE_MAX_AGENTS);
 }
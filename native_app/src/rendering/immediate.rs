@@ -264,6 +264,7 @@ impl ImmediateDraw {
                         pos,
                         dir,
                         tint: color.a(1.0),
+                        roll: 0.0,
                     });
                 }
             }
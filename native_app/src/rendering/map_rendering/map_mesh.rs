@@ -45,6 +45,8 @@ struct MapBuilders {
     crosswalk_builder: MeshBuilder<false>,
     tess_map: Tesselator<false>,
     tess_lots: Tesselator<false>,
+    /// Coarser meshes without markings nor props, see [`MapRenderOptions::performance_mode`]
+    performance_mode: bool,
 }
 
 impl MapMeshHandler {
@@ -68,6 +70,42 @@ impl MapMeshHandler {
                 SpriteBatchBuilder::new(gfx.texture(asset, "goods_company_tex"), gfx),
             );
         }
+        buildsprites.insert(
+            BuildingKind::SnowPlowDepot,
+            SpriteBatchBuilder::new(
+                gfx.texture("assets/sprites/cement.jpg", "snow_plow_depot_tex"),
+                gfx,
+            ),
+        );
+        buildsprites.insert(
+            BuildingKind::Airport,
+            SpriteBatchBuilder::new(gfx.texture("assets/sprites/cement.jpg", "airport_tex"), gfx),
+        );
+        buildsprites.insert(
+            BuildingKind::Harbor,
+            SpriteBatchBuilder::new(gfx.texture("assets/sprites/cement.jpg", "harbor_tex"), gfx),
+        );
+        buildsprites.insert(
+            BuildingKind::MetroStation,
+            SpriteBatchBuilder::new(
+                gfx.texture("assets/sprites/cement.jpg", "metro_station_tex"),
+                gfx,
+            ),
+        );
+        buildsprites.insert(
+            BuildingKind::ParkingLot,
+            SpriteBatchBuilder::new(
+                gfx.texture("assets/sprites/cement.jpg", "parking_lot_tex"),
+                gfx,
+            ),
+        );
+        buildsprites.insert(
+            BuildingKind::EventVenue,
+            SpriteBatchBuilder::new(
+                gfx.texture("assets/sprites/cement.jpg", "event_venue_tex"),
+                gfx,
+            ),
+        );
 
         for (asset, bkind) in sim
             .read::<GoodsCompanyRegistry>()
@@ -162,6 +200,7 @@ impl MapMeshHandler {
             buildmeshes,
             zonemeshes,
             tess_lots: Tesselator::new(gfx, None, 15.0),
+            performance_mode: false,
         };
 
         Self {
@@ -178,6 +217,15 @@ impl MapMeshHandler {
         options: MapRenderOptions,
         ctx: &mut FrameContext<'_>,
     ) {
+        if options.performance_mode != self.builders.performance_mode {
+            self.builders.performance_mode = options.performance_mode;
+            self.builders.tess_map.zoom = if options.performance_mode { 5.0 } else { 15.0 };
+            for &chunk in self.cache.keys() {
+                self.road_sub.dispatch(UpdateType::Road, chunk);
+                self.building_sub.dispatch(UpdateType::Building, chunk);
+            }
+        }
+
         for chunk in self.road_sub.take_updated_chunks() {
             let b = &mut self.builders;
             b.map_mesh(map, chunk);
@@ -225,7 +273,7 @@ impl MapMeshHandler {
         for v in self.cache.values() {
             ctx.draw(v.build.clone());
             ctx.draw(v.road.clone());
-            if options.show_arrows {
+            if options.show_arrows && !options.performance_mode {
                 if let Some(ref x) = v.arrows {
                     ctx.draw(x.clone());
                 }
@@ -364,9 +412,12 @@ impl MapBuilders {
             self.zone_mesh(building);
             self.houses_mesh(building);
 
+            // sprites and meshes cannot be mirrored, they keep their orientation
+            let obb = building.obb.unmirrored();
+
             if let Some(x) = self.buildsprites.get_mut(&building.kind) {
-                let axis = building.obb.axis();
-                let c = building.obb.center();
+                let axis = obb.axis();
+                let c = obb.center();
                 let w = axis[0].mag();
                 let d = axis[0] / w;
                 let h = axis[1].mag();
@@ -379,13 +430,14 @@ impl MapBuilders {
             }
 
             if let Some(x) = self.buildmeshes.get_mut(&building.kind) {
-                let pos = building.obb.center().z(building.height);
-                let dir = building.obb.axis()[0].normalize().z0();
+                let pos = obb.center().z(building.height);
+                let dir = obb.axis()[0].normalize().z0();
 
                 x.instances.push(MeshInstance {
                     pos,
                     dir,
                     tint: LinearColor::WHITE,
+                    roll: 0.0,
                 });
             }
         }
@@ -427,35 +479,39 @@ impl MapBuilders {
         let principal_dist = (max - min).dot(principal_axis).abs();
         let secondary_dist = (max - min).dot(secondary_axis).abs();
 
-        for principal_offset in (0..=(principal_dist as i32)).step_by(4) {
-            for secondary_offset in (0..=(secondary_dist as i32)).step_by(4) {
-                let mut pos = min
-                    + principal_axis * principal_offset as f32
-                    + secondary_axis * secondary_offset as f32;
-                if randomize {
-                    pos = pos
-                        + vec2(
-                            common::rand::rand3(pos.x, pos.y, 10.0),
-                            common::rand::rand3(pos.x, pos.y, 20.0),
-                        ) * 2.0
-                        - 1.0 * Vec2::XY;
-                }
+        // fillers are props, they are skipped in performance mode
+        if !self.performance_mode {
+            for principal_offset in (0..=(principal_dist as i32)).step_by(4) {
+                for secondary_offset in (0..=(secondary_dist as i32)).step_by(4) {
+                    let mut pos = min
+                        + principal_axis * principal_offset as f32
+                        + secondary_axis * secondary_offset as f32;
+                    if randomize {
+                        pos = pos
+                            + vec2(
+                                common::rand::rand3(pos.x, pos.y, 10.0),
+                                common::rand::rand3(pos.x, pos.y, 20.0),
+                            ) * 2.0
+                            - 1.0 * Vec2::XY;
+                    }
 
-                if !zone.contains(pos) {
-                    continue;
-                }
-                if zone.distance(pos) < 3.0 {
-                    continue;
-                }
-                if hull.contains(pos) {
-                    continue;
-                }
+                    if !zone.contains(pos) {
+                        continue;
+                    }
+                    if zone.distance(pos) < 3.0 {
+                        continue;
+                    }
+                    if hull.contains(pos) {
+                        continue;
+                    }
 
-                filler.instances.push(MeshInstance {
-                    pos: pos.z(building.height),
-                    dir: principal_axis.perpendicular().z0(),
-                    tint: LinearColor::WHITE,
-                });
+                    filler.instances.push(MeshInstance {
+                        pos: pos.z(building.height),
+                        dir: principal_axis.perpendicular().z0(),
+                        tint: LinearColor::WHITE,
+                        roll: 0.0,
+                    });
+                }
             }
         }
 
@@ -552,6 +608,43 @@ impl MapBuilders {
         }
     }
 
+    /// Rails embedded in the street surface, without sleepers
+    fn draw_tram_tracks(tess: &mut Tesselator<false>, cut: &PolyLine3, off: f32) {
+        tess.set_color(Color::gray(0.45));
+        for side in [-0.6, 0.6] {
+            tess.draw_polyline_full(
+                cut.as_slice().iter().map(|v| vec3(v.x, v.y, v.z + 0.01)),
+                unwrap_ret!(cut.first_dir()).xy(),
+                unwrap_ret!(cut.last_dir()).xy(),
+                0.1,
+                off + side,
+            );
+        }
+    }
+
+    /// Overhead lines of electrified rails: a contact wire above the track held by cantilevers
+    fn draw_catenary(tess: &mut Tesselator<false>, cut: &PolyLine3, off: f32) {
+        const WIRE_HEIGHT: f32 = 5.5;
+        tess.set_color(Color::gray(0.2));
+        tess.draw_polyline_full(
+            cut.as_slice().iter().map(|v| v.up(WIRE_HEIGHT)),
+            unwrap_ret!(cut.first_dir()).xy(),
+            unwrap_ret!(cut.last_dir()).xy(),
+            0.08,
+            off,
+        );
+        for (v, dir) in cut.equipoints_dir(40.0, false) {
+            let up = v.up(WIRE_HEIGHT + 0.1);
+            tess.draw_polyline_full(
+                [up, up + dir * 0.2].into_iter(),
+                dir.xy(),
+                dir.xy(),
+                3.0,
+                off,
+            );
+        }
+    }
+
     fn map_mesh(&mut self, map: &Map, chunk: SubscriberChunkID) {
         self.arrow_builder.clear();
         self.crosswalk_builder.clear();
@@ -562,6 +655,8 @@ impl MapBuilders {
         let mid_col: LinearColor = simulation::config().road_mid_col.into();
         let hig_col: LinearColor = simulation::config().road_hig_col.into();
         let line_col: LinearColor = simulation::config().road_line_col.into();
+        let water_col: LinearColor = simulation::config().sea_col.into();
+        let bus_col: LinearColor = simulation::config().road_bus_col.into();
 
         let objs = map.spatial_map().query(
             chunk.bbox(),
@@ -593,69 +688,119 @@ impl MapBuilders {
         for road in chunk_roads {
             let road = &roads[road];
 
-            self.arrows(road, lanes);
-
-            let cut = road.interfaced_points();
-            let first_dir = unwrap_cont!(cut.first_dir());
-            let last_dir = unwrap_cont!(cut.last_dir());
+            if !self.performance_mode {
+                self.arrows(road, lanes);
+            }
 
             road_pylons(&mut self.tess_map.meshbuilder, env, road);
 
-            self.tess_map.normal.z = -1.0;
-            self.tess_map.draw_polyline_full(
-                cut.iter().map(|x| x.up(-0.3)),
-                first_dir.xy(),
-                last_dir.xy(),
-                road.width,
-                0.0,
-            );
-            self.tess_map.normal.z = 1.0;
+            // the parts of the road in a tunnel are not drawn, only their portals
+            let full = road.interfaced_points();
+            let len = full.length();
+            for (from, to) in Road::open_sections(full, env) {
+                let cut = &full.cut(from, len - to);
+                let first_dir = unwrap_cont!(cut.first_dir());
+                let last_dir = unwrap_cont!(cut.last_dir());
+
+                if from > 0.0 {
+                    tunnel_portal(
+                        &mut self.tess_map.meshbuilder,
+                        road.width,
+                        cut.first(),
+                        first_dir,
+                    );
+                }
+                if to < len {
+                    tunnel_portal(
+                        &mut self.tess_map.meshbuilder,
+                        road.width,
+                        cut.last(),
+                        last_dir,
+                    );
+                }
 
-            let draw_off = |tess: &mut Tesselator<false>, col: LinearColor, w, off| {
-                tess.set_color(col);
-                tess.draw_polyline_full(
-                    cut.as_slice().iter().copied(),
+                self.tess_map.normal.z = -1.0;
+                self.tess_map.draw_polyline_full(
+                    cut.iter().map(|x| x.up(-0.3)),
                     first_dir.xy(),
                     last_dir.xy(),
-                    w,
-                    off,
+                    road.width,
+                    0.0,
                 );
-            };
-
-            let mut start = true;
-            for l in road.lanes_iter().flat_map(|(l, _)| lanes.get(l)) {
-                if l.kind.is_rail() {
-                    let off = l.dist_from_bottom - road.width * 0.5 + LaneKind::Rail.width() * 0.5;
-                    draw_off(&mut self.tess_map, mid_col, LaneKind::Rail.width(), off);
-                    Self::draw_rail(&mut self.tess_map, cut, off, true);
-                    start = true;
+                self.tess_map.normal.z = 1.0;
+
+                let draw_off = |tess: &mut Tesselator<false>, col: LinearColor, w, off| {
+                    tess.set_color(col);
+                    tess.draw_polyline_full(
+                        cut.as_slice().iter().copied(),
+                        first_dir.xy(),
+                        last_dir.xy(),
+                        w,
+                        off,
+                    );
+                };
+
+                if self.performance_mode {
+                    // a single strip for the whole road, only the tracks are kept
+                    draw_off(&mut self.tess_map, mid_col, road.width, 0.0);
+                    for l in road.lanes_iter().flat_map(|(l, _)| lanes.get(l)) {
+                        if l.kind.is_rail() {
+                            let off = l.dist_from_bottom - road.width * 0.5
+                                + LaneKind::Rail.width() * 0.5;
+                            Self::draw_rail(&mut self.tess_map, cut, off, true);
+                        }
+                    }
                     continue;
                 }
-                if start {
+
+                let mut start = true;
+                for l in road.lanes_iter().flat_map(|(l, _)| lanes.get(l)) {
+                    if l.kind.is_rail() {
+                        let off =
+                            l.dist_from_bottom - road.width * 0.5 + LaneKind::Rail.width() * 0.5;
+                        draw_off(&mut self.tess_map, mid_col, LaneKind::Rail.width(), off);
+                        Self::draw_rail(&mut self.tess_map, cut, off, true);
+                        if road.electrified {
+                            Self::draw_catenary(&mut self.tess_map, cut, off);
+                        }
+                        start = true;
+                        continue;
+                    }
+                    if start {
+                        draw_off(
+                            &mut self.tess_map,
+                            line_col,
+                            0.25,
+                            l.dist_from_bottom - road.width * 0.5,
+                        );
+                        start = false;
+                    }
+                    draw_off(
+                        &mut self.tess_map,
+                        match l.kind {
+                            LaneKind::Walking => hig_col,
+                            LaneKind::Parking => low_col,
+                            LaneKind::Water => water_col,
+                            LaneKind::Bus => bus_col,
+                            _ => mid_col,
+                        },
+                        l.kind.width() - 0.25,
+                        l.dist_from_bottom - road.width * 0.5 + l.kind.width() * 0.5,
+                    );
                     draw_off(
                         &mut self.tess_map,
                         line_col,
                         0.25,
-                        l.dist_from_bottom - road.width * 0.5,
+                        l.dist_from_bottom - road.width * 0.5 + l.kind.width(),
                     );
-                    start = false;
+                    if l.kind == LaneKind::Tram {
+                        Self::draw_tram_tracks(
+                            &mut self.tess_map,
+                            cut,
+                            l.dist_from_bottom - road.width * 0.5 + l.kind.width() * 0.5,
+                        );
+                    }
                 }
-                draw_off(
-                    &mut self.tess_map,
-                    match l.kind {
-                        LaneKind::Walking => hig_col,
-                        LaneKind::Parking => low_col,
-                        _ => mid_col,
-                    },
-                    l.kind.width() - 0.25,
-                    l.dist_from_bottom - road.width * 0.5 + l.kind.width() * 0.5,
-                );
-                draw_off(
-                    &mut self.tess_map,
-                    line_col,
-                    0.25,
-                    l.dist_from_bottom - road.width * 0.5 + l.kind.width(),
-                );
             }
         }
 
@@ -674,11 +819,15 @@ impl MapBuilders {
                 continue;
             }
 
-            self.crosswalks(inter, lanes);
-
             inter_pylon(&mut self.tess_map.meshbuilder, env, inter, roads);
             intersection_mesh(&mut self.tess_map, &hig_col, inter, roads);
 
+            if self.performance_mode {
+                continue;
+            }
+
+            self.crosswalks(inter, lanes);
+
             // Walking corners
             for turn in inter
                 .turns()
@@ -735,6 +884,7 @@ impl MapBuilders {
             let col = match lot.kind {
                 LotKind::Unassigned => simulation::config().lot_unassigned_col,
                 LotKind::Residential => simulation::config().lot_residential_col,
+                LotKind::Commercial => simulation::config().lot_commercial_col,
             };
             self.tess_lots.set_color(col);
             self.tess_lots
@@ -827,6 +977,111 @@ fn road_pylons(meshb: &mut MeshBuilder<false>, env: &Environment, road: &Road) {
     }
 }
 
+/// Concrete frame at the entrance of a tunnel, `dir` is the direction of the road
+fn tunnel_portal(meshb: &mut MeshBuilder<false>, road_width: f32, pos: Vec3, dir: Vec3) {
+    const THICKNESS: f32 = 0.5;
+    let height = Road::CLEARANCE;
+    let dir = dir.xy().z0().try_normalize().unwrap_or(Vec3::X);
+    let side = dir.perp_up();
+    let half_w = road_width * 0.5 + THICKNESS;
+
+    for s in [-1.0, 1.0] {
+        add_cuboid(
+            meshb,
+            pos + side * s * half_w + Vec3::Z * height * 0.5,
+            dir,
+            [THICKNESS, THICKNESS, height * 0.5],
+        );
+    }
+    add_cuboid(
+        meshb,
+        pos + Vec3::Z * (height + THICKNESS),
+        dir,
+        [THICKNESS, half_w + THICKNESS, THICKNESS],
+    );
+}
+
+/// Box of the pylon color, `half` being its half size along `dir`, across `dir` and vertically.
+/// The bottom face is left out as it is never seen.
+fn add_cuboid(mut meshb: &mut MeshBuilder<false>, center: Vec3, dir: Vec3, half: [f32; 3]) {
+    let color = LinearColor::from(simulation::config().road_pylon_col);
+    let color: [f32; 4] = color.into();
+
+    let x = dir * half[0];
+    let y = dir.perp_up() * half[1];
+    let z = Vec3::Z * half[2];
+    let corner = |sx: f32, sy: f32, sz: f32| center + x * sx + y * sy + z * sz;
+
+    let mr = &mut meshb;
+    let mut quad = move |verts: [Vec3; 4], nor: Vec3| {
+        mr.extend_with(None, move |vertices, add_idx| {
+            for p in verts {
+                vertices.push(MeshVertex {
+                    position: p.into(),
+                    normal: nor,
+                    uv: [0.0; 2],
+                    color,
+                    tangent: [0.0; 4],
+                });
+            }
+            add_idx(0);
+            add_idx(1);
+            add_idx(2);
+
+            add_idx(1);
+            add_idx(3);
+            add_idx(2);
+        });
+    };
+    let nx = dir;
+    let ny = dir.perp_up();
+    quad(
+        [
+            corner(1.0, -1.0, -1.0),
+            corner(1.0, 1.0, -1.0),
+            corner(1.0, -1.0, 1.0),
+            corner(1.0, 1.0, 1.0),
+        ],
+        nx,
+    );
+    quad(
+        [
+            corner(-1.0, 1.0, -1.0),
+            corner(-1.0, -1.0, -1.0),
+            corner(-1.0, 1.0, 1.0),
+            corner(-1.0, -1.0, 1.0),
+        ],
+        -nx,
+    );
+    quad(
+        [
+            corner(-1.0, -1.0, -1.0),
+            corner(1.0, -1.0, -1.0),
+            corner(-1.0, -1.0, 1.0),
+            corner(1.0, -1.0, 1.0),
+        ],
+        -ny,
+    );
+    quad(
+        [
+            corner(1.0, 1.0, -1.0),
+            corner(-1.0, 1.0, -1.0),
+            corner(1.0, 1.0, 1.0),
+            corner(-1.0, 1.0, 1.0),
+        ],
+        ny,
+    );
+    quad(
+        [
+            corner(-1.0, -1.0, 1.0),
+            corner(1.0, -1.0, 1.0),
+            corner(-1.0, 1.0, 1.0),
+            corner(1.0, 1.0, 1.0),
+        ],
+        Vec3::Z,
+    );
+}
+
 fn inter_pylon(
     meshb: &mut MeshBuilder<false>,
     env: &Environment,
@@ -834,7 +1089,7 @@ fn inter_pylon(
     roads: &Roads,
 ) {
     let h = unwrap_ret!(env.height(inter.pos.xy()));
-    if (h - inter.pos.z).abs() <= 2.0 {
+    if inter.pos.z - h <= 2.0 {
         return;
     }
 
@@ -1,7 +1,9 @@
 use engine::{Context, FrameContext, GfxContext, Water};
-use geom::{Camera, Circle, InfiniteFrustrum, Intersect3};
+use geom::{Camera, Circle, Color, InfiniteFrustrum, Intersect3};
 use map_mesh::MapMeshHandler;
-use simulation::map::{Lane, LaneID, LaneKind, Map, ProjectFilter, ProjectKind, TrafficBehavior};
+use simulation::map::{
+    Intersection, Lane, LaneID, LaneKind, Map, ProjectFilter, ProjectKind, TrafficBehavior,
+};
 use simulation::Simulation;
 use terrain::TerrainRender;
 
@@ -26,6 +28,8 @@ pub struct MapRenderer {
 pub struct MapRenderOptions {
     pub show_arrows: bool,
     pub show_lots: bool,
+    /// Skip the props and the road markings, see [`engine::GfxSettings::performance_mode`]
+    pub performance_mode: bool,
 }
 
 impl MapRenderer {
@@ -59,17 +63,21 @@ impl MapRenderer {
         profiling::scope!("render map renderer");
         self.terrain.draw(cam, ctx);
 
-        self.trees.draw(map, cam, ctx);
+        let performance_mode = options.performance_mode;
+
+        if !performance_mode {
+            self.trees.draw(map, cam, ctx);
+        }
 
         self.meshb.latest_mesh(map, options, ctx);
 
-        Self::signals_render(map, time, cam, &ctx.gfx.frustrum, draw);
+        Self::signals_render(map, time, cam, &ctx.gfx.frustrum, performance_mode, draw);
 
         ctx.draw(self.water.clone());
     }
 
     fn render_lane_signals(n: &Lane, draw: &mut ImmediateDraw, time: u32) {
-        if n.control.is_always() {
+        if n.control.is_always() || n.control.is_yield() {
             return;
         }
 
@@ -86,12 +94,29 @@ impl MapRenderer {
         let mesh = match n.control.get_behavior(time) {
             TrafficBehavior::RED | TrafficBehavior::STOP => "traffic_light_red.glb",
             TrafficBehavior::ORANGE => "traffic_light_orange.glb",
-            TrafficBehavior::GREEN => "traffic_light_green.glb",
+            TrafficBehavior::GREEN | TrafficBehavior::YIELD => "traffic_light_green.glb",
         };
 
         draw.mesh(mesh, r_center, dir_perp.z(0.0));
     }
 
+    /// Draws the walk signal of the crosswalks at both of their ends
+    fn render_crosswalk_signals(inter: &Intersection, draw: &mut ImmediateDraw, time: u32) {
+        for turn in inter.turns() {
+            if !turn.kind.is_crosswalk() || !turn.control.is_light() {
+                continue;
+            }
+            let col = match turn.control.get_behavior(time) {
+                TrafficBehavior::GREEN | TrafficBehavior::YIELD => Color::new(0.9, 0.9, 0.9, 1.0),
+                TrafficBehavior::ORANGE => Color::new(1.0, 0.6, 0.1, 1.0),
+                TrafficBehavior::RED | TrafficBehavior::STOP => Color::new(0.9, 0.2, 0.1, 1.0),
+            };
+            for p in [turn.points.first(), turn.points.last()] {
+                draw.circle(p.up(0.05), 0.4).color(col);
+            }
+        }
+    }
+
     fn render_lanes(
         map: &Map,
         lanes: impl Iterator<Item = (LaneID, LaneKind)>,
@@ -119,14 +144,21 @@ impl MapRenderer {
         time: u32,
         cam: &Camera,
         frustrum: &InfiniteFrustrum,
+        performance_mode: bool,
         draw: &mut ImmediateDraw,
     ) {
         let pos = cam.pos;
 
-        for kind in map
-            .spatial_map()
-            .query(Circle::new(pos.xy(), 200.0), ProjectFilter::ROAD)
-        {
+        for kind in map.spatial_map().query(
+            Circle::new(pos.xy(), 200.0),
+            ProjectFilter::ROAD | ProjectFilter::INTER,
+        ) {
+            if let ProjectKind::Inter(id) = kind {
+                if let Some(inter) = map.intersections().get(id) {
+                    Self::render_crosswalk_signals(inter, draw, time);
+                }
+                continue;
+            }
             let ProjectKind::Road(id) = kind else {
                 continue;
             };
@@ -137,7 +169,7 @@ impl MapRenderer {
                 continue;
             }
 
-            if !r.lanes_iter().all(|(_, kind)| kind.is_rail()) {
+            if !performance_mode && !r.lanes_iter().all(|(_, kind)| kind.is_rail()) {
                 let has_sidewalks = r.has_sidewalks();
                 let offset = if has_sidewalks {
                     LaneKind::Walking.width()
@@ -43,6 +43,7 @@ impl TreesRender {
                         pos: t.pos.z(map.environment.height(t.pos).unwrap_or_default()),
                         dir: t.dir.z0() * t.size * 0.2,
                         tint: ((1.0 - t.size * 0.05) * t.col * LinearColor::WHITE).a(1.0),
+                        roll: 0.0,
                     });
                 });
 
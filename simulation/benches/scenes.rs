@@ -0,0 +1,114 @@
+//! Benchmarks running on canonical scenes, so performance changes can be measured objectively.
+//!
+//! Scenes are stored as replays: they are small, readable and stay valid across save format changes.
+//! Run with `cargo bench -p simulation`.
+
+use common::saveload::{Bincode, Encoder, JSONPretty};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use simulation::map::PathKind;
+use simulation::map_dynamic::Itinerary;
+use simulation::utils::scheduler::SeqSchedule;
+use simulation::utils::time::Tick;
+use simulation::{Replay, Simulation};
+
+/// A 6x6 road grid with a few hundred cars driving around
+static SMALL_TOWN: &[u8] = include_bytes!("fixtures/small_town.json");
+/// A 30x30 road grid with thousands of cars driving around
+static BIG_GRID: &[u8] = include_bytes!("fixtures/big_grid.json");
+/// Rail lines, freight stations and trains, shared with the isomorphism test
+static RAIL_HEAVY: &[u8] = include_bytes!("../src/tests/world_replay.json");
+
+const SCENES: [(&str, &[u8]); 3] = [
+    ("small_town", SMALL_TOWN),
+    ("big_grid", BIG_GRID),
+    ("rail_heavy", RAIL_HEAVY),
+];
+
+/// Number of ticks simulated after loading so vehicles are on the move when measuring
+const WARMUP_TICKS: usize = 100;
+const N_PATHS: usize = 100;
+
+fn load_scene(fixture: &[u8]) -> Simulation {
+    let replay: Replay = JSONPretty::decode(fixture).expect("invalid scene fixture");
+    let (mut sim, mut loader) = Simulation::from_replay(replay);
+    loader.speed = 10000;
+
+    // commands are applied without systems, it's much faster and the result is the same map
+    let mut empty = SeqSchedule::default();
+    while !loader.advance_tick(&mut sim, &mut empty) {}
+
+    let mut sched = Simulation::schedule();
+    for _ in 0..WARMUP_TICKS {
+        sim.tick(&mut sched, &[]);
+    }
+    sim
+}
+
+fn tick_time(c: &mut Criterion, name: &str, sim: &mut Simulation) {
+    let mut sched = Simulation::schedule();
+    c.bench_function(&format!("{name}/tick"), |b| {
+        b.iter(|| sim.tick(&mut sched, &[]))
+    });
+}
+
+fn pathfinding(c: &mut Criterion, name: &str, sim: &Simulation) {
+    let map = sim.map();
+    let inters: Vec<_> = map.intersections().values().map(|i| i.pos).collect();
+    if inters.len() < 2 {
+        return;
+    }
+    let pairs: Vec<_> = (0..N_PATHS)
+        .map(|i| {
+            let a = common::rand::rand2(i as f32, 1.0) * inters.len() as f32;
+            let b = common::rand::rand2(i as f32, 2.0) * inters.len() as f32;
+            (
+                inters[(a as usize).min(inters.len() - 1)],
+                inters[(b as usize).min(inters.len() - 1)],
+            )
+        })
+        .collect();
+
+    c.bench_function(&format!("{name}/pathfinding_{N_PATHS}"), |b| {
+        b.iter(|| {
+            pairs
+                .iter()
+                .filter_map(|&(from, to)| {
+                    Itinerary::route(Tick::default(), from, to, &map, PathKind::Vehicle)
+                })
+                .count()
+        })
+    });
+}
+
+fn serialization(c: &mut Criterion, name: &str, sim: &Simulation) {
+    c.bench_function(&format!("{name}/serialize"), |b| {
+        b.iter(|| Bincode::encode(sim).unwrap())
+    });
+
+    let encoded = Bincode::encode(sim).unwrap();
+    c.bench_function(&format!("{name}/deserialize"), |b| {
+        b.iter_batched(
+            || encoded.clone(),
+            |x| Bincode::decode::<Simulation>(&x).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn scenes(c: &mut Criterion) {
+    simulation::init::init();
+
+    for (name, fixture) in SCENES {
+        let mut sim = load_scene(fixture);
+        tick_time(c, name, &mut sim);
+        pathfinding(c, name, &sim);
+        serialization(c, name, &sim);
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = scenes
+}
+criterion_main!(benches);
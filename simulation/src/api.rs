@@ -0,0 +1,243 @@
+use crate::economy::{Government, Money};
+use crate::map::{BuildingID, BuildingKind, IntersectionID, RoadID};
+use crate::utils::events::{SimEvent, SimEvents};
+use crate::utils::layout_import::{LayoutImport, RowFailure};
+use crate::utils::scheduler::SeqSchedule;
+use crate::utils::sim_snapshot::SimSnapshot;
+use crate::utils::time::{GameTime, Tick};
+use crate::world_command::{WorldCommand, WorldCommands};
+use crate::{AnyEntity, Simulation, SimulationOptions};
+use geom::Vec3;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Once;
+use std::time::Duration;
+
+static INIT: Once = Once::new();
+
+/// SimulationHandle is the entry point to embed the simulation as a library.
+/// It owns the simulation and its schedule, so that tools and experiments can step it
+/// without depending on the native_app game loop.
+///
+/// Commands submitted between two steps are applied at the start of the next tick,
+/// the same way the game loop does it, so that runs stay deterministic.
+pub struct SimulationHandle {
+    sim: Simulation,
+    schedule: SeqSchedule,
+    pending: WorldCommands,
+    subscribers: Vec<Sender<SimEvent>>,
+}
+
+/// The position of an entity at the time of the snapshot
+#[derive(Debug, Copy, Clone)]
+pub struct EntitySnapshot {
+    pub id: AnyEntity,
+    pub pos: Vec3,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct RoadSnapshot {
+    pub id: RoadID,
+    pub src: IntersectionID,
+    pub dst: IntersectionID,
+    pub length: f32,
+    pub n_lanes: usize,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct BuildingSnapshot {
+    pub id: BuildingID,
+    pub kind: BuildingKind,
+    pub door_pos: Vec3,
+}
+
+/// A copy of the map network, decoupled from the map so that it can be kept across ticks
+#[derive(Debug, Clone, Default)]
+pub struct MapSnapshot {
+    pub intersections: Vec<(IntersectionID, Vec3)>,
+    pub roads: Vec<RoadSnapshot>,
+    pub buildings: Vec<BuildingSnapshot>,
+}
+
+impl SimulationHandle {
+    /// Creates a new simulation, registering the systems the first time it's called.
+    /// Do not call [`crate::init::init`] yourself when using the handle.
+    pub fn new(opts: SimulationOptions) -> Self {
+        INIT.call_once(crate::init::init);
+        Self::from_simulation(Simulation::new_with_options(opts))
+    }
+
+    /// Loads a save made by the game or by [`SimulationHandle::save`]
+    pub fn load(save_name: &str) -> Option<Self> {
+        INIT.call_once(crate::init::init);
+        Simulation::load_from_disk(save_name).map(Self::from_simulation)
+    }
+
+    fn from_simulation(sim: Simulation) -> Self {
+        Self {
+            sim,
+            schedule: Simulation::schedule(),
+            pending: WorldCommands::default(),
+            subscribers: vec![],
+        }
+    }
+
+    pub fn save(&self, save_name: &str) {
+        self.sim.save_to_disk(save_name);
+    }
+
+    /// Queues a command to be applied at the start of the next tick.
+    /// Rejected commands are reported through [`crate::world_command::RejectedCommands`]
+    pub fn submit(&mut self, command: WorldCommand) {
+        self.pending.push(command);
+    }
+
+    /// Queues the buildings of a csv or json layout, see [`LayoutImport`].
+    /// Returns the rows that were left out and why.
+    pub fn import_layout(&mut self, text: &str) -> Vec<RowFailure> {
+        let import = LayoutImport::new(&self.sim, text);
+        for command in import.commands {
+            self.pending.push(command);
+        }
+        import.failures
+    }
+
+    /// Advances the simulation by n ticks, returns the time spent ticking
+    pub fn step(&mut self, n: u32) -> Duration {
+        let mut spent = Duration::ZERO;
+        for _ in 0..n {
+            let tick = *self.sim.read::<Tick>();
+            let commands = std::mem::take(&mut self.pending);
+            spent += self.sim.tick(&mut self.schedule, commands.iter());
+            self.dispatch_events(tick);
+        }
+        spent
+    }
+
+    /// Returns a receiver getting every event from now on.
+    /// Dropping the receiver unsubscribes it.
+    pub fn subscribe(&mut self) -> Receiver<SimEvent> {
+        let (tx, rx) = channel();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    fn dispatch_events(&mut self, tick: Tick) {
+        if self.subscribers.is_empty() {
+            return;
+        }
+        let events = self.sim.read::<SimEvents>();
+        let events: Vec<SimEvent> = events.during(tick).copied().collect();
+        self.subscribers
+            .retain(|sub| events.iter().all(|&e| sub.send(e).is_ok()));
+    }
+
+    pub fn tick(&self) -> Tick {
+        *self.sim.read::<Tick>()
+    }
+
+    pub fn time(&self) -> GameTime {
+        *self.sim.read::<GameTime>()
+    }
+
+    pub fn money(&self) -> Money {
+        self.sim.read::<Government>().money
+    }
+
+    /// Returns the position of every entity
+    pub fn entities(&self) -> Vec<EntitySnapshot> {
+        self.sim
+            .world
+            .entities()
+            .filter_map(|id| self.entity(id))
+            .collect()
+    }
+
+    pub fn entity(&self, id: AnyEntity) -> Option<EntitySnapshot> {
+        Some(EntitySnapshot {
+            id,
+            pos: self.sim.pos_any(id)?,
+        })
+    }
+
+    pub fn map_snapshot(&self) -> MapSnapshot {
+        let map = self.sim.map();
+        MapSnapshot {
+            intersections: map
+                .intersections()
+                .values()
+                .map(|i| (i.id, i.pos))
+                .collect(),
+            roads: map
+                .roads()
+                .values()
+                .map(|r| RoadSnapshot {
+                    id: r.id,
+                    src: r.src,
+                    dst: r.dst,
+                    length: r.length(),
+                    n_lanes: r.n_lanes(),
+                })
+                .collect(),
+            buildings: map
+                .buildings()
+                .values()
+                .map(|b| BuildingSnapshot {
+                    id: b.id,
+                    kind: b.kind,
+                    door_pos: b.door_pos,
+                })
+                .collect(),
+        }
+    }
+
+    /// Aggregated statistics of the run, ready to be exported as json
+    pub fn stats_snapshot(&self) -> SimSnapshot {
+        SimSnapshot::take(&self.sim)
+    }
+
+    /// Escape hatch to read anything the facade doesn't expose
+    pub fn simulation(&self) -> &Simulation {
+        &self.sim
+    }
+
+    pub fn into_simulation(self) -> Simulation {
+        self.sim
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::procgen::EdgeConnections;
+    use crate::utils::events::SimEventKind;
+    use geom::Vec2;
+
+    #[test]
+    fn step_submit_subscribe() {
+        common::logger::MyLog::init();
+        let mut h = SimulationHandle::new(SimulationOptions {
+            terrain_size: 1,
+            save_replay: false,
+            edge_connections: EdgeConnections::None,
+            ..Default::default()
+        });
+        let rx = h.subscribe();
+        let start = h.tick();
+
+        h.submit(WorldCommand::MapLoadTestField {
+            pos: Vec2::ZERO,
+            size: 2,
+            spacing: 100.0,
+        });
+        h.step(3);
+
+        assert_eq!(h.tick().0, start.0 + 3);
+        assert!(!h.map_snapshot().roads.is_empty());
+
+        h.sim
+            .write::<SimEvents>()
+            .push(h.tick(), Vec3::ZERO, SimEventKind::TrainArrived);
+        h.step(1);
+        assert!(rx.try_iter().any(|e| e.kind == SimEventKind::TrainArrived));
+    }
+}
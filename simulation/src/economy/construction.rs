@@ -0,0 +1,229 @@
+use crate::economy::{Government, Money};
+use crate::utils::time::{
+    GameTime, Tick, SECONDS_PER_DAY, SECONDS_PER_REALTIME_SECOND, TICKS_PER_SECOND,
+};
+use crate::utils::tutorial::tutorial_command;
+use crate::world_command::WorldCommand;
+use crate::Simulation;
+use serde::{Deserialize, Serialize};
+
+/// Spending limit of the construction queue per day when none was chosen
+const DEFAULT_DAILY_LIMIT: Money = Money::new_bucks(20_000);
+/// The queue is funded once per real second, so this many times per game day
+const FUNDINGS_PER_DAY: i64 = (SECONDS_PER_DAY / SECONDS_PER_REALTIME_SECOND as i32) as i64;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ConstructionID(pub u32);
+
+debug_inspect_impl!(ConstructionID);
+
+/// A public construction waiting to be funded, it is built once fully paid
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Construction {
+    pub id: ConstructionID,
+    pub command: WorldCommand,
+    pub cost: Money,
+    pub paid: Money,
+}
+
+impl Construction {
+    /// Share of the cost already paid, between 0 and 1
+    pub fn progress(&self) -> f32 {
+        if self.cost <= Money::ZERO {
+            return 1.0;
+        }
+        (self.paid.inner() as f64 / self.cost.inner() as f64) as f32
+    }
+}
+
+/// The public constructions waiting for the budget to allow them.
+/// The first constructions of the queue are funded first, the player can reorder them.
+#[derive(Serialize, Deserialize)]
+pub struct ConstructionQueue {
+    /// Whether new constructions are queued instead of paid at once.
+    /// The constructions already queued are still funded when disabled.
+    pub enabled: bool,
+    /// Most money spent on the queue per day
+    pub daily_limit: Money,
+    queue: Vec<Construction>,
+    next_id: u32,
+}
+
+impl Default for ConstructionQueue {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            daily_limit: DEFAULT_DAILY_LIMIT,
+            queue: Vec::new(),
+            next_id: 0,
+        }
+    }
+}
+
+impl ConstructionQueue {
+    pub(crate) fn push(&mut self, command: WorldCommand, cost: Money) -> ConstructionID {
+        let id = ConstructionID(self.next_id);
+        self.next_id += 1;
+        self.queue.push(Construction {
+            id,
+            command,
+            cost,
+            paid: Money::ZERO,
+        });
+        id
+    }
+
+    /// The queued constructions, in the order they are funded
+    pub fn iter(&self) -> impl Iterator<Item = &Construction> {
+        self.queue.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn contains(&self, id: ConstructionID) -> bool {
+        self.queue.iter().any(|c| c.id == id)
+    }
+
+    /// Money still needed to build everything in the queue
+    pub fn remaining(&self) -> Money {
+        self.queue.iter().map(|c| c.cost - c.paid).sum()
+    }
+
+    /// Moves the construction to the given place in the queue, 0 being funded first
+    pub(crate) fn reorder(&mut self, id: ConstructionID, position: usize) {
+        let Some(i) = self.queue.iter().position(|c| c.id == id) else {
+            return;
+        };
+        let c = self.queue.remove(i);
+        let position = position.min(self.queue.len());
+        self.queue.insert(position, c);
+    }
+
+    /// Removes the construction from the queue, returning what was already paid for it
+    pub(crate) fn cancel(&mut self, id: ConstructionID) -> Money {
+        let Some(i) = self.queue.iter().position(|c| c.id == id) else {
+            return Money::ZERO;
+        };
+        self.queue.remove(i).paid
+    }
+
+    /// Pays the constructions in order with the budget, returning what was spent and the
+    /// constructions that are now fully paid
+    fn fund(&mut self, mut budget: Money) -> (Money, Vec<Construction>) {
+        let mut spent = Money::ZERO;
+        for c in &mut self.queue {
+            if budget <= Money::ZERO {
+                break;
+            }
+            let pay = (c.cost - c.paid).min(budget);
+            c.paid += pay;
+            budget -= pay;
+            spent += pay;
+        }
+        let mut funded = vec![];
+        self.queue.retain(|c| {
+            if c.paid < c.cost {
+                return true;
+            }
+            funded.push(c.clone());
+            false
+        });
+        (spent, funded)
+    }
+}
+
+/// Funds the construction queue as the budget allows and builds what is fully paid
+pub fn construction_system(sim: &mut Simulation) {
+    profiling::scope!("economy::construction_system");
+    if sim.read::<Tick>().0 % TICKS_PER_SECOND != 0 {
+        return;
+    }
+
+    let funded = {
+        let mut queue = sim.write::<ConstructionQueue>();
+        if queue.is_empty() {
+            return;
+        }
+        let mut gov = sim.write::<Government>();
+        // the queue does not put the government in debt
+        let budget = (queue.daily_limit / FUNDINGS_PER_DAY).min(gov.money);
+        if budget <= Money::ZERO {
+            return;
+        }
+        let (spent, funded) = queue.fund(budget);
+        gov.spend(spent, "Construction", sim.read::<GameTime>().timestamp);
+        funded
+    };
+
+    for c in funded {
+        if c.command.apply_paid(sim).is_err() {
+            sim.write::<Government>().money += c.paid;
+            continue;
+        }
+        tutorial_command(sim, &c.command);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{LanePatternBuilder, MapProject};
+    use crate::tests::TestCtx;
+    use geom::vec3;
+
+    #[test]
+    fn queued_constructions_are_built_once_paid() {
+        let mut test = TestCtx::new();
+        let road = |x: f32| WorldCommand::MapMakeConnection {
+            from: MapProject::ground(vec3(x, 0.0, 0.0)),
+            to: MapProject::ground(vec3(x, 100.0, 0.0)),
+            inter: None,
+            pat: LanePatternBuilder::default().build(),
+        };
+        let cost = Government::action_cost(&road(0.0), &test.g);
+        let per_funding = cost * 3 / 4;
+
+        test.apply(&[WorldCommand::SetConstructionBudget {
+            enabled: true,
+            daily_limit: per_funding * FUNDINGS_PER_DAY,
+        }]);
+        let money = test.g.read::<Government>().money;
+        test.apply(&[road(0.0), road(200.0)]);
+
+        assert_eq!(test.g.read::<Government>().money, money);
+        assert_eq!(test.g.read::<ConstructionQueue>().len(), 2);
+        assert!(test.g.map().roads().is_empty());
+
+        // the second road is funded first
+        let second = test.g.read::<ConstructionQueue>().iter().nth(1).unwrap().id;
+        test.apply(&[WorldCommand::MoveConstruction {
+            id: second,
+            position: 0,
+        }]);
+
+        for _ in 0..2 * TICKS_PER_SECOND {
+            test.tick();
+        }
+        let map = test.g.map();
+        assert_eq!(map.roads().len(), 1);
+        assert!(map.roads().values().next().unwrap().points.first().x > 100.0);
+        drop(map);
+
+        let queue = test.g.read::<ConstructionQueue>();
+        assert_eq!(queue.len(), 1);
+        let (first, paid) = queue.iter().next().map(|c| (c.id, c.paid)).unwrap();
+        assert!(paid > Money::ZERO);
+        drop(queue);
+
+        let money = test.g.read::<Government>().money;
+        test.apply(&[WorldCommand::CancelConstruction(first)]);
+        assert_eq!(test.g.read::<Government>().money, money + paid);
+        assert!(test.g.read::<ConstructionQueue>().is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use crate::economy::{Government, ItemID, ItemRegistry, Market, Money};
+use crate::multiplayer::chat::{Message, MessageKind};
+use crate::multiplayer::MultiplayerState;
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::resources::Resources;
+use crate::utils::time::{GameInstant, GameTime, SECONDS_PER_DAY, SECONDS_PER_HOUR};
+use crate::World;
+use geom::Color;
+use serde::{Deserialize, Serialize};
+
+/// Extra operating cost per vehicle per second when fuel is at its normal price
+const FUEL_SURCHARGE_PER_SECOND: Money = Money::new_cents(1);
+const CRISIS_ANNOUNCER: &str = "World News";
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrisisFrequency {
+    Disabled,
+    Rare,
+    #[default]
+    Normal,
+    Frequent,
+}
+
+impl CrisisFrequency {
+    pub const ALL: [CrisisFrequency; 4] = [
+        CrisisFrequency::Disabled,
+        CrisisFrequency::Rare,
+        CrisisFrequency::Normal,
+        CrisisFrequency::Frequent,
+    ];
+
+    /// Probability that a new crisis starts during a given game hour
+    fn hourly_chance(self) -> f32 {
+        match self {
+            CrisisFrequency::Disabled => 0.0,
+            CrisisFrequency::Rare => 0.005,
+            CrisisFrequency::Normal => 0.015,
+            CrisisFrequency::Frequent => 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CrisisKind {
+    /// The world price of an item is multiplied by the factor
+    PriceShock { item: ItemID, factor: f32 },
+    /// Fuel gets expensive, every vehicle costs `factor` times the fuel surcharge to operate
+    FuelCrisis { factor: f32 },
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Crisis {
+    pub kind: CrisisKind,
+    pub ends_at: GameInstant,
+    /// Price of the affected item before the crisis, restored when it ends
+    base_price: Money,
+}
+
+/// EconomyCrises holds the external shocks currently hitting the economy.
+/// Shocks change the world price of items, which drives the local companies to adapt.
+#[derive(Default, Serialize, Deserialize)]
+pub struct EconomyCrises {
+    pub frequency: CrisisFrequency,
+    active: Vec<Crisis>,
+}
+
+impl EconomyCrises {
+    pub fn active(&self) -> &[Crisis] {
+        &self.active
+    }
+
+    /// Multiplier applied to the operating cost of vehicles
+    pub fn fuel_factor(&self) -> f32 {
+        self.active
+            .iter()
+            .map(|c| match c.kind {
+                CrisisKind::FuelCrisis { factor } => factor,
+                _ => 1.0,
+            })
+            .fold(1.0, f32::max)
+    }
+
+    fn is_item_affected(&self, item: ItemID) -> bool {
+        self.active
+            .iter()
+            .any(|c| matches!(c.kind, CrisisKind::PriceShock { item: i, .. } if i == item))
+    }
+
+    fn has_fuel_crisis(&self) -> bool {
+        self.active
+            .iter()
+            .any(|c| matches!(c.kind, CrisisKind::FuelCrisis { .. }))
+    }
+}
+
+fn scale(m: Money, factor: f32) -> Money {
+    Money::new_inner((m.inner() as f64 * factor as f64) as i64)
+}
+
+pub fn economy_crisis_update(world: &mut World, res: &mut Resources) {
+    profiling::scope!("economy::economy_crisis_update");
+    let time = *res.read::<GameTime>();
+    let mut crises = res.write::<EconomyCrises>();
+
+    if time.tick(1) {
+        let fuel = crises.fuel_factor();
+        if fuel > 1.0 {
+            let surcharge = scale(
+                FUEL_SURCHARGE_PER_SECOND * world.vehicles.len() as i64,
+                fuel - 1.0,
+            );
+            res.write::<Government>()
+                .spend(surcharge, "Fuel surcharge", time.timestamp);
+        }
+    }
+
+    if !time.tick(SECONDS_PER_HOUR as u32) {
+        return;
+    }
+
+    let registry = res.read::<ItemRegistry>();
+    let mut market = res.write::<Market>();
+    let mut announcements = vec![];
+
+    let now = time.instant();
+    crises.active.retain(|c| {
+        if c.ends_at > now {
+            return true;
+        }
+        match c.kind {
+            CrisisKind::PriceShock { item, .. } => {
+                market.m(item).ext_value = c.base_price;
+                let label = registry.get(item).map_or("?", |i| i.label.as_str());
+                announcements.push((
+                    MessageKind::Info,
+                    format!("The world price of {} is back to normal.", label),
+                ));
+            }
+            CrisisKind::FuelCrisis { .. } => {
+                announcements.push((
+                    MessageKind::Info,
+                    "The fuel crisis is over, vehicles are cheap to run again.".to_string(),
+                ));
+            }
+        }
+        false
+    });
+
+    let mut rng = res.write::<RandProvider>();
+    if rng.next_f32() < crises.frequency.hourly_chance() {
+        let duration = SECONDS_PER_DAY as f64 * (1.0 + 2.0 * rng.next_f32() as f64);
+        let ends_at = GameInstant {
+            timestamp: now.timestamp + duration,
+        };
+
+        let crisis = if rng.next_f32() < 0.2 {
+            (!crises.has_fuel_crisis()).then(|| {
+                let factor = 2.0 + 2.0 * rng.next_f32();
+                announcements.push((
+                    MessageKind::Warning,
+                    format!(
+                        "Fuel crisis! Vehicles are {:.0}x more expensive to run.",
+                        factor
+                    ),
+                ));
+                Crisis {
+                    kind: CrisisKind::FuelCrisis { factor },
+                    ends_at,
+                    base_price: Money::ZERO,
+                }
+            })
+        } else {
+            let candidates: Vec<_> = market
+                .inner()
+                .iter()
+                .filter(|(&id, m)| m.ext_value > Money::ZERO && !crises.is_item_affected(id))
+                .map(|(&id, _)| id)
+                .collect();
+            let pick = rng.next_u32() as usize;
+            let spike = rng.next_f32() < 0.5;
+            (!candidates.is_empty()).then(|| {
+                let item = candidates[pick % candidates.len()];
+                let factor = if spike {
+                    1.5 + 1.5 * rng.next_f32()
+                } else {
+                    0.2 + 0.4 * rng.next_f32()
+                };
+                let m = market.m(item);
+                let base_price = m.ext_value;
+                m.ext_value = scale(base_price, factor);
+
+                let label = registry.get(item).map_or("?", |i| i.label.as_str());
+                announcements.push((
+                    MessageKind::Warning,
+                    if spike {
+                        format!("The world price of {} spiked to {}!", label, m.ext_value)
+                    } else {
+                        format!("The world price of {} crashed to {}!", label, m.ext_value)
+                    },
+                ));
+                Crisis {
+                    kind: CrisisKind::PriceShock { item, factor },
+                    ends_at,
+                    base_price,
+                }
+            })
+        };
+
+        crises.active.extend(crisis);
+    }
+
+    if announcements.is_empty() {
+        return;
+    }
+    let mut state = res.write::<MultiplayerState>();
+    for (kind, text) in announcements {
+        state.chat.add_message(Message {
+            name: CRISIS_ANNOUNCER.to_string(),
+            text,
+            sent_at: now,
+            color: match kind {
+                MessageKind::Warning => Color::new(1.0, 0.5, 0.2, 1.0),
+                _ => Color::new(0.6, 0.8, 1.0, 1.0),
+            },
+            kind,
+        });
+    }
+}
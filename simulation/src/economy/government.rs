@@ -1,28 +1,110 @@
 use crate::economy::Money;
 use crate::map::{LanePattern, MapProject, MAX_ZONE_AREA};
+use crate::souls::goods_company::{MAX_FLEET_SIZE, TRUCK_PRICE};
+use crate::transportation::bus::BusLines;
+use crate::transportation::metro::{MetroLines, METRO_TRAIN_PRICE};
+use crate::transportation::train::LocomotiveKind;
+use crate::transportation::waterway::{n_locks, LOCK_PRICE};
+use crate::utils::resources::Resources;
+use crate::utils::time::{GameTime, SECONDS_PER_HOUR};
 use crate::world_command::WorldCommand;
-use crate::{BuildingKind, GoodsCompanyRegistry, Simulation};
+use crate::{BuildingKind, GoodsCompanyRegistry, Simulation, World};
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Price of moving a cubic meter of earth when terraforming, in cents
+const TERRAFORM_CENTS_PER_M3: f32 = 0.1;
+/// Number of hourly records of the money kept, 30 days
+const MONEY_HISTORY_SIZE: usize = 30 * 24;
+/// Expenses at least this big are annotated on the money history
+pub const BIG_EXPENSE: Money = Money::new_bucks(10_000);
+/// Number of big expenses kept
+const MAX_BIG_EXPENSES: usize = 50;
+
+/// A big expense of the player, shown on the money history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Expense {
+    pub timestamp: f64,
+    pub amount: Money,
+    pub label: String,
+}
 
 /// The government represents the player.
 #[derive(Serialize, Deserialize)]
 pub struct Government {
     pub money: Money,
+    /// The money at the start of every hour, with the timestamp
+    history: VecDeque<(f64, Money)>,
+    big_expenses: VecDeque<Expense>,
 }
 
 impl Default for Government {
     fn default() -> Self {
         Self {
             money: Money::new_bucks(150_000),
+            history: VecDeque::new(),
+            big_expenses: VecDeque::new(),
         }
     }
 }
 
 impl Government {
+    /// Pays for something, the big expenses are remembered with the given label
+    pub fn spend(&mut self, amount: Money, label: &str, timestamp: f64) {
+        self.money -= amount;
+        if amount < BIG_EXPENSE {
+            return;
+        }
+        if self.big_expenses.len() >= MAX_BIG_EXPENSES {
+            self.big_expenses.pop_front();
+        }
+        self.big_expenses.push_back(Expense {
+            timestamp,
+            amount,
+            label: label.to_string(),
+        });
+    }
+
+    /// The money at the start of every hour of the last days, oldest first
+    pub fn history(&self) -> impl Iterator<Item = (f64, Money)> + '_ {
+        self.history.iter().copied()
+    }
+
+    /// The last big expenses, oldest first
+    pub fn big_expenses(&self) -> impl Iterator<Item = &Expense> {
+        self.big_expenses.iter()
+    }
+
+    fn record_history(&mut self, timestamp: f64) {
+        if self.history.len() >= MONEY_HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back((timestamp, self.money));
+    }
+
     pub fn action_cost(action: &WorldCommand, sim: &Simulation) -> Money {
         Money::new_bucks(match action {
+            WorldCommand::Batch(commands) => {
+                return commands.iter().map(|c| Self::action_cost(c, sim)).sum();
+            }
             WorldCommand::MapBuildHouse(_) => 100,
-            WorldCommand::AddTrain { n_wagons, .. } => 1000 + 100 * (*n_wagons as i64),
+            WorldCommand::AddTrain { n_wagons, loco, .. } => {
+                let loco_cost = match loco {
+                    LocomotiveKind::Diesel => 1000,
+                    LocomotiveKind::Electric => 1500,
+                };
+                loco_cost + 100 * (*n_wagons as i64)
+            }
+            WorldCommand::MapSetRoadElectrified {
+                road,
+                electrified: true,
+            } => {
+                let m = sim.map();
+                let Some(r) = m.roads().get(*road) else {
+                    return Money::ZERO;
+                };
+                Self::electrification_cost(r.length(), r.n_lanes())
+            }
             WorldCommand::MapMakeConnection { from, to, pat, .. } => {
                 Self::connection_cost(from, to, pat)
             }
@@ -46,6 +128,40 @@ impl Government {
                     (newarea - oldarea) as i64 * zonedescr.price_per_area / MAX_ZONE_AREA as i64,
                 );
             }
+            WorldCommand::SetCompanyLogistics {
+                company,
+                fleet_size,
+                ..
+            } => {
+                let Some(c) = sim.world().companies.get(*company) else {
+                    return Money::ZERO;
+                };
+                let bought = (*fleet_size)
+                    .min(MAX_FLEET_SIZE)
+                    .saturating_sub(c.comp.fleet_size);
+                return TRUCK_PRICE * bought as i64;
+            }
+            WorldCommand::MapAddBusStop(_) => 50,
+            WorldCommand::AddBusLine(line) => line.vehicle_price() * line.n_buses as i64,
+            WorldCommand::UpdateBusLine(id, line) => {
+                let Some(old) = sim.read::<BusLines>().get(*id).map(|l| (l.n_buses, l.tram)) else {
+                    return Money::ZERO;
+                };
+                // switching between buses and trams replaces the whole fleet
+                let bought = if old.1 == line.tram {
+                    line.n_buses.saturating_sub(old.0)
+                } else {
+                    line.n_buses
+                };
+                line.vehicle_price() * bought as i64
+            }
+            WorldCommand::AddMetroLine(line) => METRO_TRAIN_PRICE * line.n_trains as i64,
+            WorldCommand::UpdateMetroLine(id, line) => {
+                let Some(old) = sim.read::<MetroLines>().get(*id).map(|l| l.n_trains) else {
+                    return Money::ZERO;
+                };
+                METRO_TRAIN_PRICE * line.n_trains.saturating_sub(old) as i64
+            }
             WorldCommand::MapMakeMultipleConnections(ref projs, ref links) => {
                 let mut total = 0;
                 for (from, to, _, pat) in links.iter() {
@@ -68,6 +184,12 @@ impl Government {
                 }
                 BuildingKind::RailFreightStation => 1000,
                 BuildingKind::TrainStation => 1000,
+                BuildingKind::SnowPlowDepot => 2000,
+                BuildingKind::Airport => 50_000,
+                BuildingKind::Harbor => 5000,
+                BuildingKind::MetroStation => 3000,
+                BuildingKind::ParkingLot => 500,
+                BuildingKind::EventVenue => 20_000,
                 _ => 0,
             },
             _ => 0,
@@ -76,7 +198,57 @@ impl Government {
 
     fn connection_cost(p1: &MapProject, p2: &MapProject, pat: &LanePattern) -> i64 {
         let dist = p1.pos.distance(p2.pos);
-        50 + ((0.03 * dist) as i64).max(1)
-            * (pat.lanes_forward.len() + pat.lanes_backward.len()) as i64
+        let n_lanes = pat.lanes_forward.len() + pat.lanes_backward.len();
+        let mut cost = 50 + ((0.03 * dist) as i64).max(1) * n_lanes as i64;
+        if pat.electrified {
+            cost += Self::electrification_cost(dist, n_lanes);
+        }
+        if pat.lanes().any(|(kind, _, _)| kind.is_water()) {
+            cost += n_locks([p1.pos, p2.pos]) as i64 * LOCK_PRICE;
+        }
+        cost
+    }
+
+    /// Cost of terraforming, charged per volume of earth moved
+    pub fn terraform_cost(volume: f32) -> Money {
+        Money::new_cents((volume * TERRAFORM_CENTS_PER_M3) as i64)
+    }
+
+    /// Premium for the overhead lines of electrified rails
+    fn electrification_cost(length: f32, n_tracks: usize) -> i64 {
+        ((0.02 * length) as i64).max(1) * n_tracks as i64
+    }
+}
+
+pub fn government_update(_: &mut World, res: &mut Resources) {
+    profiling::scope!("economy::government_update");
+    let time = *res.read::<GameTime>();
+    if !time.tick(SECONDS_PER_HOUR as u32) {
+        return;
+    }
+    res.write::<Government>().record_history(time.timestamp);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn big_expenses_are_remembered() {
+        let mut gov = Government::default();
+        let money = gov.money;
+        gov.spend(Money::new_bucks(100), "House", 1.0);
+        gov.spend(BIG_EXPENSE, "Airport", 2.0);
+        assert_eq!(gov.money, money - Money::new_bucks(100) - BIG_EXPENSE);
+
+        let expenses: Vec<_> = gov.big_expenses().collect();
+        assert_eq!(expenses.len(), 1);
+        assert_eq!(expenses[0].label, "Airport");
+
+        for i in 0..MONEY_HISTORY_SIZE + 10 {
+            gov.record_history(i as f64);
+        }
+        assert_eq!(gov.history().count(), MONEY_HISTORY_SIZE);
+        assert_eq!(gov.history().next().map(|(t, _)| t), Some(10.0));
     }
 }
@@ -1,3 +1,5 @@
+use crate::economy::TransportMode;
+use common::expr::Expr;
 use common::saveload::Encoder;
 use common::FastMap;
 use serde::{Deserialize, Serialize};
@@ -11,6 +13,11 @@ struct ItemDefinition {
     label: String,
     #[serde(default)]
     optout_exttrade: bool,
+    #[serde(default)]
+    transport: TransportMode,
+    /// Formula of how much people want to buy the item, see [`Item::demand`]
+    #[serde(default)]
+    demand: Option<Expr>,
 }
 
 /// Item is the runtime representation of an item, such as meat, wood, etc.
@@ -20,6 +27,22 @@ pub struct Item {
     pub name: String,
     pub label: String,
     pub optout_exttrade: bool,
+    pub transport: TransportMode,
+    pub demand: Option<Expr>,
+}
+
+/// Variables of the demand formula of the items
+pub const DEMAND_VARS: [&str; 1] = ["days"];
+
+impl Item {
+    /// How much people want to buy the item, `days` after they last got it.
+    /// It competes with the other desires: staying home is worth 0.2 and working 0.5.
+    /// `days - 1` if the item has no demand formula
+    pub fn demand(&self, days: f32) -> f32 {
+        self.demand
+            .as_ref()
+            .map_or(days - 1.0, |formula| formula.eval(&[("days", days)]))
+    }
 }
 
 new_key_type! {
@@ -72,11 +95,23 @@ impl ItemRegistry {
         };
         for definition in definitions {
             let name = definition.name.clone();
+            let demand =
+                definition
+                    .demand
+                    .filter(|formula| match formula.check_vars(&DEMAND_VARS) {
+                        Ok(()) => true,
+                        Err(e) => {
+                            log::error!("error loading the demand of {}: {}", name, e);
+                            false
+                        }
+                    });
             let id = self.items.insert_with_key(move |id| Item {
                 id,
                 name: definition.name,
                 label: definition.label,
                 optout_exttrade: definition.optout_exttrade,
+                transport: definition.transport,
+                demand,
             });
             self.item_names.insert(name, id);
             #[cfg(not(test))]
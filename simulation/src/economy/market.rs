@@ -1,4 +1,6 @@
-use crate::economy::{Item, ItemID, ItemRegistry, Money, WORKER_CONSUMPTION_PER_SECOND};
+use crate::economy::{
+    Item, ItemID, ItemRegistry, Money, TransportCosts, TransportMode, WORKER_CONSUMPTION_PER_SECOND,
+};
 use crate::map::BuildingID;
 use crate::map_dynamic::BuildingInfos;
 use crate::souls::goods_company::GoodsCompanyID;
@@ -31,16 +33,18 @@ pub struct SingleMarket {
     sell_orders: BTreeMap<SoulID, SellOrder>,
     pub ext_value: Money,
     optout_exttrade: bool,
+    pub transport: TransportMode,
 }
 
 impl SingleMarket {
-    pub fn new(ext_value: Money, optout_exttrade: bool) -> Self {
+    pub fn new(ext_value: Money, optout_exttrade: bool, transport: TransportMode) -> Self {
         Self {
             capital: Default::default(),
             buy_orders: Default::default(),
             sell_orders: Default::default(),
             ext_value,
             optout_exttrade,
+            transport,
         }
     }
 
@@ -57,11 +61,21 @@ impl SingleMarket {
     pub fn capital_map(&self) -> &BTreeMap<SoulID, i32> {
         &self.capital
     }
+
+    pub fn buy_orders(&self) -> &BTreeMap<SoulID, BuyOrder> {
+        &self.buy_orders
+    }
+
+    pub fn sell_orders(&self) -> &BTreeMap<SoulID, SellOrder> {
+        &self.sell_orders
+    }
 }
 
 /// Market handles good exchanging between souls themselves and the external market.
 /// When goods are exchanges between souls, money is not involved.
 /// When goods are exchanged with the external market, money is involved.
+/// Sellers are picked by the cost of carrying the goods to the buyer, and buyers that are closer
+/// to a freight station or an airport than to any seller buy from the external market.
 #[derive(Serialize, Deserialize)]
 pub struct Market {
     markets: BTreeMap<ItemID, SingleMarket>,
@@ -71,6 +85,8 @@ pub struct Market {
     // reuse the potential vec to avoid allocations
     #[serde(skip)]
     potential: Vec<(Trade, f32)>,
+    #[serde(skip)]
+    transport: TransportCosts,
 }
 
 #[derive(PartialOrd, Ord, PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
@@ -107,16 +123,17 @@ pub fn find_trade_place(
 ) -> Option<BuildingID> {
     match target {
         TradeTarget::Soul(id) => binfos.building_owned_by(id),
-        TradeTarget::ExternalTrade => {
-            map.bkinds
-                .get(&BuildingKind::RailFreightStation)
-                .and_then(|b| {
-                    b.iter()
-                        .filter_map(|&bid| map.buildings.get(bid))
-                        .min_by_key(|&b| OrderedFloat(b.door_pos.xy().distance2(pos)))
-                        .map(|x| x.id)
-                })
-        }
+        TradeTarget::ExternalTrade => [
+            BuildingKind::RailFreightStation,
+            BuildingKind::Airport,
+            BuildingKind::Harbor,
+        ]
+        .iter()
+        .filter_map(|kind| map.bkinds.get(kind))
+        .flatten()
+        .filter_map(|&bid| map.buildings.get(bid))
+        .min_by_key(|&b| OrderedFloat(b.door_pos.xy().distance2(pos)))
+        .map(|x| x.id),
     }
 }
 
@@ -126,10 +143,14 @@ impl Market {
         Self {
             markets: registry
                 .iter()
-                .map(|v| (v.id, SingleMarket::new(prices[&v.id], v.optout_exttrade)))
+                .map(|v| {
+                    let m = SingleMarket::new(prices[&v.id], v.optout_exttrade, v.transport);
+                    (v.id, m)
+                })
                 .collect(),
             all_trades: Default::default(),
             potential: Default::default(),
+            transport: Default::default(),
         }
     }
 
@@ -215,12 +236,27 @@ impl Market {
     /// A trade updates the buy and sell orders from the market, and the capital of the buyers and sellers.
     /// A trade can only be completed if the seller has enough capital.
     /// Please do not keep the trades around much, it needs to be destroyed by the next time you call this function.
-    pub fn make_trades(&mut self) -> &[Trade] {
+    pub fn make_trades(&mut self, map: &Map) -> &[Trade] {
         self.all_trades.clear();
+        self.transport.update(map);
 
         for (&kind, market) in &mut self.markets {
+            let mode = market.transport;
+            let ext_costs: BTreeMap<SoulID, Money> = if market.optout_exttrade {
+                BTreeMap::new()
+            } else {
+                market
+                    .buy_orders
+                    .iter()
+                    .filter_map(|(&buyer, border)| {
+                        let cost = self.transport.external_cost(map, border.pos, mode)?;
+                        Some((buyer, cost))
+                    })
+                    .collect()
+            };
+
             // Naive O(n²) alg
-            // We don't immediatly apply the trades, because we want to find the nearest-positioned trades
+            // We don't immediatly apply the trades, because we want to find the cheapest trades to carry
             for (&seller, sorder) in &market.sell_orders {
                 let qty_sell = sorder.qty as i32;
 
@@ -241,7 +277,12 @@ impl Market {
                     if qty_buy > qty_sell {
                         continue;
                     }
-                    let score = sorder.pos.distance2(border.pos);
+                    let cost = self.transport.cost(map, sorder.pos, border.pos, mode);
+                    if ext_costs.get(&buyer).map_or(false, |&ext| ext < cost) {
+                        // buying from outside the city is cheaper
+                        continue;
+                    }
+                    let score = cost.inner() as f32;
                     self.potential.push((
                         Trade {
                             buyer: TradeTarget::Soul(buyer),
@@ -318,12 +359,14 @@ impl Market {
                     let qty_buy = order.qty as i32;
                     *capital.entry(buyer).or_default() += qty_buy;
 
+                    // we buy from external so we pay, including bringing the goods to the buyer
+                    let price = *ext_value + ext_costs.get(&buyer).copied().unwrap_or(Money::ZERO);
                     self.all_trades.push(Trade {
                         buyer: TradeTarget::Soul(buyer),
                         seller: TradeTarget::ExternalTrade,
                         qty: qty_buy,
                         kind,
-                        money_delta: -(*ext_value * qty_buy as i64), // we buy from external so we pay
+                        money_delta: -(price * qty_buy as i64),
                     });
                 }
 
@@ -341,12 +384,18 @@ impl Market {
                     *cap -= qty_sell;
                     order.qty -= qty_sell as u32;
 
+                    // carrying the goods out of the city is taken from the sale
+                    let transport = self
+                        .transport
+                        .external_cost(map, order.pos, mode)
+                        .unwrap_or(Money::ZERO);
+                    let price = (*ext_value - transport).max(Money::ZERO);
                     self.all_trades.push(Trade {
                         buyer: TradeTarget::ExternalTrade,
                         seller: TradeTarget::Soul(seller),
                         qty: qty_sell,
                         kind,
-                        money_delta: *ext_value * qty_sell as i64,
+                        money_delta: price * qty_sell as i64,
                     });
                 }
             }
@@ -412,7 +461,9 @@ fn calculate_prices(
                 * WORKER_CONSUMPTION_PER_SECOND;
 
             let newprice = (price_consumption
-                + Money::new_inner((price_workers.inner() as f32 * price_multiplier) as i64))
+                + Money::new_inner(
+                    (price_workers.inner() as f32 * company.wage() * price_multiplier) as i64,
+                ))
                 / qty;
 
             minprice = minprice.map(|x: Money| x.min(newprice)).or(Some(newprice));
@@ -439,10 +490,12 @@ fn calculate_prices(
 mod tests {
     use super::Market;
     use crate::economy::{ItemRegistry, WORKER_CONSUMPTION_PER_SECOND};
+    use crate::map::Map;
     use crate::souls::goods_company::{GoodsCompanyDescription, Recipe};
     use crate::world::CompanyID;
     use crate::{GoodsCompanyRegistry, SoulID};
     use common::descriptions::{BuildingGen, CompanyKind};
+    use common::expr::Expr;
     use geom::{vec2, Vec2};
 
     fn mk_ent(id: u64) -> CompanyID {
@@ -483,7 +536,7 @@ mod tests {
         m.sell(seller, Vec2::X, cereal, 3, 5);
         m.sell(seller_far, vec2(10.0, 10.0), cereal, 3, 5);
 
-        let trades = m.make_trades();
+        let trades = m.make_trades(&Map::empty());
 
         assert_eq!(trades.len(), 1);
         let t0 = trades[0];
@@ -532,6 +585,14 @@ mod tests {
                 asset_location: "".to_string(),
                 price: 0,
                 zone: None,
+                opening_hours: None,
+                night_shift: false,
+                qualification: Default::default(),
+                teaches: None,
+                service: None,
+                leisure: None,
+                productivity: None,
+                wage: None,
             });
 
         companies
@@ -552,6 +613,14 @@ mod tests {
                 asset_location: "".to_string(),
                 price: 0,
                 zone: None,
+                opening_hours: None,
+                night_shift: false,
+                qualification: Default::default(),
+                teaches: None,
+                service: None,
+                leisure: None,
+                productivity: None,
+                wage: None,
             });
 
         let prices = super::calculate_prices(&registry, &companies, 1.0);
@@ -563,5 +632,18 @@ mod tests {
             prices[&wheat],
             (price_cereal * 2 + 5 * WORKER_CONSUMPTION_PER_SECOND * 10) / 2
         );
+
+        // the workers of the wheat factory are paid twice the base wage
+        let (_, wheat_factory) = companies
+            .descriptions
+            .iter_mut()
+            .find(|(_, d)| d.name == "Wheat factory")
+            .unwrap();
+        wheat_factory.wage = Some(Expr::parse("1 + 1").unwrap());
+        let prices = super::calculate_prices(&registry, &companies, 1.0);
+        assert_eq!(
+            prices[&wheat],
+            (price_cereal * 2 + 5 * WORKER_CONSUMPTION_PER_SECOND * 10 * 2) / 2
+        );
     }
 }
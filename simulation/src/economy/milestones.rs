@@ -0,0 +1,165 @@
+use crate::economy::{Government, Money};
+use crate::map::BuildingKind;
+use crate::multiplayer::chat::{Message, MessageKind};
+use crate::multiplayer::MultiplayerState;
+use crate::utils::resources::Resources;
+use crate::utils::time::GameTime;
+use crate::{GoodsCompanyRegistry, World};
+use geom::Color;
+use serde::{Deserialize, Serialize};
+
+/// Companies at least this big are considered large factories
+const LARGE_COMPANY_SIZE: f32 = 120.0;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Milestone {
+    pub name: &'static str,
+    pub population: usize,
+    pub money: Money,
+}
+
+/// The milestones of the city, in order. The first one is reached from the start.
+pub static MILESTONES: [Milestone; 4] = [
+    Milestone {
+        name: "Village",
+        population: 0,
+        money: Money::ZERO,
+    },
+    Milestone {
+        name: "Town",
+        population: 100,
+        money: Money::ZERO,
+    },
+    Milestone {
+        name: "City",
+        population: 500,
+        money: Money::new_bucks(200_000),
+    },
+    Milestone {
+        name: "Metropolis",
+        population: 2000,
+        money: Money::new_bucks(500_000),
+    },
+];
+
+/// Milestones tracks the progression of the city.
+/// Advanced buildings are locked until their milestone is reached, unless in sandbox mode.
+#[derive(Serialize, Deserialize)]
+pub struct Milestones {
+    /// When disabled (sandbox), every building is unlocked
+    pub enabled: bool,
+    reached: usize,
+}
+
+impl Default for Milestones {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            reached: 0,
+        }
+    }
+}
+
+impl Milestones {
+    /// Index in `MILESTONES` of the last reached milestone
+    pub fn reached(&self) -> usize {
+        self.reached
+    }
+
+    pub fn next(&self) -> Option<&'static Milestone> {
+        MILESTONES.get(self.reached + 1)
+    }
+
+    /// Index in `MILESTONES` of the milestone needed to build this kind of building
+    pub fn required(kind: BuildingKind, companies: &GoodsCompanyRegistry) -> usize {
+        match kind {
+            BuildingKind::RailFreightStation | BuildingKind::TrainStation => 1,
+            BuildingKind::Airport => 3,
+            BuildingKind::Harbor | BuildingKind::MetroStation | BuildingKind::EventVenue => 2,
+            BuildingKind::GoodsCompany(id) => companies.descriptions.get(id).map_or(0, |d| {
+                if d.size >= LARGE_COMPANY_SIZE {
+                    2
+                } else {
+                    0
+                }
+            }),
+            BuildingKind::House
+            | BuildingKind::ExternalTrading
+            | BuildingKind::SnowPlowDepot
+            | BuildingKind::ParkingLot => 0,
+        }
+    }
+
+    /// Returns the milestone needed to build this kind of building if it is still locked
+    pub fn locked_by(
+        &self,
+        kind: BuildingKind,
+        companies: &GoodsCompanyRegistry,
+    ) -> Option<&'static Milestone> {
+        if !self.enabled {
+            return None;
+        }
+        let required = Self::required(kind, companies);
+        (required > self.reached).then(|| &MILESTONES[required])
+    }
+}
+
+pub fn milestones_update(world: &mut World, res: &mut Resources) {
+    profiling::scope!("economy::milestones_update");
+    if !res.read::<GameTime>().tick(10) {
+        return;
+    }
+    let mut milestones = res.write::<Milestones>();
+    let money = res.read::<Government>().money;
+    let population = world.humans.len();
+
+    while let Some(next) = milestones.next() {
+        if population < next.population || money < next.money {
+            break;
+        }
+        milestones.reached += 1;
+
+        if !milestones.enabled {
+            continue;
+        }
+        let sent_at = res.read::<GameTime>().instant();
+        res.write::<MultiplayerState>().chat.add_message(Message {
+            name: "Milestone".to_string(),
+            text: format!(
+                "Your city became a {}! New buildings are now available.",
+                next.name
+            ),
+            sent_at,
+            color: Color::new(0.4, 1.0, 0.4, 1.0),
+            kind: MessageKind::Info,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locked_until_reached() {
+        let companies = GoodsCompanyRegistry::default();
+        let mut milestones = Milestones::default();
+
+        assert!(milestones
+            .locked_by(BuildingKind::SnowPlowDepot, &companies)
+            .is_none());
+        let locked = milestones.locked_by(BuildingKind::RailFreightStation, &companies);
+        assert_eq!(locked.map(|m| m.name), Some("Town"));
+
+        milestones.reached = 1;
+        assert!(milestones
+            .locked_by(BuildingKind::RailFreightStation, &companies)
+            .is_none());
+
+        milestones.reached = 0;
+        milestones.enabled = false;
+        assert!(milestones
+            .locked_by(BuildingKind::RailFreightStation, &companies)
+            .is_none());
+    }
+}
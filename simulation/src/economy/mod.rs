@@ -7,6 +7,8 @@
 //! - The market, which is the place where goods are exchanged.
 //! - The government, which is the entity representing the player
 //!
+use crate::map::Map;
+use crate::map_dynamic::BuildingInfos;
 use crate::utils::resources::Resources;
 use crate::World;
 use crate::{GoodsCompanyRegistry, SoulID};
@@ -16,20 +18,31 @@ use std::collections::BTreeMap;
 use std::fmt::{Debug, Display, Formatter};
 use std::iter::Sum;
 use std::ops::{Add, AddAssign, Div, Mul, Neg, SubAssign};
+use std::sync::RwLock;
 
+mod construction;
+mod crisis;
 mod ecostats;
 mod government;
 mod item;
 mod market;
+mod milestones;
+mod transport;
+mod wages;
 
-use crate::utils::time::{Tick, TICKS_PER_SECOND};
+use crate::utils::time::{GameTime, Tick, TICKS_PER_SECOND};
 use crate::world::HumanID;
+pub use construction::*;
+pub use crisis::*;
 pub use ecostats::*;
 pub use government::*;
 pub use item::*;
 pub use market::*;
+pub use milestones::*;
+pub use transport::*;
+pub use wages::*;
 
-const WORKER_CONSUMPTION_PER_SECOND: Money = Money::new_cents(1);
+pub(crate) const WORKER_CONSUMPTION_PER_SECOND: Money = Money::new_cents(1);
 
 /// Money in cents, can be negative when expressing debt.
 #[derive(Default, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
@@ -39,18 +52,76 @@ pub struct Money(i64);
 
 debug_inspect_impl!(Money);
 
+/// Symbol the money is shown with, empty for the default `$`
+static CURRENCY_SYMBOL: RwLock<String> = RwLock::new(String::new());
+
+/// Changes the symbol the money is shown with, it is a setting of the player
+pub fn set_currency_symbol(symbol: &str) {
+    *CURRENCY_SYMBOL.write().unwrap() = symbol.trim().to_string();
+}
+
+fn write_sign_and_symbol(f: &mut Formatter<'_>, negative: bool) -> std::fmt::Result {
+    if negative {
+        f.write_str("-")?;
+    }
+    match CURRENCY_SYMBOL.read().unwrap().as_str() {
+        "" => f.write_str("$"),
+        symbol => f.write_str(symbol),
+    }
+}
+
+/// Writes the number with commas between the groups of thousands
+fn write_thousands(f: &mut Formatter<'_>, n: u64) -> std::fmt::Result {
+    if n >= 1000 {
+        write_thousands(f, n / 1000)?;
+        return write!(f, ",{:03}", n % 1000);
+    }
+    Display::fmt(&n, f)
+}
+
 impl Display for Money {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        Display::fmt(&(self.bucks()), f)?;
-        let cent = (self.0 % 10000) / 100;
+        let abs = self.0.unsigned_abs();
+        write_sign_and_symbol(f, self.0 < 0)?;
+        write_thousands(f, abs / 10000)?;
+        let cent = (abs % 10000) / 100;
         if cent > 0 {
-            f.write_str(".")?;
-            if cent < 10 {
-                f.write_str("0")?;
-            }
-            Display::fmt(&cent, f)?;
+            write!(f, ".{:02}", cent)?;
         }
-        f.write_str("$")
+        Ok(())
+    }
+}
+
+/// Money rounded to a short form such as `$1.2M`, see [`Money::compact`]
+#[derive(Copy, Clone)]
+pub struct CompactMoney(Money);
+
+impl Display for CompactMoney {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let bucks = self.0.bucks().unsigned_abs();
+        if bucks < 10_000 {
+            write_sign_and_symbol(f, self.0 .0 < 0)?;
+            return write_thousands(f, bucks);
+        }
+        let mut units = ["k", "M", "B", "T"].into_iter();
+        let mut unit = units.next().unwrap();
+        let mut div = 1000;
+        // tenths of the unit, rounded. 999,960 is shown as 1M rather than 1000k
+        let mut tenths = (bucks * 10 + div / 2) / div;
+        while tenths >= 10_000 {
+            let Some(next) = units.next() else {
+                break;
+            };
+            unit = next;
+            div *= 1000;
+            tenths = (bucks * 10 + div / 2) / div;
+        }
+        write_sign_and_symbol(f, self.0 .0 < 0)?;
+        write_thousands(f, tenths / 10)?;
+        if tenths % 10 != 0 {
+            write!(f, ".{}", tenths % 10)?;
+        }
+        f.write_str(unit)
     }
 }
 
@@ -153,6 +224,12 @@ impl Money {
     pub fn bucks(&self) -> i64 {
         self.0 / 10000
     }
+
+    /// Short form for the places with little room, such as `$1.2M`.
+    /// Amounts under $10,000 are shown in full without the cents
+    pub fn compact(self) -> CompactMoney {
+        CompactMoney(self)
+    }
 }
 
 #[derive(Inspect, Default, Serialize, Deserialize)]
@@ -194,28 +271,39 @@ pub fn init_market(_: &mut World, res: &mut Resources) {
 
 pub fn market_update(world: &mut World, resources: &mut Resources) {
     profiling::scope!("economy::market_update");
-    let n_workers = world.humans.len();
-
     let mut m = resources.write::<Market>();
     let job_opening = resources.read::<ItemRegistry>().id("job-opening");
     let mut gvt = resources.write::<Government>();
     let tick = resources.read::<Tick>().0;
 
+    let map = resources.read::<Map>();
+
     if tick % TICKS_PER_SECOND == 0 {
-        gvt.money -= n_workers as i64 * WORKER_CONSUMPTION_PER_SECOND;
+        let timestamp = resources.read::<GameTime>().timestamp;
+        let registry = resources.read::<GoodsCompanyRegistry>();
+        pay_wages(world, &map, &registry, &mut gvt, timestamp);
     }
 
-    let trades = m.make_trades();
+    let mut binfos = resources.write::<BuildingInfos>();
+    let trades = m.make_trades(&map);
 
     resources.write::<EcoStats>().advance(tick, trades);
 
+    // people hired by full workplaces, they go back looking for a job
+    let mut refused: Vec<HumanID> = vec![];
+
     for &trade in trades.iter() {
         log::debug!("A trade was made! {:?}", trade);
 
         if trade.kind == job_opening {
             if let SoulID::GoodsCompany(id) = trade.seller.soul() {
                 let comp = world.companies.get_mut(id).unwrap();
-                comp.workers.0.push(trade.buyer.soul().try_into().unwrap())
+                let worker: HumanID = trade.buyer.soul().try_into().unwrap();
+                if binfos.add_worker(comp.comp.building) {
+                    comp.workers.0.push(worker)
+                } else {
+                    refused.push(worker);
+                }
             }
         }
         gvt.money += trade.money_delta;
@@ -246,4 +334,33 @@ pub fn market_update(world: &mut World, resources: &mut Resources) {
             TradeTarget::ExternalTrade => {}
         }
     }
+
+    for worker in refused {
+        let Some(house) = world
+            .humans
+            .get(worker)
+            .and_then(|h| map.buildings().get(h.home.house))
+        else {
+            continue;
+        };
+        m.buy(SoulID::Human(worker), house.door_pos.xy(), job_opening, 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Money;
+
+    #[test]
+    fn money_formatting() {
+        assert_eq!(Money::new_bucks(12_839_122).to_string(), "$12,839,122");
+        assert_eq!(Money::new_cents(-100_005).to_string(), "-$1,000.05");
+        assert_eq!(Money::new_bucks(999).to_string(), "$999");
+
+        assert_eq!(Money::new_bucks(9_999).compact().to_string(), "$9,999");
+        assert_eq!(Money::new_bucks(12_839_122).compact().to_string(), "$12.8M");
+        assert_eq!(Money::new_bucks(-1_200_000).compact().to_string(), "-$1.2M");
+        assert_eq!(Money::new_bucks(999_960).compact().to_string(), "$1M");
+        assert_eq!(Money::new_bucks(45_000).compact().to_string(), "$45k");
+    }
 }
@@ -0,0 +1,329 @@
+use crate::economy::Money;
+use crate::map::{BuildingKind, IntersectionID, LaneKind, Map, ProjectFilter, ProjectKind};
+use common::FastMap;
+use geom::Vec2;
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
+/// Cost of carrying one unit over one kilometer by truck
+const TRUCK_COST_PER_KM: Money = Money::new_cents(1);
+/// Cost of carrying one unit over one kilometer by freight train
+const RAIL_COST_PER_KM: Money = Money::new_inner(25);
+/// Cost of carrying one unit over one kilometer by barge on the canals
+const WATER_COST_PER_KM: Money = Money::new_inner(10);
+/// Cost of getting one unit through a canal lock
+const LOCK_COST: Money = Money::new_inner(20);
+/// Cost of moving one unit between a truck and a train or a barge at a freight station or harbor
+const TRANSFER_COST: Money = Money::new_cents(1);
+/// Industries this close to a canal have their own wharf to load barges
+const CANAL_SIDE_REACH: f32 = 60.0;
+/// Straight line distances are this much shorter than the roads, used off the network
+const DETOUR: f32 = 1.3;
+/// How far from a position the network can be entered
+const ACCESS_RADIUS: f32 = 300.0;
+
+/// How an item is carried between the souls trading it
+#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransportMode {
+    /// Carried by trucks on the roads
+    #[default]
+    Truck,
+    /// Heavy goods, carried by freight trains on the rails or by barges on the canals where the
+    /// city has them
+    Bulk,
+    /// Not carried at all, like electricity on the grid
+    Free,
+}
+
+#[derive(Copy, Clone)]
+struct Edge {
+    length: f32,
+    road: bool,
+    rail: bool,
+    water: bool,
+    locks: u32,
+    /// Between a road and a rail or canal intersection next to a freight station or a harbor
+    transfer: bool,
+}
+
+impl Edge {
+    fn cost(&self, mode: TransportMode) -> Option<f32> {
+        let per_km = |c: Money| self.length * 0.001 * c.inner() as f32;
+        let truck = self.road.then(|| per_km(TRUCK_COST_PER_KM));
+        match mode {
+            TransportMode::Truck => truck,
+            TransportMode::Bulk if self.transfer => Some(TRANSFER_COST.inner() as f32),
+            TransportMode::Bulk => {
+                let rail = self.rail.then(|| per_km(RAIL_COST_PER_KM));
+                let water = self.water.then(|| {
+                    per_km(WATER_COST_PER_KM) + (LOCK_COST.inner() * self.locks as i64) as f32
+                });
+                [truck, rail, water]
+                    .into_iter()
+                    .flatten()
+                    .min_by_key(|&c| OrderedFloat(c))
+            }
+            TransportMode::Free => Some(0.0),
+        }
+    }
+}
+
+/// Estimates the cost of carrying goods between two positions on the road and rail networks.
+/// The networks are cached until a road or a building is added or removed.
+#[derive(Default)]
+pub struct TransportCosts {
+    /// Network version of the map the graph was built for
+    version: Option<u32>,
+    graph: BTreeMap<IntersectionID, Vec<(IntersectionID, Edge)>>,
+    /// Where goods enter and leave the city
+    external: Vec<Vec2>,
+    /// Cost to every reachable intersection, from the intersections queried so far
+    cache: FastMap<(TransportMode, IntersectionID), BTreeMap<IntersectionID, f32>>,
+}
+
+impl TransportCosts {
+    pub fn update(&mut self, map: &Map) {
+        if self.version == Some(map.network_version) {
+            return;
+        }
+        self.version = Some(map.network_version);
+        self.graph.clear();
+        self.cache.clear();
+
+        for road in map.roads().values() {
+            let edge = Edge {
+                length: road.length(),
+                road: road
+                    .lanes_iter()
+                    .any(|(_, k)| k.is_driving() || *k == LaneKind::Bus),
+                rail: road.is_rail(),
+                water: road.is_canal(),
+                locks: road.locks(),
+                transfer: false,
+            };
+            if !edge.road && !edge.rail && !edge.water {
+                continue;
+            }
+            self.graph
+                .entry(road.src)
+                .or_default()
+                .push((road.dst, edge));
+            self.graph
+                .entry(road.dst)
+                .or_default()
+                .push((road.src, edge));
+        }
+
+        // Freight stations let goods switch between trucks and trains
+        let stations = map.bkinds.get(&BuildingKind::RailFreightStation);
+        for b in stations.into_iter().flatten() {
+            let Some(b) = map.buildings().get(*b) else {
+                continue;
+            };
+            let road = self.nearest(map, b.door_pos.xy(), |e| e.road);
+            let rail = self.nearest(map, b.obb.center(), |e| e.rail);
+            if let (Some((road, _)), Some((rail, _))) = (road, rail) {
+                self.add_transfer(road, rail);
+            }
+        }
+
+        // Harbors and canal-side industries let goods switch between trucks and barges
+        let harbors = map.bkinds.get(&BuildingKind::Harbor).into_iter().flatten();
+        for b in harbors.filter_map(|&b| map.buildings().get(b)) {
+            let road = self.nearest(map, b.door_pos.xy(), |e| e.road);
+            let water = self.nearest(map, b.obb.center(), |e| e.water);
+            if let (Some((road, _)), Some((water, _))) = (road, water) {
+                self.add_transfer(road, water);
+            }
+        }
+        for b in map.buildings().values() {
+            if b.kind.as_goods_company().is_none() {
+                continue;
+            }
+            let center = b.obb.center().z(b.door_pos.z);
+            if map
+                .nearest_lane(center, LaneKind::Water, Some(CANAL_SIDE_REACH))
+                .is_none()
+            {
+                continue;
+            }
+            let road = self.nearest(map, b.door_pos.xy(), |e| e.road);
+            let water = self.nearest(map, b.obb.center(), |e| e.water);
+            if let (Some((road, _)), Some((water, _))) = (road, water) {
+                self.add_transfer(road, water);
+            }
+        }
+
+        self.external = [
+            BuildingKind::RailFreightStation,
+            BuildingKind::Airport,
+            BuildingKind::Harbor,
+        ]
+        .iter()
+        .filter_map(|kind| map.bkinds.get(kind))
+        .flatten()
+        .filter_map(|&b| map.buildings().get(b))
+        .map(|b| b.door_pos.xy())
+        .collect();
+    }
+
+    fn add_transfer(&mut self, a: IntersectionID, b: IntersectionID) {
+        let edge = Edge {
+            length: 0.0,
+            road: false,
+            rail: false,
+            water: false,
+            locks: 0,
+            transfer: true,
+        };
+        self.graph.entry(a).or_default().push((b, edge));
+        self.graph.entry(b).or_default().push((a, edge));
+    }
+
+    /// Estimated cost of carrying one unit from `from` to `to`
+    pub fn cost(&mut self, map: &Map, from: Vec2, to: Vec2, mode: TransportMode) -> Money {
+        if mode == TransportMode::Free {
+            return Money::ZERO;
+        }
+        let truck_per_m = TRUCK_COST_PER_KM.inner() as f32 * 0.001;
+        let direct = from.distance(to) * DETOUR * truck_per_m;
+
+        let network = self.network_cost(map, from, to, mode);
+        Money::new_inner(network.unwrap_or(direct) as i64)
+    }
+
+    fn network_cost(
+        &mut self,
+        map: &Map,
+        from: Vec2,
+        to: Vec2,
+        mode: TransportMode,
+    ) -> Option<f32> {
+        let (a, da) = self.nearest(map, from, |e| e.road)?;
+        let (b, db) = self.nearest(map, to, |e| e.road)?;
+        let graph = &self.graph;
+        let net = *self
+            .cache
+            .entry((mode, a))
+            .or_insert_with(|| dijkstra(graph, a, mode))
+            .get(&b)?;
+        let truck_per_m = TRUCK_COST_PER_KM.inner() as f32 * 0.001;
+        Some((da + db) * DETOUR * truck_per_m + net)
+    }
+
+    /// Estimated cost of carrying one unit between `to` and the nearest place trading with the
+    /// outside of the city, None if there is no such place
+    pub fn external_cost(&mut self, map: &Map, to: Vec2, mode: TransportMode) -> Option<Money> {
+        let nearest = self
+            .external
+            .iter()
+            .copied()
+            .min_by_key(|p| OrderedFloat(p.distance2(to)))?;
+        Some(self.cost(map, nearest, to, mode))
+    }
+
+    /// The nearest intersection to `p` with an edge matching the filter, and its distance
+    fn nearest(
+        &self,
+        map: &Map,
+        p: Vec2,
+        filter: impl Fn(&Edge) -> bool,
+    ) -> Option<(IntersectionID, f32)> {
+        map.spatial_map()
+            .query_around(p, ACCESS_RADIUS, ProjectFilter::INTER)
+            .filter_map(|k| match k {
+                ProjectKind::Inter(id) => Some(id),
+                _ => None,
+            })
+            .filter(|id| {
+                self.graph
+                    .get(id)
+                    .map_or(false, |edges| edges.iter().any(|(_, e)| filter(e)))
+            })
+            .filter_map(|id| Some((id, map.intersections().get(id)?.pos.xy().distance(p))))
+            .min_by_key(|&(id, d)| (OrderedFloat(d), id))
+    }
+}
+
+fn dijkstra(
+    graph: &BTreeMap<IntersectionID, Vec<(IntersectionID, Edge)>>,
+    src: IntersectionID,
+    mode: TransportMode,
+) -> BTreeMap<IntersectionID, f32> {
+    let mut costs = BTreeMap::new();
+    let mut heap = BinaryHeap::new();
+    heap.push(Reverse((OrderedFloat(0.0), src)));
+
+    while let Some(Reverse((OrderedFloat(cost), node))) = heap.pop() {
+        if costs.contains_key(&node) {
+            continue;
+        }
+        costs.insert(node, cost);
+        for (next, edge) in graph.get(&node).into_iter().flatten() {
+            if costs.contains_key(next) {
+                continue;
+            }
+            if let Some(c) = edge.cost(mode) {
+                heap.push(Reverse((OrderedFloat(cost + c), *next)));
+            }
+        }
+    }
+
+    costs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use geom::{vec2, vec3};
+
+    #[test]
+    fn transport_follows_the_network() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[
+            vec3(0.0, 0.0, 0.0),
+            vec3(1000.0, 0.0, 0.0),
+            vec3(1000.0, 1000.0, 0.0),
+        ]);
+        let map = test.g.map();
+        let mut transport = TransportCosts::default();
+        transport.update(&map);
+
+        let from = vec2(0.0, 10.0);
+        let to = vec2(1000.0, 990.0);
+        let truck = transport.cost(&map, from, to, TransportMode::Truck);
+        // goes around the corner instead of in a straight line
+        assert!(truck.inner() as f32 > 1.9 * TRUCK_COST_PER_KM.inner() as f32);
+        assert_eq!(
+            transport.cost(&map, from, to, TransportMode::Free),
+            Money::ZERO
+        );
+        assert!(transport
+            .external_cost(&map, to, TransportMode::Truck)
+            .is_none());
+
+        let near = transport.cost(&map, from, vec2(500.0, 10.0), TransportMode::Truck);
+        assert!(near < truck);
+    }
+
+    #[test]
+    fn barges_are_cheap_but_slowed_by_locks() {
+        let edge = |rail, water, locks| Edge {
+            length: 1000.0,
+            road: false,
+            rail,
+            water,
+            locks,
+            transfer: false,
+        };
+        let barge = |locks| edge(false, true, locks).cost(TransportMode::Bulk).unwrap();
+        let train = edge(true, false, 0).cost(TransportMode::Bulk).unwrap();
+
+        assert!(barge(0) < train);
+        assert!(barge(2) > barge(0));
+        assert!(edge(false, true, 0).cost(TransportMode::Truck).is_none());
+    }
+}
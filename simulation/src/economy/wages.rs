@@ -0,0 +1,149 @@
+use crate::economy::{Government, Money, WORKER_CONSUMPTION_PER_SECOND};
+use crate::map::Map;
+use crate::souls::goods_company::GoodsCompanyRegistry;
+use crate::utils::time::SECONDS_PER_HOUR;
+use crate::World;
+use egui_inspect::Inspect;
+use serde::{Deserialize, Serialize};
+
+/// What a human earned working for the companies of the city
+#[derive(Inspect, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Earnings {
+    /// Earned and not spent yet
+    pub savings: Money,
+    /// Everything ever earned
+    pub earned: Money,
+    /// Number of seconds the earnings were paid for
+    pub paid_seconds: u32,
+}
+
+impl Earnings {
+    /// Pays the wage of one second of work
+    pub fn earn(&mut self, wage: Money) {
+        self.savings += wage;
+        self.earned += wage;
+        self.paid_seconds += 1;
+    }
+
+    /// Takes up to `amount` from the savings, returns what was taken
+    pub fn take(&mut self, amount: Money) -> Money {
+        let taken = amount.min(self.savings).max(Money::ZERO);
+        self.savings -= taken;
+        taken
+    }
+
+    /// Average wage per hour, None if never paid
+    pub fn hourly_wage(&self) -> Option<Money> {
+        if self.paid_seconds == 0 {
+            return None;
+        }
+        Some(Money::new_inner(
+            self.earned.inner() * SECONDS_PER_HOUR as i64 / self.paid_seconds as i64,
+        ))
+    }
+}
+
+/// Pays one second of wages to the workers of every company, the relative wage of the
+/// company's description applies on top of the base wage
+pub(crate) fn pay_wages(
+    world: &mut World,
+    map: &Map,
+    registry: &GoodsCompanyRegistry,
+    gvt: &mut Government,
+    timestamp: f64,
+) {
+    let mut total = Money::ZERO;
+    for c in world.companies.values() {
+        let relative = map
+            .buildings()
+            .get(c.comp.building)
+            .and_then(|b| b.kind.as_goods_company())
+            .and_then(|id| registry.descriptions.get(id))
+            .map_or(1.0, |d| d.wage());
+        let wage = Money::new_inner(
+            (WORKER_CONSUMPTION_PER_SECOND.inner() as f32 * relative).round() as i64,
+        );
+        for &worker in &c.workers.0 {
+            let Some(h) = world.humans.get_mut(worker) else {
+                continue;
+            };
+            h.earnings.earn(wage);
+            total += wage;
+        }
+    }
+    gvt.spend(total, "Wages", timestamp);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::souls::goods_company::{company_soul, GoodsCompany, Recipe};
+    use crate::souls::human::spawn_human;
+    use crate::tests::TestCtx;
+    use crate::SoulID;
+    use common::descriptions::CompanyKind;
+    use geom::{vec2, vec3};
+
+    #[test]
+    fn workers_are_paid_by_the_government() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(50.0, 50.0));
+        let worker = spawn_human(&mut test.g, house).unwrap();
+        let idle = spawn_human(&mut test.g, house).unwrap();
+
+        let soul = company_soul(
+            &mut test.g,
+            GoodsCompany {
+                kind: CompanyKind::Store,
+                recipe: Recipe {
+                    consumption: vec![],
+                    production: vec![],
+                    complexity: 100,
+                    storage_multiplier: 5,
+                },
+                building: house,
+                max_workers: 1,
+                qualification: Default::default(),
+                progress: 0.0,
+                fleet: vec![],
+                fleet_size: 0,
+                freight_mode: Default::default(),
+                fleet_utilization: 0.0,
+                brand: Default::default(),
+                money: Money::ZERO,
+            },
+        )
+        .unwrap();
+        let SoulID::GoodsCompany(company) = soul else {
+            unreachable!()
+        };
+        let world = test.g.world_mut_unchecked();
+        world.companies[company].workers.0.push(worker);
+
+        let money = test.g.read::<Government>().money;
+        for _ in 0..3 {
+            let (world, res) = test.g.world_res();
+            pay_wages(
+                world,
+                &res.read::<Map>(),
+                &res.read::<GoodsCompanyRegistry>(),
+                &mut res.write::<Government>(),
+                0.0,
+            );
+        }
+
+        let world = test.g.world();
+        let earnings = &world.humans[worker].earnings;
+        assert_eq!(earnings.savings, 3 * WORKER_CONSUMPTION_PER_SECOND);
+        assert_eq!(
+            earnings.hourly_wage(),
+            Some(SECONDS_PER_HOUR as i64 * WORKER_CONSUMPTION_PER_SECOND)
+        );
+        assert_eq!(world.humans[idle].earnings.hourly_wage(), None);
+        assert_eq!(
+            test.g.read::<Government>().money,
+            money - 3 * WORKER_CONSUMPTION_PER_SECOND
+        );
+    }
+}
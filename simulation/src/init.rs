@@ -1,25 +1,48 @@
-use crate::economy::{init_market, market_update, EcoStats, Government, ItemRegistry, Market};
+use crate::economy::{
+    construction_system, economy_crisis_update, government_update, init_market, market_update,
+    milestones_update, ConstructionQueue, EcoStats, EconomyCrises, Government, ItemRegistry,
+    Market, Milestones,
+};
+use crate::map::procgen::{init_building_archetypes, BuildingArchetypes};
 use crate::map::Map;
 use crate::map_dynamic::{
-    dispatch_system, itinerary_update, routing_changed_system, routing_update_system,
-    BuildingInfos, Dispatcher, ParkingManagement,
+    building_risk_update, dispatch_system, itinerary_invalidation, itinerary_update,
+    leisure_venues_system, night_activity_system, parking_enforcement_update, policies_system,
+    rezoning_system, routing_changed_system, routing_update_system, venue_events_system,
+    BuildingInfos, BuildingRisks, Dispatcher, IntersectionStats, ItineraryIndex, LeisureVenues,
+    MapMarkers, NightActivity, ParkingEnforcement, ParkingManagement, ParkingPressure, Policies,
+    Rezoning, TrafficFlow, TripStats, VenueEvents,
 };
 use crate::multiplayer::MultiplayerState;
 use crate::physics::coworld_synchronize;
+use crate::souls::education::education_system;
 use crate::souls::freight_station::freight_station_system;
 use crate::souls::goods_company::{company_system, GoodsCompanyRegistry};
+use crate::souls::health::{health_system, HealthStats};
 use crate::souls::human::update_decision_system;
+use crate::transportation::airport::{airport_system, Airports};
+use crate::transportation::bus::{bus_system, BusLines};
+use crate::transportation::emergency::{emergency_system, EmergencyResponses};
+use crate::transportation::fuel::{fuel_system, GasStations};
+use crate::transportation::metro::{metro_system, MetroLines};
 use crate::transportation::pedestrian_decision_system;
 use crate::transportation::road::{vehicle_decision_system, vehicle_state_update_system};
+use crate::transportation::road_maintenance::{road_maintenance_system, RoadMaintenance};
 use crate::transportation::testing_vehicles::{random_vehicles_update, RandomVehicles};
 use crate::transportation::train::{
     locomotive_system, train_reservations_update, TrainReservations,
 };
+use crate::transportation::waterway::{harbor_system, Harbors};
+use crate::utils::events::{sim_events_prune, SimEvents};
+use crate::utils::pool::EntityPool;
 use crate::utils::resources::Resources;
+use crate::utils::scenario::{scenario_system, Scenario};
 use crate::utils::time::Tick;
+use crate::utils::timelapse::{timelapse_update, Timelapse};
 use crate::wildlife::add_flocks_randomly;
 use crate::wildlife::bird::bird_decision_system;
 use crate::world::{CompanyEnt, FreightStationEnt, HumanEnt, TrainEnt, VehicleEnt, WagonEnt};
+use crate::world_command::{RejectedCommands, UndoableEdits};
 use crate::World;
 use crate::{
     add_souls_to_empty_buildings, utils, CollisionWorld, GameTime, ParCommandBuffer, RandProvider,
@@ -34,36 +57,67 @@ pub fn init() {
     register_system("dispatch_system", dispatch_system);
     register_system("update_decision_system", update_decision_system);
     register_system("company_system", company_system);
+    register_system("education_system", education_system);
+    register_system("health_system", health_system);
     register_system("pedestrian_decision_system", pedestrian_decision_system);
     register_system("bird_decision_system", bird_decision_system);
     register_system("coworld_synchronize", coworld_synchronize);
     register_system("locomotive_system", locomotive_system);
     register_system("vehicle_decision_system", vehicle_decision_system);
+    register_system("fuel_system", fuel_system);
     register_system("vehicle_state_update_system", vehicle_state_update_system);
     register_system("routing_changed_system", routing_changed_system);
     register_system("routing_update_system", routing_update_system);
     register_system("itinerary_update", itinerary_update);
     register_system("market_update", market_update);
+    register_system("economy_crisis_update", economy_crisis_update);
+    register_system("milestones_update", milestones_update);
+    register_system("government_update", government_update);
     register_system("train_reservations_update", train_reservations_update);
     register_system("freight_station", freight_station_system);
+    register_system("airport", airport_system);
+    register_system("harbor", harbor_system);
+    register_system("policies", policies_system);
+    register_system("night_activity", night_activity_system);
+    register_system("leisure_venues", leisure_venues_system);
+    register_system("building_risk", building_risk_update);
+    register_system("parking_enforcement", parking_enforcement_update);
     register_system("random_vehicles", random_vehicles_update);
+    register_system("sim_events_prune", sim_events_prune);
+    register_system("itinerary_invalidation", itinerary_invalidation);
+    register_system("timelapse", timelapse_update);
 
     register_system_sim("add_souls_to_empty_buildings", add_souls_to_empty_buildings);
     register_system_sim("add_flocks_randomly", add_flocks_randomly);
+    register_system_sim("road_maintenance", road_maintenance_system);
+    register_system_sim("buses", bus_system);
+    register_system_sim("metro", metro_system);
+    register_system_sim("emergency", emergency_system);
+    register_system_sim("venue_events", venue_events_system);
+    register_system_sim("construction_queue", construction_system);
+    register_system_sim("rezoning", rezoning_system);
+    register_system_sim("scenario", scenario_system);
 
     register_resource_noserialize::<GoodsCompanyRegistry>();
+    register_resource_noserialize::<BuildingArchetypes>();
     register_resource_noserialize::<ItemRegistry>();
+    register_resource_noserialize::<EntityPool>();
     register_resource_noserialize::<ParCommandBuffer<VehicleEnt>>();
     register_resource_noserialize::<ParCommandBuffer<TrainEnt>>();
     register_resource_noserialize::<ParCommandBuffer<HumanEnt>>();
     register_resource_noserialize::<ParCommandBuffer<WagonEnt>>();
     register_resource_noserialize::<ParCommandBuffer<FreightStationEnt>>();
     register_resource_noserialize::<ParCommandBuffer<CompanyEnt>>();
+    register_resource_noserialize::<SimEvents>();
+    register_resource_noserialize::<RejectedCommands>();
+    register_resource_noserialize::<UndoableEdits>();
+    register_resource_noserialize::<ItineraryIndex>();
     register_resource_noinit::<Market, Bincode>("market");
     register_resource_noinit::<EcoStats, Bincode>("ecostats");
     register_resource_noinit::<SimulationOptions, Bincode>("simoptions");
 
     register_init(init_market);
+    register_init(init_building_archetypes);
 
     register_resource_default::<MultiplayerState, Bincode>("multiplayer_state");
     register_resource_default::<RandomVehicles, Bincode>("random_vehicles");
@@ -71,15 +125,40 @@ pub fn init() {
     register_resource_default::<Map, Bincode>("map");
     register_resource_default::<TrainReservations, Bincode>("train_reservations");
     register_resource_default::<Government, Bincode>("government");
+    register_resource_default::<ConstructionQueue, Bincode>("construction_queue");
+    register_resource_default::<EconomyCrises, Bincode>("economy_crises");
+    register_resource_default::<Milestones, Bincode>("milestones");
     register_resource_default::<ParkingManagement, Bincode>("pmanagement");
+    register_resource_default::<ParkingEnforcement, Bincode>("parking_enforcement");
+    register_resource_default::<ParkingPressure, Bincode>("parking_pressure");
+    register_resource_default::<Rezoning, Bincode>("rezoning");
+    register_resource_default::<Airports, Bincode>("airports");
+    register_resource_default::<Harbors, Bincode>("harbors");
+    register_resource_default::<GasStations, Bincode>("gas_stations");
+    register_resource_default::<TripStats, Bincode>("trip_stats");
+    register_resource_default::<IntersectionStats, Bincode>("intersection_stats");
+    register_resource_default::<TrafficFlow, Bincode>("traffic_flow");
+    register_resource_default::<NightActivity, Bincode>("night_activity");
+    register_resource_default::<Policies, Bincode>("policies");
+    register_resource_default::<MapMarkers, Bincode>("map_markers");
+    register_resource_default::<BusLines, Bincode>("bus_lines");
+    register_resource_default::<MetroLines, Bincode>("metro_lines");
+    register_resource_default::<EmergencyResponses, Bincode>("emergency_responses");
+    register_resource_default::<VenueEvents, Bincode>("venue_events");
+    register_resource_default::<LeisureVenues, Bincode>("leisure_venues");
     register_resource_default::<BuildingInfos, Bincode>("binfos");
+    register_resource_default::<BuildingRisks, Bincode>("building_risks");
+    register_resource_default::<HealthStats, Bincode>("health_stats");
+    register_resource_default::<Timelapse, Bincode>("timelapse");
     register_resource::<GameTime, Bincode>("game_time", || {
         GameTime::new(0.0, SECONDS_PER_DAY as f64 + 10.0 * SECONDS_PER_HOUR as f64)
     });
     register_resource::<CollisionWorld, Bincode>("coworld", || CollisionWorld::new(100));
     register_resource::<RandProvider, Bincode>("randprovider", || RandProvider::new(RNG_SEED));
     register_resource_default::<Dispatcher, Bincode>("dispatcher");
+    register_resource_default::<RoadMaintenance, Bincode>("road_maintenance");
     register_resource_default::<Replay, JSON>("replay");
+    register_resource_default::<Scenario, JSON>("scenario");
 }
 
 pub struct InitFunc {
@@ -88,10 +167,16 @@ pub struct InitFunc {
 
 pub(crate) struct SaveLoadFunc {
     pub name: &'static str,
-    pub save: Box<dyn Fn(&Simulation) -> Vec<u8> + 'static>,
-    pub load: Box<dyn Fn(&mut Simulation, Vec<u8>) + 'static>,
+    pub save: Box<dyn Fn(&Simulation) -> Vec<u8> + Send + Sync + 'static>,
+    /// Decodes the resource, the returned function inserts it in the simulation.
+    /// Split in two so that resources can be decoded in parallel.
+    pub decode: Box<dyn Fn(&[u8]) -> Option<ResourceInsert> + Send + Sync + 'static>,
+    /// Field by field view of the resource, used to find where two simulations diverge
+    pub flatten: Box<dyn Fn(&Simulation) -> Vec<(String, String)> + Send + Sync + 'static>,
 }
 
+pub(crate) type ResourceInsert = Box<dyn FnOnce(&mut Simulation) + Send>;
+
 pub(crate) struct GSystem {
     pub(crate) s: Box<dyn Fn() -> Box<dyn RunnableSystem>>,
 }
@@ -165,14 +250,14 @@ fn register_resource_noinit<T: 'static + Send + Sync + Serialize + DeserializeOw
         SAVELOAD_FUNCS.push(SaveLoadFunc {
             name,
             save: Box::new(move |uiworld| E::encode(&*uiworld.read::<T>()).unwrap()),
-            load: Box::new(move |uiworld, data| match E::decode::<T>(&data) {
-                Ok(res) => {
-                    uiworld.insert(res);
-                }
+            decode: Box::new(move |data| match E::decode::<T>(data) {
+                Ok(res) => Some(Box::new(move |sim: &mut Simulation| sim.insert(res))),
                 Err(e) => {
                     log::error!("Error loading resource {}: {}", name, e);
+                    None
                 }
             }),
+            flatten: Box::new(move |uiworld| common::flatten::flatten(&*uiworld.read::<T>())),
         });
     }
 }
@@ -1,6 +1,7 @@
 #![allow(clippy::too_many_arguments)]
 #![allow(clippy::type_complexity)]
 
+use crate::map::procgen::EdgeConnections;
 use crate::map::{BuildingKind, Map};
 use crate::map_dynamic::{Itinerary, ItineraryLeader};
 use crate::physics::CollisionWorld;
@@ -19,8 +20,11 @@ use std::fmt::{Display, Formatter};
 use std::hash::Hash;
 use std::time::{Duration, Instant};
 use utils::rand_provider::RandProvider;
+use utils::scenario::Scenario;
+use utils::scenario_result::record_command;
 use utils::scheduler::SeqSchedule;
 use utils::time::{GameTime, SECONDS_PER_DAY, SECONDS_PER_HOUR};
+use utils::tutorial::tutorial_command;
 
 #[macro_use]
 extern crate common;
@@ -35,6 +39,7 @@ extern crate egui_inspect;
 #[macro_use]
 extern crate log as extern_log;
 
+pub mod api;
 pub mod economy;
 pub mod init;
 pub mod map;
@@ -110,6 +115,22 @@ pub struct Simulation {
     resources: Resources,
 }
 
+/// A save of the simulation encoded in memory, see [`Simulation::snapshot`]
+pub struct SaveSnapshot {
+    chunks: Vec<(String, Vec<u8>)>,
+    replay: Option<Vec<u8>>,
+}
+
+impl SaveSnapshot {
+    pub fn write_to_disk(&self, save_name: &str) {
+        let save = utils::chunked_save::compress(VERSION, &self.chunks);
+        common::saveload::Chunked::save(&save, save_name);
+        if let Some(ref replay) = self.replay {
+            common::saveload::JSONPretty::save_encoded(replay, &format!("{save_name}_replay"));
+        }
+    }
+}
+
 const RNG_SEED: u64 = 123;
 const VERSION: &str = include_str!("../../VERSION");
 
@@ -117,6 +138,23 @@ const VERSION: &str = include_str!("../../VERSION");
 pub struct SimulationOptions {
     pub terrain_size: u16,
     pub save_replay: bool,
+    /// Older replays have no edge connections
+    #[serde(default)]
+    pub edge_connections: EdgeConnections,
+    /// Fraction of the drivers stopping for pedestrians waiting at crosswalks without walk signal
+    #[serde(default = "default_crosswalk_yield_rate")]
+    pub crosswalk_yield_rate: f32,
+    /// Fraction of the new pedestrians with a reduced mobility (strollers, wheelchairs...)
+    #[serde(default = "default_slow_walkers_fraction")]
+    pub slow_walkers_fraction: f32,
+}
+
+fn default_crosswalk_yield_rate() -> f32 {
+    0.8
+}
+
+fn default_slow_walkers_fraction() -> f32 {
+    0.1
 }
 
 impl Default for SimulationOptions {
@@ -124,6 +162,9 @@ impl Default for SimulationOptions {
         SimulationOptions {
             terrain_size: 50,
             save_replay: true,
+            edge_connections: EdgeConnections::OnePerSide,
+            crosswalk_yield_rate: default_crosswalk_yield_rate(),
+            slow_walkers_fraction: default_slow_walkers_fraction(),
         }
     }
 }
@@ -246,7 +287,11 @@ impl Simulation {
         {
             profiling::scope!("applying commands");
             for command in commands {
-                command.apply(self);
+                let applied = command.apply(self);
+                record_command(&mut self.write::<Scenario>(), command);
+                if applied {
+                    tutorial_command(self, command);
+                }
             }
         }
 
@@ -290,19 +335,46 @@ impl Simulation {
         Some(replay)
     }
 
+    /// Whether there is a save with this name, chunked or from before saves were chunked
+    pub fn save_exists(save_name: &str) -> bool {
+        std::path::Path::new(&common::saveload::Chunked::filename(save_name)).exists()
+            || std::path::Path::new(&common::saveload::CompressedBincode::filename(save_name))
+                .exists()
+    }
+
     pub fn load_from_disk(save_name: &str) -> Option<Self> {
+        if std::path::Path::new(&common::saveload::Chunked::filename(save_name)).exists() {
+            let save: utils::chunked_save::ChunkedSave = common::saveload::Chunked::load(save_name)
+                .map_err(|e| log::error!("{}", e))
+                .ok()?;
+            return Some(utils::chunked_save::decode(save));
+        }
+        // saves from before saves were chunked
         let sim: Simulation = common::saveload::CompressedBincode::load(save_name).ok()?;
         Some(sim)
     }
 
     pub fn save_to_disk(&self, save_name: &str) {
-        common::saveload::CompressedBincode::save(&self, save_name);
-        let rep = self.resources.read::<Replay>();
-        if rep.enabled {
-            common::saveload::JSONPretty::save(&*rep, &format!("{save_name}_replay"));
+        if let Some(snapshot) = self.snapshot() {
+            snapshot.write_to_disk(save_name);
         }
     }
 
+    /// Encodes the simulation in memory, the slow part of saving (compressing and writing to disk)
+    /// can then be done without holding the simulation
+    pub fn snapshot(&self) -> Option<SaveSnapshot> {
+        let chunks = utils::chunked_save::encode(self)
+            .map_err(|e| log::error!("failed serializing: {}", e))
+            .ok()?;
+        let rep = self.resources.read::<Replay>();
+        let replay = if rep.enabled {
+            common::saveload::JSONPretty::encode(&*rep).ok()
+        } else {
+            None
+        };
+        Some(SaveSnapshot { chunks, replay })
+    }
+
     pub fn pos<E: WorldTransform>(&self, id: E) -> Option<Vec3> {
         self.world.pos(id)
     }
@@ -406,18 +478,7 @@ impl<'de> Deserialize<'de> for Simulation {
             t.elapsed().as_secs_f32()
         );
 
-        let cur_version_parts = VERSION.split('.').collect::<Vec<_>>();
-        let deser_parts = simdeser.version.split('.').collect::<Vec<_>>();
-
-        if cur_version_parts[0] != deser_parts[0]
-            || (cur_version_parts[0] == "0" && cur_version_parts[1] != deser_parts[1])
-        {
-            log::warn!(
-                "incompatible version, save might be corrupted! save is: {} - game is: {}",
-                simdeser.version,
-                VERSION
-            );
-        }
+        check_save_version(&simdeser.version);
 
         let mut sim = Self {
             world: World::default(),
@@ -434,11 +495,12 @@ impl<'de> Deserialize<'de> for Simulation {
 
         unsafe {
             for l in &SAVELOAD_FUNCS {
-                if let Some(data) = simdeser.res.remove(l.name) {
-                    (l.load)(&mut sim, data);
+                if let Some(insert) = simdeser.res.remove(l.name).and_then(|d| (l.decode)(&d)) {
+                    insert(&mut sim);
                 }
             }
         }
+        transportation::relink_parked_vehicles(&mut sim);
 
         log::info!(
             "took {}s to deserialize in total",
@@ -449,6 +511,22 @@ impl<'de> Deserialize<'de> for Simulation {
     }
 }
 
+/// Warns when the save comes from a version of the game it might not be compatible with
+fn check_save_version(version: &str) {
+    let cur_version_parts = VERSION.split('.').collect::<Vec<_>>();
+    let deser_parts = version.split('.').collect::<Vec<_>>();
+
+    if cur_version_parts[0] != deser_parts[0]
+        || (cur_version_parts[0] == "0" && cur_version_parts[1] != deser_parts[1])
+    {
+        log::warn!(
+            "incompatible version, save might be corrupted! save is: {} - game is: {}",
+            version,
+            VERSION
+        );
+    }
+}
+
 const START_COMMANDS: &str = r#"
 [
   [
@@ -0,0 +1,281 @@
+use crate::map::{Intersection, IntersectionID, Map, RoadID};
+use crate::utils::time::SECONDS_PER_REALTIME_SECOND;
+use std::collections::BTreeSet;
+
+/// Minimum number of signalized intersections for a corridor to be an arterial
+pub const MIN_ARTERIAL_SIGNALS: usize = 3;
+/// Roads continue straight through an intersection when the angle between them is smaller than
+/// this, as a cosine (about 30°)
+const STRAIGHT_COS: f32 = 0.866;
+
+/// A signalized intersection of an arterial
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ArterialSignal {
+    pub inter: IntersectionID,
+    /// Distance along the arterial from its first signal, in meters
+    pub dist: f32,
+    /// Road of the arterial the traffic of the green wave arrives from.
+    /// For the first signal, the road it leaves by, which shares its phase with the one facing it.
+    pub road: RoadID,
+}
+
+/// A corridor along a main road going through several signalized intersections
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arterial {
+    pub signals: Vec<ArterialSignal>,
+    /// Roads of the corridor between the first and the last signal, in order
+    pub roads: Vec<RoadID>,
+}
+
+impl Arterial {
+    pub fn length(&self) -> f32 {
+        self.signals.last().map_or(0.0, |s| s.dist)
+    }
+
+    /// Offsets of the lights so that vehicles leaving the first signal at the start of its green
+    /// and driving at `speed` (in m/s) get green at all the next ones: a green wave.
+    /// The first signal keeps its offset, signals with a different cycle length than the first
+    /// cannot be coordinated and are skipped.
+    pub fn green_wave(&self, map: &Map, speed: f32) -> Vec<(IntersectionID, u16)> {
+        let roads = map.roads();
+        let timing = |s: &ArterialSignal| {
+            let inter = map.intersections().get(s.inter)?;
+            inter.light_policy.road_light_timing(inter, roads, s.road)
+        };
+        let Some(first) = self.signals.first() else {
+            return vec![];
+        };
+        let Some(first_timing) = timing(first) else {
+            return vec![];
+        };
+        let period = first_timing.period as i64;
+
+        self.signals
+            .iter()
+            .filter_map(|s| {
+                let t = timing(s).filter(|t| t.period == first_timing.period)?;
+                let travel = ((s.dist - first.dist) / speed.max(1.0)
+                    * SECONDS_PER_REALTIME_SECOND as f32)
+                    .round() as i64;
+                let offset =
+                    (first_timing.offset() as i64 - travel - t.phase as i64).rem_euclid(period);
+                Some((s.inter, offset as u16))
+            })
+            .collect()
+    }
+}
+
+/// Finds the arterials of the map: chains of at least [`MIN_ARTERIAL_SIGNALS`] signalized
+/// intersections linked by roads going straight through them.
+/// Each corridor is found once, intersections without lights can be crossed.
+pub fn detect_arterials(map: &Map) -> Vec<Arterial> {
+    let mut visited = BTreeSet::new();
+    let mut arterials = vec![];
+
+    for inter in map.intersections().values() {
+        if !inter.light_policy.has_lights(inter, map.roads()) {
+            continue;
+        }
+        for &road in &inter.roads {
+            if visited.contains(&road) || map.roads().get(road).map_or(true, |r| r.is_rail()) {
+                continue;
+            }
+            let forward = walk(map, inter.id, road, &mut visited);
+            let backward = straight_continuation(map, inter, road)
+                .filter(|r| !visited.contains(r))
+                .map(|r| walk(map, inter.id, r, &mut visited))
+                .unwrap_or_default();
+
+            let mut chain = vec![];
+            let mut prev_road = None;
+            for (road, inter) in backward.into_iter().rev() {
+                chain.push((prev_road, inter));
+                prev_road = Some(road);
+            }
+            chain.push((prev_road, inter.id));
+            chain.extend(forward.into_iter().map(|(road, inter)| (Some(road), inter)));
+
+            if let Some(arterial) = arterial_of_chain(map, &chain) {
+                arterials.push(arterial);
+            }
+        }
+    }
+
+    arterials
+}
+
+/// The arterials going through the intersection, the corridors it can coordinate its lights with
+pub fn arterials_through(map: &Map, inter: IntersectionID) -> Vec<Arterial> {
+    detect_arterials(map)
+        .into_iter()
+        .filter(|a| a.signals.iter().any(|s| s.inter == inter))
+        .collect()
+}
+
+/// Follows the roads going straight from `start` along `road`, marking them as visited.
+/// Returns each road followed with the intersection it leads to.
+fn walk(
+    map: &Map,
+    start: IntersectionID,
+    mut road: RoadID,
+    visited: &mut BTreeSet<RoadID>,
+) -> Vec<(RoadID, IntersectionID)> {
+    let mut path = vec![];
+    let mut cur = start;
+    while visited.insert(road) {
+        let Some(r) = map.roads().get(road) else {
+            break;
+        };
+        let next = if r.src == cur { r.dst } else { r.src };
+        path.push((road, next));
+        let Some(inter) = map.intersections().get(next) else {
+            break;
+        };
+        let Some(next_road) = straight_continuation(map, inter, road) else {
+            break;
+        };
+        cur = next;
+        road = next_road;
+    }
+    path
+}
+
+/// The road continuing `road` straight through the intersection, the one with the most lanes
+/// when several do
+fn straight_continuation(map: &Map, inter: &Intersection, road: RoadID) -> Option<RoadID> {
+    let roads = map.roads();
+    let dir_in = -roads.get(road)?.dir_from(inter.id);
+    inter
+        .roads
+        .iter()
+        .filter(|&&r| r != road)
+        .filter_map(|&r| roads.get(r))
+        .filter(|r| !r.is_rail())
+        .map(|r| (r, dir_in.dot(r.dir_from(inter.id))))
+        .filter(|&(_, cos)| cos > STRAIGHT_COS)
+        .max_by(|(a, cos_a), (b, cos_b)| a.n_lanes().cmp(&b.n_lanes()).then(cos_a.total_cmp(cos_b)))
+        .map(|(r, _)| r.id)
+}
+
+/// Trims the chain to its signalized ends, `None` if it has too few signals.
+/// Each element of the chain is an intersection with the road leading to it from the previous.
+fn arterial_of_chain(map: &Map, chain: &[(Option<RoadID>, IntersectionID)]) -> Option<Arterial> {
+    let has_lights = |id: IntersectionID| {
+        map.intersections()
+            .get(id)
+            .map_or(false, |i| i.light_policy.has_lights(i, map.roads()))
+    };
+    let first = chain.iter().position(|&(_, i)| has_lights(i))?;
+    let last = chain.iter().rposition(|&(_, i)| has_lights(i))?;
+    let chain = &chain[first..=last];
+    if chain.iter().filter(|&&(_, i)| has_lights(i)).count() < MIN_ARTERIAL_SIGNALS {
+        return None;
+    }
+
+    let roads: Vec<RoadID> = chain.iter().skip(1).filter_map(|&(r, _)| r).collect();
+    let mut signals = vec![];
+    let mut dist = 0.0;
+    for (idx, &(road, inter)) in chain.iter().enumerate() {
+        if let Some(r) = road.filter(|_| idx > 0).and_then(|r| map.roads().get(r)) {
+            dist += r.length();
+        }
+        if !has_lights(inter) {
+            continue;
+        }
+        let road = match idx {
+            0 => *roads.first()?,
+            _ => road?,
+        };
+        signals.push(ArterialSignal { inter, dist, road });
+    }
+
+    Some(Arterial { signals, roads })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{LightPolicy, LightProgram, TrafficControl};
+    use crate::tests::TestCtx;
+    use geom::vec3;
+
+    #[test]
+    fn green_wave() {
+        let test = TestCtx::new();
+        let xs = [200.0, 400.0, 600.0];
+        let mut main = vec![vec3(0.0, 0.0, 0.0)];
+        main.extend(xs.iter().map(|&x| vec3(x, 0.0, 0.0)));
+        main.push(vec3(800.0, 0.0, 0.0));
+        test.build_roads(&main);
+        for x in xs {
+            test.build_roads(&[vec3(x, -100.0, 0.0), vec3(x, 0.0, 0.0), vec3(x, 100.0, 0.0)]);
+        }
+
+        let ids: Vec<IntersectionID> = {
+            let map = test.g.map();
+            xs.iter()
+                .map(|&x| {
+                    map.intersections()
+                        .values()
+                        .find(|i| i.pos.xy().distance(vec3(x, 0.0, 0.0).xy()) < 1.0)
+                        .unwrap()
+                        .id
+                })
+                .collect()
+        };
+        for &id in &ids {
+            test.g
+                .map_mut()
+                .update_intersection(id, |i| i.light_policy = LightPolicy::Lights);
+        }
+
+        // uneven splits keep the cycle length, the light can still be coordinated
+        let mut program = {
+            let map = test.g.map();
+            LightProgram::new(&map.intersections()[ids[1]], map.roads())
+        };
+        assert_eq!(program.phases.len(), 2);
+        program.phases[0] += 60;
+        program.phases[1] -= 60;
+        test.g
+            .map_mut()
+            .update_intersection(ids[1], |i| i.light_program = Some(program.clone()));
+        assert_eq!(arterials_through(&test.g.map(), ids[1]).len(), 1);
+
+        let arterials = detect_arterials(&test.g.map());
+        assert_eq!(arterials.len(), 1);
+        let arterial = &arterials[0];
+        assert_eq!(arterial.signals.len(), 3);
+        assert!((arterial.length() - 400.0).abs() < 10.0);
+
+        let speed = 10.0;
+        let offsets = arterial.green_wave(&test.g.map(), speed);
+        assert_eq!(offsets.len(), 3);
+        for (id, offset) in offsets {
+            test.g
+                .map_mut()
+                .update_intersection(id, |i| i.light_offset = Some(offset));
+        }
+
+        // leaving the first light when it turns green, every light is green on arrival
+        let map = test.g.map();
+        let light = |s: &ArterialSignal| {
+            let lane = map.roads()[s.road].incoming_lanes_to(s.inter)[0].0;
+            let control = map.lanes()[lane].control;
+            assert!(control.is_light());
+            control
+        };
+        let first = &arterial.signals[0];
+        let first_light = light(first);
+        let start = (1..10000)
+            .find(|&t| {
+                first_light.get_behavior(t).is_green()
+                    && !first_light.get_behavior(t - 1).is_green()
+            })
+            .unwrap();
+        for s in &arterial.signals[1..] {
+            let arrival = start + ((s.dist / speed) * SECONDS_PER_REALTIME_SECOND as f32) as u32;
+            assert!(light(s).get_behavior(arrival + 1).is_green());
+        }
+    }
+}
@@ -1,17 +1,27 @@
 //! This module contains the change detection system for the map.
 //! This should not be used inside the simulation as change subscribers are not serialized.
 //! It is mostly for rendering purposes by decoupling it from the simulation.
+//! The simulation uses the [`DirtyRegions`] log instead.
 
 use crate::map::{Building, Intersection, Lot, Road};
 use common::ChunkID;
-use geom::Vec2;
-use std::collections::BTreeSet;
+use geom::{Shape, Vec2, AABB};
+use std::collections::{BTreeSet, VecDeque};
 use std::sync::{Arc, Mutex};
 
+/// Regions kept in the log, consumers further behind recompute everything
+const MAX_DIRTY_REGIONS: usize = 4096;
+
 // CanonicalPosition is a trait that describes the canonical position of an object.
 // It is used to determine which chunk an object belongs to.
 pub trait CanonicalPosition {
     fn canonical_position(&self) -> Vec2;
+
+    /// Area covered by the object, used to know what changed around it
+    fn canonical_bbox(&self) -> AABB {
+        let p = self.canonical_position();
+        AABB::new(p, p)
+    }
 }
 
 pub type SubscriberChunkID = ChunkID<5>;
@@ -23,18 +33,80 @@ pub enum UpdateType {
     Terrain,
 }
 
+/// Log of the areas where roads and buildings changed.
+/// Unlike the subscribers, it can be used inside the simulation to recompute what is derived
+/// from the map only where it changed: consumers remember the version they caught up with, and
+/// recompute everything when the log can't tell what changed since.
 #[derive(Default)]
-pub struct MapSubscribers(Mutex<Vec<MapSubscriber>>);
+pub struct DirtyRegions {
+    /// Version of the first region of the log
+    first: u64,
+    regions: VecDeque<AABB>,
+}
+
+impl DirtyRegions {
+    pub fn version(&self) -> u64 {
+        self.first + self.regions.len() as u64
+    }
+
+    /// Regions that changed since the given version, None if everything must be recomputed
+    pub fn since(&self, version: Option<u64>) -> Option<Vec<AABB>> {
+        let version = version?;
+        if version < self.first || version > self.version() {
+            return None;
+        }
+        Some(
+            self.regions
+                .iter()
+                .skip((version - self.first) as usize)
+                .copied()
+                .collect(),
+        )
+    }
+
+    fn mark(&mut self, region: AABB) {
+        self.regions.push_back(region);
+        if self.regions.len() > MAX_DIRTY_REGIONS {
+            self.regions.pop_front();
+            self.first += 1;
+        }
+    }
+
+    /// Everything changed
+    fn clear(&mut self) {
+        self.first = self.version() + 1;
+        self.regions.clear();
+    }
+}
+
+/// Whether the position is within the given distance of one of the regions
+pub fn is_dirty(regions: &[AABB], pos: Vec2, dist: f32) -> bool {
+    regions.iter().any(|r| r.contains_within(pos, dist))
+}
+
+#[derive(Default)]
+pub struct MapSubscribers {
+    subs: Mutex<Vec<MapSubscriber>>,
+    dirty: Mutex<DirtyRegions>,
+}
 
 impl MapSubscribers {
     pub fn subscribe(&self, filter: UpdateType) -> MapSubscriber {
         let sub = MapSubscriber::new(filter);
-        self.0.lock().unwrap().push(sub.clone());
+        self.subs.lock().unwrap().push(sub.clone());
         sub
     }
 
+    /// Regions where roads and buildings changed since the given version, and the current version.
+    /// None if everything must be recomputed
+    pub fn dirty_since(&self, version: Option<u64>) -> (Option<Vec<AABB>>, u64) {
+        let dirty = self.dirty.lock().unwrap();
+        (dirty.since(version), dirty.version())
+    }
+
     pub fn dispatch_all(&self, chunks: impl Iterator<Item = SubscriberChunkID>) {
-        let mut me = self.0.lock().unwrap();
+        self.dirty.lock().unwrap().clear();
+        let mut me = self.subs.lock().unwrap();
         for chunk in chunks {
             for sub in me.iter_mut() {
                 sub.dispatch(UpdateType::Road, chunk);
@@ -45,13 +117,17 @@ impl MapSubscribers {
     }
 
     pub fn dispatch_clear(&self) {
-        let mut me = self.0.lock().unwrap();
+        self.dirty.lock().unwrap().clear();
+        let mut me = self.subs.lock().unwrap();
         for sub in me.iter_mut() {
             sub.inner.lock().unwrap().cleared = true;
         }
     }
 
     pub fn dispatch(&mut self, update_type: UpdateType, p: &impl CanonicalPosition) {
+        if update_type != UpdateType::Terrain {
+            self.dirty.lock().unwrap().mark(p.canonical_bbox());
+        }
         let chunk_id = SubscriberChunkID::new(p.canonical_position());
         self.dispatch_chunk(update_type, chunk_id);
     }
@@ -69,7 +145,7 @@ impl MapSubscribers {
         update_type: UpdateType,
         chunks: impl Iterator<Item = SubscriberChunkID>,
     ) {
-        let mut me = self.0.lock().unwrap();
+        let mut me = self.subs.lock().unwrap();
         for chunk in chunks {
             for sub in me.iter_mut() {
                 sub.dispatch(update_type, chunk);
@@ -133,16 +209,29 @@ impl CanonicalPosition for Road {
     fn canonical_position(&self) -> Vec2 {
         self.points.first().xy()
     }
+
+    fn canonical_bbox(&self) -> AABB {
+        let bbox = self.points.bbox();
+        AABB::new(bbox.ll.xy(), bbox.ur.xy()).expand(self.width * 0.5)
+    }
 }
 
 impl CanonicalPosition for Building {
     fn canonical_position(&self) -> Vec2 {
         self.obb.center()
     }
+
+    fn canonical_bbox(&self) -> AABB {
+        self.obb.bbox()
+    }
 }
 
 impl CanonicalPosition for Lot {
     fn canonical_position(&self) -> Vec2 {
         self.shape.center()
     }
+
+    fn canonical_bbox(&self) -> AABB {
+        self.shape.bbox()
+    }
 }
@@ -0,0 +1,626 @@
+//! Contraction hierarchy over the lanes cars drive on, so that the car pathfinding of large maps
+//! doesn't explore most of the network.
+//! See <https://en.wikipedia.org/wiki/Contraction_hierarchies>
+//!
+//! The lanes are contracted from the least important to the most important one, adding shortcuts
+//! between all their neighbours. A query then only goes up the hierarchy from both ends.
+//!
+//! The shortcuts don't depend on the costs of the lanes, so that the hierarchy is only rebuilt
+//! when the turns change. The costs of the shortcuts are then computed from the ones of the arcs
+//! they skip, and only the shortcuts going through the lanes whose costs changed are updated
+//! afterwards. See <https://arxiv.org/abs/1402.0402> (customizable contraction hierarchies).
+//!
+//! Building the hierarchy is spread over several ticks, the previous hierarchy keeps answering
+//! the queries meanwhile. Rebuilds contract the lanes in the order of the previous build, which
+//! saves ordering them again, the lanes added since then being contracted first. Queries fail
+//! until the first build is done, callers fall back to A*.
+//! The hierarchy is saved with the map so that the paths don't depend on when the game was
+//! loaded.
+#![allow(clippy::indexing_slicing)]
+
+use crate::map::{LaneID, LaneKind, Map};
+use common::FastMap;
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+
+/// Lanes looked at per tick while building
+const LANES_PER_TICK: usize = 2000;
+/// Mid lane of the arcs that are actual turns
+const NO_MID: u32 = u32::MAX;
+/// The lanes are ordered again once this share of them was added since they were last ordered
+const MAX_UNORDERED_SHARE: f32 = 0.2;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+struct Arc {
+    /// The other end of the arc, its source for incoming arcs
+    other: u32,
+    /// Seconds to drive through the lanes the arc goes through, see [`Map::lane_travel_time`].
+    /// Infinite when the arc can't be used.
+    cost: f32,
+}
+
+/// The ways to go through an arc
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ArcParts {
+    /// Whether there is a turn between the two lanes
+    turn: bool,
+    /// The lanes the arc is a shortcut through
+    mids: Vec<u32>,
+    /// The mid lane of the cheapest way, [`NO_MID`] for the turn
+    best: u32,
+}
+
+/// The lane graph being contracted, lanes are numbered in the order of `lanes`
+#[derive(Clone, Serialize, Deserialize)]
+struct Builder {
+    lanes: Vec<LaneID>,
+    /// Arcs between the lanes not contracted yet
+    out: Vec<Vec<u32>>,
+    inc: Vec<Vec<u32>>,
+    contracted_neighbours: Vec<i32>,
+    queue: BTreeSet<(i32, u32)>,
+    parts: BTreeMap<(u32, u32), ArcParts>,
+    /// The lanes with a turn to each lane
+    turns_into: Vec<Vec<u32>>,
+    up: Vec<Vec<u32>>,
+    down: Vec<Vec<u32>>,
+    /// Whether the lanes are contracted in the order of the previous build
+    reordered: bool,
+    /// Lanes added since the lanes were last ordered
+    unordered: usize,
+    /// The lanes in the order they were contracted
+    sequence: Vec<u32>,
+}
+
+impl Builder {
+    fn new(map: &Map, order: &[LaneID], unordered: usize) -> Self {
+        let (lanes, inters) = (&map.lanes, &map.intersections);
+        let ids: Vec<LaneID> = lanes
+            .iter()
+            .filter(|(_, l)| matches!(l.kind, LaneKind::Driving | LaneKind::Tram))
+            .map(|(id, _)| id)
+            .collect();
+        let index: FastMap<LaneID, u32> = ids
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| (id, i as u32))
+            .collect();
+
+        let n = ids.len();
+        let mut b = Self {
+            lanes: ids,
+            out: vec![vec![]; n],
+            inc: vec![vec![]; n],
+            contracted_neighbours: vec![0; n],
+            queue: BTreeSet::new(),
+            parts: BTreeMap::new(),
+            turns_into: vec![vec![]; n],
+            up: vec![vec![]; n],
+            down: vec![vec![]; n],
+            reordered: true,
+            unordered: 0,
+            sequence: Vec::with_capacity(n),
+        };
+
+        for i in 0..n as u32 {
+            let id = b.lanes[i as usize];
+            let Some(inter) = lanes.get(id).and_then(|l| inters.get(l.dst)) else {
+                continue;
+            };
+            for (turn, _) in inter.turns_from(id) {
+                let Some(&j) = index.get(&turn.dst) else {
+                    continue;
+                };
+                if i != j {
+                    b.add_arc(i, j, NO_MID);
+                    b.turns_into[j as usize].push(i);
+                }
+            }
+        }
+
+        let rank: FastMap<LaneID, i32> = order
+            .iter()
+            .enumerate()
+            .map(|(r, &id)| (id, r as i32))
+            .collect();
+        let added = b.lanes.iter().filter(|id| !rank.contains_key(id)).count();
+        if !order.is_empty() && (unordered + added) as f32 <= n as f32 * MAX_UNORDERED_SHARE {
+            b.reordered = false;
+            b.unordered = unordered + added;
+        }
+
+        for v in 0..n as u32 {
+            let priority = if b.reordered {
+                // a rough estimate, the actual priority is computed when it comes up
+                let (i, o) = (
+                    b.inc[v as usize].len() as i32,
+                    b.out[v as usize].len() as i32,
+                );
+                i * o - i - o
+            } else {
+                // the lanes added since the previous build are the least important
+                rank.get(&b.lanes[v as usize]).copied().unwrap_or(-1)
+            };
+            b.queue.insert((priority, v));
+        }
+        b
+    }
+
+    /// Adds the arc if it doesn't exist yet, as a turn or as a shortcut through `mid`
+    fn add_arc(&mut self, from: u32, to: u32, mid: u32) {
+        let parts = match self.parts.entry((from, to)) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                self.out[from as usize].push(to);
+                self.inc[to as usize].push(from);
+                e.insert(ArcParts {
+                    turn: false,
+                    mids: vec![],
+                    best: NO_MID,
+                })
+            }
+        };
+        if mid == NO_MID {
+            parts.turn = true;
+        } else {
+            parts.mids.push(mid);
+        }
+    }
+
+    /// Lanes that add few shortcuts and have few contracted neighbours are contracted first
+    fn priority(&self, v: u32) -> i32 {
+        let (ins, outs) = (&self.inc[v as usize], &self.out[v as usize]);
+        let added = ins
+            .iter()
+            .flat_map(|&u| outs.iter().map(move |&w| (u, w)))
+            .filter(|&(u, w)| u != w && !self.parts.contains_key(&(u, w)))
+            .count();
+        added as i32 - (ins.len() + outs.len()) as i32 + self.contracted_neighbours[v as usize]
+    }
+
+    fn contract(&mut self, v: u32) {
+        let ins = std::mem::take(&mut self.inc[v as usize]);
+        let outs = std::mem::take(&mut self.out[v as usize]);
+        for &u in &ins {
+            self.out[u as usize].retain(|&x| x != v);
+        }
+        for &w in &outs {
+            self.inc[w as usize].retain(|&x| x != v);
+        }
+        for &u in &ins {
+            for &w in &outs {
+                if u != w {
+                    self.add_arc(u, w, v);
+                }
+            }
+        }
+        for &x in ins.iter().chain(&outs) {
+            self.contracted_neighbours[x as usize] += 1;
+        }
+        self.up[v as usize] = outs;
+        self.down[v as usize] = ins;
+        self.sequence.push(v);
+    }
+
+    /// Looks at up to `budget` lanes, returns whether every lane is contracted
+    fn step(&mut self, budget: usize) -> bool {
+        for _ in 0..budget {
+            let Some((_, v)) = self.queue.pop_first() else {
+                return true;
+            };
+            // priorities are only updated when they come up
+            if self.reordered {
+                let p = self.priority(v);
+                if let Some(&(next, _)) = self.queue.first() {
+                    if p > next {
+                        self.queue.insert((p, v));
+                        continue;
+                    }
+                }
+            }
+            self.contract(v);
+        }
+        self.queue.is_empty()
+    }
+
+    fn order(&self) -> Vec<LaneID> {
+        self.sequence
+            .iter()
+            .map(|&v| self.lanes[v as usize])
+            .collect()
+    }
+
+    fn finish(self, map: &Map) -> Hierarchy {
+        let mut rank = vec![0; self.lanes.len()];
+        for (r, &v) in self.sequence.iter().enumerate() {
+            rank[v as usize] = r as u32;
+        }
+        let arcs = |arcs: Vec<Vec<u32>>| -> Vec<Vec<Arc>> {
+            arcs.into_iter()
+                .map(|a| {
+                    a.into_iter()
+                        .map(|other| Arc {
+                            other,
+                            cost: f32::INFINITY,
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+        let mut h = Hierarchy {
+            index: self
+                .lanes
+                .iter()
+                .enumerate()
+                .map(|(i, &id)| (id, i as u32))
+                .collect(),
+            lanes: self.lanes,
+            rank,
+            up: arcs(self.up),
+            down: arcs(self.down),
+            parts: self.parts,
+            turns_into: self.turns_into,
+        };
+        h.customize_all(map, &self.sequence);
+        h
+    }
+}
+
+/// A contracted lane graph: every lane only keeps its arcs to more important lanes
+#[derive(Clone, Serialize, Deserialize)]
+struct Hierarchy {
+    index: BTreeMap<LaneID, u32>,
+    lanes: Vec<LaneID>,
+    /// Position of each lane in the contraction order
+    rank: Vec<u32>,
+    /// Arcs from each lane to more important lanes
+    up: Vec<Vec<Arc>>,
+    /// Arcs from more important lanes to each lane, `other` is their source
+    down: Vec<Vec<Arc>>,
+    parts: BTreeMap<(u32, u32), ArcParts>,
+    /// The lanes with a turn to each lane
+    turns_into: Vec<Vec<u32>>,
+}
+
+impl Hierarchy {
+    fn arc(&self, from: u32, to: u32) -> Option<&Arc> {
+        if self.rank[from as usize] < self.rank[to as usize] {
+            self.up[from as usize].iter().find(|a| a.other == to)
+        } else {
+            self.down[to as usize].iter().find(|a| a.other == from)
+        }
+    }
+
+    fn arc_mut(&mut self, from: u32, to: u32) -> Option<&mut Arc> {
+        if self.rank[from as usize] < self.rank[to as usize] {
+            self.up[from as usize].iter_mut().find(|a| a.other == to)
+        } else {
+            self.down[to as usize].iter_mut().find(|a| a.other == from)
+        }
+    }
+
+    /// The arcs a shortcut goes through are less important than its ends, so they are
+    /// customized before the shortcut when going by increasing level
+    fn level(&self, from: u32, to: u32) -> u32 {
+        self.rank[from as usize].min(self.rank[to as usize])
+    }
+
+    /// Computes the cost of the arc from the lane it goes to and the arcs it skips, returns
+    /// whether it changed
+    fn customize_arc(&mut self, map: &Map, from: u32, to: u32) -> bool {
+        let Some(parts) = self.parts.get(&(from, to)) else {
+            return false;
+        };
+        let mut best = (f32::INFINITY, NO_MID);
+        if parts.turn {
+            if let Some(l) = map.lanes.get(self.lanes[to as usize]) {
+                best.0 = map.lane_travel_time(l);
+            }
+        }
+        for &mid in &parts.mids {
+            let cost = self.arc(from, mid).map_or(f32::INFINITY, |a| a.cost)
+                + self.arc(mid, to).map_or(f32::INFINITY, |a| a.cost);
+            if cost < best.0 {
+                best = (cost, mid);
+            }
+        }
+
+        if let Some(parts) = self.parts.get_mut(&(from, to)) {
+            parts.best = best.1;
+        }
+        let Some(arc) = self.arc_mut(from, to) else {
+            return false;
+        };
+        let changed = arc.cost != best.0;
+        arc.cost = best.0;
+        changed
+    }
+
+    fn customize_all(&mut self, map: &Map, sequence: &[u32]) {
+        for &v in sequence {
+            for i in 0..self.up[v as usize].len() {
+                let w = self.up[v as usize][i].other;
+                self.customize_arc(map, v, w);
+            }
+            for i in 0..self.down[v as usize].len() {
+                let u = self.down[v as usize][i].other;
+                self.customize_arc(map, u, v);
+            }
+        }
+    }
+
+    /// Updates the costs of the turns to the lanes and of the shortcuts going through them
+    fn customize(&mut self, map: &Map, lanes: &[LaneID]) {
+        let mut todo = BTreeSet::new();
+        for id in lanes {
+            let Some(&to) = self.index.get(id) else {
+                continue;
+            };
+            for &from in &self.turns_into[to as usize] {
+                todo.insert((self.level(from, to), from, to));
+            }
+        }
+
+        while let Some((_, from, to)) = todo.pop_first() {
+            if !self.customize_arc(map, from, to) {
+                continue;
+            }
+            // the shortcuts through the least important end of the arc use it
+            if self.rank[from as usize] > self.rank[to as usize] {
+                for a in &self.up[to as usize] {
+                    if a.other != from {
+                        todo.insert((self.level(from, a.other), from, a.other));
+                    }
+                }
+            } else {
+                for a in &self.down[from as usize] {
+                    if a.other != to {
+                        todo.insert((self.level(a.other, to), a.other, to));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Costs and parents of the lanes reachable going up from `from`
+    fn search(arcs: &[Vec<Arc>], from: u32) -> FastMap<u32, (f32, u32)> {
+        let mut dist: FastMap<u32, (f32, u32)> = FastMap::default();
+        let mut queue = BinaryHeap::new();
+        dist.insert(from, (0.0, from));
+        queue.push(Reverse((OrderedFloat(0.0), from)));
+
+        while let Some(Reverse((OrderedFloat(d), v))) = queue.pop() {
+            if dist.get(&v).map_or(false, |&(best, _)| d > best) {
+                continue;
+            }
+            for a in &arcs[v as usize] {
+                if a.cost == f32::INFINITY {
+                    continue;
+                }
+                let nd = d + a.cost;
+                if dist.get(&a.other).map_or(true, |&(best, _)| nd < best) {
+                    dist.insert(a.other, (nd, v));
+                    queue.push(Reverse((OrderedFloat(nd), a.other)));
+                }
+            }
+        }
+        dist
+    }
+
+    fn path(&self, start: LaneID, end: LaneID, seed: u32) -> Option<Vec<LaneID>> {
+        let s = *self.index.get(&start)?;
+        let t = *self.index.get(&end)?;
+
+        let forward = Self::search(&self.up, s);
+        let backward = Self::search(&self.down, t);
+        // routes of about the same cost mostly meet at different lanes, a random cost at the
+        // meeting lane is enough to spread the trips over them
+        let (&meet, _) = forward
+            .iter()
+            .filter_map(|(v, &(d, _))| Some((v, d + backward.get(v)?.0)))
+            .min_by_key(|&(v, d)| (OrderedFloat(d + common::rand::randu(*v ^ seed)), *v))?;
+
+        let mut nodes = vec![meet];
+        let mut v = meet;
+        while v != s {
+            v = forward[&v].1;
+            nodes.push(v);
+        }
+        nodes.reverse();
+        let mut v = meet;
+        while v != t {
+            v = backward[&v].1;
+            nodes.push(v);
+        }
+
+        // replace the shortcuts by the lanes they skip
+        let mut lanes = vec![start];
+        for pair in nodes.windows(2) {
+            let mut stack = vec![(pair[0], pair[1])];
+            while let Some((from, to)) = stack.pop() {
+                match self.parts.get(&(from, to)) {
+                    Some(&ArcParts { best: mid, .. }) if mid != NO_MID => {
+                        stack.push((mid, to));
+                        stack.push((from, mid));
+                    }
+                    _ => lanes.push(self.lanes[to as usize]),
+                }
+            }
+        }
+        Some(lanes)
+    }
+}
+
+/// Contraction hierarchy of the lanes cars can drive on, bus lanes are left out.
+/// Costs are the seconds to drive through the lanes, including the jams.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct ContractionHierarchy {
+    ready: Option<Hierarchy>,
+    building: Option<Builder>,
+    /// The lanes or turns changed since the ready hierarchy was built
+    outdated: bool,
+    /// Contraction order of the last build
+    order: Vec<LaneID>,
+    /// Lanes added since the lanes were last ordered
+    unordered: usize,
+}
+
+impl ContractionHierarchy {
+    /// The lanes or turns changed, the hierarchy must be rebuilt.
+    /// The current one is used until then, see [`Map::shortest_path_fast`].
+    pub fn invalidate(&mut self) {
+        self.outdated = true;
+        self.building = None;
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.is_some() && !self.outdated && self.building.is_none()
+    }
+
+    /// Continues building the hierarchy if it is outdated
+    pub fn update(&mut self, map: &Map) {
+        if self.ready.is_some() && !self.outdated && self.building.is_none() {
+            return;
+        }
+        self.outdated = false;
+        let builder = self
+            .building
+            .get_or_insert_with(|| Builder::new(map, &self.order, self.unordered));
+        if builder.step(LANES_PER_TICK) {
+            self.order = builder.order();
+            self.unordered = builder.unordered;
+            self.ready = self.building.take().map(|b| b.finish(map));
+        }
+    }
+
+    /// The travel times of the lanes changed, see [`Map::lane_travel_time`].
+    /// A hierarchy being built takes the travel times of the map once it is done.
+    pub fn customize(&mut self, map: &Map, lanes: &[LaneID]) {
+        if let Some(ready) = &mut self.ready {
+            ready.customize(map, lanes);
+        }
+    }
+
+    /// The lanes of the shortest path from `start` to `end`, both included. Paths of about the
+    /// same cost are picked at random from the seed.
+    /// None until the hierarchy is first built, when there is no path or when the path loops
+    /// back to the start lane.
+    /// The hierarchy may be outdated: the path can go through lanes and turns that were removed.
+    pub fn path(&self, start: LaneID, end: LaneID, seed: u32) -> Option<Vec<LaneID>> {
+        if start == end {
+            return None;
+        }
+        self.ready.as_ref()?.path(start, end, seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::procgen::{OsmNetwork, OsmRoad};
+    use crate::map::Map;
+    use common::saveload::{Bincode, Encoder};
+    use geom::vec2;
+
+    #[test]
+    fn same_costs_as_dijkstra() {
+        let mut network = OsmNetwork::default();
+        for y in 0..4 {
+            for x in 0..4 {
+                network.nodes.push(vec2(x as f32 * 100.0, y as f32 * 100.0));
+            }
+        }
+        let road = |src: u32, dst: u32| OsmRoad {
+            src,
+            dst,
+            n_lanes: 1,
+            one_way: (src + dst) % 3 == 0,
+            speed_limit: 5.0 + (src * dst % 7) as f32,
+            sidewalks: false,
+            parking: false,
+        };
+        for i in 0..16 {
+            if i % 4 != 3 {
+                network.roads.push(road(i, i + 1));
+            }
+            if i < 12 {
+                network.roads.push(road(i, i + 4));
+            }
+        }
+        let mut map = Map::empty();
+        network.build(&mut map);
+
+        let mut ch = ContractionHierarchy::default();
+        while !ch.is_ready() {
+            ch.update(&map);
+        }
+        check_costs(&map, &ch);
+
+        // only the costs of the jammed lanes are updated
+        let mut congested = slotmapd::SecondaryMap::new();
+        for (id, l) in map.lanes.iter().step_by(3) {
+            congested.insert(id, map.lane_travel_time(l) * 4.0);
+        }
+        let jammed: Vec<LaneID> = congested.keys().collect();
+        map.set_congested_lanes(congested);
+        ch.customize(&map, &jammed);
+        check_costs(&map, &ch);
+
+        // and back once the jams are gone
+        map.set_congested_lanes(slotmapd::SecondaryMap::new());
+        ch.customize(&map, &jammed);
+        check_costs(&map, &ch);
+
+        // the loaded hierarchy gives the same paths
+        let loaded: ContractionHierarchy = Bincode::decode(&Bincode::encode(&ch).unwrap()).unwrap();
+        for (start, _) in map.lanes.iter() {
+            for (end, _) in map.lanes.iter() {
+                assert_eq!(ch.path(start, end, 0), loaded.path(start, end, 0));
+            }
+        }
+    }
+
+    fn check_costs(map: &Map, ch: &ContractionHierarchy) {
+        let cost = |l: LaneID| map.lane_travel_time(&map.lanes[l]);
+        let successors = |&l: &LaneID| {
+            let inter = &map.intersections[map.lanes[l].dst];
+            inter
+                .turns_from(l)
+                .map(|(t, _)| (t.dst, OrderedFloat(cost(t.dst))))
+                .collect::<Vec<_>>()
+        };
+
+        let driving: Vec<LaneID> = map
+            .lanes
+            .iter()
+            .filter(|(_, l)| l.kind == LaneKind::Driving)
+            .map(|(id, _)| id)
+            .collect();
+        for &start in &driving {
+            for &end in &driving {
+                if start == end {
+                    continue;
+                }
+                let expected =
+                    pathfinding::directed::dijkstra::dijkstra(&start, successors, |l| *l == end);
+                let path = ch.path(start, end, 0);
+                assert_eq!(expected.is_some(), path.is_some());
+                let (Some((_, expected)), Some(path)) = (expected, path) else {
+                    continue;
+                };
+                assert_eq!(path.first(), Some(&start));
+                assert_eq!(path.last(), Some(&end));
+                for pair in path.windows(2) {
+                    let inter = &map.intersections[map.lanes[pair[0]].dst];
+                    assert!(inter.turns_from(pair[0]).any(|(t, _)| t.dst == pair[1]));
+                }
+                // the random cost of the trip is below a second
+                let total: f32 = path.iter().skip(1).map(|&l| cost(l)).sum();
+                assert!(total > expected.0 - 1e-2 && total < expected.0 + 1.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use crate::map::{Environment, LaneID, LaneKind, Map, ProjectKind};
+use geom::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// Depth under the terrain at which the metro tunnels are dug
+pub const METRO_DEPTH: f32 = 20.0;
+
+/// The map is edited in two layers: the surface, and the metro tunnels dug deep under it.
+/// Shallow tunnels are part of the surface layer.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MapLayer {
+    #[default]
+    Surface,
+    Underground,
+}
+
+impl MapLayer {
+    /// The layer a point belongs to
+    pub fn of(pos: Vec3, env: &Environment) -> Self {
+        match env.height(pos.xy()) {
+            Some(h) if pos.z < h - METRO_DEPTH * 0.5 => MapLayer::Underground,
+            _ => MapLayer::Surface,
+        }
+    }
+
+    /// Height of the layer at the given position
+    pub fn height(self, pos: Vec3, env: &Environment) -> Option<f32> {
+        let h = env.height(pos.xy())?;
+        Some(match self {
+            MapLayer::Surface => h,
+            MapLayer::Underground => h - METRO_DEPTH,
+        })
+    }
+
+    /// Whether the map object belongs to the layer, only roads and intersections go underground
+    pub fn contains(self, map: &Map, kind: ProjectKind) -> bool {
+        let pos = match kind {
+            ProjectKind::Inter(id) => map.intersections().get(id).map(|i| i.pos),
+            ProjectKind::Road(id) => map
+                .roads()
+                .get(id)
+                .map(|r| r.points().point_along(r.points().length() * 0.5)),
+            _ => return self == MapLayer::Surface,
+        };
+        pos.map_or(false, |p| Self::of(p, &map.environment) == self)
+    }
+}
+
+/// The metro tracks running under the position, within the given horizontal distance
+pub fn metro_lane_under(map: &Map, pos: Vec3, reach: f32) -> Option<LaneID> {
+    let h = MapLayer::Underground.height(pos, &map.environment)?;
+    let lane = map.nearest_lane(pos.xy().z(h), LaneKind::Rail, Some(reach))?;
+    let lane = map.lanes().get(lane)?;
+    let on_lane = lane.points.project(pos.xy().z(h));
+    if !on_lane.xy().is_close(pos.xy(), reach)
+        || MapLayer::of(on_lane, &map.environment) != MapLayer::Underground
+    {
+        return None;
+    }
+    Some(lane.id)
+}
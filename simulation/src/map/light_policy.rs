@@ -1,105 +1,373 @@
-use crate::map::{Intersection, LaneID, Lanes, Roads, TrafficControl, TrafficLightSchedule};
+use crate::map::{
+    Intersection, LaneID, Lanes, RoadID, Roads, TrafficControl, TrafficLightSchedule,
+};
 use crate::utils::time::SECONDS_PER_REALTIME_SECOND;
 use egui_inspect::{egui, egui::Ui, Inspect, InspectArgs};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LightPolicy {
     NoLights,
     StopSigns,
+    /// Lights where pedestrians cross a road while its traffic is stopped
     Lights,
+    /// Lights with an exclusive phase where all traffic is stopped and pedestrians cross
+    /// every road at once
+    PedestrianScramble,
+    /// Lights or stop signs depending on the number of roads, roundabout entries yield
     #[default]
     Auto,
 }
 
+/// What the policy resolves to for a given intersection
+enum Control {
+    None,
+    StopSigns,
+    Yield,
+    Lights { scramble: bool },
+}
+
+const CYCLE_SIZE: u16 = 14 * SECONDS_PER_REALTIME_SECOND as u16;
+const ORANGE_LENGTH: u16 = 4 * SECONDS_PER_REALTIME_SECOND as u16;
+const SCRAMBLE_LENGTH: u16 = 12 * SECONDS_PER_REALTIME_SECOND as u16;
+/// Time at the end of a walk phase where pedestrians on the crosswalk finish crossing but
+/// no one starts crossing anymore
+const PEDESTRIAN_CLEARANCE: u16 = 4 * SECONDS_PER_REALTIME_SECOND as u16;
+/// Head start of the bus and tram lanes: when a bus or tram waits at the light, the other lanes
+/// of the road stay red this long after it turns green so it clears the intersection ahead of
+/// the queue
+pub const QUEUE_JUMP: u16 = 3 * SECONDS_PER_REALTIME_SECOND as u16;
+
+/// Shortest phase a custom program can have, orange included
+pub const MIN_PHASE_LENGTH: u16 = ORANGE_LENGTH + 4 * SECONDS_PER_REALTIME_SECOND as u16;
+/// Longest phase a custom program can have, orange included
+pub const MAX_PHASE_LENGTH: u16 = 90 * SECONDS_PER_REALTIME_SECOND as u16;
+
+/// A custom timing of the lights of an intersection, replacing the default one where every phase
+/// lasts the same time. Each incoming road gets a phase, roads facing each other share it.
+/// The offset of the cycle is [`Intersection::light_offset`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LightProgram {
+    /// Length of each phase in seconds, orange included. The i-th incoming road gets the phase
+    /// `i % phases.len()`
+    pub phases: Vec<u16>,
+}
+
+impl LightProgram {
+    /// The default timing of the lights of the intersection, as a program to edit
+    pub fn new(inter: &Intersection, roads: &Roads) -> Self {
+        let n_roads = LightPolicy::in_road_lanes(inter, roads).len();
+        Self {
+            phases: vec![CYCLE_SIZE; n_phases(n_roads)],
+        }
+    }
+
+    /// Whether the program still has a phase for every pair of incoming roads, it doesn't once
+    /// roads were connected to or removed from the intersection
+    pub fn fits(&self, inter: &Intersection, roads: &Roads) -> bool {
+        self.phases.len() == n_phases(LightPolicy::in_road_lanes(inter, roads).len())
+    }
+
+    /// Length of the cycle without the scramble phase, in seconds
+    pub fn cycle_length(&self) -> u16 {
+        self.phases.iter().map(|&p| Self::clamp_phase(p)).sum()
+    }
+
+    /// Scales the phases so that the cycle lasts about `length` seconds, keeping the splits
+    pub fn set_cycle_length(&mut self, length: u16) {
+        let cur = self.cycle_length() as f32;
+        for p in &mut self.phases {
+            *p = Self::clamp_phase((*p as f32 * length as f32 / cur).round() as u16);
+        }
+    }
+
+    /// Which phase the incoming roads of the intersection get
+    pub fn road_phases(&self, inter: &Intersection, roads: &Roads) -> Vec<(RoadID, usize)> {
+        LightPolicy::in_road_lanes(inter, roads)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (road, _))| (road, i % self.phases.len().max(1)))
+            .collect()
+    }
+
+    pub fn clamp_phase(length: u16) -> u16 {
+        length.clamp(MIN_PHASE_LENGTH, MAX_PHASE_LENGTH)
+    }
+}
+
+/// Number of phases of the lights of an intersection with `n_roads` incoming roads
+fn n_phases(n_roads: usize) -> usize {
+    ((n_roads + 1) / 2).max(1)
+}
+
+/// When the light of an incoming road is green, see [`LightPolicy::road_light_timing`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LightTiming {
+    /// Length of the whole cycle of the intersection, in seconds
+    pub period: u16,
+    /// Offset of the phase of the road within the cycle
+    pub phase: u16,
+    /// Offset of the cycle of the intersection, see [`Intersection::light_offset`]
+    pub inter_offset: u16,
+}
+
+impl LightTiming {
+    /// Total offset of the light, it turns green when `(seconds + offset) % period == 0`
+    pub fn offset(&self) -> u16 {
+        (self.phase + self.inter_offset) % self.period
+    }
+}
+
+/// Timing of the lights of an intersection.
+/// Each road gets a phase, of CYCLE_SIZE unless the intersection has a [`LightProgram`] fitting
+/// its roads.
+/// Roads facing each other share the same phase.
+/// Scrambles are an additional phase where every light is red.
+struct LightCycle {
+    /// Length of each phase, orange included
+    phases: Vec<u16>,
+    scramble: u16,
+    total_length: u16,
+    inter_offset: u16,
+}
+
+impl LightCycle {
+    fn new(n_roads: usize, inter: &Intersection, scramble: bool) -> Self {
+        let phases: Vec<u16> = match inter.light_program {
+            // a program made for other roads falls back to the default timing
+            Some(ref program) if program.phases.len() == n_phases(n_roads) => program
+                .phases
+                .iter()
+                .map(|&p| LightProgram::clamp_phase(p))
+                .collect(),
+            _ => vec![CYCLE_SIZE; n_phases(n_roads)],
+        };
+        let scramble = if scramble { SCRAMBLE_LENGTH } else { 0 };
+        let total_length = phases.iter().sum::<u16>() + scramble;
+
+        let inter_offset = match inter.light_offset {
+            Some(offset) => offset % total_length,
+            None => (common::rand::rand(inter.id.as_ffi() as f32) * total_length as f32) as u16,
+        };
+
+        Self {
+            phases,
+            scramble,
+            total_length,
+            inter_offset,
+        }
+    }
+
+    /// Length of the phase of the i-th road
+    fn phase_length(&self, i: u16) -> u16 {
+        self.phases[i as usize % self.phases.len()]
+    }
+
+    /// Offset of the phase of the i-th road within the cycle. The schedules count the offsets
+    /// backwards: the phase of the i-th road ends when the one of the (i-1)-th starts
+    fn phase_start(&self, i: u16) -> u16 {
+        self.phases[1..=i as usize % self.phases.len()].iter().sum()
+    }
+
+    fn offset(&self, i: u16) -> u16 {
+        self.phase_start(i) + self.inter_offset
+    }
+
+    fn road_light(&self, i: u16) -> TrafficLightSchedule {
+        let length = self.phase_length(i);
+        TrafficLightSchedule::from_basic(
+            length - ORANGE_LENGTH,
+            ORANGE_LENGTH,
+            self.total_length - length,
+            self.offset(i),
+        )
+    }
+
+    /// Walk signal of the crosswalk crossing the i-th road, starting when its traffic stops.
+    /// With scrambles, pedestrians only walk during the scramble phase.
+    fn crosswalk_light(&self, i: Option<u16>) -> Option<TrafficLightSchedule> {
+        let (i, walk_length) = match (self.scramble, i) {
+            (0, Some(i)) => (i, self.total_length - self.phase_length(i)),
+            (0, None) => return None,
+            (scramble, _) => (0, scramble),
+        };
+        let (walk_start, traffic_length) = (self.offset(i), self.phase_length(i));
+        if walk_length <= PEDESTRIAN_CLEARANCE {
+            return None;
+        }
+        Some(TrafficLightSchedule::from_basic(
+            walk_length - PEDESTRIAN_CLEARANCE,
+            PEDESTRIAN_CLEARANCE,
+            self.total_length - walk_length,
+            (walk_start + self.total_length - traffic_length) % self.total_length,
+        ))
+    }
+}
+
 impl LightPolicy {
     pub fn apply(self, inter: &Intersection, lanes: &mut Lanes, roads: &Roads) {
-        let in_road_lanes: Vec<Vec<LaneID>> = inter
-            .roads
-            .iter()
-            .map(|&x| {
-                roads
-                    .get(x)
-                    .into_iter()
-                    .flat_map(|r| {
-                        r.incoming_lanes_to(inter.id)
-                            .iter()
-                            .filter(|(_, kind)| kind.needs_light())
-                            .map(|&(id, _)| id)
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .filter(|v| !v.is_empty())
-            .collect();
+        let in_road_lanes = Self::in_road_lanes(inter, roads);
 
-        for incoming_lanes in &in_road_lanes {
+        for (_, incoming_lanes) in &in_road_lanes {
             for &lane in incoming_lanes {
                 unwrap_cont!(lanes.get_mut(lane)).control = TrafficControl::Always;
             }
         }
 
-        match self {
-            LightPolicy::NoLights => {}
-            LightPolicy::StopSigns => {
-                Self::stop_signs(in_road_lanes, lanes);
+        match self.control(in_road_lanes.len(), inter) {
+            Control::None => Self::set_signs(inter, &in_road_lanes, lanes),
+            Control::StopSigns => {
+                Self::set_control(&in_road_lanes, lanes, TrafficControl::StopSign);
+                Self::set_signs(inter, &in_road_lanes, lanes);
             }
-            LightPolicy::Lights => {
-                Self::lights(in_road_lanes, inter, lanes);
+            Control::Yield => {
+                Self::set_control(&in_road_lanes, lanes, TrafficControl::Yield);
+                Self::set_signs(inter, &in_road_lanes, lanes);
             }
-            LightPolicy::Auto => {
-                if in_road_lanes.len() <= 2 {
-                    return;
-                }
-                if in_road_lanes.len() == 3 {
-                    Self::stop_signs(in_road_lanes, lanes);
-                    return;
-                }
-
-                if inter.turn_policy.left_turns {
-                    Self::lights(in_road_lanes, inter, lanes);
-                } else {
-                    Self::stop_signs(in_road_lanes, lanes);
+            Control::Lights { scramble } => {
+                let cycle = LightCycle::new(in_road_lanes.len(), inter, scramble);
+                for (i, (_, incoming_lanes)) in in_road_lanes.into_iter().enumerate() {
+                    let light = TrafficControl::Light(cycle.road_light(i as u16));
+                    for lane in incoming_lanes {
+                        unwrap_cont!(lanes.get_mut(lane)).control = light;
+                    }
                 }
             }
         }
     }
 
+    /// Walk signals of the crosswalks of the intersection, by the road they cross.
+    /// Roads that are missing can always be crossed.
+    pub fn crosswalk_controls(
+        self,
+        inter: &Intersection,
+        roads: &Roads,
+    ) -> BTreeMap<RoadID, TrafficControl> {
+        let in_road_lanes = Self::in_road_lanes(inter, roads);
+        let Control::Lights { scramble } = self.control(in_road_lanes.len(), inter) else {
+            return BTreeMap::new();
+        };
+        let cycle = LightCycle::new(in_road_lanes.len(), inter, scramble);
+
+        inter
+            .roads
+            .iter()
+            .filter_map(|&road| {
+                let i = in_road_lanes
+                    .iter()
+                    .position(|(r, _)| *r == road)
+                    .map(|i| i as u16);
+                let light = cycle.crosswalk_light(i)?;
+                Some((road, TrafficControl::Light(light)))
+            })
+            .collect()
+    }
+
+    /// Timing of the light of the incoming road, `None` if the road has no light
+    pub fn road_light_timing(
+        self,
+        inter: &Intersection,
+        roads: &Roads,
+        road: RoadID,
+    ) -> Option<LightTiming> {
+        let in_road_lanes = Self::in_road_lanes(inter, roads);
+        let Control::Lights { scramble } = self.control(in_road_lanes.len(), inter) else {
+            return None;
+        };
+        let i = in_road_lanes.iter().position(|(r, _)| *r == road)? as u16;
+        let cycle = LightCycle::new(in_road_lanes.len(), inter, scramble);
+        Some(LightTiming {
+            period: cycle.total_length,
+            phase: cycle.phase_start(i),
+            inter_offset: cycle.inter_offset,
+        })
+    }
+
+    /// Whether the policy puts lights at the intersection
+    pub fn has_lights(self, inter: &Intersection, roads: &Roads) -> bool {
+        let n_roads = Self::in_road_lanes(inter, roads).len();
+        matches!(self.control(n_roads, inter), Control::Lights { .. })
+    }
+
     pub fn is_stop_signs(&self) -> bool {
         matches!(self, LightPolicy::StopSigns)
     }
 
-    fn stop_signs(in_road_lanes: Vec<Vec<LaneID>>, lanes: &mut Lanes) {
-        for incoming_lanes in in_road_lanes {
-            for lane in incoming_lanes {
-                unwrap_cont!(lanes.get_mut(lane)).control = TrafficControl::StopSign;
+    fn control(self, n_roads: usize, inter: &Intersection) -> Control {
+        match self {
+            LightPolicy::NoLights => Control::None,
+            LightPolicy::StopSigns => Control::StopSigns,
+            LightPolicy::Lights => Control::Lights { scramble: false },
+            LightPolicy::PedestrianScramble => Control::Lights { scramble: true },
+            LightPolicy::Auto => {
+                if inter.is_roundabout() {
+                    return Control::Yield;
+                }
+                if n_roads <= 2 {
+                    return Control::None;
+                }
+                if n_roads == 3 || !inter.turn_policy.left_turns {
+                    return Control::StopSigns;
+                }
+                Control::Lights { scramble: false }
             }
         }
     }
 
-    fn lights(in_road_lanes: Vec<Vec<LaneID>>, inter: &Intersection, lanes: &mut Lanes) {
-        let n_cycles = ((in_road_lanes.len() + 1) / 2) as u16;
-        let cycle_size = 14 * SECONDS_PER_REALTIME_SECOND as u16;
-        let orange_length = 4 * SECONDS_PER_REALTIME_SECOND as u16;
-
-        let total_length = cycle_size * n_cycles;
-
-        let inter_offset =
-            (common::rand::rand(inter.id.as_ffi() as f32) * total_length as f32) as u16;
+    /// Incoming lanes needing a light of each road, roads without such lanes are skipped
+    fn in_road_lanes(inter: &Intersection, roads: &Roads) -> Vec<(RoadID, Vec<LaneID>)> {
+        inter
+            .roads
+            .iter()
+            .map(|&x| {
+                let lanes = roads
+                    .get(x)
+                    .into_iter()
+                    .flat_map(|r| {
+                        r.incoming_lanes_to(inter.id)
+                            .iter()
+                            .filter(|(_, kind)| kind.needs_light())
+                            .map(|&(id, _)| id)
+                    })
+                    .collect::<Vec<_>>();
+                (x, lanes)
+            })
+            .filter(|(_, v)| !v.is_empty())
+            .collect()
+    }
 
-        for (i, incoming_lanes) in in_road_lanes.into_iter().enumerate() {
-            let i = i as u16;
-            let light = TrafficControl::Light(TrafficLightSchedule::from_basic(
-                cycle_size - orange_length,
-                orange_length,
-                total_length - cycle_size,
-                cycle_size * (i % n_cycles) + inter_offset,
-            ));
+    fn set_control(
+        in_road_lanes: &[(RoadID, Vec<LaneID>)],
+        lanes: &mut Lanes,
+        control: TrafficControl,
+    ) {
+        for (_, incoming_lanes) in in_road_lanes {
+            for &lane in incoming_lanes {
+                unwrap_cont!(lanes.get_mut(lane)).control = control;
+            }
+        }
+    }
 
-            for lane in incoming_lanes {
-                unwrap_cont!(lanes.get_mut(lane)).control = light;
+    /// Applies the signs of the approaches over the control of the policy
+    fn set_signs(inter: &Intersection, in_road_lanes: &[(RoadID, Vec<LaneID>)], lanes: &mut Lanes) {
+        for (road, incoming_lanes) in in_road_lanes {
+            let Some(sign) = inter.approach_signs.get(road) else {
+                continue;
+            };
+            for &lane in incoming_lanes {
+                unwrap_cont!(lanes.get_mut(lane)).control = sign.control();
             }
         }
     }
+
+    /// Incoming roads of the intersection that can get a sign
+    pub fn approaches(inter: &Intersection, roads: &Roads) -> Vec<RoadID> {
+        Self::in_road_lanes(inter, roads)
+            .into_iter()
+            .map(|(road, _)| road)
+            .collect()
+    }
 }
 
 impl Inspect<LightPolicy> for LightPolicy {
@@ -118,13 +386,15 @@ impl Inspect<LightPolicy> for LightPolicy {
             LightPolicy::NoLights => 0,
             LightPolicy::StopSigns => 1,
             LightPolicy::Lights => 2,
-            LightPolicy::Auto => 3,
+            LightPolicy::PedestrianScramble => 3,
+            LightPolicy::Auto => 4,
         };
 
         let tostr = |x: LightPolicy| match x {
             LightPolicy::NoLights => "No lights",
             LightPolicy::StopSigns => "Stop signs",
             LightPolicy::Lights => "Lights",
+            LightPolicy::PedestrianScramble => "Lights + scramble",
             LightPolicy::Auto => "Auto",
         };
 
@@ -132,12 +402,13 @@ impl Inspect<LightPolicy> for LightPolicy {
             0 => LightPolicy::NoLights,
             1 => LightPolicy::StopSigns,
             2 => LightPolicy::Lights,
-            3 => LightPolicy::Auto,
+            3 => LightPolicy::PedestrianScramble,
+            4 => LightPolicy::Auto,
             _ => unreachable!(),
         };
 
         let changed = egui::ComboBox::from_label(label)
-            .show_index(ui, &mut id, 4, |i| tostr(get(i)).to_string())
+            .show_index(ui, &mut id, 5, |i| tostr(get(i)).to_string())
             .changed();
         if changed {
             *p = get(id);
@@ -146,3 +417,140 @@ impl Inspect<LightPolicy> for LightPolicy {
         changed
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use geom::vec3;
+
+    fn cycle(phases: Vec<u16>, scramble: u16) -> LightCycle {
+        LightCycle {
+            total_length: phases.iter().sum::<u16>() + scramble,
+            phases,
+            scramble,
+            inter_offset: 37,
+        }
+    }
+
+    #[test]
+    fn crosswalks_walk_while_traffic_is_stopped() {
+        let layouts = [vec![CYCLE_SIZE; 2], vec![CYCLE_SIZE, 2 * CYCLE_SIZE]];
+        for (phases, scramble) in layouts
+            .iter()
+            .flat_map(|p| [(p.clone(), 0), (p.clone(), SCRAMBLE_LENGTH)])
+        {
+            let cycle = cycle(phases, scramble);
+            let roads: Vec<_> = (0..4)
+                .map(|i| TrafficControl::Light(cycle.road_light(i)))
+                .collect();
+
+            for i in 0..4 {
+                let walk = TrafficControl::Light(cycle.crosswalk_light(Some(i)).unwrap());
+                let mut walked = false;
+                for t in 0..2 * cycle.total_length as u32 {
+                    if !walk.get_behavior(t).is_green() {
+                        continue;
+                    }
+                    walked = true;
+                    assert!(roads[i as usize].get_behavior(t).is_red());
+                    if scramble > 0 {
+                        assert!(roads.iter().all(|r| r.get_behavior(t).is_red()));
+                    }
+                }
+                assert!(walked);
+            }
+        }
+    }
+
+    #[test]
+    fn lights_tell_how_long_they_have_been_green() {
+        let cycle = cycle(vec![CYCLE_SIZE; 2], 0);
+        let light = TrafficControl::Light(cycle.road_light(1));
+        let mut greens = 0;
+        for t in 1..2 * cycle.total_length as u32 {
+            match light.green_since(t) {
+                Some(since) => {
+                    greens += 1;
+                    assert!(light.get_behavior(t).is_green());
+                    if since > 0 {
+                        assert_eq!(light.green_since(t - 1), Some(since - 1));
+                    } else {
+                        assert!(!light.get_behavior(t - 1).is_green());
+                    }
+                }
+                None => assert!(!light.get_behavior(t).is_green()),
+            }
+        }
+        assert!(greens > QUEUE_JUMP as u32);
+    }
+
+    #[test]
+    fn custom_phases_take_turns() {
+        let phases = vec![CYCLE_SIZE, 2 * CYCLE_SIZE, 3 * CYCLE_SIZE];
+        let cycle = cycle(phases.clone(), SCRAMBLE_LENGTH);
+        let roads: Vec<_> = (0..3)
+            .map(|i| TrafficControl::Light(cycle.road_light(i)))
+            .collect();
+
+        let mut greens = [0; 3];
+        for t in 0..cycle.total_length as u32 {
+            let green: Vec<_> = (0..3)
+                .filter(|&i| roads[i].get_behavior(t).is_green())
+                .collect();
+            assert!(green.len() <= 1);
+            for i in green {
+                greens[i] += 1;
+            }
+        }
+        for (i, length) in phases.into_iter().enumerate() {
+            assert_eq!(greens[i], length - ORANGE_LENGTH);
+        }
+    }
+
+    #[test]
+    fn program_of_other_roads_falls_back_to_the_default() {
+        let test = TestCtx::new();
+        test.build_roads(&[
+            vec3(0.0, 0.0, 0.0),
+            vec3(100.0, 0.0, 0.0),
+            vec3(200.0, 0.0, 0.0),
+        ]);
+        let id = test
+            .g
+            .map()
+            .intersections()
+            .values()
+            .find(|i| i.pos.xy().distance(vec3(100.0, 0.0, 0.0).xy()) < 1.0)
+            .unwrap()
+            .id;
+        test.g.map_mut().update_intersection(id, |i| {
+            i.light_policy = LightPolicy::Lights;
+            i.light_program = Some(LightProgram {
+                phases: vec![MAX_PHASE_LENGTH],
+            });
+        });
+        let period = |test: &TestCtx| {
+            let map = test.g.map();
+            let inter = &map.intersections()[id];
+            let road = inter.roads[0];
+            assert_eq!(
+                inter
+                    .light_program
+                    .as_ref()
+                    .unwrap()
+                    .fits(inter, map.roads()),
+                inter.roads.len() == 2
+            );
+            LightPolicy::Lights
+                .road_light_timing(inter, map.roads(), road)
+                .unwrap()
+                .period
+        };
+        assert_eq!(period(&test), MAX_PHASE_LENGTH);
+
+        test.build_roads(&[vec3(100.0, 100.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        assert_eq!(test.g.map().intersections()[id].roads.len(), 3);
+        assert_eq!(period(&test), 2 * CYCLE_SIZE);
+    }
+}
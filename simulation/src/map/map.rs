@@ -1,24 +1,34 @@
+use crate::map::pathfinding::lane_path;
+use crate::map::procgen::BuildingArchetype;
 use crate::map::serializing::SerializedMap;
 use crate::map::{
-    Building, BuildingID, BuildingKind, Environment, Intersection, IntersectionID, Lane, LaneID,
-    LaneKind, LanePattern, Lot, LotID, LotKind, MapSubscriber, MapSubscribers, ParkingSpotID,
-    ParkingSpots, ProjectFilter, ProjectKind, Road, RoadID, RoadSegmentKind, SpatialMap,
-    SubscriberChunkID, TerraformKind, UpdateType, Zone,
+    Building, BuildingID, BuildingKind, BusStop, BusStopID, ContractionHierarchy, CurbKind,
+    CurbZone, Curbs, Decoration, DecorationID, DecorationSupport, DoorRole, Environment,
+    Intersection, IntersectionID, Lane, LaneDirection, LaneID, LaneKind, LanePattern, Lot, LotID,
+    LotKind, MapLayer, MapSubscriber, MapSubscribers, ParkingSpotID, ParkingSpots, ProjectFilter,
+    ProjectKind, Road, RoadFurniture, RoadID, RoadSegmentKind, SpatialMap, SubscriberChunkID,
+    TerraformKind, Traversable, TurnID, UpdateType, Zone, BUS_BAY_LENGTH, BUS_STOP_SNAP_DIST,
 };
+use crate::map_dynamic::{free_flow_time, District};
 use crate::utils::time::Tick;
 use common::descriptions::BuildingGen;
 use geom::OBB;
-use geom::{Spline3, Vec2, Vec3};
+use geom::{Spline3, Vec2, Vec3, AABB};
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
-use slotmapd::HopSlotMap;
-use std::collections::BTreeMap;
+use slotmapd::{HopSlotMap, Key, SecondaryMap};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Height difference in meters between an object and the terrain above which it doesn't fit
+const MAX_TERRAIN_MISMATCH: f32 = 2.0;
 
 pub type Roads = HopSlotMap<RoadID, Road>;
 pub type Lanes = HopSlotMap<LaneID, Lane>;
 pub type Intersections = HopSlotMap<IntersectionID, Intersection>;
 pub type Buildings = HopSlotMap<BuildingID, Building>;
 pub type Lots = HopSlotMap<LotID, Lot>;
+pub type Decorations = HopSlotMap<DecorationID, Decoration>;
+pub type BusStops = HopSlotMap<BusStopID, BusStop>;
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct MapProject {
@@ -34,9 +44,21 @@ pub struct Map {
     pub(crate) lots: Lots,
     pub(crate) spatial_map: SpatialMap,
     pub(crate) bkinds: BTreeMap<BuildingKind, Vec<BuildingID>>,
+    pub(crate) decorations: Decorations,
+    pub(crate) bus_stops: BusStops,
     pub environment: Environment,
     pub parking: ParkingSpots,
     pub subscribers: MapSubscribers,
+    /// Districts trucks avoid, kept in sync with the policies
+    pub(crate) heavy_vehicle_bans: BTreeSet<District>,
+    /// Roads removed or modified since the itineraries were last checked, not serialized
+    pub(crate) edited_roads: Vec<RoadID>,
+    /// Incremented whenever a road or a building is added or removed, not serialized
+    pub(crate) network_version: u32,
+    /// Speeds up the car pathfinding, rebuilt after the turns change
+    pub(crate) fast_paths: ContractionHierarchy,
+    /// Travel time of the congested lanes, copied from the traffic flow every minute
+    pub(crate) congested_lanes: SecondaryMap<LaneID, f32>,
 }
 
 defer_serialize!(Map, SerializedMap);
@@ -60,7 +82,14 @@ impl Map {
             environment: Environment::default(),
             spatial_map: SpatialMap::default(),
             bkinds: Default::default(),
+            decorations: Decorations::default(),
+            bus_stops: BusStops::default(),
             subscribers: Default::default(),
+            heavy_vehicle_bans: Default::default(),
+            edited_roads: Default::default(),
+            network_version: 0,
+            fast_paths: Default::default(),
+            congested_lanes: Default::default(),
         }
     }
 
@@ -115,6 +144,9 @@ impl Map {
 
         let b = self.buildings.remove(b)?;
         self.spatial_map.remove(b.id);
+        self.decorations
+            .retain(|_, d| d.on != DecorationSupport::Building(b.id));
+        self.network_version += 1;
         self.subscribers.dispatch(UpdateType::Building, &b);
 
         if b.kind.is_cached_in_bkinds() {
@@ -123,6 +155,10 @@ impl Map {
                 .and_modify(|v| v.retain(|id| *id != b.id));
         }
 
+        if b.kind == BuildingKind::ParkingLot {
+            self.parking.remove_lot_spots(b.id);
+        }
+
         self.check_invariants();
 
         Some(b)
@@ -233,6 +269,11 @@ impl Map {
         if let Some(id) = v {
             self.subscribers
                 .dispatch(UpdateType::Building, &self.buildings[id]);
+            self.network_version += 1;
+
+            if kind == BuildingKind::ParkingLot {
+                self.parking.generate_lot_spots(&self.buildings[id]);
+            }
         }
 
         if kind.is_cached_in_bkinds() {
@@ -245,23 +286,37 @@ impl Map {
         v
     }
 
-    pub fn build_house(&mut self, id: LotID) -> Option<BuildingID> {
+    /// Builds a house on the lot, with the default style if no archetype is given
+    pub fn build_house(
+        &mut self,
+        id: LotID,
+        archetype: Option<&BuildingArchetype>,
+    ) -> Option<BuildingID> {
         info!("build house on {:?}", id);
 
         let lot = self.lots.remove(id)?;
         self.subscribers.dispatch(UpdateType::Road, &lot);
         self.spatial_map.remove(lot.id);
 
+        let gen = match archetype {
+            Some(a) => BuildingGen::StyledHouse(a.style),
+            None => BuildingGen::House,
+        };
         let v = Building::make(
             &mut self.buildings,
             &mut self.spatial_map,
             &self.environment,
             lot.shape,
             BuildingKind::House,
-            BuildingGen::House,
+            gen,
             None,
         );
         if let Some(id) = v {
+            if let Some(a) = archetype {
+                let b = &mut self.buildings[id];
+                b.archetype = Some(a.name.clone());
+                b.housing = a.housing(&lot);
+            }
             self.subscribers
                 .dispatch(UpdateType::Building, &self.buildings[id]);
         }
@@ -281,6 +336,11 @@ impl Map {
         self.subscribers.subscribe(filter)
     }
 
+    /// See [`DirtyRegions`](crate::map::DirtyRegions)
+    pub fn dirty_since(&self, version: Option<u64>) -> (Option<Vec<AABB>>, u64) {
+        self.subscribers.dirty_since(version)
+    }
+
     fn clean_lots_inner(&mut self, to_clean: Vec<ProjectKind>) {
         for id in to_clean {
             if let ProjectKind::Lot(id) = id {
@@ -298,11 +358,16 @@ impl Map {
     fn remove_road_inner(&mut self, road_id: RoadID) -> Option<Road> {
         let road = self.remove_raw_road(road_id)?;
         self.subscribers.dispatch(UpdateType::Road, &road);
+        self.edited_roads.push(road_id);
 
         for (id, _) in road.lanes_iter() {
             self.parking.remove_spots(id);
         }
 
+        self.decorations
+            .retain(|_, d| d.on != DecorationSupport::Road(road_id));
+        self.bus_stops.retain(|_, s| s.road != road_id);
+
         let smap = &mut self.spatial_map;
         self.lots.retain(|_, lot| {
             let to_remove = lot.parent == road_id;
@@ -327,6 +392,165 @@ impl Map {
         }
     }
 
+    /// Adds or removes the overhead lines of a rail road
+    pub fn set_road_electrified(&mut self, road: RoadID, electrified: bool) {
+        match self.roads.get_mut(road) {
+            Some(r) => {
+                r.electrified = electrified && r.is_rail();
+                self.subscribers.dispatch(UpdateType::Road, &*r);
+                self.edited_roads.push(road);
+            }
+            None => log::warn!("trying to electrify non-existing road {:?}", road),
+        }
+    }
+
+    /// Designates a section of the curb of a road, its parking spots are regenerated to honor it
+    pub fn set_road_curb(&mut self, road: RoadID, zone: CurbZone) {
+        let Some(r) = self.roads.get(road) else {
+            log::warn!("trying to set curb of non-existing road {:?}", road);
+            return;
+        };
+        let mut curbs = r.curbs.clone();
+        curbs.set(zone);
+        self.set_road_curbs(road, curbs);
+    }
+
+    fn set_road_curbs(&mut self, road: RoadID, curbs: Curbs) {
+        let r = unwrap_ret!(self.roads.get_mut(road));
+        r.curbs = curbs;
+        r.update_lanes(&mut self.lanes, &mut self.parking);
+        self.subscribers.dispatch(UpdateType::Road, &*r);
+    }
+
+    /// Places a speed bump or a sign along a road
+    pub fn add_road_furniture(&mut self, road: RoadID, furniture: RoadFurniture) {
+        let Some(r) = self.roads.get_mut(road) else {
+            log::warn!("trying to add furniture to non-existing road {:?}", road);
+            return;
+        };
+        r.furniture.add(furniture);
+        self.subscribers.dispatch(UpdateType::Road, &*r);
+    }
+
+    /// Removes the speed bump or sign close to the given distance along the road
+    pub fn remove_road_furniture(&mut self, road: RoadID, dist: f32) {
+        let Some(r) = self.roads.get_mut(road) else {
+            return;
+        };
+        if r.furniture.remove_near(dist) {
+            self.subscribers.dispatch(UpdateType::Road, &*r);
+        }
+    }
+
+    /// Places a cosmetic decoration, returns None if what it is placed on doesn't exist
+    pub fn add_decoration(&mut self, decoration: Decoration) -> Option<DecorationID> {
+        let exists = match decoration.on {
+            DecorationSupport::Ground => true,
+            DecorationSupport::Road(id) => self.roads.contains_key(id),
+            DecorationSupport::Building(id) => self.buildings.contains_key(id),
+        };
+        if !exists {
+            log::warn!("trying to place a decoration on {:?}", decoration.on);
+            return None;
+        }
+        Some(self.decorations.insert(decoration))
+    }
+
+    pub fn remove_decoration(&mut self, id: DecorationID) -> Option<Decoration> {
+        self.decorations.remove(id)
+    }
+
+    /// Places a bus stop, returns None if its road doesn't exist
+    /// Places the stop and designates the curb in front of it as its bus bay
+    pub fn add_bus_stop(&mut self, stop: BusStop) -> Option<BusStopID> {
+        if !self.roads.contains_key(stop.road) {
+            log::warn!("trying to place a bus stop on {:?}", stop.road);
+            return None;
+        }
+        if let Some(bay) = self.bus_bay(&stop) {
+            self.set_road_curb(stop.road, bay);
+        }
+        Some(self.bus_stops.insert(stop))
+    }
+
+    /// Removes the stop, the cars can park again on its bus bay
+    pub fn remove_bus_stop(&mut self, id: BusStopID) -> Option<BusStop> {
+        let stop = self.bus_stops.remove(id)?;
+        if let Some(bay) = self.bus_bay(&stop) {
+            self.set_road_curb(
+                stop.road,
+                CurbZone {
+                    kind: CurbKind::Parking,
+                    ..bay
+                },
+            );
+        }
+        Some(stop)
+    }
+
+    /// The section of curb in front of the stop, on the side of its sidewalk
+    fn bus_bay(&self, stop: &BusStop) -> Option<CurbZone> {
+        let road = self.roads.get(stop.road)?;
+        let side_dist = |side| {
+            road.curb_lane(side)
+                .and_then(|l| self.lanes.get(l))
+                .map_or(f32::INFINITY, |l| l.points.project_dist2(stop.pos))
+        };
+        let side = if side_dist(LaneDirection::Backward) < side_dist(LaneDirection::Forward) {
+            LaneDirection::Backward
+        } else {
+            LaneDirection::Forward
+        };
+        let dist = road.points.length_at_proj(road.points.project(stop.pos));
+        Some(CurbZone {
+            side,
+            start: (dist - BUS_BAY_LENGTH * 0.5).max(0.0),
+            end: (dist + BUS_BAY_LENGTH * 0.5).min(road.length()),
+            kind: CurbKind::BusStop,
+        })
+    }
+
+    /// The bus stop on the sidewalk nearest to `pos`, None if there is no sidewalk around
+    pub fn snap_bus_stop(&self, pos: Vec3, name: String) -> Option<BusStop> {
+        let lane = self.nearest_lane(pos, LaneKind::Walking, Some(BUS_STOP_SNAP_DIST))?;
+        let lane = self.lanes.get(lane)?;
+        let (pos, _, dir) = lane.points.project_segment_dir(pos);
+        Some(BusStop {
+            name,
+            pos,
+            dir: dir.xy(),
+            road: lane.parent,
+        })
+    }
+
+    /// Replaces a house by an empty lot of the given kind, so that it can be built upon again
+    pub fn rezone_building(&mut self, b: BuildingID, kind: LotKind) -> Option<LotID> {
+        if !matches!(self.buildings.get(b)?.kind, BuildingKind::House) {
+            return None;
+        }
+        info!("rezone building {:?} to {:?}", b, kind);
+
+        let b = self.remove_building(b)?;
+        let ProjectKind::Road(parent) = self.project(b.door_pos, 30.0, ProjectFilter::ROAD).kind
+        else {
+            return None;
+        };
+        let height = self.environment.height(b.obb.center())?;
+
+        let id = self.lots.insert_with_key(move |id| Lot {
+            id,
+            parent,
+            kind,
+            shape: b.obb,
+            height,
+        });
+        self.spatial_map.insert(id, b.obb);
+        self.subscribers.dispatch(UpdateType::Road, &self.lots[id]);
+
+        self.check_invariants();
+        Some(id)
+    }
+
     pub fn terraform(
         &mut self,
         tick: Tick,
@@ -336,20 +560,46 @@ impl Map {
         amount: f32,
         level: f32,
         slope: Option<(Vec3, Vec3)>,
-    ) {
-        let modified = self
+    ) -> f32 {
+        let (modified, volume) = self
             .environment
             .terraform(tick, kind, center, radius, amount, level, slope);
 
         for id in modified {
             self.subscribers.dispatch_chunk(UpdateType::Terrain, id);
         }
+
+        // pylons and foundations follow the terrain, so the objects on it are redrawn
+        let area = AABB::centered(center, Vec2::splat(radius * 2.0));
+        for obj in self
+            .spatial_map
+            .query(area, ProjectFilter::ROAD | ProjectFilter::BUILDING)
+        {
+            match obj {
+                ProjectKind::Road(id) => {
+                    let Some(road) = self.roads.get(id) else {
+                        continue;
+                    };
+                    self.subscribers.dispatch(UpdateType::Road, road);
+                }
+                ProjectKind::Building(id) => {
+                    let Some(b) = self.buildings.get(id) else {
+                        continue;
+                    };
+                    self.subscribers.dispatch(UpdateType::Building, b);
+                }
+                _ => {}
+            }
+        }
+
+        volume
     }
 
     pub fn clear(&mut self) {
         info!("clear");
         let before = std::mem::replace(self, Self::empty());
         self.environment = before.environment;
+        self.network_version = before.network_version + 1;
         self.subscribers.dispatch_clear();
 
         self.check_invariants();
@@ -369,6 +619,7 @@ impl Map {
 
         let inter = unwrap_ret!(self.intersections.get_mut(id));
         self.subscribers.dispatch(UpdateType::Road, inter);
+        self.fast_paths.invalidate();
 
         if inter.roads.is_empty() {
             self.remove_intersection_inner(id);
@@ -411,6 +662,7 @@ impl Map {
         let road = self.roads.remove(road_id)?;
 
         self.spatial_map.remove(road_id);
+        self.network_version += 1;
 
         for (id, _) in road.lanes_iter() {
             self.lanes.remove(id);
@@ -437,6 +689,7 @@ impl Map {
             return None;
         });
         self.subscribers.dispatch(UpdateType::Road, &r);
+        self.edited_roads.push(r_id);
 
         for (id, _) in r.lanes_iter() {
             self.parking.remove_to_reuse(id);
@@ -485,6 +738,20 @@ impl Map {
             }
         };
 
+        let split = r.points.length_at_proj(r.points.project(pos));
+        if !r.curbs.is_empty() {
+            self.set_road_curbs(r1, r.curbs.cut(0.0, split));
+            self.set_road_curbs(r2, r.curbs.cut(split, f32::INFINITY));
+        }
+        if !r.furniture.is_empty() {
+            if let Some(road) = self.roads.get_mut(r1) {
+                road.furniture = r.furniture.cut(0.0, split);
+            }
+            if let Some(road) = self.roads.get_mut(r2) {
+                road.furniture = r.furniture.cut(split, f32::INFINITY);
+            }
+        }
+
         log::info!(
             "{} parking spots reused when splitting",
             self.parking.clean_reuse()
@@ -519,6 +786,24 @@ impl Map {
             true
         });
 
+        for d in self.decorations.values_mut() {
+            if d.on != DecorationSupport::Road(r_id) {
+                continue;
+            }
+            let d1 = r1.points.project(d.pos).distance(d.pos);
+            let d2 = r2.points.project(d.pos).distance(d.pos);
+            d.on = DecorationSupport::Road(if d1 < d2 { r1.id } else { r2.id });
+        }
+
+        for stop in self.bus_stops.values_mut() {
+            if stop.road != r_id {
+                continue;
+            }
+            let d1 = r1.points.project(stop.pos).distance(stop.pos);
+            let d2 = r2.points.project(stop.pos).distance(stop.pos);
+            stop.road = if d1 < d2 { r1.id } else { r2.id };
+        }
+
         Some(id)
     }
 
@@ -548,6 +833,7 @@ impl Map {
             &mut self.parking,
             &mut self.spatial_map,
         );
+        self.network_version += 1;
         #[allow(clippy::indexing_slicing)]
         let r = &self.roads[id];
 
@@ -574,10 +860,35 @@ impl Map {
 
     // Public helpers
     pub fn project(&self, pos: Vec3, tolerance: f32, filter: ProjectFilter) -> MapProject {
+        self.project_where(pos, tolerance, filter, |_| true)
+    }
+
+    /// Same as [`Map::project`], only snapping to the objects of the layer
+    pub fn project_layer(
+        &self,
+        pos: Vec3,
+        tolerance: f32,
+        filter: ProjectFilter,
+        layer: MapLayer,
+    ) -> MapProject {
+        self.project_where(pos, tolerance, filter, |kind| layer.contains(self, kind))
+    }
+
+    fn project_where(
+        &self,
+        pos: Vec3,
+        tolerance: f32,
+        filter: ProjectFilter,
+        keep: impl Fn(ProjectKind) -> bool,
+    ) -> MapProject {
         let mk_proj = move |kind| MapProject { pos, kind };
 
         let mut qroad = None;
-        for pkind in self.spatial_map.query_around(pos.xy(), tolerance, filter) {
+        for pkind in self
+            .spatial_map
+            .query_around(pos.xy(), tolerance, filter)
+            .filter(|&kind| keep(kind))
+        {
             match pkind {
                 ProjectKind::Inter(id) => {
                     let inter = unwrap_contlog!(self.intersections.get(id),
@@ -634,12 +945,26 @@ impl Map {
     pub fn buildings(&self) -> &Buildings {
         &self.buildings
     }
+    pub fn decorations(&self) -> &Decorations {
+        &self.decorations
+    }
+
+    pub fn bus_stops(&self) -> &BusStops {
+        &self.bus_stops
+    }
     pub fn lots(&self) -> &Lots {
         &self.lots
     }
     pub fn spatial_map(&self) -> &SpatialMap {
         &self.spatial_map
     }
+    pub fn heavy_vehicle_bans(&self) -> &BTreeSet<District> {
+        &self.heavy_vehicle_bans
+    }
+
+    pub fn set_heavy_vehicle_bans(&mut self, bans: BTreeSet<District>) {
+        self.heavy_vehicle_bans = bans;
+    }
 
     pub fn building_overlaps(&self, obb: OBB) -> bool {
         self.spatial_map
@@ -648,6 +973,31 @@ impl Map {
             .is_some()
     }
 
+    /// Roads and buildings in the area that don't fit the terrain anymore:
+    /// roads buried under it and buildings whose foundations are off the ground
+    pub fn terrain_conflicts(&self, area: AABB) -> Vec<ProjectKind> {
+        let env = &self.environment;
+        self.spatial_map
+            .query(area, ProjectFilter::ROAD | ProjectFilter::BUILDING)
+            .filter(|&obj| match obj {
+                ProjectKind::Road(id) => self.roads.get(id).map_or(false, |road| {
+                    road.points().equipoints_dir(10.0, true).any(|(pos, _)| {
+                        env.height(pos.xy())
+                            .map_or(false, |h| h > pos.z + MAX_TERRAIN_MISMATCH)
+                    })
+                }),
+                ProjectKind::Building(id) => self.buildings.get(id).map_or(false, |b| {
+                    let base = b.door_pos.z - 0.1;
+                    b.obb.corners.iter().any(|&c| {
+                        env.height(c)
+                            .map_or(false, |h| (h - base).abs() > MAX_TERRAIN_MISMATCH)
+                    })
+                }),
+                _ => false,
+            })
+            .collect()
+    }
+
     pub fn find_road(&self, src: IntersectionID, dst: IntersectionID) -> Option<RoadID> {
         for &r in &self.intersections.get(src)?.roads {
             let road = unwrap_cont!(self.roads.get(r));
@@ -658,7 +1008,76 @@ impl Map {
         None
     }
 
+    /// Car path using the contraction hierarchy, None until it is first built or if the lanes
+    /// are not driving lanes. The trips are spread over the paths of about the same cost like
+    /// the search does.
+    /// While the hierarchy is rebuilt after an edit, the paths going through removed turns are
+    /// None too and the new roads are not used yet.
+    pub fn shortest_path_fast(
+        &self,
+        tick: Tick,
+        start: Traversable,
+        end: LaneID,
+    ) -> Option<Vec<Traversable>> {
+        let start_lane = start.destination_lane();
+        let seed = common::hash_u64((start_lane.data().as_ffi(), tick.0)) as u32;
+        let lanes = self.fast_paths.path(start_lane, end, seed)?;
+        for pair in lanes.windows(2) {
+            let inter = self.intersections.get(self.lanes.get(pair[1])?.src)?;
+            inter.find_turn(TurnID::new(inter.id, pair[0], pair[1], false))?;
+        }
+        lane_path(self, start, lanes.into_iter().skip(1))
+    }
+
+    /// Seconds to drive through the lane, measured by the traffic flow when it is congested
+    pub fn lane_travel_time(&self, lane: &Lane) -> f32 {
+        self.congested_lanes
+            .get(lane.id)
+            .copied()
+            .unwrap_or_else(|| free_flow_time(lane))
+    }
+
+    /// Updates the costs of the contraction hierarchy for the lanes whose travel time changed
+    pub fn set_congested_lanes(&mut self, lanes: SecondaryMap<LaneID, f32>) {
+        let changed: Vec<LaneID> = lanes
+            .iter()
+            .filter(|&(id, t)| self.congested_lanes.get(id) != Some(t))
+            .map(|(id, _)| id)
+            .chain(
+                self.congested_lanes
+                    .keys()
+                    .filter(|&id| !lanes.contains_key(id)),
+            )
+            .collect();
+        self.congested_lanes = lanes;
+
+        let mut fast_paths = std::mem::take(&mut self.fast_paths);
+        fast_paths.customize(self, &changed);
+        self.fast_paths = fast_paths;
+    }
+
+    /// Continues the rebuild of the contraction hierarchy after the turns changed
+    pub fn update_fast_paths(&mut self) {
+        let mut fast_paths = std::mem::take(&mut self.fast_paths);
+        fast_paths.update(self);
+        self.fast_paths = fast_paths;
+    }
+
     pub fn nearest_lane(&self, p: Vec3, kind: LaneKind, cutoff: Option<f32>) -> Option<LaneID> {
+        self.nearest_lane_where(p, cutoff, |k| k == kind)
+    }
+
+    /// The nearest lane every vehicle can drive on, the tram tracks in the street included
+    pub fn nearest_driving_lane(&self, p: Vec3, cutoff: Option<f32>) -> Option<LaneID> {
+        self.nearest_lane_where(p, cutoff, LaneKind::is_driving)
+    }
+
+    fn nearest_lane_where(
+        &self,
+        p: Vec3,
+        cutoff: Option<f32>,
+        accepts: impl Fn(LaneKind) -> bool,
+    ) -> Option<LaneID> {
         let tryfind = |radius| {
             self.spatial_map()
                 .query_around(p.xy(), radius, ProjectFilter::ROAD)
@@ -671,7 +1090,7 @@ impl Map {
                 })
                 .filter_map(|id| self.roads().get(id))
                 .flat_map(|road| road.lanes_iter())
-                .filter(|&(_, x)| x == kind)
+                .filter(|&(_, x)| accepts(x))
                 .map(|(id, _)| &self.lanes[id])
                 .min_by_key(|lane| OrderedFloat(lane.points.project_dist2(p)))
         };
@@ -690,36 +1109,36 @@ impl Map {
 
         self.lanes
             .iter()
-            .filter(|(_, x)| x.kind == kind)
+            .filter(|(_, x)| accepts(x.kind))
             .min_by_key(|(_, lane)| OrderedFloat(lane.points.project_dist2(p)))
             .map(|(id, _)| id)
     }
 
     pub fn parking_to_drive(&self, spot: ParkingSpotID) -> Option<LaneID> {
         let spot = self.parking.get(spot)?;
+        if let Some(lot) = spot.lot {
+            let entrance = self.buildings.get(lot)?.door(DoorRole::Driveway);
+            return self.nearest_driving_lane(entrance, None);
+        }
         let park_lane = self.lanes.get(spot.parent)?;
         let road = self.roads.get(park_lane.parent)?;
         road.outgoing_lanes_from(park_lane.src)
             .iter()
-            .rfind(|&&(_, kind)| kind == LaneKind::Driving)
+            .rfind(|&&(_, kind)| kind.is_driving())
             .map(|&(id, _)| id)
     }
 
-    pub fn parking_to_drive_pos(&self, spot: ParkingSpotID) -> Option<Vec3> {
-        let spot = self.parking.get(spot)?;
-        let park_lane = self.lanes.get(spot.parent)?;
-        let road = self.roads.get(park_lane.parent)?;
-        let lane = road
-            .outgoing_lanes_from(park_lane.src)
-            .iter()
-            .rfind(|&&(_, kind)| kind == LaneKind::Driving)
-            .map(|&(id, _)| id)?;
-
-        let (pos, _, dir) = self
-            .lanes()
-            .get(lane)?
-            .points
-            .project_segment_dir(spot.trans.position);
+    /// Where the vehicles stop on the road before parking in the spot. For the spots of the
+    /// parking lots, it is in front of the lot's entrance
+    pub fn parking_to_drive_pos(&self, spot_id: ParkingSpotID) -> Option<Vec3> {
+        let spot = self.parking.get(spot_id)?;
+        let lane = self.parking_to_drive(spot_id)?;
+        let target = match spot.lot {
+            Some(lot) => self.buildings.get(lot)?.door(DoorRole::Driveway),
+            None => spot.trans.position,
+        };
+
+        let (pos, _, dir) = self.lanes().get(lane)?.points.project_segment_dir(target);
         Some(pos - dir * 4.0)
     }
 
@@ -771,6 +1190,10 @@ impl Map {
             }
         }
 
+        for (lot, _) in self.parking.lots() {
+            assert!(self.buildings.contains_key(lot), "{:?}", lot);
+        }
+
         for bs in self.bkinds.values() {
             for &b in bs {
                 assert!(self.buildings.contains_key(b));
@@ -806,7 +1229,7 @@ impl Map {
                 let has_driving = road
                     .incoming_lanes_to(road.src)
                     .iter()
-                    .any(|(_, kind)| matches!(kind, LaneKind::Driving));
+                    .any(|(_, kind)| kind.is_driving());
                 assert!(has_driving);
             }
 
@@ -820,7 +1243,7 @@ impl Map {
                 let has_driving = road
                     .outgoing_lanes_from(road.src)
                     .iter()
-                    .any(|(_, kind)| matches!(kind, LaneKind::Driving));
+                    .any(|(_, kind)| kind.is_driving());
                 assert!(has_driving);
             }
         }
@@ -1,5 +1,9 @@
 mod objects {
     mod building;
+    mod bus_stop;
+    mod curb;
+    mod decoration;
+    mod furniture;
     mod intersection;
     mod lane;
     mod lot;
@@ -8,6 +12,10 @@ mod objects {
     mod turn;
 
     pub use building::*;
+    pub use bus_stop::*;
+    pub use curb::*;
+    pub use decoration::*;
+    pub use furniture::*;
     pub use intersection::*;
     pub use lane::*;
     pub use lot::*;
@@ -19,15 +27,28 @@ mod objects {
 pub use objects::*;
 
 pub mod procgen {
+    mod archetypes;
     mod building;
+    mod edges;
     pub mod heightmap;
+    mod interchange;
+    mod osm;
     mod presets;
+    mod sumo;
 
+    pub use archetypes::*;
     pub use building::*;
+    pub use edges::*;
+    pub use interchange::*;
+    pub use osm::*;
     pub use presets::*;
+    pub use sumo::*;
 }
 
+mod arterials;
 mod change_detection;
+mod contraction;
+mod layer;
 mod light_policy;
 #[allow(clippy::module_inception)]
 mod map;
@@ -41,7 +62,10 @@ mod turn_policy;
 
 // Use self or else it would be ambiguous with "pathfinding" crate
 pub use self::pathfinding::*;
+pub use arterials::*;
 pub use change_detection::*;
+pub use contraction::*;
+pub use layer::*;
 pub use light_policy::*;
 pub use map::*;
 pub use spatial_map::*;
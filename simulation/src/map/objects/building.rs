@@ -1,9 +1,9 @@
 use crate::map::procgen::{gen_exterior_farm, gen_exterior_house, ColoredMesh};
 use crate::map::{Buildings, Environment, LanePattern, SpatialMap};
 use crate::souls::goods_company::GoodsCompanyID;
-use common::descriptions::BuildingGen;
+use common::descriptions::{BuildingGen, HouseStyle};
 use egui_inspect::debug_inspect_impl;
-use geom::{Color, Polygon, Vec2, Vec3, OBB};
+use geom::{Color, Polygon, Transform, Vec2, Vec3, OBB};
 use serde::{Deserialize, Serialize};
 use slotmapd::new_key_type;
 
@@ -20,6 +20,16 @@ pub enum BuildingKind {
     RailFreightStation,
     TrainStation,
     ExternalTrading,
+    SnowPlowDepot,
+    Airport,
+    /// Where barges load and unload, built next to a canal
+    Harbor,
+    /// Entrance to the platform of a metro tunnel, built above it
+    MetroStation,
+    /// Off-street parking, its footprint is covered with spots
+    ParkingLot,
+    /// Stadium or concert hall hosting events, see [`crate::map_dynamic::VenueEvents`]
+    EventVenue,
 }
 
 impl BuildingKind {
@@ -33,7 +43,13 @@ impl BuildingKind {
     pub fn is_cached_in_bkinds(&self) -> bool {
         matches!(
             self,
-            BuildingKind::RailFreightStation | BuildingKind::ExternalTrading
+            BuildingKind::RailFreightStation
+                | BuildingKind::ExternalTrading
+                | BuildingKind::SnowPlowDepot
+                | BuildingKind::Airport
+                | BuildingKind::Harbor
+                | BuildingKind::MetroStation
+                | BuildingKind::EventVenue
         )
     }
 }
@@ -69,18 +85,62 @@ impl Zone {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DoorRole {
+    /// Where people get in and out of the building
+    Pedestrian,
+    /// Where trucks load and unload goods, away from the pedestrian entrance
+    FreightDock,
+    /// Where vehicles kept inside the building come out onto the road
+    Driveway,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Door {
+    pub pos: Vec3,
+    pub role: DoorRole,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Building {
     pub id: BuildingID,
+    /// Main pedestrian door
     pub door_pos: Vec3,
+    /// Every door of the building, including the main one
+    pub doors: Vec<Door>,
     pub kind: BuildingKind,
     pub mesh: ColoredMesh,
     pub obb: OBB,
     pub height: f32,
     pub zone: Option<Zone>,
+    /// Name of the archetype the house was generated from
+    pub archetype: Option<String>,
+    /// Number of people living in the house
+    pub housing: u32,
+    /// How the building was generated, to build it again when its removal is undone
+    pub gen: Option<BuildingGen>,
 }
 
 impl Building {
+    /// Position of the first door with the given role, falling back to the main door
+    pub fn door(&self, role: DoorRole) -> Vec3 {
+        self.doors
+            .iter()
+            .find(|d| d.role == role)
+            .map_or(self.door_pos, |d| d.pos)
+    }
+
+    /// Where the driveway meets the road, facing away from the building
+    pub fn driveway(&self) -> Option<Transform> {
+        let door = self.doors.iter().find(|d| d.role == DoorRole::Driveway)?;
+        let out = -self.obb.axis()[1].normalize();
+        Some(Transform::new_dir(door.pos, out.z0()))
+    }
+
+    pub fn is_at_door(&self, pos: Vec3, dist: f32) -> bool {
+        self.door_pos.is_close(pos, dist) || self.doors.iter().any(|d| d.pos.is_close(pos, dist))
+    }
+
     pub fn make(
         buildings: &mut Buildings,
         spatial_map: &mut SpatialMap,
@@ -91,28 +151,42 @@ impl Building {
         zone: Option<Zone>,
     ) -> Option<BuildingID> {
         let at = obb.center().z(env.height(obb.center())?);
-        let axis = (obb.corners[1] - obb.corners[0]).normalize();
-        let size = obb.corners[0].distance(obb.corners[1]);
+        let [front, side] = obb.axis();
+        let size = front.mag();
+        let axis = front.normalize();
+        let depth = side.normalize();
+        // mirrored boxes have their corners in clockwise order so the x axis is flipped
+        let to_world = |v: Vec2| axis * v.x + depth * v.y;
 
         let r = common::rand::rand2(obb.center().x, obb.center().y).to_bits();
 
         let (mut mesh, door_pos) = match gen {
-            BuildingGen::House => gen_exterior_house(size, r as u64),
+            BuildingGen::House => gen_exterior_house(size, r as u64, &HouseStyle::default()),
+            BuildingGen::StyledHouse(ref style) => gen_exterior_house(size, r as u64, style),
             BuildingGen::Farm => gen_exterior_farm(size, r as u64),
             BuildingGen::CenteredDoor {
                 vertical_factor, ..
             } => (Default::default(), Vec2::y(-vertical_factor * 0.5 * size)),
-            BuildingGen::NoWalkway { door_pos } => (Default::default(), door_pos),
+            BuildingGen::NoWalkway { door_pos, .. } => (Default::default(), door_pos),
         };
 
+        let mirrored = obb.is_mirrored();
         for (poly, _) in &mut mesh.faces {
-            for v in poly {
-                *v = v.rotate_z(axis) + at;
+            for v in poly.iter_mut() {
+                *v = to_world(v.xy()).z(v.z) + at;
+            }
+            if mirrored {
+                // keep the faces facing outward
+                poly.reverse();
             }
         }
-        let door_pos = door_pos.rotated_by(axis).z0() + at + Vec3::z(0.1);
+        let door_pos = to_world(door_pos).z0() + at + Vec3::z(0.1);
 
-        if let BuildingGen::House | BuildingGen::Farm | BuildingGen::CenteredDoor { .. } = gen {
+        if let BuildingGen::House
+        | BuildingGen::StyledHouse(_)
+        | BuildingGen::Farm
+        | BuildingGen::CenteredDoor { .. } = gen
+        {
             let bot = obb.segments()[0];
             let rpos = bot.project(door_pos.xy()).z(door_pos.z);
             let dir = bot.vec().normalize().z(0.0);
@@ -127,6 +201,53 @@ impl Building {
             mesh.faces.push((walkway, Color::gray(0.4).into()));
         }
 
+        let mut doors = vec![Door {
+            pos: door_pos,
+            role: DoorRole::Pedestrian,
+        }];
+
+        let layout = gen.doors();
+
+        // Trucks park on the street next to a corner of the building instead of in front of the
+        // pedestrian entrance, so deliveries don't clog the sidewalk
+        if let Some(x) = layout.freight_dock {
+            let dock_pos = to_world(Vec2::new(x * size, -0.5 * size)).z0() + at + Vec3::z(0.1);
+            let dir = axis.z(0.0);
+            let depth = depth.z(0.0) * 3.0;
+
+            let dock = vec![
+                dock_pos - dir * 3.0,
+                dock_pos + dir * 3.0,
+                dock_pos + dir * 3.0 + depth,
+                dock_pos - dir * 3.0 + depth,
+            ];
+
+            mesh.faces.push((dock, Color::gray(0.25).into()));
+            doors.push(Door {
+                pos: dock_pos,
+                role: DoorRole::FreightDock,
+            });
+        }
+
+        if let Some(x) = layout.driveway {
+            let drive_pos = to_world(Vec2::new(x * size, -0.5 * size)).z0() + at + Vec3::z(0.05);
+            let dir = axis.z(0.0);
+            let depth = depth.z(0.0) * 5.0;
+
+            let driveway = vec![
+                drive_pos - dir * 1.5,
+                drive_pos + dir * 1.5,
+                drive_pos + dir * 1.5 + depth,
+                drive_pos - dir * 1.5 + depth,
+            ];
+
+            mesh.faces.push((driveway, Color::gray(0.3).into()));
+            doors.push(Door {
+                pos: drive_pos,
+                role: DoorRole::Driveway,
+            });
+        }
+
         Some(buildings.insert_with_key(move |id| {
             if let Some(zone) = zone.clone() {
                 spatial_map.insert(id, zone.poly);
@@ -138,9 +259,13 @@ impl Building {
                 mesh,
                 kind,
                 door_pos,
+                doors,
                 obb,
                 height: at.z,
                 zone,
+                archetype: None,
+                housing: matches!(kind, BuildingKind::House) as u32,
+                gen: Some(gen),
             }
         }))
     }
@@ -0,0 +1,59 @@
+use crate::map::{Map, PathKind, Pathfinder, RoadID};
+use egui_inspect::debug_inspect_impl;
+use geom::{Vec2, Vec3, OBB};
+use serde::{Deserialize, Serialize};
+use slotmapd::new_key_type;
+
+new_key_type! {
+    pub struct BusStopID;
+}
+
+debug_inspect_impl!(BusStopID);
+
+/// How far from a sidewalk a bus stop can be placed, it is moved onto it
+pub const BUS_STOP_SNAP_DIST: f32 = 15.0;
+/// Minimum distance between two bus stops
+pub const MIN_BUS_STOP_SPACING: f32 = 20.0;
+/// Length of the curb in front of a bus stop where the cars can't park
+pub const BUS_BAY_LENGTH: f32 = 20.0;
+/// Longest name of a bus stop
+pub const MAX_BUS_STOP_NAME: usize = 30;
+const SHELTER_LENGTH: f32 = 5.0;
+const SHELTER_WIDTH: f32 = 1.5;
+
+/// A shelter on a sidewalk, the passengers wait there for the buses of the lines serving it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BusStop {
+    pub name: String,
+    /// Where the passengers wait, on the sidewalk
+    pub pos: Vec3,
+    /// Direction of the sidewalk
+    pub dir: Vec2,
+    /// The stop goes away along with its road
+    pub road: RoadID,
+}
+
+impl BusStop {
+    /// Footprint of the shelter
+    pub fn obb(&self) -> OBB {
+        OBB::new(self.pos.xy(), self.dir, SHELTER_LENGTH, SHELTER_WIDTH)
+    }
+
+    /// Where the buses or trams stop to let the passengers in, on the nearest lane they can
+    /// drive on
+    pub fn bus_pos(&self, map: &Map, kind: PathKind) -> Option<Vec3> {
+        let lane = kind.nearest_lane(map, self.pos)?;
+        Some(map.lanes().get(lane)?.points.project(self.pos))
+    }
+
+    /// Why the stop can't be placed, if it can't
+    pub fn check(&self) -> Result<(), &'static str> {
+        if self.name.chars().count() > MAX_BUS_STOP_NAME {
+            return Err("Bus stop name is too long");
+        }
+        if self.dir.try_normalize().is_none() {
+            return Err("Bus stop has no direction");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use crate::map::LaneDirection;
+use serde::{Deserialize, Serialize};
+
+/// Designated sections shorter than this are dropped
+pub const MIN_CURB_ZONE_LENGTH: f32 = 2.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CurbKind {
+    /// Anyone can park, the curbs without designation are parking
+    Parking,
+    /// The bus bay in front of a [`crate::map::BusStop`], nobody can park.
+    /// It comes and goes with the bus stop
+    BusStop,
+    /// Only trucks can park there, to load and unload goods
+    LoadingZone,
+    /// Nobody can park
+    NoParking,
+}
+
+impl CurbKind {
+    /// What the curb tool designates, the bus bays are placed with the bus stops
+    pub const DESIGNATIONS: [CurbKind; 3] = [
+        CurbKind::Parking,
+        CurbKind::LoadingZone,
+        CurbKind::NoParking,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CurbKind::Parking => "Parking",
+            CurbKind::BusStop => "Bus stop",
+            CurbKind::LoadingZone => "Loading zone",
+            CurbKind::NoParking => "No parking",
+        }
+    }
+
+    /// Whether parking spots are generated along the curb
+    pub fn has_spots(self) -> bool {
+        matches!(self, CurbKind::Parking | CurbKind::LoadingZone)
+    }
+}
+
+/// A designated section of the curb on one side of a road
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CurbZone {
+    /// The side of the lanes going in that direction
+    pub side: LaneDirection,
+    /// Distance along the road from its source, start < end
+    pub start: f32,
+    pub end: f32,
+    pub kind: CurbKind,
+}
+
+impl CurbZone {
+    pub fn length(&self) -> f32 {
+        self.end - self.start
+    }
+
+    pub fn contains(&self, side: LaneDirection, dist: f32) -> bool {
+        self.side == side && self.start <= dist && dist < self.end
+    }
+}
+
+/// The curb designations of both sides of a road, sorted and not overlapping
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Curbs {
+    zones: Vec<CurbZone>,
+}
+
+impl Curbs {
+    pub fn zones(&self) -> &[CurbZone] {
+        &self.zones
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    pub fn kind_at(&self, side: LaneDirection, dist: f32) -> CurbKind {
+        self.zones
+            .iter()
+            .find(|z| z.contains(side, dist))
+            .map_or(CurbKind::Parking, |z| z.kind)
+    }
+
+    /// Designates the section, replacing the designations it overlaps.
+    /// Designating as [`CurbKind::Parking`] clears the section.
+    pub fn set(&mut self, zone: CurbZone) {
+        let mut zones = Vec::with_capacity(self.zones.len() + 2);
+        for z in self.zones.drain(..) {
+            if z.side != zone.side || z.end <= zone.start || z.start >= zone.end {
+                zones.push(z);
+                continue;
+            }
+            if z.start < zone.start {
+                zones.push(CurbZone {
+                    end: zone.start,
+                    ..z
+                });
+            }
+            if z.end > zone.end {
+                zones.push(CurbZone {
+                    start: zone.end,
+                    ..z
+                });
+            }
+        }
+        if zone.kind != CurbKind::Parking {
+            zones.push(zone);
+        }
+        self.zones = zones;
+        self.normalize();
+    }
+
+    /// The designations between `from` and `to`, moved so that `from` is at 0.
+    /// Used to keep the designations of a road that is split.
+    pub fn cut(&self, from: f32, to: f32) -> Curbs {
+        let mut curbs = Curbs {
+            zones: self
+                .zones
+                .iter()
+                .filter(|z| z.end > from && z.start < to)
+                .map(|z| CurbZone {
+                    start: z.start.max(from) - from,
+                    end: z.end.min(to) - from,
+                    ..*z
+                })
+                .collect(),
+        };
+        curbs.normalize();
+        curbs
+    }
+
+    fn normalize(&mut self) {
+        self.zones.retain(|z| z.length() >= MIN_CURB_ZONE_LENGTH);
+        self.zones.sort_by(|a, b| {
+            let side = |z: &CurbZone| z.side == LaneDirection::Backward;
+            side(a).cmp(&side(b)).then(a.start.total_cmp(&b.start))
+        });
+        self.zones.dedup_by(|next, prev| {
+            let merge =
+                prev.side == next.side && prev.kind == next.kind && next.start - prev.end < 0.01;
+            if merge {
+                prev.end = prev.end.max(next.end);
+            }
+            merge
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone(start: f32, end: f32, kind: CurbKind) -> CurbZone {
+        CurbZone {
+            side: LaneDirection::Forward,
+            start,
+            end,
+            kind,
+        }
+    }
+
+    #[test]
+    fn set_curbs() {
+        let mut curbs = Curbs::default();
+        curbs.set(zone(10.0, 50.0, CurbKind::LoadingZone));
+        assert_eq!(
+            curbs.kind_at(LaneDirection::Forward, 20.0),
+            CurbKind::LoadingZone
+        );
+        assert_eq!(
+            curbs.kind_at(LaneDirection::Backward, 20.0),
+            CurbKind::Parking
+        );
+
+        curbs.set(zone(20.0, 30.0, CurbKind::BusStop));
+        assert_eq!(
+            curbs.zones(),
+            &[
+                zone(10.0, 20.0, CurbKind::LoadingZone),
+                zone(20.0, 30.0, CurbKind::BusStop),
+                zone(30.0, 50.0, CurbKind::LoadingZone),
+            ]
+        );
+
+        curbs.set(zone(15.0, 35.0, CurbKind::LoadingZone));
+        assert_eq!(curbs.zones(), &[zone(10.0, 50.0, CurbKind::LoadingZone)]);
+
+        curbs.set(zone(0.0, 49.0, CurbKind::Parking));
+        assert!(curbs.is_empty());
+
+        curbs.set(zone(10.0, 50.0, CurbKind::NoParking));
+        assert_eq!(
+            curbs.cut(40.0, 100.0).zones(),
+            &[zone(0.0, 10.0, CurbKind::NoParking)]
+        );
+    }
+}
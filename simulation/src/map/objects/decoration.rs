@@ -0,0 +1,113 @@
+use crate::map::{BuildingID, RoadID};
+use egui_inspect::debug_inspect_impl;
+use geom::{Color, Vec2, Vec3, OBB};
+use serde::{Deserialize, Serialize};
+use slotmapd::new_key_type;
+
+new_key_type! {
+    pub struct DecorationID;
+}
+
+debug_inspect_impl!(DecorationID);
+
+/// Longest text of a sign
+pub const MAX_SIGN_TEXT: usize = 40;
+/// Size of the biggest decal, in meters
+pub const MAX_DECORATION_SIZE: f32 = 50.0;
+/// Depth of the panel of a sign relative to its width
+const SIGN_DEPTH: f32 = 0.15;
+
+/// Pattern painted by a decal
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DecalPattern {
+    /// Parallel stripes, like a crosswalk or hatched road markings
+    Stripes,
+    /// Direction arrow painted on roads
+    Arrow,
+    /// Checkerboard paving of plazas
+    Checker,
+    /// Concentric rings paving of plazas
+    Rings,
+}
+
+impl DecalPattern {
+    pub const ALL: [DecalPattern; 4] = [
+        DecalPattern::Stripes,
+        DecalPattern::Arrow,
+        DecalPattern::Checker,
+        DecalPattern::Rings,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DecalPattern::Stripes => "Stripes",
+            DecalPattern::Arrow => "Arrow",
+            DecalPattern::Checker => "Checker",
+            DecalPattern::Rings => "Rings",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DecorationKind {
+    /// Paint or paving on the ground
+    Decal(DecalPattern),
+    /// Panel with a text, for wayfinding or naming places
+    Sign(String),
+}
+
+/// What the decoration is placed on, it goes away along with it
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecorationSupport {
+    Ground,
+    Road(RoadID),
+    Building(BuildingID),
+}
+
+/// Cosmetic decoration placed by the player, it doesn't affect the simulation
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Decoration {
+    pub kind: DecorationKind,
+    pub pos: Vec3,
+    /// Direction the decal or the sign faces
+    pub dir: Vec2,
+    /// Width of the decal or the sign
+    pub size: f32,
+    pub color: Color,
+    pub on: DecorationSupport,
+}
+
+impl Decoration {
+    /// Footprint of the decoration on the ground
+    pub fn obb(&self) -> OBB {
+        match self.kind {
+            DecorationKind::Decal(_) => OBB::new(self.pos.xy(), self.dir, self.size, self.size),
+            // the panel runs across the direction it faces
+            DecorationKind::Sign(_) => OBB::new(
+                self.pos.xy(),
+                self.dir.perpendicular(),
+                self.size,
+                self.size * SIGN_DEPTH,
+            ),
+        }
+    }
+
+    /// Why the decoration can't be placed, if it can't
+    pub fn check(&self) -> Result<(), &'static str> {
+        if !(0.5..=MAX_DECORATION_SIZE).contains(&self.size) {
+            return Err("Decoration is too small or too big");
+        }
+        if self.dir.try_normalize().is_none() {
+            return Err("Decoration has no direction");
+        }
+        if let DecorationKind::Sign(ref text) = self.kind {
+            if text.trim().is_empty() {
+                return Err("Sign has no text");
+            }
+            if text.chars().count() > MAX_SIGN_TEXT {
+                return Err("Sign text is too long");
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use crate::map::LaneDirection;
+use serde::{Deserialize, Serialize};
+
+/// Placing furniture closer than this to another one replaces it
+pub const FURNITURE_MIN_SPACING: f32 = 5.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FurnitureKind {
+    /// Vehicles in both directions slow down to go over it
+    SpeedBump,
+    /// Vehicles come to a full stop before going on
+    StopSign,
+    /// Vehicles slow down to look for pedestrians and cross traffic
+    YieldSign,
+}
+
+impl FurnitureKind {
+    pub const ALL: [FurnitureKind; 3] = [
+        FurnitureKind::SpeedBump,
+        FurnitureKind::StopSign,
+        FurnitureKind::YieldSign,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FurnitureKind::SpeedBump => "Speed bump",
+            FurnitureKind::StopSign => "Stop sign",
+            FurnitureKind::YieldSign => "Yield sign",
+        }
+    }
+}
+
+/// Traffic control placed in the middle of a road, away from the intersections
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoadFurniture {
+    pub kind: FurnitureKind,
+    /// The signs only apply to the lanes going in that direction, speed bumps apply to both
+    pub side: LaneDirection,
+    /// Distance along the road from its source
+    pub dist: f32,
+}
+
+impl RoadFurniture {
+    pub fn applies_to(&self, side: LaneDirection) -> bool {
+        self.kind == FurnitureKind::SpeedBump || self.side == side
+    }
+}
+
+/// The furniture of a road, sorted by distance along the road
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RoadFurnitures {
+    items: Vec<RoadFurniture>,
+}
+
+impl RoadFurnitures {
+    pub fn items(&self) -> &[RoadFurniture] {
+        &self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Places the furniture, replacing the one too close to it
+    pub fn add(&mut self, f: RoadFurniture) {
+        self.remove_near(f.dist);
+        let i = self.items.partition_point(|x| x.dist < f.dist);
+        self.items.insert(i, f);
+    }
+
+    /// Removes the furniture close to the given distance along the road, returns whether there was one
+    pub fn remove_near(&mut self, dist: f32) -> bool {
+        let n = self.items.len();
+        self.items
+            .retain(|x| (x.dist - dist).abs() >= FURNITURE_MIN_SPACING);
+        self.items.len() != n
+    }
+
+    /// The furniture between `from` and `to`, moved so that `from` is at 0.
+    /// Used to keep the furniture of a road that is split.
+    pub fn cut(&self, from: f32, to: f32) -> RoadFurnitures {
+        RoadFurnitures {
+            items: self
+                .items
+                .iter()
+                .filter(|f| f.dist >= from && f.dist < to)
+                .map(|f| RoadFurniture {
+                    dist: f.dist - from,
+                    ..*f
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn furniture(dist: f32, kind: FurnitureKind) -> RoadFurniture {
+        RoadFurniture {
+            kind,
+            side: LaneDirection::Forward,
+            dist,
+        }
+    }
+
+    #[test]
+    fn place_and_cut_furniture() {
+        let mut f = RoadFurnitures::default();
+        f.add(furniture(50.0, FurnitureKind::StopSign));
+        f.add(furniture(10.0, FurnitureKind::SpeedBump));
+        f.add(furniture(52.0, FurnitureKind::YieldSign));
+        assert_eq!(
+            f.items(),
+            &[
+                furniture(10.0, FurnitureKind::SpeedBump),
+                furniture(52.0, FurnitureKind::YieldSign),
+            ]
+        );
+
+        assert!(f.items()[0].applies_to(LaneDirection::Backward));
+        assert!(!f.items()[1].applies_to(LaneDirection::Backward));
+
+        assert_eq!(
+            f.cut(30.0, 100.0).items(),
+            &[furniture(22.0, FurnitureKind::YieldSign)]
+        );
+
+        assert!(f.remove_near(12.0));
+        assert!(!f.remove_near(30.0));
+        assert_eq!(f.items().len(), 1);
+    }
+}
@@ -1,13 +1,13 @@
 use crate::map::{
-    Intersections, LaneID, LaneKind, Lanes, LightPolicy, Road, RoadID, Roads, SpatialMap,
-    TraverseDirection, Turn, TurnID, TurnPolicy,
+    ApproachSign, Intersections, LaneID, LaneKind, Lanes, LightPolicy, LightProgram, Road, RoadID,
+    Roads, SpatialMap, TraverseDirection, Turn, TurnID, TurnPolicy,
 };
 use geom::{pseudo_angle, Circle};
 use geom::{Vec2, Vec3};
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 use slotmapd::new_key_type;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 new_key_type! {
     pub struct IntersectionID;
@@ -31,6 +31,14 @@ pub struct Intersection {
 
     pub turn_policy: TurnPolicy,
     pub light_policy: LightPolicy,
+    /// Offset of the light cycle in seconds, set to coordinate the lights with the neighbouring
+    /// intersections. Derived from the id when none.
+    pub light_offset: Option<u16>,
+    /// Custom timing of the phases of the lights, the default one when none
+    pub light_program: Option<LightProgram>,
+    /// Signs of the incoming roads when the intersection has no lights, the roads without one
+    /// get the control of the light policy
+    pub approach_signs: BTreeMap<RoadID, ApproachSign>,
 }
 
 impl Intersection {
@@ -42,6 +50,9 @@ impl Intersection {
             roads: Default::default(),
             turn_policy: Default::default(),
             light_policy: Default::default(),
+            light_offset: None,
+            light_program: None,
+            approach_signs: BTreeMap::new(),
         });
         spatial.insert(id, pos.xy());
         id
@@ -74,9 +85,12 @@ impl Intersection {
 
     pub fn remove_road(&mut self, road_id: RoadID) {
         self.roads.retain(|x| *x != road_id);
+        self.approach_signs.remove(&road_id);
     }
 
     pub fn update_turns(&mut self, lanes: &Lanes, roads: &Roads) {
+        let crosswalk_controls = self.light_policy.crosswalk_controls(self, roads);
+
         self.turns = self
             .turn_policy
             .generate_turns(self, lanes, roads)
@@ -88,6 +102,14 @@ impl Intersection {
             .into_iter()
             .map(|mut x| {
                 x.make_points(lanes, self);
+                if x.kind.is_crosswalk() {
+                    if let Some(&control) = lanes
+                        .get(x.id.src)
+                        .and_then(|l| crosswalk_controls.get(&l.parent))
+                    {
+                        x.control = control;
+                    }
+                }
                 x
             })
             .collect();
@@ -198,7 +220,10 @@ impl Intersection {
         self.roads.iter().flat_map(move |&x| {
             let r = roads.get(x)?;
             r.outgoing_lanes_from(id).iter().find(|(_, kind)| {
-                matches!(kind, LaneKind::Driving | LaneKind::Rail | LaneKind::Bus)
+                matches!(
+                    kind,
+                    LaneKind::Driving | LaneKind::Rail | LaneKind::Bus | LaneKind::Tram
+                )
             })?;
             r.other_end(id)
         })
@@ -17,39 +17,67 @@ pub enum LaneKind {
     Parking,
     Walking,
     Rail,
+    /// Canals, only barges sail on them
+    Water,
+    /// Driving lane with rails embedded in the street, trams share it with the other vehicles
+    Tram,
 }
 
 impl LaneKind {
     #[inline]
     pub fn vehicles(self) -> bool {
-        matches!(self, LaneKind::Driving | LaneKind::Biking | LaneKind::Bus)
+        matches!(
+            self,
+            LaneKind::Driving | LaneKind::Biking | LaneKind::Bus | LaneKind::Tram
+        )
     }
 
     #[inline]
     pub fn needs_light(self) -> bool {
-        matches!(self, LaneKind::Driving | LaneKind::Biking | LaneKind::Bus)
+        matches!(
+            self,
+            LaneKind::Driving | LaneKind::Biking | LaneKind::Bus | LaneKind::Tram
+        )
     }
 
     #[inline]
     pub fn needs_arrows(self) -> bool {
         matches!(
             self,
-            LaneKind::Driving | LaneKind::Biking | LaneKind::Bus | LaneKind::Rail
+            LaneKind::Driving | LaneKind::Biking | LaneKind::Bus | LaneKind::Rail | LaneKind::Tram
         )
     }
 
+    /// Lanes every vehicle can drive on, the trams share theirs with the others
+    #[inline]
+    pub fn is_driving(self) -> bool {
+        matches!(self, LaneKind::Driving | LaneKind::Tram)
+    }
+
+    /// Lanes turning green before the others at traffic lights so that transit gets ahead
+    #[inline]
+    pub fn transit_priority(self) -> bool {
+        matches!(self, LaneKind::Bus | LaneKind::Tram)
+    }
+
     #[inline]
     pub fn is_rail(self) -> bool {
         matches!(self, LaneKind::Rail)
     }
 
+    #[inline]
+    pub fn is_water(self) -> bool {
+        matches!(self, LaneKind::Water)
+    }
+
     #[inline]
     pub const fn width(self) -> f32 {
         match self {
-            LaneKind::Driving | LaneKind::Biking | LaneKind::Bus => 4.0,
+            LaneKind::Driving | LaneKind::Biking | LaneKind::Bus | LaneKind::Tram => 4.0,
             LaneKind::Parking => 2.5,
             LaneKind::Walking => 3.0,
             LaneKind::Rail => 5.3,
+            LaneKind::Water => 12.0,
         }
     }
 }
@@ -83,6 +111,9 @@ pub struct Lane {
 pub struct LanePattern {
     pub lanes_forward: Vec<(LaneKind, f32)>,
     pub lanes_backward: Vec<(LaneKind, f32)>,
+    /// Whether the rails have overhead lines, needed by electric locomotives
+    #[serde(default)]
+    pub electrified: bool,
 }
 
 impl LanePattern {
@@ -112,6 +143,12 @@ pub struct LanePatternBuilder {
     pub parking: bool,
     pub one_way: bool,
     pub rail: bool,
+    pub electrified: bool,
+    pub canal: bool,
+    /// The outermost driving lane of each direction is reserved to buses
+    pub bus_lanes: bool,
+    /// The innermost driving lane of each direction has tram tracks, shared with the other vehicles
+    pub tram: bool,
 }
 impl Eq for LanePatternBuilder {}
 
@@ -130,6 +167,10 @@ impl LanePatternBuilder {
             parking: true,
             one_way: false,
             rail: false,
+            electrified: false,
+            canal: false,
+            bus_lanes: false,
+            tram: false,
         }
     }
 
@@ -163,7 +204,31 @@ impl LanePatternBuilder {
         self
     }
 
+    pub const fn electrified(mut self, electrified: bool) -> Self {
+        self.electrified = electrified;
+        self
+    }
+
+    pub const fn canal(mut self, canal: bool) -> Self {
+        self.canal = canal;
+        self
+    }
+
+    pub const fn bus_lanes(mut self, bus_lanes: bool) -> Self {
+        self.bus_lanes = bus_lanes;
+        self
+    }
+
+    pub const fn tram(mut self, tram: bool) -> Self {
+        self.tram = tram;
+        self
+    }
+
     pub fn width(self) -> f32 {
+        if self.canal {
+            let wayf = if self.one_way { 1.0 } else { 2.0 };
+            return self.n_lanes as f32 * LaneKind::Water.width() * wayf;
+        }
         if self.rail {
             let wayf = if self.one_way { 1.0 } else { 2.0 };
             return self.n_lanes as f32 * LaneKind::Rail.width() * wayf;
@@ -195,6 +260,23 @@ impl LanePatternBuilder {
 
         let mut forward: Vec<_> = (0..self.n_lanes).map(|_| LaneKind::Driving).collect();
 
+        // keep at least one lane for the other vehicles
+        if self.bus_lanes && self.n_lanes >= 2 {
+            for lanes in [&mut backward, &mut forward] {
+                if let Some(outer) = lanes.last_mut() {
+                    *outer = LaneKind::Bus;
+                }
+            }
+        }
+
+        if self.tram {
+            for lanes in [&mut backward, &mut forward] {
+                if let Some(inner) = lanes.first_mut() {
+                    *inner = LaneKind::Tram;
+                }
+            }
+        }
+
         if self.parking {
             if !self.one_way {
                 backward.push(LaneKind::Parking);
@@ -216,12 +298,22 @@ impl LanePatternBuilder {
             forward = (0..self.n_lanes).map(|_| LaneKind::Rail).collect();
         }
 
+        if self.canal {
+            backward = if self.one_way {
+                vec![]
+            } else {
+                (0..self.n_lanes).map(|_| LaneKind::Water).collect()
+            };
+            forward = (0..self.n_lanes).map(|_| LaneKind::Water).collect();
+        }
+
         LanePattern {
             lanes_backward: backward
                 .into_iter()
                 .map(|x| (x, self.speed_limit))
                 .collect(),
             lanes_forward: forward.into_iter().map(|x| (x, self.speed_limit)).collect(),
+            electrified: self.rail && self.electrified,
         }
     }
 }
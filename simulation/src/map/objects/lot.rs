@@ -1,4 +1,4 @@
-use crate::map::{Map, ProjectFilter, ProjectKind, RoadID, UpdateType};
+use crate::map::{Map, ProjectFilter, ProjectKind, Road, RoadID, UpdateType};
 use geom::Vec2;
 use geom::OBB;
 use geom::{Circle, Vec3};
@@ -10,10 +10,11 @@ new_key_type! {
     pub struct LotID;
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LotKind {
     Unassigned,
     Residential,
+    Commercial,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -85,6 +86,10 @@ impl Lot {
 
             let mut lots = vec![];
             while let Some((pos, dir)) = along.next(d) {
+                if Road::is_underground(pos, &map.environment) {
+                    d += 2.0;
+                    continue;
+                }
                 let axis = side * dir.perp_up();
                 let l = Lot::try_make(map, road, pos + axis * (w + 1.0), axis.xy(), size);
                 if let Some(id) = l {
@@ -1,4 +1,4 @@
-use crate::map::{Lane, LaneID, LaneKind, CROSSWALK_WIDTH};
+use crate::map::{Building, BuildingID, CurbKind, Lane, LaneID, LaneKind, Road, CROSSWALK_WIDTH};
 use flat_spatial::Grid;
 use geom::{Transform, Vec2, Vec3};
 use ordered_float::OrderedFloat;
@@ -11,10 +11,24 @@ new_key_type! {
 
 pub const PARKING_SPOT_LENGTH: f32 = 6.0;
 
+/// Width of the spots of the parking lots
+const LOT_SPOT_WIDTH: f32 = 2.5;
+/// Depth of the spots of the parking lots
+const LOT_SPOT_DEPTH: f32 = 5.0;
+/// Width of the aisles between the rows of spots of the parking lots
+const LOT_AISLE_WIDTH: f32 = 6.0;
+/// Space left at both ends of the rows to drive around them
+const LOT_ROW_MARGIN: f32 = 3.0;
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct ParkingSpot {
+    /// The parking lane of the spot, null for the spots of the parking lots
     pub parent: LaneID,
     pub trans: Transform,
+    /// Either [`CurbKind::Parking`] or [`CurbKind::LoadingZone`]
+    pub curb: CurbKind,
+    /// The parking lot the spot is in, `None` for the spots along the streets
+    pub lot: Option<BuildingID>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -22,6 +36,7 @@ pub struct ParkingSpots {
     pub(crate) spots: SlotMap<ParkingSpotID, ParkingSpot>,
     pub(crate) lane_spots: SecondaryMap<LaneID, Vec<ParkingSpotID>>,
     pub(crate) reuse_spot: Grid<ParkingSpotID, Vec2>,
+    pub(crate) lot_spots: SecondaryMap<BuildingID, Vec<ParkingSpotID>>,
 }
 
 impl Default for ParkingSpots {
@@ -30,6 +45,7 @@ impl Default for ParkingSpots {
             spots: Default::default(),
             lane_spots: Default::default(),
             reuse_spot: Grid::new(10),
+            lot_spots: Default::default(),
         }
     }
 }
@@ -77,7 +93,8 @@ impl ParkingSpots {
         }
     }
 
-    pub fn generate_spots(&mut self, lane: &Lane) {
+    /// Generates the spots along the parking lane, on the curbs designated for parking
+    pub fn generate_spots(&mut self, lane: &Lane, road: &Road) {
         debug_assert!(matches!(lane.kind, LaneKind::Parking));
         if self.lane_spots.contains_key(lane.id) {
             self.remove_to_reuse(lane.id);
@@ -93,12 +110,17 @@ impl ParkingSpots {
         let step = l / n_spots as f32;
 
         let parent = lane.id;
+        let side = road.side_of(lane);
         let spots = &mut self.spots;
         let reuse = &mut self.reuse_spot;
         let spots = lane
             .points
             .points_dirs_along((0..n_spots).map(|x| (x as f32 + 0.5) * step + gap))
-            .map(move |(pos, dir)| {
+            .filter_map(|(pos, dir)| {
+                let curb = road.curb_at(side, pos);
+                curb.has_spots().then_some((pos, dir, curb))
+            })
+            .map(move |(pos, dir, curb)| {
                 let mut iter = reuse.query_around(pos.xy(), 3.0);
                 if let Some((h, _)) = iter.next() {
                     if let Some((_, spot_id)) = reuse.get(h) {
@@ -110,6 +132,8 @@ impl ParkingSpots {
                             *p = ParkingSpot {
                                 parent,
                                 trans: Transform::new_dir(pos, dir),
+                                curb,
+                                lot: None,
                             };
                             return spot_id;
                         } else {
@@ -121,6 +145,8 @@ impl ParkingSpots {
                 spots.insert(ParkingSpot {
                     parent,
                     trans: Transform::new_dir(pos, dir),
+                    curb,
+                    lot: None,
                 })
             })
             .collect();
@@ -128,9 +154,72 @@ impl ParkingSpots {
         self.lane_spots.insert(lane.id, spots);
     }
 
+    /// Generates the spots of the parking lot, in rows parallel to its front with an aisle
+    /// between every two rows
+    pub fn generate_lot_spots(&mut self, lot: &Building) {
+        self.remove_lot_spots(lot.id);
+
+        let [front, side] = lot.obb.axis();
+        let length = front.mag();
+        let depth = side.mag();
+        let (Some(along), Some(back)) = (front.try_normalize(), side.try_normalize()) else {
+            return;
+        };
+        let origin = lot.obb.corners[0];
+
+        // (distance of the row from the front, whether its aisle is behind it)
+        let mut rows = vec![];
+        let mut y = 0.0;
+        while y + LOT_SPOT_DEPTH + LOT_AISLE_WIDTH <= depth {
+            rows.push((y + LOT_SPOT_DEPTH * 0.5, true));
+            y += LOT_SPOT_DEPTH + LOT_AISLE_WIDTH;
+            if y + LOT_SPOT_DEPTH <= depth {
+                rows.push((y + LOT_SPOT_DEPTH * 0.5, false));
+                y += LOT_SPOT_DEPTH;
+            }
+        }
+
+        let n_per_row = ((length - LOT_ROW_MARGIN * 2.0) / LOT_SPOT_WIDTH).max(0.0) as usize;
+        let mut ids = Vec::with_capacity(rows.len() * n_per_row);
+        for (y, aisle_behind) in rows {
+            // cars drive into the spots from the aisle
+            let dir = if aisle_behind { -back } else { back };
+            for i in 0..n_per_row {
+                let x = LOT_ROW_MARGIN + (i as f32 + 0.5) * LOT_SPOT_WIDTH;
+                let pos = (origin + along * x + back * y).z(lot.height + 0.1);
+                ids.push(self.spots.insert(ParkingSpot {
+                    parent: LaneID::default(),
+                    trans: Transform::new_dir(pos, dir.z0()),
+                    curb: CurbKind::Parking,
+                    lot: Some(lot.id),
+                }));
+            }
+        }
+        self.lot_spots.insert(lot.id, ids);
+    }
+
+    pub fn remove_lot_spots(&mut self, lot: BuildingID) {
+        if let Some(spots) = self.lot_spots.remove(lot) {
+            for spot in spots {
+                self.spots.remove(spot);
+            }
+        }
+    }
+
+    /// The spots of the parking lot, closest to its front first
+    pub fn lot_spots(&self, lot: BuildingID) -> &[ParkingSpotID] {
+        self.lot_spots.get(lot).map_or(&[], |x| x)
+    }
+
+    /// Every parking lot with its spots
+    pub fn lots(&self) -> impl Iterator<Item = (BuildingID, &[ParkingSpotID])> + '_ {
+        self.lot_spots.iter().map(|(id, spots)| (id, &**spots))
+    }
+
     pub fn clear(&mut self) {
         self.spots.clear();
         self.lane_spots.clear();
+        self.lot_spots.clear();
         for _ in self.reuse_spot.clear() {}
     }
 
@@ -1,9 +1,10 @@
 use crate::map::{
-    Environment, Intersection, IntersectionID, Lane, LaneDirection, LaneID, LaneKind, LanePattern,
-    Lanes, ParkingSpots, Roads, SpatialMap,
+    CurbKind, Curbs, Environment, Intersection, IntersectionID, Lane, LaneDirection, LaneID,
+    LaneKind, LanePattern, Lanes, ParkingSpots, RoadFurnitures, Roads, SpatialMap,
 };
+use crate::transportation::waterway::n_locks;
 use geom::Spline3;
-use geom::{BoldLine, PolyLine3};
+use geom::{BoldLine, PolyLine, PolyLine3};
 use geom::{Vec2, Vec3};
 use serde::{Deserialize, Serialize};
 use slotmapd::new_key_type;
@@ -25,6 +26,16 @@ impl RoadSegmentKind {
             (to - elbow) * std::f32::consts::FRAC_1_SQRT_2,
         ))
     }
+
+    /// The elbow the segment was made from, see [`RoadSegmentKind::from_elbow`]
+    pub fn elbow(&self, from: Vec2) -> Option<Vec2> {
+        match *self {
+            RoadSegmentKind::Straight => None,
+            RoadSegmentKind::Curved((from_derivative, _)) => {
+                Some(from + from_derivative * std::f32::consts::SQRT_2)
+            }
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -46,6 +57,15 @@ pub struct Road {
 
     lanes_forward: Vec<(LaneID, LaneKind)>,
     lanes_backward: Vec<(LaneID, LaneKind)>,
+
+    /// Rails with overhead lines, see [`LanePattern::electrified`]
+    pub electrified: bool,
+
+    /// Bus stops, loading zones and no parking zones along the road
+    pub curbs: Curbs,
+
+    /// Speed bumps, stop and yield signs along the road
+    pub furniture: RoadFurnitures,
 }
 #[derive(Copy, Clone)]
 pub struct LanePair {
@@ -60,6 +80,12 @@ pub struct PylonPosition {
 }
 
 impl Road {
+    /// Height difference under which two crossing roads overlap, roads further apart vertically
+    /// cross on a bridge
+    pub const CLEARANCE: f32 = 5.0;
+    /// Depth under the terrain from which a road is in a tunnel
+    pub const TUNNEL_DEPTH: f32 = 1.0;
+
     /// Builds the road and its associated lanes
     pub fn make(
         src: &Intersection,
@@ -89,6 +115,9 @@ impl Road {
             width,
             lanes_forward: vec![],
             lanes_backward: vec![],
+            electrified: lane_pattern.electrified,
+            curbs: Curbs::default(),
+            furniture: RoadFurnitures::default(),
             interfaced_points: PolyLine3::new(vec![points.first()]),
             points,
         });
@@ -162,6 +191,46 @@ impl Road {
             .map(|&(id, _)| id)
     }
 
+    /// The side of the road the lane is on
+    pub fn side_of(&self, lane: &Lane) -> LaneDirection {
+        if lane.src == self.src {
+            LaneDirection::Forward
+        } else {
+            LaneDirection::Backward
+        }
+    }
+
+    /// The lanes going in the direction of the side, from the middle of the road to the curb
+    pub fn lanes_on(&self, side: LaneDirection) -> &[(LaneID, LaneKind)] {
+        match side {
+            LaneDirection::Forward => &self.lanes_forward,
+            LaneDirection::Backward => &self.lanes_backward,
+        }
+    }
+
+    /// The lane vehicles stop on along the curb of the given side
+    pub fn curb_lane(&self, side: LaneDirection) -> Option<LaneID> {
+        let lanes = self.lanes_on(side);
+        lanes
+            .iter()
+            .find(|(_, kind)| matches!(kind, LaneKind::Parking))
+            .or_else(|| {
+                lanes
+                    .iter()
+                    .rfind(|(_, kind)| kind.is_driving() || *kind == LaneKind::Bus)
+            })
+            .map(|&(id, _)| id)
+    }
+
+    /// The curb designation at the projection of `pos` on the given side
+    pub fn curb_at(&self, side: LaneDirection, pos: Vec3) -> CurbKind {
+        if self.curbs.is_empty() {
+            return CurbKind::Parking;
+        }
+        let dist = self.points.length_at_proj(self.points.project(pos));
+        self.curbs.kind_at(side, dist)
+    }
+
     fn mk_pair(
         &self,
         from: IntersectionID,
@@ -189,7 +258,7 @@ impl Road {
             let l = unwrap_contlog!(lanes.get_mut(id), "lane in road does not exist anymore");
             l.gen_pos(self);
             if matches!(l.kind, LaneKind::Parking) {
-                parking.generate_spots(l);
+                parking.generate_spots(l, self);
             }
         }
         parking.clean_reuse();
@@ -233,9 +302,56 @@ impl Road {
                     ))
                 })
                 .collect(),
+            electrified: self.electrified,
         }
     }
 
+    pub fn is_rail(&self) -> bool {
+        self.lanes_iter().any(|(_, kind)| kind.is_rail())
+    }
+
+    pub fn is_canal(&self) -> bool {
+        self.lanes_iter().any(|(_, kind)| kind.is_water())
+    }
+
+    /// Number of locks on the canal, zero for other roads
+    pub fn locks(&self) -> u32 {
+        if !self.is_canal() {
+            return 0;
+        }
+        n_locks(self.points.iter().copied())
+    }
+
+    /// Height of the road deck at the closest point of the road from `p`
+    pub fn height_at(&self, p: Vec2) -> f32 {
+        self.deck_at(&self.points.flatten(), p).1
+    }
+
+    /// Whether a road of width `width` following `points` would overlap this road, crossing
+    /// it at a height where neither passes over the other
+    pub fn overlaps(&self, points: &PolyLine3, width: f32) -> bool {
+        let flat = self.points.flatten();
+        let reach = (self.width + width) * 0.5;
+        points.equipoints_dir(1.0, true).any(|(p, _)| {
+            let (proj, h) = self.deck_at(&flat, p.xy());
+            proj.is_close(p.xy(), reach) && (h - p.z).abs() < Self::CLEARANCE
+        })
+    }
+
+    /// Projection of `p` on the flattened points of the road, with the height of the deck there
+    fn deck_at(&self, flat: &PolyLine, p: Vec2) -> (Vec2, f32) {
+        let (proj, i) = flat.project_segment(p);
+        let (Some(a), Some(b)) = (self.points.get(i.saturating_sub(1)), self.points.get(i)) else {
+            return (proj, self.points.first().z);
+        };
+        let len = a.xy().distance(b.xy());
+        if len < 0.001 {
+            return (proj, a.z);
+        }
+        let t = a.xy().distance(proj) / len;
+        (proj, a.z + (b.z - a.z) * t)
+    }
+
     pub fn pylons_positions<'a>(
         interfaced_points: &'a PolyLine3,
         env: &'a Environment,
@@ -244,7 +360,8 @@ impl Road {
             .equipoints_dir(80.0, true)
             .filter_map(move |(pos, dir)| {
                 let h = env.height(pos.xy())?;
-                if (h - pos.z).abs() <= 2.0 {
+                // roads close to the ground or in a tunnel need no pylon
+                if pos.z - h <= 2.0 {
                     return None;
                 }
                 Some(PylonPosition {
@@ -255,6 +372,39 @@ impl Road {
             })
     }
 
+    /// Whether the point of a road is in a tunnel
+    pub fn is_underground(p: Vec3, env: &Environment) -> bool {
+        env.height(p.xy())
+            .map_or(false, |h| p.z < h - Self::TUNNEL_DEPTH)
+    }
+
+    /// Parts of the points that are not in a tunnel, as distances along the points.
+    /// The ends of the parts that are not the ends of the points are tunnel portals.
+    pub fn open_sections(points: &PolyLine3, env: &Environment) -> Vec<(f32, f32)> {
+        const STEP: f32 = 1.0;
+        let len = points.length();
+        let n_steps = (len / STEP).ceil() as usize;
+
+        let mut sections = vec![];
+        let mut start = None;
+        for i in 0..=n_steps {
+            let d = (i as f32 * STEP).min(len);
+            let underground = Self::is_underground(points.point_along(d), env);
+            match (underground, start) {
+                (false, None) => start = Some(d),
+                (true, Some(s)) => {
+                    sections.push((s, d));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(s) = start {
+            sections.push((s, len));
+        }
+        sections
+    }
+
     pub fn points(&self) -> &PolyLine3 {
         &self.points
     }
@@ -457,3 +607,44 @@ impl Road {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Road;
+    use crate::tests::TestCtx;
+    use geom::{vec2, vec3, PolyLine3};
+
+    #[test]
+    fn roads_cross_over_lower_roads() {
+        let test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.3), vec3(100.0, 0.0, 0.3)]);
+
+        let map = test.g.map();
+        let road = map.roads().values().next().unwrap();
+        assert!((road.height_at(vec3(50.0, 10.0, 0.0).xy()) - 0.3).abs() < 0.01);
+
+        let crossing = |z| PolyLine3::new(vec![vec3(50.0, -50.0, z), vec3(50.0, 50.0, z)]);
+        assert!(road.overlaps(&crossing(0.3), 8.0));
+        assert!(!road.overlaps(&crossing(10.3), 8.0));
+    }
+
+    #[test]
+    fn tunnels_are_not_open() {
+        let test = TestCtx::new();
+        let map = test.g.map();
+        let env = &map.environment;
+
+        let a = vec2(0.0, 0.0);
+        let b = vec2(100.0, 0.0);
+        let c = vec2(200.0, 0.0);
+        let h = |p| env.height(p).unwrap();
+        let points = PolyLine3::new(vec![a.z(h(a) + 0.3), b.z(h(b) - 20.0), c.z(h(c) + 0.3)]);
+
+        let sections = Road::open_sections(&points, env);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].0, 0.0);
+        assert!(sections[0].1 < points.length() * 0.5);
+        assert!(sections[1].0 > points.length() * 0.5);
+        assert_eq!(sections[1].1, points.length());
+    }
+}
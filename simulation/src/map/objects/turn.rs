@@ -1,4 +1,4 @@
-use crate::map::{Intersection, IntersectionID, LaneID, Lanes};
+use crate::map::{Intersection, IntersectionID, LaneID, Lanes, TrafficControl};
 use geom::{Degrees, PolyLine3, Radians, Vec2};
 use geom::{Spline, Vec3};
 use serde::{Deserialize, Serialize};
@@ -33,6 +33,15 @@ pub enum TurnKind {
     Rail,
 }
 
+/// How pedestrians get over the height difference of a walking turn.
+/// People with reduced mobility (strollers, wheelchairs) avoid the stairs.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Accessibility {
+    #[default]
+    Ramp,
+    Stairs,
+}
+
 impl TurnKind {
     pub fn is_crosswalk(self) -> bool {
         matches!(self, TurnKind::Crosswalk)
@@ -44,6 +53,9 @@ pub struct Turn {
     pub id: TurnID,
     pub points: PolyLine3,
     pub kind: TurnKind,
+    /// Walk signal of crosswalks at lights, always green otherwise
+    pub control: TrafficControl,
+    pub accessibility: Accessibility,
 }
 
 impl Borrow<TurnID> for Turn {
@@ -76,6 +88,8 @@ const TURN_ANG_ADD: f32 = 0.29;
 const TURN_ANG_MUL: f32 = 0.36;
 const TURN_MUL: f32 = 0.46;
 const N_SPLINE: usize = 6;
+/// Steepest slope of a walking turn that is still built as a ramp (1:12)
+const MAX_RAMP_SLOPE: f32 = 1.0 / 12.0;
 
 impl Turn {
     pub fn new(id: TurnID, kind: TurnKind) -> Self {
@@ -83,6 +97,8 @@ impl Turn {
             id,
             points: PolyLine3::new(vec![Vec3::ZERO; N_SPLINE + 2]),
             kind,
+            control: TrafficControl::Always,
+            accessibility: Accessibility::Ramp,
         }
     }
 
@@ -98,6 +114,7 @@ impl Turn {
         let pos_dst = dst_lane.get_inter_node_pos(self.id.parent);
 
         self.points.clear_push(pos_src);
+        self.accessibility = Self::walking_accessibility(self.kind, pos_src, pos_dst);
 
         if self.kind.is_crosswalk() {
             self.points.push(pos_dst);
@@ -161,6 +178,19 @@ impl Turn {
         );
     }
 
+    fn walking_accessibility(kind: TurnKind, pos_src: Vec3, pos_dst: Vec3) -> Accessibility {
+        if !matches!(kind, TurnKind::Crosswalk | TurnKind::WalkingCorner) {
+            return Accessibility::Ramp;
+        }
+        let height = (pos_dst.z - pos_src.z).abs();
+        let length = pos_src.xy().distance(pos_dst.xy()).max(1.0);
+        if height / length > MAX_RAMP_SLOPE {
+            Accessibility::Stairs
+        } else {
+            Accessibility::Ramp
+        }
+    }
+
     pub fn gen_roundabout(
         pos_src: Vec3,
         pos_dst: Vec3,
@@ -1,12 +1,17 @@
 use crate::map::{
-    LaneID, LaneKind, LanePatternBuilder, Map, Traversable, TraverseDirection, TraverseKind, TurnID,
+    Accessibility, LaneID, LaneKind, LanePatternBuilder, Map, Traversable, TraverseDirection,
+    TraverseKind, TurnID,
 };
+use crate::map_dynamic::{District, TRUCK_BAN_PENALTY};
 use crate::utils::time::Tick;
-use common::hash_u64;
+use common::{hash_u64, FastMap, FastSet};
 use geom::{PolyLine3, Vec3};
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 use slotmapd::Key;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::hash::Hash;
 
 pub trait Pathfinder {
     fn path(
@@ -16,16 +21,124 @@ pub trait Pathfinder {
         start: Traversable,
         end: LaneID,
     ) -> Option<Vec<Traversable>>;
+    /// Same as `path` but records the explored nodes, much slower so only used for debugging
+    fn path_debug(&self, map: &Map, tick: Tick, start: Traversable, end: LaneID) -> PathDebug;
     fn nearest_lane(&self, map: &Map, pos: Vec3) -> Option<LaneID>;
     fn local_route(&self, map: &Map, lane: LaneID, start: Vec3, end: Vec3) -> Option<PolyLine3>;
     fn authorized_lane(&self, kind: LaneKind) -> bool;
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PathKind {
     Pedestrian,
     Vehicle,
     Rail,
+    /// Same as vehicle but avoids the districts banning heavy vehicles
+    Truck,
+    /// Same as pedestrian but avoids the stairs, for people with reduced mobility
+    StepFree,
+    /// Same as rail but only uses electrified tracks, for electric locomotives
+    ElectricRail,
+    /// Same as vehicle but also uses the bus lanes
+    Bus,
+    /// Only uses the lanes with tram tracks, for trams
+    Tram,
+}
+
+/// The parameters of a pathfinding query, kept so that it can be replayed for debugging
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathQuery {
+    pub kind: PathKind,
+    pub tick: Tick,
+    pub start: Traversable,
+    pub end: LaneID,
+}
+
+impl PathQuery {
+    pub fn debug(&self, map: &Map) -> PathDebug {
+        self.kind.path_debug(map, self.tick, self.start, self.end)
+    }
+}
+
+/// What happened during a pathfinding query, costs are the ones used by the search
+#[derive(Default, Clone, Debug)]
+pub struct PathDebug {
+    /// Nodes that were expanded with their cost from the start
+    pub explored: Vec<(Traversable, f32)>,
+    /// Nodes that were discovered but never expanded with their best known cost
+    pub frontier: Vec<(Traversable, f32)>,
+    /// The resulting path with the cost from the start at each step, empty if none was found
+    pub path: Vec<(Traversable, f32)>,
+}
+
+struct SearchRecorder<N> {
+    best: FastMap<N, f32>,
+    explored: Vec<(N, f32)>,
+}
+
+impl<N: Copy + Eq + Hash> SearchRecorder<N> {
+    fn new(start: N) -> Self {
+        let mut best = FastMap::default();
+        best.insert(start, 0.0);
+        Self {
+            best,
+            explored: vec![],
+        }
+    }
+
+    /// Called when the search expands a node, returns its cost from the start
+    fn expand(&mut self, node: N) -> f32 {
+        let g = self.best.get(&node).copied().unwrap_or(0.0);
+        self.explored.push((node, g));
+        g
+    }
+
+    fn discover(&mut self, node: N, cost: f32) {
+        let v = self.best.entry(node).or_insert(f32::INFINITY);
+        *v = v.min(cost);
+    }
+
+    fn finish(
+        self,
+        path: Option<Vec<Traversable>>,
+        node_of: impl Fn(&Traversable) -> N,
+        travers_of: impl Fn(N) -> Traversable,
+    ) -> PathDebug {
+        let explored: FastSet<N> = self.explored.iter().map(|&(n, _)| n).collect();
+        let frontier = self
+            .best
+            .iter()
+            .filter(|(n, _)| !explored.contains(n))
+            .map(|(&n, &g)| (travers_of(n), g))
+            .collect();
+        let path = path
+            .unwrap_or_default()
+            .into_iter()
+            .map(|t| (t, self.best.get(&node_of(&t)).copied().unwrap_or(0.0)))
+            .collect();
+        PathDebug {
+            explored: self
+                .explored
+                .iter()
+                .map(|&(n, g)| (travers_of(n), g))
+                .collect(),
+            frontier,
+            path,
+        }
+    }
+}
+
+/// Records the expansion of `node` and returns a closure recording its successors
+fn record<N: Copy + Eq + Hash>(
+    rec: Option<&RefCell<SearchRecorder<N>>>,
+    node: N,
+) -> impl Fn(&(N, OrderedFloat<f32>)) + '_ {
+    let g = rec.map(|r| r.borrow_mut().expand(node)).unwrap_or(0.0);
+    move |&(child, cost)| {
+        if let Some(r) = rec {
+            r.borrow_mut().discover(child, g + cost.0);
+        }
+    }
 }
 
 impl Pathfinder for PathKind {
@@ -37,46 +150,86 @@ impl Pathfinder for PathKind {
         end: LaneID,
     ) -> Option<Vec<Traversable>> {
         match self {
-            PathKind::Pedestrian => PedestrianPath.path(map, tick, start, end),
-            PathKind::Vehicle => CarPath.path(map, tick, start, end),
-            PathKind::Rail => RailPath.path(map, tick, start, end),
+            PathKind::Pedestrian => PedestrianPath::WALK.path(map, tick, start, end),
+            PathKind::StepFree => PedestrianPath::STEP_FREE.path(map, tick, start, end),
+            PathKind::Vehicle => CarPath::CAR.path(map, tick, start, end),
+            PathKind::Bus => CarPath::BUS.path(map, tick, start, end),
+            PathKind::Tram => CarPath::TRAM.path(map, tick, start, end),
+            PathKind::Rail => RailPath::ANY.path(map, tick, start, end),
+            PathKind::ElectricRail => RailPath::ELECTRIFIED.path(map, tick, start, end),
+            PathKind::Truck => TruckPath.path(map, tick, start, end),
+        }
+    }
+
+    fn path_debug(&self, map: &Map, tick: Tick, start: Traversable, end: LaneID) -> PathDebug {
+        match self {
+            PathKind::Pedestrian => PedestrianPath::WALK.path_debug(map, tick, start, end),
+            PathKind::StepFree => PedestrianPath::STEP_FREE.path_debug(map, tick, start, end),
+            PathKind::Vehicle => CarPath::CAR.path_debug(map, tick, start, end),
+            PathKind::Bus => CarPath::BUS.path_debug(map, tick, start, end),
+            PathKind::Tram => CarPath::TRAM.path_debug(map, tick, start, end),
+            PathKind::Rail => RailPath::ANY.path_debug(map, tick, start, end),
+            PathKind::ElectricRail => RailPath::ELECTRIFIED.path_debug(map, tick, start, end),
+            PathKind::Truck => TruckPath.path_debug(map, tick, start, end),
         }
     }
 
     fn nearest_lane(&self, map: &Map, pos: Vec3) -> Option<LaneID> {
         match self {
-            PathKind::Pedestrian => PedestrianPath.nearest_lane(map, pos),
-            PathKind::Vehicle => CarPath.nearest_lane(map, pos),
-            PathKind::Rail => RailPath.nearest_lane(map, pos),
+            PathKind::Pedestrian => PedestrianPath::WALK.nearest_lane(map, pos),
+            PathKind::StepFree => PedestrianPath::STEP_FREE.nearest_lane(map, pos),
+            PathKind::Vehicle => CarPath::CAR.nearest_lane(map, pos),
+            PathKind::Bus => CarPath::BUS.nearest_lane(map, pos),
+            PathKind::Tram => CarPath::TRAM.nearest_lane(map, pos),
+            PathKind::Rail => RailPath::ANY.nearest_lane(map, pos),
+            PathKind::ElectricRail => RailPath::ELECTRIFIED.nearest_lane(map, pos),
+            PathKind::Truck => TruckPath.nearest_lane(map, pos),
         }
     }
 
     fn local_route(&self, map: &Map, lane: LaneID, start: Vec3, end: Vec3) -> Option<PolyLine3> {
         match self {
-            PathKind::Pedestrian => PedestrianPath.local_route(map, lane, start, end),
-            PathKind::Vehicle => CarPath.local_route(map, lane, start, end),
-            PathKind::Rail => RailPath.local_route(map, lane, start, end),
+            PathKind::Pedestrian => PedestrianPath::WALK.local_route(map, lane, start, end),
+            PathKind::StepFree => PedestrianPath::STEP_FREE.local_route(map, lane, start, end),
+            PathKind::Vehicle => CarPath::CAR.local_route(map, lane, start, end),
+            PathKind::Bus => CarPath::BUS.local_route(map, lane, start, end),
+            PathKind::Tram => CarPath::TRAM.local_route(map, lane, start, end),
+            PathKind::Rail => RailPath::ANY.local_route(map, lane, start, end),
+            PathKind::ElectricRail => RailPath::ELECTRIFIED.local_route(map, lane, start, end),
+            PathKind::Truck => TruckPath.local_route(map, lane, start, end),
         }
     }
 
     fn authorized_lane(&self, kind: LaneKind) -> bool {
         match self {
-            PathKind::Pedestrian => PedestrianPath.authorized_lane(kind),
-            PathKind::Vehicle => CarPath.authorized_lane(kind),
-            PathKind::Rail => RailPath.authorized_lane(kind),
+            PathKind::Pedestrian => PedestrianPath::WALK.authorized_lane(kind),
+            PathKind::StepFree => PedestrianPath::STEP_FREE.authorized_lane(kind),
+            PathKind::Vehicle => CarPath::CAR.authorized_lane(kind),
+            PathKind::Bus => CarPath::BUS.authorized_lane(kind),
+            PathKind::Tram => CarPath::TRAM.authorized_lane(kind),
+            PathKind::Rail => RailPath::ANY.authorized_lane(kind),
+            PathKind::ElectricRail => RailPath::ELECTRIFIED.authorized_lane(kind),
+            PathKind::Truck => TruckPath.authorized_lane(kind),
         }
     }
 }
 
-struct PedestrianPath;
+struct PedestrianPath {
+    /// Cost of taking stairs, in meters
+    stairs_cost: f32,
+}
 
-impl Pathfinder for PedestrianPath {
-    fn path(
+impl PedestrianPath {
+    const WALK: Self = Self { stairs_cost: 0.001 };
+    /// Stairs are not forbidden as the only way might have stairs, but a long detour is preferred
+    const STEP_FREE: Self = Self { stairs_cost: 500.0 };
+
+    fn search(
         &self,
         map: &Map,
-        _tick: Tick,
         start: Traversable,
         end: LaneID,
+        rec: Option<&RefCell<SearchRecorder<Traversable>>>,
     ) -> Option<Vec<Traversable>> {
         let inters = &map.intersections;
         let lanes = &map.lanes;
@@ -97,6 +250,7 @@ impl Pathfinder for PedestrianPath {
         };
 
         let successors = |t: &Traversable| {
+            let recorded = record(rec, *t);
             let inter = t
                 .destination_intersection(lanes)
                 .and_then(|x| inters.get(x));
@@ -116,14 +270,19 @@ impl Pathfinder for PedestrianPath {
             inter
                 .into_iter()
                 .flat_map(move |inter| {
-                    inter.turns_from(lane_from_id).map(|(x, dir)| {
+                    inter.turns_from(lane_from_id).map(move |(x, dir)| {
+                        let cost = match inter.find_turn(x) {
+                            Some(t) if t.accessibility == Accessibility::Stairs => self.stairs_cost,
+                            _ => 0.001,
+                        };
                         (
                             Traversable::new(TraverseKind::Turn(x), dir),
-                            OrderedFloat(0.001),
+                            OrderedFloat(cost),
                         )
                     })
                 })
                 .chain(lane_travers)
+                .inspect(recorded)
         };
 
         let has_arrived = |p: &Traversable| match p.kind {
@@ -134,6 +293,24 @@ impl Pathfinder for PedestrianPath {
         pathfinding::directed::astar::astar(&start, successors, heuristic, has_arrived)
             .map(|(v, _)| v)
     }
+}
+
+impl Pathfinder for PedestrianPath {
+    fn path(
+        &self,
+        map: &Map,
+        _tick: Tick,
+        start: Traversable,
+        end: LaneID,
+    ) -> Option<Vec<Traversable>> {
+        self.search(map, start, end, None)
+    }
+
+    fn path_debug(&self, map: &Map, _tick: Tick, start: Traversable, end: LaneID) -> PathDebug {
+        let rec = RefCell::new(SearchRecorder::new(start));
+        let path = self.search(map, start, end, Some(&rec));
+        rec.into_inner().finish(path, |t| *t, |t| t)
+    }
 
     fn nearest_lane(&self, map: &Map, pos: Vec3) -> Option<LaneID> {
         map.nearest_lane(pos, LaneKind::Walking, None)
@@ -160,7 +337,19 @@ impl Pathfinder for PedestrianPath {
     }
 }
 
-struct RailPath;
+struct RailPath {
+    /// Only use rails with overhead lines
+    electrified_only: bool,
+}
+
+impl RailPath {
+    const ANY: Self = Self {
+        electrified_only: false,
+    };
+    const ELECTRIFIED: Self = Self {
+        electrified_only: true,
+    };
+}
 
 impl Pathfinder for RailPath {
     fn path(
@@ -170,7 +359,11 @@ impl Pathfinder for RailPath {
         start: Traversable,
         end: LaneID,
     ) -> Option<Vec<Traversable>> {
-        CarPath.path(map, tick, start, end)
+        CarPath::CAR.search(map, tick, start, end, None, self.electrified_only, None)
+    }
+
+    fn path_debug(&self, map: &Map, tick: Tick, start: Traversable, end: LaneID) -> PathDebug {
+        CarPath::CAR.search_debug(map, tick, start, end, None, self.electrified_only)
     }
 
     fn nearest_lane(&self, map: &Map, pos: Vec3) -> Option<LaneID> {
@@ -178,7 +371,7 @@ impl Pathfinder for RailPath {
     }
 
     fn local_route(&self, map: &Map, lane: LaneID, start: Vec3, end: Vec3) -> Option<PolyLine3> {
-        CarPath.local_route(map, lane, start, end)
+        CarPath::CAR.local_route(map, lane, start, end)
     }
 
     fn authorized_lane(&self, kind: LaneKind) -> bool {
@@ -186,18 +379,70 @@ impl Pathfinder for RailPath {
     }
 }
 
-struct CarPath;
+/// Builds the traversables going from `start` through `lanes`, with the turns between them
+pub(crate) fn lane_path(
+    map: &Map,
+    start: Traversable,
+    lanes: impl ExactSizeIterator<Item = LaneID>,
+) -> Option<Vec<Traversable>> {
+    let mut path = Vec::with_capacity(lanes.len() * 2 + 1);
+    path.push(start);
+
+    let mut last_id = start.destination_lane();
+
+    for lane in lanes {
+        let inter_end = &map.intersections.get(map.lanes.get(lane)?.src)?;
+        let id = TurnID::new(inter_end.id, last_id, lane, false);
+        path.push(Traversable::new(
+            TraverseKind::Turn(id),
+            TraverseDirection::Forward,
+        ));
+        path.push(Traversable::new(
+            TraverseKind::Lane(lane),
+            TraverseDirection::Forward,
+        ));
+
+        last_id = lane;
+    }
+    Some(path)
+}
+
+struct CarPath {
+    /// Whether the bus lanes can be used, other vehicles only enter them to reach their destination
+    bus: bool,
+    /// Whether only the lanes with tram tracks can be used
+    tram: bool,
+}
 
-impl Pathfinder for CarPath {
-    fn path(
+impl CarPath {
+    const CAR: Self = Self {
+        bus: false,
+        tram: false,
+    };
+    const BUS: Self = Self {
+        bus: true,
+        tram: false,
+    };
+    const TRAM: Self = Self {
+        bus: false,
+        tram: true,
+    };
+
+    #[allow(clippy::too_many_arguments)]
+    fn search(
         &self,
         map: &Map,
         tick: Tick,
         start: Traversable,
         end: LaneID,
+        avoid: Option<&BTreeSet<District>>,
+        electrified_only: bool,
+        rec: Option<&RefCell<SearchRecorder<LaneID>>>,
     ) -> Option<Vec<Traversable>> {
         let inters = &map.intersections;
         let lanes = &map.lanes;
+        let roads = &map.roads;
+        let avoid = avoid.filter(|a| !a.is_empty());
 
         let start_lane = start.destination_lane();
 
@@ -219,6 +464,7 @@ impl Pathfinder for CarPath {
         let base_random = hash_u64((start_lane.data().as_ffi(), tick.0)) as u32;
 
         let successors = move |&p: &LaneID| {
+            let recorded = record(rec, p);
             let l;
             let p = if p == dummy {
                 l = lanes.get(start_lane);
@@ -234,42 +480,92 @@ impl Pathfinder for CarPath {
                         let mut cost = f32::INFINITY;
 
                         if let Some(l) = lanes.get(x.dst) {
-                            cost = l.points.length() / l.speed_limit;
+                            cost = map.lane_travel_time(l);
                             cost += common::rand::randu(l.dist_from_bottom.to_bits() ^ base_random);
+
+                            // Banned districts can still be entered to reach the destination
+                            if x.dst != end
+                                && avoid.map_or(false, |a| {
+                                    a.contains(&District::of(l.points.first().xy()))
+                                })
+                            {
+                                cost *= TRUCK_BAN_PENALTY;
+                            }
                         }
 
                         (x.dst, OrderedFloat(cost))
                     })
                 })
+                .filter(move |(x, _)| {
+                    self.bus || *x == end || lanes.get(*x).map_or(true, |l| l.kind != LaneKind::Bus)
+                })
+                .filter(move |(x, _)| {
+                    !self.tram || lanes.get(*x).map_or(false, |l| l.kind == LaneKind::Tram)
+                })
+                .filter(move |(x, _)| {
+                    !electrified_only
+                        || lanes
+                            .get(*x)
+                            .and_then(|l| roads.get(l.parent))
+                            .map_or(false, |r| r.electrified)
+                })
+                .inspect(recorded)
         };
 
         let (v, _) =
             pathfinding::directed::astar::astar(&dummy, successors, heuristic, |p| *p == end)?;
 
-        let mut path = Vec::with_capacity(v.len() * 2);
-        path.push(start);
+        lane_path(map, start, v.into_iter().skip(1))
+    }
 
-        let mut last_id = start_lane;
+    fn search_debug(
+        &self,
+        map: &Map,
+        tick: Tick,
+        start: Traversable,
+        end: LaneID,
+        avoid: Option<&BTreeSet<District>>,
+        electrified_only: bool,
+    ) -> PathDebug {
+        let dummy = LaneID::null();
+        let rec = RefCell::new(SearchRecorder::new(dummy));
+        let path = self.search(map, tick, start, end, avoid, electrified_only, Some(&rec));
+        rec.into_inner().finish(
+            path,
+            |t| match t.kind {
+                TraverseKind::Lane(l) => l,
+                TraverseKind::Turn(t) => t.src,
+            },
+            |l| {
+                if l == dummy {
+                    return start;
+                }
+                Traversable::new(TraverseKind::Lane(l), TraverseDirection::Forward)
+            },
+        )
+    }
+}
 
-        for lane in v.into_iter().skip(1) {
-            let inter_end = &inters.get(lanes.get(lane)?.src)?;
-            let id = TurnID::new(inter_end.id, last_id, lane, false);
-            path.push(Traversable::new(
-                TraverseKind::Turn(id),
-                TraverseDirection::Forward,
-            ));
-            path.push(Traversable::new(
-                TraverseKind::Lane(lane),
-                TraverseDirection::Forward,
-            ));
+impl Pathfinder for CarPath {
+    fn path(
+        &self,
+        map: &Map,
+        tick: Tick,
+        start: Traversable,
+        end: LaneID,
+    ) -> Option<Vec<Traversable>> {
+        self.search(map, tick, start, end, None, false, None)
+    }
 
-            last_id = lane;
-        }
-        Some(path)
+    fn path_debug(&self, map: &Map, tick: Tick, start: Traversable, end: LaneID) -> PathDebug {
+        self.search_debug(map, tick, start, end, None, false)
     }
 
     fn nearest_lane(&self, map: &Map, pos: Vec3) -> Option<LaneID> {
-        map.nearest_lane(pos, LaneKind::Driving, None)
+        if self.tram {
+            return map.nearest_lane(pos, LaneKind::Tram, None);
+        }
+        map.nearest_driving_lane(pos, None)
     }
 
     fn local_route(&self, map: &Map, lane: LaneID, start: Vec3, end: Vec3) -> Option<PolyLine3> {
@@ -294,6 +590,47 @@ impl Pathfinder for CarPath {
     }
 
     fn authorized_lane(&self, kind: LaneKind) -> bool {
-        matches!(kind, LaneKind::Driving | LaneKind::Bus)
+        if self.tram {
+            return kind == LaneKind::Tram;
+        }
+        matches!(kind, LaneKind::Driving | LaneKind::Bus | LaneKind::Tram)
+    }
+}
+
+struct TruckPath;
+
+impl Pathfinder for TruckPath {
+    fn path(
+        &self,
+        map: &Map,
+        tick: Tick,
+        start: Traversable,
+        end: LaneID,
+    ) -> Option<Vec<Traversable>> {
+        CarPath::CAR.search(
+            map,
+            tick,
+            start,
+            end,
+            Some(&map.heavy_vehicle_bans),
+            false,
+            None,
+        )
+    }
+
+    fn path_debug(&self, map: &Map, tick: Tick, start: Traversable, end: LaneID) -> PathDebug {
+        CarPath::CAR.search_debug(map, tick, start, end, Some(&map.heavy_vehicle_bans), false)
+    }
+
+    fn nearest_lane(&self, map: &Map, pos: Vec3) -> Option<LaneID> {
+        CarPath::CAR.nearest_lane(map, pos)
+    }
+
+    fn local_route(&self, map: &Map, lane: LaneID, start: Vec3, end: Vec3) -> Option<PolyLine3> {
+        CarPath::CAR.local_route(map, lane, start, end)
+    }
+
+    fn authorized_lane(&self, kind: LaneKind) -> bool {
+        CarPath::CAR.authorized_lane(kind)
     }
 }
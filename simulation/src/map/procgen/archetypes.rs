@@ -0,0 +1,196 @@
+use crate::map::{Lot, LotKind};
+use crate::utils::resources::Resources;
+use crate::World;
+use common::descriptions::HouseStyle;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[cfg(not(test))]
+const ARCHETYPES_PATH: &str = "assets/buildings";
+#[cfg(test)]
+const ARCHETYPES_PATH: &str = "../assets/buildings";
+#[cfg(not(test))]
+const THEMES_PATH: &str = "assets/building_themes";
+#[cfg(test)]
+const THEMES_PATH: &str = "../assets/building_themes";
+
+/// Lot area used as the unit of the density, in m²
+const DENSITY_AREA: f32 = 100.0;
+
+/// A kind of house that can be built on a lot.
+/// Several archetypes exist for the same lot kind so that blocks don't look identical.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildingArchetype {
+    pub name: String,
+    pub lot_kind: LotKind,
+    /// Relative chance of being picked among the archetypes of the same lot kind
+    #[serde(default = "one")]
+    pub weight: f32,
+    #[serde(default)]
+    pub style: HouseStyle,
+    /// Number of residents per 100m² of lot
+    pub density: f32,
+    /// Maximum number of residents
+    pub capacity: u32,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+impl BuildingArchetype {
+    /// Number of people living in a house of this archetype built on the lot
+    pub fn housing(&self, lot: &Lot) -> u32 {
+        let [front, side] = lot.shape.axis();
+        let area = front.mag() * side.mag();
+        ((area / DENSITY_AREA * self.density).round() as u32).clamp(1, self.capacity.max(1))
+    }
+}
+
+/// An architectural theme given to a district, it biases the archetypes picked for its houses
+/// so that neighborhoods have their own look.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildingTheme {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Weight multipliers of the archetypes, by archetype name
+    #[serde(default)]
+    pub weights: BTreeMap<String, f32>,
+    /// Weight multiplier of the archetypes missing from `weights`
+    #[serde(default = "one")]
+    pub others: f32,
+}
+
+impl BuildingTheme {
+    /// Weight of the archetype in a district with this theme
+    pub fn weight(&self, archetype: &BuildingArchetype) -> f32 {
+        archetype.weight
+            * self
+                .weights
+                .get(&archetype.name)
+                .copied()
+                .unwrap_or(self.others)
+    }
+}
+
+/// BuildingArchetypes holds the archetypes read from the json files of assets/buildings,
+/// and the themes read from assets/building_themes.
+/// Mods can add archetypes and themes by adding files to the directories.
+#[derive(Default)]
+pub struct BuildingArchetypes {
+    archetypes: Vec<BuildingArchetype>,
+    themes: Vec<BuildingTheme>,
+}
+
+/// Reads every json file of the directory as a list of `T`
+fn load_dir<T: serde::de::DeserializeOwned>(dir: &Path) -> Vec<T> {
+    let mut v = vec![];
+    if !dir.exists() {
+        return v;
+    }
+
+    let mut paths: Vec<_> = common::saveload::walkdir(dir)
+        .filter(|p| p.extension().map_or(false, |ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        match common::saveload::load_raw(&path)
+            .and_then(|data| common::saveload::JSON::decode::<Vec<T>>(&data))
+        {
+            Ok(x) => v.extend(x),
+            Err(e) => log::error!("could not load {:?}: {}", path, e),
+        }
+    }
+    v
+}
+
+impl BuildingArchetypes {
+    pub fn load(dir: &Path, themes_dir: &Path) -> Self {
+        Self {
+            archetypes: load_dir(dir),
+            themes: load_dir(themes_dir),
+        }
+    }
+
+    pub fn themes(&self) -> impl Iterator<Item = &BuildingTheme> {
+        self.themes.iter()
+    }
+
+    pub fn theme(&self, name: &str) -> Option<&BuildingTheme> {
+        self.themes.iter().find(|t| t.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &BuildingArchetype> {
+        self.archetypes.iter()
+    }
+
+    /// Picks the archetype of the house built on the lot, biased by the theme of its district.
+    /// The choice only depends on the lot position so it is the same on every client.
+    pub fn pick(&self, lot: &Lot, theme: Option<&str>) -> Option<&BuildingArchetype> {
+        let theme = theme.and_then(|name| self.theme(name));
+        let weight = |a: &BuildingArchetype| theme.map_or(a.weight, |t| t.weight(a));
+        // houses built on unassigned lots are residential
+        let kind = match lot.kind {
+            LotKind::Unassigned => LotKind::Residential,
+            k => k,
+        };
+        let candidates = || {
+            self.archetypes
+                .iter()
+                .filter(move |a| a.lot_kind == kind && weight(a) > 0.0)
+        };
+
+        let total: f32 = candidates().map(weight).sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let c = lot.shape.center();
+        let r = common::rand::rand3(c.x, c.y, 17.0) * total;
+        let mut partial = 0.0;
+        for a in candidates() {
+            partial += weight(a);
+            if partial > r {
+                return Some(a);
+            }
+        }
+        candidates().last()
+    }
+}
+
+pub fn init_building_archetypes(_: &mut World, res: &mut Resources) {
+    res.insert(BuildingArchetypes::load(
+        Path::new(ARCHETYPES_PATH),
+        Path::new(THEMES_PATH),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_base_archetypes() {
+        let archetypes =
+            BuildingArchetypes::load(Path::new(ARCHETYPES_PATH), Path::new(THEMES_PATH));
+        assert!(archetypes
+            .iter()
+            .any(|a| a.lot_kind == LotKind::Residential));
+        for a in archetypes.iter() {
+            assert!(a.capacity > 0, "{} has no capacity", a.name);
+        }
+        for t in archetypes.themes() {
+            for name in t.weights.keys() {
+                assert!(
+                    archetypes.iter().any(|a| &a.name == name),
+                    "theme {} biases unknown archetype {}",
+                    t.name,
+                    name
+                );
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use common::descriptions::HouseStyle;
 use geom::skeleton::{faces_from_skeleton, skeleton};
 use geom::{minmax, vec2, Intersect, LinearColor, Polygon, Segment, Shape, Vec2, Vec3, AABB};
 use ordered_float::OrderedFloat;
@@ -27,7 +28,10 @@ impl ColoredMesh {
     }
 }
 
-pub fn gen_exterior_house(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
+/// Height of the floors above the ground floor
+const FLOOR_HEIGHT: f32 = 3.0;
+
+pub fn gen_exterior_house(size: f32, seed: u64, style: &HouseStyle) -> (ColoredMesh, Vec2) {
     let mut retry_cnt = 0;
     'retry: loop {
         let mut ri = 0.0;
@@ -71,7 +75,7 @@ pub fn gen_exterior_house(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
         }
 
         for x in p.iter_mut() {
-            *x *= size / 40.0;
+            *x *= size / 40.0 * style.footprint;
         }
 
         let c = p.bbox().center();
@@ -136,9 +140,16 @@ pub fn gen_exterior_house(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
         );
 
         let mut roofs = ColoredMesh::default();
-        let roof_col = LinearColor::from(crate::config().roof_col);
-
-        let height = 4.0 + gen_range(0.0, 2.0);
+        let roof_col = LinearColor::from(style.roof_col.unwrap_or(crate::config().roof_col));
+        let wall_col = LinearColor::from(style.wall_col.unwrap_or(crate::config().house_col));
+
+        let mut height = 4.0 + gen_range(0.0, 2.0);
+        let mut floors = style.min_floors.max(1);
+        if style.max_floors > floors {
+            let extra = gen_range(0.0, (style.max_floors - floors + 1) as f32) as u32;
+            floors = (floors + extra).min(style.max_floors);
+        }
+        height += (floors - 1) as f32 * FLOOR_HEIGHT;
 
         for mut face in faces {
             if face.len() < 3 {
@@ -175,7 +186,7 @@ pub fn gen_exterior_house(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
 
         for (&a, &b, _) in geom::skeleton::window(&walls) {
             let face = vec![a, b, b.xy().z0(), a.xy().z0()];
-            roofs.faces.push((face, crate::config().house_col.into()));
+            roofs.faces.push((face, wall_col));
         }
 
         return (roofs, lowest_segment.middle());
@@ -188,7 +199,7 @@ pub fn gen_exterior_house(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
 ///     |
 pub fn gen_exterior_farm(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
     let h_size = 30.0;
-    let (mut mesh, mut door_pos) = gen_exterior_house(h_size, seed);
+    let (mut mesh, mut door_pos) = gen_exterior_house(h_size, seed, &HouseStyle::default());
 
     let gen_range = |a, b| -> f32 { common::rand::rand(seed as f32 + 7.0) * (b - a) + a };
 
@@ -0,0 +1,146 @@
+use crate::map::{LanePattern, LanePatternBuilder, Map, MapProject, ProjectKind};
+use geom::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// Distance kept between the map border and the end of the stubs
+const EDGE_MARGIN: f32 = 20.0;
+/// Length of the highway and rail stubs going inward from the border
+const STUB_LENGTH: f32 = 400.0;
+/// The rail stub runs parallel to the highway stub
+const RAIL_OFFSET: f32 = 150.0;
+/// Distance between the ring highway and the map border
+const RING_INSET: f32 = 600.0;
+
+/// Template of the external connections built at the map borders when starting a new map.
+/// They are anchor points for external trade and traffic.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EdgeConnections {
+    #[default]
+    None,
+    /// A highway and a rail stub in the middle of each side
+    OnePerSide,
+    /// A highway ring along the borders, connected to the middle of each side, and rail stubs
+    Ring,
+}
+
+impl EdgeConnections {
+    pub const ALL: [EdgeConnections; 3] = [
+        EdgeConnections::None,
+        EdgeConnections::OnePerSide,
+        EdgeConnections::Ring,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EdgeConnections::None => "None",
+            EdgeConnections::OnePerSide => "One per side",
+            EdgeConnections::Ring => "Ring highway",
+        }
+    }
+}
+
+fn highway() -> LanePattern {
+    LanePatternBuilder::new()
+        .n_lanes(2)
+        .speed_limit(25.0)
+        .sidewalks(false)
+        .parking(false)
+        .build()
+}
+
+fn ground(map: &Map, pos: Vec2) -> MapProject {
+    let h = map.environment.height(pos).unwrap_or(0.0);
+    MapProject::ground(pos.z(h))
+}
+
+pub fn add_edge_connections(map: &mut Map, template: EdgeConnections) {
+    if template == EdgeConnections::None {
+        return;
+    }
+    let bounds = map.environment.bounds();
+    if bounds.w() < 2.0 * (STUB_LENGTH + RING_INSET)
+        || bounds.h() < 2.0 * (STUB_LENGTH + RING_INSET)
+    {
+        log::warn!("map too small for edge connections");
+        return;
+    }
+
+    let highway = highway();
+    let rail = LanePatternBuilder::new().rail(true).build();
+    let center = bounds.center();
+
+    // middle of each side with the direction going inward
+    let sides = [
+        (vec2(center.x, bounds.ll.y), Vec2::Y),
+        (vec2(bounds.ur.x, center.y), -Vec2::X),
+        (vec2(center.x, bounds.ur.y), -Vec2::Y),
+        (vec2(bounds.ll.x, center.y), Vec2::X),
+    ];
+
+    for (mid, inward) in sides {
+        let start = mid + inward * EDGE_MARGIN;
+        let along = inward.perpendicular();
+
+        let rail_start = start + along * RAIL_OFFSET;
+        map.make_connection(
+            ground(map, rail_start),
+            ground(map, rail_start + inward * STUB_LENGTH),
+            None,
+            &rail,
+        );
+
+        if template == EdgeConnections::OnePerSide {
+            map.make_connection(
+                ground(map, start),
+                ground(map, start + inward * STUB_LENGTH),
+                None,
+                &highway,
+            );
+        }
+    }
+
+    if template != EdgeConnections::Ring {
+        return;
+    }
+
+    let ll = bounds.ll + Vec2::splat(RING_INSET);
+    let ur = bounds.ur - Vec2::splat(RING_INSET);
+    let corners = [ll, vec2(ur.x, ll.y), ur, vec2(ll.x, ur.y)];
+    let corner_inters: Vec<_> = corners
+        .iter()
+        .map(|&c| {
+            let p = ground(map, c);
+            map.add_intersection(p.pos)
+        })
+        .collect();
+
+    for (i, (mid, inward)) in sides.into_iter().enumerate() {
+        let from = corner_inters[i];
+        let to = corner_inters[(i + 1) % 4];
+        let Some((_, ring_road)) = map.make_connection(
+            MapProject {
+                pos: map.intersections[from].pos,
+                kind: ProjectKind::Inter(from),
+            },
+            MapProject {
+                pos: map.intersections[to].pos,
+                kind: ProjectKind::Inter(to),
+            },
+            None,
+            &highway,
+        ) else {
+            continue;
+        };
+
+        let ring_mid = mid + inward * RING_INSET;
+        map.make_connection(
+            ground(map, mid + inward * EDGE_MARGIN),
+            MapProject {
+                pos: ring_mid.z(map.environment.height(ring_mid).unwrap_or(0.0)),
+                kind: ProjectKind::Road(ring_road),
+            },
+            None,
+            &highway,
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use crate::map::{Environment, LanePattern, LanePatternBuilder, MapProject};
+use geom::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// Grade-separated interchange templates, the crossing road always passes over the main road
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InterchangeKind {
+    /// The crossing road passes over the main road without any connection, works with rails
+    Overpass,
+    Diamond,
+    Cloverleaf,
+    /// The crossing road ends at the main road, on one side only
+    Trumpet,
+}
+
+impl InterchangeKind {
+    pub const ALL: [InterchangeKind; 4] = [
+        InterchangeKind::Overpass,
+        InterchangeKind::Diamond,
+        InterchangeKind::Cloverleaf,
+        InterchangeKind::Trumpet,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InterchangeKind::Overpass => "Overpass",
+            InterchangeKind::Diamond => "Diamond",
+            InterchangeKind::Cloverleaf => "Cloverleaf",
+            InterchangeKind::Trumpet => "Trumpet",
+        }
+    }
+}
+
+/// The parameters of an interchange, generated as a set of connections built in one action
+#[derive(Debug, Clone)]
+pub struct InterchangeParams {
+    pub kind: InterchangeKind,
+    pub center: Vec2,
+    /// Direction of the main road
+    pub dir: Vec2,
+    /// Distance from the center to the end of each arm, in meters
+    pub size: f32,
+    /// Height of the bridge above the main road, in meters
+    pub height: f32,
+    pub main: LanePattern,
+    pub crossing: LanePattern,
+}
+
+/// Projects and links ready to be sent as a `MapMakeMultipleConnections` command
+#[derive(Debug, Clone, Default)]
+pub struct Interchange {
+    pub projects: Vec<MapProject>,
+    pub links: Vec<(usize, usize, Option<Vec2>, LanePattern)>,
+}
+
+impl Interchange {
+    pub fn ramp_pattern() -> LanePattern {
+        LanePatternBuilder::new()
+            .n_lanes(1)
+            .sidewalks(false)
+            .parking(false)
+            .speed_limit(13.0)
+            .build()
+    }
+
+    /// Polylines of the links, to preview the footprint before building
+    pub fn preview(&self) -> impl Iterator<Item = (MapProject, Option<Vec2>, MapProject)> + '_ {
+        self.links
+            .iter()
+            .map(|&(from, to, elbow, _)| (self.projects[from], elbow, self.projects[to]))
+    }
+}
+
+impl InterchangeParams {
+    /// Generates the interchange in local coordinates: x along the main road, y along the crossing
+    /// road. Ramps are two-way single lane roads.
+    pub fn generate(&self, env: &Environment) -> Interchange {
+        let s = self.size;
+        let h = self.height;
+        let a = 0.5 * s;
+        let i = 0.15 * s;
+        let r = 0.1 * s;
+
+        let mut g = Generator {
+            params: self,
+            env,
+            out: Interchange::default(),
+        };
+        let ramp = Interchange::ramp_pattern();
+        let main = self.main.clone();
+        let crossing = self.crossing.clone();
+
+        match self.kind {
+            InterchangeKind::Overpass => {
+                let m0 = g.node(-s, 0.0, 0.0);
+                let m1 = g.node(s, 0.0, 0.0);
+                g.link(m0, m1, None, &main);
+
+                let c0 = g.node(0.0, -s, 0.0);
+                let c1 = g.node(0.0, -i, h);
+                let c2 = g.node(0.0, i, h);
+                let c3 = g.node(0.0, s, 0.0);
+                g.chain(&[c0, c1, c2, c3], &crossing);
+            }
+            InterchangeKind::Diamond => {
+                let m = [
+                    g.node(-s, 0.0, 0.0),
+                    g.node(-a, 0.0, 0.0),
+                    g.node(a, 0.0, 0.0),
+                    g.node(s, 0.0, 0.0),
+                ];
+                g.chain(&m, &main);
+
+                let c = [
+                    g.node(0.0, -s, 0.0),
+                    g.node(0.0, -i, h),
+                    g.node(0.0, i, h),
+                    g.node(0.0, s, 0.0),
+                ];
+                g.chain(&c, &crossing);
+
+                for from in [m[1], m[2]] {
+                    for to in [c[1], c[2]] {
+                        g.link(from, to, None, &ramp);
+                    }
+                }
+            }
+            InterchangeKind::Cloverleaf => {
+                let m = [
+                    g.node(-s, 0.0, 0.0),
+                    g.node(-a, 0.0, 0.0),
+                    g.node(-i, 0.0, 0.0),
+                    g.node(i, 0.0, 0.0),
+                    g.node(a, 0.0, 0.0),
+                    g.node(s, 0.0, 0.0),
+                ];
+                g.chain(&m, &main);
+
+                let c = [
+                    g.node(0.0, -s, 0.0),
+                    g.node(0.0, -a, h),
+                    g.node(0.0, -i, h),
+                    g.node(0.0, i, h),
+                    g.node(0.0, a, h),
+                    g.node(0.0, s, 0.0),
+                ];
+                g.chain(&c, &crossing);
+
+                let far = i + 2.0 * r;
+                for (qx, mi, mo) in [(-1.0, m[2], m[1]), (1.0, m[3], m[4])] {
+                    for (qy, ci, co) in [(-1.0, c[2], c[1]), (1.0, c[3], c[4])] {
+                        // outer ramp going around the quadrant
+                        g.link(mo, co, Some(g.local(qx * a, qy * a)), &ramp);
+
+                        // loop inside the quadrant, each half stays on its side of the diagonal
+                        // so that the loop doesn't cross itself
+                        let w = g.node(qx * far, qy * far, h * 0.5);
+                        g.link(mi, w, Some(g.local(qx * far, qy * i * 0.5)), &ramp);
+                        g.link(w, ci, Some(g.local(qx * i * 0.5, qy * far)), &ramp);
+                    }
+                }
+            }
+            InterchangeKind::Trumpet => {
+                let m = [
+                    g.node(-s, 0.0, 0.0),
+                    g.node(-a, 0.0, 0.0),
+                    g.node(a, 0.0, 0.0),
+                    g.node(s, 0.0, 0.0),
+                ];
+                g.chain(&m, &main);
+
+                let c0 = g.node(0.0, s, 0.0);
+                let t = g.node(0.0, i, h);
+                g.link(c0, t, None, &crossing);
+
+                // direct ramp on the side of the crossing road
+                g.link(t, m[2], Some(g.local(a, i)), &ramp);
+
+                // bridge over the main road then loop back to it
+                let far = i + 2.0 * r;
+                let u = g.node(0.0, -i, h);
+                let w = g.node(-far, -far, h * 0.5);
+                g.link(t, u, None, &ramp);
+                g.link(u, w, Some(g.local(0.0, -far)), &ramp);
+                g.link(w, m[1], Some(g.local(-a, -far)), &ramp);
+            }
+        }
+
+        g.out
+    }
+}
+
+struct Generator<'a> {
+    params: &'a InterchangeParams,
+    env: &'a Environment,
+    out: Interchange,
+}
+
+impl Generator<'_> {
+    fn local(&self, x: f32, y: f32) -> Vec2 {
+        let dir = self.params.dir.normalize();
+        self.params.center + dir * x + dir.perpendicular() * y
+    }
+
+    /// Adds a node at the given local position, lifted above the terrain
+    fn node(&mut self, x: f32, y: f32, lift: f32) -> usize {
+        let pos = self.local(x, y);
+        let ground = self
+            .env
+            .height(pos)
+            .or_else(|| self.env.height(self.params.center))
+            .unwrap_or(0.0);
+        self.out
+            .projects
+            .push(MapProject::ground(pos.z(ground + lift)));
+        self.out.projects.len() - 1
+    }
+
+    fn link(&mut self, from: usize, to: usize, elbow: Option<Vec2>, pat: &LanePattern) {
+        self.out.links.push((from, to, elbow, pat.clone()));
+    }
+
+    fn chain(&mut self, nodes: &[usize], pat: &LanePattern) {
+        for w in nodes.windows(2) {
+            self.link(w[0], w[1], None, pat);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use crate::WorldCommand;
+    use geom::vec2;
+
+    #[test]
+    fn interchanges_are_built_in_one_action() {
+        for kind in InterchangeKind::ALL {
+            let mut test = TestCtx::new();
+
+            let params = InterchangeParams {
+                kind,
+                center: vec2(0.0, 0.0),
+                dir: vec2(1.0, 0.0),
+                size: 200.0,
+                height: 8.0,
+                main: LanePatternBuilder::new()
+                    .n_lanes(2)
+                    .parking(false)
+                    .sidewalks(false)
+                    .build(),
+                crossing: LanePatternBuilder::new().build(),
+            };
+            let inter = params.generate(&test.g.map().environment);
+            let n_links = inter.links.len();
+
+            test.apply(&[WorldCommand::MapMakeMultipleConnections(
+                inter.projects,
+                inter.links,
+            )]);
+
+            let map = test.g.map();
+            map.check_invariants();
+            assert_eq!(map.roads().len(), n_links, "{:?}", kind);
+        }
+    }
+}
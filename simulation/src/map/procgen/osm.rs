@@ -0,0 +1,724 @@
+//! Import of the road network of an OpenStreetMap extract (.osm.pbf)
+//! See <https://wiki.openstreetmap.org/wiki/PBF_Format> for the format
+#![allow(clippy::indexing_slicing)]
+
+use crate::map::{IntersectionID, LanePatternBuilder, Map, RoadSegmentKind};
+use common::saveload::CompressedBincode;
+use common::{FastMap, FastSet};
+use flat_spatial::Grid;
+use geom::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+/// Junctions closer than this are merged, in meters
+const MERGE_DIST: f32 = 8.0;
+/// Shape nodes closer than this to the previous kept node are dropped, in meters
+const MIN_ROAD_LENGTH: f32 = 15.0;
+/// Shape nodes where the way turns less than this are dropped, as a cosine
+const MIN_TURN_COS: f32 = 0.97;
+/// Biggest uncompressed blob allowed by the format
+const MAX_BLOB_SIZE: usize = 32 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OsmError {
+    Io(String),
+    /// The file is not a valid .osm.pbf file
+    Format(&'static str),
+    Unsupported(&'static str),
+    NoRoads,
+}
+
+impl Display for OsmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OsmError::Io(err) => write!(f, "Could not read the file: {err}"),
+            OsmError::Format(reason) => write!(f, "Not a valid .osm.pbf file: {reason}"),
+            OsmError::Unsupported(what) => write!(f, "Unsupported .osm.pbf file: {what}"),
+            OsmError::NoRoads => write!(f, "The extract contains no road"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OsmRoad {
+    /// Indices in [`OsmNetwork::nodes`]
+    pub src: u32,
+    pub dst: u32,
+    /// Lanes in each direction
+    pub n_lanes: u32,
+    pub one_way: bool,
+    /// In m/s
+    pub speed_limit: f32,
+    pub sidewalks: bool,
+    pub parking: bool,
+}
+
+impl OsmRoad {
+    pub fn pattern(&self) -> LanePatternBuilder {
+        LanePatternBuilder::new()
+            .n_lanes(self.n_lanes)
+            .one_way(self.one_way)
+            .speed_limit(self.speed_limit)
+            .sidewalks(self.sidewalks)
+            .parking(self.parking)
+    }
+}
+
+/// The road network of an extract, in meters around its center
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OsmNetwork {
+    pub nodes: Vec<Vec2>,
+    pub roads: Vec<OsmRoad>,
+}
+
+impl OsmNetwork {
+    pub fn from_pbf(data: &[u8]) -> Result<Self, OsmError> {
+        let extract = parse_pbf(data)?;
+        let network = Self::from_extract(&extract);
+        if network.roads.is_empty() {
+            return Err(OsmError::NoRoads);
+        }
+        Ok(network)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, OsmError> {
+        let data = std::fs::read(path).map_err(|e| OsmError::Io(e.to_string()))?;
+        Self::from_pbf(&data)
+    }
+
+    fn from_extract(extract: &Extract) -> Self {
+        let ways: Vec<(&Way, Highway)> = extract
+            .ways
+            .iter()
+            .filter_map(|w| Some((w, Highway::new(w)?)))
+            .filter(|(w, _)| w.refs.iter().all(|r| extract.nodes.contains_key(r)))
+            .collect();
+
+        // nodes shared by several ways and the ends of the ways are junctions
+        let mut uses: FastMap<i64, u32> = FastMap::default();
+        for (w, _) in &ways {
+            for r in &w.refs {
+                *uses.entry(*r).or_default() += 1;
+            }
+            for end in [w.refs.first(), w.refs.last()].into_iter().flatten() {
+                *uses.entry(*end).or_default() += 2;
+            }
+        }
+
+        let (mut min, mut max) = (vec2(f32::MAX, f32::MAX), vec2(f32::MIN, f32::MIN));
+        let (mut lat0, mut lon0, mut n) = (0.0, 0.0, 0.0);
+        for r in uses.keys() {
+            let (lat, lon) = extract.nodes[r];
+            lat0 += lat;
+            lon0 += lon;
+            n += 1.0;
+        }
+        lat0 /= n;
+        lon0 /= n;
+        let project = |id: &i64| {
+            let (lat, lon) = extract.nodes[id];
+            let x = (lon - lon0) * 111_320.0 * f64::cos(lat0.to_radians());
+            let y = (lat - lat0) * 110_540.0;
+            vec2(x as f32, y as f32)
+        };
+        for r in uses.keys() {
+            let p = project(r);
+            min = min.min(p);
+            max = max.max(p);
+        }
+        let center = (min + max) * 0.5;
+
+        let mut network = OsmNetwork::default();
+        let mut grid: Grid<u32, Vec2> = Grid::new(50);
+        let mut ids: FastMap<i64, u32> = FastMap::default();
+        let mut node = |network: &mut OsmNetwork, id: i64, pos: Vec2| -> u32 {
+            if let Some(&i) = ids.get(&id) {
+                return i;
+            }
+            let merged = grid
+                .query_around(pos, MERGE_DIST)
+                .next()
+                .and_then(|(h, _)| grid.get(h))
+                .map(|(_, &i)| i);
+            let i = merged.unwrap_or_else(|| {
+                network.nodes.push(pos);
+                let i = network.nodes.len() as u32 - 1;
+                grid.insert(pos, i);
+                i
+            });
+            ids.insert(id, i);
+            i
+        };
+
+        let mut seen = FastSet::default();
+        for (w, highway) in ways {
+            let pos = |r: &i64| project(r) - center;
+            let mut kept = vec![w.refs[0]];
+            for (i, r) in w.refs.iter().enumerate().skip(1) {
+                let last = *kept.last().unwrap();
+                let Some(next) = w.refs.get(i + 1) else {
+                    kept.push(*r);
+                    break;
+                };
+                if uses[r] >= 2 {
+                    kept.push(*r);
+                    continue;
+                }
+                let (a, b, c) = (pos(&last), pos(r), pos(next));
+                let turns = match ((b - a).try_normalize(), (c - b).try_normalize()) {
+                    (Some(d1), Some(d2)) => d1.dot(d2) < MIN_TURN_COS,
+                    _ => false,
+                };
+                if turns && a.distance(b) > MIN_ROAD_LENGTH {
+                    kept.push(*r);
+                }
+            }
+
+            for pair in kept.windows(2) {
+                let src = node(&mut network, pair[0], pos(&pair[0]));
+                let dst = node(&mut network, pair[1], pos(&pair[1]));
+                if src == dst || !seen.insert((src.min(dst), src.max(dst))) {
+                    continue;
+                }
+                network.roads.push(highway.road(src, dst));
+            }
+        }
+
+        network
+    }
+
+    /// Builds the roads and intersections on the map, centered on its terrain
+    pub fn build(&self, map: &mut Map) {
+        let time = std::time::Instant::now();
+        let bounds = map.environment.bounds();
+        let has_terrain = bounds.w() > 0.0 && bounds.h() > 0.0;
+        let offset = if has_terrain {
+            bounds.center()
+        } else {
+            Vec2::ZERO
+        };
+
+        let mut inters: Vec<Option<IntersectionID>> = vec![None; self.nodes.len()];
+        for road in &self.roads {
+            let src = self.nodes[road.src as usize] + offset;
+            let dst = self.nodes[road.dst as usize] + offset;
+            if has_terrain && (!bounds.contains(src) || !bounds.contains(dst)) {
+                continue;
+            }
+            let mut inter = |map: &mut Map, i: u32, pos: Vec2| {
+                *inters[i as usize].get_or_insert_with(|| {
+                    let z = map.environment.height(pos).unwrap_or(0.0) + 0.3;
+                    map.add_intersection(pos.z(z))
+                })
+            };
+            let src = inter(map, road.src, src);
+            let dst = inter(map, road.dst, dst);
+            map.connect(src, dst, &road.pattern().build(), RoadSegmentKind::Straight);
+        }
+
+        // connecting might have failed, an intersection always has a road
+        for id in inters.into_iter().flatten() {
+            if map
+                .intersections
+                .get(id)
+                .map_or(false, |i| i.roads.is_empty())
+            {
+                map.remove_intersection(id);
+            }
+        }
+
+        info!(
+            "importing osm network took {}ms",
+            time.elapsed().as_secs_f32() * 1000.0
+        );
+
+        map.check_invariants();
+    }
+}
+
+/// Reads the road network of an OpenStreetMap extract into a new map without terrain
+pub fn import_osm(path: impl AsRef<Path>) -> Result<Map, OsmError> {
+    let network = OsmNetwork::from_file(path)?;
+    let mut map = Map::empty();
+    network.build(&mut map);
+    Ok(map)
+}
+
+/// The kind of road of a way and what can be deduced of its lanes from its tags
+struct Highway {
+    n_lanes: u32,
+    one_way: bool,
+    speed_limit: f32,
+    sidewalks: bool,
+    parking: bool,
+}
+
+impl Highway {
+    fn new(w: &Way) -> Option<Self> {
+        let tag = |k: &str| w.tags.iter().find(|(key, _)| key == k).map(|(_, v)| &**v);
+
+        // (lanes per direction, speed in km/h, sidewalks, parking)
+        let (n_lanes, kmh, sidewalks, parking) = match tag("highway")? {
+            "motorway" => (2, 110.0, false, false),
+            "trunk" => (2, 90.0, false, false),
+            "motorway_link" | "trunk_link" => (1, 70.0, false, false),
+            "primary" => (2, 50.0, true, false),
+            "secondary" => (1, 50.0, true, false),
+            "primary_link" | "secondary_link" | "tertiary_link" => (1, 40.0, true, false),
+            "tertiary" | "unclassified" => (1, 50.0, true, true),
+            "residential" => (1, 30.0, true, true),
+            "living_street" => (1, 20.0, true, false),
+            _ => return None,
+        };
+
+        let one_way = matches!(tag("oneway"), Some("yes" | "1" | "true" | "-1"))
+            || matches!(tag("junction"), Some("roundabout" | "circular"))
+            || (matches!(tag("highway"), Some("motorway" | "motorway_link"))
+                && tag("oneway") != Some("no"));
+
+        let n_lanes = match tag("lanes").and_then(|l| l.parse::<u32>().ok()) {
+            Some(lanes) if one_way => lanes,
+            Some(lanes) => (lanes / 2).max(1),
+            None => n_lanes,
+        }
+        .clamp(1, 4);
+
+        let kmh = tag("maxspeed").and_then(parse_maxspeed).unwrap_or(kmh);
+
+        Some(Self {
+            n_lanes,
+            one_way,
+            speed_limit: (kmh / 3.6).clamp(4.0, 40.0),
+            sidewalks,
+            parking: parking && tag("parking:lane:both") != Some("no"),
+        })
+    }
+
+    fn road(&self, src: u32, dst: u32) -> OsmRoad {
+        OsmRoad {
+            src,
+            dst,
+            n_lanes: self.n_lanes,
+            one_way: self.one_way,
+            speed_limit: self.speed_limit,
+            sidewalks: self.sidewalks,
+            parking: self.parking,
+        }
+    }
+}
+
+/// Speed limit in km/h, from values like "50", "30 mph"
+fn parse_maxspeed(v: &str) -> Option<f32> {
+    let v = v.trim();
+    if let Some(mph) = v.strip_suffix("mph") {
+        return mph.trim().parse::<f32>().ok().map(|x| x * 1.609);
+    }
+    v.parse().ok()
+}
+
+/// The nodes and ways of an extract
+#[derive(Default)]
+struct Extract {
+    /// Latitude and longitude of the nodes, in degrees
+    nodes: FastMap<i64, (f64, f64)>,
+    ways: Vec<Way>,
+}
+
+struct Way {
+    refs: Vec<i64>,
+    tags: Vec<(String, String)>,
+}
+
+fn parse_pbf(mut data: &[u8]) -> Result<Extract, OsmError> {
+    let mut extract = Extract::default();
+    while !data.is_empty() {
+        if data.len() < 4 {
+            return Err(OsmError::Format("truncated blob header"));
+        }
+        let header_len = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let header = data
+            .get(4..4 + header_len)
+            .ok_or(OsmError::Format("truncated blob header"))?;
+
+        let mut kind = "";
+        let mut datasize = 0;
+        for field in Proto::new(header) {
+            match field? {
+                (1, Value::Bytes(b)) => {
+                    kind = std::str::from_utf8(b).map_err(|_| OsmError::Format("blob type"))?
+                }
+                (3, Value::Varint(v)) => datasize = v as usize,
+                _ => {}
+            }
+        }
+
+        let blob = data
+            .get(4 + header_len..4 + header_len + datasize)
+            .ok_or(OsmError::Format("truncated blob"))?;
+        data = &data[4 + header_len + datasize..];
+
+        match kind {
+            "OSMHeader" => check_header(&blob_data(blob)?)?,
+            "OSMData" => parse_block(&blob_data(blob)?, &mut extract)?,
+            _ => {}
+        }
+    }
+    Ok(extract)
+}
+
+fn blob_data(blob: &[u8]) -> Result<Vec<u8>, OsmError> {
+    for field in Proto::new(blob) {
+        match field? {
+            (1, Value::Bytes(raw)) => return Ok(raw.to_vec()),
+            (3, Value::Bytes(zlib)) => {
+                // errors once the blob inflates past the limit, without inflating the rest
+                return CompressedBincode::decompress_with_limit(zlib, MAX_BLOB_SIZE)
+                    .map_err(|_| OsmError::Format("could not decompress blob or blob is too big"));
+            }
+            (4..=7, _) => return Err(OsmError::Unsupported("compression other than zlib")),
+            _ => {}
+        }
+    }
+    Err(OsmError::Format("empty blob"))
+}
+
+fn check_header(header: &[u8]) -> Result<(), OsmError> {
+    for field in Proto::new(header) {
+        if let (4, Value::Bytes(feature)) = field? {
+            if !matches!(feature, b"OsmSchema-V0.6" | b"DenseNodes") {
+                return Err(OsmError::Unsupported("required feature"));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_block(block: &[u8], extract: &mut Extract) -> Result<(), OsmError> {
+    let mut strings: Vec<&[u8]> = vec![];
+    let mut groups = vec![];
+    let mut granularity = 100;
+    let (mut lat_offset, mut lon_offset) = (0, 0);
+    for field in Proto::new(block) {
+        match field? {
+            (1, Value::Bytes(table)) => {
+                for s in Proto::new(table) {
+                    if let (1, Value::Bytes(s)) = s? {
+                        strings.push(s);
+                    }
+                }
+            }
+            (2, Value::Bytes(group)) => groups.push(group),
+            (17, Value::Varint(v)) => granularity = v as i64,
+            (19, Value::Varint(v)) => lat_offset = v as i64,
+            (20, Value::Varint(v)) => lon_offset = v as i64,
+            _ => {}
+        }
+    }
+
+    let string = |i: u64| -> Result<String, OsmError> {
+        let s = strings
+            .get(i as usize)
+            .ok_or(OsmError::Format("string out of the table"))?;
+        Ok(String::from_utf8_lossy(s).into_owned())
+    };
+    let degrees = |offset: i64, v: i64| 1e-9 * (offset + granularity * v) as f64;
+
+    for group in groups {
+        for field in Proto::new(group) {
+            match field? {
+                (1, Value::Bytes(node)) => {
+                    let (mut id, mut lat, mut lon) = (0, 0, 0);
+                    for field in Proto::new(node) {
+                        match field? {
+                            (1, Value::Varint(v)) => id = zigzag(v),
+                            (8, Value::Varint(v)) => lat = zigzag(v),
+                            (9, Value::Varint(v)) => lon = zigzag(v),
+                            _ => {}
+                        }
+                    }
+                    extract
+                        .nodes
+                        .insert(id, (degrees(lat_offset, lat), degrees(lon_offset, lon)));
+                }
+                (2, Value::Bytes(dense)) => {
+                    let (mut ids, mut lats, mut lons) = (vec![], vec![], vec![]);
+                    for field in Proto::new(dense) {
+                        match field? {
+                            (1, v) => ids = v.packed()?,
+                            (8, v) => lats = v.packed()?,
+                            (9, v) => lons = v.packed()?,
+                            _ => {}
+                        }
+                    }
+                    if ids.len() != lats.len() || ids.len() != lons.len() {
+                        return Err(OsmError::Format("dense nodes of different lengths"));
+                    }
+                    let (mut id, mut lat, mut lon) = (0, 0, 0);
+                    for i in 0..ids.len() {
+                        id += zigzag(ids[i]);
+                        lat += zigzag(lats[i]);
+                        lon += zigzag(lons[i]);
+                        extract
+                            .nodes
+                            .insert(id, (degrees(lat_offset, lat), degrees(lon_offset, lon)));
+                    }
+                }
+                (3, Value::Bytes(way)) => {
+                    let (mut keys, mut vals, mut refs) = (vec![], vec![], vec![]);
+                    for field in Proto::new(way) {
+                        match field? {
+                            (2, v) => keys = v.packed()?,
+                            (3, v) => vals = v.packed()?,
+                            (8, v) => refs = v.packed()?,
+                            _ => {}
+                        }
+                    }
+                    let mut node = 0;
+                    let mut refs: Vec<i64> = refs
+                        .into_iter()
+                        .map(|r| {
+                            node += zigzag(r);
+                            node
+                        })
+                        .collect();
+                    if refs.len() < 2 {
+                        continue;
+                    }
+                    let tags = keys
+                        .into_iter()
+                        .zip(vals)
+                        .map(|(k, v)| Ok((string(k)?, string(v)?)))
+                        .collect::<Result<Vec<_>, OsmError>>()?;
+                    // ways going against their node order are turned around
+                    if tags.iter().any(|(k, v)| k == "oneway" && v == "-1") {
+                        refs.reverse();
+                    }
+                    extract.ways.push(Way { refs, tags });
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+fn zigzag(v: u64) -> i64 {
+    (v >> 1) as i64 ^ -((v & 1) as i64)
+}
+
+/// Field of a protobuf message
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+impl Value<'_> {
+    /// Values of a repeated varint field, packed or not
+    fn packed(self) -> Result<Vec<u64>, OsmError> {
+        match self {
+            Value::Varint(v) => Ok(vec![v]),
+            Value::Bytes(mut b) => {
+                let mut values = vec![];
+                while !b.is_empty() {
+                    values.push(varint(&mut b)?);
+                }
+                Ok(values)
+            }
+            Value::Fixed => Err(OsmError::Format("fixed value in a varint field")),
+        }
+    }
+}
+
+/// Iterator over the fields of a protobuf message
+struct Proto<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Proto<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn field(&mut self) -> Result<(u64, Value<'a>), OsmError> {
+        let key = varint(&mut self.data)?;
+        let value = match key & 7 {
+            0 => Value::Varint(varint(&mut self.data)?),
+            1 | 5 => {
+                let len = if key & 7 == 1 { 8 } else { 4 };
+                if self.data.len() < len {
+                    return Err(OsmError::Format("truncated field"));
+                }
+                self.data = &self.data[len..];
+                Value::Fixed
+            }
+            2 => {
+                let len = varint(&mut self.data)? as usize;
+                if self.data.len() < len {
+                    return Err(OsmError::Format("truncated field"));
+                }
+                let (bytes, rest) = self.data.split_at(len);
+                self.data = rest;
+                Value::Bytes(bytes)
+            }
+            _ => return Err(OsmError::Format("unknown wire type")),
+        };
+        Ok((key >> 3, value))
+    }
+}
+
+impl<'a> Iterator for Proto<'a> {
+    type Item = Result<(u64, Value<'a>), OsmError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let field = self.field();
+        if field.is_err() {
+            self.data = &[];
+        }
+        Some(field)
+    }
+}
+
+fn varint(data: &mut &[u8]) -> Result<u64, OsmError> {
+    let mut v = 0;
+    for (i, &b) in data.iter().enumerate().take(10) {
+        v |= ((b & 0x7f) as u64) << (7 * i);
+        if b & 0x80 == 0 {
+            *data = &data[i + 1..];
+            return Ok(v);
+        }
+    }
+    Err(OsmError::Format("truncated varint"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(field: u64, wire: u64, out: &mut Vec<u8>) {
+        write_varint(field << 3 | wire, out);
+    }
+
+    fn write_varint(mut v: u64, out: &mut Vec<u8>) {
+        while v >= 0x80 {
+            out.push((v as u8) | 0x80);
+            v >>= 7;
+        }
+        out.push(v as u8);
+    }
+
+    fn bytes(field: u64, b: &[u8], out: &mut Vec<u8>) {
+        key(field, 2, out);
+        write_varint(b.len() as u64, out);
+        out.extend_from_slice(b);
+    }
+
+    fn packed(field: u64, values: &[i64], out: &mut Vec<u8>) {
+        let mut b = vec![];
+        let mut last = 0;
+        for &v in values {
+            write_varint(((v - last) << 1 ^ ((v - last) >> 63)) as u64, &mut b);
+            last = v;
+        }
+        bytes(field, &b, out);
+    }
+
+    /// A grid of 3x3 nodes 100m apart with a two way street and a one way street crossing
+    fn extract() -> Vec<u8> {
+        let mut strings = vec![];
+        for s in [
+            "",
+            "highway",
+            "residential",
+            "oneway",
+            "yes",
+            "maxspeed",
+            "50",
+        ] {
+            bytes(1, s.as_bytes(), &mut strings);
+        }
+
+        let ids: Vec<i64> = (1..=9).collect();
+        let lat = |i: i64| (48.0 + ((i - 1) / 3) as f64 * 0.0009) * 1e7;
+        let lon = |i: i64| (2.0 + ((i - 1) % 3) as f64 * 0.00135) * 1e7;
+        let mut dense = vec![];
+        packed(1, &ids, &mut dense);
+        packed(
+            8,
+            &ids.iter().map(|&i| lat(i) as i64).collect::<Vec<_>>(),
+            &mut dense,
+        );
+        packed(
+            9,
+            &ids.iter().map(|&i| lon(i) as i64).collect::<Vec<_>>(),
+            &mut dense,
+        );
+
+        let mut group = vec![];
+        bytes(2, &dense, &mut group);
+        // west to east, through the middle
+        let mut way = vec![];
+        packed(2, &[1], &mut way);
+        packed(3, &[2], &mut way);
+        packed(8, &[4, 5, 6], &mut way);
+        bytes(3, &way, &mut group);
+        // one way, south to north, through the middle
+        let mut way = vec![];
+        packed(2, &[1, 3, 5], &mut way);
+        packed(3, &[2, 4, 6], &mut way);
+        packed(8, &[2, 5, 8], &mut way);
+        bytes(3, &way, &mut group);
+
+        let mut block = vec![];
+        bytes(1, &strings, &mut block);
+        bytes(2, &group, &mut block);
+
+        let mut blob = vec![];
+        bytes(3, &CompressedBincode::compress(&block), &mut blob);
+        let mut header = vec![];
+        bytes(1, b"OSMData", &mut header);
+        key(3, 0, &mut header);
+        write_varint(blob.len() as u64, &mut header);
+
+        let mut file = (header.len() as u32).to_be_bytes().to_vec();
+        file.extend(header);
+        file.extend(blob);
+        file
+    }
+
+    #[test]
+    fn network_from_pbf() {
+        let network = OsmNetwork::from_pbf(&extract()).unwrap();
+
+        assert_eq!(network.nodes.len(), 5);
+        assert_eq!(network.roads.len(), 4);
+        let one_way: Vec<_> = network.roads.iter().filter(|r| r.one_way).collect();
+        assert_eq!(one_way.len(), 2);
+        for r in one_way {
+            assert!((r.speed_limit - 50.0 / 3.6).abs() < 0.01);
+            let (src, dst) = (network.nodes[r.src as usize], network.nodes[r.dst as usize]);
+            assert!(dst.y > src.y);
+            assert!((src.distance(dst) - 100.0).abs() < 2.0);
+        }
+
+        let mut map = Map::empty();
+        network.build(&mut map);
+        assert_eq!(map.roads().len(), 4);
+        assert_eq!(map.intersections().len(), 5);
+    }
+
+    #[test]
+    fn invalid_pbf() {
+        assert!(matches!(
+            OsmNetwork::from_pbf(&[0, 0, 0, 12, 1]),
+            Err(OsmError::Format(_))
+        ));
+        let mut file = extract();
+        file.truncate(file.len() - 3);
+        assert!(OsmNetwork::from_pbf(&file).is_err());
+    }
+}
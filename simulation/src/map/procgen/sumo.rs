@@ -0,0 +1,506 @@
+//! Import of the road network of a SUMO network (.net.xml), so that networks calibrated in SUMO
+//! can be brought into the game, along with the demand of its route files (.rou.xml).
+//! The export lives in [`crate::utils::sumo`].
+//! See <https://sumo.dlr.de/docs/Networks/SUMO_Road_Networks.html> and
+//! <https://sumo.dlr.de/docs/Definition_of_Vehicles%2C_Vehicle_Types%2C_and_Routes.html> for the
+//! formats
+//!
+//! The import is best effort: the shape of the edges, the connections and the traffic lights are
+//! left out, the game recomputes them from the roads.
+
+use crate::map::procgen::{OsmNetwork, OsmRoad};
+use common::{FastMap, FastSet};
+use geom::{vec2, Vec2};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+/// Flows are expanded into vehicles, a huge flow would spawn more cars than the game can handle
+const MAX_FLOW_VEHICLES: usize = 10000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SumoError {
+    Io(String),
+    /// The file is not a valid SUMO file
+    Format(&'static str),
+    NoRoads,
+    NoTrips,
+}
+
+impl Display for SumoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SumoError::Io(err) => write!(f, "Could not read the file: {err}"),
+            SumoError::Format(reason) => write!(f, "Not a valid SUMO file: {reason}"),
+            SumoError::NoRoads => write!(f, "The network contains no road"),
+            SumoError::NoTrips => write!(f, "The routes contain no trip"),
+        }
+    }
+}
+
+/// A directed edge of the network, with what its lanes allow
+struct SumoEdge {
+    id: String,
+    from: String,
+    to: String,
+    n_lanes: u32,
+    /// Highest speed of its lanes in m/s
+    speed: f32,
+    sidewalk: bool,
+}
+
+/// The junctions and the normal edges of a .net.xml file
+struct SumoNet {
+    junctions: Vec<(String, Vec2)>,
+    edges: Vec<SumoEdge>,
+}
+
+impl SumoNet {
+    fn parse(text: &str) -> Result<Self, SumoError> {
+        let mut junctions: Vec<(String, Vec2)> = vec![];
+        let mut edges: Vec<SumoEdge> = vec![];
+        // lanes only appear in edges, right after the edge they belong to
+        let mut in_edge = false;
+
+        for tag in Tags::new(text) {
+            let tag = tag?;
+            match tag.name {
+                "junction" => {
+                    if tag.attr("type") == Some("internal") {
+                        continue;
+                    }
+                    let (Some(id), Some(x), Some(y)) = (tag.attr("id"), tag.f32("x"), tag.f32("y"))
+                    else {
+                        return Err(SumoError::Format("junction without id or position"));
+                    };
+                    junctions.push((id.to_string(), vec2(x, y)));
+                }
+                "edge" => {
+                    // internal edges, crossings and walking areas have a function
+                    in_edge = tag.attr("function").map_or(true, |f| f == "normal");
+                    if !in_edge {
+                        continue;
+                    }
+                    let (Some(from), Some(to)) = (tag.attr("from"), tag.attr("to")) else {
+                        return Err(SumoError::Format("edge without from or to"));
+                    };
+                    edges.push(SumoEdge {
+                        id: tag.attr("id").unwrap_or_default().to_string(),
+                        from: from.to_string(),
+                        to: to.to_string(),
+                        n_lanes: 0,
+                        speed: 0.0,
+                        sidewalk: false,
+                    });
+                }
+                "lane" if in_edge => {
+                    let Some(edge) = edges.last_mut() else {
+                        continue;
+                    };
+                    let allows = |class: &str| match (tag.attr("allow"), tag.attr("disallow")) {
+                        (Some(allow), _) => allow.split_whitespace().any(|c| c == class),
+                        (None, Some(disallow)) => !disallow.split_whitespace().any(|c| c == class),
+                        (None, None) => true,
+                    };
+                    if allows("passenger") {
+                        edge.n_lanes += 1;
+                        edge.speed = edge.speed.max(tag.f32("speed").unwrap_or(13.9));
+                    } else if allows("pedestrian") {
+                        edge.sidewalk = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self { junctions, edges })
+    }
+
+    /// Center of the junctions, it becomes the origin of the map
+    fn center(&self) -> Vec2 {
+        let (mut min, mut max) = (vec2(f32::MAX, f32::MAX), vec2(f32::MIN, f32::MIN));
+        for (_, p) in &self.junctions {
+            min = min.min(*p);
+            max = max.max(*p);
+        }
+        (min + max) * 0.5
+    }
+}
+
+impl OsmNetwork {
+    pub fn from_sumo(text: &str) -> Result<Self, SumoError> {
+        let net = SumoNet::parse(text)?;
+        let (junctions, edges) = (&net.junctions, &net.edges);
+        let center = net.center();
+
+        let mut network = OsmNetwork::default();
+        let mut ids: FastMap<&str, u32> = FastMap::default();
+        for (id, pos) in junctions {
+            ids.insert(id, network.nodes.len() as u32);
+            network.nodes.push(*pos - center);
+        }
+
+        let by_ends: FastMap<(&str, &str), &SumoEdge> = edges
+            .iter()
+            .filter(|e| e.n_lanes > 0)
+            .map(|e| ((&*e.from, &*e.to), e))
+            .collect();
+        let mut seen = FastSet::default();
+        for e in edges {
+            if e.n_lanes == 0 {
+                continue;
+            }
+            let (Some(&src), Some(&dst)) = (ids.get(&*e.from), ids.get(&*e.to)) else {
+                return Err(SumoError::Format("edge between unknown junctions"));
+            };
+            if src == dst || !seen.insert((src.min(dst), src.max(dst))) {
+                continue;
+            }
+            // both directions of a street are two edges in SUMO, lanes are counted per direction
+            let reverse = by_ends.get(&(&*e.to, &*e.from));
+            network.roads.push(OsmRoad {
+                src,
+                dst,
+                n_lanes: e.n_lanes.max(reverse.map_or(0, |r| r.n_lanes)).clamp(1, 4),
+                one_way: reverse.is_none(),
+                speed_limit: e
+                    .speed
+                    .max(reverse.map_or(0.0, |r| r.speed))
+                    .clamp(4.0, 40.0),
+                sidewalks: e.sidewalk || reverse.map_or(false, |r| r.sidewalk),
+                parking: false,
+            });
+        }
+
+        if network.roads.is_empty() {
+            return Err(SumoError::NoRoads);
+        }
+        Ok(network)
+    }
+
+    pub fn from_sumo_file(path: impl AsRef<Path>) -> Result<Self, SumoError> {
+        let text = std::fs::read_to_string(path).map_err(|e| SumoError::Io(e.to_string()))?;
+        Self::from_sumo(&text)
+    }
+}
+
+/// A trip of a SUMO route file, from the start of its first edge to the end of its last edge.
+/// The positions are those of the map imported with [`OsmNetwork::from_sumo`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SumoTrip {
+    /// Seconds after the start of the demand
+    pub depart: f32,
+    pub from: Vec2,
+    pub to: Vec2,
+}
+
+/// Reads the demand of a route file (.rou.xml) written for the given network (.net.xml).
+/// Vehicles, trips and flows are read, each flow is expanded into its vehicles.
+/// Vehicles with a non numeric departure (such as "triggered") and random flows are left out.
+pub fn sumo_demand(net: &str, routes: &str) -> Result<Vec<SumoTrip>, SumoError> {
+    let net = SumoNet::parse(net)?;
+    let center = net.center();
+    let junctions: FastMap<&str, Vec2> = net
+        .junctions
+        .iter()
+        .map(|(id, pos)| (&**id, *pos - center))
+        .collect();
+    let edges: FastMap<&str, &SumoEdge> = net.edges.iter().map(|e| (&*e.id, e)).collect();
+
+    let ends = |first: &str, last: &str| -> Result<(Vec2, Vec2), SumoError> {
+        let (Some(first), Some(last)) = (edges.get(first), edges.get(last)) else {
+            return Err(SumoError::Format(
+                "route on an edge missing from the network",
+            ));
+        };
+        let (Some(&from), Some(&to)) = (junctions.get(&*first.from), junctions.get(&*last.to))
+        else {
+            return Err(SumoError::Format("edge between unknown junctions"));
+        };
+        Ok((from, to))
+    };
+    let route_ends = |route: &str| -> Result<(Vec2, Vec2), SumoError> {
+        let mut it = route.split_whitespace();
+        let Some(first) = it.next() else {
+            return Err(SumoError::Format("route without edges"));
+        };
+        ends(first, it.last().unwrap_or(first))
+    };
+
+    let mut named_routes: FastMap<&str, (Vec2, Vec2)> = FastMap::default();
+    // departures of the vehicle or flow whose route is given by the `route` tag that follows it
+    let mut pending: Option<Vec<f32>> = None;
+    let mut trips = vec![];
+
+    for tag in Tags::new(routes) {
+        let tag = tag?;
+        let departs = match tag.name {
+            "vehicle" | "trip" => tag.f32("depart").into_iter().collect(),
+            "flow" => flow_departs(&tag),
+            "route" => {
+                let Some(route) = tag.attr("edges") else {
+                    return Err(SumoError::Format("route without edges"));
+                };
+                let (from, to) = route_ends(route)?;
+                match (pending.take(), tag.attr("id")) {
+                    (Some(departs), _) => {
+                        trips.extend(departs.into_iter().map(|depart| SumoTrip {
+                            depart,
+                            from,
+                            to,
+                        }))
+                    }
+                    (None, Some(id)) => {
+                        named_routes.insert(id, (from, to));
+                    }
+                    (None, None) => {}
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        pending = None;
+
+        let (from, to) = match (tag.attr("route"), tag.attr("from"), tag.attr("to")) {
+            (Some(route), _, _) => match named_routes.get(route) {
+                Some(&found) => found,
+                None => return Err(SumoError::Format("vehicle on an unknown route")),
+            },
+            (None, Some(from), Some(to)) => ends(from, to)?,
+            // the route is the next tag
+            _ => {
+                pending = Some(departs);
+                continue;
+            }
+        };
+        trips.extend(
+            departs
+                .into_iter()
+                .map(|depart| SumoTrip { depart, from, to }),
+        );
+    }
+
+    if trips.is_empty() {
+        return Err(SumoError::NoTrips);
+    }
+    trips.sort_by(|a, b| a.depart.total_cmp(&b.depart));
+    Ok(trips)
+}
+
+pub fn sumo_demand_files(
+    net: impl AsRef<Path>,
+    routes: impl AsRef<Path>,
+) -> Result<Vec<SumoTrip>, SumoError> {
+    let read =
+        |path: &Path| std::fs::read_to_string(path).map_err(|e| SumoError::Io(e.to_string()));
+    sumo_demand(&read(net.as_ref())?, &read(routes.as_ref())?)
+}
+
+/// Departures of the vehicles of a flow, evenly spread between its begin and end
+fn flow_departs(tag: &Tag) -> Vec<f32> {
+    let begin = tag.f32("begin").unwrap_or(0.0);
+    let end = tag.f32("end").unwrap_or(begin + 3600.0);
+    let period = if let Some(number) = tag.f32("number") {
+        (end - begin) / number.max(1.0)
+    } else if let Some(period) = tag.f32("period") {
+        period
+    } else if let Some(per_hour) = tag.f32("vehsPerHour") {
+        3600.0 / per_hour
+    } else {
+        return vec![];
+    };
+    if period.is_nan() || period <= 0.0 {
+        return vec![];
+    }
+    let n = (((end - begin) / period).ceil() as usize).min(MAX_FLOW_VEHICLES);
+    (0..n).map(|i| begin + i as f32 * period).collect()
+}
+
+/// An opening or self closing xml tag with its attributes
+struct Tag<'a> {
+    name: &'a str,
+    attrs: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Tag<'a> {
+    fn attr(&self, key: &str) -> Option<&'a str> {
+        self.attrs.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+    }
+
+    fn f32(&self, key: &str) -> Option<f32> {
+        self.attr(key)?.parse().ok()
+    }
+}
+
+/// Iterates over the opening tags of an xml document, skipping the closing tags, comments and
+/// declarations. Enough for the flat documents SUMO writes, not a general xml parser.
+struct Tags<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Tags<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { rest: text }
+    }
+}
+
+impl<'a> Iterator for Tags<'a> {
+    type Item = Result<Tag<'a>, SumoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.rest.find('<')?;
+            self.rest = &self.rest[start + 1..];
+            if let Some(comment) = self.rest.strip_prefix("!--") {
+                let Some(end) = comment.find("-->") else {
+                    self.rest = "";
+                    return Some(Err(SumoError::Format("unclosed comment")));
+                };
+                self.rest = &comment[end + 3..];
+                continue;
+            }
+            let Some(end) = self.rest.find('>') else {
+                self.rest = "";
+                return Some(Err(SumoError::Format("unclosed tag")));
+            };
+            let body = &self.rest[..end];
+            self.rest = &self.rest[end + 1..];
+            if body.starts_with(['/', '?', '!']) {
+                continue;
+            }
+            return Some(parse_tag(body.trim_end_matches('/')));
+        }
+    }
+}
+
+fn parse_tag(body: &str) -> Result<Tag<'_>, SumoError> {
+    let body = body.trim();
+    let name_end = body.find(char::is_whitespace).unwrap_or(body.len());
+    let mut tag = Tag {
+        name: &body[..name_end],
+        attrs: vec![],
+    };
+    let mut rest = body[name_end..].trim_start();
+    while !rest.is_empty() {
+        let Some(eq) = rest.find('=') else {
+            return Err(SumoError::Format("attribute without value"));
+        };
+        let key = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_start();
+        let Some(quote) = value.chars().next().filter(|&c| c == '"' || c == '\'') else {
+            return Err(SumoError::Format("unquoted attribute"));
+        };
+        let Some(len) = value[1..].find(quote) else {
+            return Err(SumoError::Format("unclosed attribute"));
+        };
+        // ids, classes and numbers never need the entities to be unescaped
+        tag.attrs.push((key, &value[1..1 + len]));
+        rest = value[len + 2..].trim_start();
+    }
+    Ok(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+
+    const NET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- a two way street and a one way street -->
+<net version="1.16">
+    <location netOffset="0.00,0.00" convBoundary="0.00,0.00,200.00,0.00"/>
+    <edge id=":b_0" function="internal">
+        <lane id=":b_0_0" index="0" speed="10.00" length="5.00" shape="95,0 105,0"/>
+    </edge>
+    <edge id="ab" from="a" to="b" priority="-1">
+        <lane id="ab_0" index="0" allow="pedestrian" speed="2.00" length="100.00"/>
+        <lane id="ab_1" index="1" disallow="pedestrian" speed="13.89" length="100.00"/>
+    </edge>
+    <edge id="ba" from="b" to="a" priority="-1">
+        <lane id="ba_0" index="0" speed="13.89" length="100.00"/>
+    </edge>
+    <edge id="bc" from="b" to="c" priority="-1">
+        <lane id="bc_0" index="0" speed="8.33" length="100.00"/>
+        <lane id="bc_1" index="1" speed="8.33" length="100.00"/>
+    </edge>
+    <junction id="a" type="dead_end" x="0.00" y="0.00" incLanes="ba_0" intLanes=""/>
+    <junction id="b" type="priority" x="100.00" y="0.00" incLanes="ab_1" intLanes=":b_0_0"/>
+    <junction id="c" type="dead_end" x="200.00" y="0.00" incLanes="bc_0 bc_1" intLanes=""/>
+    <junction id=":b_0_0" type="internal" x="100.00" y="0.00" incLanes="" intLanes=""/>
+</net>
+"#;
+
+    #[test]
+    fn network_from_sumo() {
+        let network = OsmNetwork::from_sumo(NET).unwrap();
+
+        assert_eq!(network.nodes.len(), 3);
+        assert_eq!(network.nodes[0], vec2(-100.0, 0.0));
+        assert_eq!(network.roads.len(), 2);
+
+        let street = network.roads[0];
+        assert!(!street.one_way);
+        assert!(street.sidewalks);
+        assert_eq!(street.n_lanes, 1);
+
+        let one_way = network.roads[1];
+        assert!(one_way.one_way);
+        assert_eq!(one_way.n_lanes, 2);
+        assert!((one_way.speed_limit - 8.33).abs() < 0.01);
+
+        let mut map = Map::empty();
+        network.build(&mut map);
+        assert_eq!(map.roads().len(), 2);
+        assert_eq!(map.intersections().len(), 3);
+    }
+
+    #[test]
+    fn demand_from_sumo() {
+        let routes = r#"<routes>
+    <vType id="car" accel="2.6"/>
+    <route id="west" edges="bc ba"/>
+    <vehicle id="0" depart="10.00">
+        <route edges="ab bc"/>
+    </vehicle>
+    <vehicle id="1" depart="5.00" route="west"/>
+    <trip id="2" depart="triggered" from="ab" to="bc"/>
+    <flow id="f" begin="0" end="100" number="4" from="ba" to="ab"/>
+</routes>
+"#;
+        let trips = sumo_demand(NET, routes).unwrap();
+
+        let (a, b, c) = (vec2(-100.0, 0.0), vec2(0.0, 0.0), vec2(100.0, 0.0));
+        assert_eq!(trips.len(), 6);
+        assert_eq!(
+            trips.iter().map(|t| t.depart).collect::<Vec<_>>(),
+            vec![0.0, 5.0, 10.0, 25.0, 50.0, 75.0]
+        );
+        let trip = |depart| *trips.iter().find(|t| t.depart == depart).unwrap();
+        assert_eq!((trip(10.0).from, trip(10.0).to), (a, c));
+        assert_eq!((trip(5.0).from, trip(5.0).to), (b, a));
+        assert_eq!((trip(25.0).from, trip(25.0).to), (b, b));
+
+        assert!(matches!(
+            sumo_demand(
+                NET,
+                r#"<routes><trip depart="0" from="ab" to="zz"/></routes>"#
+            ),
+            Err(SumoError::Format(_))
+        ));
+        assert_eq!(
+            sumo_demand(NET, "<routes></routes>").err(),
+            Some(SumoError::NoTrips)
+        );
+    }
+
+    #[test]
+    fn invalid_sumo() {
+        assert!(matches!(
+            OsmNetwork::from_sumo("<net><edge id=\"a\" from=\"x\"></net>"),
+            Err(SumoError::Format(_))
+        ));
+        assert_eq!(
+            OsmNetwork::from_sumo("<net></net>").err(),
+            Some(SumoError::NoRoads)
+        );
+    }
+}
@@ -1,9 +1,10 @@
 use crate::map::{
-    BuildingID, Buildings, Environment, Intersections, Lanes, Lots, Map, ParkingSpots, Roads,
-    SpatialMap,
+    BuildingID, Buildings, BusStops, ContractionHierarchy, Decorations, Environment, Intersections,
+    LaneID, Lanes, Lots, Map, ParkingSpots, Roads, SpatialMap,
 };
 use crate::BuildingKind;
 use serde::{Deserialize, Serialize};
+use slotmapd::SecondaryMap;
 use std::collections::BTreeMap;
 
 #[derive(Default, Serialize, Deserialize)]
@@ -16,6 +17,10 @@ pub(crate) struct SerializedMap {
     pub lots: Lots,
     pub environment: Environment,
     pub bkinds: BTreeMap<BuildingKind, Vec<BuildingID>>,
+    pub decorations: Decorations,
+    pub bus_stops: BusStops,
+    pub fast_paths: ContractionHierarchy,
+    pub congested_lanes: SecondaryMap<LaneID, f32>,
 }
 
 impl From<&Map> for SerializedMap {
@@ -29,6 +34,10 @@ impl From<&Map> for SerializedMap {
             lots: m.lots.clone(),
             environment: m.environment.clone(),
             bkinds: m.bkinds.clone(),
+            decorations: m.decorations.clone(),
+            bus_stops: m.bus_stops.clone(),
+            fast_paths: m.fast_paths.clone(),
+            congested_lanes: m.congested_lanes.clone(),
         }
     }
 }
@@ -46,7 +55,14 @@ impl From<SerializedMap> for Map {
             parking: sel.parking,
             environment: sel.environment,
             bkinds: sel.bkinds,
+            decorations: sel.decorations,
+            bus_stops: sel.bus_stops,
             subscribers: Default::default(),
+            heavy_vehicle_bans: Default::default(),
+            edited_roads: Default::default(),
+            network_version: 0,
+            fast_paths: sel.fast_paths,
+            congested_lanes: sel.congested_lanes,
         }
     }
 }
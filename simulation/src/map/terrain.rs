@@ -17,6 +17,9 @@ const CELL_SIZE: f32 = TerrainChunkID::SIZE_F32 / TERRAIN_CHUNK_RESOLUTION as f3
 
 const TREE_GRID_SIZE: usize = 256;
 
+/// Height the water brush digs down to, the sea level being at 0
+pub const WATER_DEPTH: f32 = -10.0;
+
 pub type Chunk = geom::HeightmapChunk<TERRAIN_CHUNK_RESOLUTION, { TerrainChunkID::SIZE }>;
 pub type Heightmap = geom::Heightmap<TERRAIN_CHUNK_RESOLUTION, { TerrainChunkID::SIZE }>;
 
@@ -41,6 +44,8 @@ pub enum TerraformKind {
     Level,
     Slope,
     Erode,
+    /// Digs below the sea level so that the sea fills the hole
+    Water,
 }
 
 defer_serialize!(Environment, SerializedEnvironment);
@@ -142,6 +147,7 @@ impl Environment {
             .collect()
     }
 
+    /// Returns the chunks that were modified and the volume of earth moved in m³
     pub fn terraform(
         &mut self,
         tick: Tick,
@@ -151,6 +157,43 @@ impl Environment {
         amount: f32,
         level: f32,
         slope: Option<(Vec3, Vec3)>,
+    ) -> (Vec<TerrainChunkID>, f32) {
+        let bbox = AABB::centered(center, Vec2::splat(radius * 2.0));
+        let before = self.chunks_in(bbox.expand(CELL_SIZE));
+        let modified = self.terraform_inner(tick, kind, center, radius, amount, level, slope);
+
+        let volume = before
+            .iter()
+            .filter(|(id, _)| modified.contains(id))
+            .filter_map(|(id, old)| Some(volume_between(old, self.get_chunk(*id)?)))
+            .sum();
+        (modified, volume)
+    }
+
+    fn chunks_in(&self, bounds: AABB) -> Vec<(TerrainChunkID, Chunk)> {
+        let ll = TerrainChunkID::new(bounds.ll);
+        let ur = TerrainChunkID::new(bounds.ur);
+        let mut chunks = vec![];
+        for y in ll.1..=ur.1 {
+            for x in ll.0..=ur.0 {
+                let id = TerrainChunkID::new_i16(x, y);
+                if let Some(chunk) = self.get_chunk(id) {
+                    chunks.push((id, chunk.clone()));
+                }
+            }
+        }
+        chunks
+    }
+
+    fn terraform_inner(
+        &mut self,
+        tick: Tick,
+        kind: TerraformKind,
+        center: Vec2,
+        radius: f32,
+        amount: f32,
+        level: f32,
+        slope: Option<(Vec3, Vec3)>,
     ) -> Vec<TerrainChunkID> {
         let bbox = AABB::centered(center, Vec2::splat(radius * 2.0));
         match kind {
@@ -223,6 +266,14 @@ impl Environment {
                     .map(|(x, y)| TerrainChunkID::new_i16(x as i16, y as i16))
                     .collect()
             }
+            TerraformKind::Water => self.terrain_apply(bbox, |pos| {
+                let dist = pos.xy().distance(center) / radius;
+                if dist >= 1.0 || pos.z <= WATER_DEPTH {
+                    return pos.z;
+                }
+                let phi = (-1.0 / (1.0 - dist * dist)).exp();
+                (pos.z - (amount.abs() * UP_DT.as_secs_f32()) * phi).max(WATER_DEPTH)
+            }),
         }
     }
 
@@ -276,6 +327,18 @@ impl Environment {
     }
 }
 
+/// Volume in m³ between the heights of a chunk before and after it was terraformed
+fn volume_between(before: &Chunk, after: &Chunk) -> f32 {
+    let diff: f32 = before
+        .heights()
+        .iter()
+        .flatten()
+        .zip(after.heights().iter().flatten())
+        .map(|(a, b)| (a - b).abs())
+        .sum();
+    diff * CELL_SIZE * CELL_SIZE
+}
+
 impl Tree {
     pub fn new(pos: Vec2) -> Self {
         let crand = common::rand::rand3(pos.x, pos.y, 1.0);
@@ -357,3 +420,35 @@ impl From<&Environment> for SerializedEnvironment {
         t
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn water_brush_digs_and_reports_volume() {
+        let mut env = Environment::new(2, 2);
+        env.terrain_apply(env.bounds(), |_| 5.0);
+        let center = Vec2::splat(TerrainChunkID::SIZE_F32);
+
+        let mut total = 0.0;
+        for _ in 0..1000 {
+            let (modified, volume) = env.terraform(
+                Tick::default(),
+                TerraformKind::Water,
+                center,
+                100.0,
+                200.0,
+                0.0,
+                None,
+            );
+            assert!(!modified.is_empty());
+            total += volume;
+        }
+
+        let after = env.height(center).unwrap();
+        assert!(after < 0.0);
+        assert!(after >= WATER_DEPTH - 0.01);
+        assert!(total > 0.0);
+    }
+}
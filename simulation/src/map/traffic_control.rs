@@ -6,12 +6,17 @@ pub enum TrafficBehavior {
     ORANGE,
     GREEN,
     STOP,
+    YIELD,
 }
 
 impl TrafficBehavior {
     pub fn is_red(self) -> bool {
         matches!(self, TrafficBehavior::RED)
     }
+
+    pub fn is_green(self) -> bool {
+        matches!(self, TrafficBehavior::GREEN)
+    }
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
@@ -33,13 +38,49 @@ impl TrafficLightSchedule {
             offset,
         }
     }
+
+    /// Seconds since the start of the current cycle, which starts with the green light
+    fn cycle_time(&self, seconds: u32) -> u16 {
+        ((seconds % self.period as u32) as u16 + self.offset) % self.period
+    }
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub enum TrafficControl {
+    #[default]
     Always,
     Light(TrafficLightSchedule),
     StopSign,
+    /// Vehicles slow down and give way to the traffic already in the intersection
+    Yield,
+}
+
+/// Sign of an incoming road of an intersection without lights, replacing the control the
+/// [`crate::map::LightPolicy`] gives it
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApproachSign {
+    /// The other roads give way to this one
+    Priority,
+    /// Vehicles come to a halt then go once the priority roads are clear
+    Stop,
+    /// Vehicles slow down and give way to the priority roads
+    Yield,
+}
+
+impl ApproachSign {
+    pub const ALL: [ApproachSign; 3] = [
+        ApproachSign::Priority,
+        ApproachSign::Stop,
+        ApproachSign::Yield,
+    ];
+
+    pub fn control(self) -> TrafficControl {
+        match self {
+            ApproachSign::Priority => TrafficControl::Always,
+            ApproachSign::Stop => TrafficControl::StopSign,
+            ApproachSign::Yield => TrafficControl::Yield,
+        }
+    }
 }
 
 impl TrafficControl {
@@ -51,16 +92,30 @@ impl TrafficControl {
         matches!(self, TrafficControl::StopSign)
     }
 
+    pub fn is_yield(&self) -> bool {
+        matches!(self, TrafficControl::Yield)
+    }
+
     pub fn is_light(&self) -> bool {
         matches!(self, TrafficControl::Light(_))
     }
 
+    /// Seconds since the light turned green, None when it is not a green light
+    pub fn green_since(&self, seconds: u32) -> Option<u16> {
+        match self {
+            TrafficControl::Light(schedule) => {
+                let t = schedule.cycle_time(seconds);
+                (t < schedule.green).then_some(t)
+            }
+            _ => None,
+        }
+    }
+
     pub fn get_behavior(&self, seconds: u32) -> TrafficBehavior {
         match self {
             TrafficControl::Always => TrafficBehavior::GREEN,
             TrafficControl::Light(schedule) => {
-                let remainder =
-                    ((seconds % schedule.period as u32) as u16 + schedule.offset) % schedule.period;
+                let remainder = schedule.cycle_time(seconds);
                 if remainder < schedule.green {
                     TrafficBehavior::GREEN
                 } else if remainder < schedule.green + schedule.orange {
@@ -70,6 +125,7 @@ impl TrafficControl {
                 }
             }
             TrafficControl::StopSign => TrafficBehavior::STOP,
+            TrafficControl::Yield => TrafficBehavior::YIELD,
         }
     }
 }
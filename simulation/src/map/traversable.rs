@@ -64,6 +64,24 @@ impl Traversable {
         }
     }
 
+    /// Whether the traversable can be entered, only crosswalks waiting for the walk signal
+    /// can't be entered
+    pub fn can_enter(&self, time: u32, map: &Map) -> bool {
+        match self.kind {
+            TraverseKind::Lane(_) => true,
+            TraverseKind::Turn(id) => {
+                let Some(turn) = map
+                    .intersections
+                    .get(id.parent)
+                    .and_then(|i| i.find_turn(id))
+                else {
+                    return true;
+                };
+                turn.control.get_behavior(time).is_green()
+            }
+        }
+    }
+
     pub fn destination_intersection(&self, lanes: &Lanes) -> Option<IntersectionID> {
         Some(match self.kind {
             TraverseKind::Lane(p) => match self.dir {
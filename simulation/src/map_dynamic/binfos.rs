@@ -1,13 +1,81 @@
-use crate::map::BuildingID;
+use crate::map::{Building, BuildingID, BuildingKind};
+use crate::souls::goods_company::GoodsCompanyRegistry;
 use crate::SoulID;
 use serde::{Deserialize, Serialize};
 use slotmapd::SecondaryMap;
 use std::collections::BTreeMap;
 
+/// How many people can be assigned to a building, derived from the archetype of houses and
+/// from the company description of workplaces
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Capacity {
+    pub residents: u32,
+    pub workers: u32,
+}
+
+impl Capacity {
+    pub fn of(building: &Building, registry: &GoodsCompanyRegistry) -> Self {
+        match building.kind {
+            BuildingKind::House => Self {
+                residents: building.housing,
+                workers: 0,
+            },
+            BuildingKind::GoodsCompany(id) => Self {
+                residents: 0,
+                workers: registry
+                    .descriptions
+                    .get(id)
+                    .map_or(0, |d| d.n_workers.max(0) as u32),
+            },
+            _ => Self::default(),
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.residents + self.workers
+    }
+}
+
 #[derive(Clone, Default, Serialize, Deserialize, Debug)]
 pub struct BuildingInfo {
     pub owner: Option<SoulID>,
     pub inside: Vec<SoulID>,
+    /// People living in the house, the first one is the owner
+    pub residents: Vec<SoulID>,
+    /// Number of people working in the building
+    pub workers: u32,
+    pub capacity: Capacity,
+}
+
+impl BuildingInfo {
+    pub fn n_residents(&self) -> usize {
+        self.residents.len()
+    }
+
+    /// Number of people assigned to the building, residents and workers
+    pub fn headcount(&self) -> u32 {
+        self.n_residents() as u32 + self.workers
+    }
+
+    /// Headcount over capacity, above 1 when the building is overcrowded.
+    /// `None` for buildings nobody can be assigned to
+    pub fn occupancy(&self) -> Option<f32> {
+        let capacity = self.capacity.total();
+        (capacity > 0).then(|| self.headcount() as f32 / capacity as f32)
+    }
+
+    /// In [0; +inf) range, share of the capacity the building is over
+    pub fn overcrowding(&self) -> f32 {
+        self.occupancy().map_or(0.0, |o| (o - 1.0).max(0.0))
+    }
+
+    pub fn vacant_homes(&self) -> usize {
+        (self.capacity.residents as usize).saturating_sub(self.n_residents())
+    }
+
+    pub fn vacant_jobs(&self) -> u32 {
+        self.capacity.workers.saturating_sub(self.workers)
+    }
 }
 
 #[derive(Clone, Default, Serialize, Deserialize, Debug)]
@@ -17,8 +85,14 @@ pub struct BuildingInfos {
 }
 
 impl BuildingInfos {
-    pub fn insert(&mut self, building: BuildingID) {
-        self.assignment.insert(building, BuildingInfo::default());
+    pub fn insert(&mut self, building: &Building, registry: &GoodsCompanyRegistry) {
+        self.assignment.insert(
+            building.id,
+            BuildingInfo {
+                capacity: Capacity::of(building, registry),
+                ..Default::default()
+            },
+        );
     }
 
     pub fn get(&self, building: BuildingID) -> Option<&BuildingInfo> {
@@ -40,6 +114,50 @@ impl BuildingInfos {
         self.owners.insert(soul, building);
     }
 
+    /// Updates the capacity and the number of workers of the building
+    pub fn set_occupancy(&mut self, building: BuildingID, capacity: Capacity, workers: u32) {
+        if let Some(b) = self.get_mut(building) {
+            b.capacity = capacity;
+            b.workers = workers;
+        }
+    }
+
+    /// Adds a resident to the house, the first one becomes the owner.
+    /// Full houses refuse the resident, returns whether it was added
+    pub fn add_resident(&mut self, building: BuildingID, soul: SoulID) -> bool {
+        let b = unwrap_or!(self.get_mut(building), return false);
+        if b.vacant_homes() == 0 {
+            return false;
+        }
+        b.residents.push(soul);
+        if b.owner.is_none() {
+            b.owner = Some(soul);
+        }
+        self.owners.insert(soul, building);
+        true
+    }
+
+    /// Removes a resident from the house, the next one becomes the owner
+    pub fn remove_resident(&mut self, building: BuildingID, soul: SoulID) {
+        self.owners.remove(&soul);
+        let b = unwrap_ret!(self.get_mut(building));
+        b.residents.retain(|&r| r != soul);
+        if b.owner == Some(soul) {
+            b.owner = b.residents.first().copied();
+        }
+    }
+
+    /// Counts a new worker of the building, full workplaces refuse them.
+    /// Returns whether the worker was hired
+    pub fn add_worker(&mut self, building: BuildingID) -> bool {
+        let b = unwrap_or!(self.get_mut(building), return false);
+        if b.vacant_jobs() == 0 {
+            return false;
+        }
+        b.workers += 1;
+        true
+    }
+
     pub fn owner(&self, building: BuildingID) -> Option<SoulID> {
         self.assignment.get(building).and_then(|x| x.owner)
     }
@@ -70,3 +188,39 @@ impl BuildingInfos {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use crate::world::HumanID;
+    use geom::{vec2, vec3};
+
+    #[test]
+    fn capacity_limits() {
+        let test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(150.0, 20.0));
+        let soul = |i: u64| SoulID::Human(HumanID::from(slotmapd::KeyData::from_ffi(i)));
+
+        let mut binfos = test.g.write::<BuildingInfos>();
+        let capacity = Capacity {
+            residents: 2,
+            workers: 0,
+        };
+        binfos.set_occupancy(house, capacity, 0);
+        assert!(binfos.add_resident(house, soul(1)));
+        assert!(binfos.add_resident(house, soul(2)));
+        assert!(!binfos.add_resident(house, soul(3)));
+        assert!(!binfos.add_worker(house));
+        assert_eq!(binfos.get(house).unwrap().occupancy(), Some(1.0));
+
+        // the house got smaller, its residents are now crowded
+        let capacity = Capacity {
+            residents: 1,
+            workers: 0,
+        };
+        binfos.set_occupancy(house, capacity, 0);
+        assert_eq!(binfos.get(house).unwrap().overcrowding(), 1.0);
+    }
+}
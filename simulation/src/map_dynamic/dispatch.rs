@@ -87,9 +87,14 @@ impl Dispatcher {
             .entry(DispatchKind::FreightTrain)
             .or_insert_with(|| DispatchOne::new(DispatchKind::FreightTrain.lane_kind()));
 
-        world.trains.iter().for_each(|(ent, train)| {
-            disp_trains.register(DispatchID::FreightTrain(ent), map, train.trans.position);
-        });
+        // metro trains only serve their line
+        world
+            .trains
+            .iter()
+            .filter(|(_, train)| train.metro.is_none())
+            .for_each(|(ent, train)| {
+                disp_trains.register(DispatchID::FreightTrain(ent), map, train.trans.position);
+            });
 
         /*
         let disp_trucks = self
@@ -152,7 +157,7 @@ impl DispatchOne {
         let ent = self.positions.entry(id);
 
         let lanekind = self.lanekind;
-        let find_lane = move || map.nearest_lane(pos, lanekind, Some(50.0));
+        let find_lane = move || nearest_lane(map, lanekind, pos);
 
         match ent {
             Entry::Vacant(v) => {
@@ -234,7 +239,7 @@ impl DispatchOne {
 
         let target_lane = match target {
             DispatchQueryTarget::Pos(pos) => {
-                let lid = map.nearest_lane(pos, kind.lane_kind(), Some(50.0))?;
+                let lid = nearest_lane(map, kind.lane_kind(), pos)?;
                 let lane = map.lanes().get(lid)?;
                 let proj = lane.points.project(pos);
                 start_along = lane.points.length_at_proj(proj);
@@ -288,7 +293,16 @@ impl DispatchOne {
     }
 }
 
+/// The lane of the kind closest to the position, the trucks also drive on the tram tracks
+fn nearest_lane(map: &Map, kind: LaneKind, pos: Vec3) -> Option<LaneID> {
+    if kind.is_driving() {
+        return map.nearest_driving_lane(pos, Some(50.0));
+    }
+    map.nearest_lane(pos, kind, Some(50.0))
+}
+
 pub fn dispatch_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("map_dynamic::dispatch_system");
     let mut dispatcher = resources.write::<Dispatcher>();
     let map = resources.read::<Map>();
     dispatcher.update(&map, world);
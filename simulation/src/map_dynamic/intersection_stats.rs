@@ -0,0 +1,129 @@
+use crate::map::{IntersectionID, Map, TurnID, TurnKind};
+use serde::{Deserialize, Serialize};
+use slotmapd::SecondaryMap;
+use std::collections::BTreeMap;
+
+/// Vehicles slower than this while waiting to enter an intersection are delayed by it, in m/s
+pub const STOPPED_SPEED: f32 = 1.0;
+
+/// Traffic going through an intersection, counted over the current hour and kept for the last one
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct IntersectionStat {
+    cur_volumes: BTreeMap<TurnID, u32>,
+    /// Seconds spent stopped by the vehicles waiting to enter the intersection
+    cur_delay: f32,
+    volumes: BTreeMap<TurnID, u32>,
+    delay: f32,
+}
+
+impl IntersectionStat {
+    /// Number of vehicles that took each turn during the last hour
+    pub fn turn_volumes(&self) -> &BTreeMap<TurnID, u32> {
+        &self.volumes
+    }
+
+    /// Number of vehicles that went through the intersection during the last hour
+    pub fn volume(&self) -> u32 {
+        self.volumes.values().sum()
+    }
+
+    /// Average seconds a vehicle waited before entering the intersection during the last hour.
+    /// Returns `None` if no vehicle went through
+    pub fn avg_delay(&self) -> Option<f32> {
+        let volume = self.volume();
+        (volume > 0).then(|| self.delay / volume as f32)
+    }
+}
+
+/// IntersectionStats measures the vehicle traffic of every intersection: how many vehicles take
+/// each turn per hour and how long they wait before entering, to tune the turns and signals.
+#[derive(Default, Serialize, Deserialize)]
+pub struct IntersectionStats {
+    stats: SecondaryMap<IntersectionID, IntersectionStat>,
+}
+
+impl IntersectionStats {
+    pub fn get(&self, id: IntersectionID) -> Option<&IntersectionStat> {
+        self.stats.get(id)
+    }
+
+    fn stat_mut(&mut self, id: IntersectionID) -> Option<&mut IntersectionStat> {
+        if !self.stats.contains_key(id) {
+            self.stats.insert(id, IntersectionStat::default());
+        }
+        self.stats.get_mut(id)
+    }
+
+    /// Records a vehicle entering the turn, walking turns are ignored
+    pub fn record_turn(&mut self, map: &Map, turn: TurnID) {
+        if !is_driving(map, turn) {
+            return;
+        }
+        if let Some(stat) = self.stat_mut(turn.parent) {
+            *stat.cur_volumes.entry(turn).or_default() += 1;
+        }
+    }
+
+    /// Records a vehicle stopped for the given seconds while waiting to take the turn
+    pub fn record_delay(&mut self, map: &Map, turn: TurnID, seconds: f32) {
+        if !is_driving(map, turn) {
+            return;
+        }
+        if let Some(stat) = self.stat_mut(turn.parent) {
+            stat.cur_delay += seconds;
+        }
+    }
+
+    /// Starts a new hour, what was counted during the current one becomes the last hour
+    pub fn next_hour(&mut self, map: &Map) {
+        self.stats
+            .retain(|id, _| map.intersections().contains_key(id));
+        for stat in self.stats.values_mut() {
+            stat.volumes = std::mem::take(&mut stat.cur_volumes);
+            stat.delay = std::mem::take(&mut stat.cur_delay);
+        }
+    }
+}
+
+fn is_driving(map: &Map, turn: TurnID) -> bool {
+    map.intersections()
+        .get(turn.parent)
+        .and_then(|inter| inter.find_turn(turn))
+        .map_or(false, |t| t.kind == TurnKind::Driving)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use geom::vec3;
+
+    #[test]
+    fn volumes_and_delay() {
+        let test = TestCtx::new();
+        test.build_roads(&[
+            vec3(0.0, 0.0, 0.0),
+            vec3(100.0, 0.0, 0.0),
+            vec3(100.0, 100.0, 0.0),
+        ]);
+        let map = test.g.map();
+        let turn = map
+            .intersections()
+            .values()
+            .flat_map(|i| i.turns())
+            .find(|t| t.kind == TurnKind::Driving)
+            .unwrap()
+            .id;
+
+        let mut stats = IntersectionStats::default();
+        stats.record_turn(&map, turn);
+        stats.record_turn(&map, turn);
+        stats.record_delay(&map, turn, 10.0);
+        assert_eq!(stats.get(turn.parent).unwrap().volume(), 0);
+
+        stats.next_hour(&map);
+        let stat = stats.get(turn.parent).unwrap();
+        assert_eq!(stat.turn_volumes().get(&turn), Some(&2));
+        assert_eq!(stat.avg_delay(), Some(5.0));
+    }
+}
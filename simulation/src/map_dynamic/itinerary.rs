@@ -1,12 +1,17 @@
-use crate::map::{Map, PathKind, Pathfinder, Traversable, TraverseDirection, TraverseKind};
+use crate::map::{
+    LaneID, Map, PathKind, PathQuery, Pathfinder, RoadID, Traversable, TraverseDirection,
+    TraverseKind,
+};
+use crate::map_dynamic::{IntersectionStats, TrafficFlow, TripStats, STOPPED_SPEED};
 use crate::utils::resources::Resources;
-use crate::utils::time::{GameTime, Tick};
-use crate::world::TrainID;
+use crate::utils::time::{GameTime, Tick, SECONDS_PER_HOUR};
+use crate::world::{AnyEntity, TrainID};
 use crate::World;
 use egui_inspect::egui::Ui;
 use egui_inspect::{Inspect, InspectArgs};
 use geom::{Follower, Polyline3Queue, Transform, Vec3};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 
 #[derive(Inspect, Debug, Serialize, Deserialize)]
 pub struct ItineraryFollower {
@@ -48,6 +53,12 @@ pub struct Route {
     pub reversed_route: Vec<Traversable>,
     pub end_pos: Vec3,
     pub cur: Traversable,
+    /// The query that produced this route, used to debug pathfinding
+    #[inspect(skip)]
+    pub query: PathQuery,
+    /// When the current lane was entered from a turn, to measure the traffic flow
+    #[inspect(skip)]
+    pub lane_entered: Option<f64>,
 }
 
 pub const OBJECTIVE_OK_DIST: f32 = 3.0;
@@ -103,6 +114,12 @@ impl Itinerary {
         let end_lane = pathkind.nearest_lane(map, end)?;
 
         let mut cur = Traversable::new(TraverseKind::Lane(start_lane), TraverseDirection::Forward);
+        let query = PathQuery {
+            kind: pathkind,
+            tick,
+            start: cur,
+            end: end_lane,
+        };
 
         if start_lane == end_lane {
             if let Some(mut p) = pathkind.local_route(map, start_lane, start, end) {
@@ -113,6 +130,8 @@ impl Itinerary {
                             reversed_route: vec![],
                             end_pos: end,
                             cur,
+                            query,
+                            lane_entered: None,
                         },
                         pathkind,
                     ),
@@ -121,11 +140,13 @@ impl Itinerary {
             }
         }
 
-        let mut reversed_route: Vec<Traversable> = pathkind
-            .path(map, tick, cur, end_lane)?
-            .into_iter()
-            .rev()
-            .collect();
+        let path = match pathkind {
+            PathKind::Vehicle => map
+                .shortest_path_fast(tick, cur, end_lane)
+                .or_else(|| pathkind.path(map, tick, cur, end_lane)),
+            _ => pathkind.path(map, tick, cur, end_lane),
+        };
+        let mut reversed_route: Vec<Traversable> = path?.into_iter().rev().collect();
 
         reversed_route.pop(); // Remove start
 
@@ -145,6 +166,8 @@ impl Itinerary {
                 reversed_route,
                 end_pos: end,
                 cur,
+                query,
+                lane_entered: None,
             },
             pathkind,
         );
@@ -160,7 +183,7 @@ impl Itinerary {
             kind,
             reversed_local_path: points,
         };
-        if matches!(pathkind, PathKind::Rail) {
+        if matches!(pathkind, PathKind::Rail | PathKind::ElectricRail) {
             return Some(it);
         }
 
@@ -225,7 +248,9 @@ impl Itinerary {
                     return p;
                 });
 
-                if k.can_pass(time, map.lanes()) {
+                let can_enter_next = self.next_travers().map_or(true, |n| n.can_enter(time, map));
+
+                if can_enter_next && k.can_pass(time, map.lanes()) {
                     self.advance(map, position);
                     continue;
                 }
@@ -326,6 +351,11 @@ impl Itinerary {
         }
     }
 
+    /// The traversable after the current one
+    pub fn next_travers(&self) -> Option<&Traversable> {
+        self.get_route()?.reversed_route.last()
+    }
+
     pub fn get_route(&self) -> Option<&Route> {
         match &self.kind {
             ItineraryKind::Route(r, _) => Some(r),
@@ -355,6 +385,13 @@ impl Itinerary {
         }
     }
 
+    /// Drops the current route and replans it from the current position on the next update
+    pub fn invalidate(&mut self) {
+        if let ItineraryKind::Route(ref r, kind) = self.kind {
+            *self = Self::wait_for_reroute(kind, r.end_pos);
+        }
+    }
+
     pub fn is_none_or_wait(&self) -> bool {
         matches!(self.kind, ItineraryKind::None | ItineraryKind::WaitUntil(_))
     }
@@ -364,6 +401,99 @@ impl Itinerary {
     }
 }
 
+impl Route {
+    /// To be called when the current traversable changed, returns the lane that was left with
+    /// the seconds it took to drive through it
+    fn track_lane(&mut self, left: Option<Traversable>, timestamp: f64) -> Option<(LaneID, f32)> {
+        let entered = if matches!(self.cur.kind, TraverseKind::Lane(_)) {
+            self.lane_entered.replace(timestamp)
+        } else {
+            self.lane_entered.take()
+        };
+        let TraverseKind::Lane(lane) = left?.kind else {
+            return None;
+        };
+        Some((lane, (timestamp - entered?) as f32))
+    }
+
+    /// Roads the remaining route goes through, turns count for the roads they connect
+    fn roads(&self, map: &Map) -> BTreeSet<RoadID> {
+        let parent = |lane| map.lanes.get(lane).map(|l| l.parent);
+        std::iter::once(&self.cur)
+            .chain(self.reversed_route.iter())
+            .flat_map(|t| match t.kind {
+                TraverseKind::Lane(id) => [parent(id), None],
+                TraverseKind::Turn(id) => [parent(id.src), parent(id.dst)],
+            })
+            .flatten()
+            .collect()
+    }
+}
+
+/// Index from roads to the entities routed through them, so that the itineraries going through
+/// a removed or modified road are replanned right away instead of following lanes that no
+/// longer exist. It is rebuilt from the itineraries so it doesn't need to be serialized.
+#[derive(Default)]
+pub struct ItineraryIndex {
+    by_road: BTreeMap<RoadID, BTreeSet<AnyEntity>>,
+    routes: BTreeMap<AnyEntity, (PathQuery, BTreeSet<RoadID>)>,
+}
+
+impl ItineraryIndex {
+    fn insert(&mut self, id: AnyEntity, route: &Route, map: &Map) {
+        self.remove(id);
+        let roads = route.roads(map);
+        for &road in &roads {
+            self.by_road.entry(road).or_default().insert(id);
+        }
+        self.routes.insert(id, (route.query, roads));
+    }
+
+    fn remove(&mut self, id: AnyEntity) {
+        let Some((_, roads)) = self.routes.remove(&id) else {
+            return;
+        };
+        for road in roads {
+            let Some(ents) = self.by_road.get_mut(&road) else {
+                continue;
+            };
+            ents.remove(&id);
+            if ents.is_empty() {
+                self.by_road.remove(&road);
+            }
+        }
+    }
+
+    /// Entities whose current route goes through the road
+    pub fn routed_through(&self, road: RoadID) -> impl Iterator<Item = AnyEntity> + '_ {
+        self.by_road.get(&road).into_iter().flatten().copied()
+    }
+
+    /// Registers the new routes and forgets the ones that ended or were replaced
+    fn update(&mut self, world: &World, map: &Map) {
+        let mut seen = BTreeSet::new();
+        for (id, (_, it)) in world.query_trans_itin() {
+            let Some(route) = it.get_route() else {
+                continue;
+            };
+            seen.insert(id);
+            if self.routes.get(&id).map(|(q, _)| *q) != Some(route.query) {
+                self.insert(id, route, map);
+            }
+        }
+
+        let stale: Vec<_> = self
+            .routes
+            .keys()
+            .filter(|id| !seen.contains(id))
+            .copied()
+            .collect();
+        for id in stale {
+            self.remove(id);
+        }
+    }
+}
+
 impl Inspect<ItineraryKind> for ItineraryKind {
     fn render(d: &ItineraryKind, label: &'static str, ui: &mut Ui, args: &InspectArgs) {
         match *d {
@@ -417,9 +547,15 @@ pub fn itinerary_update(world: &mut World, resources: &mut Resources) {
     let time = &*resources.read::<GameTime>();
     let map = &*resources.read::<Map>();
     let tick = *resources.read::<Tick>();
+    let mut finished = vec![];
+    let mut entered_turns = vec![];
+    let mut waiting_turns = vec![];
+    let mut lanes_driven = vec![];
 
     world.query_it_trans_speed().for_each(
         |(it, trans, speed): (&mut Itinerary, &mut Transform, f32)| {
+            let was_running = !it.has_ended(time.timestamp);
+            let was_on = it.get_travers().copied();
             trans.position = it.update(
                 trans.position,
                 speed * time.realdelta,
@@ -427,9 +563,61 @@ pub fn itinerary_update(world: &mut World, resources: &mut Resources) {
                 time.seconds,
                 map,
             );
+            if was_running && it.has_ended(time.timestamp) {
+                if let Some(r) = it.get_route() {
+                    finished.push((r.query, r.end_pos));
+                }
+            }
+
+            let Some(&on) = it.get_travers() else {
+                return;
+            };
+            if Some(on) != was_on {
+                if let TraverseKind::Turn(turn) = on.kind {
+                    entered_turns.push(turn);
+                }
+                if let ItineraryKind::Route(ref mut r, _) = it.kind {
+                    lanes_driven.extend(r.track_lane(was_on, time.timestamp));
+                }
+            } else if speed < STOPPED_SPEED {
+                if let Some(&Traversable {
+                    kind: TraverseKind::Turn(turn),
+                    ..
+                }) = it.next_travers()
+                {
+                    waiting_turns.push(turn);
+                }
+            }
         },
     );
 
+    {
+        let mut istats = resources.write::<IntersectionStats>();
+        for turn in entered_turns {
+            istats.record_turn(map, turn);
+        }
+        for turn in waiting_turns {
+            istats.record_delay(map, turn, time.realdelta);
+        }
+        if time.tick(SECONDS_PER_HOUR as u32) {
+            istats.next_hour(map);
+        }
+    }
+
+    if !lanes_driven.is_empty() {
+        let mut flow = resources.write::<TrafficFlow>();
+        for (lane, seconds) in lanes_driven {
+            flow.record(map, lane, seconds);
+        }
+    }
+
+    if !finished.is_empty() {
+        let mut trips = resources.write::<TripStats>();
+        for (query, end) in finished {
+            trips.record(map, &query, end);
+        }
+    }
+
     world.trains.values_mut().for_each(|train| {
         train.leader.past.push(train.trans.position);
     });
@@ -442,3 +630,142 @@ pub fn itinerary_update(world: &mut World, resources: &mut Resources) {
         wagon.trans.dir = (dir + dir2).try_normalize().unwrap_or(dir);
     });
 }
+
+/// Replans the itineraries going through the roads edited since the last tick, continues the
+/// rebuild of the contraction hierarchy used by the car pathfinding and gives it the congested
+/// lanes every minute.
+/// Runs at the end of the tick so that the routes computed during the tick are indexed before
+/// the next map edits.
+pub fn itinerary_invalidation(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("map_dynamic::itinerary_invalidation");
+    let mut map = resources.write::<Map>();
+    let index = &mut *resources.write::<ItineraryIndex>();
+
+    let edited = std::mem::take(&mut map.edited_roads);
+    for road in edited {
+        let routed: Vec<_> = index.routed_through(road).collect();
+        for id in routed {
+            let indexed = index.routes.get(&id).map(|(q, _)| q);
+            if let Some(it) = world.it_any_mut(id) {
+                // the route might already have been replaced during this tick
+                if it.get_route().map(|r| &r.query) == indexed {
+                    it.invalidate();
+                }
+            }
+            index.remove(id);
+        }
+    }
+
+    index.update(world, &map);
+    map.update_fast_paths();
+
+    if resources.read::<GameTime>().tick(60) {
+        let mut flow = resources.write::<TrafficFlow>();
+        flow.relax(&map);
+        // Pathfinding only has access to the map, so the congested lanes are copied there
+        map.set_congested_lanes(flow.congested(&map));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use crate::transportation::{spawn_parked_vehicle, unpark, VehicleKind};
+    use crate::{VehicleID, WorldCommand};
+    use geom::vec3;
+
+    /// Unparks a car near `from` and sends it to `to`
+    fn drive(test: &mut TestCtx, from: Vec3, to: Vec3) -> VehicleID {
+        let car = spawn_parked_vehicle(&mut test.g, VehicleKind::Car, from).unwrap();
+        unpark(&mut test.g, car);
+        let start = test.g.world().vehicles[car].trans.position;
+        let it = Itinerary::route(Tick(0), start, to, &test.g.map(), PathKind::Vehicle).unwrap();
+        test.g.world_mut_unchecked().vehicles[car].it = it;
+        car
+    }
+
+    fn assert_route_exists(test: &TestCtx, car: VehicleID) {
+        let map = test.g.map();
+        let it = &test.g.world().vehicles[car].it;
+        if let Some(r) = it.get_route() {
+            for t in std::iter::once(&r.cur).chain(&r.reversed_route) {
+                assert!(
+                    t.points(&map).is_some(),
+                    "route goes through a removed road"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bulldozed_roads_invalidate_itineraries() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[
+            vec3(0.0, 0.0, 0.0),
+            vec3(150.0, 0.0, 0.0),
+            vec3(300.0, 0.0, 0.0),
+        ]);
+
+        let car = drive(&mut test, Vec3::ZERO, vec3(290.0, 0.0, 0.0));
+        test.tick();
+
+        let far = test
+            .g
+            .map()
+            .roads()
+            .values()
+            .find(|r| r.points.first().x > 100.0 || r.points.last().x > 200.0)
+            .unwrap()
+            .id;
+        let routed: Vec<_> = test
+            .g
+            .read::<ItineraryIndex>()
+            .routed_through(far)
+            .collect();
+        assert_eq!(routed, vec![AnyEntity::VehicleID(car)]);
+
+        test.apply(&[WorldCommand::MapRemoveRoad(far)]);
+        test.tick();
+
+        assert_route_exists(&test, car);
+    }
+
+    #[test]
+    fn split_roads_invalidate_itineraries() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+        test.build_roads(&[vec3(0.0, 100.0, 0.0), vec3(300.0, 100.0, 0.0)]);
+        let split: Vec<RoadID> = test.g.map().roads().keys().collect();
+
+        let cars = [
+            drive(&mut test, Vec3::ZERO, vec3(290.0, 0.0, 0.0)),
+            drive(&mut test, vec3(0.0, 100.0, 0.0), vec3(290.0, 100.0, 0.0)),
+        ];
+        test.tick();
+        for &road in &split {
+            assert_eq!(
+                test.g.read::<ItineraryIndex>().routed_through(road).count(),
+                1
+            );
+        }
+
+        // the new road splits both roads in halves
+        test.build_roads(&[vec3(150.0, 0.0, 0.0), vec3(150.0, 100.0, 0.0)]);
+        assert_eq!(test.g.map().roads().len(), 5);
+        for road in &split {
+            assert!(test.g.map().edited_roads.contains(road));
+        }
+        test.tick();
+
+        for road in split {
+            assert_eq!(
+                test.g.read::<ItineraryIndex>().routed_through(road).count(),
+                0
+            );
+        }
+        for car in cars {
+            assert_route_exists(&test, car);
+        }
+    }
+}
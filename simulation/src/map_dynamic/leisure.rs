@@ -0,0 +1,382 @@
+//! Leisure venues are the restaurants, bars and cinemas where people spend their evenings out,
+//! see [`crate::souls::desire::GoOut`]. Only so many people fit in a venue at once, the others
+//! queue at the door.
+//! The visitors pay the evening from the pocket money they put aside from their wages every day,
+//! the venue's company gets the payment minus the city's tax. Venues producing goods serve one of
+//! them to every visitor, the bars their drinks and the restaurants their meals, and can't let
+//! people in once they ran out.
+
+use crate::economy::{Government, ItemID, Market, Money};
+use crate::map::{BuildingID, Map};
+use crate::souls::goods_company::GoodsCompanyRegistry;
+use crate::utils::resources::Resources;
+use crate::utils::time::{DayTime, GameInstant, GameTime, RecTimeInterval, SECONDS_PER_HOUR};
+use crate::world::{CompanyID, HumanID};
+use crate::{Simulation, SoulID, World};
+use common::descriptions::OpeningHours;
+use geom::Vec3;
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// How long people stay at a venue, in seconds
+pub const VISIT_DURATION: f64 = 2.0 * SECONDS_PER_HOUR as f64;
+/// People give up queuing after this long, in seconds
+pub const MAX_QUEUE_WAIT: f64 = 0.5 * SECONDS_PER_HOUR as f64;
+/// Share of what the visitors pay going to the city, in percent
+pub const LEISURE_TAX_PERCENT: i64 = 10;
+/// People don't go to venues further than this, in meters
+const MAX_VENUE_DIST: f32 = 3000.0;
+/// How much further people are ready to go to save a buck, in meters
+const DIST_PER_BUCK: f32 = 50.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeisureVenue {
+    pub company: CompanyID,
+    pub door: Vec3,
+    pub capacity: u32,
+    /// What an evening costs to the visitors
+    pub price: Money,
+    /// The goods served to every visitor, the first product of the company
+    pub serves: Option<ItemID>,
+    /// Always open if not set
+    pub opening_hours: Option<OpeningHours>,
+    /// The people inside, with when they got in
+    pub visitors: BTreeMap<HumanID, GameInstant>,
+    /// The people waiting at the door, with when they arrived
+    pub queue: VecDeque<(HumanID, GameInstant)>,
+}
+
+impl LeisureVenue {
+    pub fn is_open(&self, t: DayTime) -> bool {
+        self.opening_hours.map_or(true, |h| {
+            RecTimeInterval::new((h.open, 0), (h.close, 0)).dist_until(t) == 0
+        })
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.visitors.len() >= self.capacity as usize
+    }
+
+    /// Lets the people queuing in while there is room and something left to serve them.
+    /// The people who can't pay anymore are turned away.
+    fn let_in(
+        &mut self,
+        world: &mut World,
+        market: &mut Market,
+        gov: &mut Government,
+        now: GameInstant,
+    ) {
+        let soul = SoulID::GoodsCompany(self.company);
+        while !self.is_full() {
+            let Some(&(h, _)) = self.queue.front() else {
+                break;
+            };
+            if let Some(item) = self.serves {
+                if market.capital(soul, item) <= 0 {
+                    break;
+                }
+            }
+            self.queue.pop_front();
+
+            let Some(human) = world.humans.get_mut(h) else {
+                continue;
+            };
+            if human.go_out.wallet < self.price {
+                continue;
+            }
+            human.go_out.wallet -= self.price;
+            if let Some(item) = self.serves {
+                market.produce(soul, item, -1);
+            }
+            let tax = self.price * LEISURE_TAX_PERCENT / 100;
+            gov.money += tax;
+            if let Some(c) = world.companies.get_mut(self.company) {
+                c.comp.money += self.price - tax;
+            }
+            self.visitors.insert(h, now);
+        }
+    }
+}
+
+/// LeisureVenues keeps track of the people in and queuing at the leisure venues, the companies
+/// whose description has a [`common::descriptions::LeisureDescription`]
+#[derive(Default, Serialize, Deserialize)]
+pub struct LeisureVenues {
+    venues: BTreeMap<BuildingID, LeisureVenue>,
+    /// Day the pocket money was last given
+    payday: i32,
+}
+
+impl LeisureVenues {
+    pub fn venue(&self, b: BuildingID) -> Option<&LeisureVenue> {
+        self.venues.get(&b)
+    }
+
+    pub fn is_open(&self, b: BuildingID, t: DayTime) -> bool {
+        self.venues.get(&b).map_or(false, |v| v.is_open(t))
+    }
+
+    pub fn is_inside(&self, b: BuildingID, h: HumanID) -> bool {
+        self.venues
+            .get(&b)
+            .map_or(false, |v| v.visitors.contains_key(&h))
+    }
+
+    pub fn is_queuing(&self, b: BuildingID, h: HumanID) -> bool {
+        self.venues
+            .get(&b)
+            .map_or(false, |v| v.queue.iter().any(|&(x, _)| x == h))
+    }
+
+    /// The open venue the person prefers among the ones they can afford: the closest, the
+    /// pricier ones counting as further
+    pub fn pick(&self, pos: Vec3, wallet: Money, t: DayTime) -> Option<BuildingID> {
+        self.venues
+            .iter()
+            .filter(|(_, v)| {
+                v.is_open(t) && v.price <= wallet && v.door.is_close(pos, MAX_VENUE_DIST)
+            })
+            .min_by_key(|(_, v)| {
+                OrderedFloat(v.door.distance(pos) + v.price.bucks() as f32 * DIST_PER_BUCK)
+            })
+            .map(|(&id, _)| id)
+    }
+}
+
+/// The person arrived at the door, they queue behind the others and get in if there is room
+pub fn arrive_at_venue(sim: &mut Simulation, b: BuildingID, h: HumanID, now: GameInstant) {
+    let (world, res) = sim.world_res();
+    let mut venues = res.write::<LeisureVenues>();
+    let Some(v) = venues.venues.get_mut(&b) else {
+        return;
+    };
+    if v.visitors.contains_key(&h) || v.queue.iter().any(|&(x, _)| x == h) {
+        return;
+    }
+    v.queue.push_back((h, now));
+    v.let_in(
+        world,
+        &mut res.write::<Market>(),
+        &mut res.write::<Government>(),
+        now,
+    );
+}
+
+pub fn leisure_venues_system(world: &mut World, res: &mut Resources) {
+    profiling::scope!("map_dynamic::leisure_venues_system");
+    let time = *res.read::<GameTime>();
+    if !time.tick(60) {
+        return;
+    }
+    let now = time.instant();
+    let map = res.read::<Map>();
+    let registry = res.read::<GoodsCompanyRegistry>();
+    let mut venues = res.write::<LeisureVenues>();
+    let mut market = res.write::<Market>();
+    let mut gov = res.write::<Government>();
+
+    if venues.payday != time.daytime.day {
+        venues.payday = time.daytime.day;
+        for h in world.humans.values_mut() {
+            let pocket_money = h.earnings.take(h.go_out.budget - h.go_out.wallet);
+            h.go_out.wallet += pocket_money;
+        }
+    }
+
+    let mut found = BTreeMap::new();
+    for (id, c) in world.companies.iter() {
+        let Some(b) = map.buildings().get(c.comp.building) else {
+            continue;
+        };
+        let Some(descr) = b
+            .kind
+            .as_goods_company()
+            .and_then(|id| registry.descriptions.get(id))
+        else {
+            continue;
+        };
+        if let Some(leisure) = descr.leisure {
+            let serves = descr.recipe.production.first().map(|&(item, _)| item);
+            found.insert(b.id, (id, b.door_pos, leisure, serves, descr.opening_hours));
+        }
+    }
+    venues.venues.retain(|id, _| found.contains_key(id));
+
+    for (id, (company, door, leisure, serves, opening_hours)) in found {
+        let v = venues.venues.entry(id).or_insert_with(|| LeisureVenue {
+            company,
+            door,
+            capacity: leisure.capacity,
+            price: Money::new_bucks(leisure.price),
+            serves,
+            opening_hours,
+            visitors: BTreeMap::new(),
+            queue: VecDeque::new(),
+        });
+        v.company = company;
+        v.door = door;
+        v.capacity = leisure.capacity;
+        v.price = Money::new_bucks(leisure.price);
+        v.serves = serves;
+        v.opening_hours = opening_hours;
+
+        if !v.is_open(time.daytime) {
+            v.visitors.clear();
+            v.queue.clear();
+            continue;
+        }
+        v.visitors.retain(|&h, since| {
+            world.humans.contains_key(h) && since.elapsed(&time) <= VISIT_DURATION
+        });
+        v.queue.retain(|&(h, since)| {
+            world.humans.contains_key(h) && since.elapsed(&time) <= MAX_QUEUE_WAIT
+        });
+        v.let_in(world, &mut market, &mut gov, now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economy::ItemRegistry;
+    use crate::souls::goods_company::{company_soul, GoodsCompany, Recipe};
+    use crate::souls::human::spawn_human;
+    use crate::tests::TestCtx;
+    use common::descriptions::CompanyKind;
+    use geom::{vec2, vec3};
+    use slotmapd::KeyData;
+
+    fn venue(company: CompanyID, capacity: u32, price: i64, door: Vec3) -> LeisureVenue {
+        LeisureVenue {
+            company,
+            door,
+            capacity,
+            price: Money::new_bucks(price),
+            serves: None,
+            opening_hours: Some(OpeningHours { open: 18, close: 2 }),
+            visitors: BTreeMap::new(),
+            queue: VecDeque::new(),
+        }
+    }
+
+    #[test]
+    fn visitors_pay_the_venue_and_queue_when_full() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(50.0, 50.0));
+        let humans: Vec<_> = (0..3)
+            .map(|_| spawn_human(&mut test.g, house).unwrap())
+            .collect();
+        for &h in &humans {
+            let human = test.g.world_mut_unchecked().humans.get_mut(h).unwrap();
+            human.go_out.wallet = Money::new_bucks(15);
+        }
+
+        let soul = company_soul(
+            &mut test.g,
+            GoodsCompany {
+                kind: CompanyKind::Store,
+                recipe: Recipe {
+                    consumption: vec![],
+                    production: vec![],
+                    complexity: 100,
+                    storage_multiplier: 5,
+                },
+                building: house,
+                max_workers: 1,
+                qualification: Default::default(),
+                progress: 0.0,
+                fleet: vec![],
+                fleet_size: 0,
+                freight_mode: Default::default(),
+                fleet_utilization: 0.0,
+                brand: Default::default(),
+                money: Money::ZERO,
+            },
+        )
+        .unwrap();
+        let SoulID::GoodsCompany(company) = soul else {
+            unreachable!()
+        };
+        let mut v = venue(company, 2, 10, Vec3::ZERO);
+        v.opening_hours = None;
+        test.g.write::<LeisureVenues>().venues.insert(house, v);
+
+        let money = test.g.read::<Government>().money;
+        let now = GameInstant { timestamp: 0.0 };
+        for &h in &humans {
+            arrive_at_venue(&mut test.g, house, h, now);
+        }
+        let venues = test.g.read::<LeisureVenues>();
+        assert!(venues.is_inside(house, humans[0]));
+        assert!(venues.is_inside(house, humans[1]));
+        assert!(venues.is_queuing(house, humans[2]));
+        drop(venues);
+
+        let world = test.g.world();
+        assert_eq!(world.humans[humans[0]].go_out.wallet, Money::new_bucks(5));
+        assert_eq!(world.humans[humans[2]].go_out.wallet, Money::new_bucks(15));
+        assert_eq!(world.companies[company].comp.money, Money::new_bucks(18));
+        assert_eq!(
+            test.g.read::<Government>().money,
+            money + Money::new_bucks(2)
+        );
+
+        // someone leaves, but there is nothing left to serve
+        let drinks = test.g.read::<ItemRegistry>().id("drinks");
+        let (world, res) = test.g.world_res();
+        let mut venues = res.write::<LeisureVenues>();
+        let v = venues.venues.get_mut(&house).unwrap();
+        v.visitors.remove(&humans[0]);
+        v.serves = Some(drinks);
+        let mut gov = res.write::<Government>();
+        v.let_in(world, &mut res.write::<Market>(), &mut gov, now);
+        assert_eq!(v.queue.len(), 1);
+
+        res.write::<Market>().produce(soul, drinks, 1);
+        v.let_in(world, &mut res.write::<Market>(), &mut gov, now);
+        assert!(v.visitors.contains_key(&humans[2]));
+        assert_eq!(res.read::<Market>().capital(soul, drinks), 0);
+    }
+
+    #[test]
+    fn people_pick_by_distance_and_price() {
+        let (near, far) = (
+            BuildingID::from(KeyData::from_ffi(1)),
+            BuildingID::from(KeyData::from_ffi(2)),
+        );
+        let company = CompanyID::from(KeyData::from_ffi(1));
+        let evening = DayTime {
+            day: 0,
+            hour: 20,
+            second: 0,
+        };
+
+        let mut venues = LeisureVenues::default();
+        venues
+            .venues
+            .insert(near, venue(company, 10, 30, Vec3::x(100.0)));
+        venues
+            .venues
+            .insert(far, venue(company, 10, 5, Vec3::x(500.0)));
+
+        // the cheaper venue is worth the walk
+        let wallet = Money::new_bucks(50);
+        assert_eq!(venues.pick(Vec3::ZERO, wallet, evening), Some(far));
+        // unless the price difference is small
+        venues.venues.get_mut(&near).unwrap().price = Money::new_bucks(8);
+        assert_eq!(venues.pick(Vec3::ZERO, wallet, evening), Some(near));
+        // nobody goes to a place they can't afford
+        assert_eq!(
+            venues.pick(Vec3::ZERO, Money::new_bucks(6), evening),
+            Some(far)
+        );
+
+        let morning = DayTime {
+            hour: 10,
+            ..evening
+        };
+        assert_eq!(venues.pick(Vec3::ZERO, wallet, morning), None);
+    }
+}
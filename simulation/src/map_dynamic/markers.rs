@@ -0,0 +1,90 @@
+use geom::{Color, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct MarkerID(pub u32);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MarkerIcon {
+    Pin,
+    Star,
+    Home,
+    Industry,
+    Station,
+    Warning,
+}
+
+impl MarkerIcon {
+    pub const ALL: [MarkerIcon; 6] = [
+        MarkerIcon::Pin,
+        MarkerIcon::Star,
+        MarkerIcon::Home,
+        MarkerIcon::Industry,
+        MarkerIcon::Station,
+        MarkerIcon::Warning,
+    ];
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            MarkerIcon::Pin => "📍",
+            MarkerIcon::Star => "⭐",
+            MarkerIcon::Home => "🏠",
+            MarkerIcon::Industry => "🏭",
+            MarkerIcon::Station => "🚉",
+            MarkerIcon::Warning => "⚠",
+        }
+    }
+}
+
+/// A named location placed by the player to find their way around big cities
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapMarker {
+    pub name: String,
+    pub pos: Vec3,
+    pub icon: MarkerIcon,
+    pub color: Color,
+}
+
+/// MapMarkers keeps the markers of the save, they don't affect the simulation
+#[derive(Default, Serialize, Deserialize)]
+pub struct MapMarkers {
+    markers: BTreeMap<MarkerID, MapMarker>,
+    next_id: u32,
+}
+
+impl MapMarkers {
+    pub fn add(&mut self, marker: MapMarker) -> MarkerID {
+        let id = MarkerID(self.next_id);
+        self.next_id += 1;
+        self.markers.insert(id, marker);
+        id
+    }
+
+    /// Replaces the marker if it still exists
+    pub fn update(&mut self, id: MarkerID, marker: MapMarker) {
+        if let Some(m) = self.markers.get_mut(&id) {
+            *m = marker;
+        }
+    }
+
+    pub fn remove(&mut self, id: MarkerID) {
+        self.markers.remove(&id);
+    }
+
+    pub fn get(&self, id: MarkerID) -> Option<&MapMarker> {
+        self.markers.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (MarkerID, &MapMarker)> {
+        self.markers.iter().map(|(&id, m)| (id, m))
+    }
+
+    pub fn len(&self) -> usize {
+        self.markers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.markers.is_empty()
+    }
+}
@@ -1,11 +1,35 @@
 mod binfos;
 mod dispatch;
+mod intersection_stats;
 mod itinerary;
+mod leisure;
+mod markers;
+mod night_activity;
 mod parking;
+mod parking_enforcement;
+mod policies;
+mod rezoning;
+mod risk;
 mod router;
+mod tour;
+mod traffic_flow;
+mod trips;
+mod venue_events;
 
 pub use binfos::*;
 pub use dispatch::*;
+pub use intersection_stats::*;
 pub use itinerary::*;
+pub use leisure::*;
+pub use markers::*;
+pub use night_activity::*;
 pub use parking::*;
+pub use parking_enforcement::*;
+pub use policies::*;
+pub use rezoning::*;
+pub use risk::*;
 pub use router::*;
+pub use tour::*;
+pub use traffic_flow::*;
+pub use trips::*;
+pub use venue_events::*;
@@ -0,0 +1,71 @@
+use crate::transportation::Location;
+use crate::utils::resources::Resources;
+use crate::utils::time::GameTime;
+use crate::World;
+use geom::{Vec2, AABB};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Size of the side of a cell of the night activity grid, in meters
+pub const NIGHT_CELL_SIZE: f32 = 100.0;
+/// Hour at which the night activity is measured
+pub const NIGHT_ACTIVITY_HOUR: i32 = 2;
+
+/// NightActivity counts the people out of their home in the middle of the night, to know which
+/// parts of town are still alive at 2am.
+/// It is measured once per night and kept until the next one.
+#[derive(Default, Serialize, Deserialize)]
+pub struct NightActivity {
+    cells: BTreeMap<(i32, i32), u32>,
+    /// Day of the last measure
+    day: Option<i32>,
+}
+
+impl NightActivity {
+    pub fn cell(pos: Vec2) -> (i32, i32) {
+        (
+            (pos.x / NIGHT_CELL_SIZE).floor() as i32,
+            (pos.y / NIGHT_CELL_SIZE).floor() as i32,
+        )
+    }
+
+    pub fn cell_bounds((x, y): (i32, i32)) -> AABB {
+        let ll = Vec2::new(x as f32, y as f32) * NIGHT_CELL_SIZE;
+        AABB::new(ll, ll + Vec2::splat(NIGHT_CELL_SIZE))
+    }
+
+    /// Number of people out per cell during the last night
+    pub fn cells(&self) -> impl Iterator<Item = ((i32, i32), u32)> + '_ {
+        self.cells.iter().map(|(&c, &n)| (c, n))
+    }
+
+    pub fn n_people(&self) -> u32 {
+        self.cells.values().sum()
+    }
+
+    /// Day of the last measure
+    pub fn day(&self) -> Option<i32> {
+        self.day
+    }
+}
+
+pub fn night_activity_system(world: &mut World, res: &mut Resources) {
+    profiling::scope!("map_dynamic::night_activity_system");
+    let daytime = res.read::<GameTime>().daytime;
+    let mut activity = res.write::<NightActivity>();
+    if daytime.hour != NIGHT_ACTIVITY_HOUR || activity.day == Some(daytime.day) {
+        return;
+    }
+    activity.day = Some(daytime.day);
+    activity.cells.clear();
+
+    for h in world.humans.values() {
+        if h.location == Location::Building(h.home.house) {
+            continue;
+        }
+        *activity
+            .cells
+            .entry(NightActivity::cell(h.trans.position.xy()))
+            .or_default() += 1;
+    }
+}
@@ -1,10 +1,17 @@
-use crate::map::{Lane, LaneKind, Map, ParkingSpot, ParkingSpotID, ParkingSpots};
+use crate::map::{CurbKind, Lane, Map, ParkingSpot, ParkingSpotID, ParkingSpots};
+use crate::map_dynamic::District;
 use common::AccessCmp;
 use geom::Vec3;
+use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::option::Option::None;
 
+/// How far from the destination the parking lots are considered, in meters
+const MAX_LOT_WALK: f32 = 300.0;
+/// Weight of a new search in the parking pressure of a district
+const PRESSURE_SMOOTHING: f32 = 0.05;
+
 #[derive(Debug, Serialize, Deserialize)]
 #[repr(transparent)]
 pub struct SpotReservation(ParkingSpotID);
@@ -39,6 +46,12 @@ impl ParkingManagement {
         !self.reserved_spots.contains(&spot)
     }
 
+    /// Marks the spot of a vehicle parked in a loaded save as reserved, in case the reservation
+    /// was lost with an older or corrupted save
+    pub(crate) fn relink(&mut self, spot: &SpotReservation) {
+        self.reserved_spots.insert(spot.0);
+    }
+
     pub fn reserve_random_free_spot(
         &mut self,
         spots: &ParkingSpots,
@@ -48,6 +61,12 @@ impl ParkingManagement {
             let Some(spot) = spots.random_spot(rng.wrapping_add(i_try)) else {
                 continue;
             };
+            if spots
+                .get(spot)
+                .map_or(true, |s| s.curb != CurbKind::Parking)
+            {
+                continue;
+            }
             if self.reserved_spots.insert(spot) {
                 return Some(SpotReservation(spot));
             }
@@ -55,19 +74,79 @@ impl ParkingManagement {
         None
     }
 
+    /// Reserves the free parking spot closest to `near`, either along the streets or in a
+    /// parking lot
     pub fn reserve_near(
         &mut self,
         near: Vec3,
         map: &Map,
     ) -> Result<SpotReservation, ParkingReserveError> {
+        let street = self.find_near_curb(near, map, CurbKind::Parking, 7);
+        let lot = self.find_in_lots(near, map);
+
+        let dist = |spot: ParkingSpotID| {
+            map.parking
+                .get(spot)
+                .map_or(f32::INFINITY, |s| s.trans.position.distance2(near))
+        };
+        let spot = match (street, lot) {
+            (Ok(street), Some(lot)) if dist(lot) < dist(street) => lot,
+            (Ok(street), _) => street,
+            (Err(_), Some(lot)) => lot,
+            (Err(e), None) => return Err(e),
+        };
+        Ok(self.reserve(spot))
+    }
+
+    /// Reserves a spot in a loading zone close by for a truck, or a parking spot if there are none
+    pub fn reserve_loading_near(
+        &mut self,
+        near: Vec3,
+        map: &Map,
+    ) -> Result<SpotReservation, ParkingReserveError> {
+        match self.find_near_curb(near, map, CurbKind::LoadingZone, 2) {
+            Ok(spot) => Ok(self.reserve(spot)),
+            Err(_) => self.reserve_near(near, map),
+        }
+    }
+
+    fn reserve(&mut self, spot: ParkingSpotID) -> SpotReservation {
+        self.reserved_spots.insert(spot);
+        SpotReservation(spot)
+    }
+
+    /// The free spot of the parking lots around `near` closest to it
+    fn find_in_lots(&self, near: Vec3, map: &Map) -> Option<ParkingSpotID> {
+        map.parking
+            .lots()
+            .filter(|&(lot, _)| {
+                map.buildings()
+                    .get(lot)
+                    .map_or(false, |b| b.door_pos.is_close(near, MAX_LOT_WALK))
+            })
+            .flat_map(|(_, spots)| spots.iter().copied())
+            .filter(|&spot| self.is_spot_free(spot))
+            .min_by_key(|&spot| {
+                let p = unwrap_ret!(map.parking.get(spot), OrderedFloat(f32::INFINITY));
+                OrderedFloat(p.trans.position.distance2(near))
+            })
+    }
+
+    /// Finds a free spot of the given curb kind along the lanes around `near`, without
+    /// reserving it
+    fn find_near_curb(
+        &self,
+        near: Vec3,
+        map: &Map,
+        curb: CurbKind,
+        depth: usize,
+    ) -> Result<ParkingSpotID, ParkingReserveError> {
         use ParkingReserveError as E;
         let lane = map
-            .nearest_lane(near, LaneKind::Driving, None)
+            .nearest_driving_lane(near, None)
             .ok_or(E::FindingNearestLane)?;
         let lane = map.lanes().get(lane).ok_or(E::FetchingLaneData)?;
 
-        let depth = 7;
-
         let idget = |l: &Lane| l.id;
 
         let mut potential = BTreeSet::new();
@@ -99,8 +178,11 @@ impl ParkingManagement {
 
                 if let Some(p_iter) = map.parking.closest_spots(plane, near) {
                     for spot in p_iter {
-                        if self.reserved_spots.insert(spot) {
-                            return Ok(SpotReservation(spot));
+                        if map.parking.get(spot).map_or(true, |s| s.curb != curb) {
+                            continue;
+                        }
+                        if self.is_spot_free(spot) {
+                            return Ok(spot);
                         }
                     }
                 }
@@ -111,7 +193,37 @@ impl ParkingManagement {
     }
 }
 
+/// ParkingPressure measures, for every district, the share of the drivers arriving at their
+/// destination who found no free spot and had to cruise around the block
+#[derive(Default, Serialize, Deserialize)]
+pub struct ParkingPressure {
+    districts: BTreeMap<District, f32>,
+}
+
+impl ParkingPressure {
+    /// Records a driver looking for a spot in the district
+    pub fn record(&mut self, district: District, found: bool) {
+        let v = if found { 0.0 } else { 1.0 };
+        let p = self.districts.entry(district).or_insert(v);
+        *p += (v - *p) * PRESSURE_SMOOTHING;
+    }
+
+    /// Share of the recent searches of the district that found no spot, in [0; 1].
+    /// Returns `None` if nobody looked for a spot there
+    pub fn pressure(&self, district: District) -> Option<f32> {
+        self.districts.get(&district).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (District, f32)> + '_ {
+        self.districts.iter().map(|(&d, &p)| (d, p))
+    }
+}
+
 impl SpotReservation {
+    pub fn id(&self) -> ParkingSpotID {
+        self.0
+    }
+
     pub fn exists(&self, spots: &ParkingSpots) -> bool {
         spots.contains(self.0)
     }
@@ -124,3 +236,137 @@ impl SpotReservation {
         map.parking_to_drive_pos(self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{BuildingKind, LaneKind, LanePatternBuilder, PathKind, ProjectFilter};
+    use crate::map_dynamic::Itinerary;
+    use crate::tests::TestCtx;
+    use crate::transportation::{
+        relink_parked_vehicles, spawn_parked_vehicle, VehicleKind, VehicleState,
+    };
+    use crate::utils::time::Tick;
+    use crate::Simulation;
+    use common::descriptions::{BuildingGen, DoorLayout};
+    use geom::{vec2, vec3, Intersect, OBB};
+
+    #[test]
+    fn parking_lots_are_used_when_closer() {
+        let test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+        let obb = OBB::new(vec2(150.0, 50.0), vec2(1.0, 0.0), 40.0, 40.0);
+        let lot = test
+            .g
+            .map_mut()
+            .build_special_building(
+                &obb,
+                BuildingKind::ParkingLot,
+                BuildingGen::CenteredDoor {
+                    vertical_factor: 1.0,
+                    doors: DoorLayout::DRIVEWAY,
+                },
+                None,
+            )
+            .unwrap();
+
+        let map = test.g.map();
+        let spots = map.parking.lot_spots(lot);
+        assert!(!spots.is_empty());
+        for &spot in spots {
+            let pos = map.parking.get(spot).unwrap().trans.position;
+            assert!(obb.intersects(&pos.xy()));
+        }
+
+        let mut parking = ParkingManagement::default();
+        let in_lot = parking.reserve_near(vec3(150.0, 50.0, 0.0), &map).unwrap();
+        assert_eq!(in_lot.get(&map.parking).unwrap().lot, Some(lot));
+        assert!(in_lot.park_pos(&map).is_some());
+
+        let on_street = parking.reserve_near(vec3(150.0, -30.0, 0.0), &map).unwrap();
+        assert_eq!(on_street.get(&map.parking).unwrap().lot, None);
+
+        parking.free(in_lot);
+        parking.free(on_street);
+        drop(map);
+
+        test.g.map_mut().remove_building(lot);
+        assert!(test.g.map().parking.lot_spots(lot).is_empty());
+    }
+
+    #[test]
+    fn cars_park_and_drive_along_tram_streets() {
+        let test = TestCtx::new();
+        {
+            let mut m = test.g.map_mut();
+            let pat = LanePatternBuilder::new().tram(true).build();
+            for w in [0.0, 300.0, 600.0].windows(2) {
+                let a = m.project(vec3(w[0], 0.0, 0.0), 0.0, ProjectFilter::ALL);
+                let b = m.project(vec3(w[1], 0.0, 0.0), 0.0, ProjectFilter::ALL);
+                m.make_connection(a, b, None, &pat);
+            }
+        }
+
+        let map = test.g.map();
+        assert!(map.lanes().values().all(|l| l.kind != LaneKind::Driving));
+
+        let mut parking = ParkingManagement::default();
+        let spot = parking.reserve_near(vec3(450.0, 10.0, 0.0), &map).unwrap();
+        let drive = map.parking_to_drive(spot.0).unwrap();
+        assert_eq!(map.lanes()[drive].kind, LaneKind::Tram);
+
+        let start = map.parking.get(spot.0).unwrap().trans.position;
+        let itinerary = Itinerary::route(
+            Tick(0),
+            start,
+            vec3(50.0, 0.0, 0.0),
+            &map,
+            PathKind::Vehicle,
+        );
+        assert!(itinerary.is_some());
+    }
+
+    #[test]
+    fn parked_vehicles_are_relinked_after_loading() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+        let a = spawn_parked_vehicle(&mut test.g, VehicleKind::Car, vec3(100.0, 0.0, 0.0)).unwrap();
+        let b = spawn_parked_vehicle(&mut test.g, VehicleKind::Car, vec3(200.0, 0.0, 0.0)).unwrap();
+        let spot_of = |sim: &Simulation, v| match sim.world().vehicles[v].vehicle.state {
+            VehicleState::Parked(ref spot) => spot.0,
+            _ => panic!("{:?} isn't parked", v),
+        };
+        let spot_a = spot_of(&test.g, a);
+
+        // the reservations were lost and both vehicles think they are in the same spot
+        test.g.resources.insert(ParkingManagement::default());
+        test.g.world_mut_unchecked().vehicles[b].vehicle.state =
+            VehicleState::Parked(SpotReservation(spot_a));
+
+        relink_parked_vehicles(&mut test.g);
+        assert_eq!(spot_of(&test.g, a), spot_a);
+        let spot_b = spot_of(&test.g, b);
+        assert_ne!(spot_a, spot_b);
+
+        let pm = test.g.read::<ParkingManagement>();
+        assert!(!pm.is_spot_free(spot_a));
+        assert!(!pm.is_spot_free(spot_b));
+        let trans = test.g.map().parking.get(spot_b).unwrap().trans;
+        assert!(test.g.world().vehicles[b].trans == trans);
+    }
+
+    #[test]
+    fn parking_pressure() {
+        let d = District(0, 0);
+        let mut pressure = ParkingPressure::default();
+        assert_eq!(pressure.pressure(d), None);
+
+        pressure.record(d, false);
+        assert_eq!(pressure.pressure(d), Some(1.0));
+        for _ in 0..100 {
+            pressure.record(d, true);
+        }
+        assert!(pressure.pressure(d).unwrap() < 0.01);
+        assert_eq!(pressure.pressure(District(1, 0)), None);
+    }
+}
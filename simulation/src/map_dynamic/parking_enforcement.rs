@@ -0,0 +1,92 @@
+use crate::economy::{Government, Money};
+use crate::map::Map;
+use crate::map_dynamic::{coverage_at, service_buildings};
+use crate::souls::goods_company::GoodsCompanyRegistry;
+use crate::transportation::VehicleState;
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::resources::Resources;
+use crate::utils::time::GameTime;
+use crate::world::{VehicleEnt, VehicleID};
+use crate::{ParCommandBuffer, World};
+use common::descriptions::ServiceKind;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Fine of a car parked illegally, paid to the city
+pub const PARKING_FINE: Money = Money::new_bucks(50);
+/// Fee paid to the city when a car is towed to the impound lot
+pub const TOWING_FEE: Money = Money::new_bucks(150);
+/// Game seconds a car stays double parked before it can be towed
+const TOW_AFTER: f64 = 30.0 * 60.0;
+/// Probability that a patrol notices a double parked car during a minute, where the police
+/// fully covers the street
+const PATROL_CHANCE: f32 = 0.1;
+
+/// ParkingEnforcement fines and tows the cars double parked because they found no spot.
+/// Police stations patrol the streets they cover: a car they notice is fined, then towed to the
+/// impound lot if it is still there after a while, freeing the lane it blocks.
+#[derive(Default, Serialize, Deserialize)]
+pub struct ParkingEnforcement {
+    /// Double parked cars that were already fined
+    fined: BTreeSet<VehicleID>,
+    pub n_double_parked: u32,
+    pub n_fines: u32,
+    pub n_towed: u32,
+    /// Money collected from the fines and the towing fees
+    pub income: Money,
+}
+
+impl ParkingEnforcement {
+    fn collect(&mut self, gov: &mut Government, amount: Money) {
+        gov.money += amount;
+        self.income += amount;
+    }
+}
+
+pub fn parking_enforcement_update(world: &mut World, res: &mut Resources) {
+    profiling::scope!("map_dynamic::parking_enforcement_update");
+    let time = *res.read::<GameTime>();
+    if !time.tick(60) {
+        return;
+    }
+    let map = res.read::<Map>();
+    let patrols: Vec<_> = service_buildings(world, &map, &res.read::<GoodsCompanyRegistry>())
+        .into_iter()
+        .filter(|&(_, service, _)| service == ServiceKind::Police)
+        .collect();
+
+    let mut enforcement = res.write::<ParkingEnforcement>();
+    let mut gov = res.write::<Government>();
+    let mut rng = res.write::<RandProvider>();
+    let cbuf = res.read::<ParCommandBuffer<VehicleEnt>>();
+
+    let mut double_parked = BTreeSet::new();
+    for (id, v) in world.vehicles.iter() {
+        let VehicleState::DoubleParked(since) = v.vehicle.state else {
+            continue;
+        };
+        double_parked.insert(id);
+
+        let pos = v.trans.position.xy();
+        let coverage = patrols
+            .iter()
+            .map(|&(p, _, staffing)| coverage_at(p.distance(pos), staffing))
+            .fold(0.0, f32::max);
+        if rng.next_f32() >= coverage * PATROL_CHANCE {
+            continue;
+        }
+
+        if enforcement.fined.insert(id) {
+            enforcement.n_fines += 1;
+            enforcement.collect(&mut gov, PARKING_FINE);
+        } else if since.elapsed(&time) > TOW_AFTER {
+            cbuf.kill(id);
+            double_parked.remove(&id);
+            enforcement.n_towed += 1;
+            enforcement.collect(&mut gov, TOWING_FEE);
+        }
+    }
+
+    enforcement.fined.retain(|id| double_parked.contains(id));
+    enforcement.n_double_parked = double_parked.len() as u32;
+}
@@ -0,0 +1,250 @@
+use crate::economy::{Government, Money};
+use crate::map::Map;
+use crate::map_dynamic::District;
+use crate::utils::resources::Resources;
+use crate::utils::time::GameTime;
+use crate::World;
+use geom::Vec2;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Speed multiplier of the lanes of a district with reduced speed limits
+pub const REDUCED_SPEED_FACTOR: f32 = 0.7;
+/// Pathfinding cost multiplier of the lanes of a district banning trucks
+pub const TRUCK_BAN_PENALTY: f32 = 20.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Policy {
+    /// Vehicles drive slower in the district
+    ReducedSpeedLimit,
+    /// Trucks avoid going through the district unless they deliver there
+    HeavyVehicleBan,
+    /// Visitors cannot park in the district and walk instead, residents can still park at home
+    ParkingRestriction,
+    /// Transit is free in the district, drivers going there leave their car when a line takes them
+    FreeTransit,
+}
+
+impl Policy {
+    pub const ALL: [Policy; 4] = [
+        Policy::ReducedSpeedLimit,
+        Policy::HeavyVehicleBan,
+        Policy::ParkingRestriction,
+        Policy::FreeTransit,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Policy::ReducedSpeedLimit => "Reduced speed limit",
+            Policy::HeavyVehicleBan => "Heavy vehicle ban",
+            Policy::ParkingRestriction => "Parking restriction",
+            Policy::FreeTransit => "Free transit",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Policy::ReducedSpeedLimit => "Vehicles drive 30% slower in the district.",
+            Policy::HeavyVehicleBan => "Trucks avoid the district unless they deliver there.",
+            Policy::ParkingRestriction => {
+                "Only residents can park in the district, visitors have to walk."
+            }
+            Policy::FreeTransit => {
+                "Drivers going to the district take the bus or the metro instead."
+            }
+        }
+    }
+
+    /// Cost of keeping the policy enacted in one district, paid every day
+    pub fn daily_cost(self) -> Money {
+        Money::new_bucks(match self {
+            Policy::ReducedSpeedLimit => 200,
+            Policy::HeavyVehicleBan => 500,
+            Policy::ParkingRestriction => 300,
+            Policy::FreeTransit => 800,
+        })
+    }
+}
+
+/// Policies keeps the policies enacted in each district.
+/// They are modifiers consumed by the vehicle, pathfinding and routing systems.
+/// It also keeps the building theme of the districts, see [`crate::map::procgen::BuildingTheme`].
+#[derive(Default, Serialize, Deserialize)]
+pub struct Policies {
+    enacted: BTreeMap<District, BTreeSet<Policy>>,
+    last_paid_day: i32,
+    /// Name of the building theme of the districts that have one
+    themes: BTreeMap<District, String>,
+}
+
+impl Policies {
+    pub fn is_enacted(&self, district: District, policy: Policy) -> bool {
+        self.enacted
+            .get(&district)
+            .map_or(false, |p| p.contains(&policy))
+    }
+
+    pub fn set(&mut self, district: District, policy: Policy, enacted: bool) {
+        if enacted {
+            self.enacted.entry(district).or_default().insert(policy);
+            return;
+        }
+        if let Some(p) = self.enacted.get_mut(&district) {
+            p.remove(&policy);
+            if p.is_empty() {
+                self.enacted.remove(&district);
+            }
+        }
+    }
+
+    /// Every enacted policy with its district
+    pub fn iter(&self) -> impl Iterator<Item = (District, Policy)> + '_ {
+        self.enacted
+            .iter()
+            .flat_map(|(&d, p)| p.iter().map(move |&p| (d, p)))
+    }
+
+    pub fn districts_with(&self, policy: Policy) -> BTreeSet<District> {
+        self.iter()
+            .filter(|&(_, p)| p == policy)
+            .map(|(d, _)| d)
+            .collect()
+    }
+
+    pub fn theme(&self, district: District) -> Option<&str> {
+        self.themes.get(&district).map(String::as_str)
+    }
+
+    pub fn set_theme(&mut self, district: District, theme: Option<String>) {
+        match theme {
+            Some(theme) => self.themes.insert(district, theme),
+            None => self.themes.remove(&district),
+        };
+    }
+
+    /// Every district with a building theme
+    pub fn themes(&self) -> impl Iterator<Item = (District, &str)> + '_ {
+        self.themes.iter().map(|(&d, t)| (d, t.as_str()))
+    }
+
+    pub fn daily_cost(&self) -> Money {
+        self.iter().map(|(_, p)| p.daily_cost()).sum()
+    }
+
+    /// Speed multiplier for vehicles driving at the given position
+    pub fn speed_factor(&self, pos: Vec2) -> f32 {
+        if self.is_enacted(District::of(pos), Policy::ReducedSpeedLimit) {
+            return REDUCED_SPEED_FACTOR;
+        }
+        1.0
+    }
+
+    pub fn parking_restricted(&self, pos: Vec2) -> bool {
+        self.is_enacted(District::of(pos), Policy::ParkingRestriction)
+    }
+
+    pub fn free_transit(&self, pos: Vec2) -> bool {
+        self.is_enacted(District::of(pos), Policy::FreeTransit)
+    }
+}
+
+pub fn policies_system(_: &mut World, res: &mut Resources) {
+    profiling::scope!("map_dynamic::policies_system");
+    let day = res.read::<GameTime>().daytime.day;
+    let mut policies = res.write::<Policies>();
+
+    if day > policies.last_paid_day {
+        policies.last_paid_day = day;
+        let timestamp = res.read::<GameTime>().timestamp;
+        res.write::<Government>()
+            .spend(policies.daily_cost(), "Policies", timestamp);
+    }
+
+    // Pathfinding only has access to the map, so the truck bans are copied there
+    let bans = policies.districts_with(Policy::HeavyVehicleBan);
+    if *res.read::<Map>().heavy_vehicle_bans() != bans {
+        res.write::<Map>().set_heavy_vehicle_bans(bans);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_dynamic::router::leaves_car_for_transit;
+    use crate::tests::TestCtx;
+    use crate::transportation::bus::{BusLine, BusLines};
+    use crate::transportation::metro::MetroLines;
+    use crate::world_command::WorldCommand;
+    use geom::{vec3, Color};
+
+    #[test]
+    fn set_policies() {
+        let mut p = Policies::default();
+        let d = District(1, -2);
+        p.set(d, Policy::HeavyVehicleBan, true);
+        p.set(d, Policy::ReducedSpeedLimit, true);
+        assert!(p.is_enacted(d, Policy::HeavyVehicleBan));
+        assert!(!p.is_enacted(District(0, 0), Policy::HeavyVehicleBan));
+        assert_eq!(
+            p.daily_cost(),
+            Policy::HeavyVehicleBan.daily_cost() + Policy::ReducedSpeedLimit.daily_cost()
+        );
+        assert_eq!(p.speed_factor(d.center()), REDUCED_SPEED_FACTOR);
+
+        p.set(d, Policy::HeavyVehicleBan, false);
+        p.set(d, Policy::ReducedSpeedLimit, false);
+        assert_eq!(p.iter().count(), 0);
+        assert_eq!(p.daily_cost(), Money::ZERO);
+    }
+    #[test]
+    fn free_transit_takes_drivers_out_of_their_car() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0., 0., 0.), vec3(1000., 0., 0.)]);
+
+        let stops = [50.0, 950.0].map(|x| {
+            test.g
+                .map()
+                .snap_bus_stop(vec3(x, 10.0, 0.0), String::new())
+                .unwrap()
+        });
+        test.apply(&stops.map(WorldCommand::MapAddBusStop));
+        let stops = test.g.map().bus_stops().keys().collect();
+        test.apply(&[WorldCommand::AddBusLine(BusLine {
+            name: "1".to_string(),
+            color: Color::RED,
+            stops,
+            n_buses: 1,
+            tram: false,
+        })]);
+
+        let (from, to) = (vec3(0.0, 20.0, 0.0), vec3(1000.0, 20.0, 0.0));
+        let leaves_car = |test: &TestCtx| {
+            leaves_car_for_transit(
+                &test.g.read::<Policies>(),
+                &test.g.read::<BusLines>(),
+                &test.g.read::<MetroLines>(),
+                &test.g.map(),
+                from,
+                to,
+            )
+        };
+        assert!(!leaves_car(&test));
+
+        test.apply(&[WorldCommand::SetDistrictPolicy {
+            district: District::of(to.xy()),
+            policy: Policy::FreeTransit,
+            enacted: true,
+        }]);
+        assert!(Policy::ALL.contains(&Policy::FreeTransit));
+        assert!(leaves_car(&test));
+        // too close for the bus to be worth it
+        assert!(!leaves_car_for_transit(
+            &test.g.read::<Policies>(),
+            &test.g.read::<BusLines>(),
+            &test.g.read::<MetroLines>(),
+            &test.g.map(),
+            vec3(900.0, 20.0, 0.0),
+            to,
+        ));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::economy::Milestones;
+use crate::map::{BuildingID, BuildingKind, LotKind};
+use crate::souls::goods_company::GoodsCompanyRegistry;
+use crate::utils::time::GameTime;
+use crate::world_command::{insert_building_info, push_construction_event};
+use crate::Simulation;
+use common::descriptions::CompanyKind;
+use geom::OBB;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Number of (game) seconds between two house replacements
+const REZONE_INTERVAL_SECS: u32 = 30;
+
+/// Rezoning keeps the houses waiting to be replaced after the lots around them were rezoned.
+/// Houses are replaced one at a time so that neighbourhoods change progressively, and the
+/// commercial lots they leave are then built upon by shops, one at a time as well.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Rezoning {
+    pending: VecDeque<(BuildingID, LotKind)>,
+}
+
+impl Rezoning {
+    pub fn queue(&mut self, building: BuildingID, kind: LotKind) {
+        if let Some(v) = self.pending.iter_mut().find(|(b, _)| *b == building) {
+            v.1 = kind;
+            return;
+        }
+        self.pending.push_back((building, kind));
+    }
+
+    pub fn is_pending(&self, building: BuildingID) -> bool {
+        self.pending.iter().any(|(b, _)| *b == building)
+    }
+
+    pub fn n_pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+pub fn rezoning_system(sim: &mut Simulation) {
+    profiling::scope!("map_dynamic::rezoning_system");
+    if !sim.read::<GameTime>().tick(REZONE_INTERVAL_SECS) {
+        return;
+    }
+
+    replace_next_house(sim);
+    build_shop(sim);
+}
+
+fn replace_next_house(sim: &mut Simulation) {
+    loop {
+        let Some((b, kind)) = sim.write::<Rezoning>().pending.pop_front() else {
+            return;
+        };
+        let is_house = sim
+            .map()
+            .buildings()
+            .get(b)
+            .map_or(false, |b| matches!(b.kind, BuildingKind::House));
+        if !is_house {
+            continue;
+        }
+        if kind != LotKind::Residential {
+            sim.map_mut().rezone_building(b, kind);
+        }
+        return;
+    }
+}
+
+/// Builds a shop on a commercial lot. The shop is picked among the unlocked stores that fit on
+/// the lot, depending only on the lot position so that it is the same on every client.
+fn build_shop(sim: &mut Simulation) {
+    let map = sim.map();
+    let Some(lot) = map.lots().values().find(|l| l.kind == LotKind::Commercial) else {
+        return;
+    };
+    let shape = lot.shape;
+    drop(map);
+
+    let [side, front] = shape.axis();
+    let lot_size = side.mag().min(front.mag());
+    let registry = sim.read::<GoodsCompanyRegistry>();
+    let milestones = sim.read::<Milestones>();
+    let shops: Vec<_> = registry
+        .descriptions
+        .values()
+        .filter(|d| {
+            matches!(d.kind, CompanyKind::Store)
+                && d.service.is_none()
+                && d.teaches.is_none()
+                && d.zone.is_none()
+                // shops don't cover the whole lot, so that they don't clean the neighbouring lots
+                && d.size < lot_size - 1.0
+                && milestones
+                    .locked_by(BuildingKind::GoodsCompany(d.id), &registry)
+                    .is_none()
+        })
+        .map(|d| (d.id, d.bgen, d.size))
+        .collect();
+    drop((registry, milestones));
+
+    let c = shape.center();
+    let Some(&(id, gen, size)) =
+        shops.get((common::rand::rand3(c.x, c.y, 23.0) * shops.len() as f32) as usize)
+    else {
+        return;
+    };
+    let obb = OBB::new(c, front.normalize(), size, size);
+    let build =
+        sim.map_mut()
+            .build_special_building(&obb, BuildingKind::GoodsCompany(id), gen, None);
+    if let Some(build) = build {
+        insert_building_info(sim, build);
+        push_construction_event(sim, build);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_dynamic::BuildingInfos;
+    use crate::tests::TestCtx;
+    use geom::{vec2, vec3};
+
+    #[test]
+    fn shops_are_built_on_commercial_lots() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(150.0, 30.0));
+        test.g.write::<Milestones>().enabled = false;
+
+        test.g.write::<Rezoning>().queue(house, LotKind::Commercial);
+        replace_next_house(&mut test.g);
+        assert!(!test.g.map().buildings().contains_key(house));
+        let n_commercial = |test: &TestCtx| {
+            let map = test.g.map();
+            map.lots()
+                .values()
+                .filter(|l| l.kind == LotKind::Commercial)
+                .count()
+        };
+        assert_eq!(n_commercial(&test), 1);
+
+        build_shop(&mut test.g);
+        assert_eq!(n_commercial(&test), 0);
+        let map = test.g.map();
+        let (shop, _) = map
+            .buildings()
+            .iter()
+            .find(|(_, b)| b.kind.as_goods_company().is_some())
+            .unwrap();
+        assert!(test.g.read::<BuildingInfos>().get(shop).is_some());
+    }
+}
@@ -0,0 +1,367 @@
+use crate::economy::{Government, Money};
+use crate::map::{is_dirty, Building, BuildingID, BuildingKind, Map, ProjectFilter, ProjectKind};
+use crate::map_dynamic::{BuildingInfo, BuildingInfos};
+use crate::multiplayer::chat::{Message, MessageKind};
+use crate::multiplayer::MultiplayerState;
+use crate::souls::goods_company::GoodsCompanyRegistry;
+use crate::utils::events::{SimEventKind, SimEvents};
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::resources::Resources;
+use crate::utils::time::{GameInstant, GameTime, Tick, SECONDS_PER_HOUR};
+use crate::World;
+use common::descriptions::ServiceKind;
+use geom::{Color, Vec2};
+use serde::{Deserialize, Serialize};
+use slotmapd::SecondaryMap;
+
+/// Distance from a service building at which its coverage starts to fade, in meters
+pub const SERVICE_RADIUS: f32 = 400.0;
+/// Distance over which the coverage fades out past the service radius, in meters
+const SERVICE_FALLOFF: f32 = 300.0;
+/// Radius in which the neighbours of a building are counted for its density, in meters
+const DENSITY_RADIUS: f32 = 80.0;
+/// Number of neighbours at which a building is considered fully dense
+const DENSE_NEIGHBOURS: f32 = 12.0;
+/// Share of the risk a fully covered building still has
+const COVERED_RISK: f32 = 0.2;
+/// Risk added to a building twice as crowded as its capacity, relative to its normal risk
+const OVERCROWDING_RISK: f32 = 1.0;
+/// Probability that an incident happens during an hour for a building with a risk of 1
+const HOURLY_INCIDENT_CHANCE: f32 = 0.0005;
+/// Incidents kept for the inspector
+const MAX_INCIDENTS: usize = 64;
+const INCIDENT_ANNOUNCER: &str = "Emergency";
+
+/// Risk of incidents of a building, with the factors it comes from
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct BuildingRisk {
+    /// In [0; 1] range, how packed the buildings around are
+    pub density: f32,
+    /// In [0; 1] range, how well each service covers the building, see [`ServiceKind::ALL`]
+    pub coverage: [f32; 3],
+    /// In [0; 1] range, how exposed the kind of building is to each kind of incident
+    pub exposure: [f32; 3],
+    /// Share of its capacity the building is over, see [`BuildingInfo::overcrowding`]
+    pub overcrowding: f32,
+}
+
+impl BuildingRisk {
+    pub fn new(kind: BuildingKind, density: f32, coverage: [f32; 3]) -> Self {
+        Self {
+            density,
+            coverage,
+            exposure: ServiceKind::ALL.map(|s| exposure(kind, s)),
+            overcrowding: 0.0,
+        }
+    }
+
+    pub fn coverage(&self, service: ServiceKind) -> f32 {
+        self.coverage[service as usize]
+    }
+
+    pub fn exposure(&self, service: ServiceKind) -> f32 {
+        self.exposure[service as usize]
+    }
+
+    /// In [0; 1] range, dense, overcrowded and uncovered buildings are the most at risk
+    pub fn risk(&self, service: ServiceKind) -> f32 {
+        let density = 0.5 + 0.5 * self.density;
+        let mitigation = 1.0 - (1.0 - COVERED_RISK) * self.coverage(service);
+        let crowding = 1.0 + OVERCROWDING_RISK * self.overcrowding;
+        (self.exposure(service) * density * mitigation * crowding).min(1.0)
+    }
+}
+
+/// How exposed a kind of building is to an incident, before density and coverage
+fn exposure(kind: BuildingKind, service: ServiceKind) -> f32 {
+    match (kind, service) {
+        (BuildingKind::House, ServiceKind::Fire) => 0.6,
+        (BuildingKind::House, ServiceKind::Police) => 0.5,
+        (BuildingKind::House, ServiceKind::Health) => 0.8,
+        (BuildingKind::GoodsCompany(_), ServiceKind::Fire) => 0.8,
+        (BuildingKind::GoodsCompany(_), ServiceKind::Police) => 0.7,
+        (BuildingKind::GoodsCompany(_), ServiceKind::Health) => 0.3,
+        (_, ServiceKind::Fire) => 0.4,
+        (_, ServiceKind::Police) => 0.4,
+        (_, ServiceKind::Health) => 0.2,
+    }
+}
+
+/// Coverage given by a service building at the given distance, in [0; 1] range
+pub fn coverage_at(distance: f32, staffing: f32) -> f32 {
+    let fade = ((distance - SERVICE_RADIUS) / SERVICE_FALLOFF).clamp(0.0, 1.0);
+    staffing.clamp(0.0, 1.0) * (1.0 - fade)
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Incident {
+    pub building: BuildingID,
+    pub kind: ServiceKind,
+    pub at: GameInstant,
+    pub cost: Money,
+}
+
+/// BuildingRisks holds the risk of fires, crimes and health emergencies of every building.
+/// Risks grow with the density and are lowered by the fire stations, police stations and clinics
+/// nearby, they drive the probability of incidents which cost money to the city.
+#[derive(Default, Serialize, Deserialize)]
+pub struct BuildingRisks {
+    risks: SecondaryMap<BuildingID, BuildingRisk>,
+    incidents: Vec<Incident>,
+    /// Version of the dirty regions of the map the risks are up to date with
+    #[serde(skip)]
+    map_version: Option<u64>,
+    /// Service buildings the coverage was computed with
+    #[serde(skip)]
+    services: Vec<(Vec2, ServiceKind, f32)>,
+}
+
+impl BuildingRisks {
+    pub fn get(&self, building: BuildingID) -> Option<&BuildingRisk> {
+        self.risks.get(building)
+    }
+
+    /// The most recent incidents, oldest first
+    pub fn incidents(&self) -> &[Incident] {
+        &self.incidents
+    }
+
+    pub fn update(
+        &mut self,
+        world: &World,
+        map: &Map,
+        registry: &GoodsCompanyRegistry,
+        binfos: &BuildingInfos,
+    ) {
+        let services = service_buildings(world, map, registry);
+
+        // only the buildings close to what changed since the last update are recomputed
+        let (dirty, version) = map.dirty_since(self.map_version);
+        self.map_version = Some(version);
+        let changed_services: Vec<Vec2> = services
+            .iter()
+            .filter(|s| !self.services.contains(s))
+            .chain(self.services.iter().filter(|s| !services.contains(s)))
+            .map(|&(p, _, _)| p)
+            .collect();
+        if dirty.is_none() {
+            self.risks.clear();
+        }
+        let dirty = dirty.unwrap_or_default();
+        self.risks.retain(|id, _| map.buildings().contains_key(id));
+
+        for b in map.buildings().values() {
+            let pos = b.door_pos.xy();
+            let prev = self.risks.get(b.id);
+            let density = match prev {
+                Some(r) if !is_dirty(&dirty, b.obb.center(), DENSITY_RADIUS) => r.density,
+                _ => density(map, b),
+            };
+            let covered_by_changed = changed_services
+                .iter()
+                .any(|p| p.distance(pos) < SERVICE_RADIUS + SERVICE_FALLOFF);
+            let coverage = match prev {
+                Some(r) if !covered_by_changed => r.coverage,
+                _ => ServiceKind::ALL.map(|s| {
+                    services
+                        .iter()
+                        .filter(|(_, service, _)| *service == s)
+                        .map(|&(p, _, staffing)| coverage_at(p.distance(pos), staffing))
+                        .fold(0.0, f32::max)
+                }),
+            };
+            let overcrowding = binfos.get(b.id).map_or(0.0, BuildingInfo::overcrowding);
+            self.risks.insert(
+                b.id,
+                BuildingRisk {
+                    overcrowding,
+                    ..BuildingRisk::new(b.kind, density, coverage)
+                },
+            );
+        }
+        self.services = services;
+    }
+}
+
+/// Position, service and staffing of every service building
+pub(crate) fn service_buildings(
+    world: &World,
+    map: &Map,
+    registry: &GoodsCompanyRegistry,
+) -> Vec<(Vec2, ServiceKind, f32)> {
+    world
+        .companies
+        .values()
+        .filter_map(|c| {
+            let b = map.buildings().get(c.comp.building)?;
+            let service = registry
+                .descriptions
+                .get(b.kind.as_goods_company()?)?
+                .service?;
+            let staffing = c.comp.productivity(c.workers.0.len() as f32, None);
+            Some((b.door_pos.xy(), service, staffing))
+        })
+        .collect()
+}
+
+fn density(map: &Map, b: &Building) -> f32 {
+    let neighbours = map
+        .spatial_map()
+        .query_around(b.obb.center(), DENSITY_RADIUS, ProjectFilter::BUILDING)
+        .filter(|k| matches!(k, ProjectKind::Building(id) if *id != b.id))
+        .count();
+    (neighbours as f32 / DENSE_NEIGHBOURS).min(1.0)
+}
+
+/// Cost to the city of an incident
+fn incident_cost(kind: ServiceKind) -> Money {
+    match kind {
+        ServiceKind::Fire => Money::new_bucks(500),
+        ServiceKind::Police => Money::new_bucks(200),
+        ServiceKind::Health => Money::new_bucks(300),
+    }
+}
+
+fn incident_text(kind: ServiceKind, building: BuildingKind) -> String {
+    let place = match building {
+        BuildingKind::House => "a house",
+        _ => "a building",
+    };
+    match kind {
+        ServiceKind::Fire => format!("A fire broke out in {}!", place),
+        ServiceKind::Police => format!("{} was burglarized!", capitalize(place)),
+        ServiceKind::Health => format!("A medical emergency happened in {}!", place),
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut c = s.chars();
+    c.next()
+        .map(|f| f.to_uppercase().chain(c).collect())
+        .unwrap_or_default()
+}
+
+pub fn building_risk_update(world: &mut World, res: &mut Resources) {
+    profiling::scope!("map_dynamic::building_risk_update");
+    let time = *res.read::<GameTime>();
+    if !time.tick(SECONDS_PER_HOUR as u32) {
+        return;
+    }
+    let map = res.read::<Map>();
+    let mut risks = res.write::<BuildingRisks>();
+    risks.update(
+        world,
+        &map,
+        &res.read::<GoodsCompanyRegistry>(),
+        &res.read::<BuildingInfos>(),
+    );
+
+    let mut rng = res.write::<RandProvider>();
+    let mut incidents = vec![];
+    for (id, risk) in risks.risks.iter() {
+        for s in ServiceKind::ALL {
+            if rng.next_f32() < risk.risk(s) * HOURLY_INCIDENT_CHANCE {
+                incidents.push((id, s));
+            }
+        }
+    }
+    if incidents.is_empty() {
+        return;
+    }
+
+    let mut state = res.write::<MultiplayerState>();
+    let mut gov = res.write::<Government>();
+    let mut events = res.write::<SimEvents>();
+    let tick = *res.read::<Tick>();
+    for (building, kind) in incidents {
+        let Some(b) = map.buildings().get(building) else {
+            continue;
+        };
+        let cost = incident_cost(kind);
+        gov.spend(cost, "Incident", time.timestamp);
+        risks.incidents.push(Incident {
+            building,
+            kind,
+            at: time.instant(),
+            cost,
+        });
+        events.push(tick, b.door_pos, SimEventKind::Incident);
+        state.chat.add_message(Message {
+            name: INCIDENT_ANNOUNCER.to_string(),
+            text: incident_text(kind, b.kind),
+            sent_at: time.instant(),
+            color: Color::new(1.0, 0.4, 0.3, 1.0),
+            kind: MessageKind::Warning,
+        });
+    }
+    let extra = risks.incidents.len().saturating_sub(MAX_INCIDENTS);
+    risks.incidents.drain(..extra);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use geom::{vec2, vec3};
+
+    #[test]
+    fn risk_factors() {
+        let test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(150.0, 20.0));
+
+        let mut risks = BuildingRisks::default();
+        risks.update(
+            test.g.world(),
+            &test.g.map(),
+            &test.g.read::<GoodsCompanyRegistry>(),
+            &test.g.read::<BuildingInfos>(),
+        );
+        let risk = risks.get(house).unwrap();
+        assert_eq!(risk.coverage(ServiceKind::Fire), 0.0);
+        assert!(risk.risk(ServiceKind::Fire) > 0.0);
+
+        let covered = BuildingRisk::new(BuildingKind::House, risk.density, [1.0; 3]);
+        assert!(covered.risk(ServiceKind::Fire) < risk.risk(ServiceKind::Fire));
+
+        let crowded = BuildingRisk {
+            overcrowding: 1.0,
+            ..*risk
+        };
+        assert!(crowded.risk(ServiceKind::Fire) > risk.risk(ServiceKind::Fire));
+
+        assert_eq!(coverage_at(100.0, 1.0), 1.0);
+        assert_eq!(coverage_at(100.0, 0.5), 0.5);
+        assert!(coverage_at(SERVICE_RADIUS + SERVICE_FALLOFF * 0.5, 1.0) < 1.0);
+        assert_eq!(coverage_at(SERVICE_RADIUS + SERVICE_FALLOFF, 1.0), 0.0);
+    }
+
+    #[test]
+    fn risks_are_recomputed_around_changes() {
+        let test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(150.0, 20.0));
+        let update = |risks: &mut BuildingRisks| {
+            risks.update(
+                test.g.world(),
+                &test.g.map(),
+                &test.g.read::<GoodsCompanyRegistry>(),
+                &test.g.read::<BuildingInfos>(),
+            )
+        };
+
+        let mut risks = BuildingRisks::default();
+        update(&mut risks);
+        let alone = risks.get(house).unwrap().density;
+
+        let neighbour = test.build_house_near(vec2(180.0, 20.0));
+        update(&mut risks);
+        let mut full = BuildingRisks::default();
+        update(&mut full);
+
+        assert!(risks.get(house).unwrap().density > alone);
+        for b in [house, neighbour] {
+            assert_eq!(risks.get(b).unwrap().density, full.get(b).unwrap().density);
+        }
+    }
+}
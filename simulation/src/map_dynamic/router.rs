@@ -1,12 +1,19 @@
-use crate::map::{BuildingID, Map, PathKind};
-use crate::map_dynamic::{Itinerary, ParkingManagement, ParkingReserveError, SpotReservation};
-use crate::physics::CollisionWorld;
-use crate::transportation::{put_pedestrian_in_coworld, unpark, Location, VehicleState};
+use crate::map::{BuildingID, DoorRole, Map, PathKind};
+use crate::map_dynamic::{
+    District, Itinerary, Journey, Leg, LegMode, ParkingManagement, ParkingPressure,
+    ParkingReserveError, Policies, SpotReservation, TripStats,
+};
+use crate::transportation::bus::{BusLines, BusTrip, MAX_BUS_WAIT};
+use crate::transportation::metro::{MetroLines, MetroTrip};
+use crate::transportation::{
+    put_pedestrian_in_coworld, unpark, unpark_from_building, Location, VehicleKind, VehicleState,
+};
 use crate::utils::resources::Resources;
+use crate::utils::time::{GameInstant, GameTime, Tick, TICKS_PER_SECOND};
 use crate::world::{HumanEnt, HumanID, VehicleEnt, VehicleID};
 use crate::{ParCommandBuffer, World};
 use egui_inspect::Inspect;
-use geom::{Spline3, Transform, Vec3};
+use geom::{Radians, Spline3, Transform, Vec2, Vec3};
 use serde::{Deserialize, Serialize};
 use slotmapd::HopSlotMap;
 
@@ -19,6 +26,22 @@ pub struct Router {
     vehicle: Option<VehicleID>,
     pub personal_car: Option<VehicleID>,
     pub last_error: Option<RouterError>,
+    /// How the current destination is reached
+    mode: LegMode,
+    /// Remaining legs of the planned tour, reversed, allows for efficient popping
+    tour: Vec<Leg>,
+    /// Start of the journey to the current building destination, for the trip statistics
+    #[inspect(skip)]
+    journey: Option<JourneyStart>,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+struct JourneyStart {
+    tick: Tick,
+    from: Vec3,
+    drove: bool,
+    /// Rode the bus or the metro
+    bus: bool,
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
@@ -48,14 +71,45 @@ pub enum RoutingStep {
     GetOutVehicle(VehicleID),
     GetInBuilding(BuildingID),
     GetOutBuilding(BuildingID),
+    /// Gets in the vehicle kept in the building and leaves through the driveway
+    UnparkFromBuilding(VehicleID, BuildingID),
+    /// Stops the vehicle on the lane where it is, blocking it, because no spot was found
+    DoublePark(VehicleID),
+    /// Looks for a free spot around the objective once the vehicle arrived near it, and cruises
+    /// around the block to try again when there is none. Counts the laps already done
+    FindParking(VehicleID, Vec3, u8),
+    /// Waits at the stop until a bus of the line picks the human up, walks instead after
+    /// [`MAX_BUS_WAIT`] since the instant the wait started
+    WaitForBus(BusTrip, GameInstant),
+    /// Rides the bus until it drops the human off at the stop
+    RideBus(BusTrip),
+    /// Waits at the entrance of the station until a train of the line picks the human up
+    WaitForMetro(MetroTrip),
+    /// Rides the metro until it drops the human off at the station
+    RideMetro(MetroTrip),
 }
 
 debug_inspect_impl!(RoutingStep);
 
+/// Percentage of the drivers that park illegally instead of cruising again when they still find
+/// no spot after a first lap
+const ILLEGAL_PARKERS_PERCENT: u64 = 30;
+/// Drivers only park in the spots this close to their objective, in meters
+const MAX_PARKING_WALK: f32 = 300.0;
+/// Drivers looking for a spot drive by the roads this far around their objective, in meters
+const CRUISE_RADIUS: f32 = 150.0;
+/// Laps around the objective before the drivers give up and double park
+const MAX_CRUISE_LAPS: u8 = 4;
+
 pub fn routing_changed_system(world: &mut World, resources: &mut Resources) {
     profiling::scope!("map_dynamic::routing_changed_system");
     let map: &Map = &resources.read();
     let parking: &mut ParkingManagement = &mut resources.write();
+    let policies: &Policies = &resources.read();
+    let lines: &BusLines = &resources.read();
+    let metro_lines: &MetroLines = &resources.read();
+    let tick = *resources.read::<Tick>();
+    let now = resources.read::<GameTime>().instant();
 
     world.humans.values_mut().for_each(|h| {
         let router = &mut h.router;
@@ -64,17 +118,42 @@ pub fn routing_changed_system(world: &mut World, resources: &mut Resources) {
             return;
         }
         let dest = unwrap_ret!(router.target_dest);
+        // Passengers get off at their stop before going anywhere else
+        if matches!(
+            router.cur_step,
+            Some(RoutingStep::RideBus(_) | RoutingStep::RideMetro(_))
+        ) {
+            return;
+        }
 
         router.clear_steps(parking);
         match dest {
             Destination::Outside(pos) => {
-                router.steps = match router.steps_to(pos, parking, map, loc, &world.vehicles) {
+                let can_park = router.mode == LegMode::Drive
+                    && !policies.parking_restricted(pos.xy())
+                    && !leaves_car_for_transit(
+                        policies,
+                        lines,
+                        metro_lines,
+                        map,
+                        h.trans.position,
+                        pos,
+                    );
+                router.steps = match router.steps_to(pos, can_park, map, loc, &world.vehicles) {
                     Ok(x) => x,
                     Err(e) => {
                         router.last_error = Some(e);
                         return;
                     }
                 };
+                take_transit(
+                    &mut router.steps,
+                    h.trans.position,
+                    now,
+                    lines,
+                    metro_lines,
+                    map,
+                );
             }
             Destination::Building(build) => {
                 if let Location::Building(cur_build) = loc {
@@ -91,15 +170,48 @@ pub fn routing_changed_system(world: &mut World, resources: &mut Resources) {
                         return;
                     }
                 };
-                let door_pos = bobj.door_pos;
-                router.steps = match router.steps_to(door_pos, parking, map, loc, &world.vehicles) {
+                // Truck drivers deliver through the freight dock, everyone else uses the front door
+                let role = match router.vehicle.and_then(|v| world.vehicles.get(v)) {
+                    Some(v) if matches!(v.vehicle.kind, VehicleKind::Truck) => {
+                        DoorRole::FreightDock
+                    }
+                    _ => DoorRole::Pedestrian,
+                };
+                let door_pos = bobj.door(role);
+                // Residents can always park at home
+                let can_park = router.mode == LegMode::Drive
+                    && (build == h.home.house || !policies.parking_restricted(door_pos.xy()))
+                    && !leaves_car_for_transit(
+                        policies,
+                        lines,
+                        metro_lines,
+                        map,
+                        h.trans.position,
+                        door_pos,
+                    );
+                router.steps = match router.steps_to(door_pos, can_park, map, loc, &world.vehicles)
+                {
                     Ok(x) => x,
                     Err(e) => {
                         router.last_error = Some(e);
                         return;
                     }
                 };
+                take_transit(
+                    &mut router.steps,
+                    h.trans.position,
+                    now,
+                    lines,
+                    metro_lines,
+                    map,
+                );
                 router.steps.push(RoutingStep::GetInBuilding(build));
+                router.journey = Some(JourneyStart {
+                    tick,
+                    from: h.trans.position,
+                    drove: false,
+                    bus: false,
+                });
             }
         }
 
@@ -114,6 +226,14 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
     let map: &Map = &resources.read();
     let cbuf_human: &ParCommandBuffer<HumanEnt> = &resources.read();
     let cbuf_vehicle: &ParCommandBuffer<VehicleEnt> = &resources.read();
+    let lines: &BusLines = &resources.read();
+    let metro_lines: &MetroLines = &resources.read();
+    let parking: &mut ParkingManagement = &mut resources.write();
+    let tick = *resources.read::<Tick>();
+    let time = *resources.read::<GameTime>();
+    let mut access_times = vec![];
+    let mut journeys = vec![];
+    let mut searches = vec![];
 
     world.humans.iter_mut().for_each(|(body, h)| {
         if h.router.cur_step.is_none() && h.router.steps.is_empty() {
@@ -135,6 +255,11 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
                 .get(id)
                 .map(|b| b.door_pos)
                 .unwrap_or_else(|| trans.position),
+            Location::Train(id) => world
+                .trains
+                .get(id)
+                .map(|x| x.trans.position)
+                .unwrap_or_else(|| trans.position),
         };
 
         let mut cur_step_over = true;
@@ -159,6 +284,30 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
                 RoutingStep::GetOutVehicle(_) => true,
                 RoutingStep::GetInBuilding(_) => true,
                 RoutingStep::GetOutBuilding(_) => true,
+                RoutingStep::UnparkFromBuilding(_, _) => true,
+                RoutingStep::DoublePark(_) => true,
+                RoutingStep::FindParking(..) => true,
+                RoutingStep::WaitForBus(trip, since) => {
+                    // the line may have changed while waiting or the bus is late, the human
+                    // walks instead
+                    h.location != Location::Outside
+                        || since.elapsed(&time) > MAX_BUS_WAIT
+                        || !lines.get(trip.line).map_or(false, |l| {
+                            l.is_running()
+                                && l.stops.contains(&trip.from)
+                                && l.stops.contains(&trip.to)
+                        })
+                }
+                RoutingStep::RideBus(_) => !matches!(h.location, Location::Vehicle(_)),
+                RoutingStep::WaitForMetro(trip) => {
+                    h.location != Location::Outside
+                        || !metro_lines.get(trip.line).map_or(false, |l| {
+                            l.is_running()
+                                && l.stations.contains(&trip.from)
+                                && l.stations.contains(&trip.to)
+                        })
+                }
+                RoutingStep::RideMetro(_) => !matches!(h.location, Location::Train(_)),
             };
         }
         let mut next_step_ready = true;
@@ -178,9 +327,16 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
                 RoutingStep::GetInBuilding(build) => map
                     .buildings()
                     .get(build)
-                    .map(|b| b.door_pos.is_close(pos, 3.0))
+                    .map(|b| b.is_at_door(pos, 3.0))
                     .unwrap_or(true),
                 RoutingStep::GetOutBuilding(_) => true,
+                RoutingStep::UnparkFromBuilding(_, _) => true,
+                RoutingStep::DoublePark(_) => true,
+                RoutingStep::FindParking(..) => true,
+                RoutingStep::WaitForBus(..) => true,
+                RoutingStep::RideBus(_) => true,
+                RoutingStep::WaitForMetro(_) => true,
+                RoutingStep::RideMetro(_) => true,
             };
         }
 
@@ -193,12 +349,11 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
         if let Some(ref mut next_step) = h.router.cur_step {
             match *next_step {
                 RoutingStep::WalkTo(obj) => {
-                    h.it = Itinerary::wait_for_reroute(PathKind::Pedestrian, obj);
+                    h.it = Itinerary::wait_for_reroute(h.pedestrian.mobility.path_kind(), obj);
                 }
                 RoutingStep::DriveTo(vehicle, obj) => {
-                    let route = Itinerary::wait_for_reroute(PathKind::Vehicle, obj);
                     if let Some(x) = world.vehicles.get_mut(vehicle) {
-                        x.it = route
+                        x.it = Itinerary::wait_for_reroute(x.vehicle.kind.path_kind(), obj);
                     }
                 }
                 RoutingStep::Park(vehicle, ref mut spot) => {
@@ -213,15 +368,70 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
                         }
                     }
                 }
+                RoutingStep::DoublePark(vehicle) => {
+                    if let Some(v) = world.vehicles.get_mut(vehicle) {
+                        v.vehicle.state = VehicleState::DoubleParked(time.instant());
+                        v.speed.0 = 0.0;
+                    }
+                }
+                RoutingStep::FindParking(vehicle, obj, laps) => {
+                    let Some(kind) = world.vehicles.get(vehicle).map(|v| v.vehicle.kind) else {
+                        h.router.reset_dest();
+                        return;
+                    };
+                    let spot = find_spot(parking, map, kind, obj);
+                    searches.push((District::of(obj.xy()), spot.is_some()));
+
+                    let steps = &mut h.router.steps;
+                    if let Some((spot_resa, park_pos)) = spot {
+                        steps.push(RoutingStep::Park(vehicle, Some(spot_resa)));
+                        steps.push(RoutingStep::DriveTo(vehicle, park_pos));
+                    } else if laps >= MAX_CRUISE_LAPS
+                        || (laps > 0 && common::hash_u64(vehicle) % 100 < ILLEGAL_PARKERS_PERCENT)
+                    {
+                        steps.push(RoutingStep::DoublePark(vehicle));
+                    } else {
+                        steps.push(RoutingStep::FindParking(vehicle, obj, laps + 1));
+                        if let Some(pos) = cruise_pos(map, obj, vehicle, laps) {
+                            steps.push(RoutingStep::DriveTo(vehicle, pos));
+                        }
+                    }
+                }
                 RoutingStep::Unpark(vehicle) => {
                     cbuf_vehicle.exec_ent(vehicle, move |sim| unpark(sim, vehicle));
                 }
+                RoutingStep::UnparkFromBuilding(vehicle, build) => {
+                    if !world.vehicles.contains_key(vehicle) {
+                        h.router.reset_dest();
+                        return;
+                    }
+                    h.location = Location::Vehicle(vehicle);
+                    if let Some(ref mut journey) = h.router.journey {
+                        journey.drove = true;
+                    }
+                    walk_inside(body, h, cbuf_human);
+                    cbuf_vehicle.exec_ent(vehicle, move |sim| {
+                        unpark_from_building(sim, vehicle, build)
+                    });
+                }
                 RoutingStep::GetInVehicle(vehicle) => {
                     if !world.vehicles.contains_key(vehicle) {
                         h.router.reset_dest();
                         return;
                     }
+                    if let Some(r) = h.it.get_route() {
+                        if matches!(r.query.kind, PathKind::Pedestrian | PathKind::StepFree) {
+                            let ticks = tick.0.saturating_sub(r.query.tick.0);
+                            access_times.push((
+                                ticks as f32 / TICKS_PER_SECOND as f32,
+                                h.pedestrian.mobility,
+                            ));
+                        }
+                    }
                     h.location = Location::Vehicle(vehicle);
+                    if let Some(ref mut journey) = h.router.journey {
+                        journey.drove = true;
+                    }
                     walk_inside(body, h, cbuf_human);
                 }
                 RoutingStep::GetOutVehicle(vehicle) => {
@@ -239,8 +449,32 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
                         return;
                     }
                     h.location = Location::Building(build);
+                    if let Some(journey) = h.router.journey.take() {
+                        journeys.push(Journey {
+                            seconds: tick.0.saturating_sub(journey.tick.0) as f32
+                                / TICKS_PER_SECOND as f32,
+                            distance: journey.from.xy().distance(pos.xy()),
+                            mode: if journey.drove {
+                                LegMode::Drive
+                            } else if journey.bus {
+                                LegMode::Bus
+                            } else {
+                                LegMode::Walk
+                            },
+                            commute: h.work.as_ref().map_or(false, |w| w.workplace == build),
+                        });
+                    }
                     walk_inside(body, h, cbuf_human);
                 }
+                RoutingStep::WaitForBus(_, ref mut since) => *since = time.instant(),
+                RoutingStep::WaitForMetro(_) => {}
+                RoutingStep::RideBus(_) | RoutingStep::RideMetro(_) => {
+                    if let (Location::Vehicle(_) | Location::Train(_), Some(ref mut journey)) =
+                        (&h.location, &mut h.router.journey)
+                    {
+                        journey.bus = true;
+                    }
+                }
                 RoutingStep::GetOutBuilding(build) => {
                     let wpos = map
                         .buildings()
@@ -251,27 +485,49 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
                 }
             }
         }
-    })
+    });
+
+    if !searches.is_empty() {
+        let mut pressure = resources.write::<ParkingPressure>();
+        for (district, found) in searches {
+            pressure.record(district, found);
+        }
+    }
+
+    if !access_times.is_empty() || !journeys.is_empty() {
+        let mut trips = resources.write::<TripStats>();
+        for (seconds, mobility) in access_times {
+            trips.record_access(seconds, mobility);
+        }
+        for journey in journeys {
+            trips.record_journey(journey);
+        }
+    }
 }
 
-fn walk_inside(body: HumanID, h: &mut HumanEnt, cbuf: &ParCommandBuffer<HumanEnt>) {
+pub(crate) fn walk_inside(body: HumanID, h: &mut HumanEnt, cbuf: &ParCommandBuffer<HumanEnt>) {
     if let Some(coll) = h.collider.take() {
         cbuf.exec_ent(body, coll.destroy());
     }
     h.speed.0 = 0.0;
 }
 
-fn walk_outside(body: HumanID, pos: Vec3, cbuf: &ParCommandBuffer<HumanEnt>, loc: &mut Location) {
+pub(crate) fn walk_outside(
+    body: HumanID,
+    pos: Vec3,
+    cbuf: &ParCommandBuffer<HumanEnt>,
+    loc: &mut Location,
+) {
     *loc = Location::Outside;
     cbuf.exec_ent(body, move |sim| {
-        let coll = put_pedestrian_in_coworld(&mut sim.write::<CollisionWorld>(), pos);
+        let coll = put_pedestrian_in_coworld(sim, pos);
         let h = unwrap_ret!(sim.world.humans.get_mut(body));
         h.trans.position = pos;
         h.collider = Some(coll);
     });
 }
 
-fn park(map: &Map, vehicle: &mut VehicleEnt, spot_resa: SpotReservation) {
+pub(crate) fn park(map: &Map, vehicle: &mut VehicleEnt, spot_resa: SpotReservation) {
     let trans = vehicle.trans;
     let spot = match spot_resa.get(&map.parking) {
         Some(x) => x,
@@ -302,13 +558,56 @@ impl Router {
             vehicle: personal_car,
             cur_dest: None,
             last_error: None,
+            mode: LegMode::Drive,
+            tour: vec![],
+            journey: None,
         }
     }
 
+    /// Resets the router of a recycled human, keeping the allocation of the steps
+    pub(crate) fn reset(&mut self, personal_car: Option<VehicleID>) {
+        self.steps.clear();
+        self.cur_step = None;
+        self.target_dest = None;
+        self.cur_dest = None;
+        self.vehicle = personal_car;
+        self.personal_car = personal_car;
+        self.last_error = None;
+        self.mode = LegMode::Drive;
+        self.tour.clear();
+        self.journey = None;
+    }
+
     pub fn use_vehicle(&mut self, v: Option<VehicleID>) {
         self.vehicle = v;
     }
 
+    /// The vehicle the human currently travels with
+    pub fn vehicle(&self) -> Option<VehicleID> {
+        self.vehicle
+    }
+
+    /// The bus trip of the human waiting at a stop
+    pub fn waiting_for_bus(&self) -> Option<BusTrip> {
+        match self.cur_step {
+            Some(RoutingStep::WaitForBus(trip, _)) => Some(trip),
+            _ => None,
+        }
+    }
+
+    /// Whether the human drives around the objective because there was no free spot near it
+    pub fn is_cruising(&self) -> bool {
+        matches!(self.steps.last(), Some(RoutingStep::FindParking(_, _, laps)) if *laps > 0)
+    }
+
+    /// The metro trip of the human waiting at a station entrance
+    pub fn waiting_for_metro(&self) -> Option<MetroTrip> {
+        match self.cur_step {
+            Some(RoutingStep::WaitForMetro(trip)) => Some(trip),
+            _ => None,
+        }
+    }
+
     pub(crate) fn clear_steps(&mut self, parking: &mut ParkingManagement) {
         for s in self.steps.drain(..).chain(self.cur_step.take()) {
             if let RoutingStep::Park(_, Some(spot)) = s {
@@ -323,54 +622,109 @@ impl Router {
 
     /// Returns wheter or not the destination was already attained
     pub fn go_to(&mut self, dest: Destination) -> bool {
+        self.go_to_by(dest, LegMode::Drive)
+    }
+
+    /// Same as [`Router::go_to`], choosing how to get there
+    pub fn go_to_by(&mut self, dest: Destination, mode: LegMode) -> bool {
         if let Some(router_dest) = self.cur_dest {
             if router_dest == dest {
                 return self.steps.is_empty() && self.cur_step.is_none();
             }
         }
         self.target_dest = Some(dest);
+        self.mode = mode;
+        false
+    }
+
+    /// Replaces the planned tour, the legs are traveled in order
+    pub fn plan_tour(&mut self, mut legs: Vec<Leg>) {
+        legs.reverse();
+        self.tour = legs;
+    }
+
+    /// The remaining legs of the tour, in order
+    pub fn tour(&self) -> impl Iterator<Item = &Leg> {
+        self.tour.iter().rev()
+    }
+
+    /// Goes to the next stop of the tour.
+    /// Returns true once a stop is reached, so that the errands can be run there,
+    /// or if there is nothing left to do.
+    pub fn follow_tour(&mut self) -> bool {
+        let Some(&leg) = self.tour.last() else {
+            return true;
+        };
+        if !self.go_to_by(leg.dest, leg.mode) {
+            return false;
+        }
+        self.tour.pop();
+        true
+    }
+
+    /// Whether the tour goes on towards the destination.
+    /// The tour is dropped if plans changed in the meantime.
+    pub fn continues_tour(&mut self, dest: Destination) -> bool {
+        if self.tour.first().map_or(false, |leg| leg.dest == dest) {
+            return true;
+        }
+        self.tour.clear();
         false
     }
 
+    /// Steps to go to the objective, driving there if the human has a car and can park near it.
+    /// The spot is only looked for once the car arrived near the objective
     fn steps_to(
         &mut self,
         obj: Vec3,
-        parking: &mut ParkingManagement,
+        can_park: bool,
         map: &Map,
         loc: &Location,
         cars: &HopSlotMap<VehicleID, VehicleEnt>,
     ) -> Result<Vec<RoutingStep>, RouterError> {
+        /// Cars parked this close to the building are kept inside it and leave through its driveway
+        const DRIVEWAY_CAR_DIST: f32 = 40.0;
+
+        let drive = can_park || matches!(loc, Location::Vehicle(_));
+        let car = self.vehicle.filter(|_| drive);
+
+        let driveway = match (loc, car.and_then(|car| cars.get(car))) {
+            (Location::Building(cur_build), Some(v)) => map
+                .buildings()
+                .get(*cur_build)
+                .filter(|b| b.driveway().is_some())
+                .filter(|b| v.trans.position.is_close(b.door_pos, DRIVEWAY_CAR_DIST))
+                .map(|_| *cur_build),
+            _ => None,
+        };
+
         let mut steps = vec![];
         if let Location::Building(cur_build) = loc {
-            steps.push(RoutingStep::GetOutBuilding(*cur_build));
+            if driveway.is_none() {
+                steps.push(RoutingStep::GetOutBuilding(*cur_build));
+            }
         }
 
-        if let Some(car) = self.vehicle {
-            let spot_resa = parking
-                .reserve_near(obj, map)
-                .map_err(RouterError::ReservingParkingSpot)?;
-            let parking_pos = match spot_resa.park_pos(map) {
-                Some(x) => x,
-                None => {
-                    parking.free(spot_resa);
-                    return Err(RouterError::TranslatingParkingSpotToDrivePos);
-                }
-            };
+        if let Some(car) = car {
+            let arrival = double_parking_pos(map, obj).ok_or(RouterError::ReservingParkingSpot(
+                ParkingReserveError::FindingNearestLane,
+            ))?;
 
-            if !matches!(loc, Location::Vehicle(_)) {
+            if let Some(build) = driveway {
+                steps.push(RoutingStep::UnparkFromBuilding(car, build));
+            } else if !matches!(loc, Location::Vehicle(_)) {
                 if let Some(pos) = cars.get(car).map(|x| x.trans.position) {
                     steps.push(RoutingStep::WalkTo(pos));
                     steps.push(RoutingStep::GetInVehicle(car));
                     steps.push(RoutingStep::Unpark(car));
                 } else {
-                    parking.free(spot_resa);
                     self.vehicle = None;
                     return Err(RouterError::LocatingVehicle);
                 }
             }
 
-            steps.push(RoutingStep::DriveTo(car, parking_pos));
-            steps.push(RoutingStep::Park(car, Some(spot_resa)));
+            steps.push(RoutingStep::DriveTo(car, arrival));
+            steps.push(RoutingStep::FindParking(car, obj, 0));
             steps.push(RoutingStep::GetOutVehicle(car));
         }
 
@@ -378,3 +732,91 @@ impl Router {
         Ok(steps)
     }
 }
+
+/// Drivers going to a district with free transit leave their car if a line takes them there
+pub(crate) fn leaves_car_for_transit(
+    policies: &Policies,
+    lines: &BusLines,
+    metro_lines: &MetroLines,
+    map: &Map,
+    from: Vec3,
+    obj: Vec3,
+) -> bool {
+    policies.free_transit(obj.xy())
+        && (metro_lines.plan_trip(map, from, obj).is_some()
+            || lines.plan_trip(map, from, obj).is_some())
+}
+
+/// Takes the metro, or else the bus, instead of walking to the objective if a line gets there
+/// faster
+fn take_transit(
+    steps: &mut Vec<RoutingStep>,
+    from: Vec3,
+    now: GameInstant,
+    lines: &BusLines,
+    metro_lines: &MetroLines,
+    map: &Map,
+) {
+    if steps.iter().any(|s| matches!(s, RoutingStep::DriveTo(..))) {
+        return;
+    }
+    let Some(&RoutingStep::WalkTo(obj)) = steps.last() else {
+        return;
+    };
+    let legs = if let Some((trip, wait_at)) = metro_lines.plan_trip(map, from, obj) {
+        [
+            RoutingStep::WalkTo(wait_at),
+            RoutingStep::WaitForMetro(trip),
+            RoutingStep::RideMetro(trip),
+        ]
+    } else if let Some((trip, wait_at)) = lines.plan_trip(map, from, obj) {
+        [
+            RoutingStep::WalkTo(wait_at),
+            RoutingStep::WaitForBus(trip, now),
+            RoutingStep::RideBus(trip),
+        ]
+    } else {
+        return;
+    };
+    let last = steps.len() - 1;
+    steps.splice(last..last, legs);
+}
+
+/// Reserves a free spot close enough to walk to the objective, with where to stop on the road
+/// before parking in it. Trucks unload in the loading zones
+fn find_spot(
+    parking: &mut ParkingManagement,
+    map: &Map,
+    kind: VehicleKind,
+    obj: Vec3,
+) -> Option<(SpotReservation, Vec3)> {
+    let spot_resa = match kind {
+        VehicleKind::Truck => parking.reserve_loading_near(obj, map),
+        _ => parking.reserve_near(obj, map),
+    }
+    .ok()?;
+    let close = spot_resa
+        .get(&map.parking)
+        .map_or(false, |s| s.trans.position.is_close(obj, MAX_PARKING_WALK));
+    match spot_resa.park_pos(map) {
+        Some(pos) if close => Some((spot_resa, pos)),
+        _ => {
+            parking.free(spot_resa);
+            None
+        }
+    }
+}
+
+/// A point on the roads around the objective to drive by while looking for a spot, every lap
+/// goes around another side of it
+fn cruise_pos(map: &Map, obj: Vec3, vehicle: VehicleID, laps: u8) -> Option<Vec3> {
+    let angle = (common::hash_u64(vehicle) % 4 + laps as u64) as f32 * std::f32::consts::FRAC_PI_2;
+    let dir = Vec2::from_angle(Radians(angle));
+    double_parking_pos(map, obj + (dir * CRUISE_RADIUS).z0())
+}
+
+/// Where to stop on the lane closest to the objective when parking illegally
+fn double_parking_pos(map: &Map, obj: Vec3) -> Option<Vec3> {
+    let lane = map.nearest_driving_lane(obj, None)?;
+    Some(map.lanes().get(lane)?.points.project(obj))
+}
@@ -0,0 +1,103 @@
+use crate::map::{BuildingID, Map};
+use crate::map_dynamic::Destination;
+use geom::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// Stops closer than this to the previous one are walked to, the car stays parked where it is
+/// and is picked up on the next driven leg
+pub const WALKING_DIST: f32 = 250.0;
+
+/// How a leg of a tour is traveled
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LegMode {
+    /// Walks, even when the human has a car
+    Walk,
+    /// Drives if the human has a car and can park near the stop
+    #[default]
+    Drive,
+    /// Never drives, takes the bus when a line gets there faster than walking
+    Bus,
+}
+
+debug_inspect_impl!(LegMode);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Leg {
+    pub dest: Destination,
+    pub mode: LegMode,
+}
+
+debug_inspect_impl!(Leg);
+
+/// Plans the legs of a tour going through the errands before reaching the destination.
+/// The errands are visited nearest first.
+pub fn plan_tour(map: &Map, from: Vec3, errands: &[BuildingID], dest: Destination) -> Vec<Leg> {
+    let buildings = map.buildings();
+    let mut errands: Vec<(Destination, Vec3)> = errands
+        .iter()
+        .filter(|&&b| Destination::Building(b) != dest)
+        .filter_map(|&b| Some((Destination::Building(b), buildings.get(b)?.door_pos)))
+        .collect();
+    errands.sort_by(|(_, a), (_, b)| a.distance2(from).total_cmp(&b.distance2(from)));
+
+    let dest_pos = match dest {
+        Destination::Outside(pos) => Some(pos),
+        Destination::Building(b) => buildings.get(b).map(|b| b.door_pos),
+    };
+    let Some(dest_pos) = dest_pos else {
+        return vec![];
+    };
+    errands.push((dest, dest_pos));
+
+    plan_legs(from, &errands)
+}
+
+fn plan_legs(from: Vec3, stops: &[(Destination, Vec3)]) -> Vec<Leg> {
+    let mut prev = from;
+    stops
+        .iter()
+        .map(|&(dest, pos)| {
+            let mode = if pos.distance(prev) < WALKING_DIST {
+                LegMode::Walk
+            } else {
+                LegMode::Drive
+            };
+            prev = pos;
+            Leg { dest, mode }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geom::vec3;
+
+    #[test]
+    fn close_stops_are_walked() {
+        let work = vec3(0.0, 0.0, 0.0);
+        let store = Destination::Outside(vec3(100.0, 0.0, 0.0));
+        let home = Destination::Outside(vec3(2000.0, 0.0, 0.0));
+
+        let legs = plan_legs(
+            work,
+            &[
+                (store, vec3(100.0, 0.0, 0.0)),
+                (home, vec3(2000.0, 0.0, 0.0)),
+            ],
+        );
+        assert_eq!(
+            legs,
+            vec![
+                Leg {
+                    dest: store,
+                    mode: LegMode::Walk
+                },
+                Leg {
+                    dest: home,
+                    mode: LegMode::Drive
+                },
+            ]
+        );
+    }
+}
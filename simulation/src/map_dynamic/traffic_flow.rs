@@ -0,0 +1,109 @@
+use crate::map::{Lane, LaneID, LaneKind, Map};
+use serde::{Deserialize, Serialize};
+use slotmapd::SecondaryMap;
+
+/// Weight of a new traversal in the average travel time of a lane
+const FLOW_SMOOTHING: f32 = 0.2;
+/// Share of the gap to the free flow time closed every minute, so that the jams nobody drives
+/// through anymore are forgotten
+const FLOW_RELAXATION: f32 = 0.1;
+/// Lanes taking this many times their free flow time to drive through are congested
+pub const CONGESTION_FACTOR: f32 = 1.5;
+
+/// Seconds to drive through the lane at its speed limit
+pub fn free_flow_time(lane: &Lane) -> f32 {
+    lane.points.length() / lane.speed_limit
+}
+
+/// TrafficFlow measures how long vehicles take to drive through each lane, the pathfinding uses
+/// it as the cost of the lanes so that vehicles route around the jams.
+#[derive(Default, Serialize, Deserialize)]
+pub struct TrafficFlow {
+    travel_times: SecondaryMap<LaneID, f32>,
+}
+
+impl TrafficFlow {
+    /// Average seconds the last vehicles took to drive through the lane.
+    /// Returns `None` if no vehicle drove through it recently
+    pub fn travel_time(&self, lane: LaneID) -> Option<f32> {
+        self.travel_times.get(lane).copied()
+    }
+
+    /// Travel time of the lane divided by its free flow time, 1.0 when nobody drove through it
+    pub fn congestion(&self, map: &Map, lane: LaneID) -> f32 {
+        let (Some(time), Some(lane)) = (self.travel_time(lane), map.lanes().get(lane)) else {
+            return 1.0;
+        };
+        time / free_flow_time(lane)
+    }
+
+    /// Records a vehicle that drove through the whole lane in the given seconds, lanes that are
+    /// not driven on are ignored
+    pub fn record(&mut self, map: &Map, lane: LaneID, seconds: f32) {
+        let Some(l) = map.lanes().get(lane) else {
+            return;
+        };
+        if !matches!(l.kind, LaneKind::Driving | LaneKind::Bus | LaneKind::Tram) {
+            return;
+        }
+        match self.travel_times.get_mut(lane) {
+            Some(avg) => *avg += (seconds - *avg) * FLOW_SMOOTHING,
+            None => {
+                self.travel_times.insert(lane, seconds);
+            }
+        }
+    }
+
+    /// Brings the travel times back toward the free flow times, to be called every minute.
+    /// Lanes back to their free flow time are forgotten
+    pub fn relax(&mut self, map: &Map) {
+        self.travel_times.retain(|id, time| {
+            let Some(lane) = map.lanes().get(id) else {
+                return false;
+            };
+            let free = free_flow_time(lane);
+            *time += (free - *time) * FLOW_RELAXATION;
+            (*time - free).abs() > free * 0.01
+        });
+    }
+
+    /// The travel times of the congested lanes, the only ones the pathfinding needs to know
+    pub fn congested(&self, map: &Map) -> SecondaryMap<LaneID, f32> {
+        let mut costs = SecondaryMap::new();
+        for (id, &time) in &self.travel_times {
+            if self.congestion(map, id) >= CONGESTION_FACTOR {
+                costs.insert(id, time);
+            }
+        }
+        costs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use geom::vec3;
+
+    #[test]
+    fn travel_times() {
+        let test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let map = test.g.map();
+        let lane = map.lanes().values().find(|l| l.kind.is_driving()).unwrap();
+        let free = free_flow_time(lane);
+
+        let mut flow = TrafficFlow::default();
+        flow.record(&map, lane.id, free * 3.0);
+        assert!((flow.congestion(&map, lane.id) - 3.0).abs() < 0.01);
+        flow.record(&map, lane.id, free);
+        assert!((flow.congestion(&map, lane.id) - 2.6).abs() < 0.01);
+        assert!(flow.congested(&map).contains_key(lane.id));
+
+        for _ in 0..100 {
+            flow.relax(&map);
+        }
+        assert_eq!(flow.travel_time(lane.id), None);
+        assert!(flow.congested(&map).is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+use crate::map::{Map, PathKind, PathQuery, Pathfinder, RoadID, TraverseKind};
+use crate::map_dynamic::LegMode;
+use crate::transportation::Mobility;
+use geom::{Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::{Display, Formatter};
+
+/// Size of the side of a district, in meters
+pub const DISTRICT_SIZE: f32 = 1000.0;
+/// One in `SAMPLE_RATE` completed trips keeps its path for select-link analysis
+const SAMPLE_RATE: u64 = 5;
+const MAX_SAMPLES: usize = 1000;
+/// The access times are averaged over the last `MAX_ACCESS_TIMES` walks
+const MAX_ACCESS_TIMES: usize = 500;
+/// Number of recent journeys kept for the analytics export
+const MAX_JOURNEYS: usize = 5000;
+
+/// A district is a square cell of the map, trips are aggregated by district
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct District(pub i32, pub i32);
+
+impl District {
+    pub fn of(pos: Vec2) -> Self {
+        Self(
+            (pos.x / DISTRICT_SIZE).floor() as i32,
+            (pos.y / DISTRICT_SIZE).floor() as i32,
+        )
+    }
+
+    pub fn center(&self) -> Vec2 {
+        Vec2::new(
+            (self.0 as f32 + 0.5) * DISTRICT_SIZE,
+            (self.1 as f32 + 0.5) * DISTRICT_SIZE,
+        )
+    }
+}
+
+impl Display for District {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.0, self.1)
+    }
+}
+
+/// A completed trip whose path was kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TripSample {
+    pub from: District,
+    pub to: District,
+    pub roads: BTreeSet<RoadID>,
+}
+
+/// A journey of a human from one building to another, without anything identifying the human
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Journey {
+    pub seconds: f32,
+    /// Distance as the crow flies between both buildings, in meters
+    pub distance: f32,
+    /// Drive if a vehicle was used for part of the journey
+    pub mode: LegMode,
+    /// Whether the human was going to work
+    pub commute: bool,
+}
+
+/// TripStats aggregates the completed vehicle trips into an origin-destination matrix by district.
+/// A sample of the trips also keeps the roads they used, to know where the traffic of a road comes from.
+/// It also keeps the access time: how long people walk before getting in a vehicle.
+#[derive(Default, Serialize, Deserialize)]
+pub struct TripStats {
+    od: BTreeMap<(District, District), u32>,
+    samples: VecDeque<TripSample>,
+    n_trips: u64,
+    access_times: VecDeque<(f32, Mobility)>,
+    journeys: VecDeque<Journey>,
+}
+
+impl TripStats {
+    /// Number of completed trips between each pair of districts
+    pub fn od_matrix(&self) -> &BTreeMap<(District, District), u32> {
+        &self.od
+    }
+
+    pub fn n_trips(&self) -> u64 {
+        self.n_trips
+    }
+
+    pub fn n_samples(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Every district appearing in the matrix, sorted
+    pub fn districts(&self) -> BTreeSet<District> {
+        self.od.keys().flat_map(|&(a, b)| [a, b]).collect()
+    }
+
+    /// Origin-destination matrix of the sampled trips going through the road
+    pub fn select_link(&self, road: RoadID) -> BTreeMap<(District, District), u32> {
+        let mut m = BTreeMap::new();
+        for s in self.samples.iter().filter(|s| s.roads.contains(&road)) {
+            *m.entry((s.from, s.to)).or_default() += 1;
+        }
+        m
+    }
+
+    /// Records a walk to a vehicle, in seconds
+    pub fn record_access(&mut self, seconds: f32, mobility: Mobility) {
+        if self.access_times.len() >= MAX_ACCESS_TIMES {
+            self.access_times.pop_front();
+        }
+        self.access_times.push_back((seconds, mobility));
+    }
+
+    /// Average access time in seconds of the recent walks, `None` filters by mobility.
+    /// Returns `None` if no walk was recorded
+    pub fn avg_access_time(&self, mobility: Option<Mobility>) -> Option<f32> {
+        let (sum, n) = self
+            .access_times
+            .iter()
+            .filter(|(_, m)| mobility.map_or(true, |x| x == *m))
+            .fold((0.0, 0), |(sum, n), (t, _)| (sum + t, n + 1));
+        (n > 0).then(|| sum / n as f32)
+    }
+
+    pub fn record_journey(&mut self, journey: Journey) {
+        if self.journeys.len() >= MAX_JOURNEYS {
+            self.journeys.pop_front();
+        }
+        self.journeys.push_back(journey);
+    }
+
+    /// The recent journeys between buildings, oldest first
+    pub fn journeys(&self) -> impl Iterator<Item = &Journey> {
+        self.journeys.iter()
+    }
+
+    /// Records a completed trip, the path is replayed from the query for sampled trips
+    pub fn record(&mut self, map: &Map, query: &PathQuery, end: Vec3) {
+        if !matches!(
+            query.kind,
+            PathKind::Vehicle | PathKind::Truck | PathKind::Bus | PathKind::Tram
+        ) {
+            return;
+        }
+        let TraverseKind::Lane(start_lane) = query.start.kind else {
+            return;
+        };
+        let Some(start) = map.lanes().get(start_lane) else {
+            return;
+        };
+        let from = District::of(start.points.first().xy());
+        let to = District::of(end.xy());
+
+        *self.od.entry((from, to)).or_default() += 1;
+        self.n_trips += 1;
+
+        if self.n_trips % SAMPLE_RATE != 0 {
+            return;
+        }
+        let Some(path) = query.kind.path(map, query.tick, query.start, query.end) else {
+            return;
+        };
+        let roads = std::iter::once(query.start)
+            .chain(path)
+            .filter_map(|t| match t.kind {
+                TraverseKind::Lane(l) => map.lanes().get(l).map(|l| l.parent),
+                _ => None,
+            })
+            .collect();
+
+        if self.samples.len() >= MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(TripSample { from, to, roads });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geom::vec2;
+
+    #[test]
+    fn district_of() {
+        assert_eq!(District::of(vec2(10.0, 10.0)), District(0, 0));
+        assert_eq!(District::of(vec2(-10.0, 1500.0)), District(-1, 1));
+        assert_eq!(District::of(District(3, -2).center()), District(3, -2));
+    }
+
+    #[test]
+    fn avg_access_time() {
+        let mut stats = TripStats::default();
+        assert_eq!(stats.avg_access_time(None), None);
+
+        stats.record_access(60.0, Mobility::Regular);
+        stats.record_access(120.0, Mobility::Regular);
+        stats.record_access(300.0, Mobility::Reduced);
+
+        assert_eq!(stats.avg_access_time(None), Some(160.0));
+        assert_eq!(stats.avg_access_time(Some(Mobility::Regular)), Some(90.0));
+        assert_eq!(stats.avg_access_time(Some(Mobility::Reduced)), Some(300.0));
+    }
+}
@@ -0,0 +1,276 @@
+//! Event venues host a match or a concert every few days. The attendees all leave for the venue
+//! before the start and come back together at the end, so the events are announced the day
+//! before to let the players prepare the roads, the parking and the transit around the venue.
+
+use crate::map::{BuildingID, BuildingKind, Map};
+use crate::multiplayer::chat::{Message, MessageKind};
+use crate::multiplayer::MultiplayerState;
+use crate::souls::desire::Ticket;
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::time::{GameInstant, GameTime, SECONDS_PER_DAY, SECONDS_PER_HOUR};
+use crate::Simulation;
+use geom::Color;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// Days between the end of an event and the next one at the same venue
+const DAYS_BETWEEN_EVENTS: i32 = 3;
+/// How long before the start the events are announced, in seconds
+pub const ANNOUNCE_ADVANCE: f64 = SECONDS_PER_DAY as f64;
+/// How long before the start the attendees leave for the venue, in seconds
+pub const ARRIVE_AHEAD: f64 = SECONDS_PER_HOUR as f64;
+/// How long the events last, in seconds
+const EVENT_DURATION: f64 = 3.0 * SECONDS_PER_HOUR as f64;
+/// Number of seats of a venue
+pub const VENUE_CAPACITY: u32 = 1000;
+/// Share of the people living around the venue who get a ticket
+const ATTENDANCE_SHARE: f32 = 0.2;
+/// Only the people living this close to the venue attend its events, in meters
+const MAX_ATTENDEE_DIST: f32 = 5000.0;
+
+const EVENTS_ANNOUNCER: &str = "Events";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VenueEventKind {
+    Match,
+    Concert,
+}
+
+impl VenueEventKind {
+    fn start_hour(self) -> i32 {
+        match self {
+            VenueEventKind::Match => 15,
+            VenueEventKind::Concert => 20,
+        }
+    }
+}
+
+impl Display for VenueEventKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VenueEventKind::Match => write!(f, "Match"),
+            VenueEventKind::Concert => write!(f, "Concert"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VenueEvent {
+    pub kind: VenueEventKind,
+    pub start: GameInstant,
+    pub end: GameInstant,
+    pub announced: bool,
+    /// Number of tickets handed out when the event was announced
+    pub tickets: u32,
+}
+
+impl VenueEvent {
+    pub fn is_ongoing(&self, time: &GameTime) -> bool {
+        self.start.timestamp <= time.timestamp && time.timestamp < self.end.timestamp
+    }
+}
+
+/// VenueEvents schedules the events of the venues, see [`BuildingKind::EventVenue`].
+/// The attendees are given a ticket when the event is announced, see
+/// [`crate::souls::desire::AttendEvent`].
+#[derive(Default, Serialize, Deserialize)]
+pub struct VenueEvents {
+    /// The next or ongoing event of every venue
+    events: BTreeMap<BuildingID, VenueEvent>,
+}
+
+impl VenueEvents {
+    /// The next or ongoing event of the venue
+    pub fn event(&self, venue: BuildingID) -> Option<&VenueEvent> {
+        self.events.get(&venue)
+    }
+
+    /// The events announced and not over yet, with their venue
+    pub fn upcoming(&self, time: &GameTime) -> impl Iterator<Item = (BuildingID, &VenueEvent)> {
+        let now = time.timestamp;
+        self.events
+            .iter()
+            .filter(move |(_, e)| e.announced && now < e.end.timestamp)
+            .map(|(&id, e)| (id, e))
+    }
+}
+
+pub fn venue_events_system(sim: &mut Simulation) {
+    profiling::scope!("map_dynamic::venue_events_system");
+    let time = *sim.read::<GameTime>();
+    if !time.tick(60) {
+        return;
+    }
+    update_venue_events(sim, &time);
+}
+
+fn update_venue_events(sim: &mut Simulation, time: &GameTime) {
+    schedule_events(sim, time);
+
+    let to_announce: Vec<_> = sim
+        .read::<VenueEvents>()
+        .events
+        .iter()
+        .filter(|(_, e)| !e.announced && time.timestamp >= e.start.timestamp - ANNOUNCE_ADVANCE)
+        .map(|(&id, _)| id)
+        .collect();
+    for venue in to_announce {
+        announce_event(sim, time, venue);
+    }
+}
+
+/// Plans the next event of the venues whose last event is over
+fn schedule_events(sim: &mut Simulation, time: &GameTime) {
+    let map = sim.map();
+    let venues = map
+        .bkinds
+        .get(&BuildingKind::EventVenue)
+        .cloned()
+        .unwrap_or_default();
+    drop(map);
+
+    let mut events = sim.write::<VenueEvents>();
+    let mut rng = sim.write::<RandProvider>();
+    events.events.retain(|id, _| venues.contains(id));
+    for venue in venues {
+        let day = match events.events.get(&venue) {
+            Some(e) if time.timestamp < e.end.timestamp => continue,
+            Some(e) => GameTime::new(0.0, e.end.timestamp).daytime.day + DAYS_BETWEEN_EVENTS,
+            None => time.daytime.day + 1,
+        };
+        let kind = if rng.next_f32() < 0.5 {
+            VenueEventKind::Match
+        } else {
+            VenueEventKind::Concert
+        };
+        let start = (day * SECONDS_PER_DAY + kind.start_hour() * SECONDS_PER_HOUR) as f64;
+        events.events.insert(
+            venue,
+            VenueEvent {
+                kind,
+                start: GameInstant { timestamp: start },
+                end: GameInstant {
+                    timestamp: start + EVENT_DURATION,
+                },
+                announced: false,
+                tickets: 0,
+            },
+        );
+    }
+}
+
+/// Hands out the tickets to the people living around the venue and warns the players
+fn announce_event(sim: &mut Simulation, time: &GameTime, venue: BuildingID) {
+    let Some(venue_pos) = sim.map().buildings().get(venue).map(|b| b.door_pos) else {
+        return;
+    };
+    let Some(event) = sim.read::<VenueEvents>().events.get(&venue).cloned() else {
+        return;
+    };
+    let ticket = Ticket {
+        venue,
+        start: event.start,
+        end: event.end,
+    };
+
+    let mut tickets = 0;
+    {
+        let (world, res) = sim.world_res();
+        let map = res.read::<Map>();
+        let mut rng = res.write::<RandProvider>();
+        for h in world.humans.values_mut() {
+            if tickets >= VENUE_CAPACITY {
+                break;
+            }
+            if h.event.busy_during(&ticket) || rng.next_f32() >= ATTENDANCE_SHARE {
+                continue;
+            }
+            let Some(house) = map.buildings().get(h.home.house) else {
+                continue;
+            };
+            if !house.door_pos.is_close(venue_pos, MAX_ATTENDEE_DIST) {
+                continue;
+            }
+            h.event.ticket = Some(ticket);
+            tickets += 1;
+        }
+    }
+
+    if let Some(e) = sim.write::<VenueEvents>().events.get_mut(&venue) {
+        e.announced = true;
+        e.tickets = tickets;
+    }
+
+    sim.write::<MultiplayerState>().chat.add_message(Message {
+        name: EVENTS_ANNOUNCER.to_string(),
+        text: format!(
+            "{} at {}: {} people expected, traffic, parking and transit will be busy around \
+             the venue before and after",
+            event.kind, event.start, tickets
+        ),
+        sent_at: time.instant(),
+        color: Color::new(0.6, 0.6, 1.0, 1.0),
+        kind: MessageKind::Info,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::souls::desire::AttendEvent;
+    use crate::tests::TestCtx;
+    use common::descriptions::{BuildingGen, DoorLayout};
+    use geom::{vec2, OBB};
+
+    #[test]
+    fn events_are_scheduled_and_announced() {
+        let mut test = TestCtx::new();
+        let venue = test
+            .g
+            .map_mut()
+            .build_special_building(
+                &OBB::new(vec2(0.0, 0.0), vec2(1.0, 0.0), 80.0, 80.0),
+                BuildingKind::EventVenue,
+                BuildingGen::NoWalkway {
+                    door_pos: vec2(0.0, -40.0),
+                    doors: DoorLayout::NONE,
+                },
+                None,
+            )
+            .unwrap();
+
+        update_venue_events(&mut test.g, &GameTime::new(0.0, 0.0));
+        let event = test.g.read::<VenueEvents>().event(venue).cloned().unwrap();
+        assert!(!event.announced);
+        assert!(event.start.timestamp >= SECONDS_PER_DAY as f64);
+
+        let announce_at = event.start.timestamp - ANNOUNCE_ADVANCE;
+        update_venue_events(&mut test.g, &GameTime::new(0.0, announce_at));
+        let events = test.g.read::<VenueEvents>();
+        assert!(events.event(venue).unwrap().announced);
+        assert_eq!(events.upcoming(&GameTime::new(0.0, announce_at)).count(), 1);
+        drop(events);
+
+        // the next event is planned once this one is over
+        let after = GameTime::new(0.0, event.end.timestamp + 1.0);
+        update_venue_events(&mut test.g, &after);
+        let next = test.g.read::<VenueEvents>().event(venue).cloned().unwrap();
+        assert!(next.start.timestamp > event.end.timestamp);
+        assert!(!next.announced);
+
+        let attend = AttendEvent {
+            ticket: Some(Ticket {
+                venue,
+                start: event.start,
+                end: event.end,
+            }),
+            last_score: 0.0,
+        };
+        let before = GameTime::new(0.0, event.start.timestamp - ARRIVE_AHEAD * 2.0);
+        assert_eq!(attend.score(&before), 0.0);
+        let during = GameTime::new(0.0, event.start.timestamp);
+        assert!(attend.score(&during) > 0.0);
+        assert_eq!(attend.score(&after), 0.0);
+    }
+}
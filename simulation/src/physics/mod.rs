@@ -9,6 +9,10 @@ use geom::Vec2;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Formatter};
 
+mod query;
+
+pub use query::*;
+
 #[derive(Clone, Default, Serialize, Deserialize)]
 #[repr(transparent)]
 pub struct Speed(pub f32);
@@ -39,6 +43,10 @@ pub struct PhysicsObject {
     pub height: f32,
     pub group: PhysicsGroup,
     pub flag: u64,
+    /// Emergency vehicle with its siren on, the vehicles around pull over
+    pub siren: bool,
+    /// Vehicle pulled over to let an emergency vehicle through
+    pub pulled_over: bool,
 }
 
 impl Default for PhysicsObject {
@@ -50,6 +58,8 @@ impl Default for PhysicsObject {
             height: 0.0,
             group: PhysicsGroup::Unknown,
             flag: 0,
+            siren: false,
+            pulled_over: false,
         }
     }
 }
@@ -76,13 +86,16 @@ pub fn coworld_synchronize(world: &mut World, resources: &mut Resources) {
 
     world.query_trans_speed_coll_vehicle().for_each(
         |(trans, kin, coll, v): (&Transform, &Speed, Collider, Option<&Vehicle>)| {
-            coworld.set_position(coll.0, trans.position.xy());
+            let offset = v.map_or(Vec2::ZERO, |v| v.pull_over_offset(trans.dir).xy());
+            coworld.set_position(coll.0, trans.position.xy() + offset);
             let (_, po) = coworld.get_mut(coll.0).unwrap(); // Unwrap ok: handle is deleted only when entity is deleted too
             po.dir = trans.dir.xy();
             po.speed = kin.0;
             po.height = trans.position.z;
             if let Some(v) = v {
                 po.flag = v.flag;
+                po.siren = v.siren;
+                po.pulled_over = v.is_pulled_over();
             }
         },
     );
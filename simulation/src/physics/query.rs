@@ -0,0 +1,187 @@
+use crate::physics::{CollisionWorld, PhysicsGroup, PhysicsObject};
+use crate::transportation::VehicleKind;
+use flat_spatial::grid::GridHandle;
+use geom::{Segment, Vec2, OBB};
+use ordered_float::OrderedFloat;
+
+/// Biggest radius of any object inserted in the collision world, used to extend the broadphase.
+/// The vehicles are the biggest objects, their radius is half their width.
+pub const MAX_COLLIDER_RADIUS: f32 = VehicleKind::MAX_WIDTH * 0.5;
+
+#[derive(Copy, Clone, Debug)]
+pub struct PhysicsFilter(u8);
+
+impl PhysicsFilter {
+    pub const UNKNOWN: Self = Self(1);
+    pub const VEHICLES: Self = Self(2);
+    pub const PEDESTRIANS: Self = Self(4);
+    pub const ALL: Self = Self(!0);
+
+    pub fn test(self, group: PhysicsGroup) -> bool {
+        match group {
+            PhysicsGroup::Unknown => (self.0 & Self::UNKNOWN.0) != 0,
+            PhysicsGroup::Vehicles => (self.0 & Self::VEHICLES.0) != 0,
+            PhysicsGroup::Pedestrians => (self.0 & Self::PEDESTRIANS.0) != 0,
+        }
+    }
+}
+
+impl std::ops::BitOr for PhysicsFilter {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct RaycastHit {
+    pub handle: GridHandle,
+    /// Position of the object that was hit
+    pub pos: Vec2,
+    /// Distance from the start of the ray to the point of impact
+    pub dist: f32,
+}
+
+/// Spatial queries on the collision world, objects are considered as circles of their radius
+pub trait CollisionWorldQuery {
+    /// Returns the first object hit when going from `seg.src` to `seg.dst`
+    fn raycast(&self, seg: Segment, filter: PhysicsFilter) -> Option<RaycastHit>;
+
+    /// Returns all objects overlapping the given obb
+    fn query_obb(
+        &self,
+        obb: OBB,
+        filter: PhysicsFilter,
+    ) -> impl Iterator<Item = (GridHandle, Vec2, &PhysicsObject)> + '_;
+}
+
+impl CollisionWorldQuery for CollisionWorld {
+    fn raycast(&self, seg: Segment, filter: PhysicsFilter) -> Option<RaycastHit> {
+        let v = seg.vec();
+        let length = v.mag();
+        let dir = v.try_normalize().unwrap_or(Vec2::X);
+
+        self.query_around(seg.middle(), length * 0.5 + MAX_COLLIDER_RADIUS)
+            .filter_map(|(handle, pos)| {
+                let (_, obj) = self.get(handle)?;
+                if !filter.test(obj.group) {
+                    return None;
+                }
+                let perp_dist2 = seg.project(pos).distance2(pos);
+                let r2 = obj.radius * obj.radius;
+                if perp_dist2 > r2 {
+                    return None;
+                }
+                let along = (pos - seg.src).dot(dir);
+                let dist = (along - (r2 - perp_dist2).sqrt()).max(0.0);
+                Some(RaycastHit { handle, pos, dist })
+            })
+            .min_by_key(|hit| OrderedFloat(hit.dist))
+    }
+
+    fn query_obb(
+        &self,
+        obb: OBB,
+        filter: PhysicsFilter,
+    ) -> impl Iterator<Item = (GridHandle, Vec2, &PhysicsObject)> + '_ {
+        let center = obb.center();
+        let radius = obb.corners[0].distance(center) + MAX_COLLIDER_RADIUS;
+
+        self.query_around(center, radius)
+            .filter_map(move |(handle, pos)| {
+                let (_, obj) = self.get(handle)?;
+                (filter.test(obj.group) && obb.is_close(pos, obj.radius))
+                    .then_some((handle, pos, obj))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(cow: &mut CollisionWorld, pos: Vec2, group: PhysicsGroup) -> GridHandle {
+        cow.insert(
+            pos,
+            PhysicsObject {
+                radius: 1.0,
+                group,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn raycast_returns_first_hit() {
+        let mut cow = CollisionWorld::new(10);
+        let far = insert(&mut cow, Vec2::new(20.0, 0.0), PhysicsGroup::Vehicles);
+        let near = insert(&mut cow, Vec2::new(10.0, 0.5), PhysicsGroup::Vehicles);
+        let ped = insert(&mut cow, Vec2::new(5.0, 0.0), PhysicsGroup::Pedestrians);
+        insert(&mut cow, Vec2::new(15.0, 5.0), PhysicsGroup::Vehicles);
+
+        let seg = Segment::new(Vec2::ZERO, Vec2::new(30.0, 0.0));
+
+        let hit = cow.raycast(seg, PhysicsFilter::ALL).unwrap();
+        assert_eq!(hit.handle, ped);
+        assert!((hit.dist - 4.0).abs() < 0.001);
+
+        let hit = cow.raycast(seg, PhysicsFilter::VEHICLES).unwrap();
+        assert_eq!(hit.handle, near);
+
+        let short = Segment::new(Vec2::new(12.0, 0.0), Vec2::new(30.0, 0.0));
+        let hit = cow.raycast(short, PhysicsFilter::VEHICLES).unwrap();
+        assert_eq!(hit.handle, far);
+
+        assert!(cow
+            .raycast(
+                Segment::new(Vec2::ZERO, Vec2::new(0.0, 30.0)),
+                PhysicsFilter::ALL
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn query_obb_filters() {
+        let mut cow = CollisionWorld::new(10);
+        let a = insert(&mut cow, Vec2::new(0.0, 0.0), PhysicsGroup::Vehicles);
+        let b = insert(&mut cow, Vec2::new(5.5, 0.0), PhysicsGroup::Pedestrians);
+        insert(&mut cow, Vec2::new(20.0, 0.0), PhysicsGroup::Vehicles);
+
+        let obb = OBB::new(Vec2::ZERO, Vec2::X, 10.0, 10.0);
+
+        let all: Vec<_> = cow
+            .query_obb(obb, PhysicsFilter::ALL)
+            .map(|(h, _, _)| h)
+            .collect();
+        assert_eq!(all.len(), 2);
+        assert!(all.contains(&a) && all.contains(&b));
+
+        let vehicles: Vec<_> = cow
+            .query_obb(obb, PhysicsFilter::VEHICLES)
+            .map(|(h, _, _)| h)
+            .collect();
+        assert_eq!(vehicles, vec![a]);
+    }
+
+    #[test]
+    fn trams_are_found_from_afar() {
+        let mut cow = CollisionWorld::new(10);
+        let tram = cow.insert(
+            Vec2::new(1.0, 7.5),
+            PhysicsObject {
+                radius: VehicleKind::Tram.width() * 0.5,
+                group: PhysicsGroup::Vehicles,
+                ..Default::default()
+            },
+        );
+
+        let seg = Segment::new(Vec2::ZERO, Vec2::new(2.0, 0.0));
+        assert_eq!(
+            cow.raycast(seg, PhysicsFilter::ALL).map(|hit| hit.handle),
+            Some(tram)
+        );
+        let obb = OBB::new(Vec2::new(1.0, 0.0), Vec2::X, 2.0, 2.0);
+        assert_eq!(cow.query_obb(obb, PhysicsFilter::ALL).count(), 1);
+    }
+}
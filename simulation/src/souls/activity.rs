@@ -0,0 +1,174 @@
+use crate::map_dynamic::Router;
+use crate::transportation::Location;
+use crate::utils::time::{GameInstant, GameTime};
+use egui_inspect::Inspect;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// How a commuting human is currently moving
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommuteLeg {
+    Walking,
+    Driving,
+    WaitingForBus,
+    RidingBus,
+    WaitingForMetro,
+    RidingMetro,
+}
+
+impl CommuteLeg {
+    pub fn new(loc: &Location, router: &Router) -> Self {
+        match *loc {
+            Location::Vehicle(v) if router.vehicle() == Some(v) => CommuteLeg::Driving,
+            Location::Vehicle(_) => CommuteLeg::RidingBus,
+            Location::Train(_) => CommuteLeg::RidingMetro,
+            _ if router.waiting_for_bus().is_some() => CommuteLeg::WaitingForBus,
+            _ if router.waiting_for_metro().is_some() => CommuteLeg::WaitingForMetro,
+            _ => CommuteLeg::Walking,
+        }
+    }
+}
+
+/// What a human is doing. The desires pick the next activity, the human commutes to it
+/// and stays in it until the next transition.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Activity {
+    /// At home, sleeping or resting
+    #[default]
+    Sleeping,
+    /// On the way to the goal activity
+    Commuting {
+        leg: CommuteLeg,
+    },
+    Working,
+    /// Buying food or fuel
+    Shopping,
+    /// Out in the city for fun
+    Leisure,
+}
+
+debug_inspect_impl!(Activity);
+
+impl Display for Activity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Activity::Sleeping => write!(f, "Sleeping"),
+            Activity::Commuting { leg } => match leg {
+                CommuteLeg::Walking => write!(f, "Commuting (walking)"),
+                CommuteLeg::Driving => write!(f, "Commuting (driving)"),
+                CommuteLeg::WaitingForBus => write!(f, "Commuting (waiting for the bus)"),
+                CommuteLeg::RidingBus => write!(f, "Commuting (riding the bus)"),
+                CommuteLeg::WaitingForMetro => write!(f, "Commuting (waiting for the metro)"),
+                CommuteLeg::RidingMetro => write!(f, "Commuting (riding the metro)"),
+            },
+            Activity::Working => write!(f, "Working"),
+            Activity::Shopping => write!(f, "Shopping"),
+            Activity::Leisure => write!(f, "Leisure"),
+        }
+    }
+}
+
+/// The state machine of the activities of a human.
+/// Commuting is a sub state leading to the goal, the other activities are where it ends.
+#[derive(Inspect, Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityState {
+    pub activity: Activity,
+    /// The activity the human is in or commutes to, never `Commuting`
+    pub goal: Activity,
+    pub since: GameInstant,
+    /// When the goal is expected to change, `None` when it lasts until its errand is done
+    pub next_transition: Option<GameInstant>,
+}
+
+impl Default for ActivityState {
+    fn default() -> Self {
+        Self {
+            activity: Activity::Sleeping,
+            goal: Activity::Sleeping,
+            since: GameInstant { timestamp: 0.0 },
+            next_transition: None,
+        }
+    }
+}
+
+impl ActivityState {
+    pub fn is_commuting(&self) -> bool {
+        matches!(self.activity, Activity::Commuting { .. })
+    }
+
+    /// Follows the commute, `arrived` is true once the decision leading to the goal is done
+    pub fn update(&mut self, time: &GameTime, arrived: bool, loc: &Location, router: &Router) {
+        let next = if arrived {
+            self.goal
+        } else {
+            Activity::Commuting {
+                leg: CommuteLeg::new(loc, router),
+            }
+        };
+        self.set(next, time);
+    }
+
+    /// Starts heading to a new goal, staying in it if it is the current one
+    pub fn transition(
+        &mut self,
+        goal: Activity,
+        next_transition: Option<GameInstant>,
+        time: &GameTime,
+    ) {
+        debug_assert!(!matches!(goal, Activity::Commuting { .. }));
+        self.next_transition = next_transition;
+        if goal == self.goal && !self.is_commuting() {
+            return;
+        }
+        self.goal = goal;
+        self.set(
+            Activity::Commuting {
+                leg: CommuteLeg::Walking,
+            },
+            time,
+        );
+    }
+
+    fn set(&mut self, activity: Activity, time: &GameTime) {
+        // the leg changing is part of the same commute
+        if std::mem::discriminant(&activity) != std::mem::discriminant(&self.activity) {
+            self.since = time.instant();
+        }
+        self.activity = activity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commutes_to_the_goal_then_stays() {
+        let router = Router::new(None);
+        let t = |timestamp| GameTime::new(0.0, timestamp);
+        let mut state = ActivityState::default();
+
+        state.transition(Activity::Working, None, &t(10.0));
+        assert_eq!(state.goal, Activity::Working);
+        assert!(state.is_commuting());
+        assert_eq!(state.since.timestamp, 10.0);
+
+        state.update(&t(20.0), false, &Location::Outside, &router);
+        assert_eq!(
+            state.activity,
+            Activity::Commuting {
+                leg: CommuteLeg::Walking
+            }
+        );
+        assert_eq!(state.since.timestamp, 10.0);
+
+        state.update(&t(30.0), true, &Location::Outside, &router);
+        assert_eq!(state.activity, Activity::Working);
+        assert_eq!(state.since.timestamp, 30.0);
+
+        // picking the same activity again does not leave it
+        state.transition(Activity::Working, None, &t(40.0));
+        assert_eq!(state.activity, Activity::Working);
+        assert_eq!(state.since.timestamp, 30.0);
+    }
+}
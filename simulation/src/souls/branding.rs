@@ -0,0 +1,125 @@
+use crate::souls::human::LAST_NAMES;
+use crate::utils::rand_provider::RandProvider;
+use geom::Color;
+use serde::{Deserialize, Serialize};
+
+const PREFIXES: [&str; 12] = [
+    "Golden",
+    "United",
+    "Northern",
+    "Royal",
+    "Modern",
+    "Blue",
+    "Central",
+    "Pioneer",
+    "Summit",
+    "Riverside",
+    "Oak",
+    "Silver",
+];
+
+const SUFFIXES: [&str; 4] = ["Co.", "Ltd", "Inc.", "& Sons"];
+
+/// Shape of the logo, drawn behind the initial of the company
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogoShape {
+    #[default]
+    Circle,
+    Square,
+    Diamond,
+    Triangle,
+}
+
+/// Brand is the name and logo of a company.
+/// It is generated from a seed so that the same company always gets the same brand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Brand {
+    pub name: String,
+    pub shape: LogoShape,
+    /// Color of the logo, also used to paint the delivery trucks
+    pub color: Color,
+}
+
+debug_inspect_impl!(Brand);
+
+impl Default for Brand {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            shape: LogoShape::Circle,
+            color: Color::WHITE,
+        }
+    }
+}
+
+impl Brand {
+    /// `trade` is what the company does, e.g. "Bakery"
+    pub fn new(seed: u64, trade: &str) -> Self {
+        let mut rng = RandProvider::new(seed);
+
+        let name = match rng.next_u32() % 4 {
+            0 => format!("{} {}", last_name(&mut rng), trade),
+            1 => format!("{} & {}", last_name(&mut rng), last_name(&mut rng)),
+            2 => {
+                let suffix = SUFFIXES[rng.next_u32() as usize % SUFFIXES.len()];
+                format!("{} {}", last_name(&mut rng), suffix)
+            }
+            _ => {
+                let prefix = PREFIXES[rng.next_u32() as usize % PREFIXES.len()];
+                format!("{} {}", prefix, trade)
+            }
+        };
+
+        let shape = match rng.next_u32() % 4 {
+            0 => LogoShape::Circle,
+            1 => LogoShape::Square,
+            2 => LogoShape::Diamond,
+            _ => LogoShape::Triangle,
+        };
+        let color = Color::hsv(
+            rng.next_f32() * 360.0,
+            0.5 + rng.next_f32() * 0.4,
+            0.6 + rng.next_f32() * 0.3,
+            1.0,
+        );
+
+        Self { name, shape, color }
+    }
+
+    /// The letter drawn on the logo
+    pub fn initial(&self) -> char {
+        self.name.chars().next().unwrap_or('?')
+    }
+
+    /// Companies created before brands existed have none
+    pub fn is_set(&self) -> bool {
+        !self.name.is_empty()
+    }
+}
+
+fn last_name(rng: &mut RandProvider) -> &'static str {
+    loop {
+        let name = LAST_NAMES[rng.next_u32() as usize % LAST_NAMES.len()].trim();
+        if !name.is_empty() {
+            return name;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brands_are_deterministic() {
+        let a = Brand::new(42, "Bakery");
+        let b = Brand::new(42, "Bakery");
+        assert_eq!(a.name, b.name);
+        assert_eq!(a.shape, b.shape);
+        assert!(a.is_set());
+
+        let names: std::collections::BTreeSet<String> =
+            (0..20).map(|i| Brand::new(i, "Bakery").name).collect();
+        assert!(names.len() > 10);
+    }
+}
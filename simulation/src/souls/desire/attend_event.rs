@@ -0,0 +1,48 @@
+use crate::map::BuildingID;
+use crate::map_dynamic::{Destination, ARRIVE_AHEAD};
+use crate::souls::human::HumanDecisionKind;
+use crate::utils::time::{GameInstant, GameTime};
+use egui_inspect::Inspect;
+use serde::{Deserialize, Serialize};
+
+/// A seat at an event of a venue, see [`crate::map_dynamic::VenueEvents`]
+#[derive(Inspect, Copy, Clone, Serialize, Deserialize, Debug)]
+pub struct Ticket {
+    pub venue: BuildingID,
+    pub start: GameInstant,
+    pub end: GameInstant,
+}
+
+/// AttendEvent is the desire of going to the match or the concert the human has a ticket for.
+/// All the attendees leave for the venue at the same time and come back together once it's over.
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug, Default)]
+pub struct AttendEvent {
+    pub ticket: Option<Ticket>,
+    pub last_score: f32,
+}
+
+impl AttendEvent {
+    /// Whether the human already has a ticket for an event overlapping the other one
+    pub fn busy_during(&self, other: &Ticket) -> bool {
+        self.ticket.map_or(false, |t| {
+            t.start.timestamp < other.end.timestamp && other.start.timestamp < t.end.timestamp
+        })
+    }
+
+    pub fn score(&self, time: &GameTime) -> f32 {
+        let Some(t) = self.ticket else {
+            return 0.0;
+        };
+        if time.timestamp < t.start.timestamp - ARRIVE_AHEAD || time.timestamp >= t.end.timestamp {
+            return 0.0;
+        }
+        0.7
+    }
+
+    pub fn apply(&self) -> HumanDecisionKind {
+        match self.ticket {
+            Some(t) => HumanDecisionKind::GoTo(Destination::Building(t.venue)),
+            None => HumanDecisionKind::Yield,
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::economy::{find_trade_place, Bought, ItemID, ItemRegistry, Market};
 use crate::map::BuildingID;
 use crate::map_dynamic::{BuildingInfos, Destination};
+use crate::souls::goods_company::GoodsCompanyRegistry;
 use crate::souls::human::HumanDecisionKind;
 use crate::transportation::Location;
 use crate::utils::time::{GameInstant, GameTime};
@@ -37,7 +38,29 @@ impl BuyFood {
         }
     }
 
-    pub fn score(&self, time: &GameTime, loc: &Location, bought: &Bought) -> f32 {
+    /// The store where the food was bought, if it is open.
+    /// The food can be picked up on the way to somewhere else.
+    pub fn errand(
+        &self,
+        time: &GameTime,
+        map: &Map,
+        companies: &GoodsCompanyRegistry,
+    ) -> Option<BuildingID> {
+        match self.state {
+            BuyFoodState::BoughtAt(b) if companies.is_open(map, b, time.daytime) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn score(
+        &self,
+        time: &GameTime,
+        loc: &Location,
+        bought: &Bought,
+        map: &Map,
+        companies: &GoodsCompanyRegistry,
+        items: &ItemRegistry,
+    ) -> f32 {
         if matches!(self.state, BuyFoodState::WaitingForTrade)
             && bought
                 .0
@@ -51,8 +74,15 @@ impl BuyFood {
             if loc == &Location::Building(id) {
                 return 1.0;
             }
+            // the store is closed, wait for it to open
+            if !companies.is_open(map, id, time.daytime) {
+                return 0.0;
+            }
         }
-        self.last_ate.elapsed(time) as f32 / GameTime::DAY as f32 - 1.0
+        let days = self.last_ate.elapsed(time) as f32 / GameTime::DAY as f32;
+        items
+            .get(self.bread)
+            .map_or(days - 1.0, |bread| bread.demand(days))
     }
 
     pub fn apply(
@@ -0,0 +1,133 @@
+use crate::economy::Money;
+use crate::map::BuildingID;
+use crate::map_dynamic::{arrive_at_venue, Destination, LeisureVenues};
+use crate::souls::human::HumanDecisionKind;
+use crate::transportation::Location;
+use crate::utils::time::{GameInstant, GameTime, RecTimeInterval};
+use crate::world::{HumanEnt, HumanID};
+use crate::ParCommandBuffer;
+use egui_inspect::Inspect;
+use geom::Transform;
+use serde::{Deserialize, Serialize};
+
+/// Hours during which people think about going out
+const EVENING: (i32, i32) = (19, 1);
+/// How many days people wait before going out again
+const DAYS_BETWEEN_OUTINGS: f64 = 2.0;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum GoOutState {
+    Empty,
+    GoingTo(BuildingID),
+    /// Arrived at the door, queuing if the venue is full
+    Queued(BuildingID),
+    At(BuildingID),
+}
+
+debug_inspect_impl!(GoOutState);
+
+/// GoOut is the desire of spending the evening at a restaurant, a bar or a cinema, see
+/// [`LeisureVenues`]. People pick the venue by distance and price within their budget, and
+/// only go to places that are open, so the town still has some life at night.
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct GoOut {
+    pub last_out: GameInstant,
+    state: GoOutState,
+    /// The most the person spends on an evening out
+    pub budget: Money,
+    /// What is left of the pocket money, topped up to the budget every day from the savings
+    pub wallet: Money,
+    pub last_score: f32,
+}
+
+impl GoOut {
+    pub fn new(start: GameInstant, seed: u32) -> Self {
+        let budget = Money::new_bucks(5 + (seed % 30) as i64);
+        GoOut {
+            last_out: start,
+            state: GoOutState::Empty,
+            budget,
+            wallet: budget,
+            last_score: 0.0,
+        }
+    }
+
+    pub fn score(&self, time: &GameTime, venues: &LeisureVenues, me: HumanID) -> f32 {
+        match self.state {
+            GoOutState::Empty => self.evening_score(time),
+            GoOutState::GoingTo(b) if venues.is_open(b, time.daytime) => 0.3,
+            GoOutState::Queued(b) if venues.is_queuing(b, me) || venues.is_inside(b, me) => 0.3,
+            GoOutState::At(b) if venues.is_inside(b, me) => 0.3,
+            // the venue closed, was full for too long or the evening is over
+            _ => self.evening_score(time),
+        }
+    }
+
+    fn evening_score(&self, time: &GameTime) -> f32 {
+        let evening = RecTimeInterval::new((EVENING.0, 0), (EVENING.1, 0));
+        if evening.dist_until(time.daytime) != 0
+            || self.last_out.elapsed(time) < DAYS_BETWEEN_OUTINGS * GameTime::DAY as f64
+        {
+            return 0.0;
+        }
+        0.3
+    }
+
+    pub fn apply(
+        &mut self,
+        cbuf: &ParCommandBuffer<HumanEnt>,
+        venues: &LeisureVenues,
+        time: &GameTime,
+        id: HumanID,
+        trans: &Transform,
+        loc: &Location,
+    ) -> HumanDecisionKind {
+        use HumanDecisionKind::*;
+        match self.state {
+            GoOutState::Empty => {
+                let Some(b) = venues.pick(trans.position, self.wallet, time.daytime) else {
+                    // nowhere to go tonight
+                    self.last_out = time.instant();
+                    return Yield;
+                };
+                self.state = GoOutState::GoingTo(b);
+                GoTo(Destination::Building(b))
+            }
+            GoOutState::GoingTo(b) => {
+                if !venues.is_open(b, time.daytime) {
+                    self.state = GoOutState::Empty;
+                    return self.apply(cbuf, venues, time, id, trans, loc);
+                }
+                if loc != &Location::Building(b) {
+                    return GoTo(Destination::Building(b));
+                }
+                let now = time.instant();
+                cbuf.exec_ent(id, move |sim| arrive_at_venue(sim, b, id, now));
+                self.state = GoOutState::Queued(b);
+                Yield
+            }
+            GoOutState::Queued(b) => {
+                if venues.is_inside(b, id) {
+                    self.state = GoOutState::At(b);
+                    self.last_out = time.instant();
+                    log::debug!("{:?} went out at {:?}", id, b);
+                    return GoTo(Destination::Building(b));
+                }
+                if venues.is_queuing(b, id) {
+                    return GoTo(Destination::Building(b));
+                }
+                // gave up waiting, the evening is spoiled
+                self.state = GoOutState::Empty;
+                self.last_out = time.instant();
+                Yield
+            }
+            GoOutState::At(b) => {
+                if venues.is_inside(b, id) {
+                    return GoTo(Destination::Building(b));
+                }
+                self.state = GoOutState::Empty;
+                Yield
+            }
+        }
+    }
+}
@@ -1,7 +1,13 @@
+mod attend_event;
 mod buyfood;
+mod goout;
 mod home;
+mod refuel;
 mod work;
 
+pub use attend_event::*;
 pub use buyfood::*;
+pub use goout::*;
 pub use home::*;
+pub use refuel::*;
 pub use work::*;
@@ -0,0 +1,154 @@
+use crate::economy::{find_trade_place, Bought, ItemID, ItemRegistry, Market};
+use crate::map::BuildingID;
+use crate::map_dynamic::{BuildingInfos, Destination};
+use crate::souls::human::HumanDecisionKind;
+use crate::transportation::fuel::{EnergyKind, GasStations, LOW_FUEL};
+use crate::transportation::Location;
+use crate::world::{HumanEnt, HumanID, VehicleID};
+use crate::{Map, ParCommandBuffer, SoulID};
+use egui_inspect::Inspect;
+use geom::Transform;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub enum RefuelState {
+    #[default]
+    Empty,
+    WaitingForTrade,
+    /// Bought fuel at this gas station, drive there and queue at the pumps
+    BoughtAt(BuildingID),
+    /// Bought electricity, charge the car at home
+    Charging,
+}
+
+debug_inspect_impl!(RefuelState);
+
+/// The state of the personal car of a human, as seen by the refuel desire
+#[derive(Copy, Clone, Debug)]
+pub struct CarTank {
+    pub car: VehicleID,
+    pub fuel: f32,
+    pub energy: EnergyKind,
+}
+
+/// Refuel is the desire of filling the tank of the personal car at a gas station,
+/// or of charging it at home for electric cars.
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug, Default)]
+pub struct Refuel {
+    state: RefuelState,
+    pub last_score: f32,
+}
+
+impl Refuel {
+    fn item(energy: EnergyKind, items: &ItemRegistry) -> Option<ItemID> {
+        items.try_id(match energy {
+            EnergyKind::Fuel => "fill-up",
+            EnergyKind::Electric => "electricity",
+        })
+    }
+
+    /// The gas station where the fuel was bought, to stop by on the way to somewhere else
+    pub fn errand(&self) -> Option<BuildingID> {
+        match self.state {
+            RefuelState::BoughtAt(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn score(
+        &self,
+        tank: CarTank,
+        loc: &Location,
+        bought: &Bought,
+        items: &ItemRegistry,
+    ) -> f32 {
+        match self.state {
+            RefuelState::WaitingForTrade => {
+                let waiting = Self::item(tank.energy, items)
+                    .and_then(|item| bought.0.get(&item))
+                    .map_or(true, Vec::is_empty);
+                if waiting {
+                    return 0.0;
+                }
+            }
+            // stay at the pump until the tank is full
+            RefuelState::BoughtAt(b) if loc == &Location::Building(b) => return 1.0,
+            _ => {}
+        }
+        if tank.fuel >= LOW_FUEL {
+            return 0.0;
+        }
+        1.5 * (1.0 - tank.fuel / LOW_FUEL)
+    }
+
+    pub fn apply(
+        &mut self,
+        cbuf: &ParCommandBuffer<HumanEnt>,
+        binfos: &BuildingInfos,
+        map: &Map,
+        items: &ItemRegistry,
+        id: HumanID,
+        trans: &Transform,
+        loc: &Location,
+        bought: &mut Bought,
+        house: Option<BuildingID>,
+        tank: CarTank,
+    ) -> HumanDecisionKind {
+        use HumanDecisionKind::*;
+        let Some(item) = Self::item(tank.energy, items) else {
+            return Yield;
+        };
+        match self.state {
+            RefuelState::Empty => {
+                let pos = trans.position;
+                cbuf.exec_on(id, move |market: &mut Market| {
+                    market.buy(SoulID::Human(id), pos.xy(), item, 1)
+                });
+                self.state = RefuelState::WaitingForTrade;
+                Yield
+            }
+            RefuelState::WaitingForTrade => {
+                for trade in bought.0.entry(item).or_default().drain(..) {
+                    self.state = match tank.energy {
+                        EnergyKind::Electric => RefuelState::Charging,
+                        EnergyKind::Fuel => {
+                            match find_trade_place(trade.seller, trans.position.xy(), binfos, map) {
+                                Some(b) => RefuelState::BoughtAt(b),
+                                None => RefuelState::Empty,
+                            }
+                        }
+                    };
+                }
+                Yield
+            }
+            RefuelState::BoughtAt(b) => {
+                if tank.fuel >= 1.0 {
+                    self.state = RefuelState::Empty;
+                    return Yield;
+                }
+                if loc == &Location::Building(b) {
+                    cbuf.exec_on(id, move |stations: &mut GasStations| stations.join(b, id));
+                    return Yield;
+                }
+                GoTo(Destination::Building(b))
+            }
+            RefuelState::Charging => {
+                let Some(house) = house else {
+                    self.state = RefuelState::Empty;
+                    return Yield;
+                };
+                if loc != &Location::Building(house) {
+                    return GoTo(Destination::Building(house));
+                }
+                let car = tank.car;
+                cbuf.exec_ent(id, move |sim| {
+                    if let Some(v) = sim.world.vehicles.get_mut(car) {
+                        v.vehicle.fuel = 1.0;
+                    }
+                });
+                self.state = RefuelState::Empty;
+                Yield
+            }
+        }
+    }
+}
@@ -7,6 +7,11 @@ use crate::world::VehicleID;
 use egui_inspect::Inspect;
 use serde::{Deserialize, Serialize};
 
+/// Working hours of the companies without opening hours
+pub const DAY_SHIFT: (i32, i32) = (8, 18);
+/// Working hours of the night workers of companies running through the night
+pub const NIGHT_SHIFT: (i32, i32) = (22, 6);
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum WorkKind {
     Driver {
@@ -26,12 +31,17 @@ pub struct Work {
 }
 
 impl Work {
-    pub fn new(workplace: BuildingID, kind: WorkKind, offset: f32) -> Self {
+    pub fn new(
+        workplace: BuildingID,
+        kind: WorkKind,
+        (start, end): (i32, i32),
+        offset: f32,
+    ) -> Self {
         Work {
             workplace,
             work_inter: RecTimeInterval::new(
-                (8, (offset * SECONDS_PER_HOUR as f32) as i32),
-                (18, (offset * SECONDS_PER_HOUR as f32) as i32),
+                (start, (offset * SECONDS_PER_HOUR as f32) as i32),
+                (end, (offset * SECONDS_PER_HOUR as f32) as i32),
             ),
             kind,
             last_score: 0.0,
@@ -43,7 +53,7 @@ impl Work {
         match self.kind {
             WorkKind::Worker => GoTo(Destination::Building(self.workplace)),
             WorkKind::Driver {
-                deliver_order,
+                ref mut deliver_order,
                 truck,
             } => {
                 if &Location::Building(self.workplace) != loc {
@@ -51,7 +61,7 @@ impl Work {
                         GoTo(Destination::Building(self.workplace)),
                         SetVehicle(router.personal_car),
                     ])
-                } else if let Some(b) = deliver_order {
+                } else if let Some(b) = deliver_order.take() {
                     MultiStack(vec![
                         SetVehicle(router.personal_car),
                         GoTo(Destination::Building(self.workplace)),
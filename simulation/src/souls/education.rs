@@ -0,0 +1,98 @@
+use crate::map::Map;
+use crate::souls::goods_company::GoodsCompanyRegistry;
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::resources::Resources;
+use crate::utils::time::{GameTime, SECONDS_PER_HOUR};
+use crate::World;
+use common::descriptions::EducationLevel;
+use egui_inspect::Inspect;
+use serde::{Deserialize, Serialize};
+
+/// Distance from their home at which people can go to school, in meters
+pub const SCHOOL_RADIUS: f32 = 500.0;
+/// Days of study at a fully staffed school to reach the next education level
+pub const DAYS_PER_LEVEL: f32 = 10.0;
+
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct Education {
+    pub level: EducationLevel,
+    /// In [0; 1] range, how far the person is into studying the next level
+    pub progress: f32,
+}
+
+impl Education {
+    /// Newcomers studied elsewhere, most of them only have a basic education
+    pub fn new(rng: &mut RandProvider) -> Self {
+        let r = rng.next_f32();
+        let level = if r < 0.1 {
+            EducationLevel::Higher
+        } else if r < 0.4 {
+            EducationLevel::Secondary
+        } else {
+            EducationLevel::Basic
+        };
+        Self {
+            level,
+            progress: 0.0,
+        }
+    }
+
+    pub fn study(&mut self, amount: f32) {
+        let Some(next) = self.level.next() else {
+            return;
+        };
+        self.progress += amount;
+        if self.progress >= 1.0 {
+            self.level = next;
+            self.progress = 0.0;
+        }
+    }
+}
+
+/// Schools and universities raise the education of the people living around them, up to the
+/// level they teach. Understaffed schools teach slower.
+pub fn education_system(world: &mut World, res: &mut Resources) {
+    profiling::scope!("souls::education_system");
+    let time: &GameTime = &res.read();
+    if !time.tick(SECONDS_PER_HOUR as u32) {
+        return;
+    }
+    let map: &Map = &res.read();
+    let registry: &GoodsCompanyRegistry = &res.read();
+
+    let schools: Vec<_> = world
+        .companies
+        .values()
+        .filter_map(|c| {
+            let b = map.buildings().get(c.comp.building)?;
+            let teaches = registry
+                .descriptions
+                .get(b.kind.as_goods_company()?)?
+                .teaches?;
+            let staffing = c.comp.productivity(c.workers.0.len() as f32, None);
+            Some((b.door_pos.xy(), teaches, staffing.min(1.0)))
+        })
+        .collect();
+    if schools.is_empty() {
+        return;
+    }
+
+    let per_hour = 1.0 / (DAYS_PER_LEVEL * 24.0);
+
+    for h in world.humans.values_mut() {
+        let Some(home) = map.buildings().get(h.home.house) else {
+            continue;
+        };
+        let pos = home.door_pos.xy();
+        let staffing = schools
+            .iter()
+            .filter(|&&(p, teaches, _)| {
+                teaches > h.education.level && p.is_close(pos, SCHOOL_RADIUS)
+            })
+            .map(|&(_, _, staffing)| staffing)
+            .fold(0.0, f32::max);
+        if staffing > 0.0 {
+            h.education.study(staffing * per_hour);
+        }
+    }
+}
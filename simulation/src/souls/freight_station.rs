@@ -1,14 +1,21 @@
-use crate::map::{BuildingID, BuildingKind, Map, PathKind};
+use crate::economy::{Government, Money};
+use crate::map::{BuildingID, BuildingKind, LaneID, Map, MapLayer, PathKind};
 use crate::map_dynamic::{
     BuildingInfos, DispatchID, DispatchKind, DispatchQueryTarget, Dispatcher, Itinerary,
 };
+use crate::transportation::train::{
+    check_train_placement, despawn_train, spawn_train, LocomotiveKind, RailWagonKind,
+};
+use crate::utils::events::{SimEventKind, SimEvents};
 use crate::utils::resources::Resources;
 use crate::utils::time::{GameTime, Tick};
-use crate::world::{FreightStationEnt, FreightStationID, TrainID};
+use crate::world::{FreightStationEnt, FreightStationID, TrainEnt, TrainID};
 use crate::World;
 use crate::{ParCommandBuffer, Simulation, SoulID};
-use geom::Transform;
+use geom::{Transform, Vec3};
 use serde::{Deserialize, Serialize};
+use slotmapd::HopSlotMap;
+use std::collections::VecDeque;
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, Inspect)]
 pub enum FreightTrainState {
@@ -20,7 +27,106 @@ pub enum FreightTrainState {
     Moving,
 }
 
-const MAX_TRAINS_PER_STATION: usize = 2;
+/// Upper bound of the trains a station can ask for, see [`FreightStation::max_trains`]
+pub const MAX_TRAINS_PER_STATION: u32 = 8;
+const DEFAULT_MAX_TRAINS: u32 = 2;
+/// Cargo carried by a freight train, in tons
+const TRAIN_CAPACITY: u32 = 100;
+/// Wagons of the trains spawned by the stations
+const AUTO_TRAIN_WAGONS: u32 = 7;
+/// A station spawns at most one train per this many game seconds,
+/// so that the new train is registered to the dispatcher before the next one is spawned
+const AUTO_TRAIN_COOLDOWN: f64 = 120.0;
+/// Trains are spawned on a rail lane this close to the station
+const AUTO_TRAIN_RADIUS: f32 = 300.0;
+/// Same as adding a diesel train of AUTO_TRAIN_WAGONS by hand
+const AUTO_TRAIN_PRICE: Money = Money::new_bucks(1000 + 100 * AUTO_TRAIN_WAGONS as i64);
+/// Number of past days kept by the station statistics
+pub const STATS_DAYS: usize = 14;
+
+/// What happened at a station during one day
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct FreightDayStats {
+    /// Cargo brought to the station, in tons
+    pub tons_in: u32,
+    /// Cargo taken away from the station, in tons
+    pub tons_out: u32,
+    /// Sum of the time between dispatching a train and its departure, in seconds
+    pub turnaround_total: f64,
+    pub n_departures: u32,
+    /// Highest amount of cargo waiting in the yard
+    pub max_yard_cargo: u32,
+}
+
+impl FreightDayStats {
+    /// Average train turnaround time in seconds
+    pub fn avg_turnaround(&self) -> Option<f64> {
+        (self.n_departures > 0).then(|| self.turnaround_total / self.n_departures as f64)
+    }
+}
+
+/// Daily statistics of a freight station, used by the logistics dashboards
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FreightStationStats {
+    /// The last day is the current one
+    days: VecDeque<FreightDayStats>,
+    cur_day: i32,
+    /// When the trains currently serving the station were dispatched
+    dispatched_at: Vec<(TrainID, f64)>,
+}
+
+impl FreightStationStats {
+    /// Past days from the oldest to the current one
+    pub fn days(&self) -> impl ExactSizeIterator<Item = &FreightDayStats> {
+        self.days.iter()
+    }
+
+    pub fn today(&self) -> FreightDayStats {
+        self.days.back().copied().unwrap_or_default()
+    }
+
+    fn today_mut(&mut self) -> &mut FreightDayStats {
+        if self.days.is_empty() {
+            self.days.push_back(Default::default());
+        }
+        self.days.back_mut().unwrap()
+    }
+
+    fn advance(&mut self, day: i32, yard_cargo: u32) {
+        if self.days.is_empty() {
+            self.cur_day = day;
+        }
+        while self.cur_day < day {
+            self.cur_day += 1;
+            self.days.push_back(Default::default());
+            if self.days.len() > STATS_DAYS {
+                self.days.pop_front();
+            }
+        }
+        let today = self.today_mut();
+        today.max_yard_cargo = today.max_yard_cargo.max(yard_cargo);
+    }
+
+    pub(crate) fn cargo_moved(&mut self, tons_in: u32, tons_out: u32) {
+        let today = self.today_mut();
+        today.tons_in += tons_in;
+        today.tons_out += tons_out;
+    }
+
+    fn train_dispatched(&mut self, train: TrainID, timestamp: f64) {
+        self.dispatched_at.push((train, timestamp));
+    }
+
+    fn train_departed(&mut self, train: TrainID, timestamp: f64) {
+        let Some(i) = self.dispatched_at.iter().position(|x| x.0 == train) else {
+            return;
+        };
+        let (_, dispatched) = self.dispatched_at.swap_remove(i);
+        let today = self.today_mut();
+        today.turnaround_total += timestamp - dispatched;
+        today.n_departures += 1;
+    }
+}
 
 /// A freight train station
 /// A component that identifies freight station souls, managing freight station logic
@@ -31,6 +137,80 @@ pub struct FreightStation {
     pub trains: Vec<(TrainID, FreightTrainState)>,
     pub waiting_cargo: u32,
     pub wanted_cargo: u32,
+    #[inspect(skip)]
+    pub stats: FreightStationStats,
+    /// Trains kept serving the station even when no cargo is waiting
+    pub min_trains: u32,
+    /// More trains are dispatched as the backlog grows, up to this number
+    pub max_trains: u32,
+    /// Trains spawned by the station when no train was free,
+    /// they are removed once they are not needed anymore
+    pub auto_trains: Vec<TrainID>,
+    #[inspect(skip)]
+    last_spawn: f64,
+}
+
+impl FreightStation {
+    /// Number of trains needed to carry the cargo waiting at the station,
+    /// within the min/max train counts of the line
+    pub fn wanted_trains(&self) -> u32 {
+        let backlog = self.waiting_cargo + self.wanted_cargo;
+        let needed = if backlog < 10 {
+            0
+        } else {
+            (backlog + TRAIN_CAPACITY - 1) / TRAIN_CAPACITY
+        };
+        needed.clamp(self.min_trains, self.max_trains.max(self.min_trains))
+    }
+
+    /// Trains that are coming to the station or loading at it
+    pub fn serving_trains(&self) -> u32 {
+        self.trains
+            .iter()
+            .filter(|(_, state)| !matches!(state, FreightTrainState::Moving))
+            .count() as u32
+    }
+}
+
+pub fn set_freight_service(
+    sim: &mut Simulation,
+    station: FreightStationID,
+    min_trains: u32,
+    max_trains: u32,
+) {
+    let Some(f) = sim.world.freight_stations.get_mut(station) else {
+        return;
+    };
+    f.f.max_trains = max_trains.min(MAX_TRAINS_PER_STATION);
+    f.f.min_trains = min_trains.min(f.f.max_trains);
+}
+
+/// A rail lane near the position with room to spawn a train on,
+/// and the distance along it where the locomotive is put
+fn auto_train_lane(
+    map: &Map,
+    trains: &HopSlotMap<TrainID, TrainEnt>,
+    near: Vec3,
+) -> Option<(LaneID, f32)> {
+    map.lanes()
+        .iter()
+        // the metro tunnels are for the metro trains
+        .filter(|(_, l)| MapLayer::of(l.points.first(), &map.environment) == MapLayer::Surface)
+        .map(|(id, l)| (id, l.points.length() - 0.5, l.points.project_dist2(near)))
+        .filter(|&(_, _, d2)| d2 < AUTO_TRAIN_RADIUS * AUTO_TRAIN_RADIUS)
+        .filter(|&(id, dist, _)| {
+            check_train_placement(
+                map,
+                trains,
+                id,
+                dist,
+                AUTO_TRAIN_WAGONS,
+                LocomotiveKind::Diesel,
+            )
+            .is_ok()
+        })
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(id, dist, _)| (id, dist))
 }
 
 pub fn freight_station_soul(
@@ -41,9 +221,14 @@ pub fn freight_station_soul(
 
     let f = FreightStation {
         building,
-        trains: Vec::with_capacity(MAX_TRAINS_PER_STATION),
+        trains: Vec::with_capacity(DEFAULT_MAX_TRAINS as usize),
         waiting_cargo: 0,
         wanted_cargo: 0,
+        stats: Default::default(),
+        min_trains: 0,
+        max_trains: DEFAULT_MAX_TRAINS,
+        auto_trains: vec![],
+        last_spawn: 0.0,
     };
     let b = map.buildings.get(building)?;
 
@@ -66,11 +251,14 @@ pub fn freight_station_soul(
 }
 
 pub fn freight_station_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("souls::freight_station_system");
     let cbuf = resources.read::<ParCommandBuffer<FreightStationEnt>>();
     let mut dispatch = resources.write::<Dispatcher>();
     let map = resources.read::<Map>();
     let time = resources.read::<GameTime>();
     let tick = *resources.read::<Tick>();
+    let mut events = resources.write::<SimEvents>();
+    let gvt_money = resources.read::<Government>().money;
 
     for (me, f) in world.freight_stations.iter_mut() {
         let pos = f.trans;
@@ -79,6 +267,17 @@ pub fn freight_station_system(world: &mut World, resources: &mut Resources) {
             cbuf.kill(me);
             continue;
         }
+        station
+            .stats
+            .advance(time.daytime.day, station.waiting_cargo);
+
+        // airports and harbors ship their cargo by plane or barge, see their systems
+        if matches!(
+            map.buildings[station.building].kind,
+            BuildingKind::Airport | BuildingKind::Harbor
+        ) {
+            continue;
+        }
 
         // update our trains, and remove the ones that are done
         let mut to_clean = vec![];
@@ -92,9 +291,13 @@ pub fn freight_station_system(world: &mut World, resources: &mut Resources) {
             match state {
                 FreightTrainState::Arriving => {
                     if itin.has_ended(0.0) {
+                        events.push(tick, train.trans.position, SimEventKind::TrainArrived);
                         *state = FreightTrainState::Loading;
-                        station.waiting_cargo = station.waiting_cargo.saturating_sub(100);
-                        station.wanted_cargo = station.wanted_cargo.saturating_sub(100);
+                        let tons_out = station.waiting_cargo.min(TRAIN_CAPACITY);
+                        let tons_in = station.wanted_cargo.min(TRAIN_CAPACITY);
+                        station.waiting_cargo -= tons_out;
+                        station.wanted_cargo -= tons_in;
+                        station.stats.cargo_moved(tons_in, tons_out);
                         *itin = Itinerary::wait_until(time.timestamp + 10.0);
                     }
                 }
@@ -103,15 +306,20 @@ pub fn freight_station_system(world: &mut World, resources: &mut Resources) {
                         let ext = map.bkinds.get(&BuildingKind::ExternalTrading).unwrap()[0];
                         let bpos = map.buildings[ext].obb.center().z(0.0);
 
-                        *itin = if let Some(r) =
-                            Itinerary::route(tick, train.trans.position, bpos, &map, PathKind::Rail)
-                        {
+                        *itin = if let Some(r) = Itinerary::route(
+                            tick,
+                            train.trans.position,
+                            bpos,
+                            &map,
+                            train.locomotive.kind.path_kind(),
+                        ) {
                             r
                         } else {
                             Itinerary::wait_until(time.timestamp + 10.0);
                             continue;
                         };
                         *state = FreightTrainState::Moving;
+                        station.stats.train_departed(*trainid, time.timestamp);
                     }
                 }
                 FreightTrainState::Moving => {
@@ -121,16 +329,21 @@ pub fn freight_station_system(world: &mut World, resources: &mut Resources) {
                 }
             }
         }
+        let wanted = station.wanted_trains();
+        let serving = station.serving_trains();
         for v in to_clean {
             station.trains.retain(|x| x.0 != v);
+            // the trains spawned for a backlog leave the network once it is cleared
+            if station.auto_trains.contains(&v) && serving >= wanted {
+                station.auto_trains.retain(|&x| x != v);
+                cbuf.exec_ent(me, move |sim| despawn_train(sim, v));
+                continue;
+            }
             dispatch.free(v)
         }
 
-        // If enough goods are waiting, query for a train to take them to the external trading station
-        if station.trains.len() >= MAX_TRAINS_PER_STATION {
-            continue;
-        }
-        if station.waiting_cargo + station.wanted_cargo < 10 {
+        // Query for trains to take the waiting goods to the external trading station
+        if serving >= wanted {
             continue;
         }
 
@@ -141,6 +354,49 @@ pub fn freight_station_system(world: &mut World, resources: &mut Resources) {
             DispatchKind::FreightTrain,
             DispatchQueryTarget::Pos(destination),
         ) else {
+            // no train is free, add one to the network
+            if time.timestamp - station.last_spawn < AUTO_TRAIN_COOLDOWN {
+                continue;
+            }
+            if gvt_money < AUTO_TRAIN_PRICE {
+                continue;
+            }
+            let Some((lane, dist)) = auto_train_lane(&map, &world.trains, destination) else {
+                continue;
+            };
+            station.last_spawn = time.timestamp;
+            cbuf.exec_ent(me, move |sim| {
+                // another station might have bought a train or spent the money in the meantime
+                if sim.read::<Government>().money < AUTO_TRAIN_PRICE
+                    || check_train_placement(
+                        &sim.map(),
+                        &sim.world.trains,
+                        lane,
+                        dist,
+                        AUTO_TRAIN_WAGONS,
+                        LocomotiveKind::Diesel,
+                    )
+                    .is_err()
+                {
+                    return;
+                }
+                let Some(train) = spawn_train(
+                    sim,
+                    dist,
+                    AUTO_TRAIN_WAGONS,
+                    lane,
+                    RailWagonKind::Freight,
+                    LocomotiveKind::Diesel,
+                ) else {
+                    return;
+                };
+                let timestamp = sim.read::<GameTime>().timestamp;
+                sim.write::<Government>()
+                    .spend(AUTO_TRAIN_PRICE, "Freight train", timestamp);
+                if let Some(f) = sim.world.freight_stations.get_mut(me) {
+                    f.f.auto_trains.push(train);
+                }
+            });
             continue;
         };
 
@@ -152,22 +408,24 @@ pub fn freight_station_system(world: &mut World, resources: &mut Resources) {
                 train.trans.position,
                 destination,
                 &map,
-                PathKind::Rail,
+                train.locomotive.kind.path_kind(),
             ),
             continue
         );
 
         station.trains.push((trainid, FreightTrainState::Arriving));
+        station.stats.train_dispatched(trainid, time.timestamp);
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{FreightStation, DEFAULT_MAX_TRAINS};
     use crate::map_dynamic::BuildingInfos;
     use crate::souls::human::{spawn_human, HumanDecisionKind};
     use crate::tests::TestCtx;
     use crate::{BuildingKind, SoulID, WorldCommand};
-    use common::descriptions::BuildingGen;
+    use common::descriptions::{BuildingGen, DoorLayout};
     use geom::{vec2, vec3, OBB};
 
     #[test]
@@ -183,6 +441,7 @@ mod tests {
             kind: BuildingKind::RailFreightStation,
             gen: BuildingGen::NoWalkway {
                 door_pos: vec2(50.0, 50.0),
+                doors: DoorLayout::NONE,
             },
             zone: None,
         }]);
@@ -221,4 +480,29 @@ mod tests {
 
         panic!("should have delivered to freight station")
     }
+
+    #[test]
+    fn wanted_trains_follow_the_backlog() {
+        let mut station = FreightStation {
+            building: Default::default(),
+            trains: vec![],
+            waiting_cargo: 0,
+            wanted_cargo: 0,
+            stats: Default::default(),
+            min_trains: 0,
+            max_trains: DEFAULT_MAX_TRAINS,
+            auto_trains: vec![],
+            last_spawn: 0.0,
+        };
+        assert_eq!(station.wanted_trains(), 0);
+
+        station.min_trains = 1;
+        assert_eq!(station.wanted_trains(), 1);
+
+        station.waiting_cargo = 150;
+        assert_eq!(station.wanted_trains(), 2);
+
+        station.waiting_cargo = 1000;
+        assert_eq!(station.wanted_trains(), DEFAULT_MAX_TRAINS);
+    }
 }
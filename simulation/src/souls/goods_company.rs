@@ -1,21 +1,26 @@
-use super::desire::Work;
-use crate::economy::{find_trade_place, ItemID, ItemRegistry, Market};
-use crate::map::{Building, BuildingID, Map, Zone, MAX_ZONE_AREA};
+use super::desire::{Work, DAY_SHIFT, NIGHT_SHIFT};
+use crate::economy::{find_trade_place, ItemID, ItemRegistry, Market, Money, TradeTarget};
+use crate::map::{Building, BuildingID, BuildingKind, Map, Zone, MAX_ZONE_AREA};
 use crate::map_dynamic::BuildingInfos;
+use crate::souls::branding::Brand;
 use crate::souls::desire::WorkKind;
+use crate::transportation::{spawn_parked_vehicle, VehicleKind};
 use crate::utils::resources::Resources;
-use crate::utils::time::GameTime;
-use crate::world::{CompanyEnt, HumanEnt, HumanID, VehicleID};
+use crate::utils::time::{DayTime, GameTime, RecTimeInterval, SECONDS_PER_HOUR};
+use crate::world::{CompanyEnt, CompanyID, HumanEnt, HumanID, VehicleEnt, VehicleID};
 use crate::{ParCommandBuffer, SoulID};
 use crate::{Simulation, World};
 use common::descriptions::{
-    BuildingGen, CompanyKind, GoodsCompanyDescriptionJSON, ZoneDescription,
+    BuildingGen, CompanyKind, EducationLevel, GoodsCompanyDescriptionJSON, LeisureDescription,
+    OpeningHours, ServiceKind, ZoneDescription,
 };
+use common::expr::Expr;
 use common::saveload::Encoder;
 use egui_inspect::Inspect;
-use geom::{Transform, Vec2};
+use geom::{Color, Transform, Vec2, Vec3};
+use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
-use slotmapd::{new_key_type, SlotMap};
+use slotmapd::{new_key_type, HopSlotMap, SlotMap};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Inspect)]
 pub struct Recipe {
@@ -49,6 +54,60 @@ pub struct GoodsCompanyDescription {
     pub asset_location: String,
     pub price: i64,
     pub zone: Option<Box<ZoneDescription>>,
+    pub opening_hours: Option<OpeningHours>,
+    pub night_shift: bool,
+    pub qualification: EducationLevel,
+    pub teaches: Option<EducationLevel>,
+    pub service: Option<ServiceKind>,
+    pub leisure: Option<LeisureDescription>,
+    pub productivity: Option<Expr>,
+    pub wage: Option<Expr>,
+}
+
+/// Variables of the productivity formula of the descriptions
+pub const PRODUCTIVITY_VARS: [&str; 3] = ["workers", "max_workers", "zone"];
+/// Variables of the wage formula of the descriptions
+pub const WAGE_VARS: [&str; 1] = ["qualification"];
+
+impl GoodsCompanyDescription {
+    /// Productivity of the company, using the formula of the description if it has one
+    pub fn productivity(&self, company: &GoodsCompany, workers: f32, zone: Option<&Zone>) -> f32 {
+        let Some(ref formula) = self.productivity else {
+            return company.productivity(workers, zone);
+        };
+        formula
+            .eval(&[
+                ("workers", workers),
+                ("max_workers", company.max_workers as f32),
+                ("zone", zone_factor(zone)),
+            ])
+            .max(0.0)
+    }
+
+    /// Wage of the workers relative to the base wage
+    pub fn wage(&self) -> f32 {
+        self.wage.as_ref().map_or(1.0, |formula| {
+            formula
+                .eval(&[("qualification", self.qualification as u8 as f32)])
+                .max(0.0)
+        })
+    }
+
+    /// Companies without opening hours are always open
+    pub fn is_open(&self, t: DayTime) -> bool {
+        self.opening_hours.map_or(true, |h| {
+            RecTimeInterval::new((h.open, 0), (h.close, 0)).dist_until(t) == 0
+        })
+    }
+
+    /// Hours during which the worker works, one worker out of two works at night in night
+    /// shift companies
+    pub fn shift(&self, worker: HumanID) -> (i32, i32) {
+        if self.night_shift && common::hash_u64(worker) % 2 == 1 {
+            return NIGHT_SHIFT;
+        }
+        self.opening_hours.map_or(DAY_SHIFT, |h| (h.open, h.close))
+    }
 }
 
 #[derive(Default)]
@@ -105,12 +164,40 @@ impl GoodsCompanyRegistry {
                     asset_location: descr.asset_location,
                     price: descr.price,
                     zone: descr.zone,
+                    opening_hours: descr.opening_hours,
+                    night_shift: descr.night_shift,
+                    qualification: descr.qualification,
+                    teaches: descr.teaches,
+                    service: descr.service,
+                    leisure: descr.leisure,
+                    productivity: checked_formula(descr.productivity, &PRODUCTIVITY_VARS),
+                    wage: checked_formula(descr.wage, &WAGE_VARS),
                 });
 
             #[cfg(not(test))]
             log::debug!("loaded {:?}", &self.descriptions[id]);
         }
     }
+
+    /// Wether the building is open at the given time, buildings that aren't companies are
+    /// always open
+    pub fn is_open(&self, map: &Map, building: BuildingID, t: DayTime) -> bool {
+        map.buildings()
+            .get(building)
+            .and_then(|b| b.kind.as_goods_company())
+            .and_then(|id| self.descriptions.get(id))
+            .map_or(true, |d| d.is_open(t))
+    }
+}
+
+/// Formulas using unknown variables are ignored, the default behavior is kept
+fn checked_formula(formula: Option<Expr>, vars: &[&str]) -> Option<Expr> {
+    let formula = formula?;
+    if let Err(e) = formula.check_vars(vars) {
+        log::error!("couldn't load goods company formula: {}", e);
+        return None;
+    }
+    Some(formula)
 }
 
 impl Recipe {
@@ -153,18 +240,91 @@ pub struct GoodsCompany {
     pub recipe: Recipe,
     pub building: BuildingID,
     pub max_workers: i32,
+    /// Education the workers need to be fully productive
+    pub qualification: EducationLevel,
     /// In [0; 1] range, to show how much has been made until new product
     pub progress: f32,
+    /// Delivery trucks, each driven by one of the workers
+    pub fleet: Vec<FleetTruck>,
+    /// Number of trucks the company wants, trucks are bought or sold until the fleet has this size
+    pub fleet_size: u32,
+    pub freight_mode: FreightMode,
+    /// Average share of the fleet out delivering, in [0; 1] range
+    pub fleet_utilization: f32,
+    pub brand: Brand,
+    /// What the customers paid the company, after taxes
+    pub money: Money,
+}
+
+/// How a company ships the goods it sells
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FreightMode {
+    /// Trucks carry the goods to the buyer
+    #[default]
+    Truck,
+    /// Trucks carry the exported goods to the nearest rail freight station, trains carry them
+    /// from there. The goods sold in the city are still brought to the buyer.
+    Rail,
+}
+
+debug_inspect_impl!(FreightMode);
+
+/// A truck of a delivery fleet and the worker driving it
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct FleetTruck {
+    pub truck: VehicleID,
     pub driver: Option<HumanID>,
-    pub trucks: Vec<VehicleID>,
 }
 
+debug_inspect_impl!(FleetTruck);
+
+/// Largest delivery fleet a company can have
+pub const MAX_FLEET_SIZE: u32 = 20;
+/// Price of a delivery truck
+pub const TRUCK_PRICE: Money = Money::new_bucks(200);
+/// Utilization above which a fleet is too small for the deliveries of its company
+pub const FLEET_BUSY: f32 = 0.8;
+/// Game seconds over which the fleet utilization is averaged
+const UTILIZATION_PERIOD: f32 = SECONDS_PER_HOUR as f32;
+/// Deliveries waiting for a truck, the oldest ones are given up on
+const MAX_DELIVERY_BACKLOG: usize = 50;
+
+/// How much an unqualified worker contributes compared to a qualified one
+pub const UNQUALIFIED_EFFICIENCY: f32 = 0.25;
+/// How much each worker over the capacity of an overcrowded workplace takes away
+pub const OVERCROWDING_PENALTY: f32 = 0.5;
+
 impl GoodsCompany {
-    pub fn productivity(&self, workers: usize, zone: Option<&Zone>) -> f32 {
-        workers as f32 / self.max_workers as f32 * zone.map_or(1.0, |z| z.area / MAX_ZONE_AREA)
+    /// Number of workers weighted by wether they have the education the job needs
+    pub fn effective_workers(&self, workers: impl Iterator<Item = EducationLevel>) -> f32 {
+        workers
+            .map(|level| {
+                if level >= self.qualification {
+                    1.0
+                } else {
+                    UNQUALIFIED_EFFICIENCY
+                }
+            })
+            .sum()
+    }
+
+    /// Workers over the capacity of the building get in the way of the others
+    pub fn productivity(&self, workers: f32, zone: Option<&Zone>) -> f32 {
+        let max = self.max_workers as f32;
+        let workers = if workers > max {
+            (max - OVERCROWDING_PENALTY * (workers - max)).max(0.0)
+        } else {
+            workers
+        };
+        workers / max * zone_factor(zone)
     }
 }
 
+/// Zone area relative to the maximum, 1 for companies without zones
+fn zone_factor(zone: Option<&Zone>) -> f32 {
+    zone.map_or(1.0, |z| z.area / MAX_ZONE_AREA)
+}
+
 pub fn company_soul(sim: &mut Simulation, company: GoodsCompany) -> Option<SoulID> {
     let map = sim.map();
     let b = map.buildings().get(company.building)?;
@@ -203,7 +363,9 @@ pub fn company_soul(sim: &mut Simulation, company: GoodsCompany) -> Option<SoulI
 
 pub fn company_system(world: &mut World, res: &mut Resources) {
     profiling::scope!("souls::company_system");
-    let delta = res.read::<GameTime>().realdelta;
+    let time: &GameTime = &res.read();
+    let delta = time.realdelta;
+    let registry: &GoodsCompanyRegistry = &res.read();
     let cbuf: &ParCommandBuffer<CompanyEnt> = &res.read();
     let cbuf_human: &ParCommandBuffer<HumanEnt> = &res.read();
     let binfos: &BuildingInfos = &res.read();
@@ -211,17 +373,32 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
     let map: &Map = &res.read();
 
     world.companies.iter_mut().for_each(|(me, c)| {
-        let n_workers = c.workers.0.len();
+        let n_workers = c.comp.effective_workers(
+            c.workers
+                .0
+                .iter()
+                .filter_map(|&w| world.humans.get(w))
+                .map(|h| h.education.level),
+        );
         let soul = SoulID::GoodsCompany(me);
         let b: &Building = unwrap_or!(map.buildings.get(c.comp.building), {
             cbuf.kill(me);
             return;
         });
 
-        if c.comp.recipe.should_produce(soul, market) {
-            c.comp.progress += c.comp.productivity(n_workers, b.zone.as_ref())
-                / c.comp.recipe.complexity as f32
-                * delta;
+        let descr = b
+            .kind
+            .as_goods_company()
+            .and_then(|id| registry.descriptions.get(id));
+
+        if descr.map_or(true, |d| d.is_open(time.daytime))
+            && c.comp.recipe.should_produce(soul, market)
+        {
+            let productivity = match descr {
+                Some(d) => d.productivity(&c.comp, n_workers, b.zone.as_ref()),
+                None => c.comp.productivity(n_workers, b.zone.as_ref()),
+            };
+            c.comp.progress += productivity / c.comp.recipe.complexity as f32 * delta;
         }
 
         if c.comp.progress >= 1.0 {
@@ -254,30 +431,39 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
             }
         }
 
-        (|| {
-            let Some(trade) = c.sold.0.pop() else {
-                return;
-            };
-            let Some(driver) = c.comp.driver else {
-                return;
-            };
-            let Some(w) = world.humans.get(driver).and_then(|h| h.work.as_ref()) else {
-                return;
+        update_fleet(me, c, b.door_pos, &world.humans, &world.vehicles, cbuf);
+
+        if c.comp.fleet.is_empty() {
+            c.sold.0.clear();
+        }
+        let backlog = c.sold.0.len();
+        if backlog > MAX_DELIVERY_BACKLOG {
+            c.sold.0.drain(..backlog - MAX_DELIVERY_BACKLOG);
+        }
+
+        let mut busy = 0;
+        for t in &c.comp.fleet {
+            let Some(driver) = t.driver else {
+                continue;
             };
-            if !matches!(
-                w.kind,
-                WorkKind::Driver {
-                    deliver_order: None,
-                    ..
-                }
-            ) {
-                return;
+            if is_delivering(&world.humans, driver, t.truck) {
+                busy += 1;
+                continue;
             }
-            let Some(owner_build) = find_trade_place(trade.buyer, b.door_pos.xy(), binfos, map)
-            else {
+            let Some(trade) = c.sold.0.pop() else {
+                continue;
+            };
+            let Some(place) = delivery_place(
+                c.comp.freight_mode,
+                trade.buyer,
+                b.door_pos.xy(),
+                binfos,
+                map,
+            ) else {
                 log::warn!("driver can't find the place to deliver for {:?}", &trade);
-                return;
+                continue;
             };
+            busy += 1;
             cbuf.exec_ent(me, move |sim| {
                 let Some(h) = sim.world.humans.get_mut(driver) else {
                     return;
@@ -288,40 +474,322 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
                 let WorkKind::Driver { deliver_order, .. } = &mut w.kind else {
                     return;
                 };
-                *deliver_order = Some(owner_build)
+                *deliver_order = Some(place)
             });
-        })();
+        }
+
+        if !c.comp.fleet.is_empty() {
+            let busy = busy as f32 / c.comp.fleet.len() as f32;
+            c.comp.fleet_utilization +=
+                (busy - c.comp.fleet_utilization) * (delta / UTILIZATION_PERIOD).min(1.0);
+        }
 
         for &worker in c.workers.0.iter() {
             let Some(w) = world.humans.get(worker) else {
                 continue;
             };
 
-            if w.work.is_none() {
-                let mut kind = WorkKind::Worker;
+            let is_worker = match w.work {
+                None => false,
+                Some(ref work) => matches!(work.kind, WorkKind::Worker),
+            };
+            if w.work.is_some() && !is_worker {
+                continue;
+            }
 
-                if let Some(truck) = c.comp.trucks.get(0) {
-                    if matches!(c.comp.kind, CompanyKind::Factory { .. }) && c.comp.driver.is_none()
-                    {
-                        kind = WorkKind::Driver {
-                            deliver_order: None,
-                            truck: *truck,
-                        };
+            let mut kind = WorkKind::Worker;
+            if matches!(c.comp.kind, CompanyKind::Factory { .. }) {
+                if let Some(t) = c.comp.fleet.iter_mut().find(|t| t.driver.is_none()) {
+                    kind = WorkKind::Driver {
+                        deliver_order: None,
+                        truck: t.truck,
+                    };
+                    t.driver = Some(worker);
+                }
+            }
 
-                        c.comp.driver = Some(worker);
-                    }
+            if is_worker {
+                // a worker takes the wheel of a truck without driver
+                if let WorkKind::Driver { .. } = kind {
+                    cbuf_human.exec_ent(worker, move |sim| {
+                        if let Some(w) = sim.world.humans.get_mut(worker) {
+                            if let Some(ref mut work) = w.work {
+                                work.kind = kind;
+                            }
+                        }
+                    });
                 }
+                continue;
+            }
 
-                let offset = common::rand::randu(common::hash_u64(worker) as u32);
+            let offset = common::rand::randu(common::hash_u64(worker) as u32);
+            let shift = descr.map_or(DAY_SHIFT, |d| d.shift(worker));
 
-                let b = c.comp.building;
-                cbuf_human.exec_ent(worker, move |sim| {
-                    let Some(w) = sim.world.humans.get_mut(worker) else {
-                        return;
-                    };
-                    w.work = Some(Work::new(b, kind, offset));
-                });
-            }
+            let b = c.comp.building;
+            cbuf_human.exec_ent(worker, move |sim| {
+                let Some(w) = sim.world.humans.get_mut(worker) else {
+                    return;
+                };
+                w.work = Some(Work::new(b, kind, shift, offset));
+            });
         }
     });
 }
+
+/// Spawns a delivery truck painted in the color of its company
+pub(crate) fn spawn_fleet_truck(sim: &mut Simulation, pos: Vec3, tint: Color) -> Option<VehicleID> {
+    let truck = spawn_parked_vehicle(sim, VehicleKind::Truck, pos)?;
+    if let Some(v) = sim.world.vehicles.get_mut(truck) {
+        v.vehicle.tint = tint;
+    }
+    Some(truck)
+}
+
+/// Keeps the fleet in sync with its drivers and buys or sells trucks until it has the wanted
+/// size. Only trucks that aren't out delivering are sold.
+fn update_fleet(
+    me: CompanyID,
+    c: &mut CompanyEnt,
+    door_pos: Vec3,
+    humans: &HopSlotMap<HumanID, HumanEnt>,
+    vehicles: &HopSlotMap<VehicleID, VehicleEnt>,
+    cbuf: &ParCommandBuffer<CompanyEnt>,
+) {
+    for t in &mut c.comp.fleet {
+        if t.driver.map_or(false, |d| !c.workers.0.contains(&d)) {
+            t.driver = None;
+        }
+    }
+
+    // trucks can be lost, towed away for example
+    let (kept, lost): (Vec<_>, Vec<_>) = std::mem::take(&mut c.comp.fleet)
+        .into_iter()
+        .partition(|t| vehicles.contains_key(t.truck));
+    c.comp.fleet = kept;
+    for t in lost {
+        if let Some(driver) = t.driver {
+            cbuf.exec_ent(me, move |sim| demote_driver(sim, driver));
+        }
+    }
+
+    let size = c.comp.fleet_size.min(MAX_FLEET_SIZE) as usize;
+    if c.comp.fleet.len() < size {
+        let tint = c.comp.brand.color;
+        cbuf.exec_ent(me, move |sim| {
+            let Some(truck) = spawn_fleet_truck(sim, door_pos, tint) else {
+                return;
+            };
+            let Some(c) = sim.world.companies.get_mut(me) else {
+                sim.read::<ParCommandBuffer<VehicleEnt>>().kill(truck);
+                return;
+            };
+            c.comp.fleet.push(FleetTruck {
+                truck,
+                driver: None,
+            });
+        });
+    } else if c.comp.fleet.len() > size {
+        let idle = c.comp.fleet.iter().position(|t| {
+            t.driver
+                .map_or(true, |d| !is_delivering(humans, d, t.truck))
+        });
+        let Some(i) = idle else {
+            return;
+        };
+        let t = c.comp.fleet.remove(i);
+        cbuf.exec_ent(me, move |sim| {
+            sim.read::<ParCommandBuffer<VehicleEnt>>().kill(t.truck);
+            if let Some(driver) = t.driver {
+                demote_driver(sim, driver);
+            }
+        });
+    }
+}
+
+/// Whether the driver has a delivery to make or is on the road with the truck
+fn is_delivering(
+    humans: &HopSlotMap<HumanID, HumanEnt>,
+    driver: HumanID,
+    truck: VehicleID,
+) -> bool {
+    let Some(h) = humans.get(driver) else {
+        return false;
+    };
+    h.router.vehicle() == Some(truck)
+        || matches!(
+            h.work.as_ref().map(|w| w.kind),
+            Some(WorkKind::Driver {
+                deliver_order: Some(_),
+                ..
+            })
+        )
+}
+
+/// The driver goes back to working at the company
+fn demote_driver(sim: &mut Simulation, driver: HumanID) {
+    let Some(w) = sim
+        .world
+        .humans
+        .get_mut(driver)
+        .and_then(|h| h.work.as_mut())
+    else {
+        return;
+    };
+    w.kind = WorkKind::Worker;
+}
+
+/// Where a truck delivers the goods sold to the buyer. The goods sold in the city are brought to
+/// the buyer, companies preferring rail drop their exports at the nearest rail freight station
+/// rather than at the nearest place trading with the outside.
+fn delivery_place(
+    mode: FreightMode,
+    buyer: TradeTarget,
+    from: Vec2,
+    binfos: &BuildingInfos,
+    map: &Map,
+) -> Option<BuildingID> {
+    if mode == FreightMode::Truck || buyer != TradeTarget::ExternalTrade {
+        return find_trade_place(buyer, from, binfos, map);
+    }
+    map.bkinds
+        .get(&BuildingKind::RailFreightStation)
+        .into_iter()
+        .flatten()
+        .filter_map(|&b| map.buildings().get(b))
+        .min_by_key(|b| OrderedFloat(b.door_pos.xy().distance2(from)))
+        .map(|b| b.id)
+        .or_else(|| find_trade_place(buyer, from, binfos, map))
+}
+
+/// Changes how many trucks the company wants and how it ships its goods
+pub fn set_logistics(sim: &mut Simulation, company: CompanyID, fleet_size: u32, mode: FreightMode) {
+    let Some(c) = sim.world.companies.get_mut(company) else {
+        return;
+    };
+    c.comp.fleet_size = fleet_size.min(MAX_FLEET_SIZE);
+    c.comp.freight_mode = mode;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use crate::utils::time::{SECONDS_PER_DAY, SECONDS_PER_HOUR};
+    use crate::WorldCommand;
+    use common::descriptions::DoorLayout;
+    use geom::{vec2, vec3, OBB};
+
+    #[test]
+    fn bars_are_open_past_midnight() {
+        let mut items = ItemRegistry::default();
+        items
+            .load_item_definitions(&common::saveload::load_string("../assets/items.json").unwrap());
+        let mut companies = GoodsCompanyRegistry::default();
+        companies.load(
+            &common::saveload::load_string("../assets/companies.json").unwrap(),
+            &items,
+        );
+
+        let bar = companies
+            .descriptions
+            .values()
+            .find(|d| d.name == "Bar")
+            .unwrap();
+        assert!(bar.is_open(DayTime::new(23 * SECONDS_PER_HOUR)));
+        assert!(bar.is_open(DayTime::new(SECONDS_PER_DAY + SECONDS_PER_HOUR)));
+        assert!(!bar.is_open(DayTime::new(SECONDS_PER_DAY + 12 * SECONDS_PER_HOUR)));
+    }
+
+    #[test]
+    fn unqualified_workers_slow_down_production() {
+        let company = GoodsCompany {
+            kind: CompanyKind::Store,
+            recipe: Recipe {
+                consumption: vec![],
+                production: vec![],
+                complexity: 100,
+                storage_multiplier: 5,
+            },
+            building: BuildingID::default(),
+            max_workers: 4,
+            qualification: EducationLevel::Higher,
+            progress: 0.0,
+            fleet: vec![],
+            fleet_size: 0,
+            freight_mode: FreightMode::Truck,
+            fleet_utilization: 0.0,
+            brand: Brand::default(),
+            money: Money::ZERO,
+        };
+
+        let qualified = [EducationLevel::Higher; 4];
+        let unqualified = [EducationLevel::Secondary; 4];
+        assert_eq!(
+            company.productivity(company.effective_workers(qualified.into_iter()), None),
+            1.0
+        );
+        assert_eq!(
+            company.productivity(company.effective_workers(unqualified.into_iter()), None),
+            UNQUALIFIED_EFFICIENCY
+        );
+    }
+
+    #[test]
+    fn only_exports_go_through_the_freight_station() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(400.0, 0.0, 0.0)]);
+        let shop = test.build_house_near(vec2(350.0, 50.0));
+        test.apply(&[WorldCommand::MapBuildSpecialBuilding {
+            pos: OBB::new(vec2(50.0, 50.0), vec2(1.0, 0.0), 5.0, 5.0),
+            kind: BuildingKind::RailFreightStation,
+            gen: BuildingGen::NoWalkway {
+                door_pos: vec2(50.0, 50.0),
+                doors: DoorLayout::NONE,
+            },
+            zone: None,
+        }]);
+        test.tick();
+        let station = test.g.map().bkinds[&BuildingKind::RailFreightStation][0];
+
+        let buyer = company_soul(
+            &mut test.g,
+            GoodsCompany {
+                kind: CompanyKind::Store,
+                recipe: Recipe {
+                    consumption: vec![],
+                    production: vec![],
+                    complexity: 100,
+                    storage_multiplier: 5,
+                },
+                building: shop,
+                max_workers: 1,
+                qualification: Default::default(),
+                progress: 0.0,
+                fleet: vec![],
+                fleet_size: 0,
+                freight_mode: Default::default(),
+                fleet_utilization: 0.0,
+                brand: Default::default(),
+                money: Money::ZERO,
+            },
+        )
+        .unwrap();
+
+        let map = test.g.map();
+        let binfos = test.g.read::<BuildingInfos>();
+        let place = |mode, buyer| delivery_place(mode, buyer, vec2(0.0, 50.0), &binfos, &map);
+        // the station is on the way but the buyer is in the city
+        assert_eq!(
+            place(FreightMode::Rail, TradeTarget::Soul(buyer)),
+            Some(shop)
+        );
+        assert_eq!(
+            place(FreightMode::Truck, TradeTarget::Soul(buyer)),
+            Some(shop)
+        );
+        assert_eq!(
+            place(FreightMode::Rail, TradeTarget::ExternalTrade),
+            Some(station)
+        );
+    }
+}
@@ -0,0 +1,386 @@
+use crate::economy::{ItemRegistry, Market};
+use crate::map::{is_dirty, BuildingID, Map, ProjectFilter, ProjectKind};
+use crate::map_dynamic::{BuildingInfos, Destination};
+use crate::souls::goods_company::{GoodsCompany, GoodsCompanyRegistry};
+use crate::souls::human::HumanDecisionKind;
+use crate::transportation::Location;
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::resources::Resources;
+use crate::utils::time::{GameInstant, GameTime, SECONDS_PER_HOUR};
+use crate::world::{HumanEnt, HumanID, VehicleEnt};
+use crate::{ParCommandBuffer, SoulID, World};
+use common::descriptions::{CompanyKind, ServiceKind};
+use egui_inspect::Inspect;
+use geom::Vec2;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Distance at which factories pollute the air of the homes, in meters
+pub const POLLUTION_RADIUS: f32 = 300.0;
+/// Distance at which the traffic of the roads is heard from the homes, in meters
+pub const NOISE_RADIUS: f32 = 60.0;
+/// Number of factories close by at which a home is considered fully polluted
+const POLLUTING_FACTORIES: f32 = 2.0;
+/// Number of traffic lanes nearby at which a home is considered fully exposed to noise
+const NOISY_LANES: f32 = 8.0;
+/// Patients a fully staffed hospital takes care of per worker
+pub const BEDS_PER_WORKER: f32 = 2.0;
+/// Days for the health to settle at what the age and the home of the person allow
+const DAYS_TO_SETTLE: f32 = 20.0;
+/// Probability of falling sick during an hour for someone with no health left
+const HOURLY_SICKNESS_CHANCE: f32 = 0.01;
+/// Probability of getting better alone during an hour while sick
+const HOURLY_SELF_RECOVERY: f32 = 0.01;
+/// Health lost per hour while sick outside of a hospital
+const SICK_DECLINE: f32 = 1.0 / (4.0 * 24.0);
+/// Health regained per hour while treated at a hospital
+const HOSPITAL_RECOVERY: f32 = 1.0 / 24.0;
+/// Health at which patients leave the hospital
+const CURED_HEALTH: f32 = 0.7;
+/// Deaths kept for the health window
+const MAX_DEATHS: usize = 32;
+
+/// Health of a person, it declines with age and with the pollution and the noise around their
+/// home. Unhealthy people fall sick more often, the sick go to a hospital to recover and die if
+/// their health runs out.
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct Health {
+    /// In [0; 1] range
+    pub value: f32,
+    pub sick: bool,
+    /// Hospital with a bed for the person while sick
+    pub hospital: Option<BuildingID>,
+    pub last_score: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            value: 1.0,
+            sick: false,
+            hospital: None,
+            last_score: 0.0,
+        }
+    }
+}
+
+impl Health {
+    /// The sick drop everything else
+    pub fn score(&self) -> f32 {
+        if self.sick {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Goes to the hospital, or rests at home when all of them are full
+    pub fn apply(&self, house: BuildingID) -> HumanDecisionKind {
+        HumanDecisionKind::GoTo(Destination::Building(self.hospital.unwrap_or(house)))
+    }
+}
+
+/// Pollution and noise around the given position, both in [0; 1] range.
+/// Factories pollute and busy roads are noisy
+pub fn exposure(map: &Map, factories: &[Vec2], pos: Vec2) -> (f32, f32) {
+    let pollution = factories
+        .iter()
+        .map(|f| (1.0 - f.distance(pos) / POLLUTION_RADIUS).max(0.0))
+        .sum::<f32>()
+        / POLLUTING_FACTORIES;
+
+    let lanes = map
+        .spatial_map()
+        .query_around(pos, NOISE_RADIUS, ProjectFilter::ROAD)
+        .filter_map(|k| match k {
+            ProjectKind::Road(id) => map.roads().get(id),
+            _ => None,
+        })
+        .map(|r| r.lanes_iter().filter(|(_, k)| k.needs_arrows()).count())
+        .sum::<usize>();
+
+    (pollution.min(1.0), (lanes as f32 / NOISY_LANES).min(1.0))
+}
+
+/// Health the environment of the home allows at the given age, in [0; 1] range
+pub fn healthy_level(age: u8, pollution: f32, noise: f32) -> f32 {
+    let aging = (age as f32 - 40.0).max(0.0) / 100.0;
+    (1.0 - aging - 0.5 * pollution - 0.2 * noise).clamp(0.1, 1.0)
+}
+
+/// Patients a hospital can take care of, understaffed hospitals have fewer beds
+pub fn hospital_beds(comp: &GoodsCompany, workers: usize) -> u32 {
+    let staffing = comp.productivity(workers as f32, None).min(1.0);
+    (staffing * comp.max_workers as f32 * BEDS_PER_WORKER) as u32
+}
+
+/// City-wide health statistics, updated every hour
+#[derive(Default, Serialize, Deserialize)]
+pub struct HealthStats {
+    /// Average health of the population
+    pub average: f32,
+    pub sick: u32,
+    /// Sick people with a hospital bed
+    pub hospitalized: u32,
+    /// Hospital beds in the city
+    pub beds: u32,
+    /// Deaths since the start of the game
+    pub deaths: u32,
+    /// The most recent deaths, oldest first, with the home of the deceased
+    pub recent_deaths: Vec<(BuildingID, GameInstant)>,
+    #[serde(skip)]
+    exposures: HomeExposures,
+}
+
+/// Pollution and noise of the homes, kept between updates and only recomputed close to the
+/// roads and buildings that changed and to the factories that opened or closed
+#[derive(Default)]
+struct HomeExposures {
+    /// Version of the dirty regions of the map the exposures are up to date with
+    map_version: Option<u64>,
+    factories: Vec<Vec2>,
+    values: BTreeMap<BuildingID, (f32, f32)>,
+}
+
+impl HomeExposures {
+    fn update(&mut self, map: &Map, factories: Vec<Vec2>) {
+        let (dirty, version) = map.dirty_since(self.map_version);
+        self.map_version = Some(version);
+        let Some(dirty) = dirty else {
+            self.values.clear();
+            self.factories = factories;
+            return;
+        };
+        let changed: Vec<Vec2> = factories
+            .iter()
+            .filter(|f| !self.factories.contains(f))
+            .chain(self.factories.iter().filter(|f| !factories.contains(f)))
+            .copied()
+            .collect();
+        self.factories = factories;
+        self.values.retain(|&id, _| {
+            let Some(b) = map.buildings().get(id) else {
+                return false;
+            };
+            let pos = b.door_pos.xy();
+            !is_dirty(&dirty, pos, NOISE_RADIUS)
+                && !changed.iter().any(|f| f.distance(pos) < POLLUTION_RADIUS)
+        });
+    }
+
+    fn get(&mut self, map: &Map, house: BuildingID) -> (f32, f32) {
+        *self.values.entry(house).or_insert_with(|| {
+            map.buildings().get(house).map_or((0.0, 0.0), |b| {
+                exposure(map, &self.factories, b.door_pos.xy())
+            })
+        })
+    }
+}
+
+/// Factories and hospitals of the city
+struct Facilities {
+    factories: Vec<Vec2>,
+    /// Position and number of beds of the hospitals
+    hospitals: BTreeMap<BuildingID, (Vec2, u32)>,
+}
+
+impl Facilities {
+    fn new(world: &World, map: &Map, registry: &GoodsCompanyRegistry) -> Self {
+        let mut factories = vec![];
+        let mut hospitals = BTreeMap::new();
+        for c in world.companies.values() {
+            let Some(b) = map.buildings().get(c.comp.building) else {
+                continue;
+            };
+            if matches!(c.comp.kind, CompanyKind::Factory { .. }) {
+                factories.push(b.obb.center());
+            }
+            let is_hospital = b
+                .kind
+                .as_goods_company()
+                .and_then(|id| registry.descriptions.get(id))
+                .map_or(false, |d| d.service == Some(ServiceKind::Health));
+            if is_hospital {
+                let beds = hospital_beds(&c.comp, c.workers.0.len());
+                hospitals.insert(b.id, (b.door_pos.xy(), beds));
+            }
+        }
+        Self {
+            factories,
+            hospitals,
+        }
+    }
+
+    /// Closest hospital with a free bed
+    fn free_hospital(&self, patients: &BTreeMap<BuildingID, u32>, pos: Vec2) -> Option<BuildingID> {
+        self.hospitals
+            .iter()
+            .filter(|(id, (_, beds))| patients.get(id).copied().unwrap_or(0) < *beds)
+            .min_by(|(_, (a, _)), (_, (b, _))| a.distance2(pos).total_cmp(&b.distance2(pos)))
+            .map(|(&id, _)| id)
+    }
+}
+
+pub fn health_system(world: &mut World, res: &mut Resources) {
+    profiling::scope!("souls::health_system");
+    let time = *res.read::<GameTime>();
+    if !time.tick(SECONDS_PER_HOUR as u32) {
+        return;
+    }
+    let map = res.read::<Map>();
+    let mut facilities = Facilities::new(world, &map, &res.read::<GoodsCompanyRegistry>());
+    let mut rng = res.write::<RandProvider>();
+
+    let mut patients: BTreeMap<BuildingID, u32> = BTreeMap::new();
+    for h in world.humans.values_mut() {
+        if let Some(hospital) = h.health.hospital {
+            if !h.health.sick || !facilities.hospitals.contains_key(&hospital) {
+                h.health.hospital = None;
+                continue;
+            }
+            *patients.entry(hospital).or_default() += 1;
+        }
+    }
+
+    let mut stats = res.write::<HealthStats>();
+    stats
+        .exposures
+        .update(&map, std::mem::take(&mut facilities.factories));
+    let mut dead = vec![];
+    let mut total = 0.0;
+
+    for (id, h) in world.humans.iter_mut() {
+        let (pollution, noise) = stats.exposures.get(&map, h.home.house);
+        let health = &mut h.health;
+        total += health.value;
+
+        if !health.sick {
+            let target = healthy_level(h.personal_info.age, pollution, noise);
+            health.value += (target - health.value) / (DAYS_TO_SETTLE * 24.0);
+            if rng.next_f32() < HOURLY_SICKNESS_CHANCE * (1.0 - health.value).powi(2) {
+                health.sick = true;
+            }
+            continue;
+        }
+
+        if health.hospital.is_none() {
+            health.hospital = facilities.free_hospital(&patients, h.trans.position.xy());
+            if let Some(hospital) = health.hospital {
+                *patients.entry(hospital).or_default() += 1;
+            }
+        }
+
+        let treated = health
+            .hospital
+            .map_or(false, |b| h.location == Location::Building(b));
+        if treated {
+            health.value = (health.value + HOSPITAL_RECOVERY).min(1.0);
+        } else {
+            health.value -= SICK_DECLINE;
+        }
+
+        if health.value >= CURED_HEALTH || (!treated && rng.next_f32() < HOURLY_SELF_RECOVERY) {
+            health.sick = false;
+            if let Some(hospital) = health.hospital.take() {
+                if let Some(n) = patients.get_mut(&hospital) {
+                    *n -= 1;
+                }
+            }
+            continue;
+        }
+
+        // people driving or riding finish their trip first
+        if health.value <= 0.0 && !matches!(h.location, Location::Vehicle(_) | Location::Train(_)) {
+            dead.push(id);
+        }
+    }
+    drop(rng);
+
+    stats.average = if world.humans.is_empty() {
+        0.0
+    } else {
+        total / world.humans.len() as f32
+    };
+    stats.sick = world.humans.values().filter(|h| h.health.sick).count() as u32;
+    stats.hospitalized = patients.values().sum();
+    stats.beds = facilities.hospitals.values().map(|(_, beds)| beds).sum();
+
+    drop(map);
+    for id in dead {
+        let Some(house) = world.humans.get(id).map(|h| h.home.house) else {
+            continue;
+        };
+        pass_away(world, res, id);
+        stats.deaths += 1;
+        stats.recent_deaths.push((house, time.instant()));
+    }
+    let extra = stats.recent_deaths.len().saturating_sub(MAX_DEATHS);
+    stats.recent_deaths.drain(..extra);
+}
+
+/// Removes the deceased from their home and their workplace, which can hire someone else
+fn pass_away(world: &mut World, res: &Resources, id: HumanID) {
+    let Some(h) = world.humans.get(id) else {
+        return;
+    };
+    log::info!("{:?} died", id);
+    let soul = SoulID::Human(id);
+
+    let mut binfos = res.write::<BuildingInfos>();
+    binfos.remove_resident(h.home.house, soul);
+    if let Location::Building(b) = h.location {
+        binfos.get_out(b, soul);
+    }
+
+    let company = h
+        .work
+        .as_ref()
+        .and_then(|w| binfos.owner(w.workplace))
+        .and_then(|owner| match owner {
+            SoulID::GoodsCompany(c) => Some(c),
+            _ => None,
+        });
+    drop(binfos);
+
+    if let Some(car) = h.router.personal_car {
+        res.read::<ParCommandBuffer<VehicleEnt>>().kill(car);
+    }
+    res.read::<ParCommandBuffer<HumanEnt>>().kill(id);
+
+    let Some(cid) = company else {
+        return;
+    };
+    if let Some(c) = world.companies.get_mut(cid) {
+        c.workers.0.retain(|&w| w != id);
+    }
+    let job_opening = res.read::<ItemRegistry>().id("job-opening");
+    res.write::<Market>()
+        .produce(SoulID::GoodsCompany(cid), job_opening, 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use geom::{vec2, vec3};
+
+    #[test]
+    fn exposure_and_health() {
+        let test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+
+        let map = test.g.map();
+        let (pollution, noise) = exposure(&map, &[], vec2(150.0, 20.0));
+        assert_eq!(pollution, 0.0);
+        assert!(noise > 0.0);
+
+        let (pollution, _) = exposure(&map, &[vec2(150.0, 100.0)], vec2(150.0, 20.0));
+        assert!(pollution > 0.0);
+        let (far, quiet) = exposure(&map, &[vec2(150.0, 100.0)], vec2(150.0, 1000.0));
+        assert_eq!((far, quiet), (0.0, 0.0));
+
+        assert_eq!(healthy_level(30, 0.0, 0.0), 1.0);
+        assert!(healthy_level(30, pollution, noise) < 1.0);
+        assert!(healthy_level(80, 0.0, 0.0) < healthy_level(30, 0.0, 0.0));
+    }
+}
@@ -1,17 +1,22 @@
-use crate::economy::{Bought, ItemRegistry, Market};
+use crate::economy::{Bought, Earnings, ItemRegistry, Market};
 use crate::map::BuildingID;
-use crate::map_dynamic::{BuildingInfos, Destination, Itinerary, Router};
+use crate::map_dynamic::{plan_tour, BuildingInfos, Destination, Itinerary, LeisureVenues, Router};
 use crate::physics::Speed;
-use crate::souls::desire::{BuyFood, Home, Work};
+use crate::souls::activity::{Activity, ActivityState};
+use crate::souls::desire::{AttendEvent, BuyFood, CarTank, GoOut, Home, Refuel, Work};
+use crate::souls::education::Education;
+use crate::souls::goods_company::GoodsCompanyRegistry;
+use crate::souls::health::Health;
 use crate::transportation::{
     random_pedestrian_shirt_color, spawn_parked_vehicle, Location, Pedestrian, VehicleKind,
 };
+use crate::utils::pool::EntityPool;
 use crate::utils::rand_provider::RandProvider;
 use crate::utils::resources::Resources;
-use crate::utils::time::GameTime;
+use crate::utils::time::{GameInstant, GameTime};
 use crate::world::{FreightStationEnt, HumanEnt, HumanID, VehicleID};
 use crate::World;
-use crate::{BuildingKind, Map, ParCommandBuffer, Simulation, SoulID};
+use crate::{BuildingKind, Map, ParCommandBuffer, Simulation, SimulationOptions, SoulID};
 use egui_inspect::Inspect;
 use geom::Transform;
 use lazy_static::lazy_static;
@@ -21,6 +26,7 @@ use serde::{Deserialize, Serialize};
 pub struct HumanDecision {
     pub kind: HumanDecisionKind,
     pub wait: u8,
+    pub activity: ActivityState,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,6 +34,8 @@ pub enum HumanDecisionKind {
     Yield,
     SetVehicle(Option<VehicleID>),
     GoTo(Destination),
+    /// Follows the tour planned in the router, stopping at each errand on the way
+    Tour,
     DeliverAtBuilding(BuildingID),
     MultiStack(Vec<HumanDecisionKind>),
 }
@@ -53,7 +61,7 @@ static FIRST_NAMES_BYTES: &str = include_str!("first_names.txt");
 static LAST_NAMES_BYTES: &str = include_str!("names.txt");
 
 lazy_static! {
-    static ref LAST_NAMES: Vec<&'static str> = LAST_NAMES_BYTES.split('\n').collect();
+    pub(crate) static ref LAST_NAMES: Vec<&'static str> = LAST_NAMES_BYTES.split('\n').collect();
     static ref FIRST_NAMES: Vec<&'static str> = FIRST_NAMES_BYTES.split('\n').collect();
 }
 
@@ -91,6 +99,7 @@ impl HumanDecisionKind {
     ) -> bool {
         match *self {
             HumanDecisionKind::GoTo(dest) => router.go_to(dest),
+            HumanDecisionKind::Tour => router.follow_tour(),
             HumanDecisionKind::MultiStack(ref mut decisions) => {
                 if let Some(d) = decisions.last_mut() {
                     if d.update(router, binfos, map, cbuf_freight) {
@@ -109,7 +118,10 @@ impl HumanDecisionKind {
                 let Some(b) = map.buildings().get(bid) else {
                     return true;
                 };
-                if matches!(b.kind, BuildingKind::RailFreightStation) {
+                if matches!(
+                    b.kind,
+                    BuildingKind::RailFreightStation | BuildingKind::Airport | BuildingKind::Harbor
+                ) {
                     let Some(SoulID::FreightStation(fid)) = binfos.owner(bid) else {
                         return true;
                     };
@@ -132,6 +144,23 @@ enum NextDesire<'a> {
     Home(&'a mut Home),
     Work(&'a mut Work),
     Food(&'a mut BuyFood),
+    GoOut(&'a mut GoOut),
+    Refuel(&'a mut Refuel, CarTank),
+    Event(&'a mut AttendEvent),
+    Heal(&'a mut Health),
+}
+
+impl NextDesire<'_> {
+    /// The activity the desire leads to
+    fn activity(&self) -> Option<Activity> {
+        Some(match self {
+            NextDesire::None => return None,
+            NextDesire::Home(_) | NextDesire::Heal(_) => Activity::Sleeping,
+            NextDesire::Work(_) => Activity::Working,
+            NextDesire::Food(_) | NextDesire::Refuel(..) => Activity::Shopping,
+            NextDesire::GoOut(_) | NextDesire::Event(_) => Activity::Leisure,
+        })
+    }
 }
 
 pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
@@ -141,14 +170,29 @@ pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
     let rc = &*resources.read();
     let rd = &*resources.read();
     let re = &*resources.read();
+    let rf = &*resources.read();
+    let rg = &*resources.read();
+    let rh = &*resources.read();
 
+    let vehicles = &world.vehicles;
     world.humans.iter_mut().for_each(|(ent, h)| {
+        let tank = h.router.personal_car.and_then(|car| {
+            let v = &vehicles.get(car)?.vehicle;
+            Some(CarTank {
+                car,
+                fuel: v.fuel,
+                energy: v.energy,
+            })
+        });
         update_decision(
             ra,
             rb,
             rc,
             rd,
             re,
+            rf,
+            rg,
+            rh,
             ent,
             &h.trans,
             &h.location,
@@ -156,6 +200,11 @@ pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
             &mut h.bought,
             &mut h.decision,
             Some(&mut h.food),
+            Some(&mut h.go_out),
+            Some(&mut h.refuel),
+            tank,
+            Some(&mut h.event),
+            Some(&mut h.health),
             Some(&mut h.home),
             h.work.as_mut(),
         )
@@ -169,6 +218,9 @@ pub fn update_decision(
     time: &GameTime,
     binfos: &BuildingInfos,
     map: &Map,
+    companies: &GoodsCompanyRegistry,
+    items: &ItemRegistry,
+    venues: &LeisureVenues,
     me: HumanID,
     trans: &Transform,
     loc: &Location,
@@ -176,6 +228,11 @@ pub fn update_decision(
     bought: &mut Bought,
     decision: &mut HumanDecision,
     food: Option<&mut BuyFood>,
+    go_out: Option<&mut GoOut>,
+    refuel: Option<&mut Refuel>,
+    tank: Option<CarTank>,
+    event: Option<&mut AttendEvent>,
+    health: Option<&mut Health>,
     home: Option<&mut Home>,
     work: Option<&mut Work>,
 ) {
@@ -185,12 +242,25 @@ pub fn update_decision(
     }
     let pos = trans.position;
     decision.wait = (30.0 + common::rand::rand2(pos.x, pos.y) * 50.0) as u8;
-    if !decision.kind.update(router, binfos, map, cbuf_freight) {
+    let arrived = decision.kind.update(router, binfos, map, cbuf_freight);
+    decision.activity.update(time, arrived, loc, router);
+    if !arrived {
         return;
     }
 
     let mut decision_id = NextDesire::None;
     let mut max_score = f32::NEG_INFINITY;
+    let house = home.as_ref().map(|h| h.house);
+    let shift = work.as_ref().map(|w| w.work_inter);
+
+    // errands that can be run on the way to wherever the human goes next
+    let errands: Vec<BuildingID> = food
+        .as_deref()
+        .and_then(|f| f.errand(time, map, companies))
+        .into_iter()
+        .chain(refuel.as_deref().and_then(Refuel::errand))
+        .filter(|&b| loc != &Location::Building(b))
+        .collect();
 
     if let Some(home) = home {
         let score = home.score();
@@ -213,28 +283,108 @@ pub fn update_decision(
     }
 
     if let Some(food) = food {
-        let score = food.score(time, loc, bought);
+        let score = food.score(time, loc, bought, map, companies, items);
         food.last_score = score;
 
-        #[allow(unused_assignments)]
         if score > max_score {
             max_score = score;
             decision_id = NextDesire::Food(food);
         }
     }
 
+    if let Some(go_out) = go_out {
+        let score = go_out.score(time, venues, me);
+        go_out.last_score = score;
+
+        if score > max_score {
+            max_score = score;
+            decision_id = NextDesire::GoOut(go_out);
+        }
+    }
+
+    if let (Some(refuel), Some(tank)) = (refuel, tank) {
+        let score = refuel.score(tank, loc, bought, items);
+        refuel.last_score = score;
+
+        if score > max_score {
+            max_score = score;
+            decision_id = NextDesire::Refuel(refuel, tank);
+        }
+    }
+
+    if let Some(event) = event {
+        let score = event.score(time);
+        event.last_score = score;
+
+        if score > max_score {
+            max_score = score;
+            decision_id = NextDesire::Event(event);
+        }
+    }
+
+    if let Some(health) = health {
+        let score = health.score();
+        health.last_score = score;
+
+        #[allow(unused_assignments)]
+        if score > max_score {
+            max_score = score;
+            decision_id = NextDesire::Heal(health);
+        }
+    }
+
+    if let Some(goal) = decision_id.activity() {
+        // the work schedule is the only transition known in advance
+        let next_transition = shift
+            .map(|shift| match goal {
+                Activity::Working => shift.dist_until_end(time.daytime),
+                _ => shift.dist_until(time.daytime),
+            })
+            .filter(|&secs| secs > 0 && matches!(goal, Activity::Working | Activity::Sleeping))
+            .map(|secs| GameInstant {
+                timestamp: time.timestamp + secs as f64,
+            });
+        decision.activity.transition(goal, next_transition, time);
+    }
+
     match decision_id {
         NextDesire::Home(home) => decision.kind = home.apply(),
         NextDesire::Work(work) => decision.kind = work.apply(loc, router),
         NextDesire::Food(food) => {
             decision.kind = food.apply(cbuf, binfos, map, time, me, trans, loc, bought)
         }
+        NextDesire::GoOut(go_out) => {
+            decision.kind = go_out.apply(cbuf, venues, time, me, trans, loc)
+        }
+        NextDesire::Refuel(refuel, tank) => {
+            decision.kind = refuel.apply(
+                cbuf, binfos, map, items, me, trans, loc, bought, house, tank,
+            )
+        }
+        NextDesire::Event(event) => decision.kind = event.apply(),
+        NextDesire::Heal(health) => {
+            if let Some(house) = house {
+                decision.kind = health.apply(house);
+            }
+        }
         NextDesire::None => {}
     }
+
+    if let HumanDecisionKind::GoTo(dest) = decision.kind {
+        if router.continues_tour(dest) {
+            decision.kind = HumanDecisionKind::Tour;
+        } else if !errands.is_empty() {
+            router.plan_tour(plan_tour(map, trans.position, &errands, dest));
+            decision.kind = HumanDecisionKind::Tour;
+        }
+    }
 }
 
 pub fn spawn_human(sim: &mut Simulation, house: BuildingID) -> Option<HumanID> {
     profiling::scope!("spawn_human");
+    if sim.read::<BuildingInfos>().get(house)?.vacant_homes() == 0 {
+        return None;
+    }
     let map = sim.map();
     let housepos = map.buildings().get(house)?.door_pos;
     drop(map);
@@ -242,17 +392,31 @@ pub fn spawn_human(sim: &mut Simulation, house: BuildingID) -> Option<HumanID> {
     let _color = random_pedestrian_shirt_color(&mut sim.write::<RandProvider>());
 
     let hpos = sim.map().buildings().get(house)?.door_pos;
-    let p = Pedestrian::new(&mut sim.write::<RandProvider>());
+    let slow_walkers_fraction = sim.read::<SimulationOptions>().slow_walkers_fraction;
+    let p = Pedestrian::new(&mut sim.write::<RandProvider>(), slow_walkers_fraction);
 
     let registry = sim.read::<ItemRegistry>();
     let time = sim.read::<GameTime>().instant();
 
     let food = BuyFood::new(time, &registry);
+    let go_out = GoOut::new(time, sim.write::<RandProvider>().next_u32());
     drop(registry);
 
     let car = spawn_parked_vehicle(sim, VehicleKind::Car, housepos);
 
-    let personal_info = Box::new(PersonalInfo::new(&mut sim.write::<RandProvider>()));
+    let personal_info = PersonalInfo::new(&mut sim.write::<RandProvider>());
+    let education = Education::new(&mut sim.write::<RandProvider>());
+
+    // Reuse the allocations of a dead human if there is one
+    let (personal_info, router) = match sim.write::<EntityPool>().take_human() {
+        Some(h) => {
+            let (mut info, mut router) = (h.personal_info, h.router);
+            *info = personal_info;
+            router.reset(car);
+            (info, router)
+        }
+        None => (Box::new(personal_info), Router::new(car)),
+    };
 
     let id = sim.world.insert(HumanEnt {
         trans: Transform::new(hpos),
@@ -263,10 +427,16 @@ pub fn spawn_human(sim: &mut Simulation, house: BuildingID) -> Option<HumanID> {
         decision: HumanDecision::default(),
         home: Home::new(house),
         food,
+        go_out,
+        refuel: Refuel::default(),
+        event: AttendEvent::default(),
         bought: Bought::default(),
-        router: Router::new(car),
+        router,
         collider: None,
         work: None,
+        earnings: Earnings::default(),
+        education,
+        health: Health::default(),
         personal_info,
     });
 
@@ -276,7 +446,7 @@ pub fn spawn_human(sim: &mut Simulation, house: BuildingID) -> Option<HumanID> {
     m.buy(soul, housepos.xy(), registry.id("job-opening"), 1);
 
     sim.write::<BuildingInfos>().get_in(house, soul);
-    sim.write::<BuildingInfos>().set_owner(house, soul);
+    sim.write::<BuildingInfos>().add_resident(house, soul);
 
     Some(id)
 }
@@ -1,37 +1,51 @@
+use crate::economy::Money;
 use crate::map::{BuildingID, BuildingKind};
-use crate::map_dynamic::BuildingInfos;
+use crate::map_dynamic::{BuildingInfos, Capacity};
+use crate::souls::branding::Brand;
 use crate::souls::freight_station::freight_station_soul;
-use crate::souls::goods_company::{company_soul, GoodsCompany, GoodsCompanyRegistry};
+use crate::souls::goods_company::{
+    company_soul, spawn_fleet_truck, FleetTruck, FreightMode, GoodsCompany, GoodsCompanyRegistry,
+};
 use crate::souls::human::spawn_human;
-use crate::transportation::{spawn_parked_vehicle, VehicleKind};
 use crate::Simulation;
 use common::descriptions::CompanyKind;
 use geom::Vec3;
+use slotmapd::Key;
 use std::collections::BTreeMap;
 
 #[macro_use]
 pub mod desire;
 
+pub mod activity;
+pub mod branding;
+pub mod education;
 pub mod freight_station;
 pub mod goods_company;
+pub mod health;
 pub mod human;
 
 /// Adds souls to empty buildings
 pub(crate) fn add_souls_to_empty_buildings(sim: &mut Simulation) {
     profiling::scope!("souls::add_souls_to_empty_buildings");
+    update_occupancy(sim);
+
     let map = sim.map();
     let infos = sim.read::<BuildingInfos>();
     let mut empty_buildings: BTreeMap<BuildingKind, Vec<(BuildingID, Vec3)>> = BTreeMap::default();
 
     for (id, building) in map.buildings() {
-        if unwrap_cont!(infos.get(id)).owner.is_some() {
-            continue;
-        }
+        let info = unwrap_cont!(infos.get(id));
+        // houses welcome residents until they are full
+        let vacancies = match building.kind {
+            BuildingKind::House => info.vacant_homes(),
+            _ if info.owner.is_some() => 0,
+            _ => 1,
+        };
 
-        empty_buildings
-            .entry(building.kind)
-            .or_default()
-            .push((id, building.door_pos));
+        let v = empty_buildings.entry(building.kind).or_default();
+        for _ in 0..vacancies {
+            v.push((id, building.door_pos));
+        }
     }
     drop(infos);
     drop(map);
@@ -48,10 +62,14 @@ pub(crate) fn add_souls_to_empty_buildings(sim: &mut Simulation) {
         n_souls_added += 1;
     }
 
-    for &(build_id, _) in empty_buildings
-        .get(&BuildingKind::RailFreightStation)
-        .unwrap_or(&vec![])
-        .iter()
+    for &(build_id, _) in [
+        BuildingKind::RailFreightStation,
+        BuildingKind::Airport,
+        BuildingKind::Harbor,
+    ]
+    .iter()
+    .filter_map(|kind| empty_buildings.get(kind))
+    .flatten()
     {
         freight_station_soul(sim, build_id);
         n_souls_added += 1;
@@ -66,17 +84,25 @@ pub(crate) fn add_souls_to_empty_buildings(sim: &mut Simulation) {
         let des = &unwrap_or!(registry.descriptions.get(bkind), continue);
 
         let ckind = des.kind;
-        let mk_trucks = |sim: &mut Simulation| {
-            let mut trucks = vec![];
+        let brand = Brand::new(build_id.data().as_ffi(), &des.name);
+        let tint = brand.color;
+        let mk_fleet = |sim: &mut Simulation| {
+            let mut fleet = vec![];
             if let CompanyKind::Factory { n_trucks } = ckind {
                 for _ in 0..n_trucks {
-                    trucks.extend(spawn_parked_vehicle(sim, VehicleKind::Truck, pos))
+                    let Some(truck) = spawn_fleet_truck(sim, pos, tint) else {
+                        continue;
+                    };
+                    fleet.push(FleetTruck {
+                        truck,
+                        driver: None,
+                    });
                 }
-                if trucks.is_empty() {
+                if fleet.is_empty() {
                     return None;
                 }
             }
-            Some(trucks)
+            Some(fleet)
         };
 
         let comp = GoodsCompany {
@@ -84,12 +110,20 @@ pub(crate) fn add_souls_to_empty_buildings(sim: &mut Simulation) {
             building: build_id,
             recipe: des.recipe.clone(),
             max_workers: des.n_workers,
+            qualification: des.qualification,
             progress: 0.0,
-            driver: None,
-            trucks: {
+            fleet: {
                 drop(registry);
-                unwrap_or!(mk_trucks(sim), continue)
+                unwrap_or!(mk_fleet(sim), continue)
+            },
+            fleet_size: match ckind {
+                CompanyKind::Factory { n_trucks } => n_trucks,
+                _ => 0,
             },
+            freight_mode: FreightMode::Truck,
+            fleet_utilization: 0.0,
+            brand,
+            money: Money::ZERO,
         };
 
         company_soul(sim, comp);
@@ -101,3 +135,21 @@ pub(crate) fn add_souls_to_empty_buildings(sim: &mut Simulation) {
         log::info!("{} souls added", n_souls_added);
     }
 }
+
+/// Keeps the capacity of the buildings in sync with their archetype and company description,
+/// and counts the workers of each workplace
+fn update_occupancy(sim: &Simulation) {
+    let map = sim.map();
+    let registry = sim.read::<GoodsCompanyRegistry>();
+    let mut infos = sim.write::<BuildingInfos>();
+
+    let mut workers: BTreeMap<BuildingID, u32> = BTreeMap::new();
+    for c in sim.world.companies.values() {
+        *workers.entry(c.comp.building).or_default() += c.workers.0.len() as u32;
+    }
+
+    for (id, building) in map.buildings() {
+        let capacity = Capacity::of(building, &registry);
+        infos.set_occupancy(id, capacity, workers.get(&id).copied().unwrap_or(0));
+    }
+}
@@ -1,12 +1,14 @@
 #![allow(dead_code)]
 #![cfg(test)]
 
+use crate::economy::Milestones;
+use crate::map::procgen::EdgeConnections;
 use crate::map::{BuildingID, LanePatternBuilder, ProjectFilter};
 use crate::map_dynamic::BuildingInfos;
 use crate::utils::scheduler::SeqSchedule;
 use crate::utils::time::Tick;
 use crate::world_command::{WorldCommand, WorldCommands};
-use crate::{Simulation, SimulationOptions};
+use crate::{GoodsCompanyRegistry, Simulation, SimulationOptions};
 use common::logger::MyLog;
 use common::saveload::Encoder;
 use geom::{Vec2, Vec3};
@@ -27,7 +29,11 @@ impl TestCtx {
         let g = Simulation::new_with_options(SimulationOptions {
             terrain_size: 1,
             save_replay: false,
+            edge_connections: EdgeConnections::None,
+            ..Default::default()
         });
+        // tests build whatever they need regardless of the city progression
+        g.write::<Milestones>().enabled = false;
         let sched = Simulation::schedule();
 
         Self { g, sched }
@@ -52,8 +58,11 @@ impl TestCtx {
             .unwrap()
             .id;
 
-        let b = self.g.map_mut().build_house(lot).unwrap();
-        self.g.write::<BuildingInfos>().insert(b);
+        let b = self.g.map_mut().build_house(lot, None).unwrap();
+        self.g.write::<BuildingInfos>().insert(
+            &self.g.map().buildings()[b],
+            &self.g.read::<GoodsCompanyRegistry>(),
+        );
         b
     }
 
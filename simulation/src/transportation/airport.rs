@@ -0,0 +1,216 @@
+use crate::economy::{Government, Money};
+use crate::map::{BuildingID, BuildingKind, Map};
+use crate::utils::resources::Resources;
+use crate::utils::time::{GameTime, SECONDS_PER_HOUR};
+use crate::World;
+use geom::{Vec3, OBB};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Size of the airport footprint, the runway goes along the length
+pub const AIRPORT_LENGTH: f32 = 600.0;
+pub const AIRPORT_WIDTH: f32 = 120.0;
+/// Maximum height difference allowed along the runway
+pub const MAX_RUNWAY_HEIGHT_DIFF: f32 = 4.0;
+
+/// Number of (game) seconds between two flights of the same airport
+const FLIGHT_INTERVAL: f64 = 2.0 * SECONDS_PER_HOUR as f64;
+const LANDING_DURATION: f64 = 90.0;
+const BOARDING_DURATION: f64 = 0.5 * SECONDS_PER_HOUR as f64;
+const TAKEOFF_DURATION: f64 = 90.0;
+const APPROACH_DIST: f32 = 1500.0;
+const CRUISE_ALTITUDE: f32 = 300.0;
+
+pub const PASSENGERS_PER_FLIGHT: u32 = 120;
+const TOURIST_SPENDING: Money = Money::new_bucks(15);
+pub const AIR_FREIGHT_CAPACITY: u32 = 50;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FlightKind {
+    Passenger,
+    Freight,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum PlaneState {
+    Landing { start: f64 },
+    Boarding { until: f64 },
+    TakingOff { start: f64 },
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Plane {
+    pub kind: FlightKind,
+    pub state: PlaneState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Airport {
+    pub plane: Option<Plane>,
+    pub next_flight: f64,
+    pub n_flights: u32,
+    pub tourists: u32,
+    pub exported_cargo: u32,
+}
+
+/// Airports keeps the flight schedule of every airport.
+/// Passenger flights bring tourists spending money in the city while freight flights
+/// take the goods delivered to the airport to the external market.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Airports {
+    pub airports: BTreeMap<BuildingID, Airport>,
+}
+
+/// Checks that the runway fits: the footprint must be long enough and flat
+pub fn check_airport_footprint(map: &Map, obb: &OBB) -> Result<(), &'static str> {
+    let [a, b] = obb.axis();
+    if a.mag().max(b.mag()) < AIRPORT_LENGTH * 0.99 {
+        return Err("Runway too short");
+    }
+
+    let mut min = f32::INFINITY;
+    let mut max = f32::NEG_INFINITY;
+    for p in obb
+        .corners
+        .iter()
+        .copied()
+        .chain(std::iter::once(obb.center()))
+    {
+        let Some(h) = map.environment.height(p) else {
+            return Err("Outside of the map");
+        };
+        min = min.min(h);
+        max = max.max(h);
+    }
+    if max - min > MAX_RUNWAY_HEIGHT_DIFF {
+        return Err("Terrain is not flat enough for a runway");
+    }
+    if min < 0.0 {
+        return Err("Runway must be on land");
+    }
+    Ok(())
+}
+
+/// Position and direction of the plane, the runway is the long axis of the obb
+pub fn plane_transform(obb: &OBB, height: f32, plane: &Plane, time: f64) -> (Vec3, Vec3) {
+    let [a, b] = obb.axis();
+    let runway = if a.mag2() > b.mag2() { a } else { b };
+    let dir = runway.normalize();
+    let center = obb.center();
+    let start = center - runway * 0.5;
+
+    let lerp = |start: f64, duration: f64| ((time - start) / duration).clamp(0.0, 1.0) as f32;
+
+    match plane.state {
+        PlaneState::Landing { start: t } => {
+            let p = lerp(t, LANDING_DURATION);
+            // descend on the approach for the first 2/3, then roll on the runway
+            let approach = (1.0 - p * 1.5).max(0.0);
+            let pos =
+                start + dir * (runway.mag() * 0.5 * (1.0 - approach) - APPROACH_DIST * approach);
+            (
+                pos.z(height + CRUISE_ALTITUDE * approach * approach),
+                dir.z(0.0),
+            )
+        }
+        PlaneState::Boarding { .. } => (center.z(height), dir.z(0.0)),
+        PlaneState::TakingOff { start: t } => {
+            let p = lerp(t, TAKEOFF_DURATION);
+            let climb = (p * 1.5 - 0.5).max(0.0);
+            let pos = center
+                + dir * (runway.mag() * 0.5 * p.min(1.0 / 1.5) * 1.5 + APPROACH_DIST * climb);
+            (pos.z(height + CRUISE_ALTITUDE * climb * climb), dir.z(0.0))
+        }
+    }
+}
+
+pub fn airport_system(world: &mut World, res: &mut Resources) {
+    profiling::scope!("transportation::airport_system");
+    let time = res.read::<GameTime>().timestamp;
+    let map = res.read::<Map>();
+    let mut airports = res.write::<Airports>();
+
+    let built = map.bkinds.get(&BuildingKind::Airport);
+    airports
+        .airports
+        .retain(|id, _| built.map_or(false, |v| v.contains(id)));
+    for &id in built.into_iter().flatten() {
+        airports.airports.entry(id).or_insert_with(|| Airport {
+            plane: None,
+            next_flight: time + LANDING_DURATION,
+            n_flights: 0,
+            tourists: 0,
+            exported_cargo: 0,
+        });
+    }
+    drop(map);
+
+    for (&id, airport) in airports.airports.iter_mut() {
+        let Some(mut plane) = airport.plane else {
+            if time >= airport.next_flight {
+                let kind = if airport.n_flights % 2 == 0 {
+                    FlightKind::Passenger
+                } else {
+                    FlightKind::Freight
+                };
+                airport.n_flights += 1;
+                airport.plane = Some(Plane {
+                    kind,
+                    state: PlaneState::Landing { start: time },
+                });
+            }
+            continue;
+        };
+
+        match plane.state {
+            PlaneState::Landing { start } => {
+                if time < start + LANDING_DURATION {
+                    continue;
+                }
+                if plane.kind == FlightKind::Passenger {
+                    airport.tourists += PASSENGERS_PER_FLIGHT;
+                    res.write::<Government>().money +=
+                        TOURIST_SPENDING * PASSENGERS_PER_FLIGHT as i64;
+                }
+                plane.state = PlaneState::Boarding {
+                    until: time + BOARDING_DURATION,
+                };
+            }
+            PlaneState::Boarding { until } => {
+                if time < until {
+                    continue;
+                }
+                if plane.kind == FlightKind::Freight {
+                    airport.exported_cargo += load_freight(world, id);
+                }
+                plane.state = PlaneState::TakingOff { start: time };
+            }
+            PlaneState::TakingOff { start } => {
+                if time < start + TAKEOFF_DURATION {
+                    continue;
+                }
+                airport.plane = None;
+                airport.next_flight = time + FLIGHT_INTERVAL;
+                continue;
+            }
+        }
+        airport.plane = Some(plane);
+    }
+}
+
+/// Takes the cargo delivered to the airport, returns how much was loaded
+fn load_freight(world: &mut World, airport: BuildingID) -> u32 {
+    let Some(station) = world
+        .freight_stations
+        .values_mut()
+        .find(|f| f.f.building == airport)
+    else {
+        return 0;
+    };
+    let loaded = station.f.waiting_cargo.min(AIR_FREIGHT_CAPACITY);
+    let unloaded = station.f.wanted_cargo.min(loaded);
+    station.f.waiting_cargo -= loaded;
+    station.f.wanted_cargo -= unloaded;
+    station.f.stats.cargo_moved(unloaded, loaded);
+    loaded
+}
@@ -0,0 +1,751 @@
+use crate::map::{BusStop, BusStopID, Map, PathKind, Pathfinder};
+use crate::map_dynamic::{walk_inside, walk_outside, Itinerary};
+use crate::transportation::{make_vehicle_entity, Location, Vehicle, VehicleKind, VehicleState};
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::time::{DayTime, GameTime, RecTimeInterval, Tick, SECONDS_PER_HOUR};
+use crate::world::{BusEnt, HumanEnt, VehicleEnt};
+use crate::{ParCommandBuffer, Simulation};
+use geom::{Color, Transform, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Number of passengers a bus can carry
+pub const BUS_CAPACITY: usize = 40;
+/// Number of passengers a tram can carry
+pub const TRAM_CAPACITY: usize = 120;
+/// Most buses a line can run
+pub const MAX_BUSES_PER_LINE: u32 = 20;
+/// Most stops a line can serve
+pub const MAX_BUS_LINE_STOPS: usize = 50;
+/// Longest name of a line
+pub const MAX_BUS_LINE_NAME: usize = 30;
+/// Price of a bus, paid when a line gets more of them
+pub const BUS_PRICE: i64 = 800;
+/// Price of a tram, paid when a line gets more of them
+pub const TRAM_PRICE: i64 = 2500;
+
+/// People give up waiting for the bus after this long and walk instead, in game seconds
+pub const MAX_BUS_WAIT: f64 = 0.5 * SECONDS_PER_HOUR as f64;
+
+/// Hours between which the lines run their night service, with half of their vehicles
+const NIGHT_SERVICE: (i32, i32) = (23, 5);
+
+/// How long the buses wait at each stop, in game seconds
+const DWELL_DURATION: f64 = 30.0;
+/// How close to the stop the passengers must wait to board
+const BOARDING_DIST: f32 = 10.0;
+/// How close to its stop the bus must be to let the passengers in and out
+const ARRIVAL_DIST: f32 = 15.0;
+/// Average speed of the buses, stops included, used to plan the trips in m/s
+const PLANNING_BUS_SPEED: f32 = 7.0;
+/// Walking speed used to plan the trips in m/s
+pub(crate) const PLANNING_WALK_SPEED: f32 = 1.2;
+/// Trips shorter than this are always walked, in meters
+const MIN_BUS_TRIP: f32 = 400.0;
+/// How far people are willing to walk to or from a stop, in meters
+const MAX_WALK_TO_STOP: f32 = 500.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct BusLineID(pub u32);
+
+debug_inspect_impl!(BusLineID);
+
+/// A bus line, its buses go around the stops in order and come back to the first one after the last
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BusLine {
+    pub name: String,
+    /// The buses of the line are painted with it
+    pub color: Color,
+    pub stops: Vec<BusStopID>,
+    pub n_buses: u32,
+    /// Served by trams running on the tram tracks of the streets instead of buses
+    pub tram: bool,
+}
+
+impl BusLine {
+    /// Why the line can't be created, if it can't
+    pub fn check(&self) -> Result<(), &'static str> {
+        if self.name.chars().count() > MAX_BUS_LINE_NAME {
+            return Err("Bus line name is too long");
+        }
+        if self.stops.len() > MAX_BUS_LINE_STOPS {
+            return Err("Too many stops on the bus line");
+        }
+        if self.n_buses > MAX_BUSES_PER_LINE {
+            return Err("Too many buses on the bus line");
+        }
+        Ok(())
+    }
+
+    /// Whether buses run on the line
+    pub fn is_running(&self) -> bool {
+        self.n_buses > 0 && self.stops.len() >= 2
+    }
+
+    /// Number of vehicles the line runs at the given time, fewer during the night service
+    pub fn n_running(&self, t: DayTime) -> u32 {
+        let night = RecTimeInterval::new((NIGHT_SERVICE.0, 0), (NIGHT_SERVICE.1, 0));
+        if night.dist_until(t) == 0 {
+            self.n_buses.div_ceil(2)
+        } else {
+            self.n_buses
+        }
+    }
+
+    pub fn vehicle_kind(&self) -> VehicleKind {
+        if self.tram {
+            VehicleKind::Tram
+        } else {
+            VehicleKind::Bus
+        }
+    }
+
+    /// Price of one of the vehicles of the line
+    pub fn vehicle_price(&self) -> i64 {
+        if self.tram {
+            TRAM_PRICE
+        } else {
+            BUS_PRICE
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        if self.tram {
+            TRAM_CAPACITY
+        } else {
+            BUS_CAPACITY
+        }
+    }
+}
+
+/// A trip planned by a pedestrian on a bus line
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BusTrip {
+    pub line: BusLineID,
+    /// Where the pedestrian gets on
+    pub from: BusStopID,
+    /// Where the pedestrian gets off
+    pub to: BusStopID,
+}
+
+debug_inspect_impl!(BusTrip);
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BusState {
+    /// Driving to its next stop
+    Driving,
+    /// Waiting at its stop for the passengers until the given timestamp
+    Dwelling(f64),
+}
+
+/// BusLines keeps the bus lines drawn by the player
+#[derive(Default, Serialize, Deserialize)]
+pub struct BusLines {
+    lines: BTreeMap<BusLineID, BusLine>,
+    next_id: u32,
+    /// Stops the vehicles of the line could not find a route to, they skip them
+    unreachable: BTreeSet<(BusLineID, BusStopID)>,
+}
+
+impl BusLines {
+    pub fn add(&mut self, line: BusLine) -> BusLineID {
+        let id = BusLineID(self.next_id);
+        self.next_id += 1;
+        self.lines.insert(id, line);
+        id
+    }
+
+    /// Replaces the line if it still exists
+    pub fn update(&mut self, id: BusLineID, line: BusLine) {
+        if let Some(l) = self.lines.get_mut(&id) {
+            *l = line;
+        }
+    }
+
+    pub fn remove(&mut self, id: BusLineID) {
+        self.lines.remove(&id);
+    }
+
+    pub fn get(&self, id: BusLineID) -> Option<&BusLine> {
+        self.lines.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BusLineID, &BusLine)> {
+        self.lines.iter().map(|(&id, l)| (id, l))
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Whether the vehicles of the line skip the stop because they can't get there
+    pub fn is_unreachable(&self, line: BusLineID, stop: BusStopID) -> bool {
+        self.unreachable.contains(&(line, stop))
+    }
+
+    /// Drops the stops that were removed from the map
+    fn prune(&mut self, map: &Map) {
+        for line in self.lines.values_mut() {
+            line.stops.retain(|&s| map.bus_stops().contains_key(s));
+            line.stops.dedup();
+        }
+        let lines = &self.lines;
+        self.unreachable
+            .retain(|&(l, s)| lines.contains_key(&l) && map.bus_stops().contains_key(s));
+    }
+
+    /// The fastest bus trip from `from` to `to` along with where to wait for the bus.
+    /// None if walking all the way is faster.
+    pub fn plan_trip(&self, map: &Map, from: Vec3, to: Vec3) -> Option<(BusTrip, Vec3)> {
+        let direct = from.distance(to);
+        if direct < MIN_BUS_TRIP {
+            return None;
+        }
+        let mut best_time = direct / PLANNING_WALK_SPEED;
+        let mut best = None;
+
+        for (&id, line) in &self.lines {
+            if !line.is_running() {
+                continue;
+            }
+            let stops: Vec<(BusStopID, Vec3)> = line
+                .stops
+                .iter()
+                .filter_map(|&s| Some((s, map.bus_stops().get(s)?.pos)))
+                .collect();
+            if stops.len() < 2 {
+                continue;
+            }
+
+            // distance along the line from the first stop
+            let mut along = Vec::with_capacity(stops.len());
+            let mut d = 0.0;
+            along.push(d);
+            for w in stops.windows(2) {
+                d += w[0].1.distance(w[1].1);
+                along.push(d);
+            }
+            let loop_length = d + stops[stops.len() - 1].1.distance(stops[0].1);
+            // the passengers wait half the interval between two buses on average
+            let wait = loop_length / (line.n_buses as f32 * PLANNING_BUS_SPEED) * 0.5;
+
+            for (i, &(board, board_pos)) in stops.iter().enumerate() {
+                let access = board_pos.distance(from);
+                if access > MAX_WALK_TO_STOP {
+                    continue;
+                }
+                for (j, &(alight, alight_pos)) in stops.iter().enumerate() {
+                    let egress = alight_pos.distance(to);
+                    if i == j || egress > MAX_WALK_TO_STOP {
+                        continue;
+                    }
+                    let ride = if j > i {
+                        along[j] - along[i]
+                    } else {
+                        loop_length - along[i] + along[j]
+                    };
+                    let time =
+                        (access + egress) / PLANNING_WALK_SPEED + wait + ride / PLANNING_BUS_SPEED;
+                    if time < best_time {
+                        best_time = time;
+                        best = Some((
+                            BusTrip {
+                                line: id,
+                                from: board,
+                                to: alight,
+                            },
+                            board_pos,
+                        ));
+                    }
+                }
+            }
+        }
+
+        best
+    }
+}
+
+/// Runs the buses of the lines: spawns and removes them to match the lines, drives them from stop
+/// to stop and lets the passengers in and out
+pub fn bus_system(sim: &mut Simulation) {
+    profiling::scope!("transportation::bus_system");
+    let time = *sim.read::<GameTime>();
+
+    sim.write::<BusLines>().prune(&sim.map());
+    remove_buses(sim);
+    if time.tick(60) {
+        spawn_buses(sim);
+    }
+    update_buses(sim);
+}
+
+/// Removes the buses of the lines that were removed, that run less buses or that switched
+/// between buses and trams, the passengers get off where the bus is.
+/// The buses retired for the night service first drop off their passengers.
+fn remove_buses(sim: &mut Simulation) {
+    let (world, res) = sim.world_res();
+    let lines = res.read::<BusLines>();
+    let cbuf_human = res.read::<ParCommandBuffer<HumanEnt>>();
+    let cbuf_vehicle = res.read::<ParCommandBuffer<VehicleEnt>>();
+    let daytime = res.read::<GameTime>().daytime;
+
+    let mut kept: BTreeMap<_, u32> = BTreeMap::new();
+    let mut to_remove = vec![];
+    for (id, bus) in world.buses.iter() {
+        let line = lines.get(bus.line).filter(|l| l.is_running());
+        let wanted = line.map_or(0, |l| l.n_running(daytime));
+        let kind = lines.get(bus.line).map(BusLine::vehicle_kind);
+        let n = kept.entry(bus.line).or_default();
+        if world
+            .vehicles
+            .get(bus.vehicle)
+            .map_or(true, |v| Some(v.vehicle.kind) != kind)
+        {
+            to_remove.push(id);
+            continue;
+        }
+        let night_only = line.map_or(false, |l| *n < l.n_buses);
+        if *n >= wanted && !(night_only && !bus.passengers.is_empty()) {
+            to_remove.push(id);
+            continue;
+        }
+        *n += 1;
+    }
+
+    for id in to_remove {
+        let bus = unwrap_cont!(world.buses.remove(id));
+        let pos = world.vehicles.get(bus.vehicle).map(|v| v.trans.position);
+        for (body, _) in bus.passengers {
+            let h = unwrap_cont!(world.humans.get_mut(body));
+            let pos = pos.unwrap_or(h.trans.position);
+            walk_outside(body, pos, &cbuf_human, &mut h.location);
+        }
+        cbuf_vehicle.kill(bus.vehicle);
+    }
+}
+
+/// Spawns one bus at the first stop of the lines missing some
+fn spawn_buses(sim: &mut Simulation) {
+    let map = sim.map();
+    let lines = sim.read::<BusLines>();
+    let daytime = sim.read::<GameTime>().daytime;
+
+    let mut to_spawn = vec![];
+    for (id, line) in lines.iter() {
+        if !line.is_running() {
+            continue;
+        }
+        let n_buses = sim.world.buses.values().filter(|b| b.line == id).count();
+        if n_buses >= line.n_running(daytime) as usize {
+            continue;
+        }
+        let Some(trans) = line
+            .stops
+            .first()
+            .and_then(|&s| map.bus_stops().get(s))
+            .and_then(|s| bus_spawn_trans(&map, s, line.vehicle_kind()))
+        else {
+            continue;
+        };
+        to_spawn.push((id, line.vehicle_kind(), line.color, trans));
+    }
+    drop((map, lines));
+
+    for (line, kind, color, trans) in to_spawn {
+        let vehicle = Vehicle::with_state(
+            kind,
+            VehicleState::Driving,
+            color,
+            &mut sim.write::<RandProvider>(),
+        );
+        let vehicle = make_vehicle_entity(sim, trans, vehicle, Itinerary::NONE, true);
+        sim.world.insert(BusEnt {
+            vehicle,
+            line,
+            next_stop: 0,
+            state: BusState::Driving,
+            passengers: vec![],
+        });
+    }
+}
+
+fn bus_spawn_trans(map: &Map, stop: &BusStop, kind: VehicleKind) -> Option<Transform> {
+    let lane = kind.path_kind().nearest_lane(map, stop.pos)?;
+    let (pos, _, dir) = map.lanes().get(lane)?.points.project_segment_dir(stop.pos);
+    Some(Transform::new_dir(pos, dir))
+}
+
+fn update_buses(sim: &mut Simulation) {
+    let tick = *sim.read::<Tick>();
+    let (world, res) = sim.world_res();
+    let map = res.read::<Map>();
+    let mut lines = res.write::<BusLines>();
+    let cbuf = res.read::<ParCommandBuffer<HumanEnt>>();
+    let time = *res.read::<GameTime>();
+    let now = time.timestamp;
+    let mut reached = vec![];
+    let mut unreachable = vec![];
+    // buses of each line seen so far, the ones past the night service are being retired
+    let mut seen: BTreeMap<BusLineID, u32> = BTreeMap::new();
+
+    for bus in world.buses.values_mut() {
+        let line = unwrap_cont!(lines.get(bus.line));
+        let rank = seen.entry(bus.line).or_default();
+        let retiring = *rank >= line.n_running(time.daytime);
+        *rank += 1;
+        let v = unwrap_cont!(world.vehicles.get_mut(bus.vehicle));
+        if line.stops.is_empty() {
+            continue;
+        }
+        bus.next_stop %= line.stops.len();
+        let stop_id = line.stops[bus.next_stop];
+        let stop = map.bus_stops().get(stop_id);
+
+        match bus.state {
+            BusState::Driving => {
+                if !v.it.has_ended(0.0) {
+                    continue;
+                }
+                let path_kind = line.vehicle_kind().path_kind();
+                let Some((stop, stop_pos)) =
+                    stop.and_then(|s| Some((s, s.bus_pos(&map, path_kind)?)))
+                else {
+                    bus.next_stop += 1;
+                    continue;
+                };
+                if !v.trans.position.is_close(stop_pos, ARRIVAL_DIST) {
+                    match Itinerary::route(tick, v.trans.position, stop_pos, &map, path_kind) {
+                        Some(it) => v.it = it,
+                        // the stop can't be reached, it is skipped and shown to the player
+                        None => {
+                            unreachable.push((bus.line, stop_id));
+                            bus.next_stop += 1;
+                        }
+                    }
+                    continue;
+                }
+                reached.push((bus.line, stop_id));
+
+                // the passengers whose stop was removed from the line get off here and walk
+                bus.passengers.retain(|&(body, to)| {
+                    let Some(h) = world.humans.get_mut(body) else {
+                        return false;
+                    };
+                    if to != stop_id && line.stops.contains(&to) {
+                        return true;
+                    }
+                    walk_outside(body, stop.pos, &cbuf, &mut h.location);
+                    false
+                });
+                bus.state = BusState::Dwelling(now + DWELL_DURATION);
+            }
+            BusState::Dwelling(until) => {
+                if now < until {
+                    continue;
+                }
+                // the passengers that got to the stop while the bus was there get in, unless
+                // the bus is retired for the night
+                if let Some(stop) = stop.filter(|_| !retiring) {
+                    for (body, h) in world.humans.iter_mut() {
+                        if bus.passengers.len() >= line.capacity() {
+                            break;
+                        }
+                        let Some(trip) = h.router.waiting_for_bus() else {
+                            continue;
+                        };
+                        if trip.line != bus.line
+                            || trip.from != stop_id
+                            || h.location != Location::Outside
+                            || !h.trans.position.is_close(stop.pos, BOARDING_DIST)
+                        {
+                            continue;
+                        }
+                        h.location = Location::Vehicle(bus.vehicle);
+                        walk_inside(body, h, &cbuf);
+                        bus.passengers.push((body, trip.to));
+                    }
+                }
+                bus.next_stop = (bus.next_stop + 1) % line.stops.len();
+                bus.state = BusState::Driving;
+            }
+        }
+    }
+
+    for served in reached {
+        lines.unreachable.remove(&served);
+    }
+    for (line, stop) in unreachable {
+        if lines.unreachable.insert((line, stop)) {
+            log::warn!(
+                "the vehicles of line {:?} can't reach stop {:?}",
+                line,
+                stop
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economy::{Government, Money};
+    use crate::map::{CurbKind, LaneKind, LanePatternBuilder, ProjectFilter};
+    use crate::souls::human::spawn_human;
+    use crate::tests::TestCtx;
+    use crate::world_command::WorldCommand;
+    use geom::{vec2, vec3};
+
+    #[test]
+    fn buses_serve_the_stops_of_their_line() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0., 0., 0.), vec3(1000., 0., 0.)]);
+
+        let map = test.g.map();
+        let stop = |x: f32| {
+            map.snap_bus_stop(vec3(x, 10.0, 0.0), String::new())
+                .unwrap()
+        };
+        let (a, b, too_close) = (stop(50.0), stop(950.0), stop(60.0));
+        let sidewalk = map
+            .nearest_lane(a.pos, LaneKind::Walking, Some(1.0))
+            .unwrap();
+        assert!(map.lanes()[sidewalk]
+            .points
+            .project(a.pos)
+            .is_close(a.pos, 0.01));
+        assert!(map
+            .snap_bus_stop(vec3(500.0, 200.0, 0.0), String::new())
+            .is_none());
+        drop(map);
+
+        test.apply(&[
+            WorldCommand::MapAddBusStop(a.clone()),
+            WorldCommand::MapAddBusStop(b),
+            WorldCommand::MapAddBusStop(too_close),
+        ]);
+        let stops: Vec<BusStopID> = test.g.map().bus_stops().keys().collect();
+        assert_eq!(stops.len(), 2);
+
+        test.apply(&[WorldCommand::AddBusLine(BusLine {
+            name: "1".to_string(),
+            color: Color::RED,
+            stops: stops.clone(),
+            n_buses: 1,
+            tram: false,
+        })]);
+
+        let lines = test.g.read::<BusLines>();
+        let (trip, wait_at) = lines
+            .plan_trip(&test.g.map(), vec3(0.0, 20.0, 0.0), vec3(1000.0, 20.0, 0.0))
+            .unwrap();
+        assert_eq!((trip.from, trip.to), (stops[0], stops[1]));
+        assert!(wait_at.is_close(a.pos, 0.01));
+        assert!(lines
+            .plan_trip(&test.g.map(), vec3(0.0, 20.0, 0.0), vec3(150.0, 20.0, 0.0))
+            .is_none());
+        drop(lines);
+
+        spawn_buses(&mut test.g);
+        assert_eq!(test.g.world.buses.len(), 1);
+        let bus = test.g.world.buses.values().next().unwrap();
+        assert!(matches!(
+            test.g.world.vehicles[bus.vehicle].vehicle.kind,
+            VehicleKind::Bus
+        ));
+
+        let road = test.g.map().roads().keys().next().unwrap();
+        test.apply(&[WorldCommand::MapRemoveRoad(road)]);
+        test.tick();
+        assert!(test.g.map().bus_stops().is_empty());
+        assert!(test.g.world.buses.is_empty());
+    }
+
+    #[test]
+    fn bus_stops_keep_their_bay_free_of_parked_cars() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0., 0., 0.), vec3(1000., 0., 0.)]);
+        let stop = test
+            .g
+            .map()
+            .snap_bus_stop(vec3(500.0, 10.0, 0.0), String::new())
+            .unwrap();
+        let spots_near_stop = |test: &TestCtx| {
+            let map = test.g.map();
+            map.parking
+                .all_spots()
+                .filter(|(_, s)| s.trans.position.xy().distance(stop.pos.xy()) < 8.0)
+                .count()
+        };
+        assert!(spots_near_stop(&test) > 0);
+
+        test.apply(&[WorldCommand::MapAddBusStop(stop.clone())]);
+        assert_eq!(spots_near_stop(&test), 0);
+        let id = test.g.map().bus_stops().keys().next().unwrap();
+        let kinds: Vec<CurbKind> = test.g.map().roads()[stop.road]
+            .curbs
+            .zones()
+            .iter()
+            .map(|z| z.kind)
+            .collect();
+        assert_eq!(kinds, vec![CurbKind::BusStop]);
+
+        test.apply(&[WorldCommand::MapRemoveBusStop(id)]);
+        assert!(spots_near_stop(&test) > 0);
+        assert!(test.g.map().roads()[stop.road].curbs.is_empty());
+    }
+
+    #[test]
+    fn trams_stop_on_the_tram_tracks() {
+        let mut test = TestCtx::new();
+        {
+            let mut m = test.g.map_mut();
+            let a = m.project(vec3(0., 0., 0.), 0.0, ProjectFilter::ALL);
+            let b = m.project(vec3(1000., 0., 0.), 0.0, ProjectFilter::ALL);
+            m.make_connection(a, b, None, &LanePatternBuilder::new().tram(true).build());
+        }
+
+        let map = test.g.map();
+        let stops = [50.0, 950.0].map(|x| {
+            map.snap_bus_stop(vec3(x, 10.0, 0.0), String::new())
+                .unwrap()
+        });
+        let on = |kind| {
+            let pos = stops[0].bus_pos(&map, kind).unwrap();
+            map.nearest_lane(pos, LaneKind::Tram, Some(0.1)).is_some()
+        };
+        assert!(on(PathKind::Tram));
+        assert!(!on(PathKind::Bus));
+        drop(map);
+
+        test.apply(&stops.map(WorldCommand::MapAddBusStop));
+        let stops: Vec<BusStopID> = test.g.map().bus_stops().keys().collect();
+        let mut line = BusLine {
+            name: "T1".to_string(),
+            color: Color::RED,
+            stops,
+            n_buses: 1,
+            tram: true,
+        };
+        assert_eq!(
+            Government::action_cost(&WorldCommand::AddBusLine(line.clone()), &test.g),
+            Money::new_bucks(TRAM_PRICE)
+        );
+        test.apply(&[WorldCommand::AddBusLine(line.clone())]);
+
+        spawn_buses(&mut test.g);
+        let bus = test.g.world.buses.values().next().unwrap();
+        assert_eq!(
+            test.g.world.vehicles[bus.vehicle].vehicle.kind,
+            VehicleKind::Tram
+        );
+
+        // switching to buses replaces the trams
+        let id = test.g.read::<BusLines>().iter().next().unwrap().0;
+        line.tram = false;
+        test.apply(&[WorldCommand::UpdateBusLine(id, line)]);
+        remove_buses(&mut test.g);
+        spawn_buses(&mut test.g);
+        let bus = test.g.world.buses.values().next().unwrap();
+        assert_eq!(
+            test.g.world.vehicles[bus.vehicle].vehicle.kind,
+            VehicleKind::Bus
+        );
+    }
+
+    #[test]
+    fn lines_run_half_their_buses_at_night() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0., 0., 0.), vec3(1000., 0., 0.)]);
+        let house = test.build_house_near(vec2(500.0, 50.0));
+
+        let map = test.g.map();
+        let stops = [50.0, 950.0].map(|x| {
+            map.snap_bus_stop(vec3(x, 10.0, 0.0), String::new())
+                .unwrap()
+        });
+        drop(map);
+        test.apply(&stops.map(WorldCommand::MapAddBusStop));
+        let stops: Vec<BusStopID> = test.g.map().bus_stops().keys().collect();
+        test.apply(&[WorldCommand::AddBusLine(BusLine {
+            name: "N1".to_string(),
+            color: Color::RED,
+            stops: stops.clone(),
+            n_buses: 2,
+            tram: false,
+        })]);
+        let set_hour = |test: &mut TestCtx, hour: i32| {
+            *test.g.write::<GameTime>() =
+                GameTime::new(0.0, (GameTime::DAY + hour * GameTime::HOUR) as f64);
+        };
+
+        spawn_buses(&mut test.g);
+        spawn_buses(&mut test.g);
+        assert_eq!(test.g.world.buses.len(), 2);
+
+        // the buses carrying passengers finish their trip
+        set_hour(&mut test, 2);
+        let human = spawn_human(&mut test.g, house).unwrap();
+        for bus in test.g.world.buses.values_mut() {
+            bus.passengers.push((human, stops[1]));
+        }
+        remove_buses(&mut test.g);
+        assert_eq!(test.g.world.buses.len(), 2);
+
+        for bus in test.g.world.buses.values_mut() {
+            bus.passengers.clear();
+        }
+        remove_buses(&mut test.g);
+        spawn_buses(&mut test.g);
+        assert_eq!(test.g.world.buses.len(), 1);
+
+        set_hour(&mut test, 8);
+        spawn_buses(&mut test.g);
+        assert_eq!(test.g.world.buses.len(), 2);
+    }
+
+    #[test]
+    fn passengers_of_a_removed_stop_get_off_at_the_next_one() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0., 0., 0.), vec3(1000., 0., 0.)]);
+        let house = test.build_house_near(vec2(500.0, 50.0));
+
+        let map = test.g.map();
+        let stops = [50.0, 500.0, 950.0].map(|x| {
+            map.snap_bus_stop(vec3(x, 10.0, 0.0), String::new())
+                .unwrap()
+        });
+        drop(map);
+        test.apply(&stops.map(WorldCommand::MapAddBusStop));
+        let stops: Vec<BusStopID> = test.g.map().bus_stops().keys().collect();
+        let mut line = BusLine {
+            name: "1".to_string(),
+            color: Color::RED,
+            stops: stops.clone(),
+            n_buses: 1,
+            tram: false,
+        };
+        test.apply(&[WorldCommand::AddBusLine(line.clone())]);
+        spawn_buses(&mut test.g);
+
+        let human = spawn_human(&mut test.g, house).unwrap();
+        let bus = test.g.world.buses.values_mut().next().unwrap();
+        bus.passengers.push((human, stops[1]));
+        let vehicle = bus.vehicle;
+        test.g.world.humans[human].location = Location::Vehicle(vehicle);
+
+        // the stop of the passenger is removed, the bus arrives at the first stop
+        let id = test.g.read::<BusLines>().iter().next().unwrap().0;
+        line.stops.remove(1);
+        test.apply(&[WorldCommand::UpdateBusLine(id, line)]);
+        update_buses(&mut test.g);
+
+        assert_eq!(test.g.world.humans[human].location, Location::Outside);
+        let bus = test.g.world.buses.values().next().unwrap();
+        assert!(bus.passengers.is_empty());
+        assert!(matches!(bus.state, BusState::Dwelling(_)));
+    }
+}
@@ -0,0 +1,153 @@
+//! Emergency vehicles answering the incidents, see [`crate::map_dynamic::BuildingRisks`].
+//! They drive with their siren on and the vehicles they come up behind pull over to the right of
+//! their lane, opening a corridor through the congestion.
+
+use crate::map::{Map, Pathfinder};
+use crate::map_dynamic::{service_buildings, BuildingRisks, Itinerary};
+use crate::souls::goods_company::GoodsCompanyRegistry;
+use crate::transportation::{make_vehicle_entity, Vehicle, VehicleKind, VehicleState};
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::time::{GameTime, Tick, SECONDS_PER_HOUR};
+use crate::world::{VehicleEnt, VehicleID};
+use crate::{ParCommandBuffer, Simulation};
+use common::descriptions::ServiceKind;
+use geom::{Color, Transform, Vec2};
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How long the emergency vehicles stay at the incident, in seconds
+const ON_SCENE_DURATION: f64 = 10.0 * 60.0;
+/// Emergency vehicles still on their way after this long are called back, in seconds
+const MAX_RESPONSE_DURATION: f64 = SECONDS_PER_HOUR as f64;
+
+/// EmergencyResponses keeps track of the emergency vehicles sent to the incidents
+#[derive(Default, Serialize, Deserialize)]
+pub struct EmergencyResponses {
+    /// When the last answered incident happened
+    last_call: f64,
+    /// The vehicles on their way or on scene, with when they were sent or arrived
+    vehicles: BTreeMap<VehicleID, f64>,
+}
+
+impl EmergencyResponses {
+    pub fn n_responding(&self) -> usize {
+        self.vehicles.len()
+    }
+}
+
+fn vehicle_kind(service: ServiceKind) -> VehicleKind {
+    match service {
+        ServiceKind::Fire => VehicleKind::Truck,
+        ServiceKind::Police | ServiceKind::Health => VehicleKind::Car,
+    }
+}
+
+fn tint(service: ServiceKind) -> Color {
+    match service {
+        ServiceKind::Fire => Color::new(0.8, 0.1, 0.1, 1.0),
+        ServiceKind::Police => Color::new(0.1, 0.2, 0.6, 1.0),
+        ServiceKind::Health => Color::WHITE,
+    }
+}
+
+pub fn emergency_system(sim: &mut Simulation) {
+    profiling::scope!("transportation::emergency_system");
+    dispatch_emergency_vehicles(sim);
+    update_emergency_vehicles(sim);
+}
+
+/// Sends a vehicle from the closest staffed service building to every new incident
+fn dispatch_emergency_vehicles(sim: &mut Simulation) {
+    let now = sim.read::<GameTime>().timestamp;
+    let tick = *sim.read::<Tick>();
+
+    let mut to_spawn = vec![];
+    {
+        let risks = sim.read::<BuildingRisks>();
+        let mut responses = sim.write::<EmergencyResponses>();
+        // incidents too old to still need help are not answered, e.g. when loading an old save
+        let since = responses.last_call.max(now - ON_SCENE_DURATION);
+        let calls: Vec<_> = risks
+            .incidents()
+            .iter()
+            .filter(|i| i.at.timestamp > since)
+            .collect();
+        let Some(last) = calls.last() else {
+            return;
+        };
+        responses.last_call = last.at.timestamp;
+
+        let map = sim.map();
+        let stations = service_buildings(sim.world(), &map, &sim.read::<GoodsCompanyRegistry>());
+        for incident in calls {
+            let Some(dest) = map.buildings().get(incident.building).map(|b| b.door_pos) else {
+                continue;
+            };
+            let Some(&(station, _, _)) = stations
+                .iter()
+                .filter(|&&(_, service, staffing)| service == incident.kind && staffing > 0.0)
+                .min_by_key(|(pos, _, _)| OrderedFloat(pos.distance2(dest.xy())))
+            else {
+                continue;
+            };
+            let kind = vehicle_kind(incident.kind);
+            let Some(trans) = spawn_trans(&map, station, kind) else {
+                continue;
+            };
+            let Some(it) = Itinerary::route(tick, trans.position, dest, &map, kind.path_kind())
+            else {
+                continue;
+            };
+            to_spawn.push((incident.kind, trans, it));
+        }
+    }
+
+    for (service, trans, it) in to_spawn {
+        let mut vehicle = Vehicle::with_state(
+            vehicle_kind(service),
+            VehicleState::Driving,
+            tint(service),
+            &mut sim.write::<RandProvider>(),
+        );
+        vehicle.siren = true;
+        let id = make_vehicle_entity(sim, trans, vehicle, it, true);
+        sim.write::<EmergencyResponses>().vehicles.insert(id, now);
+    }
+}
+
+/// Turns the siren off once on scene, and removes the vehicles once they are done
+fn update_emergency_vehicles(sim: &mut Simulation) {
+    let now = sim.read::<GameTime>().timestamp;
+    let (world, res) = sim.world_res();
+    let cbuf = res.read::<ParCommandBuffer<VehicleEnt>>();
+
+    res.write::<EmergencyResponses>()
+        .vehicles
+        .retain(|&id, since| {
+            let Some(v) = world.vehicles.get_mut(id) else {
+                return false;
+            };
+            if v.vehicle.siren && v.it.has_ended(now) {
+                v.vehicle.siren = false;
+                *since = now;
+            }
+            let duration = if v.vehicle.siren {
+                MAX_RESPONSE_DURATION
+            } else {
+                ON_SCENE_DURATION
+            };
+            if now - *since > duration {
+                cbuf.kill(id);
+                return false;
+            }
+            true
+        });
+}
+
+fn spawn_trans(map: &Map, station: Vec2, kind: VehicleKind) -> Option<Transform> {
+    let lane = kind.path_kind().nearest_lane(map, station.z0())?;
+    let l = map.lanes().get(lane)?;
+    let (pos, _, dir) = l.points.project_segment_dir(station.z(l.points.first().z));
+    Some(Transform::new_dir(pos, dir))
+}
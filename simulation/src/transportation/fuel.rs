@@ -0,0 +1,250 @@
+use crate::economy::{EconomyCrises, Government, Money};
+use crate::map::BuildingID;
+use crate::transportation::bus::BusState;
+use crate::transportation::{Location, Vehicle, VehicleKind, VehicleState};
+use crate::utils::resources::Resources;
+use crate::utils::time::GameTime;
+use crate::world::{HumanID, VehicleID};
+use crate::World;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Distance a car drives on a full tank at the optimal speed, in meters
+pub const CAR_RANGE: f32 = 40_000.0;
+/// Trucks and buses are heavier, they are refilled at their depot or at the end of their line
+pub const HEAVY_RANGE: f32 = 15_000.0;
+/// Speed at which vehicles consume the least, in m/s
+pub const OPTIMAL_SPEED: f32 = 15.0;
+/// Below this share of the tank, drivers start to think about refueling
+pub const LOW_FUEL: f32 = 0.25;
+/// Vehicles with an empty tank crawl to the gas station
+pub const RESERVE_SPEED: f32 = 4.0;
+/// Share of the new cars that are electric
+pub const EV_SHARE: f32 = 0.2;
+
+/// Price of a full tank of the trucks and buses refilled at their depot, out of fuel crises
+pub const DEPOT_TANK_PRICE: Money = Money::new_bucks(40);
+
+pub const PUMPS_PER_STATION: usize = 4;
+/// Time spent at the pump, in seconds
+pub const REFUEL_TIME: f64 = 120.0;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnergyKind {
+    /// Refueled at gas stations
+    #[default]
+    Fuel,
+    /// Charged at home with electricity bought from the power network
+    Electric,
+}
+
+debug_inspect_impl!(EnergyKind);
+
+/// Share of the tank consumed per meter driven at the given speed.
+/// Driving slower or faster than the optimal speed costs more.
+pub fn consumption(kind: VehicleKind, speed: f32) -> f32 {
+    let range = match kind {
+        VehicleKind::Car => CAR_RANGE,
+        VehicleKind::Truck | VehicleKind::Bus | VehicleKind::Tram => HEAVY_RANGE,
+    };
+    let off = (speed - OPTIMAL_SPEED) / OPTIMAL_SPEED;
+    (1.0 + 0.5 * off * off) / range
+}
+
+/// Fills the tank of a vehicle at its depot, returns the price of the fuel that was put in.
+/// Fuel crises multiply the price by their factor.
+pub fn refill_at_depot(vehicle: &mut Vehicle, fuel_factor: f32) -> Money {
+    let missing = 1.0 - vehicle.fuel;
+    vehicle.fuel = 1.0;
+    Money::new_inner((DEPOT_TANK_PRICE.inner() as f32 * missing * fuel_factor) as i64)
+}
+
+/// The pumps of a gas station and the drivers waiting for one
+#[derive(Default, Serialize, Deserialize)]
+pub struct GasStation {
+    /// Drivers at the pumps and when they are done
+    pumps: Vec<(HumanID, f64)>,
+    queue: VecDeque<HumanID>,
+    pub served: u32,
+}
+
+impl GasStation {
+    pub fn busy_pumps(&self) -> usize {
+        self.pumps.len()
+    }
+
+    pub fn queue_len(&self) -> usize {
+        self.queue.len()
+    }
+
+    fn contains(&self, human: HumanID) -> bool {
+        self.queue.contains(&human) || self.pumps.iter().any(|&(h, _)| h == human)
+    }
+}
+
+/// GasStations keeps the queues at the pumps of the buildings selling fuel.
+/// A station appears when its first driver arrives.
+#[derive(Default, Serialize, Deserialize)]
+pub struct GasStations {
+    stations: BTreeMap<BuildingID, GasStation>,
+}
+
+impl GasStations {
+    /// Puts the driver in the queue of the station if it isn't there yet
+    pub fn join(&mut self, station: BuildingID, human: HumanID) {
+        let s = self.stations.entry(station).or_default();
+        if !s.contains(human) {
+            s.queue.push_back(human);
+        }
+    }
+
+    pub fn get(&self, station: BuildingID) -> Option<&GasStation> {
+        self.stations.get(&station)
+    }
+
+    /// Moves the queues forward, returns the drivers done refueling
+    fn update(&mut self, now: f64, is_at: impl Fn(HumanID, BuildingID) -> bool) -> Vec<HumanID> {
+        let mut done = vec![];
+        for (&b, s) in &mut self.stations {
+            let n_done = done.len();
+            s.queue.retain(|&h| is_at(h, b));
+            s.pumps.retain(|&(h, until)| {
+                if !is_at(h, b) {
+                    return false;
+                }
+                if now >= until {
+                    done.push(h);
+                    return false;
+                }
+                true
+            });
+            s.served += (done.len() - n_done) as u32;
+            while s.pumps.len() < PUMPS_PER_STATION {
+                let Some(h) = s.queue.pop_front() else {
+                    break;
+                };
+                s.pumps.push((h, now + REFUEL_TIME));
+            }
+        }
+        done
+    }
+}
+
+/// Consumes the fuel of the moving vehicles, refills the trucks and buses at their depot and
+/// serves the drivers at the gas stations.
+/// The companies pay for the fuel of their delivery trucks, the city for the other vehicles.
+pub fn fuel_system(world: &mut World, res: &mut Resources) {
+    profiling::scope!("transportation::fuel_system");
+    let time = res.read::<GameTime>();
+    let fuel_factor = res.read::<EconomyCrises>().fuel_factor();
+
+    let mut refills: BTreeMap<VehicleID, Money> = BTreeMap::new();
+    for (id, v) in world.vehicles.iter_mut() {
+        let speed = v.speed.0;
+        if speed > 0.0 {
+            let used = speed * time.realdelta * consumption(v.vehicle.kind, speed);
+            v.vehicle.fuel = (v.vehicle.fuel - used).max(0.0);
+        } else if !matches!(v.vehicle.kind, VehicleKind::Car)
+            && matches!(v.vehicle.state, VehicleState::Parked(_))
+            && v.vehicle.fuel < 1.0
+        {
+            refills.insert(id, refill_at_depot(&mut v.vehicle, fuel_factor));
+        }
+    }
+
+    // the buses and trams are refilled at the first stop of their line
+    for bus in world.buses.values() {
+        if bus.next_stop != 0 || !matches!(bus.state, BusState::Dwelling(_)) {
+            continue;
+        }
+        let Some(v) = world.vehicles.get_mut(bus.vehicle) else {
+            continue;
+        };
+        if v.vehicle.fuel < 1.0 {
+            refills.insert(bus.vehicle, refill_at_depot(&mut v.vehicle, fuel_factor));
+        }
+    }
+
+    if !refills.is_empty() {
+        for c in world.companies.values_mut() {
+            for t in &c.comp.fleet {
+                if let Some(price) = refills.remove(&t.truck) {
+                    c.comp.money -= price;
+                }
+            }
+        }
+        let price: Money = refills.into_values().sum();
+        res.write::<Government>()
+            .spend(price, "Depot fuel", time.timestamp);
+    }
+
+    let humans = &world.humans;
+    let done = res.write::<GasStations>().update(time.timestamp, |h, b| {
+        humans
+            .get(h)
+            .map_or(false, |h| h.location == Location::Building(b))
+    });
+    for h in done {
+        let Some(car) = world.humans.get(h).and_then(|h| h.router.personal_car) else {
+            continue;
+        };
+        if let Some(v) = world.vehicles.get_mut(car) {
+            v.vehicle.fuel = 1.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::rand_provider::RandProvider;
+    use geom::Color;
+    use slotmapd::KeyData;
+
+    #[test]
+    fn pumps_serve_the_queue_in_order() {
+        let humans: Vec<HumanID> = (1..=6)
+            .map(|i| HumanID::from(KeyData::from_ffi(i)))
+            .collect();
+        let station = BuildingID::default();
+        let mut stations = GasStations::default();
+        for &h in &humans {
+            stations.join(station, h);
+        }
+        stations.join(station, humans[0]);
+
+        assert!(stations.update(0.0, |_, _| true).is_empty());
+        let s = stations.get(station).unwrap();
+        assert_eq!(s.busy_pumps(), PUMPS_PER_STATION);
+        assert_eq!(s.queue_len(), humans.len() - PUMPS_PER_STATION);
+
+        let done = stations.update(REFUEL_TIME, |h, _| h != humans[5]);
+        assert_eq!(done, humans[..PUMPS_PER_STATION]);
+        let s = stations.get(station).unwrap();
+        assert_eq!(s.busy_pumps(), 1);
+        assert_eq!(s.queue_len(), 0);
+        assert_eq!(s.served, PUMPS_PER_STATION as u32);
+    }
+
+    #[test]
+    fn driving_far_from_the_optimal_speed_costs_more() {
+        let optimal = consumption(VehicleKind::Car, OPTIMAL_SPEED);
+        assert!(consumption(VehicleKind::Car, 30.0) > optimal);
+        assert!(consumption(VehicleKind::Car, 5.0) > optimal);
+        assert!(consumption(VehicleKind::Truck, OPTIMAL_SPEED) > optimal);
+    }
+
+    #[test]
+    fn depot_refills_are_paid() {
+        let mut v = Vehicle::with_state(
+            VehicleKind::Truck,
+            VehicleState::Driving,
+            Color::WHITE,
+            &mut RandProvider::new(0),
+        );
+        v.fuel = 0.5;
+        assert_eq!(refill_at_depot(&mut v, 1.0), DEPOT_TANK_PRICE / 2);
+        assert_eq!(v.fuel, 1.0);
+        assert_eq!(refill_at_depot(&mut v, 2.0), Money::ZERO);
+    }
+}
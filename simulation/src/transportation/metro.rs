@@ -0,0 +1,639 @@
+use crate::map::{metro_lane_under, BuildingID, LaneID, Map, MapLayer};
+use crate::map_dynamic::{walk_inside, walk_outside, Itinerary};
+use crate::transportation::bus::PLANNING_WALK_SPEED;
+use crate::transportation::train::{
+    despawn_train, spawn_train, train_length, LocomotiveKind, RailWagonKind,
+};
+use crate::transportation::Location;
+use crate::utils::time::{GameTime, Tick};
+use crate::world::{HumanEnt, HumanID, TrainID};
+use crate::{ParCommandBuffer, Simulation};
+use geom::{Color, Vec3, OBB};
+use serde::{Deserialize, Serialize};
+use slotmapd::HopSlotMap;
+use std::collections::BTreeMap;
+
+/// Price of a metro train, paid when a line gets more of them
+pub const METRO_TRAIN_PRICE: i64 = 6000;
+/// Most trains a line can run
+pub const MAX_METRO_TRAINS_PER_LINE: u32 = 10;
+/// Most stations a line can serve
+pub const MAX_METRO_LINE_STATIONS: usize = 30;
+/// Shortest time between two departures of the same line, in game seconds
+pub const MIN_METRO_HEADWAY: f64 = 60.0;
+/// Longest name of a line
+pub const MAX_METRO_LINE_NAME: usize = 30;
+/// Number of passengers a metro train can carry
+pub const METRO_CAPACITY: usize = 300;
+
+/// How far from the station the platform can be, in meters
+const PLATFORM_REACH: f32 = 40.0;
+/// Passenger wagons of a metro train
+const METRO_WAGONS: u32 = 3;
+/// How long the trains stay at each station, in game seconds
+const DWELL_DURATION: f64 = 40.0;
+/// How close to the entrance of the station the passengers must wait to board
+const BOARDING_DIST: f32 = 10.0;
+/// Average speed of the trains, stops included, used to plan the trips in m/s
+const PLANNING_METRO_SPEED: f32 = 15.0;
+/// Time to go down to the platform and back up from the other one, in seconds
+const PLANNING_STAIRS_DURATION: f32 = 90.0;
+/// Trips shorter than this are always walked, in meters
+const MIN_METRO_TRIP: f32 = 800.0;
+/// How far people are willing to walk to or from a station, in meters
+const MAX_WALK_TO_STATION: f32 = 800.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct MetroLineID(pub u32);
+
+debug_inspect_impl!(MetroLineID);
+
+/// A metro line, its trains leave the first station on a schedule and go around the stations in
+/// order, coming back to the first one after the last
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetroLine {
+    pub name: String,
+    pub color: Color,
+    /// The metro stations served by the line
+    pub stations: Vec<BuildingID>,
+    pub n_trains: u32,
+    /// Game seconds between two departures from the first station
+    pub headway: f64,
+}
+
+impl MetroLine {
+    /// Why the line can't be created, if it can't
+    pub fn check(&self) -> Result<(), &'static str> {
+        if self.name.chars().count() > MAX_METRO_LINE_NAME {
+            return Err("Metro line name is too long");
+        }
+        if self.stations.len() > MAX_METRO_LINE_STATIONS {
+            return Err("Too many stations on the metro line");
+        }
+        if self.n_trains > MAX_METRO_TRAINS_PER_LINE {
+            return Err("Too many trains on the metro line");
+        }
+        if self.headway < MIN_METRO_HEADWAY {
+            return Err("Metro trains can't leave this often");
+        }
+        Ok(())
+    }
+
+    /// Whether trains run on the line
+    pub fn is_running(&self) -> bool {
+        self.n_trains > 0 && self.stations.len() >= 2
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MetroTrainState {
+    /// Running to its next station
+    Running,
+    /// Stopped at its station until the given timestamp
+    Dwelling(f64),
+}
+
+/// A trip planned by a pedestrian on a metro line
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetroTrip {
+    pub line: MetroLineID,
+    /// The station where the pedestrian gets on
+    pub from: BuildingID,
+    /// The station where the pedestrian gets off
+    pub to: BuildingID,
+}
+
+debug_inspect_impl!(MetroTrip);
+
+/// A train serving a metro line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetroTrain {
+    pub line: MetroLineID,
+    /// Index of the station the train is at or running to
+    pub next_station: usize,
+    pub state: MetroTrainState,
+    /// The passengers and the station where they get off
+    pub passengers: Vec<(HumanID, BuildingID)>,
+}
+
+impl MetroTrain {
+    /// Lets the passengers of the station out. Those whose station was removed from the line
+    /// get out too and find another way to their destination from there
+    fn let_off(
+        &mut self,
+        line: &MetroLine,
+        station: BuildingID,
+        entrance: Vec3,
+        humans: &mut HopSlotMap<HumanID, HumanEnt>,
+        cbuf: &ParCommandBuffer<HumanEnt>,
+    ) {
+        self.passengers.retain(|&(body, to)| {
+            let Some(h) = humans.get_mut(body) else {
+                return false;
+            };
+            if to == station {
+                walk_outside(body, entrance, cbuf, &mut h.location);
+                return false;
+            }
+            if line.stations.contains(&to) {
+                return true;
+            }
+            walk_outside(body, entrance, cbuf, &mut h.location);
+            h.router.reset_dest();
+            false
+        });
+    }
+}
+
+/// MetroLines keeps the metro lines and their schedule
+#[derive(Default, Serialize, Deserialize)]
+pub struct MetroLines {
+    lines: BTreeMap<MetroLineID, MetroLine>,
+    /// When the next train of each line leaves its first station
+    next_departure: BTreeMap<MetroLineID, f64>,
+    next_id: u32,
+}
+
+impl MetroLines {
+    pub fn add(&mut self, line: MetroLine) -> MetroLineID {
+        let id = MetroLineID(self.next_id);
+        self.next_id += 1;
+        self.lines.insert(id, line);
+        id
+    }
+
+    /// Replaces the line if it still exists
+    pub fn update(&mut self, id: MetroLineID, line: MetroLine) {
+        if let Some(l) = self.lines.get_mut(&id) {
+            *l = line;
+        }
+    }
+
+    pub fn remove(&mut self, id: MetroLineID) {
+        self.lines.remove(&id);
+        self.next_departure.remove(&id);
+    }
+
+    pub fn get(&self, id: MetroLineID) -> Option<&MetroLine> {
+        self.lines.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (MetroLineID, &MetroLine)> {
+        self.lines.iter().map(|(&id, l)| (id, l))
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// When the next train of the line is scheduled to leave its first station
+    pub fn next_departure(&self, id: MetroLineID) -> f64 {
+        self.next_departure.get(&id).copied().unwrap_or(0.0)
+    }
+
+    /// Drops the stations that were demolished
+    fn prune(&mut self, map: &Map) {
+        for line in self.lines.values_mut() {
+            line.stations.retain(|&s| map.buildings().contains_key(s));
+            line.stations.dedup();
+        }
+    }
+
+    /// The fastest metro trip from `from` to `to` along with the entrance where to wait for the
+    /// train. None if walking all the way is faster.
+    pub fn plan_trip(&self, map: &Map, from: Vec3, to: Vec3) -> Option<(MetroTrip, Vec3)> {
+        let direct = from.distance(to);
+        if direct < MIN_METRO_TRIP {
+            return None;
+        }
+        let mut best_time = direct / PLANNING_WALK_SPEED;
+        let mut best = None;
+
+        for (&id, line) in &self.lines {
+            if !line.is_running() {
+                continue;
+            }
+            let stations: Vec<(BuildingID, Vec3)> = line
+                .stations
+                .iter()
+                .filter_map(|&s| Some((s, map.buildings().get(s)?.door_pos)))
+                .collect();
+            if stations.len() < 2 {
+                continue;
+            }
+
+            // distance along the line from the first station
+            let mut along = Vec::with_capacity(stations.len());
+            let mut d = 0.0;
+            along.push(d);
+            for w in stations.windows(2) {
+                d += w[0].1.distance(w[1].1);
+                along.push(d);
+            }
+            let loop_length = d + stations[stations.len() - 1].1.distance(stations[0].1);
+            // the trains leave on schedule unless there are too few of them to keep it
+            let interval = (line.headway as f32)
+                .max(loop_length / (line.n_trains as f32 * PLANNING_METRO_SPEED));
+            let wait = interval * 0.5 + PLANNING_STAIRS_DURATION;
+
+            for (i, &(board, board_pos)) in stations.iter().enumerate() {
+                let access = board_pos.distance(from);
+                if access > MAX_WALK_TO_STATION {
+                    continue;
+                }
+                for (j, &(alight, alight_pos)) in stations.iter().enumerate() {
+                    let egress = alight_pos.distance(to);
+                    if i == j || egress > MAX_WALK_TO_STATION {
+                        continue;
+                    }
+                    let ride = if j > i {
+                        along[j] - along[i]
+                    } else {
+                        loop_length - along[i] + along[j]
+                    };
+                    let time = (access + egress) / PLANNING_WALK_SPEED
+                        + wait
+                        + ride / PLANNING_METRO_SPEED;
+                    if time < best_time {
+                        best_time = time;
+                        best = Some((
+                            MetroTrip {
+                                line: id,
+                                from: board,
+                                to: alight,
+                            },
+                            board_pos,
+                        ));
+                    }
+                }
+            }
+        }
+
+        best
+    }
+}
+
+/// Metro stations are built above a metro tunnel, their platform is under them
+pub fn check_metro_station_footprint(map: &Map, obb: &OBB) -> Result<(), &'static str> {
+    let center = obb.center();
+    let Some(h) = map.environment.height(center) else {
+        return Err("Outside of the map");
+    };
+    let reach = PLATFORM_REACH + obb.axis().iter().map(|a| a.mag()).fold(0.0, f32::max) * 0.5;
+    if metro_lane_under(map, center.z(h), reach).is_none() {
+        return Err("Metro stations must be built above a metro tunnel");
+    }
+    Ok(())
+}
+
+/// Where the trains stop under the station: the lane and the distance along it
+pub fn metro_platform(map: &Map, station: BuildingID) -> Option<(LaneID, f32)> {
+    let b = map.buildings().get(station)?;
+    let reach = PLATFORM_REACH + b.obb.axis().iter().map(|a| a.mag()).fold(0.0, f32::max) * 0.5;
+    let center = b.obb.center().z(b.door_pos.z);
+    let id = metro_lane_under(map, center, reach)?;
+    let points = &map.lanes().get(id)?.points;
+    let depth = MapLayer::Underground.height(center, &map.environment)?;
+    let proj = points.project(center.xy().z(depth));
+    Some((id, points.length_at_proj(proj)))
+}
+
+/// Runs the metro trains: spawns them on the schedule of their line, removes them to match the
+/// lines and stops them at every station
+pub fn metro_system(sim: &mut Simulation) {
+    profiling::scope!("transportation::metro_system");
+    sim.write::<MetroLines>().prune(&sim.map());
+    remove_metro_trains(sim);
+    spawn_metro_trains(sim);
+    update_metro_trains(sim);
+}
+
+/// Removes the trains of the lines that were removed or that run less trains, the passengers
+/// get out at the station the train was going to
+fn remove_metro_trains(sim: &mut Simulation) {
+    let mut to_remove: Vec<TrainID> = vec![];
+    {
+        let lines = sim.read::<MetroLines>();
+        let mut kept: BTreeMap<_, u32> = BTreeMap::new();
+        for (id, train) in sim.world.trains.iter() {
+            let Some(ref metro) = train.metro else {
+                continue;
+            };
+            let wanted = lines
+                .get(metro.line)
+                .filter(|l| l.is_running())
+                .map_or(0, |l| l.n_trains);
+            let n = kept.entry(metro.line).or_default();
+            if *n >= wanted {
+                to_remove.push(id);
+                continue;
+            }
+            *n += 1;
+        }
+    }
+
+    for id in to_remove {
+        let (world, res) = sim.world_res();
+        let train_pos = world.trains.get(id).map(|t| t.trans.position);
+        if let Some(metro) = world.trains.get_mut(id).and_then(|t| t.metro.take()) {
+            let map = res.read::<Map>();
+            let cbuf = res.read::<ParCommandBuffer<HumanEnt>>();
+            let station = res
+                .read::<MetroLines>()
+                .get(metro.line)
+                .and_then(|l| l.stations.get(metro.next_station).copied())
+                .and_then(|s| map.buildings().get(s).map(|b| b.door_pos));
+            for (body, _) in metro.passengers {
+                let h = unwrap_cont!(world.humans.get_mut(body));
+                let pos = station.or(train_pos).unwrap_or(h.trans.position);
+                walk_outside(body, pos, &cbuf, &mut h.location);
+                h.router.reset_dest();
+            }
+        }
+        despawn_train(sim, id);
+    }
+}
+
+/// Sends a new train from the first station of the lines missing some, when their schedule says so
+fn spawn_metro_trains(sim: &mut Simulation) {
+    let time = *sim.read::<GameTime>();
+
+    let mut to_spawn = vec![];
+    {
+        let lines = sim.read::<MetroLines>();
+        let map = sim.map();
+        let mut running: BTreeMap<MetroLineID, u32> = BTreeMap::new();
+        for train in sim.world.trains.values() {
+            if let Some(ref metro) = train.metro {
+                *running.entry(metro.line).or_default() += 1;
+            }
+        }
+
+        for (id, line) in lines.iter() {
+            if !line.is_running()
+                || running.get(&id).copied().unwrap_or(0) >= line.n_trains
+                || time.timestamp < lines.next_departure(id)
+            {
+                continue;
+            }
+            let Some((lane, dist)) = metro_platform(&map, line.stations[0]) else {
+                continue;
+            };
+            // the wagons must fit behind the locomotive
+            let length = map.lanes()[lane].points.length();
+            let min_dist = train_length(METRO_WAGONS);
+            if length < min_dist {
+                continue;
+            }
+            to_spawn.push((id, line.headway, lane, dist.clamp(min_dist, length)));
+        }
+    }
+
+    for (line, headway, lane, dist) in to_spawn {
+        sim.write::<MetroLines>()
+            .next_departure
+            .insert(line, time.timestamp + headway);
+        let Some(id) = spawn_train(
+            sim,
+            dist,
+            METRO_WAGONS,
+            lane,
+            RailWagonKind::Passenger,
+            LocomotiveKind::Electric,
+        ) else {
+            continue;
+        };
+        if let Some(train) = sim.world.trains.get_mut(id) {
+            train.metro = Some(MetroTrain {
+                line,
+                next_station: 0,
+                state: MetroTrainState::Dwelling(time.timestamp + DWELL_DURATION),
+                passengers: vec![],
+            });
+        }
+    }
+}
+
+/// Stops the trains at their station to let the passengers out and in, then sends them to the
+/// next one
+fn update_metro_trains(sim: &mut Simulation) {
+    let (world, res) = sim.world_res();
+    let lines = res.read::<MetroLines>();
+    let map = res.read::<Map>();
+    let time = res.read::<GameTime>();
+    let tick = *res.read::<Tick>();
+    let cbuf = res.read::<ParCommandBuffer<HumanEnt>>();
+
+    for (train_id, train) in world.trains.iter_mut() {
+        let Some(ref mut metro) = train.metro else {
+            continue;
+        };
+        let Some(line) = lines.get(metro.line) else {
+            continue;
+        };
+        let station = line
+            .stations
+            .get(metro.next_station)
+            .copied()
+            .and_then(|s| Some((s, map.buildings().get(s)?.door_pos)));
+        match metro.state {
+            MetroTrainState::Running => {
+                if !train.it.has_ended(time.timestamp) {
+                    continue;
+                }
+                metro.state = MetroTrainState::Dwelling(time.timestamp + DWELL_DURATION);
+                let Some((station, entrance)) = station else {
+                    continue;
+                };
+                metro.let_off(line, station, entrance, &mut world.humans, &cbuf);
+            }
+            MetroTrainState::Dwelling(until) => {
+                if time.timestamp < until || line.stations.is_empty() {
+                    continue;
+                }
+                // the passengers that got to the entrance while the train was there get in
+                if let Some((station, entrance)) = station {
+                    for (body, h) in world.humans.iter_mut() {
+                        if metro.passengers.len() >= METRO_CAPACITY {
+                            break;
+                        }
+                        let Some(trip) = h.router.waiting_for_metro() else {
+                            continue;
+                        };
+                        if trip.line != metro.line
+                            || trip.from != station
+                            || h.location != Location::Outside
+                            || !h.trans.position.is_close(entrance, BOARDING_DIST)
+                        {
+                            continue;
+                        }
+                        h.location = Location::Train(train_id);
+                        walk_inside(body, h, &cbuf);
+                        metro.passengers.push((body, trip.to));
+                    }
+                }
+                let next = (metro.next_station + 1) % line.stations.len();
+                let route = metro_platform(&map, line.stations[next]).and_then(|(lane, dist)| {
+                    Itinerary::route(
+                        tick,
+                        train.trans.position,
+                        map.lanes()[lane].points.point_along(dist),
+                        &map,
+                        train.locomotive.kind.path_kind(),
+                    )
+                });
+                let Some(route) = route else {
+                    // try again once the tunnel is fixed
+                    metro.state = MetroTrainState::Dwelling(time.timestamp + DWELL_DURATION);
+                    continue;
+                };
+                train.it = route;
+                metro.next_station = next;
+                metro.state = MetroTrainState::Running;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::BuildingKind;
+    use crate::souls::human::spawn_human;
+    use crate::tests::TestCtx;
+    use common::descriptions::{BuildingGen, DoorLayout};
+    use geom::{vec2, vec3};
+
+    #[test]
+    fn lines_need_two_stations_and_a_sane_schedule() {
+        let mut line = MetroLine {
+            name: "M1".to_string(),
+            color: Color::WHITE,
+            stations: vec![BuildingID::default()],
+            n_trains: 2,
+            headway: MIN_METRO_HEADWAY,
+        };
+        assert!(line.check().is_ok());
+        assert!(!line.is_running());
+
+        line.stations.push(BuildingID::default());
+        assert!(line.is_running());
+
+        line.headway = MIN_METRO_HEADWAY - 1.0;
+        assert!(line.check().is_err());
+    }
+
+    #[test]
+    fn long_trips_take_the_metro() {
+        let test = TestCtx::new();
+        let station = |x: f32| {
+            test.g
+                .map_mut()
+                .build_special_building(
+                    &OBB::new(vec2(x, 100.0), vec2(1.0, 0.0), 10.0, 10.0),
+                    BuildingKind::MetroStation,
+                    BuildingGen::NoWalkway {
+                        door_pos: vec2(x, 100.0),
+                        doors: DoorLayout::NONE,
+                    },
+                    None,
+                )
+                .unwrap()
+        };
+        let (a, b) = (station(0.0), station(3000.0));
+
+        let mut lines = MetroLines::default();
+        let id = lines.add(MetroLine {
+            name: "M1".to_string(),
+            color: Color::WHITE,
+            stations: vec![a, b],
+            n_trains: 1,
+            headway: MIN_METRO_HEADWAY,
+        });
+
+        let map = test.g.map();
+        let (trip, wait_at) = lines
+            .plan_trip(&map, vec3(0.0, 0.0, 0.0), vec3(3000.0, 0.0, 0.0))
+            .unwrap();
+        assert_eq!(
+            trip,
+            MetroTrip {
+                line: id,
+                from: a,
+                to: b
+            }
+        );
+        assert!(wait_at.xy().is_close(vec2(0.0, 100.0), 1.0));
+        assert!(lines
+            .plan_trip(&map, vec3(0.0, 0.0, 0.0), vec3(500.0, 0.0, 0.0))
+            .is_none());
+    }
+
+    #[test]
+    fn passengers_of_a_demolished_station_get_off_at_the_next_one() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(1000.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(500.0, 50.0));
+        let station = |x: f32| {
+            test.g
+                .map_mut()
+                .build_special_building(
+                    &OBB::new(vec2(x, 200.0), vec2(1.0, 0.0), 10.0, 10.0),
+                    BuildingKind::MetroStation,
+                    BuildingGen::NoWalkway {
+                        door_pos: vec2(x, 200.0),
+                        doors: DoorLayout::NONE,
+                    },
+                    None,
+                )
+                .unwrap()
+        };
+        let (a, b, c) = (station(0.0), station(500.0), station(1000.0));
+
+        let mut lines = MetroLines::default();
+        let id = lines.add(MetroLine {
+            name: "M1".to_string(),
+            color: Color::WHITE,
+            stations: vec![a, b, c],
+            n_trains: 1,
+            headway: MIN_METRO_HEADWAY,
+        });
+        let humans = [b, c].map(|to| {
+            let h = spawn_human(&mut test.g, house).unwrap();
+            test.g.world.humans[h].location = Location::Train(TrainID::default());
+            (h, to)
+        });
+        let mut metro = MetroTrain {
+            line: id,
+            next_station: 0,
+            state: MetroTrainState::Running,
+            passengers: humans.to_vec(),
+        };
+
+        test.g.map_mut().remove_building(b);
+        lines.prune(&test.g.map());
+        assert_eq!(lines.get(id).unwrap().stations, vec![a, c]);
+
+        let entrance = test.g.map().buildings()[a].door_pos;
+        let (world, res) = test.g.world_res();
+        let cbuf = res.read::<ParCommandBuffer<HumanEnt>>();
+        metro.let_off(
+            lines.get(id).unwrap(),
+            a,
+            entrance,
+            &mut world.humans,
+            &cbuf,
+        );
+
+        assert_eq!(metro.passengers, vec![humans[1]]);
+        assert_eq!(world.humans[humans[0].0].location, Location::Outside);
+        assert_eq!(
+            world.humans[humans[1].0].location,
+            Location::Train(TrainID::default())
+        );
+    }
+}
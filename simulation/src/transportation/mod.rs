@@ -1,13 +1,20 @@
 use crate::map::BuildingID;
 use serde::{Deserialize, Serialize};
 
+pub mod airport;
+pub mod bus;
+pub mod emergency;
+pub mod fuel;
+pub mod metro;
 pub mod pedestrian;
 pub mod road;
+pub mod road_maintenance;
 pub mod testing_vehicles;
 pub mod train;
 mod vehicle;
+pub mod waterway;
 
-use crate::world::VehicleID;
+use crate::world::{TrainID, VehicleID};
 pub use pedestrian::*;
 pub use vehicle::*;
 
@@ -16,5 +23,7 @@ pub enum Location {
     Outside,
     Vehicle(VehicleID),
     Building(BuildingID),
+    /// Riding a metro train, underground
+    Train(TrainID),
 }
 debug_inspect_impl!(Location);
@@ -1,23 +1,48 @@
+use crate::map::{Map, PathKind, Traversable, TraverseKind, Turn};
 use crate::map_dynamic::Itinerary;
-use crate::physics::{Collider, CollisionWorld, PhysicsGroup, PhysicsObject, Speed};
+use crate::physics::{
+    Collider, CollisionWorld, CollisionWorldQuery, PhysicsFilter, PhysicsGroup, PhysicsObject,
+    Speed,
+};
 use crate::utils::rand_provider::RandProvider;
 use crate::utils::resources::Resources;
 use crate::utils::time::GameTime;
-use crate::World;
+use crate::{Simulation, World};
 use egui_inspect::Inspect;
-use geom::{angle_lerpxy, Color, Transform, Vec3};
+use geom::{angle_lerpxy, Color, Transform, Vec2, Vec3, OBB};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Inspect)]
 pub struct Pedestrian {
     pub walking_speed: f32,
     pub walk_anim: f32,
+    pub mobility: Mobility,
+}
+
+/// People pushing a stroller, in a wheelchair or elderly walk slower and avoid stairs
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mobility {
+    #[default]
+    Regular,
+    Reduced,
+}
+
+debug_inspect_impl!(Mobility);
+
+impl Mobility {
+    pub fn path_kind(self) -> PathKind {
+        match self {
+            Mobility::Regular => PathKind::Pedestrian,
+            Mobility::Reduced => PathKind::StepFree,
+        }
+    }
 }
 
 const PED_SIZE: f32 = 0.5;
 
-pub fn put_pedestrian_in_coworld(coworld: &mut CollisionWorld, pos: Vec3) -> Collider {
-    Collider(coworld.insert(
+#[must_use]
+pub fn put_pedestrian_in_coworld(sim: &Simulation, pos: Vec3) -> Collider {
+    Collider(sim.write::<CollisionWorld>().insert(
         pos.xy(),
         PhysicsObject {
             radius: PED_SIZE * 0.6,
@@ -28,10 +53,23 @@ pub fn put_pedestrian_in_coworld(coworld: &mut CollisionWorld, pos: Vec3) -> Col
 }
 
 impl Pedestrian {
-    pub(crate) fn new(r: &mut RandProvider) -> Self {
+    /// `slow_fraction` is the probability of the pedestrian having a reduced mobility
+    pub(crate) fn new(r: &mut RandProvider, slow_fraction: f32) -> Self {
+        let mobility = if r.next_f32() < slow_fraction {
+            Mobility::Reduced
+        } else {
+            Mobility::Regular
+        };
+        // Sum of two uniforms so that most people walk at an average speed
+        let spread = r.next_f32() + r.next_f32();
+        let walking_speed = match mobility {
+            Mobility::Regular => 0.9 + spread * 0.35,
+            Mobility::Reduced => 0.5 + spread * 0.15,
+        };
         Self {
-            walking_speed: (0.8 + r.next_f32() * 0.8),
+            walking_speed,
             walk_anim: 0.0,
+            mobility,
         }
     }
 }
@@ -63,20 +101,27 @@ pub fn random_pedestrian_shirt_color(r: &mut RandProvider) -> Color {
 pub fn pedestrian_decision_system(world: &mut World, resources: &mut Resources) {
     profiling::scope!("transportation::pedestrian_decision_system");
     let ra = &*resources.read();
+    let rb = &*resources.read();
+    let rc = &*resources.read();
     world.humans
         .values_mut()
         //.par_bridge()
-        .for_each(|human| pedestrian_decision(ra, &mut human.it, &mut human.trans, &mut human.speed, &mut human.pedestrian))
+        .for_each(|human| pedestrian_decision(ra, rb, rc, &mut human.it, &mut human.trans, &mut human.speed, &mut human.pedestrian))
 }
 
 pub fn pedestrian_decision(
     time: &GameTime,
+    map: &Map,
+    cow: &CollisionWorld,
     it: &mut Itinerary,
     trans: &mut Transform,
     kin: &mut Speed,
     pedestrian: &mut Pedestrian,
 ) {
-    let (desired_v, desired_dir) = calc_decision(pedestrian, trans, it);
+    let (mut desired_v, desired_dir) = calc_decision(pedestrian, trans, it);
+    if waits_at_crosswalk(map, cow, it, trans.position, pedestrian.walking_speed) {
+        desired_v = 0.0;
+    }
 
     pedestrian.walk_anim += 7.0 * kin.0 * time.realdelta / pedestrian.walking_speed;
     pedestrian.walk_anim %= 2.0 * std::f32::consts::PI;
@@ -84,6 +129,7 @@ pub fn pedestrian_decision(
 }
 
 const PEDESTRIAN_ACC: f32 = 1.5;
+const PEDESTRIAN_DEC: f32 = 4.0;
 
 pub fn physics(
     kin: &mut Speed,
@@ -93,10 +139,10 @@ pub fn physics(
     desired_dir: Vec3,
 ) {
     let diff = desired_velocity - kin.0;
-    let mag = diff.min(time.realdelta * PEDESTRIAN_ACC);
-    if mag > 0.0 {
-        kin.0 += mag;
-    }
+    kin.0 += diff.clamp(
+        -time.realdelta * PEDESTRIAN_DEC,
+        time.realdelta * PEDESTRIAN_ACC,
+    );
     const ANG_VEL: f32 = 1.0;
     trans.dir = angle_lerpxy(trans.dir, desired_dir, ANG_VEL * time.realdelta);
 }
@@ -122,3 +168,116 @@ pub fn calc_decision(
     let desired_dir = dir_to_pos.normalize();
     (pedestrian.walking_speed, desired_dir)
 }
+
+/// Pedestrians closer than this to a crosswalk look for a gap in the traffic before crossing
+const CROSSWALK_LOOK_DIST: f32 = 1.0;
+/// How far pedestrians look for vehicles coming towards a crosswalk
+const CROSSWALK_LOOK_AHEAD: f32 = 60.0;
+/// Time left between the end of the crossing and the arrival of the next vehicle
+const CROSSING_MARGIN: f32 = 2.0;
+
+/// Whether the pedestrian is about to enter a crosswalk without lights and must wait for a gap
+/// in the traffic long enough to cross
+fn waits_at_crosswalk(
+    map: &Map,
+    cow: &CollisionWorld,
+    it: &Itinerary,
+    position: Vec3,
+    walking_speed: f32,
+) -> bool {
+    let Some(&Traversable {
+        kind: TraverseKind::Turn(id),
+        ..
+    }) = it.next_travers()
+    else {
+        return false;
+    };
+    let Some(turn) = map
+        .intersections()
+        .get(id.parent)
+        .and_then(|i| i.find_turn(id))
+    else {
+        return false;
+    };
+    if !turn.kind.is_crosswalk() {
+        return false;
+    }
+    let (a, b) = (turn.points.first(), turn.points.last());
+    if !a.is_close(position, CROSSWALK_LOOK_DIST) && !b.is_close(position, CROSSWALK_LOOK_DIST) {
+        return false;
+    }
+    if turn.control.is_light() {
+        // the itinerary waits for the walk signal, see Traversable::can_enter
+        return false;
+    }
+    !accepts_gap(cow, turn, walking_speed)
+}
+
+/// Gap acceptance: no moving vehicle reaches the crosswalk before the pedestrian crossed it.
+/// Vehicles stopped to let pedestrians through don't count.
+fn accepts_gap(cow: &CollisionWorld, crosswalk: &Turn, walking_speed: f32) -> bool {
+    let (a, b) = (crosswalk.points.first(), crosswalk.points.last());
+    let center = (a + b) * 0.5;
+    let half_len = a.distance(b) * 0.5;
+    let crossing_time = 2.0 * half_len / walking_speed.max(0.1) + CROSSING_MARGIN;
+
+    let size = 2.0 * CROSSWALK_LOOK_AHEAD;
+    let around = OBB::new(center.xy(), Vec2::X, size, size);
+    for (_, pos, obj) in cow.query_obb(around, PhysicsFilter::VEHICLES) {
+        if obj.speed < 0.5 || (obj.height - center.z).abs() > 5.0 {
+            continue;
+        }
+        let towards: Vec2 = center.xy() - pos;
+        let along = towards.dot(obj.dir);
+        if along < -obj.radius || towards.perp_dot(obj.dir).abs() > half_len + obj.radius {
+            // going away or passing next to the crosswalk
+            continue;
+        }
+        if along.max(0.0) / obj.speed < crossing_time {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{TurnID, TurnKind};
+    use geom::{vec3, PolyLine3};
+
+    #[test]
+    fn pedestrians_cross_in_gaps() {
+        let mut crosswalk = Turn::new(
+            TurnID::new(
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                false,
+            ),
+            TurnKind::Crosswalk,
+        );
+        crosswalk.points = PolyLine3::new(vec![vec3(0.0, -5.0, 0.0), vec3(0.0, 5.0, 0.0)]);
+
+        let car = |speed, dir| PhysicsObject {
+            dir,
+            speed,
+            radius: 2.0,
+            group: PhysicsGroup::Vehicles,
+            ..Default::default()
+        };
+
+        let mut cow = CollisionWorld::new(10);
+        assert!(accepts_gap(&cow, &crosswalk, 1.2));
+
+        // a car stopped to let pedestrians through
+        let stopped = cow.insert(Vec2::new(-5.0, 0.0), car(0.0, Vec2::X));
+        // a car that already passed the crosswalk
+        cow.insert(Vec2::new(10.0, 0.0), car(10.0, Vec2::X));
+        assert!(accepts_gap(&cow, &crosswalk, 1.2));
+
+        cow.remove_maintain(stopped);
+        cow.insert(Vec2::new(-30.0, 0.0), car(10.0, Vec2::X));
+        assert!(!accepts_gap(&cow, &crosswalk, 1.2));
+    }
+}
@@ -1,14 +1,24 @@
-use crate::map::{Map, TrafficBehavior, Traversable, TraverseKind};
-use crate::map_dynamic::{Itinerary, OBJECTIVE_OK_DIST};
+use crate::map::{
+    FurnitureKind, Intersection, IntersectionID, Lane, LaneKind, Map, RoadID, TrafficBehavior,
+    Traversable, TraverseKind, Turn, CROSSWALK_WIDTH, QUEUE_JUMP,
+};
+use crate::map_dynamic::{Itinerary, Policies, OBJECTIVE_OK_DIST};
 use crate::physics::Speed;
-use crate::physics::{Collider, CollisionWorld, PhysicsGroup, PhysicsObject};
-use crate::transportation::{Vehicle, VehicleState, TIME_TO_PARK};
+use crate::physics::{
+    Collider, CollisionWorld, CollisionWorldQuery, PhysicsFilter, PhysicsGroup, PhysicsObject,
+};
+use crate::transportation::fuel::RESERVE_SPEED;
+use crate::transportation::road_maintenance::RoadMaintenance;
+use crate::transportation::{
+    repark_vehicle, Vehicle, VehicleKind, VehicleState, MERGE_WAIT_T, PULL_OVER_OFFSET,
+    TIME_TO_MERGE, TIME_TO_PARK,
+};
 use crate::utils::resources::Resources;
 use crate::utils::time::GameTime;
 use crate::world::{VehicleEnt, VehicleID};
-use crate::ParCommandBuffer;
 use crate::World;
-use geom::{angle_lerpxy, Ray, Transform, Vec2, Vec3};
+use crate::{ParCommandBuffer, SimulationOptions};
+use geom::{angle_lerpxy, Radians, Ray, Transform, Vec2, Vec3, OBB};
 use slotmapd::Key;
 
 pub fn vehicle_decision_system(world: &mut World, resources: &mut Resources) {
@@ -16,6 +26,9 @@ pub fn vehicle_decision_system(world: &mut World, resources: &mut Resources) {
     let ra = &*resources.read();
     let rb = &*resources.read();
     let rc = &*resources.read();
+    let rd = &*resources.read();
+    let re = &*resources.read();
+    let rf = &*resources.read();
 
     world.vehicles.iter_mut().for_each(|(ent, v)| {
         let Some(ref coll) = v.collider else {
@@ -26,6 +39,9 @@ pub fn vehicle_decision_system(world: &mut World, resources: &mut Resources) {
             ra,
             rb,
             rc,
+            rd,
+            re,
+            rf,
             ent,
             &mut v.it,
             &mut v.trans,
@@ -40,6 +56,9 @@ pub fn vehicle_decision(
     map: &Map,
     time: &GameTime,
     cow: &CollisionWorld,
+    maintenance: &RoadMaintenance,
+    policies: &Policies,
+    options: &SimulationOptions,
     me: VehicleID,
     it: &mut Itinerary,
     trans: &mut Transform,
@@ -51,6 +70,7 @@ pub fn vehicle_decision(
 
     let mut desired_speed = 0.0;
     let mut desired_dir = Vec3::ZERO;
+    let mut yielding = false;
     if matches!(
         vehicle.state,
         VehicleState::Driving | VehicleState::Panicking(_)
@@ -61,11 +81,36 @@ pub fn vehicle_decision(
         let objs =
             neighbors.map(|(id, pos)| (pos, cow.get(id).expect("Handle not in collision world").1));
 
-        let (s, d) = calc_decision(me, vehicle, map, time, trans, self_obj, it, objs);
+        let (s, d) = calc_decision(
+            me,
+            vehicle,
+            map,
+            cow,
+            maintenance,
+            policies,
+            options,
+            time,
+            trans,
+            self_obj,
+            it,
+            objs,
+        );
         desired_speed = s;
         desired_dir = d;
+
+        yielding = !vehicle.siren && siren_behind(cow, collider, trans);
+        if yielding {
+            desired_speed = desired_speed.min(PULL_OVER_SPEED);
+        }
     }
 
+    let target = if yielding { PULL_OVER_OFFSET } else { 0.0 };
+    let max_shift = PULL_OVER_RATE * time.realdelta;
+    vehicle.pull_over += (target - vehicle.pull_over).clamp(-max_shift, max_shift);
+
+    let old_dir = trans.dir;
+    let old_speed = kin.0;
+
     physics(
         trans,
         kin,
@@ -76,6 +121,17 @@ pub fn vehicle_decision(
         desired_speed,
         desired_dir,
     );
+
+    let roughness = match it.get_travers().map(|t| t.kind) {
+        Some(TraverseKind::Lane(l)) => map
+            .lanes()
+            .get(l)
+            .map_or(0.0, |l| 1.0 - maintenance.condition(l.parent)),
+        _ => 0.0,
+    };
+    vehicle
+        .visual
+        .update(time.realdelta, old_dir, old_speed, trans, kin.0, roughness);
 }
 
 pub fn vehicle_state_update_system(world: &mut World, resources: &mut Resources) {
@@ -83,12 +139,14 @@ pub fn vehicle_state_update_system(world: &mut World, resources: &mut Resources)
     let ra = &*resources.read();
     let rb = &*resources.read();
     let rc = &*resources.read();
+    let rd = &*resources.read();
 
     world.vehicles.iter_mut().for_each(|(ent, v)| {
         vehicle_state_update(
             ra,
             rb,
             rc,
+            rd,
             ent,
             &mut v.vehicle,
             &mut v.trans,
@@ -103,6 +161,7 @@ pub fn vehicle_state_update(
     buf: &ParCommandBuffer<VehicleEnt>,
     time: &GameTime,
     map: &Map,
+    cow: &CollisionWorld,
     ent: VehicleID,
     vehicle: &mut Vehicle,
     trans: &mut Transform,
@@ -127,19 +186,89 @@ pub fn vehicle_state_update(
                 vehicle.state = VehicleState::Parked(spot);
             }
         }
+        VehicleState::Merging(spline, ref mut t) => {
+            // Vehicle is on rails when merging, it stops at the edge of the lane until there's a gap
+            let next = *t + time.realdelta / TIME_TO_MERGE;
+            if *t < MERGE_WAIT_T
+                && next >= MERGE_WAIT_T
+                && !merge_gap(cow, coll.as_ref(), spline.to)
+            {
+                return;
+            }
+            *t = next;
+
+            if *t >= 1.0 {
+                kin.0 = 0.0;
+                vehicle.state = VehicleState::Driving;
+            }
+        }
         VehicleState::Parked(ref spot) => {
             if let Some(p) = spot.get(&map.parking) {
                 if p.trans != *trans {
                     *trans = p.trans;
                 }
             } else {
-                buf.kill(ent);
+                buf.exec_ent(ent, move |sim| repark_vehicle(sim, ent));
             }
         }
         _ => {}
     }
 }
 
+/// Sirens are heard this far, in meters
+const SIREN_RANGE: f32 = 50.0;
+/// How fast vehicles move to the side when pulling over, in m/s
+const PULL_OVER_RATE: f32 = 1.0;
+/// Vehicles pulled over crawl until the emergency vehicle passed
+const PULL_OVER_SPEED: f32 = 2.0;
+/// Emergency vehicles drive faster than the speed limit
+const SIREN_SPEED_FACTOR: f32 = 1.3;
+/// Emergency vehicles slow down to this speed to go through red lights and stop signs
+const SIREN_CROSSING_SPEED: f32 = 5.0;
+
+/// Whether an emergency vehicle with its siren on comes up behind, in the same direction
+fn siren_behind(cow: &CollisionWorld, coll: &Collider, trans: &Transform) -> bool {
+    let pos = trans.position.xy();
+    let dir = trans.dir.xy();
+    cow.query_around(pos, SIREN_RANGE).any(|(id, his_pos)| {
+        if coll.0 == id {
+            return false;
+        }
+        let Some((_, obj)) = cow.get(id) else {
+            return false;
+        };
+        if !obj.siren || (obj.height - trans.position.z).abs() > 5.0 {
+            return false;
+        }
+        let towards = his_pos - pos;
+        towards.dot(dir) < 0.0 && obj.dir.dot(dir) > 0.7 && towards.perp_dot(dir).abs() < 6.0
+    })
+}
+
+/// Whether a merging vehicle can enter the lane at `at` without cutting off traffic
+fn merge_gap(cow: &CollisionWorld, coll: Option<&Collider>, at: Vec3) -> bool {
+    const CLEARANCE: f32 = 6.0;
+    const HEADWAY: f32 = 3.0;
+
+    cow.query_around(at.xy(), 40.0).all(|(id, pos)| {
+        if coll.map_or(false, |c| c.0 == id) {
+            return true;
+        }
+        let Some((_, obj)) = cow.get(id) else {
+            return true;
+        };
+        if !matches!(obj.group, PhysicsGroup::Vehicles) || obj.speed < 0.5 {
+            return true;
+        }
+        let to_merge = at.xy() - pos;
+        let dist = to_merge.mag();
+        if dist < CLEARANCE {
+            return false;
+        }
+        obj.dir.dot(to_merge) <= 0.0 || dist >= CLEARANCE + obj.speed * HEADWAY
+    })
+}
+
 /// Handles actually moving the vehicles around, including acceleration and other physics stuff.
 fn physics(
     trans: &mut Transform,
@@ -157,11 +286,15 @@ fn physics(
             *trans = spot.trans;
             return;
         }
-        VehicleState::RoadToPark(spline, t, _) => {
+        VehicleState::RoadToPark(spline, t, _) | VehicleState::Merging(spline, t) => {
             trans.position = spline.get(t);
             trans.dir = spline.derivative(t).normalize();
             return;
         }
+        VehicleState::DoubleParked(_) => {
+            kin.0 = 0.0;
+            return;
+        }
         _ => {}
     }
 
@@ -193,11 +326,70 @@ fn physics(
     kin.0 = speed;
 }
 
+/// Followers closer than this speed difference to their leader form a platoon
+const PLATOON_MAX_SPEED_DIFF: f32 = 1.0;
+/// Platoons only form when moving, stopped vehicles keep their minimum gap
+const PLATOON_MIN_SPEED: f32 = 5.0;
+/// Vehicles in a platoon follow each other closer
+const PLATOON_HEADWAY_FACTOR: f32 = 0.7;
+
+/// The closest obstacle in front of a vehicle
+#[derive(Debug, Copy, Clone)]
+struct Leader {
+    dist: f32,
+    /// Speed of the obstacle along our direction
+    speed: f32,
+    flag: u64,
+    is_vehicle: bool,
+}
+
+impl Leader {
+    const NONE: Leader = Leader {
+        dist: f32::INFINITY,
+        speed: 0.0,
+        flag: 0,
+        is_vehicle: false,
+    };
+}
+
+/// Intelligent Driver Model (https://en.wikipedia.org/wiki/Intelligent_driver_model):
+/// accelerates towards the desired speed while keeping a safe time gap with the leader.
+/// Unlike braking only when the leader is within stopping distance, the slow down is progressive
+/// which prevents stop-and-go waves from forming.
+fn idm_acceleration(kind: VehicleKind, speed: f32, desired_speed: f32, leader: &Leader) -> f32 {
+    let a = kind.acceleration();
+    let b = kind.comfortable_deceleration();
+
+    let approach = speed - leader.speed;
+    let mut headway = kind.time_headway();
+    if leader.is_vehicle
+        && leader.speed > PLATOON_MIN_SPEED
+        && approach.abs() < PLATOON_MAX_SPEED_DIFF
+    {
+        headway *= PLATOON_HEADWAY_FACTOR;
+    }
+
+    let free_road = if desired_speed > 0.0 {
+        1.0 - (speed / desired_speed).powi(4)
+    } else {
+        -1.0
+    };
+    let desired_gap =
+        kind.min_gap() + (speed * headway + speed * approach / (2.0 * (a * b).sqrt())).max(0.0);
+    let interaction = (desired_gap / leader.dist.max(0.1)).powi(2);
+
+    (a * (free_road - interaction)).clamp(-kind.deceleration(), a)
+}
+
 /// Decide the appropriate velocity and direction to aim for.
 pub fn calc_decision<'a>(
     me: VehicleID,
     vehicle: &mut Vehicle,
     map: &Map,
+    cow: &CollisionWorld,
+    maintenance: &RoadMaintenance,
+    policies: &Policies,
+    options: &SimulationOptions,
     time: &GameTime,
     trans: &Transform,
     self_obj: &PhysicsObject,
@@ -217,7 +409,8 @@ pub fn calc_decision<'a>(
 
     let cutoff = (0.8 + stop_dist).min(1.5);
 
-    let (front_dist, flag) = calc_front_dist(vehicle, trans, self_obj, it, neighs, cutoff);
+    let mut leader = calc_front_dist(vehicle, trans, self_obj, it, neighs, cutoff);
+    let front_dist = leader.dist;
 
     let position = trans.position;
     let dir_to_pos = unwrap_or!(
@@ -229,22 +422,23 @@ pub fn calc_decision<'a>(
         if since.elapsed(time) > 5.0 {
             vehicle.state = VehicleState::Driving;
         }
-    } else if speed.abs() < 0.2 && front_dist < 1.5 {
+    } else if speed.abs() < 0.2 && front_dist < vehicle.kind.min_gap() + 0.5 {
         let me_u64: u64 = me.data().as_ffi();
-        if me_u64 == flag {
+        if me_u64 == leader.flag {
             vehicle.state = VehicleState::Panicking(time.instant());
             log::info!("gridlock!")
         }
-        vehicle.flag = if vehicle.flag | flag == 0 {
+        vehicle.flag = if vehicle.flag | leader.flag == 0 {
             me_u64
         } else {
-            flag
+            leader.flag
         };
         vehicle.wait_time = (position.x * 1000.0).fract().abs() * 0.5;
         return default_return;
     } else {
-        // Stop at 80 cm of object in front
-        if front_dist < 0.8 + stop_dist {
+        // Emergency stop when we cannot avoid getting within 80 cm of the object in front
+        let approach = (speed - leader.speed).max(0.0);
+        if front_dist < 0.8 + approach * approach / (2.0 * vehicle.kind.deceleration()) {
             return (0.0, dir_to_pos);
         }
     }
@@ -264,12 +458,55 @@ pub fn calc_decision<'a>(
         ..
     }) = it.get_travers()
     {
+        if vehicle.halted_at.map_or(false, |h| h != *l_id) {
+            vehicle.halted_at = None;
+        }
         if let Some(l) = map.lanes().get(*l_id) {
-            speed = l.speed_limit;
+            speed = l.speed_limit
+                * maintenance.speed_factor(l.parent)
+                * policies.speed_factor(position.xy());
+            if vehicle.siren {
+                speed *= SIREN_SPEED_FACTOR;
+            }
+
+            if let Some(limit) = furniture_speed(map, l, position) {
+                speed = speed.min(limit);
+            }
+            if let Some(dist) = stop_sign_ahead(map, l, vehicle, self_obj, position) {
+                if dist < leader.dist {
+                    leader = Leader {
+                        dist,
+                        ..Leader::NONE
+                    };
+                }
+            }
 
             let light = l.control_point();
 
-            match l.control.get_behavior(time.seconds) {
+            if light.is_close(position, YIELD_REACH)
+                && (pedestrians_in_crosswalk(map, cow, l.dst, l.parent)
+                    || (pedestrians_at_crosswalk(map, cow, l)
+                        && yields_to_pedestrians(me, options)))
+            {
+                if light.is_close(position, CROSSWALK_STOP_GAP + stop_dist) {
+                    return (0.0, dir_to_pos);
+                }
+                speed = speed.min(YIELD_SPEED);
+            }
+
+            let mut behavior = l.control.get_behavior(time.seconds);
+            if behavior.is_green() && transit_jumps_queue(map, cow, l, time.seconds) {
+                behavior = TrafficBehavior::RED;
+            }
+
+            match behavior {
+                TrafficBehavior::RED | TrafficBehavior::ORANGE | TrafficBehavior::STOP
+                    if vehicle.siren =>
+                {
+                    if light.is_close(position, YIELD_REACH) {
+                        speed = speed.min(SIREN_CROSSING_SPEED);
+                    }
+                }
                 TrafficBehavior::RED | TrafficBehavior::ORANGE => {
                     if light.is_close(
                         position,
@@ -282,11 +519,28 @@ pub fn calc_decision<'a>(
                     }
                 }
                 TrafficBehavior::STOP => {
-                    if light.is_close_signed(position, stop_dist) && !light.is_close(position, 1.0)
-                    {
+                    if vehicle.halted_at != Some(*l_id) {
+                        if light.is_close(position, STOP_LINE_REACH) && self_obj.speed < HALT_SPEED
+                        {
+                            vehicle.halted_at = Some(*l_id);
+                        } else if light.is_close(position, stop_dist + STOP_LINE_REACH) {
+                            return (0.0, dir_to_pos);
+                        }
+                    }
+                    if vehicle.halted_at == Some(*l_id) && priority_traffic_coming(map, cow, l) {
                         return (0.0, dir_to_pos);
                     }
                 }
+                TrafficBehavior::YIELD => {
+                    if light.is_close_signed(position, YIELD_REACH) {
+                        speed = speed.min(YIELD_SPEED);
+                        if light.is_close(position, stop_dist + STOP_LINE_REACH)
+                            && priority_traffic_coming(map, cow, l)
+                        {
+                            return (0.0, dir_to_pos);
+                        }
+                    }
+                }
                 TrafficBehavior::GREEN => {
                     if light.is_close(position, stop_dist * 0.4) {
                         return (0.0, dir_to_pos);
@@ -296,15 +550,325 @@ pub fn calc_decision<'a>(
         }
     }
 
-    // Not facing the objective
-    if dir_to_pos.dot(trans.dir) < 0.8 {
-        return (6.0, dir_to_pos);
+    if let Some(Traversable {
+        kind: TraverseKind::Turn(id),
+        ..
+    }) = it.get_travers()
+    {
+        // turning vehicles let the pedestrians crossing the road they turn into finish
+        let end = map
+            .intersections()
+            .get(id.parent)
+            .and_then(|i| i.find_turn(*id))
+            .map(|t| t.points.last());
+        if let (Some(end), Some(dst)) = (end, map.lanes().get(id.dst)) {
+            let dist = end.distance(position);
+            if dist > (vehicle.kind.width() + CROSSWALK_WIDTH) * 0.5
+                && dist < CROSSWALK_WIDTH + CROSSWALK_STOP_GAP + stop_dist
+                && pedestrians_in_crosswalk(map, cow, id.parent, dst.parent)
+            {
+                return (0.0, dir_to_pos);
+            }
+        }
+    }
+
+    if vehicle.fuel <= 0.0 {
+        speed = speed.min(RESERVE_SPEED);
     }
 
-    (
-        vehicle.kind.speed_factor() * vehicle.max_speed_multiplier * speed,
-        dir_to_pos,
-    )
+    let desired_speed = if dir_to_pos.dot(trans.dir) < 0.8 {
+        // Not facing the objective
+        6.0
+    } else {
+        vehicle.kind.speed_factor() * vehicle.max_speed_multiplier * speed
+    };
+
+    let acc = idm_acceleration(vehicle.kind, self_obj.speed, desired_speed, &leader);
+    ((self_obj.speed + acc * time.realdelta).max(0.0), dir_to_pos)
+}
+
+/// Speed bumps closer than this slow the vehicles down
+const SPEED_BUMP_REACH: f32 = 15.0;
+const SPEED_BUMP_SPEED: f32 = 3.0;
+/// Vehicles slow down before a yield sign to look around
+const YIELD_REACH: f32 = 20.0;
+const YIELD_SPEED: f32 = 4.0;
+/// Vehicles stopping at a sign wait this close to the end of their lane
+const STOP_LINE_REACH: f32 = 2.0;
+/// Vehicles slower than this came to a halt at the stop sign
+const HALT_SPEED: f32 = 0.3;
+/// Buses and trams this close to the light are given a head start over the other lanes
+const QUEUE_JUMP_REACH: f32 = 15.0;
+/// Vehicles this close to the intersection on a priority road are given way to
+const PRIORITY_REACH: f32 = 35.0;
+/// Vehicles circulating on a roundabout this close to an entry along the ring are given way to
+const ROUNDABOUT_REACH: f32 = 20.0;
+
+/// Pedestrians closer than this to a crosswalk are waiting to cross it or crossing it
+const CROSSWALK_WAIT_DIST: f32 = 2.5;
+/// Vehicles yielding to pedestrians stop this far before the crosswalk
+const CROSSWALK_STOP_GAP: f32 = 2.0;
+
+/// Whether pedestrians wait at or cross the crosswalk without walk signal at the end of the lane
+fn pedestrians_at_crosswalk(map: &Map, cow: &CollisionWorld, l: &Lane) -> bool {
+    let Some(inter) = map.intersections().get(l.dst) else {
+        return false;
+    };
+    let Some(crosswalk) = inter.turns().find(|t| {
+        t.kind.is_crosswalk()
+            && t.control.is_always()
+            && map.lanes().get(t.id.src).map(|x| x.parent) == Some(l.parent)
+    }) else {
+        return false;
+    };
+    pedestrians_on(cow, crosswalk, true)
+}
+
+/// Whether pedestrians are crossing the road at the intersection. Drivers must let them finish
+/// whatever the signals say, be it on their way through or turning into the road.
+fn pedestrians_in_crosswalk(
+    map: &Map,
+    cow: &CollisionWorld,
+    inter: IntersectionID,
+    road: RoadID,
+) -> bool {
+    let Some(inter) = map.intersections().get(inter) else {
+        return false;
+    };
+    let lanes = map.lanes();
+    inter
+        .turns()
+        .filter(|t| t.kind.is_crosswalk() && lanes.get(t.id.src).map(|x| x.parent) == Some(road))
+        .any(|t| pedestrians_on(cow, t, false))
+}
+
+/// Whether pedestrians are on the part of the crosswalk over the road, or also waiting at its
+/// ends if `waiting`
+fn pedestrians_on(cow: &CollisionWorld, crosswalk: &Turn, waiting: bool) -> bool {
+    let (a, b) = (crosswalk.points.first(), crosswalk.points.last());
+    let Some(dir) = (b.xy() - a.xy()).try_normalize() else {
+        return false;
+    };
+    let len = a.xy().distance(b.xy());
+    let center = (a.xy() + b.xy()) * 0.5;
+    let obb = if waiting {
+        OBB::new(
+            center,
+            dir,
+            len + 2.0 * CROSSWALK_WAIT_DIST,
+            2.0 * CROSSWALK_WAIT_DIST,
+        )
+    } else {
+        let curb = LaneKind::Walking.width() * 0.5;
+        OBB::new(
+            center,
+            dir,
+            (len - 2.0 * curb).max(0.0),
+            2.0 * CROSSWALK_WIDTH,
+        )
+    };
+    cow.query_obb(obb, PhysicsFilter::PEDESTRIANS)
+        .any(|(_, _, obj)| (obj.height - a.z).abs() < 5.0)
+}
+
+/// Whether the lane stays red a moment after its light turned green, to give the bus or tram
+/// waiting at the end of a transit lane of the same road a head start
+fn transit_jumps_queue(map: &Map, cow: &CollisionWorld, l: &Lane, seconds: u32) -> bool {
+    if l.kind.transit_priority()
+        || l.control
+            .green_since(seconds)
+            .map_or(true, |t| t >= QUEUE_JUMP)
+    {
+        return false;
+    }
+    let Some(road) = map.roads().get(l.parent) else {
+        return false;
+    };
+    let lanes = map.lanes();
+    road.incoming_lanes_to(l.dst)
+        .iter()
+        .filter(|(_, kind)| kind.transit_priority())
+        .filter_map(|&(id, _)| lanes.get(id))
+        .any(|tl| {
+            let end = tl.control_point();
+            let start = tl
+                .points
+                .point_along((tl.points.length() - QUEUE_JUMP_REACH).max(0.0));
+            let Some(dir) = (end.xy() - start.xy()).try_normalize() else {
+                return false;
+            };
+            let obb = OBB::new(
+                (start.xy() + end.xy()) * 0.5,
+                dir,
+                start.xy().distance(end.xy()),
+                tl.kind.width(),
+            );
+            cow.query_obb(obb, PhysicsFilter::VEHICLES)
+                .any(|(_, _, obj)| (obj.height - end.z).abs() <= 5.0 && obj.dir.dot(dir) > 0.7)
+        })
+}
+
+/// Whether vehicles drive towards the intersection at the end of the lane on the roads that
+/// have the priority over it, close enough that pulling out in front of them is unsafe
+fn priority_traffic_coming(map: &Map, cow: &CollisionWorld, l: &Lane) -> bool {
+    let Some(inter) = map.intersections().get(l.dst) else {
+        return false;
+    };
+    if inter.is_roundabout() {
+        return roundabout_traffic_coming(inter, cow, l);
+    }
+    let lanes = map.lanes();
+    inter
+        .roads
+        .iter()
+        .filter(|&&r| r != l.parent)
+        .filter_map(|&r| map.roads().get(r))
+        .flat_map(|r| r.incoming_lanes_to(inter.id))
+        .filter_map(|&(id, _)| lanes.get(id))
+        .filter(|pl| pl.kind.vehicles() && pl.control.is_always())
+        .any(|pl| {
+            let end = pl.control_point();
+            let start = pl
+                .points
+                .point_along((pl.points.length() - PRIORITY_REACH).max(0.0));
+            let Some(dir) = (end.xy() - start.xy()).try_normalize() else {
+                return false;
+            };
+            let obb = OBB::new(
+                (start.xy() + end.xy()) * 0.5,
+                dir,
+                start.xy().distance(end.xy()),
+                4.0,
+            );
+            cow.query_obb(obb, PhysicsFilter::VEHICLES)
+                .any(|(_, _, obj)| {
+                    obj.speed >= HALT_SPEED
+                        && (obj.height - end.z).abs() <= 5.0
+                        && obj.dir.dot(dir) > 0.7
+                })
+        })
+}
+
+/// Whether vehicles circulating on the roundabout drive towards the entry at the end of the lane.
+/// The ring has the priority, it turns counter-clockwise like the roundabout turns
+fn roundabout_traffic_coming(inter: &Intersection, cow: &CollisionWorld, l: &Lane) -> bool {
+    let Some(rp) = inter.turn_policy.roundabout else {
+        return false;
+    };
+    let center = inter.pos.xy();
+    let end = l.control_point();
+    let Some(entry) = (end.xy() - center).try_normalize() else {
+        return false;
+    };
+    // the turns join the ring a bit after the lane, see Turn::gen_roundabout
+    let entry = entry.rotated_by_angle(Radians::from_deg(10.0));
+    let size = 2.0 * rp.radius;
+    let obb = OBB::new(center, Vec2::X, size, size);
+    cow.query_obb(obb, PhysicsFilter::VEHICLES)
+        .any(|(_, pos, obj)| {
+            let Some(radial) = (pos - center).try_normalize() else {
+                return false;
+            };
+            // distance along the ring to the entry, negative once the vehicle passed it
+            let ahead = radial.angle(entry) * pos.distance(center);
+            obj.speed >= HALT_SPEED
+                && (obj.height - end.z).abs() <= 5.0
+                && obj.dir.dot(-radial.perpendicular()) > 0.5
+                && ahead > -obj.radius
+                && ahead < ROUNDABOUT_REACH
+        })
+}
+
+/// Whether the driver stops for the pedestrians waiting at crosswalks without walk signal,
+/// see [`SimulationOptions::crosswalk_yield_rate`]
+fn yields_to_pedestrians(me: VehicleID, options: &SimulationOptions) -> bool {
+    let draw = common::hash_u64(me.data().as_ffi()) % 1000;
+    (draw as f32) < options.crosswalk_yield_rate * 1000.0
+}
+
+/// The speed limit imposed by the road furniture in front of the vehicle on its lane
+fn furniture_speed(map: &Map, l: &Lane, position: Vec3) -> Option<f32> {
+    let road = map.roads().get(l.parent)?;
+    if road.furniture.is_empty() {
+        return None;
+    }
+    let side = road.side_of(l);
+    let my_dist = l.points.length_at_proj(l.points.project(position));
+
+    let mut limit: Option<f32> = None;
+    for f in road.furniture.items() {
+        if !f.applies_to(side) {
+            continue;
+        }
+        let f_pos = l.points.project(road.points.point_along(f.dist));
+        let ahead = l.points.length_at_proj(f_pos) - my_dist;
+
+        let v = match f.kind {
+            FurnitureKind::SpeedBump => {
+                if ahead.abs() > SPEED_BUMP_REACH {
+                    continue;
+                }
+                SPEED_BUMP_SPEED
+            }
+            FurnitureKind::StopSign => continue,
+            FurnitureKind::YieldSign => {
+                if ahead < 0.0 || ahead > YIELD_REACH {
+                    continue;
+                }
+                YIELD_SPEED
+            }
+        };
+        limit = Some(limit.map_or(v, |x| x.min(v)));
+    }
+    limit
+}
+
+/// Distance from the front of the vehicle to the stop sign in front of it on its lane.
+/// The vehicle brakes for the sign like for a stopped vehicle, once it came to a halt
+/// there it goes on.
+fn stop_sign_ahead(
+    map: &Map,
+    l: &Lane,
+    vehicle: &mut Vehicle,
+    self_obj: &PhysicsObject,
+    position: Vec3,
+) -> Option<f32> {
+    let road = map.roads().get(l.parent)?;
+    if road.furniture.is_empty() {
+        return None;
+    }
+    let side = road.side_of(l);
+    let my_dist = l.points.length_at_proj(l.points.project(position));
+    let reach = self_obj.radius + vehicle.kind.min_gap() + STOP_LINE_REACH;
+
+    let mut closest: Option<f32> = None;
+    for f in road.furniture.items() {
+        if f.kind != FurnitureKind::StopSign || !f.applies_to(side) {
+            continue;
+        }
+        let f_pos = l.points.project(road.points.point_along(f.dist));
+        let ahead = l.points.length_at_proj(f_pos) - my_dist;
+
+        if ahead < 0.0 {
+            // the vehicle halted at this sign, the end of the lane may have a stop of its own
+            if ahead > -reach && vehicle.halted_at == Some(l.id) {
+                vehicle.halted_at = None;
+            }
+            continue;
+        }
+        if ahead < reach {
+            if vehicle.halted_at == Some(l.id) {
+                continue;
+            }
+            if self_obj.speed < HALT_SPEED {
+                vehicle.halted_at = Some(l.id);
+                continue;
+            }
+        }
+        let dist = (ahead - self_obj.radius).max(0.0);
+        closest = Some(closest.map_or(dist, |x| x.min(dist)));
+    }
+    closest
 }
 
 /// Calculates the distance to the closest problematic object in front of the car.
@@ -317,13 +881,13 @@ fn calc_front_dist<'a>(
     it: &Itinerary,
     neighs: impl Iterator<Item = (Vec2, &'a PhysicsObject)>,
     cutoff: f32,
-) -> (f32, u64) {
+) -> Leader {
     let position = trans.position;
     let direction = trans.dir;
     let pos2 = position.xy();
     let dir2 = trans.dir.xy();
 
-    let mut min_front_dist: f32 = 50.0;
+    let mut leader = Leader::NONE;
 
     let my_ray = Ray {
         from: position.xy() - direction.xy() * vehicle.kind.width() * 0.5,
@@ -334,12 +898,15 @@ fn calc_front_dist<'a>(
     let speed = self_obj.speed;
 
     let on_lane = it.get_travers().map_or(false, |t| t.kind.is_lane());
-    let mut flag = 0;
     // Collision avoidance
     for (his_pos, nei_physics_obj) in neighs {
         if (nei_physics_obj.height - position.z).abs() > 5.0 {
             continue;
         }
+        // the vehicles pulled over leave a corridor to the emergency vehicles
+        if vehicle.siren && nei_physics_obj.pulled_over {
+            continue;
+        }
         let towards_vec: Vec2 = his_pos - pos2;
         // Ignore myself and very close cars
         if towards_vec.is_close(Vec2::ZERO, 1.0) && towards_vec.x > 0.0 {
@@ -371,12 +938,20 @@ fn calc_front_dist<'a>(
             if !is_vehicle {
                 dist_to_obj -= 1.0;
             }
-            if dist_to_obj < min_front_dist {
-                min_front_dist = dist_to_obj;
-                flag = nei_physics_obj.flag;
+            if dist_to_obj < leader.dist {
+                leader = Leader {
+                    dist: dist_to_obj,
+                    speed: if is_vehicle {
+                        nei_physics_obj.speed * cos_direction_angle
+                    } else {
+                        0.0
+                    },
+                    flag: nei_physics_obj.flag,
+                    is_vehicle,
+                };
             }
-            if min_front_dist < cutoff {
-                return (min_front_dist, flag);
+            if leader.dist < cutoff {
+                return leader;
             }
             continue;
         }
@@ -408,11 +983,349 @@ fn calc_front_dist<'a>(
             continue;
         }
 
+        // the crossing point is an obstacle that doesn't move along our direction
         let final_dist = dist - my_radius - nei_physics_obj.radius - 5.0;
-        if final_dist < min_front_dist {
-            min_front_dist = final_dist;
-            flag = nei_physics_obj.flag;
+        if final_dist < leader.dist {
+            leader = Leader {
+                dist: final_dist,
+                speed: 0.0,
+                flag: nei_physics_obj.flag,
+                is_vehicle: false,
+            };
+        }
+    }
+    leader
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{
+        ApproachSign, LaneDirection, PathKind, RoadFurniture, RoundaboutPolicy, TurnID, TurnKind,
+    };
+    use crate::tests::TestCtx;
+    use crate::transportation::{spawn_parked_vehicle, unpark};
+    use crate::utils::time::Tick;
+    use geom::{vec3, PolyLine3};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn idm_follows_smoothly() {
+        let kind = VehicleKind::Car;
+        // free road: accelerate, and stop accelerating at the desired speed
+        assert_eq!(
+            idm_acceleration(kind, 0.0, 10.0, &Leader::NONE),
+            kind.acceleration()
+        );
+        assert!(idm_acceleration(kind, 10.0, 10.0, &Leader::NONE).abs() < 0.01);
+
+        let leader = |dist, speed| Leader {
+            dist,
+            speed,
+            flag: 0,
+            is_vehicle: true,
+        };
+        // following at the same speed with a large gap only brakes a little
+        let acc = idm_acceleration(kind, 10.0, 15.0, &leader(25.0, 10.0));
+        assert!(acc.abs() < kind.comfortable_deceleration());
+        // approaching a stopped car brakes progressively, more when closer
+        let far = idm_acceleration(kind, 10.0, 15.0, &leader(30.0, 0.0));
+        let close = idm_acceleration(kind, 10.0, 15.0, &leader(15.0, 0.0));
+        assert!(far < 0.0 && close < far);
+        assert!(close >= -kind.deceleration());
+    }
+
+    #[test]
+    fn sirens_behind_make_vehicles_pull_over() {
+        let trans = Transform::new_dir(Vec3::ZERO, Vec3::X);
+        let hears = |others: &[(Vec2, bool)]| {
+            let mut cow = CollisionWorld::new(10);
+            let obj = |siren| PhysicsObject {
+                radius: 2.0,
+                group: PhysicsGroup::Vehicles,
+                siren,
+                ..Default::default()
+            };
+            let me = Collider(cow.insert(Vec2::ZERO, obj(false)));
+            for &(pos, siren) in others {
+                cow.insert(pos, obj(siren));
+            }
+            cow.maintain();
+            siren_behind(&cow, &me, &trans)
+        };
+
+        assert!(hears(&[(Vec2::new(-30.0, 1.0), true)]));
+        // ahead, out of range or on another road, the corridor is not needed
+        assert!(!hears(&[(Vec2::new(20.0, 0.0), true)]));
+        assert!(!hears(&[(Vec2::new(-80.0, 0.0), true)]));
+        assert!(!hears(&[(Vec2::new(-5.0, 20.0), true)]));
+        assert!(!hears(&[(Vec2::new(-30.0, 0.0), false)]));
+    }
+
+    #[test]
+    fn unparked_vehicles_merge_before_driving() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(150.0, 0.0, 0.0)]);
+
+        let car = spawn_parked_vehicle(&mut test.g, VehicleKind::Car, Vec3::ZERO).unwrap();
+        unpark(&mut test.g, car);
+        assert!(matches!(
+            test.g.world().vehicles[car].vehicle.state,
+            VehicleState::Merging(_, _)
+        ));
+
+        for _ in 0..1000 {
+            test.tick();
+            if matches!(
+                test.g.world().vehicles[car].vehicle.state,
+                VehicleState::Driving
+            ) {
+                return;
+            }
+        }
+        panic!("car has not merged after 1000 ticks");
+    }
+
+    #[test]
+    fn double_parked_vehicles_block_the_lane() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(150.0, 0.0, 0.0)]);
+
+        let car = spawn_parked_vehicle(&mut test.g, VehicleKind::Car, Vec3::ZERO).unwrap();
+        unpark(&mut test.g, car);
+        let time = test.g.read::<GameTime>().instant();
+        test.g.world_mut_unchecked().vehicles[car].vehicle.state = VehicleState::DoubleParked(time);
+        let pos = test.g.world().vehicles[car].trans.position;
+
+        for _ in 0..100 {
+            test.tick();
+        }
+        let v = &test.g.world().vehicles[car];
+        assert_eq!(v.trans.position, pos);
+        assert!(v.collider.is_some());
+
+        // it drives off without going through a parking spot
+        unpark(&mut test.g, car);
+        assert!(matches!(
+            test.g.world().vehicles[car].vehicle.state,
+            VehicleState::Driving
+        ));
+    }
+
+    #[test]
+    fn stop_signs_give_way_to_the_priority_road() {
+        let test = TestCtx::new();
+        test.build_roads(&[
+            vec3(-100.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(100.0, 0.0, 0.0),
+        ]);
+        test.build_roads(&[vec3(0.0, -100.0, 0.0), vec3(0.0, 0.0, 0.0)]);
+
+        let (inter, side, main) = {
+            let map = test.g.map();
+            let inter = map
+                .intersections()
+                .values()
+                .find(|i| i.pos.xy().is_close(Vec2::ZERO, 1.0))
+                .unwrap();
+            let (side, main): (Vec<_>, Vec<_>) = inter.roads.iter().partition(|&&r| {
+                let r = &map.roads()[r];
+                r.points().first().y < -50.0 || r.points().last().y < -50.0
+            });
+            (inter.id, side[0], main)
+        };
+        let mut signs = BTreeMap::new();
+        signs.insert(side, ApproachSign::Stop);
+        for &r in &main {
+            signs.insert(r, ApproachSign::Priority);
+        }
+        test.g
+            .map_mut()
+            .update_intersection(inter, |i| i.approach_signs = signs.clone());
+
+        let map = test.g.map();
+        let incoming = |r: RoadID| {
+            let id = map.roads()[r]
+                .incoming_lanes_to(inter)
+                .iter()
+                .find(|(_, kind)| kind.is_driving())
+                .unwrap()
+                .0;
+            &map.lanes()[id]
+        };
+        let side_lane = incoming(side);
+        let main_lane = incoming(main[0]);
+        assert!(side_lane.control.is_stop_sign());
+        assert!(main_lane.control.is_always());
+
+        let coming = |towards: bool| {
+            let mut cow = CollisionWorld::new(10);
+            let end = main_lane.control_point();
+            let (pos, _, dir) = main_lane
+                .points
+                .project_segment_dir(end - (end - main_lane.points.first()).normalize() * 15.0);
+            cow.insert(
+                pos.xy(),
+                PhysicsObject {
+                    dir: if towards { dir.xy() } else { -dir.xy() },
+                    speed: 10.0,
+                    radius: 2.0,
+                    height: pos.z,
+                    group: PhysicsGroup::Vehicles,
+                    ..Default::default()
+                },
+            );
+            cow.maintain();
+            priority_traffic_coming(&map, &cow, side_lane)
+        };
+        assert!(coming(true));
+        assert!(!coming(false));
+    }
+
+    #[test]
+    fn roundabout_entries_give_way_to_the_ring() {
+        let test = TestCtx::new();
+        test.build_roads(&[
+            vec3(-100.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(100.0, 0.0, 0.0),
+        ]);
+        test.build_roads(&[vec3(0.0, -100.0, 0.0), vec3(0.0, 0.0, 0.0)]);
+
+        let inter = test
+            .g
+            .map()
+            .intersections()
+            .values()
+            .find(|i| i.pos.xy().is_close(Vec2::ZERO, 1.0))
+            .unwrap()
+            .id;
+        test.g.map_mut().update_intersection(inter, |i| {
+            i.turn_policy.roundabout = Some(RoundaboutPolicy::default())
+        });
+
+        let map = test.g.map();
+        assert!(map.intersections()[inter].is_roundabout());
+        let south = map.intersections()[inter]
+            .roads
+            .iter()
+            .map(|&r| &map.roads()[r])
+            .find(|r| r.points().first().y < -50.0 || r.points().last().y < -50.0)
+            .unwrap();
+        let id = south
+            .incoming_lanes_to(inter)
+            .iter()
+            .find(|(_, kind)| kind.is_driving())
+            .unwrap()
+            .0;
+        let entry_lane = &map.lanes()[id];
+
+        // a vehicle on the ring at the given angle, the entry is at the bottom
+        let coming = |deg: f32| {
+            let mut cow = CollisionWorld::new(10);
+            let radial = Vec2::from_angle(Radians::from_deg(deg - 90.0));
+            cow.insert(
+                radial * 15.0,
+                PhysicsObject {
+                    dir: -radial.perpendicular(),
+                    speed: 8.0,
+                    radius: 2.0,
+                    height: entry_lane.control_point().z,
+                    group: PhysicsGroup::Vehicles,
+                    ..Default::default()
+                },
+            );
+            cow.maintain();
+            priority_traffic_coming(&map, &cow, entry_lane)
+        };
+        // driving around the ring towards the entry
+        assert!(coming(-20.0));
+        // already past it
+        assert!(!coming(60.0));
+        // on the other side of the ring
+        assert!(!coming(180.0));
+    }
+
+    #[test]
+    fn only_pedestrians_over_the_road_block_the_crosswalk() {
+        let mut crosswalk = Turn::new(
+            TurnID::new(
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                false,
+            ),
+            TurnKind::Crosswalk,
+        );
+        crosswalk.points = PolyLine3::new(vec![vec3(0.0, -10.0, 0.0), vec3(0.0, 10.0, 0.0)]);
+        let pedestrian = || PhysicsObject {
+            radius: 0.3,
+            group: PhysicsGroup::Pedestrians,
+            ..Default::default()
+        };
+
+        let mut cow = CollisionWorld::new(10);
+        assert!(!pedestrians_on(&cow, &crosswalk, true));
+
+        // waiting on the sidewalk for the walk signal
+        let waiting = cow.insert(Vec2::new(0.0, -10.5), pedestrian());
+        assert!(pedestrians_on(&cow, &crosswalk, true));
+        assert!(!pedestrians_on(&cow, &crosswalk, false));
+
+        cow.remove_maintain(waiting);
+        cow.insert(Vec2::new(0.5, 2.0), pedestrian());
+        assert!(pedestrians_on(&cow, &crosswalk, false));
+    }
+
+    #[test]
+    fn vehicles_brake_for_stop_signs_then_go_on() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+        let road = *test.g.map().roads().keys().next().unwrap();
+        for (side, dist) in [
+            (LaneDirection::Forward, 150.0),
+            (LaneDirection::Backward, 160.0),
+        ] {
+            test.g.map_mut().add_road_furniture(
+                road,
+                RoadFurniture {
+                    kind: FurnitureKind::StopSign,
+                    side,
+                    dist,
+                },
+            );
+        }
+
+        let car =
+            spawn_parked_vehicle(&mut test.g, VehicleKind::Car, vec3(10.0, 0.0, 0.0)).unwrap();
+        unpark(&mut test.g, car);
+        let start = test.g.world().vehicles[car].trans.position;
+        let it = Itinerary::route(
+            Tick(0),
+            start,
+            vec3(290.0, 0.0, 0.0),
+            &test.g.map(),
+            PathKind::Vehicle,
+        )
+        .unwrap();
+        test.g.world_mut_unchecked().vehicles[car].it = it;
+
+        let mut top_speed: f32 = 0.0;
+        let mut halted = false;
+        for _ in 0..5000 {
+            test.tick();
+            let v = &test.g.world().vehicles[car];
+            let x = v.trans.position.x;
+            top_speed = top_speed.max(v.speed.0);
+            if x > 120.0 && x < 160.0 && top_speed > 5.0 && v.speed.0 < HALT_SPEED {
+                halted = true;
+            }
+            if x > 200.0 {
+                assert!(halted, "car did not halt at the stop sign");
+                return;
+            }
         }
+        panic!("car did not go on after the stop sign");
     }
-    (min_front_dist, flag)
 }
@@ -0,0 +1,514 @@
+use crate::economy::{Government, Money};
+use crate::map::{
+    BuildingID, BuildingKind, LaneKind, Map, PathKind, RoadID, Traversable, TraverseKind,
+};
+use crate::map_dynamic::{park, Itinerary, ParkingManagement};
+use crate::transportation::{spawn_parked_vehicle, unpark, VehicleKind, VehicleState};
+use crate::utils::time::{GameTime, Season, Tick};
+use crate::world::VehicleEnt;
+use crate::{ParCommandBuffer, Simulation, VehicleID, World};
+use geom::{Color, Vec3};
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Number of plows each depot keeps around
+pub const PLOWS_PER_DEPOT: usize = 2;
+/// Snow level above which a road is worth plowing
+pub const PLOW_THRESHOLD: f32 = 0.2;
+/// Speed multiplier when a road is completely covered in snow
+pub const MAX_SNOW_SLOWDOWN: f32 = 0.5;
+
+/// Condition below which a road starts slowing down vehicles
+pub const POOR_CONDITION: f32 = 0.5;
+/// Speed multiplier when a road is completely worn out
+pub const MAX_WEAR_SLOWDOWN: f32 = 0.4;
+/// Speed multiplier while a lane of the road is closed for repairs
+pub const CLOSURE_SLOWDOWN: f32 = 0.3;
+
+const SNOWFALL_PER_HOUR: f32 = 0.1;
+/// Condition lost every minute for each car on the road, trucks wear roads faster
+const WEAR_PER_VEHICLE: f32 = 0.0001;
+const TRUCK_WEAR_MULTIPLIER: f32 = 4.0;
+/// How long (in game seconds) a lane stays closed while a crew repairs the road
+const REPAIR_DURATION: f64 = 1800.0;
+/// Cost of repairing a completely worn out meter of road
+const REPAIR_COST_PER_METER: Money = Money::new_bucks(1);
+const SNOW_MELT_PER_HOUR: f32 = 0.25;
+const PLOW_TINT: Color = Color::new(1.0, 0.55, 0.0, 1.0);
+
+/// Weather that can be forced by a scenario instead of the seasonal one
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Weather {
+    Clear,
+    Snowstorm,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlowPriority {
+    Never,
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl PlowPriority {
+    pub const ALL: [PlowPriority; 4] = [
+        PlowPriority::Never,
+        PlowPriority::Low,
+        PlowPriority::Normal,
+        PlowPriority::High,
+    ];
+
+    fn weight(self) -> f32 {
+        match self {
+            PlowPriority::Never => 0.0,
+            PlowPriority::Low => 0.5,
+            PlowPriority::Normal => 1.0,
+            PlowPriority::High => 3.0,
+        }
+    }
+}
+
+/// How much of the budget goes to road repairs, better funding repairs roads before they get too bad
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RepairFunding {
+    Disabled,
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl RepairFunding {
+    pub const ALL: [RepairFunding; 4] = [
+        RepairFunding::Disabled,
+        RepairFunding::Low,
+        RepairFunding::Normal,
+        RepairFunding::High,
+    ];
+
+    /// Condition below which a road gets repaired
+    fn threshold(self) -> f32 {
+        match self {
+            RepairFunding::Disabled => 0.0,
+            RepairFunding::Low => 0.25,
+            RepairFunding::Normal => 0.5,
+            RepairFunding::High => 0.75,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlowState {
+    Parked,
+    Plowing(RoadID),
+    /// Going to the road to repair it, or repairing it if a closure is active
+    Repairing(RoadID),
+    Idle,
+    Returning,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Plow {
+    pub depot: BuildingID,
+    pub state: PlowState,
+}
+
+/// RoadMaintenance keeps track of the snow on the roads, of their wear and of the depot trucks maintaining them.
+/// Snow falls during winter and slows down vehicles until a plow drives over the road.
+/// Traffic wears the roads down until a repair crew closes a lane to fix them.
+#[derive(Default, Serialize, Deserialize)]
+pub struct RoadMaintenance {
+    snow: BTreeMap<RoadID, f32>,
+    priorities: BTreeMap<RoadID, PlowPriority>,
+    pub plows: BTreeMap<VehicleID, Plow>,
+    condition: BTreeMap<RoadID, f32>,
+    /// Roads with a lane closed for repairs, and when the repairs end
+    closures: BTreeMap<RoadID, f64>,
+    pub funding: RepairFunding,
+    /// Roads closed by a scenario, and when they reopen
+    scripted_closures: BTreeMap<RoadID, f64>,
+    /// Weather forced by a scenario, and until when
+    forced_weather: Option<(Weather, f64)>,
+}
+
+impl RoadMaintenance {
+    /// Snow level of the road between 0 (clear) and 1 (fully covered)
+    pub fn snow(&self, road: RoadID) -> f32 {
+        self.snow.get(&road).copied().unwrap_or(0.0)
+    }
+
+    /// Surface condition of the road between 0 (worn out) and 1 (new)
+    pub fn condition(&self, road: RoadID) -> f32 {
+        self.condition.get(&road).copied().unwrap_or(1.0)
+    }
+
+    pub fn is_closed(&self, road: RoadID) -> bool {
+        self.closures.contains_key(&road) || self.scripted_closures.contains_key(&road)
+    }
+
+    /// Closes a lane of the road until the given timestamp, independently of the repair crews
+    pub fn close_road(&mut self, road: RoadID, until: f64) {
+        let end = self.scripted_closures.entry(road).or_insert(until);
+        *end = end.max(until);
+    }
+
+    /// Overrides the seasonal weather until the given timestamp
+    pub fn force_weather(&mut self, weather: Weather, until: f64) {
+        self.forced_weather = Some((weather, until));
+    }
+
+    /// How much snow falls per hour, taking the weather forced by a scenario into account
+    pub fn snowfall(&self, time: &GameTime) -> f32 {
+        match self.forced_weather {
+            Some((weather, until)) if time.timestamp < until => match weather {
+                Weather::Clear => 0.0,
+                Weather::Snowstorm => SNOWFALL_PER_HOUR,
+            },
+            _ => snowfall(time),
+        }
+    }
+
+    pub fn speed_factor(&self, road: RoadID) -> f32 {
+        let wear = ((POOR_CONDITION - self.condition(road)) / POOR_CONDITION).max(0.0);
+        let closure = if self.is_closed(road) {
+            CLOSURE_SLOWDOWN
+        } else {
+            1.0
+        };
+        (1.0 - self.snow(road) * MAX_SNOW_SLOWDOWN) * (1.0 - wear * MAX_WEAR_SLOWDOWN) * closure
+    }
+
+    /// Cost of bringing the road back to a perfect condition
+    pub fn repair_cost(&self, map: &Map, road: RoadID) -> Money {
+        let Some(r) = map.roads().get(road) else {
+            return Money::ZERO;
+        };
+        let wear = 1.0 - self.condition(road);
+        Money::new_inner((REPAIR_COST_PER_METER.inner() as f32 * r.points().length() * wear) as i64)
+    }
+
+    pub fn priority(&self, road: RoadID) -> PlowPriority {
+        self.priorities.get(&road).copied().unwrap_or_default()
+    }
+
+    pub fn set_priority(&mut self, road: RoadID, priority: PlowPriority) {
+        if priority == PlowPriority::Normal {
+            self.priorities.remove(&road);
+            return;
+        }
+        self.priorities.insert(road, priority);
+    }
+
+    fn update_snow(&mut self, map: &Map, time: &GameTime) {
+        let snowfall = self.snowfall(time) / 60.0;
+        if snowfall > 0.0 {
+            for road in map.roads().values() {
+                if !road
+                    .lanes_iter()
+                    .any(|(_, kind)| kind.is_driving() || *kind == LaneKind::Bus)
+                {
+                    continue;
+                }
+                let s = self.snow.entry(road.id).or_default();
+                *s = (*s + snowfall).min(1.0);
+            }
+        } else if time.season() != Season::Winter {
+            self.snow.retain(|_, s| {
+                *s -= SNOW_MELT_PER_HOUR / 60.0;
+                *s > 0.0
+            });
+        }
+
+        self.snow.retain(|&r, _| map.roads().contains_key(r));
+        self.priorities.retain(|&r, _| map.roads().contains_key(r));
+        self.condition.retain(|&r, _| map.roads().contains_key(r));
+        self.closures.retain(|&r, _| map.roads().contains_key(r));
+        self.scripted_closures
+            .retain(|&r, &mut end| map.roads().contains_key(r) && end > time.timestamp);
+        if matches!(self.forced_weather, Some((_, until)) if until <= time.timestamp) {
+            self.forced_weather = None;
+        }
+    }
+
+    /// Wears down the roads according to the vehicles currently driving on them
+    fn update_wear(&mut self, world: &World, map: &Map) {
+        for v in world.vehicles.values() {
+            let Some(Traversable {
+                kind: TraverseKind::Lane(l),
+                ..
+            }) = v.it.get_travers()
+            else {
+                continue;
+            };
+            let Some(l) = map.lanes().get(*l) else {
+                continue;
+            };
+            let wear = match v.vehicle.kind {
+                VehicleKind::Truck => WEAR_PER_VEHICLE * TRUCK_WEAR_MULTIPLIER,
+                _ => WEAR_PER_VEHICLE,
+            };
+            let c = self.condition.entry(l.parent).or_insert(1.0);
+            *c = (*c - wear).max(0.0);
+        }
+    }
+
+    /// Finds the worn road most worth repairing, based on its condition and distance
+    fn pick_repair(&self, map: &Map, pos: Vec3, targeted: &BTreeSet<RoadID>) -> Option<RoadID> {
+        let threshold = self.funding.threshold();
+        self.condition
+            .iter()
+            .filter(|&(r, &c)| c < threshold && !targeted.contains(r))
+            .filter_map(|(&r, &c)| {
+                let road = map.roads().get(r)?;
+                Some((r, (1.0 - c) / (1.0 + road.pos.distance(pos) / 1000.0)))
+            })
+            .max_by_key(|&(_, score)| OrderedFloat(score))
+            .map(|(r, _)| r)
+    }
+
+    /// Finds the snowy road most worth plowing, based on its priority and distance
+    fn pick_road(&self, map: &Map, pos: Vec3, targeted: &BTreeSet<RoadID>) -> Option<RoadID> {
+        self.snow
+            .iter()
+            .filter(|&(r, &s)| s >= PLOW_THRESHOLD && !targeted.contains(r))
+            .filter_map(|(&r, &s)| {
+                let weight = self.priority(r).weight();
+                if weight <= 0.0 {
+                    return None;
+                }
+                let road = map.roads().get(r)?;
+                Some((r, s * weight / (1.0 + road.pos.distance(pos) / 1000.0)))
+            })
+            .max_by_key(|&(_, score)| OrderedFloat(score))
+            .map(|(r, _)| r)
+    }
+}
+
+/// How much snow falls per hour, there are snowstorms every so often during winter
+pub fn snowfall(time: &GameTime) -> f32 {
+    if time.season() != Season::Winter {
+        return 0.0;
+    }
+    let r = common::rand::rand2(time.daytime.day as f32, (time.daytime.hour / 6) as f32);
+    if r < 0.5 {
+        return 0.0;
+    }
+    SNOWFALL_PER_HOUR
+}
+
+pub fn road_maintenance_system(sim: &mut Simulation) {
+    profiling::scope!("transportation::road_maintenance_system");
+    let time = *sim.read::<GameTime>();
+
+    if time.tick(60) {
+        let mut maintenance = sim.write::<RoadMaintenance>();
+        maintenance.update_snow(&sim.map(), &time);
+        maintenance.update_wear(&sim.world, &sim.map());
+    }
+
+    let dispatch = time.tick(10);
+    if dispatch {
+        spawn_plows(sim);
+    }
+    update_plows(sim, dispatch);
+}
+
+fn spawn_plows(sim: &mut Simulation) {
+    let map = sim.map();
+    let maintenance = sim.read::<RoadMaintenance>();
+
+    let mut to_spawn = vec![];
+    for &depot in map
+        .bkinds
+        .get(&BuildingKind::SnowPlowDepot)
+        .into_iter()
+        .flatten()
+    {
+        let n_plows = maintenance
+            .plows
+            .values()
+            .filter(|p| p.depot == depot)
+            .count();
+        let door_pos = map.buildings[depot].door_pos;
+        to_spawn.extend((n_plows..PLOWS_PER_DEPOT).map(|_| (depot, door_pos)));
+    }
+    drop((map, maintenance));
+
+    for (depot, door_pos) in to_spawn {
+        let Some(id) = spawn_parked_vehicle(sim, VehicleKind::Truck, door_pos) else {
+            continue;
+        };
+        if let Some(v) = sim.world.vehicles.get_mut(id) {
+            v.vehicle.tint = PLOW_TINT;
+        }
+        sim.write::<RoadMaintenance>().plows.insert(
+            id,
+            Plow {
+                depot,
+                state: PlowState::Parked,
+            },
+        );
+    }
+}
+
+fn update_plows(sim: &mut Simulation, dispatch: bool) {
+    let tick = *sim.read::<Tick>();
+    let mut to_unpark = vec![];
+
+    {
+        let (world, res) = sim.world_res();
+        let map = res.read::<Map>();
+        let cbuf = res.read::<ParCommandBuffer<VehicleEnt>>();
+        let mut pm = res.write::<ParkingManagement>();
+        let mut gov = res.write::<Government>();
+        let now = res.read::<GameTime>().timestamp;
+        let mut maintenance = res.write::<RoadMaintenance>();
+        let maintenance = &mut *maintenance;
+
+        let mut targeted: BTreeSet<RoadID> = maintenance
+            .plows
+            .values()
+            .filter_map(|p| match p.state {
+                PlowState::Plowing(r) | PlowState::Repairing(r) => Some(r),
+                _ => None,
+            })
+            .collect();
+
+        let mut to_remove = vec![];
+        let plow_ids: Vec<VehicleID> = maintenance.plows.keys().copied().collect();
+
+        for id in plow_ids {
+            let Plow { depot, mut state } = maintenance.plows[&id];
+            let Some(v) = world.vehicles.get_mut(id) else {
+                to_remove.push(id);
+                continue;
+            };
+            let Some(depot) = map.buildings().get(depot) else {
+                cbuf.kill(id);
+                to_remove.push(id);
+                continue;
+            };
+
+            // Driving over a road clears it
+            if let Some(Traversable {
+                kind: TraverseKind::Lane(l),
+                ..
+            }) = v.it.get_travers()
+            {
+                if let Some(l) = map.lanes().get(*l) {
+                    maintenance.snow.remove(&l.parent);
+                }
+            }
+
+            match state {
+                PlowState::Plowing(r) => {
+                    if v.it.has_ended(0.0) || maintenance.snow(r) < PLOW_THRESHOLD {
+                        targeted.remove(&r);
+                        state = PlowState::Idle;
+                    }
+                }
+                PlowState::Repairing(r) => match maintenance.closures.get(&r) {
+                    Some(&end) if now >= end => {
+                        maintenance.closures.remove(&r);
+                        maintenance.condition.remove(&r);
+                        targeted.remove(&r);
+                        state = PlowState::Idle;
+                    }
+                    Some(_) => {}
+                    None if !map.roads().contains_key(r) => {
+                        targeted.remove(&r);
+                        state = PlowState::Idle;
+                    }
+                    None => {
+                        if v.it.has_ended(0.0) {
+                            maintenance.closures.insert(r, now + REPAIR_DURATION);
+                        }
+                    }
+                },
+                PlowState::Returning => {
+                    if v.it.has_ended(0.0) {
+                        if let Ok(spot) = pm.reserve_near(v.trans.position, &map) {
+                            park(&map, v, spot);
+                            state = PlowState::Parked;
+                        }
+                    }
+                }
+                PlowState::Parked | PlowState::Idle => {}
+            }
+
+            let is_parked = matches!(v.vehicle.state, VehicleState::Parked(_));
+            let can_dispatch = match state {
+                PlowState::Plowing(_) | PlowState::Repairing(_) => false,
+                PlowState::Parked => is_parked,
+                PlowState::Idle | PlowState::Returning => true,
+            };
+
+            if dispatch && can_dispatch {
+                let pos = v.trans.position;
+                let route = |r: RoadID| {
+                    let road = &map.roads()[r];
+                    let dest = road.points().point_along(road.points().length() * 0.5);
+                    Itinerary::route(tick, pos, dest, &map, PathKind::Vehicle)
+                };
+
+                // Snow is more urgent than repairs, which are only done if the city can pay for them
+                let job = match maintenance.pick_road(&map, pos, &targeted) {
+                    Some(r) => route(r).map(|it| (PlowState::Plowing(r), r, it)),
+                    None => maintenance
+                        .pick_repair(&map, pos, &targeted)
+                        .filter(|&r| gov.money >= maintenance.repair_cost(&map, r))
+                        .and_then(|r| {
+                            let it = route(r)?;
+                            let cost = maintenance.repair_cost(&map, r);
+                            gov.spend(cost, "Road repair", now);
+                            Some((PlowState::Repairing(r), r, it))
+                        }),
+                };
+
+                if let Some((new_state, r, it)) = job {
+                    targeted.insert(r);
+                    state = new_state;
+                    if is_parked {
+                        to_unpark.push((id, it));
+                    } else {
+                        v.it = it;
+                    }
+                } else if state == PlowState::Idle {
+                    let door_pos = depot.door_pos;
+                    if let Some(it) = Itinerary::route(tick, pos, door_pos, &map, PathKind::Vehicle)
+                    {
+                        v.it = it;
+                        state = PlowState::Returning;
+                    }
+                }
+            }
+
+            maintenance.plows.get_mut(&id).unwrap().state = state;
+        }
+
+        for id in to_remove {
+            maintenance.plows.remove(&id);
+        }
+
+        // Reopen the lanes if the crew disappeared before finishing
+        let repairing: BTreeSet<RoadID> = maintenance
+            .plows
+            .values()
+            .filter_map(|p| match p.state {
+                PlowState::Repairing(r) => Some(r),
+                _ => None,
+            })
+            .collect();
+        maintenance.closures.retain(|r, _| repairing.contains(r));
+    }
+
+    for (id, it) in to_unpark {
+        unpark(sim, id);
+        if let Some(v) = sim.world.vehicles.get_mut(id) {
+            v.it = it;
+        }
+    }
+}
@@ -1,5 +1,6 @@
-use crate::map::{IntersectionID, LaneID, Map, TraverseKind};
+use crate::map::{IntersectionID, LaneID, LaneKind, Map, PathKind, TraverseKind};
 use crate::map_dynamic::ItineraryFollower;
+use crate::utils::par_command_buffer::SimDrop;
 use crate::utils::resources::Resources;
 use crate::world::{TrainEnt, TrainID, WagonEnt};
 use crate::{GameTime, Itinerary, ItineraryLeader, Simulation, Speed, World};
@@ -27,6 +28,51 @@ pub struct Locomotive {
     pub dec_force: f32,
     /// m
     pub length: f32,
+    pub kind: LocomotiveKind,
+}
+
+/// Electric locomotives accelerate faster but can only run on electrified rails
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LocomotiveKind {
+    #[default]
+    Diesel,
+    Electric,
+}
+
+debug_inspect_impl!(LocomotiveKind);
+
+impl LocomotiveKind {
+    pub const ALL: [LocomotiveKind; 2] = [LocomotiveKind::Diesel, LocomotiveKind::Electric];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LocomotiveKind::Diesel => "Diesel",
+            LocomotiveKind::Electric => "Electric",
+        }
+    }
+
+    pub fn path_kind(self) -> PathKind {
+        match self {
+            LocomotiveKind::Diesel => PathKind::Rail,
+            LocomotiveKind::Electric => PathKind::ElectricRail,
+        }
+    }
+
+    /// m.s^2
+    pub fn acc_force(self) -> f32 {
+        match self {
+            LocomotiveKind::Diesel => 1.0,
+            LocomotiveKind::Electric => 1.8,
+        }
+    }
+
+    /// m.s^2
+    pub fn dec_force(self) -> f32 {
+        match self {
+            LocomotiveKind::Diesel => 2.5,
+            LocomotiveKind::Electric => 3.0,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Inspect)]
@@ -81,12 +127,54 @@ pub fn train_length(n_wagons: u32) -> f32 {
     1.0 + (n_wagons + 1) as f32 * WAGON_INTERLENGTH
 }
 
+/// Checks that a train of `n_wagons` wagons fits on the rail lane with its locomotive `dist`
+/// meters along it, without overlapping the trains already on the lane
+pub fn check_train_placement(
+    map: &Map,
+    trains: &HopSlotMap<TrainID, TrainEnt>,
+    lane: LaneID,
+    dist: f32,
+    n_wagons: u32,
+    loco: LocomotiveKind,
+) -> Result<(), &'static str> {
+    let Some(l) = map.lanes().get(lane) else {
+        return Err("The rail was removed");
+    };
+    if l.kind != LaneKind::Rail {
+        return Err("Trains can only be put on rails");
+    }
+    let length = train_length(n_wagons);
+    let lane_length = l.points.length();
+    if dist <= length || dist > lane_length {
+        return Err("The train doesn't fit on the rail");
+    }
+    if loco == LocomotiveKind::Electric
+        && !map.roads().get(l.parent).map_or(false, |r| r.electrified)
+    {
+        return Err("Electric locomotives need electrified rails");
+    }
+
+    // the front of a train is `past` meters after the end of the lanes it went through
+    let occupied = trains.values().any(|t| {
+        let Some(&past) = t.res.past_travers.get(&TraverseKind::Lane(lane)) else {
+            return false;
+        };
+        let front = lane_length + past;
+        front > dist - length && front - t.locomotive.length < dist
+    });
+    if occupied {
+        return Err("Another train is in the way");
+    }
+    Ok(())
+}
+
 pub fn spawn_train(
     sim: &mut Simulation,
     dist: f32,
     n_wagons: u32,
     lane: LaneID,
     kind: RailWagonKind,
+    loco: LocomotiveKind,
 ) -> Option<TrainID> {
     let (world, res) = sim.world_res();
 
@@ -113,9 +201,10 @@ pub fn spawn_train(
         it: Itinerary::NONE,
         locomotive: Locomotive {
             max_speed: 50.0,
-            acc_force: 1.0,
-            dec_force: 2.5,
+            acc_force: loco.acc_force(),
+            dec_force: loco.dec_force(),
             length: trainlength,
+            kind: loco,
         },
         res: LocomotiveReservation {
             cur_travers_dist: dist,
@@ -129,6 +218,7 @@ pub fn spawn_train(
         leader: ItineraryLeader {
             past: Polyline3Queue::new(points.into_iter(), locopos, trainlength + 20.0),
         },
+        metro: None,
     });
 
     let leader = &world.trains.get(loco).unwrap().leader;
@@ -164,6 +254,25 @@ pub fn spawn_train(
     Some(loco)
 }
 
+/// Removes the train with its wagons and frees the intersections it reserved
+pub fn despawn_train(sim: &mut Simulation, id: TrainID) {
+    let (world, res) = sim.world_res();
+    world.wagons.retain(|_, w| w.itfollower.leader != id);
+    let Some(train) = world.trains.remove(id) else {
+        return;
+    };
+
+    let mut reservs = res.write::<TrainReservations>();
+    reservs.reservations.retain(|_, t| *t != id);
+    reservs.localisations.retain(|_, locs| {
+        locs.remove(&id);
+        !locs.is_empty()
+    });
+    drop(reservs);
+
+    train.sim_drop(id, res);
+}
+
 pub fn traverse_forward<'a>(
     map: &'a Map,
     itin: &'a Itinerary,
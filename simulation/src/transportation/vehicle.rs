@@ -1,16 +1,42 @@
+use crate::map::{BuildingID, LaneID, Map, PathKind, Pathfinder};
 use crate::map_dynamic::{Itinerary, ParkingManagement, SpotReservation};
 use crate::physics::{Collider, CollisionWorld, PhysicsGroup, PhysicsObject};
+use crate::transportation::fuel::{EnergyKind, EV_SHARE};
 use crate::utils::rand_provider::RandProvider;
-use crate::utils::time::GameInstant;
+use crate::utils::time::{GameInstant, GameTime};
 use crate::world::{VehicleEnt, VehicleID};
-use crate::Simulation;
+use crate::{ParCommandBuffer, Simulation};
 use egui_inspect::Inspect;
 use geom::Transform;
 use geom::{Color, Spline3, Vec3};
 use serde::{Deserialize, Serialize};
+use slotmapd::Key;
+use std::collections::BTreeSet;
 
 /// The duration for the parking animation.
 pub const TIME_TO_PARK: f32 = 4.0;
+/// The duration to get from a parking spot or a driveway onto the lane, without waiting for a gap
+pub const TIME_TO_MERGE: f32 = 3.0;
+/// Portion of the merge at which the vehicle reaches the edge of the lane and waits for a gap
+pub const MERGE_WAIT_T: f32 = 0.5;
+/// How far along the lane the merge ends, in meters
+const MERGE_LENGTH: f32 = 8.0;
+/// How far to the right vehicles pull over to let an emergency vehicle through, in meters
+pub const PULL_OVER_OFFSET: f32 = 1.8;
+
+/// Body roll (in radians) per m/s² of lateral acceleration
+const ROLL_PER_ACC: f32 = 0.015;
+const MAX_ROLL: f32 = 0.08;
+/// Body pitch (in radians) per m/s² of longitudinal acceleration
+const PITCH_PER_ACC: f32 = 0.008;
+const MAX_PITCH: f32 = 0.05;
+/// How fast the body follows the forces, in 1/s
+const TILT_RESPONSIVENESS: f32 = 6.0;
+const SUSPENSION_STIFFNESS: f32 = 120.0;
+const SUSPENSION_DAMPING: f32 = 8.0;
+/// Impulse given to the suspension by bumps on a worn out road, per m/s of speed
+const BUMP_STRENGTH: f32 = 0.6;
+const MAX_BOUNCE: f32 = 0.15;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum VehicleState {
@@ -19,15 +45,23 @@ pub enum VehicleState {
     /// Panicked when it notices it's in a gridlock
     Panicking(GameInstant),
     RoadToPark(Spline3, f32, SpotReservation),
+    /// Leaving a parking spot or a driveway on rails, waits at the edge of the lane for a gap in
+    /// traffic before entering it
+    Merging(Spline3, f32),
+    /// Parked illegally on the lane since the given instant because no spot was found, it keeps
+    /// its collider so the traffic behind it is blocked
+    DoubleParked(GameInstant),
 }
 
 debug_inspect_impl!(VehicleState);
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum VehicleKind {
     Car,
     Truck,
     Bus,
+    /// Runs on the tram tracks of the streets, in the traffic
+    Tram,
 }
 
 #[derive(Debug, Serialize, Deserialize, Inspect)]
@@ -42,6 +76,125 @@ pub struct Vehicle {
 
     /// Used to detect gridlock
     pub flag: u64,
+
+    /// Share of the tank or battery left, in [0; 1] range
+    pub fuel: f32,
+    pub energy: EnergyKind,
+
+    /// Emergency vehicle answering an incident, see [`crate::transportation::emergency`]
+    pub siren: bool,
+    /// How far the vehicle moved to the right of its lane to let an emergency vehicle through,
+    /// in meters
+    pub pull_over: f32,
+    /// Lane at the end of which the vehicle came to a halt at the stop sign, it goes once the
+    /// priority roads are clear
+    #[inspect(skip)]
+    pub halted_at: Option<LaneID>,
+
+    #[inspect(skip)]
+    #[serde(skip)]
+    pub visual: VehicleVisual,
+}
+
+/// Purely visual dynamics of the vehicle body, derived from its movement but never affecting it
+#[derive(Debug, Default, Copy, Clone)]
+pub struct VehicleVisual {
+    /// Body roll in radians, positive leans to the right
+    pub roll: f32,
+    /// Body pitch in radians, positive lifts the nose
+    pub pitch: f32,
+    /// Vertical offset of the body from the suspension, in meters
+    pub bounce: f32,
+    bounce_vel: f32,
+}
+
+impl VehicleVisual {
+    /// Updates the body from the movement of the last frame.
+    /// `roughness` goes from 0 (perfect road) to 1 (worn out road).
+    pub fn update(
+        &mut self,
+        dt: f32,
+        old_dir: Vec3,
+        old_speed: f32,
+        trans: &Transform,
+        speed: f32,
+        roughness: f32,
+    ) {
+        if dt <= 0.0 {
+            return;
+        }
+        let yaw_rate = old_dir.xy().cross(trans.dir.xy()) / dt;
+        let lateral = yaw_rate * speed;
+        let longitudinal = (speed - old_speed) / dt;
+
+        let target_roll = (lateral * ROLL_PER_ACC).clamp(-MAX_ROLL, MAX_ROLL);
+        let target_pitch = (longitudinal * PITCH_PER_ACC).clamp(-MAX_PITCH, MAX_PITCH);
+        let k = (dt * TILT_RESPONSIVENESS).min(1.0);
+        self.roll += (target_roll - self.roll) * k;
+        self.pitch += (target_pitch - self.pitch) * k;
+
+        let noise = common::rand::rand2(trans.position.x, trans.position.y) - 0.5;
+        let bump = roughness * speed * noise * BUMP_STRENGTH;
+        self.bounce_vel +=
+            (-SUSPENSION_STIFFNESS * self.bounce - SUSPENSION_DAMPING * self.bounce_vel + bump)
+                * dt;
+        self.bounce = (self.bounce + self.bounce_vel * dt).clamp(-MAX_BOUNCE, MAX_BOUNCE);
+    }
+
+    /// Direction of the body once pitched
+    pub fn body_dir(&self, dir: Vec3) -> Vec3 {
+        (dir + Vec3::Z * self.pitch).normalize()
+    }
+}
+
+/// Share of the parked cars whose cabin light is on at a given moment of the evening
+const CABIN_LIT_SHARE: f32 = 0.03;
+/// How long a cabin light stays on, in seconds
+const CABIN_LIT_DURATION: f64 = 5.0 * 60.0;
+/// Share of the parked cars left out in the street overnight
+const DEWY_SHARE: f32 = 0.6;
+
+/// How a parked vehicle looks while it waits, so that the streets look inhabited at night.
+/// Purely visual, it is derived from the time of day and the vehicle.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ParkedIdle {
+    Resting,
+    /// Someone is fetching something from the car, the cabin light is on
+    CabinLit,
+    /// Left out overnight, the body is covered in dew until the morning
+    Dewy,
+}
+
+impl ParkedIdle {
+    pub fn at(vehicle: VehicleID, time: &GameTime) -> Self {
+        let seed = vehicle.data().as_ffi() as u32 as f32;
+        let hour = time.daytime.hour;
+        if (5..9).contains(&hour) && common::rand::rand(seed) < DEWY_SHARE {
+            return ParkedIdle::Dewy;
+        }
+        if hour >= 18 || hour < 1 {
+            let slot = (time.timestamp / CABIN_LIT_DURATION) as u32 as f32;
+            if common::rand::rand2(seed, slot) < CABIN_LIT_SHARE {
+                return ParkedIdle::CabinLit;
+            }
+        }
+        ParkedIdle::Resting
+    }
+
+    /// The tint of the body in this state
+    pub fn tint(self, tint: Color) -> Color {
+        let (towards, k) = match self {
+            ParkedIdle::Resting => return tint,
+            ParkedIdle::CabinLit => (Color::new(1.0, 0.85, 0.55, 1.0), 0.35),
+            ParkedIdle::Dewy => (Color::gray(0.85), 0.2),
+        };
+        Color::new(
+            tint.r + (towards.r - tint.r) * k,
+            tint.g + (towards.g - tint.g) * k,
+            tint.b + (towards.b - tint.b) * k,
+            tint.a,
+        )
+    }
 }
 
 #[must_use]
@@ -58,25 +211,80 @@ pub fn put_vehicle_in_coworld(sim: &Simulation, w: f32, trans: Transform) -> Col
 }
 
 impl VehicleKind {
-    pub fn width(self) -> f32 {
+    pub fn path_kind(self) -> PathKind {
+        match self {
+            VehicleKind::Truck => PathKind::Truck,
+            VehicleKind::Car => PathKind::Vehicle,
+            VehicleKind::Bus => PathKind::Bus,
+            VehicleKind::Tram => PathKind::Tram,
+        }
+    }
+
+    pub const ALL: [VehicleKind; 4] = [
+        VehicleKind::Car,
+        VehicleKind::Truck,
+        VehicleKind::Bus,
+        VehicleKind::Tram,
+    ];
+
+    pub const fn width(self) -> f32 {
         match self {
             VehicleKind::Car => 4.5,
             VehicleKind::Truck => 6.0,
             VehicleKind::Bus => 9.0,
+            VehicleKind::Tram => 16.0,
         }
     }
 
+    /// Width of the widest kind of vehicle
+    pub const MAX_WIDTH: f32 = {
+        let mut max = 0.0;
+        let mut i = 0;
+        while i < Self::ALL.len() {
+            max = f32::max(max, Self::ALL[i].width());
+            i += 1;
+        }
+        max
+    };
+
     pub fn acceleration(self) -> f32 {
         match self {
             VehicleKind::Car => 3.0,
             VehicleKind::Truck => 2.5,
             VehicleKind::Bus => 2.0,
+            VehicleKind::Tram => 1.5,
         }
     }
 
     pub fn deceleration(self) -> f32 {
         match self {
-            VehicleKind::Car | VehicleKind::Bus | VehicleKind::Truck => 6.0,
+            VehicleKind::Car | VehicleKind::Bus | VehicleKind::Truck | VehicleKind::Tram => 6.0,
+        }
+    }
+
+    /// Deceleration used to slow down behind other vehicles, the maximum is only used to avoid collisions
+    pub fn comfortable_deceleration(self) -> f32 {
+        match self {
+            VehicleKind::Car => 2.5,
+            VehicleKind::Truck | VehicleKind::Bus => 2.0,
+            VehicleKind::Tram => 1.5,
+        }
+    }
+
+    /// Time gap kept with the vehicle in front
+    pub fn time_headway(self) -> f32 {
+        match self {
+            VehicleKind::Car => 1.2,
+            VehicleKind::Truck => 1.6,
+            VehicleKind::Bus | VehicleKind::Tram => 1.5,
+        }
+    }
+
+    /// Bumper to bumper distance kept with the vehicle in front when stopped
+    pub fn min_gap(self) -> f32 {
+        match self {
+            VehicleKind::Car => 1.5,
+            VehicleKind::Truck | VehicleKind::Bus | VehicleKind::Tram => 2.5,
         }
     }
 
@@ -84,14 +292,14 @@ impl VehicleKind {
         match self {
             VehicleKind::Car => 0.5,
             VehicleKind::Truck => 3.0,
-            VehicleKind::Bus => 4.0,
+            VehicleKind::Bus | VehicleKind::Tram => 4.0,
         }
     }
 
     pub fn speed_factor(self) -> f32 {
         match self {
             VehicleKind::Car => 1.0,
-            VehicleKind::Truck | VehicleKind::Bus => 0.8,
+            VehicleKind::Truck | VehicleKind::Bus | VehicleKind::Tram => 0.8,
         }
     }
 
@@ -99,28 +307,116 @@ impl VehicleKind {
         match self {
             VehicleKind::Car => 1.0,
             VehicleKind::Truck => 0.9,
-            VehicleKind::Bus => 0.8,
+            VehicleKind::Bus | VehicleKind::Tram => 0.8,
         }
     }
 }
 
 pub fn unpark(sim: &mut Simulation, vehicle: VehicleID) {
     let v = unwrap_ret!(sim.world.vehicles.get_mut(vehicle));
-    let w = v.vehicle.kind.width();
+    let kind = v.vehicle.kind;
+    let w = kind.width();
     let trans = v.trans;
 
-    if let VehicleState::Parked(spot) =
-        std::mem::replace(&mut v.vehicle.state, VehicleState::Driving)
-    {
-        sim.write::<ParkingManagement>().free(spot);
-    } else {
-        log::warn!("Trying to unpark {:?} that wasn't parked", vehicle);
+    match std::mem::replace(&mut v.vehicle.state, VehicleState::Driving) {
+        VehicleState::Parked(spot) => sim.write::<ParkingManagement>().free(spot),
+        // Already on the lane with its collider, it just drives off
+        VehicleState::DoubleParked(_) => return,
+        _ => log::warn!("Trying to unpark {:?} that wasn't parked", vehicle),
     }
 
     let coll = put_vehicle_in_coworld(sim, w, trans);
+    let merge = merge_spline(&sim.map(), kind, trans);
 
     let v = unwrap_ret!(sim.world.vehicles.get_mut(vehicle));
     v.collider = Some(coll);
+    if let Some(spline) = merge {
+        v.vehicle.state = VehicleState::Merging(spline, 0.0);
+        v.speed.0 = 0.0;
+    }
+}
+
+/// Unparks a vehicle kept inside the building, it comes out of the driveway and merges onto the
+/// adjacent lane. Buildings without a driveway unpark it from its parking spot.
+pub fn unpark_from_building(sim: &mut Simulation, vehicle: VehicleID, building: BuildingID) {
+    let driveway = sim
+        .map()
+        .buildings()
+        .get(building)
+        .and_then(|b| b.driveway());
+    if let (Some(trans), Some(v)) = (driveway, sim.world.vehicles.get_mut(vehicle)) {
+        v.trans = trans;
+    }
+    unpark(sim, vehicle);
+}
+
+/// Links the parked vehicles of a loaded save to their spots so that they are found where they
+/// were left. Those whose spot is gone or taken by another vehicle are moved to the closest free
+/// spot.
+pub fn relink_parked_vehicles(sim: &mut Simulation) {
+    let mut lost = vec![];
+    {
+        let (world, res) = sim.world_res();
+        let map = res.read::<Map>();
+        let mut pm = res.write::<ParkingManagement>();
+        let mut linked = BTreeSet::new();
+        for (id, v) in world.vehicles.iter() {
+            let VehicleState::Parked(ref spot) = v.vehicle.state else {
+                continue;
+            };
+            if spot.exists(&map.parking) && linked.insert(spot.id()) {
+                pm.relink(spot);
+            } else {
+                lost.push(id);
+            }
+        }
+    }
+    for id in lost {
+        repark_vehicle(sim, id);
+    }
+}
+
+/// Moves a parked vehicle whose spot is gone or taken to the closest free spot, the vehicle is
+/// removed if there is none
+pub fn repark_vehicle(sim: &mut Simulation, vehicle: VehicleID) {
+    let (world, res) = sim.world_res();
+    let v = unwrap_ret!(world.vehicles.get_mut(vehicle));
+    if !matches!(v.vehicle.state, VehicleState::Parked(_)) {
+        return;
+    }
+    let map = res.read::<Map>();
+    let spot = res
+        .write::<ParkingManagement>()
+        .reserve_near(v.trans.position, &map);
+    // the old reservation isn't freed, the spot doesn't exist anymore or belongs to another
+    v.vehicle.state = VehicleState::Driving;
+    match spot {
+        Ok(spot) => {
+            if let Some(p) = spot.get(&map.parking) {
+                v.trans = p.trans;
+            }
+            v.vehicle.state = VehicleState::Parked(spot);
+        }
+        Err(_) => res.read::<ParCommandBuffer<VehicleEnt>>().kill(vehicle),
+    }
+}
+
+/// Path from the given position onto the nearest lane the vehicle can drive on, ending a bit
+/// further along the lane so that the vehicle is aligned with it
+pub fn merge_spline(map: &Map, kind: VehicleKind, from: Transform) -> Option<Spline3> {
+    let lane = kind.path_kind().nearest_lane(map, from.position)?;
+    let points = &map.lanes().get(lane)?.points;
+    let (proj, _, dir) = points.project_segment_dir(from.position);
+    let along = (points.length_at_proj(proj) + MERGE_LENGTH).min(points.length());
+    let to = points.point_along(along);
+    let d = from.position.distance(to);
+
+    Some(Spline3 {
+        from: from.position,
+        to,
+        from_derivative: from.dir * d,
+        to_derivative: dir * d,
+    })
 }
 
 pub fn spawn_parked_vehicle(
@@ -210,17 +506,46 @@ impl Vehicle {
         spot: SpotReservation,
         tint: Color,
         rng: &mut RandProvider,
+    ) -> Vehicle {
+        Self::with_state(kind, VehicleState::Parked(spot), tint, rng)
+    }
+
+    /// Same as [`Vehicle::new`] for the vehicles that don't start parked
+    pub fn with_state(
+        kind: VehicleKind,
+        state: VehicleState,
+        tint: Color,
+        rng: &mut RandProvider,
     ) -> Vehicle {
         Self {
             ang_velocity: 0.0,
             wait_time: 0.0,
             max_speed_multiplier: 0.95 + 0.1 * rng.next_f32(),
-            state: VehicleState::Parked(spot),
+            state,
             kind,
             tint,
             flag: 0,
+            fuel: 0.5 + 0.5 * rng.next_f32(),
+            energy: if matches!(kind, VehicleKind::Car) && rng.next_f32() < EV_SHARE {
+                EnergyKind::Electric
+            } else {
+                EnergyKind::Fuel
+            },
+            siren: false,
+            pull_over: 0.0,
+            halted_at: None,
+            visual: VehicleVisual::default(),
         }
     }
+
+    /// Shift of the vehicle to the right of its lane while pulled over
+    pub fn pull_over_offset(&self, dir: Vec3) -> Vec3 {
+        dir.xy().perpendicular().z0() * self.pull_over
+    }
+
+    pub fn is_pulled_over(&self) -> bool {
+        self.pull_over > PULL_OVER_OFFSET * 0.9
+    }
 }
 
 debug_inspect_impl!(VehicleKind);
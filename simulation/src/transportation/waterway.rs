@@ -0,0 +1,201 @@
+use crate::map::{BuildingID, BuildingKind, LaneKind, Map};
+use crate::utils::resources::Resources;
+use crate::utils::time::{GameTime, SECONDS_PER_HOUR};
+use crate::World;
+use geom::{Vec3, OBB};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Height a single lock can lift a barge, in meters
+pub const LOCK_RISE: f32 = 6.0;
+/// Minimum canal length between two locks, in meters
+pub const LOCK_SPACING: f32 = 80.0;
+/// Price of building one lock, in bucks
+pub const LOCK_PRICE: i64 = 400;
+/// How far from the quay a canal must be for a harbor to be built
+pub const HARBOR_REACH: f32 = 40.0;
+pub const HARBOR_WIDTH: f32 = 80.0;
+pub const HARBOR_LENGTH: f32 = 50.0;
+
+/// Number of (game) seconds between two barges calling at the same harbor
+const BARGE_INTERVAL: f64 = 6.0 * SECONDS_PER_HOUR as f64;
+/// Barges are slow: they take a long time to moor and to get loaded
+const DOCKING_DURATION: f64 = 0.5 * SECONDS_PER_HOUR as f64;
+const LOADING_DURATION: f64 = 2.0 * SECONDS_PER_HOUR as f64;
+/// Cargo carried by a barge, in tons
+pub const BARGE_CAPACITY: u32 = 400;
+
+/// Water level of the canals at the given height, a lock separates two consecutive levels.
+/// The levels are the same on every canal, so that the locks of a canal built in several
+/// pieces add up to the locks of the whole canal.
+fn water_level(z: f32) -> i32 {
+    (z / LOCK_RISE).round() as i32
+}
+
+/// Number of locks on a canal following the elevation profile, one for every water level it
+/// goes through, climbing or going down
+pub fn n_locks(profile: impl IntoIterator<Item = Vec3>) -> u32 {
+    let mut levels = profile.into_iter().map(|p| water_level(p.z));
+    let Some(mut prev) = levels.next() else {
+        return 0;
+    };
+    let mut locks = 0;
+    for level in levels {
+        locks += level.abs_diff(prev);
+        prev = level;
+    }
+    locks
+}
+
+/// Checks that the locks needed to climb between both ends of the canal fit along it
+pub fn check_canal(from: Vec3, to: Vec3) -> Result<(), &'static str> {
+    let locks = n_locks([from, to]);
+    if locks as f32 * LOCK_SPACING > from.xy().distance(to.xy()) {
+        return Err("Canal too steep, not enough room for the locks");
+    }
+    Ok(())
+}
+
+/// Checks that the harbor is built next to a canal
+pub fn check_harbor_footprint(map: &Map, obb: &OBB) -> Result<(), &'static str> {
+    let center = obb.center();
+    let Some(h) = map.environment.height(center) else {
+        return Err("Outside of the map");
+    };
+    let reach = HARBOR_REACH + obb.axis().iter().map(|a| a.mag()).fold(0.0, f32::max) * 0.5;
+    if map
+        .nearest_lane(center.z(h), LaneKind::Water, Some(reach))
+        .is_none()
+    {
+        return Err("Harbors must be built next to a canal");
+    }
+    Ok(())
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BargeState {
+    Docking { start: f64 },
+    Loading { until: f64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Harbor {
+    pub barge: Option<BargeState>,
+    pub next_barge: f64,
+    pub n_barges: u32,
+    pub exported_cargo: u32,
+    pub imported_cargo: u32,
+}
+
+/// Harbors keeps the barge schedule of every harbor.
+/// Barges call rarely but carry a lot, taking the goods delivered to the harbor to the external
+/// market and bringing the goods the city asked for.
+/// The barges don't sail on the canals, the goods carried on them are only priced by the
+/// transport costs, see [`crate::economy::TransportCosts`].
+#[derive(Default, Serialize, Deserialize)]
+pub struct Harbors {
+    pub harbors: BTreeMap<BuildingID, Harbor>,
+}
+
+pub fn harbor_system(world: &mut World, res: &mut Resources) {
+    profiling::scope!("transportation::harbor_system");
+    let time = res.read::<GameTime>().timestamp;
+    let map = res.read::<Map>();
+    let mut harbors = res.write::<Harbors>();
+
+    let built = map.bkinds.get(&BuildingKind::Harbor);
+    harbors
+        .harbors
+        .retain(|id, _| built.map_or(false, |v| v.contains(id)));
+    for &id in built.into_iter().flatten() {
+        harbors.harbors.entry(id).or_insert_with(|| Harbor {
+            barge: None,
+            next_barge: time + DOCKING_DURATION,
+            n_barges: 0,
+            exported_cargo: 0,
+            imported_cargo: 0,
+        });
+    }
+    drop(map);
+
+    for (&id, harbor) in harbors.harbors.iter_mut() {
+        match harbor.barge {
+            None => {
+                if time >= harbor.next_barge {
+                    harbor.n_barges += 1;
+                    harbor.barge = Some(BargeState::Docking { start: time });
+                }
+            }
+            Some(BargeState::Docking { start }) => {
+                if time >= start + DOCKING_DURATION {
+                    harbor.barge = Some(BargeState::Loading {
+                        until: time + LOADING_DURATION,
+                    });
+                }
+            }
+            Some(BargeState::Loading { until }) => {
+                if time < until {
+                    continue;
+                }
+                let (loaded, unloaded) = load_barge(world, id);
+                harbor.exported_cargo += loaded;
+                harbor.imported_cargo += unloaded;
+                harbor.barge = None;
+                harbor.next_barge = time + BARGE_INTERVAL;
+            }
+        }
+    }
+}
+
+/// Swaps the cargo delivered to the harbor with the cargo the city wants,
+/// returns how much was loaded and unloaded
+fn load_barge(world: &mut World, harbor: BuildingID) -> (u32, u32) {
+    let Some(station) = world
+        .freight_stations
+        .values_mut()
+        .find(|f| f.f.building == harbor)
+    else {
+        return (0, 0);
+    };
+    let loaded = station.f.waiting_cargo.min(BARGE_CAPACITY);
+    let unloaded = station.f.wanted_cargo.min(BARGE_CAPACITY);
+    station.f.waiting_cargo -= loaded;
+    station.f.wanted_cargo -= unloaded;
+    station.f.stats.cargo_moved(unloaded, loaded);
+    (loaded, unloaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geom::vec3;
+
+    #[test]
+    fn locks_need_room() {
+        let a = vec3(0.0, 0.0, 0.0);
+        assert_eq!(n_locks([a, vec3(500.0, 0.0, 2.0)]), 0);
+        assert_eq!(n_locks([a, vec3(500.0, 0.0, 12.0)]), 2);
+        assert!(check_canal(a, vec3(500.0, 0.0, 12.0)).is_ok());
+        assert!(check_canal(a, vec3(100.0, 0.0, 12.0)).is_err());
+        assert!(check_canal(a, vec3(10.0, 0.0, 0.0)).is_ok());
+    }
+
+    #[test]
+    fn locks_follow_the_profile() {
+        // climbing 20 meters in pieces of 2 meters still needs locks
+        let profile: Vec<Vec3> = (0..=10)
+            .map(|i| vec3(i as f32 * 100.0, 0.0, i as f32 * 2.0))
+            .collect();
+        assert_eq!(n_locks(profile.iter().copied()), 3);
+        let pieces: u32 = profile.windows(2).map(|w| n_locks([w[0], w[1]])).sum();
+        assert_eq!(pieces, 3);
+
+        // going over a hill needs locks on both sides
+        let hill = [
+            vec3(0.0, 0.0, 0.0),
+            vec3(300.0, 0.0, 12.0),
+            vec3(600.0, 0.0, 0.0),
+        ];
+        assert_eq!(n_locks(hill), 4);
+    }
+}
@@ -0,0 +1,142 @@
+//! Opt-in export of aggregate statistics of a save as csv files, for players who like
+//! spreadsheets and for balancing discussions.
+//! Only histograms and totals are exported, nothing identifies a human or a building.
+
+use crate::map_dynamic::{Journey, LegMode, TripStats};
+use crate::Simulation;
+use std::fmt::Write;
+
+/// Width of the bins of the commute time histogram, in minutes
+const COMMUTE_BIN: f32 = 5.0;
+/// Width of the bins of the trip length histogram, in meters
+const TRIP_LENGTH_BIN: f32 = 500.0;
+/// Width of the bins of the wage histogram, in money per hour
+const WAGE_BIN: f32 = 10.0;
+/// Histograms stop at this many bins, the last one gathers everything above
+const MAX_BINS: usize = 24;
+
+/// The csv files of the export, by name
+pub struct AnalyticsExport {
+    pub files: Vec<(&'static str, String)>,
+}
+
+impl AnalyticsExport {
+    pub fn new(sim: &Simulation) -> Self {
+        let trips = sim.read::<TripStats>();
+        let journeys: Vec<&Journey> = trips.journeys().collect();
+
+        let commutes = journeys
+            .iter()
+            .filter(|j| j.commute)
+            .map(|j| j.seconds / 60.0);
+        let lengths = journeys.iter().map(|j| j.distance);
+
+        Self {
+            files: vec![
+                (
+                    "commute_times",
+                    histogram_csv("minutes", COMMUTE_BIN, commutes),
+                ),
+                ("mode_share", mode_share_csv(&journeys)),
+                (
+                    "wages",
+                    histogram_csv("wage_per_hour", WAGE_BIN, wages(sim)),
+                ),
+                (
+                    "trip_lengths",
+                    histogram_csv("meters", TRIP_LENGTH_BIN, lengths),
+                ),
+            ],
+        }
+    }
+
+    /// Writes the files in the save folder, returns the paths that were written
+    pub fn save(&self, prefix: &str) -> Vec<String> {
+        let dir = common::saveload::save_dir();
+        let _ = std::fs::create_dir_all(&dir);
+        let mut written = vec![];
+        for (name, csv) in &self.files {
+            let path = format!("{}/{}_{}.csv", dir, prefix, name);
+            match std::fs::write(&path, csv) {
+                Ok(()) => written.push(path),
+                Err(e) => log::error!("could not write {}: {}", path, e),
+            }
+        }
+        written
+    }
+}
+
+/// Average hourly wage actually paid to every employed human
+fn wages(sim: &Simulation) -> Vec<f32> {
+    sim.world()
+        .humans
+        .values()
+        .filter(|h| h.work.is_some())
+        .filter_map(|h| h.earnings.hourly_wage())
+        .map(|wage| wage.inner() as f32 / 10000.0)
+        .collect()
+}
+
+fn mode_share_csv(journeys: &[&Journey]) -> String {
+    let mut csv = "mode,journeys,share,commutes,commute_share\n".to_string();
+    let n = journeys.len().max(1) as f32;
+    let n_commutes = journeys.iter().filter(|j| j.commute).count().max(1) as f32;
+    for (mode, name) in [
+        (LegMode::Walk, "walk"),
+        (LegMode::Drive, "drive"),
+        (LegMode::Bus, "bus"),
+    ] {
+        let all = journeys.iter().filter(|j| j.mode == mode).count();
+        let commutes = journeys
+            .iter()
+            .filter(|j| j.mode == mode && j.commute)
+            .count();
+        let _ = writeln!(
+            csv,
+            "{},{},{:.3},{},{:.3}",
+            name,
+            all,
+            all as f32 / n,
+            commutes,
+            commutes as f32 / n_commutes
+        );
+    }
+    csv
+}
+
+/// Counts the values in bins of width `bin`, the empty bins after the last value are left out
+fn histogram_csv(unit: &str, bin: f32, values: impl IntoIterator<Item = f32>) -> String {
+    let mut counts = [0u32; MAX_BINS];
+    for v in values {
+        let i = ((v.max(0.0) / bin) as usize).min(MAX_BINS - 1);
+        counts[i] += 1;
+    }
+    let n_bins = counts.iter().rposition(|&c| c > 0).map_or(0, |i| i + 1);
+
+    let mut csv = format!("{unit}_from,{unit}_to,count\n");
+    for (i, count) in counts.iter().take(n_bins).enumerate() {
+        let from = i as f32 * bin;
+        if i == MAX_BINS - 1 {
+            let _ = writeln!(csv, "{},,{}", from, count);
+        } else {
+            let _ = writeln!(csv, "{},{},{}", from, from + bin, count);
+        }
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_bins() {
+        let csv = histogram_csv("minutes", 5.0, [1.0, 4.0, 7.0, 1000.0]);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("minutes_from,minutes_to,count"));
+        assert_eq!(lines.next(), Some("0,5,2"));
+        assert_eq!(lines.next(), Some("5,10,1"));
+        assert_eq!(lines.clone().count(), MAX_BINS - 2);
+        assert_eq!(lines.last(), Some("115,,1"));
+    }
+}
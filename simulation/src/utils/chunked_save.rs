@@ -0,0 +1,208 @@
+//! Saves split in independent chunks, one per storage of the world and one per resource.
+//! Each chunk is encoded and compressed on its own so that saving and loading large cities
+//! is spread over all cores instead of going through a single stream.
+
+use crate::init::{ResourceInsert, INIT_FUNCS, SAVELOAD_FUNCS};
+use crate::utils::resources::Resources;
+use crate::{Simulation, World};
+use common::saveload::{Bincode, CompressedBincode, Encoder};
+use rayon::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::time::Instant;
+
+/// Prefix of the chunks of the world storages, resources use their registered name
+const WORLD_PREFIX: &str = "world/";
+
+/// A save as written to disk, see [`common::saveload::Chunked`]
+#[derive(Serialize, Deserialize)]
+pub struct ChunkedSave {
+    pub version: String,
+    /// Name of each chunk and its compressed data
+    pub chunks: Vec<(String, Vec<u8>)>,
+}
+
+/// Encodes every chunk of the simulation in parallel, uncompressed
+pub fn encode(sim: &Simulation) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let t = Instant::now();
+    let (world, resources) = rayon::join(
+        || encode_world(&sim.world),
+        || unsafe {
+            SAVELOAD_FUNCS
+                .par_iter()
+                .map(|l| (l.name.to_string(), (l.save)(sim)))
+                .collect::<Vec<_>>()
+        },
+    );
+    let mut chunks = world?;
+    chunks.extend(resources);
+    log::info!("took {}s to encode the chunks", t.elapsed().as_secs_f32());
+    Ok(chunks)
+}
+
+/// Compresses the encoded chunks in parallel
+pub fn compress(version: &str, chunks: &[(String, Vec<u8>)]) -> ChunkedSave {
+    let t = Instant::now();
+    let chunks = chunks
+        .par_iter()
+        .map(|(name, data)| (name.clone(), CompressedBincode::compress(data)))
+        .collect();
+    log::info!("took {}s to compress the chunks", t.elapsed().as_secs_f32());
+    ChunkedSave {
+        version: version.to_string(),
+        chunks,
+    }
+}
+
+/// Decompresses and decodes the chunks in parallel.
+/// Chunks that are missing or fail to decode are left to their initial value.
+pub fn decode(save: ChunkedSave) -> Simulation {
+    let t = Instant::now();
+    crate::check_save_version(&save.version);
+
+    let chunks: Vec<(String, Vec<u8>)> = save
+        .chunks
+        .into_par_iter()
+        .filter_map(|(name, data)| match CompressedBincode::decompress(&data) {
+            Ok(data) => Some((name, data)),
+            Err(e) => {
+                log::error!("Error decompressing chunk {}: {}", name, e);
+                None
+            }
+        })
+        .collect();
+    let sim = decode_chunks(&chunks);
+    log::info!("took {}s to decode the chunks", t.elapsed().as_secs_f32());
+    sim
+}
+
+/// Copies the simulation by encoding and decoding its chunks, without compressing them.
+/// The copy can be ticked on its own to see what would happen without touching the original.
+pub fn fork(sim: &Simulation) -> io::Result<Simulation> {
+    Ok(decode_chunks(&encode(sim)?))
+}
+
+/// Decodes the uncompressed chunks in parallel
+fn decode_chunks(chunks: &[(String, Vec<u8>)]) -> Simulation {
+    let chunk = |name: &str| {
+        chunks
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, data)| &**data)
+    };
+
+    let mut sim = Simulation {
+        world: World::default(),
+        resources: Resources::default(),
+    };
+
+    unsafe {
+        for s in &INIT_FUNCS {
+            (s.f)(&mut sim);
+        }
+    }
+
+    let (world, inserts) = rayon::join(
+        || decode_world(&chunk),
+        || unsafe {
+            SAVELOAD_FUNCS
+                .par_iter()
+                .filter_map(|l| (l.decode)(chunk(l.name)?))
+                .collect::<Vec<ResourceInsert>>()
+        },
+    );
+
+    sim.world = world;
+    for insert in inserts {
+        insert(&mut sim);
+    }
+    crate::transportation::relink_parked_vehicles(&mut sim);
+    sim
+}
+
+fn encode_world(world: &World) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let storages: [(&str, &(dyn Fn() -> io::Result<Vec<u8>> + Sync)); 9] = [
+        ("vehicles", &|| Bincode::encode(&world.vehicles)),
+        ("humans", &|| Bincode::encode(&world.humans)),
+        ("trains", &|| Bincode::encode(&world.trains)),
+        ("wagons", &|| Bincode::encode(&world.wagons)),
+        ("freight_stations", &|| {
+            Bincode::encode(&world.freight_stations)
+        }),
+        ("companies", &|| Bincode::encode(&world.companies)),
+        ("birds", &|| Bincode::encode(&world.birds)),
+        ("flocks", &|| Bincode::encode(&world.flocks)),
+        ("buses", &|| Bincode::encode(&world.buses)),
+    ];
+    storages
+        .par_iter()
+        .map(|(name, encode)| Ok((format!("{WORLD_PREFIX}{name}"), encode()?)))
+        .collect()
+}
+
+fn decode_world<'a>(chunk: &(dyn Fn(&str) -> Option<&'a [u8]> + Sync)) -> World {
+    let mut world = World::default();
+    let World {
+        vehicles,
+        humans,
+        trains,
+        wagons,
+        freight_stations,
+        companies,
+        birds,
+        flocks,
+        buses,
+    } = &mut world;
+
+    rayon::scope(|s| {
+        s.spawn(|_| decode_storage(chunk, "vehicles", vehicles));
+        s.spawn(|_| decode_storage(chunk, "humans", humans));
+        s.spawn(|_| decode_storage(chunk, "trains", trains));
+        s.spawn(|_| decode_storage(chunk, "wagons", wagons));
+        s.spawn(|_| decode_storage(chunk, "freight_stations", freight_stations));
+        s.spawn(|_| decode_storage(chunk, "companies", companies));
+        s.spawn(|_| decode_storage(chunk, "birds", birds));
+        s.spawn(|_| decode_storage(chunk, "flocks", flocks));
+        s.spawn(|_| decode_storage(chunk, "buses", buses));
+    });
+
+    world
+}
+
+fn decode_storage<'a, T: DeserializeOwned>(
+    chunk: &(dyn Fn(&str) -> Option<&'a [u8]> + Sync),
+    name: &str,
+    storage: &mut T,
+) {
+    let Some(data) = chunk(&format!("{WORLD_PREFIX}{name}")) else {
+        log::error!("Missing world chunk {}", name);
+        return;
+    };
+    match Bincode::decode(data) {
+        Ok(v) => *storage = v,
+        Err(e) => log::error!("Error loading world chunk {}: {}", name, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::TestCtx;
+    use crate::utils::snapshot_diff::diff_simulations;
+    use geom::vec3;
+
+    #[test]
+    fn chunked_roundtrip() {
+        let test = TestCtx::new();
+        test.build_roads(&[
+            vec3(0.0, 0.0, 0.0),
+            vec3(100.0, 0.0, 0.0),
+            vec3(100.0, 100.0, 0.0),
+        ]);
+
+        let chunks = super::encode(&test.g).unwrap();
+        let loaded = super::decode(super::compress(crate::VERSION, &chunks));
+
+        assert!(diff_simulations(&test.g, &loaded).is_empty());
+    }
+}
@@ -1,7 +1,7 @@
 use arc_swap::{ArcSwap, Guard};
 use common::saveload::Encoder;
 use egui_inspect::Inspect;
-use geom::Color;
+use geom::{Color, Curve};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -33,6 +33,7 @@ pub struct Config {
     pub road_hig_col: Color,
     pub road_line_col: Color,
     pub road_pylon_col: Color,
+    pub road_bus_col: Color,
 
     pub lot_unassigned_col: Color,
     pub lot_residential_col: Color,
@@ -40,6 +41,9 @@ pub struct Config {
 
     pub special_building_col: Color,
     pub special_building_invalid_col: Color,
+
+    /// Multiplier of the sun light depending on the hour of the day
+    pub sun_intensity: Curve,
 }
 
 fn load_config_start() -> Config {
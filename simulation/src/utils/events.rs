@@ -0,0 +1,90 @@
+use crate::utils::resources::Resources;
+use crate::utils::time::Tick;
+use crate::World;
+use geom::Vec3;
+use std::collections::VecDeque;
+
+/// Number of ticks an event is kept around before being discarded
+pub const EVENT_LIFETIME_TICKS: u64 = 50;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SimEventKind {
+    Construction,
+    TrainArrived,
+    /// A fire, a burglary or a medical emergency, see [`crate::map_dynamic::BuildingRisks`]
+    Incident,
+}
+
+/// Something that happened in the simulation that the render layer might want to react to
+/// with a sound or an animation.
+#[derive(Debug, Copy, Clone)]
+pub struct SimEvent {
+    pub tick: Tick,
+    pub pos: Vec3,
+    pub kind: SimEventKind,
+}
+
+/// SimEvents keeps the events of the last few ticks.
+/// Events are not consumed so that the render layer can look at all the ticks that happened
+/// since the last frame and decide to skip or aggregate them when the time warp is high.
+#[derive(Default)]
+pub struct SimEvents {
+    events: VecDeque<SimEvent>,
+}
+
+impl SimEvents {
+    pub fn push(&mut self, tick: Tick, pos: Vec3, kind: SimEventKind) {
+        self.events.push_back(SimEvent { tick, pos, kind });
+    }
+
+    /// Returns the events that happened strictly after the given tick
+    pub fn since(&self, tick: Tick) -> impl Iterator<Item = &SimEvent> + '_ {
+        let start = self.events.partition_point(|e| e.tick <= tick);
+        self.events.range(start..)
+    }
+
+    /// Returns the events that happened during the given tick
+    pub fn during(&self, tick: Tick) -> impl Iterator<Item = &SimEvent> + '_ {
+        let start = self.events.partition_point(|e| e.tick < tick);
+        self.events
+            .range(start..)
+            .take_while(move |e| e.tick == tick)
+    }
+
+    fn prune(&mut self, now: Tick) {
+        while let Some(e) = self.events.front() {
+            if e.tick.0 + EVENT_LIFETIME_TICKS >= now.0 {
+                break;
+            }
+            self.events.pop_front();
+        }
+    }
+}
+
+pub fn sim_events_prune(_: &mut World, resources: &mut Resources) {
+    profiling::scope!("utils::sim_events_prune");
+    let now = *resources.read::<Tick>();
+    resources.write::<SimEvents>().prune(now);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn since_and_prune() {
+        let mut events = SimEvents::default();
+        events.push(Tick(1), Vec3::ZERO, SimEventKind::Construction);
+        events.push(Tick(5), Vec3::ZERO, SimEventKind::TrainArrived);
+        events.push(Tick(5), Vec3::ZERO, SimEventKind::Construction);
+
+        assert_eq!(events.since(Tick(0)).count(), 3);
+        assert_eq!(events.since(Tick(1)).count(), 2);
+        assert_eq!(events.since(Tick(5)).count(), 0);
+        assert_eq!(events.during(Tick(5)).count(), 2);
+        assert_eq!(events.during(Tick(3)).count(), 0);
+
+        events.prune(Tick(EVENT_LIFETIME_TICKS + 2));
+        assert_eq!(events.since(Tick(0)).count(), 2);
+    }
+}
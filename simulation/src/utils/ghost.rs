@@ -0,0 +1,296 @@
+//! Ghost runs compare how long the same trips take before and after a corridor is rebuilt.
+//! The trips going through the corridor are captured, then each of them is driven again by a
+//! ghost car in two forks of the simulation taken at the same tick, so the real city is never
+//! touched. The corridor is put back as it was captured in the first fork by undoing the map
+//! edits made since the capture.
+
+use crate::map::{PathKind, RoadID, TraverseKind};
+use crate::map_dynamic::{Itinerary, ItineraryIndex};
+use crate::transportation::{spawn_parked_vehicle, unpark, VehicleKind};
+use crate::utils::chunked_save;
+use crate::utils::scheduler::SeqSchedule;
+use crate::utils::time::{GameTime, Tick};
+use crate::world::{AnyEntity, VehicleEnt};
+use crate::world_command::{Inverse, UndoableEdits};
+use crate::{ParCommandBuffer, Simulation, VehicleID};
+use geom::Vec3;
+use std::collections::BTreeSet;
+
+/// Ghosts that haven't arrived after this many game seconds are given up on
+pub const GHOST_TIMEOUT: f64 = 2.0 * GameTime::HOUR as f64;
+
+/// A trip that went through the corridor, from where it started to its destination
+#[derive(Debug, Copy, Clone)]
+pub struct CapturedTrip {
+    pub from: Vec3,
+    pub to: Vec3,
+}
+
+/// The trips going through a corridor when the traffic was captured
+#[derive(Debug, Clone)]
+pub struct TrafficCapture {
+    pub corridor: BTreeSet<RoadID>,
+    pub trips: Vec<CapturedTrip>,
+    pub tick: Tick,
+    /// Index of the first map edit made after the capture, see [`UndoableEdits`]
+    pub edit: u64,
+}
+
+impl TrafficCapture {
+    /// Captures the cars whose current route goes through one of the roads
+    pub fn capture(sim: &Simulation, corridor: BTreeSet<RoadID>) -> Self {
+        let index = sim.read::<ItineraryIndex>();
+        let map = sim.map();
+
+        let vehicles: BTreeSet<VehicleID> = corridor
+            .iter()
+            .flat_map(|&road| index.routed_through(road))
+            .filter_map(|ent| match ent {
+                AnyEntity::VehicleID(id) => Some(id),
+                _ => None,
+            })
+            .collect();
+
+        let trips = vehicles
+            .into_iter()
+            .filter_map(|id| {
+                let route = sim.world.vehicles.get(id)?.it.get_route()?;
+                let TraverseKind::Lane(start) = route.query.start.kind else {
+                    return None;
+                };
+                Some(CapturedTrip {
+                    from: map.lanes().get(start)?.points.first(),
+                    to: route.end_pos,
+                })
+            })
+            .collect();
+
+        Self {
+            corridor,
+            trips,
+            tick: *sim.read::<Tick>(),
+            edit: sim.read::<UndoableEdits>().next(),
+        }
+    }
+}
+
+/// The captured trips driven on the corridor as it was captured and as it is now,
+/// both runs start from the same tick of the simulation
+pub struct GhostComparison {
+    pub before: GhostRun,
+    pub after: GhostRun,
+}
+
+impl GhostComparison {
+    pub fn new(sim: &Simulation, capture: &TrafficCapture) -> Result<Self, &'static str> {
+        let edits = sim.read::<UndoableEdits>();
+        let inverses: Vec<Inverse> = edits
+            .since(capture.edit)
+            .map(|(_, inverse)| inverse.clone())
+            .collect();
+        if edits.next() < capture.edit {
+            return Err("Another city was loaded since the capture");
+        }
+        if inverses.len() as u64 != edits.next() - capture.edit {
+            return Err("Too many map edits were made since the capture");
+        }
+        drop(edits);
+
+        let mut before = chunked_save::fork(sim).map_err(|e| {
+            log::error!("failed forking the simulation: {}", e);
+            "Could not copy the city"
+        })?;
+        let after = chunked_save::fork(sim).map_err(|e| {
+            log::error!("failed forking the simulation: {}", e);
+            "Could not copy the city"
+        })?;
+
+        for inverse in inverses.iter().rev() {
+            let command = inverse.resolve(&before.map());
+            // the edits the corridor can't be put back without are reported by the run
+            let _ = command.apply_paid(&mut before);
+        }
+
+        Ok(Self {
+            before: GhostRun::new(before, capture),
+            after: GhostRun::new(after, capture),
+        })
+    }
+
+    /// Advances both runs by `n_ticks`, returns true once both are finished
+    pub fn advance(&mut self, schedule: &mut SeqSchedule, n_ticks: usize) -> bool {
+        let before = self.before.advance(schedule, n_ticks);
+        let after = self.after.advance(schedule, n_ticks);
+        before && after
+    }
+}
+
+/// The captured trips driven by ghost cars in a fork of the simulation
+pub struct GhostRun {
+    sim: Simulation,
+    /// The ghost of each trip, `None` once it arrived or if it couldn't be spawned
+    ghosts: Vec<Option<VehicleID>>,
+    /// Travel time of each trip in seconds, `None` until its ghost arrives
+    travel_times: Vec<Option<f32>>,
+    start: f64,
+}
+
+impl GhostRun {
+    /// Spawns a ghost car at the start of each captured trip in the fork
+    fn new(mut fork: Simulation, capture: &TrafficCapture) -> Self {
+        let start = fork.read::<GameTime>().timestamp;
+        let ghosts = capture
+            .trips
+            .iter()
+            .map(|trip| spawn_ghost(&mut fork, trip))
+            .collect();
+
+        Self {
+            sim: fork,
+            ghosts,
+            travel_times: vec![None; capture.trips.len()],
+            start,
+        }
+    }
+
+    /// Advances the fork by `n_ticks`, returns true once every ghost arrived or timed out
+    pub fn advance(&mut self, schedule: &mut SeqSchedule, n_ticks: usize) -> bool {
+        for _ in 0..n_ticks {
+            if self.is_finished() {
+                break;
+            }
+            self.sim.tick(schedule, &[]);
+            self.despawn_arrived();
+        }
+        self.is_finished()
+    }
+
+    fn despawn_arrived(&mut self) {
+        let elapsed = self.elapsed() as f32;
+        let cbuf = self.sim.read::<ParCommandBuffer<VehicleEnt>>();
+        for (ghost, time) in self.ghosts.iter_mut().zip(&mut self.travel_times) {
+            let Some(id) = *ghost else {
+                continue;
+            };
+            match self.sim.world.vehicles.get(id) {
+                Some(v) if v.it.has_ended(0.0) => {
+                    cbuf.kill(id);
+                    *time = Some(elapsed);
+                    *ghost = None;
+                }
+                Some(_) => {}
+                None => *ghost = None,
+            }
+        }
+    }
+
+    /// Game seconds since the fork
+    pub fn elapsed(&self) -> f64 {
+        self.sim.read::<GameTime>().timestamp - self.start
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.ghosts.iter().all(Option::is_none) || self.elapsed() > GHOST_TIMEOUT
+    }
+
+    /// Travel time of each captured trip in seconds, `None` if its ghost didn't arrive
+    pub fn travel_times(&self) -> &[Option<f32>] {
+        &self.travel_times
+    }
+
+    /// The fork the ghosts drive in
+    pub fn sim(&self) -> &Simulation {
+        &self.sim
+    }
+
+    /// Where the ghosts still driving are
+    pub fn ghost_positions(&self) -> impl Iterator<Item = Vec3> + '_ {
+        self.ghosts
+            .iter()
+            .flatten()
+            .filter_map(|&id| self.sim.pos(id))
+    }
+}
+
+fn spawn_ghost(sim: &mut Simulation, trip: &CapturedTrip) -> Option<VehicleID> {
+    let id = spawn_parked_vehicle(sim, VehicleKind::Car, trip.from)?;
+    unpark(sim, id);
+
+    let tick = *sim.read::<Tick>();
+    let it = Itinerary::route(
+        tick,
+        sim.world.vehicles.get(id)?.trans.position,
+        trip.to,
+        &sim.map(),
+        PathKind::Vehicle,
+    );
+    let Some(it) = it else {
+        sim.read::<ParCommandBuffer<VehicleEnt>>().kill(id);
+        return None;
+    };
+    sim.world.vehicles.get_mut(id)?.it = it;
+    Some(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use crate::world_command::WorldCommand;
+    use geom::vec3;
+
+    #[test]
+    fn ghosts_drive_the_corridor_before_and_after() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[
+            vec3(0.0, 0.0, 0.0),
+            vec3(200.0, 0.0, 0.0),
+            vec3(200.0, 200.0, 0.0),
+        ]);
+        let trip = CapturedTrip {
+            from: vec3(20.0, 0.0, 0.0),
+            to: vec3(200.0, 180.0, 0.0),
+        };
+        let capture = TrafficCapture {
+            trips: vec![trip],
+            ..TrafficCapture::capture(&test.g, BTreeSet::new())
+        };
+
+        // the corridor is cut after the capture
+        let last = test
+            .g
+            .map()
+            .roads()
+            .values()
+            .find(|r| r.points.last().is_close(vec3(200.0, 200.0, 0.0), 1.0))
+            .unwrap()
+            .id;
+        test.apply(&[WorldCommand::MapRemoveRoad(last)]);
+
+        let mut comparison = GhostComparison::new(&test.g, &capture).unwrap();
+        assert_eq!(comparison.before.sim().map().roads().len(), 2);
+        assert_eq!(comparison.after.sim().map().roads().len(), 1);
+        assert!(test.g.world().vehicles.is_empty());
+
+        let tick = comparison.before.sim().get_tick();
+        assert_eq!(tick, comparison.after.sim().get_tick());
+
+        let mut schedule = Simulation::schedule();
+        let mut finished = false;
+        for _ in 0..100 {
+            finished = comparison.advance(&mut schedule, 100);
+            if finished {
+                break;
+            }
+        }
+        assert!(finished);
+        assert!(comparison.before.sim().get_tick() > tick);
+
+        // the trip can only be driven on the corridor as it was captured
+        let [before] = comparison.before.travel_times() else {
+            unreachable!()
+        };
+        assert!(before.map_or(false, |t| t > 0.0));
+        assert_eq!(comparison.after.travel_times(), &[None]);
+    }
+}
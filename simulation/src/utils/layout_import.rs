@@ -0,0 +1,261 @@
+use crate::map::{BuildingKind, Zone};
+use crate::souls::goods_company::GoodsCompanyRegistry;
+use crate::transportation::airport::{AIRPORT_LENGTH, AIRPORT_WIDTH};
+use crate::transportation::waterway::{HARBOR_LENGTH, HARBOR_WIDTH};
+use crate::world_command::{CommandError, WorldCommand};
+use crate::Simulation;
+use common::descriptions::{BuildingGen, DoorLayout};
+use common::saveload::{Encoder, JSON};
+use geom::{Degrees, Intersect, Polygon, Vec2, OBB};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// A building to place, as written in a layout file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Placement {
+    /// Name of a company description (e.g. "Bakery"), or one of "Snow plow depot", "Airport",
+    /// "Harbor", "Parking lot", "Event venue"
+    pub kind: String,
+    pub x: f32,
+    pub y: f32,
+    /// In degrees, counter-clockwise
+    #[serde(default)]
+    pub rotation: f32,
+}
+
+/// Why a row of a layout file was not imported
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportError {
+    Parse(String),
+    UnknownKind(String),
+    /// Overlaps the building of an earlier row of the same file
+    OverlapsRow(usize),
+    Rejected(CommandError),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Parse(reason) => write!(f, "Could not read the row: {reason}"),
+            ImportError::UnknownKind(kind) => write!(f, "Unknown building kind \"{kind}\""),
+            ImportError::OverlapsRow(row) => {
+                write!(f, "Intersecting with the building of row {row}")
+            }
+            ImportError::Rejected(err) => err.fmt(f),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowFailure {
+    /// Line of the csv file or index in the json list, starting at 1.
+    /// 0 means the whole file could not be read.
+    pub row: usize,
+    pub error: ImportError,
+}
+
+/// The result of importing a layout: the commands to apply and the rows that were left out
+#[derive(Debug, Default)]
+pub struct LayoutImport {
+    pub commands: Vec<WorldCommand>,
+    pub failures: Vec<RowFailure>,
+}
+
+impl LayoutImport {
+    /// Validates every placement against the current state of the simulation.
+    /// Nothing is applied: the commands are meant to go through the usual command queue.
+    pub fn new(sim: &Simulation, text: &str) -> Self {
+        let mut import = LayoutImport::default();
+        let mut placed: Vec<(usize, OBB)> = vec![];
+
+        for (row, placement) in parse_layout(text) {
+            let checked = placement.and_then(|p| {
+                let (command, obb) = p.to_command(sim)?;
+                command.validate(sim).map_err(ImportError::Rejected)?;
+                if let Some(&(other, _)) = placed.iter().find(|(_, o)| o.intersects(&obb)) {
+                    return Err(ImportError::OverlapsRow(other));
+                }
+                Ok((command, obb))
+            });
+            match checked {
+                Ok((command, obb)) => {
+                    placed.push((row, obb));
+                    import.commands.push(command);
+                }
+                Err(error) => import.failures.push(RowFailure { row, error }),
+            }
+        }
+
+        import
+    }
+}
+
+impl Placement {
+    fn to_command(&self, sim: &Simulation) -> Result<(WorldCommand, OBB), ImportError> {
+        let centered = BuildingGen::CenteredDoor {
+            vertical_factor: 1.0,
+            doors: DoorLayout::DRIVEWAY,
+        };
+        let (kind, gen, w, h, has_zone) = match self.kind.to_lowercase().as_str() {
+            "snow plow depot" => (BuildingKind::SnowPlowDepot, centered, 40.0, 40.0, false),
+            "parking lot" => (BuildingKind::ParkingLot, centered, 40.0, 40.0, false),
+            "event venue" => (BuildingKind::EventVenue, centered, 80.0, 80.0, false),
+            "airport" => (
+                BuildingKind::Airport,
+                centered,
+                AIRPORT_WIDTH,
+                AIRPORT_LENGTH,
+                false,
+            ),
+            "harbor" => (
+                BuildingKind::Harbor,
+                centered,
+                HARBOR_WIDTH,
+                HARBOR_LENGTH,
+                false,
+            ),
+            name => {
+                let registry = sim.read::<GoodsCompanyRegistry>();
+                let descr = registry
+                    .descriptions
+                    .values()
+                    .find(|d| d.name.to_lowercase() == name)
+                    .ok_or_else(|| ImportError::UnknownKind(self.kind.clone()))?;
+                (
+                    BuildingKind::GoodsCompany(descr.id),
+                    descr.bgen,
+                    descr.size,
+                    descr.size,
+                    descr.zone.is_some(),
+                )
+            }
+        };
+
+        let obb = OBB::new(
+            Vec2::new(self.x, self.y),
+            Degrees(self.rotation).vec2(),
+            w,
+            h,
+        );
+        let command = WorldCommand::MapBuildSpecialBuilding {
+            pos: obb,
+            kind,
+            gen,
+            zone: has_zone.then(|| Zone::new(Polygon::from(obb.corners.as_slice()), Vec2::X)),
+        };
+        Ok((command, obb))
+    }
+}
+
+/// Reads a layout given either as a json list of placements or as csv lines of
+/// `kind,x,y[,rotation]`. The csv header and the lines starting with `#` are skipped.
+pub fn parse_layout(text: &str) -> Vec<(usize, Result<Placement, ImportError>)> {
+    if text.trim_start().starts_with('[') {
+        return match JSON::decode::<Vec<Placement>>(text.as_bytes()) {
+            Ok(placements) => placements
+                .into_iter()
+                .enumerate()
+                .map(|(i, p)| (i + 1, Ok(p)))
+                .collect(),
+            Err(e) => vec![(0, Err(ImportError::Parse(e.to_string())))],
+        };
+    }
+
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .filter(|(_, line)| !line.to_lowercase().starts_with("kind,"))
+        .map(|(row, line)| (row, parse_csv_row(line)))
+        .collect()
+}
+
+fn parse_csv_row(line: &str) -> Result<Placement, ImportError> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    if !(3..=4).contains(&fields.len()) {
+        return Err(ImportError::Parse(format!(
+            "expected 3 or 4 fields, got {}",
+            fields.len()
+        )));
+    }
+    let number = |name: &str, v: &str| {
+        v.parse::<f32>()
+            .ok()
+            .filter(|x| x.is_finite())
+            .ok_or_else(|| ImportError::Parse(format!("{name} \"{v}\" is not a number")))
+    };
+    Ok(Placement {
+        kind: fields[0].to_string(),
+        x: number("x", fields[1])?,
+        y: number("y", fields[2])?,
+        rotation: match fields.get(3) {
+            Some(v) if !v.is_empty() => number("rotation", v)?,
+            _ => 0.0,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+
+    #[test]
+    fn parse_csv_and_json() {
+        let csv = "kind,x,y,rotation\n# comment\nBakery, 10, 20, 90\n\nFarm,1,2\nBakery,a,2\n";
+        let rows = parse_layout(csv);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[0],
+            (
+                3,
+                Ok(Placement {
+                    kind: "Bakery".to_string(),
+                    x: 10.0,
+                    y: 20.0,
+                    rotation: 90.0
+                })
+            )
+        );
+        assert_eq!(rows[1].0, 5);
+        assert!(matches!(rows[2], (6, Err(ImportError::Parse(_)))));
+
+        let json = r#"[{"kind": "Airport", "x": 5.0, "y": 6.0}]"#;
+        let rows = parse_layout(json);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].1.as_ref().unwrap().rotation, 0.0);
+
+        assert!(matches!(
+            parse_layout("[{\"kind\": 3}]")[0],
+            (0, Err(ImportError::Parse(_)))
+        ));
+    }
+
+    #[test]
+    fn import_reports_failures() {
+        let test = TestCtx::new();
+        let csv = "Bakery,100,100,0\nBakery,102,100,0\nMoon base,300,100\nBakery,-5000,-5000\n";
+        let import = LayoutImport::new(&test.g, csv);
+
+        assert_eq!(import.commands.len(), 1);
+        assert_eq!(
+            import.failures,
+            vec![
+                RowFailure {
+                    row: 2,
+                    error: ImportError::OverlapsRow(1)
+                },
+                RowFailure {
+                    row: 3,
+                    error: ImportError::UnknownKind("Moon base".to_string())
+                },
+                RowFailure {
+                    row: 4,
+                    error: ImportError::Rejected(CommandError::InvalidPlacement(
+                        "Outside of the map"
+                    ))
+                },
+            ]
+        );
+    }
+}
@@ -1,9 +1,23 @@
+pub mod analytics;
+pub mod chunked_save;
 pub mod config;
+pub mod events;
+pub mod ghost;
+pub mod layout_import;
 pub mod par_command_buffer;
+pub mod pool;
 pub mod rand_provider;
+pub mod render_snapshot;
 pub mod replay;
 pub mod resources;
+pub mod scenario;
+pub mod scenario_result;
 pub mod scheduler;
+pub mod sim_snapshot;
+pub mod snapshot_diff;
+pub mod sumo;
 pub mod time;
+pub mod timelapse;
+pub mod tutorial;
 
 pub use config::*;
@@ -0,0 +1,28 @@
+use crate::world::HumanEnt;
+
+/// Maximum number of dead humans kept around for reuse
+const MAX_POOLED_HUMANS: usize = 200;
+
+/// EntityPool recycles what despawned pedestrians leave behind.
+/// Dead humans are kept so their allocations can be reused by the next ones to spawn, which
+/// saves a lot of allocations when people move in and out of the city.
+/// Their colliders are removed from the collision world when they die, like any other entity.
+#[derive(Default)]
+pub struct EntityPool {
+    humans: Vec<HumanEnt>,
+}
+
+impl EntityPool {
+    pub fn take_human(&mut self) -> Option<HumanEnt> {
+        self.humans.pop()
+    }
+
+    /// Keeps a dead human around, its collider must already be removed
+    pub fn recycle_human(&mut self, h: HumanEnt) {
+        debug_assert!(h.collider.is_none());
+        if self.humans.len() >= MAX_POOLED_HUMANS {
+            return;
+        }
+        self.humans.push(h);
+    }
+}
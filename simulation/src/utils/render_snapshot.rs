@@ -0,0 +1,197 @@
+//! The render snapshot is a cheap copy of the moving entities, the time and the money, taken
+//! once the ticks of the frame are done. Reading it instead of the simulation means those never
+//! mix the state of two ticks. The map renderer and most GUI windows still read the simulation.
+
+use crate::economy::{Government, Money};
+use crate::transportation::airport::{plane_transform, Airports};
+use crate::transportation::train::RailWagonKind;
+use crate::transportation::{Location, ParkedIdle, VehicleKind, VehicleState};
+use crate::utils::time::{GameTime, Tick};
+use crate::Simulation;
+use geom::{Color, Vec3};
+use std::sync::{Arc, Mutex};
+
+/// Position and direction of an agent, as drawn
+#[derive(Debug, Copy, Clone)]
+pub struct AgentPose {
+    pub pos: Vec3,
+    pub dir: Vec3,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct VehiclePose {
+    pub pose: AgentPose,
+    pub roll: f32,
+    /// Tint of the body, with the idle state of the parked vehicles
+    pub tint: Color,
+    pub kind: VehicleKind,
+}
+
+#[derive(Debug)]
+pub struct RenderSnapshot {
+    pub tick: Tick,
+    pub time: GameTime,
+    pub money: Money,
+    pub population: u32,
+    pub vehicles: Vec<VehiclePose>,
+    pub wagons: Vec<(AgentPose, RailWagonKind)>,
+    /// Only the humans walking outside
+    pub pedestrians: Vec<AgentPose>,
+    pub birds: Vec<AgentPose>,
+    pub planes: Vec<AgentPose>,
+    /// Position of the agents waiting for a new path, with how many ticks they waited
+    pub waiting_reroute: Vec<(Vec3, u16)>,
+}
+
+impl Default for RenderSnapshot {
+    fn default() -> Self {
+        Self {
+            tick: Tick::default(),
+            time: GameTime::new(0.0, 0.0),
+            money: Money::default(),
+            population: 0,
+            vehicles: vec![],
+            wagons: vec![],
+            pedestrians: vec![],
+            birds: vec![],
+            planes: vec![],
+            waiting_reroute: vec![],
+        }
+    }
+}
+
+impl RenderSnapshot {
+    /// Overwrites the snapshot with the current state, keeping the allocations
+    pub fn fill(&mut self, sim: &Simulation) {
+        profiling::scope!("render_snapshot::fill");
+        let world = sim.world();
+
+        self.tick = *sim.read::<Tick>();
+        self.time = *sim.read::<GameTime>();
+        self.money = sim.read::<Government>().money;
+        self.population = world.humans.len() as u32;
+
+        self.vehicles.clear();
+        let time = self.time;
+        self.vehicles.extend(world.vehicles.iter().map(|(id, v)| {
+            let trans = &v.trans;
+            let visual = &v.vehicle.visual;
+            let tint = match v.vehicle.state {
+                VehicleState::Parked(_) => ParkedIdle::at(id, &time).tint(v.vehicle.tint),
+                _ => v.vehicle.tint,
+            };
+            VehiclePose {
+                pose: AgentPose {
+                    pos: (trans.position + v.vehicle.pull_over_offset(trans.dir)).up(visual.bounce),
+                    dir: visual.body_dir(trans.dir),
+                },
+                roll: visual.roll,
+                tint,
+                kind: v.vehicle.kind,
+            }
+        }));
+
+        self.wagons.clear();
+        self.wagons.extend(world.wagons.values().map(|w| {
+            let pose = AgentPose {
+                pos: w.trans.position,
+                dir: w.trans.dir,
+            };
+            (pose, w.wagon.kind)
+        }));
+
+        self.pedestrians.clear();
+        self.pedestrians.extend(
+            world
+                .humans
+                .values()
+                .filter(|h| matches!(h.location, Location::Outside))
+                .map(|h| AgentPose {
+                    pos: h
+                        .trans
+                        .position
+                        .up(0.5 + 0.4 * h.pedestrian.walk_anim.cos()),
+                    dir: h.trans.dir.xy().z0(),
+                }),
+        );
+
+        self.birds.clear();
+        self.birds.extend(world.birds.values().map(|b| AgentPose {
+            pos: b.trans.position,
+            dir: b.trans.dir,
+        }));
+
+        self.planes.clear();
+        let map = sim.map();
+        for (&id, airport) in &sim.read::<Airports>().airports {
+            let (Some(plane), Some(b)) = (airport.plane, map.buildings().get(id)) else {
+                continue;
+            };
+            let (pos, dir) = plane_transform(&b.obb, b.height, &plane, self.time.timestamp);
+            self.planes.push(AgentPose { pos, dir });
+        }
+
+        self.waiting_reroute.clear();
+        for (_, (trans, itin)) in world.query_trans_itin() {
+            if let Some(wait) = itin.is_wait_for_reroute() {
+                self.waiting_reroute.push((trans.position, wait));
+            }
+        }
+    }
+}
+
+/// SnapshotBuffer double buffers the render snapshots: the next snapshot is written in the back
+/// buffer while the readers keep the front one, then the two are swapped.
+/// Readers hold on to the snapshot they got for as long as they need, it is never modified.
+#[derive(Default)]
+pub struct SnapshotBuffer {
+    front: Mutex<Arc<RenderSnapshot>>,
+    back: Mutex<Option<RenderSnapshot>>,
+}
+
+impl SnapshotBuffer {
+    /// The last published snapshot
+    pub fn latest(&self) -> Arc<RenderSnapshot> {
+        self.front.lock().unwrap().clone()
+    }
+
+    /// Publishes the current state of the simulation, to be called once the ticks are done
+    pub fn publish(&self, sim: &Simulation) {
+        let mut back = self.back.lock().unwrap().take().unwrap_or_default();
+        back.fill(sim);
+        let old = std::mem::replace(&mut *self.front.lock().unwrap(), Arc::new(back));
+        // the old front is reused for the next snapshot unless a reader still holds it
+        *self.back.lock().unwrap() = Arc::try_unwrap(old).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+
+    #[test]
+    fn readers_keep_their_snapshot() {
+        let mut test = TestCtx::new();
+        let buffer = SnapshotBuffer::default();
+        buffer.publish(&test.g);
+        let held = buffer.latest();
+        assert_eq!(held.tick, *test.g.read::<Tick>());
+
+        test.tick();
+        buffer.publish(&test.g);
+        let latest = buffer.latest();
+        assert_eq!(latest.tick.0, held.tick.0 + 1);
+        // the held snapshot could not be reused
+        assert!(buffer.back.lock().unwrap().is_none());
+
+        // once nobody holds the old snapshot, its allocations are reused
+        let tick = latest.tick;
+        drop(held);
+        drop(latest);
+        test.tick();
+        buffer.publish(&test.g);
+        assert!(buffer.back.lock().unwrap().is_some());
+        assert_eq!(buffer.latest().tick.0, tick.0 + 1);
+    }
+}
@@ -0,0 +1,391 @@
+use crate::map::procgen::SumoTrip;
+use crate::map::{IntersectionID, Map, PathKind, RoadID};
+use crate::map_dynamic::{Itinerary, ParkingManagement};
+use crate::multiplayer::chat::{Message, MessageKind};
+use crate::multiplayer::MultiplayerState;
+use crate::transportation::road_maintenance::{RoadMaintenance, Weather};
+use crate::transportation::{
+    get_random_car_color, make_vehicle_entity, spawn_parked_vehicle_with_spot, unpark, Vehicle,
+    VehicleKind, VehicleState,
+};
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::scenario_result::ScenarioResult;
+use crate::utils::time::{DayTime, GameTime, Tick};
+use crate::utils::tutorial::{tutorial_update, TutorialStep};
+use crate::world::VehicleEnt;
+use crate::{ParCommandBuffer, Simulation, VehicleID};
+use common::saveload::{Encoder, JSON};
+use geom::{Color, Transform, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+const SCENARIO_ANNOUNCER: &str = "Scenario";
+
+/// An action scripted by a scenario
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ScenarioAction {
+    /// Spawns cars all over the city driving to the intersection, they disappear once arrived
+    TrafficSurge {
+        intersection: IntersectionID,
+        n_cars: u32,
+    },
+    /// Closes a lane of the road for the given number of game seconds
+    CloseRoad { road: RoadID, duration: f64 },
+    /// Forces the weather for the given number of game seconds
+    SetWeather { weather: Weather, duration: f64 },
+    /// Shows a message to the players
+    Announce { text: String },
+    /// Drives a car from one position to the other, it disappears once arrived.
+    /// Used to replay the demand imported from SUMO
+    Trip { from: Vec3, to: Vec3 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioEvent {
+    pub at: DayTime,
+    pub action: ScenarioAction,
+}
+
+/// A scenario is a script of events happening at specific times, used to build challenges
+/// such as handling the rush of a stadium event.
+/// It is stored as json in the save folder so scenarios can be written by hand.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    pub events: Vec<ScenarioEvent>,
+    /// Seeds the random generator of the simulation when the scenario starts, so that every
+    /// player of the scenario gets the same random draws
+    #[serde(default)]
+    pub seed: u64,
+    /// Index of the next event to run, the events after it are kept sorted by time
+    #[serde(default)]
+    pub next: usize,
+    /// Cars spawned by traffic surges and trips that haven't arrived yet
+    #[serde(default)]
+    pub surge_vehicles: BTreeSet<VehicleID>,
+    /// Hash of the events, set when the scenario starts
+    #[serde(default)]
+    pub id: u64,
+    /// Timestamp at which the scenario started
+    #[serde(default)]
+    pub started_at: f64,
+    /// Commands applied since the scenario started, see [`ScenarioResult`]
+    #[serde(default)]
+    pub n_commands: u64,
+    #[serde(default)]
+    pub commands_hash: u64,
+    /// Set once the scenario is finished, exported by the players to compare their runs
+    #[serde(default)]
+    pub result: Option<ScenarioResult>,
+    /// Steps of a tutorial, each waits for the player to complete its objective
+    #[serde(default)]
+    pub tutorial: Vec<TutorialStep>,
+    /// Index of the current tutorial step
+    #[serde(default)]
+    pub step: usize,
+    /// Timestamp at which the current tutorial step started
+    #[serde(default)]
+    pub step_started: f64,
+}
+
+impl Scenario {
+    pub fn new(name: String, mut events: Vec<ScenarioEvent>) -> Self {
+        events.sort_by_key(|e| e.at);
+        Self {
+            name,
+            events,
+            seed: 0,
+            next: 0,
+            surge_vehicles: Default::default(),
+            id: 0,
+            started_at: 0.0,
+            n_commands: 0,
+            commands_hash: 0,
+            result: None,
+            tutorial: vec![],
+            step: 0,
+            step_started: 0.0,
+        }
+    }
+
+    /// Replays the trips of a SUMO route file, the first one departing at `start`
+    pub fn from_sumo_demand(name: String, trips: &[SumoTrip], start: DayTime) -> Self {
+        let events = trips
+            .iter()
+            .map(|t| ScenarioEvent {
+                at: DayTime::new(start.gamesec() + t.depart as i32),
+                action: ScenarioAction::Trip {
+                    from: t.from.z0(),
+                    to: t.to.z0(),
+                },
+            })
+            .collect();
+        Self::new(name, events)
+    }
+
+    /// Resets the progress and identifies the scenario by its events
+    pub fn start(&mut self, timestamp: f64) {
+        self.events.sort_by_key(|e| e.at);
+        self.next = 0;
+        self.id = common::hash_u64(JSON::encode(&self.events).unwrap_or_default());
+        self.started_at = timestamp;
+        self.n_commands = 0;
+        self.commands_hash = 0;
+        self.result = None;
+        self.step = 0;
+        self.step_started = timestamp;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.events.len()
+            && self.surge_vehicles.is_empty()
+            && self.step >= self.tutorial.len()
+    }
+
+    /// The tutorial step the player is on, `None` if there is no tutorial or it is finished
+    pub fn current_step(&self) -> Option<&TutorialStep> {
+        self.tutorial.get(self.step)
+    }
+
+    /// Pops the events that are due at the given time
+    fn due(&mut self, now: DayTime) -> Vec<ScenarioAction> {
+        // scenarios written by hand are not necessarily sorted
+        self.events[self.next..].sort_by_key(|e| e.at);
+        let mut due = vec![];
+        while let Some(e) = self.events.get(self.next) {
+            if e.at > now {
+                break;
+            }
+            due.push(e.action.clone());
+            self.next += 1;
+        }
+        due
+    }
+}
+
+pub fn scenario_system(sim: &mut Simulation) {
+    profiling::scope!("utils::scenario_system");
+    let time = *sim.read::<GameTime>();
+
+    let due = sim.write::<Scenario>().due(time.daytime);
+    for action in due {
+        run_action(sim, &time, action);
+    }
+
+    tutorial_update(sim, &time);
+
+    if time.tick(10) {
+        despawn_arrived(sim);
+    }
+
+    if sim.read::<Scenario>().result.is_none() {
+        if let Some(result) = ScenarioResult::new(sim) {
+            sim.write::<Scenario>().result = Some(result);
+        }
+    }
+}
+
+fn run_action(sim: &mut Simulation, time: &GameTime, action: ScenarioAction) {
+    let text = match action {
+        ScenarioAction::TrafficSurge {
+            intersection,
+            n_cars,
+        } => {
+            let Some(dest) = sim.map().intersections().get(intersection).map(|i| i.pos) else {
+                return;
+            };
+            let n_spawned = traffic_surge(sim, dest, n_cars);
+            format!("{} cars are heading to the event!", n_spawned)
+        }
+        ScenarioAction::CloseRoad { road, duration } => {
+            if !sim.map().roads().contains_key(road) {
+                return;
+            }
+            sim.write::<RoadMaintenance>()
+                .close_road(road, time.timestamp + duration);
+            format!(
+                "A road is closed for the next {:.0} hours.",
+                duration / GameTime::HOUR as f64
+            )
+        }
+        ScenarioAction::SetWeather { weather, duration } => {
+            sim.write::<RoadMaintenance>()
+                .force_weather(weather, time.timestamp + duration);
+            match weather {
+                Weather::Clear => "The sky clears up.".to_string(),
+                Weather::Snowstorm => "A snowstorm is coming!".to_string(),
+            }
+        }
+        ScenarioAction::Announce { text } => text,
+        ScenarioAction::Trip { from, to } => {
+            // there are too many trips to announce each of them
+            trip(sim, from, to);
+            return;
+        }
+    };
+
+    announce(sim, time, text);
+}
+
+/// Shows a message from the scenario to the players
+pub(crate) fn announce(sim: &Simulation, time: &GameTime, text: String) {
+    sim.write::<MultiplayerState>().chat.add_message(Message {
+        name: SCENARIO_ANNOUNCER.to_string(),
+        text,
+        sent_at: time.instant(),
+        color: Color::new(1.0, 0.8, 0.3, 1.0),
+        kind: MessageKind::Info,
+    });
+}
+
+/// Unparks cars from random parking spots and sends them to the destination
+/// Returns the number of cars that could be spawned
+fn traffic_surge(sim: &mut Simulation, dest: Vec3, n_cars: u32) -> u32 {
+    let tick = *sim.read::<Tick>();
+    let mut n_spawned = 0;
+    for _ in 0..n_cars {
+        let mut pm = sim.write::<ParkingManagement>();
+        let map = sim.map();
+        let mut rng = sim.write::<RandProvider>();
+
+        let Some(spot) = pm.reserve_random_free_spot(&map.parking, rng.next_u64()) else {
+            continue;
+        };
+
+        drop((map, pm, rng));
+
+        let Some(v_id) = spawn_parked_vehicle_with_spot(sim, VehicleKind::Car, spot) else {
+            continue;
+        };
+        unpark(sim, v_id);
+
+        let (world, res) = sim.world_res();
+        let Some(v) = world.vehicles.get_mut(v_id) else {
+            continue;
+        };
+        if let Some(it) = Itinerary::route(
+            tick,
+            v.trans.position,
+            dest,
+            &res.read::<Map>(),
+            PathKind::Vehicle,
+        ) {
+            v.it = it;
+        }
+
+        sim.write::<Scenario>().surge_vehicles.insert(v_id);
+        n_spawned += 1;
+    }
+    n_spawned
+}
+
+/// Spawns a car driving on the lane nearest to `from` and sends it to `to`
+fn trip(sim: &mut Simulation, from: Vec3, to: Vec3) {
+    let tick = *sim.read::<Tick>();
+    let map = sim.map();
+    let Some(lane) = PathKind::Vehicle.nearest_lane(&map, from) else {
+        return;
+    };
+    let Some(lane) = map.lanes().get(lane) else {
+        return;
+    };
+    let (pos, _, dir) = lane.points.project_segment_dir(from);
+    let Some(it) = Itinerary::route(tick, pos, to, &map, PathKind::Vehicle) else {
+        return;
+    };
+    drop(map);
+
+    let mut rng = sim.write::<RandProvider>();
+    let tint = get_random_car_color(&mut rng);
+    let vehicle = Vehicle::with_state(VehicleKind::Car, VehicleState::Driving, tint, &mut rng);
+    drop(rng);
+
+    let v_id = make_vehicle_entity(sim, Transform::new_dir(pos, dir), vehicle, it, true);
+    sim.write::<Scenario>().surge_vehicles.insert(v_id);
+}
+
+fn despawn_arrived(sim: &mut Simulation) {
+    let mut scenario = sim.write::<Scenario>();
+    let cbuf = sim.read::<ParCommandBuffer<VehicleEnt>>();
+    scenario
+        .surge_vehicles
+        .retain(|&id| match sim.world.vehicles.get(id) {
+            Some(v) if v.it.has_ended(0.0) => {
+                cbuf.kill(id);
+                false
+            }
+            Some(_) => true,
+            None => false,
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use crate::world_command::WorldCommand;
+    use geom::{vec2, vec3};
+
+    #[test]
+    fn the_scenario_seeds_the_simulation() {
+        let mut test = TestCtx::new();
+        let scenario = Scenario {
+            seed: 42,
+            ..Scenario::new("test".to_string(), vec![])
+        };
+        test.apply(&[WorldCommand::StartScenario(Box::new(scenario))]);
+        assert_eq!(
+            test.g.write::<RandProvider>().next_u64(),
+            RandProvider::new(42).next_u64()
+        );
+    }
+
+    #[test]
+    fn sumo_trips_drive_cars() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(200.0, 0.0, 0.0)]);
+        let trips = [SumoTrip {
+            depart: 30.0,
+            from: vec2(10.0, 0.0),
+            to: vec2(190.0, 0.0),
+        }];
+        let start = DayTime::new(GameTime::HOUR);
+        let mut scenario = Scenario::from_sumo_demand("sumo".to_string(), &trips, start);
+        assert_eq!(scenario.events[0].at, DayTime::new(GameTime::HOUR + 30));
+
+        let action = scenario.due(DayTime::new(2 * GameTime::HOUR)).remove(0);
+        let time = *test.g.read::<GameTime>();
+        run_action(&mut test.g, &time, action);
+
+        let spawned = test.g.read::<Scenario>().surge_vehicles.clone();
+        assert_eq!(spawned.len(), 1);
+        let v = &test.g.world().vehicles[*spawned.first().unwrap()];
+        assert!(!v.it.has_ended(0.0));
+    }
+
+    #[test]
+    fn due_events_are_popped_in_order() {
+        let announce = |text: &str| ScenarioAction::Announce {
+            text: text.to_string(),
+        };
+        let mut s = Scenario::new(
+            "test".to_string(),
+            vec![
+                ScenarioEvent {
+                    at: DayTime::new(GameTime::DAY + GameTime::HOUR),
+                    action: announce("b"),
+                },
+                ScenarioEvent {
+                    at: DayTime::new(GameTime::HOUR),
+                    action: announce("a"),
+                },
+            ],
+        );
+
+        assert!(s.due(DayTime::new(0)).is_empty());
+        assert_eq!(s.due(DayTime::new(2 * GameTime::HOUR)).len(), 1);
+        assert!(!s.is_finished());
+        assert_eq!(s.due(DayTime::new(2 * GameTime::DAY)).len(), 1);
+        assert!(s.is_finished());
+    }
+}
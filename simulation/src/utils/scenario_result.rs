@@ -0,0 +1,168 @@
+//! Results of finished scenarios, exported as files so that players can compare their runs of
+//! the same challenge without an online service.
+
+use crate::economy::{Government, Money};
+use crate::map_dynamic::TripStats;
+use crate::utils::scenario::Scenario;
+use crate::utils::time::{GameTime, Tick};
+use crate::world_command::WorldCommand;
+use crate::Simulation;
+use common::saveload::{Bincode, Encoder};
+use serde::{Deserialize, Serialize};
+
+/// Bumped when the result format changes, results of other versions are not compared
+pub const RESULT_VERSION: u32 = 1;
+
+/// Mixed in the checksum so that a plain hash of the fields doesn't match it.
+/// It is no signature: anyone reading the code can compute it, it only catches results edited
+/// by hand or damaged files.
+const CHECKSUM_SALT: u64 = 0x5ce7_a210_e6e6_0123;
+
+/// The state of the city when the scenario finished
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioStats {
+    pub population: u32,
+    pub money: Money,
+    pub n_trips: u64,
+    /// Game seconds between the start and the end of the scenario
+    pub duration: f64,
+}
+
+/// A finished scenario as written in a result file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioResult {
+    pub version: u32,
+    /// Hash of the scenario events, runs of different scenarios are not comparable
+    pub scenario_id: u64,
+    pub scenario_name: String,
+    pub player: String,
+    pub seed: u64,
+    pub finished_at: Tick,
+    pub stats: ScenarioStats,
+    /// Number of commands the player issued during the scenario
+    pub n_commands: u64,
+    /// Hash of those commands in order, runs with the same hash played the same way
+    pub commands_hash: u64,
+    /// See [`ScenarioResult::checksum_matches`]
+    pub checksum: u64,
+}
+
+impl ScenarioResult {
+    /// The result of the running scenario, `None` if it is not finished
+    pub(crate) fn new(sim: &Simulation) -> Option<Self> {
+        let scenario = sim.read::<Scenario>();
+        if scenario.events.is_empty() || !scenario.is_finished() {
+            return None;
+        }
+        let time = sim.read::<GameTime>();
+
+        let mut result = Self {
+            version: RESULT_VERSION,
+            scenario_id: scenario.id,
+            scenario_name: scenario.name.clone(),
+            player: String::new(),
+            seed: scenario.seed,
+            finished_at: *sim.read::<Tick>(),
+            stats: ScenarioStats {
+                population: sim.world.humans.len() as u32,
+                money: sim.read::<Government>().money,
+                n_trips: sim.read::<TripStats>().n_trips(),
+                duration: time.timestamp - scenario.started_at,
+            },
+            n_commands: scenario.n_commands,
+            commands_hash: scenario.commands_hash,
+            checksum: 0,
+        };
+        result.update_checksum();
+        Some(result)
+    }
+
+    fn compute_checksum(&self) -> u64 {
+        let unsummed = Self {
+            checksum: 0,
+            ..self.clone()
+        };
+        let encoded = Bincode::encode(&unsummed).unwrap_or_default();
+        common::hash_u64((CHECKSUM_SALT, &*encoded))
+    }
+
+    /// Must be called again after changing a field such as the player name
+    pub fn update_checksum(&mut self) {
+        self.checksum = self.compute_checksum();
+    }
+
+    /// Whether the result was left untouched since its checksum was computed.
+    /// The checksum can be recomputed by anyone, so a matching one doesn't prove the run happened.
+    pub fn checksum_matches(&self) -> bool {
+        self.checksum == self.compute_checksum()
+    }
+
+    /// Whether both results come from the same scenario on the same world
+    pub fn comparable(&self, other: &Self) -> bool {
+        self.version == other.version
+            && self.scenario_id == other.scenario_id
+            && self.seed == other.seed
+    }
+
+    /// Name of the file the result is exported to
+    pub fn file_name(&self) -> String {
+        let player: String = self
+            .player
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect();
+        format!("scenario_result_{:016x}_{}", self.scenario_id, player)
+    }
+}
+
+/// Hashes the commands applied while a scenario runs, see [`ScenarioResult::commands_hash`]
+pub(crate) fn record_command(scenario: &mut Scenario, command: &WorldCommand) {
+    if scenario.events.is_empty() || scenario.is_finished() {
+        return;
+    }
+    if matches!(command, WorldCommand::StartScenario(_)) {
+        return;
+    }
+    let Ok(encoded) = Bincode::encode(command) else {
+        return;
+    };
+    scenario.n_commands += 1;
+    scenario.commands_hash = common::hash_u64((scenario.commands_hash, &*encoded));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edited_results_are_detected() {
+        let mut result = ScenarioResult {
+            version: RESULT_VERSION,
+            scenario_id: 42,
+            scenario_name: "Stadium".to_string(),
+            player: String::new(),
+            seed: 123,
+            finished_at: Tick(1000),
+            stats: ScenarioStats {
+                population: 300,
+                money: Money::new_bucks(1000),
+                n_trips: 50,
+                duration: 3600.0,
+            },
+            n_commands: 12,
+            commands_hash: 7,
+            checksum: 0,
+        };
+        result.player = "Alice".to_string();
+        result.update_checksum();
+        assert!(result.checksum_matches());
+
+        let mut edited = result.clone();
+        edited.stats.money = Money::new_bucks(1_000_000);
+        assert!(!edited.checksum_matches());
+        assert!(result.comparable(&edited));
+
+        edited.scenario_id = 43;
+        assert!(!result.comparable(&edited));
+    }
+}
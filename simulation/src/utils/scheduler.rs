@@ -24,9 +24,52 @@ impl<F: Fn(&mut Simulation)> RunnableSystem for RunnableFn<F> {
     }
 }
 
+/// Number of systems listed in a slow tick report
+const SLOW_TICK_SYSTEMS: usize = 5;
+
+/// Time spent by a system during a tick, in milliseconds
+#[derive(Debug, Clone)]
+pub struct SystemTiming {
+    pub name: &'static str,
+    pub ms: f32,
+    /// Average over the last 100 ticks, to tell a spike from a system that is always slow
+    pub avg_ms: f32,
+}
+
+/// A tick that took longer than its budget, with the systems that took the most time
+#[derive(Debug, Clone)]
+pub struct SlowTick {
+    pub tick: u64,
+    pub total_ms: f32,
+    pub budget_ms: f32,
+    /// Slowest systems first
+    pub systems: Vec<SystemTiming>,
+}
+
+impl SlowTick {
+    /// One line summary, used for the logs
+    pub fn summary(&self) -> String {
+        let systems = self
+            .systems
+            .iter()
+            .map(|s| format!("{} {:.1}ms (avg {:.1}ms)", s.name, s.ms, s.avg_ms))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "tick {} took {:.1}ms over its {:.0}ms budget: {}",
+            self.tick, self.total_ms, self.budget_ms, systems
+        )
+    }
+}
+
 #[derive(Default)]
 pub struct SeqSchedule {
     systems: Vec<(Box<dyn RunnableSystem>, History)>,
+    /// Time spent by each system during the last tick, in the same order as the systems
+    last_times: Vec<f32>,
+    last_tick: u64,
+    /// Time spent applying the command buffers during a tick, included in the systems times
+    apply: History,
 }
 
 impl SeqSchedule {
@@ -38,22 +81,61 @@ impl SeqSchedule {
     #[inline(never)]
     pub fn execute(&mut self, sim: &mut Simulation) {
         profiling::scope!("scheduler::execute");
+        self.last_tick = sim.get_tick();
+        self.last_times.clear();
+        let mut apply_ms = 0.0;
         for (sys, h) in &mut self.systems {
             let start = Instant::now();
 
             sys.run(sim);
 
+            let apply_start = Instant::now();
             ParCommandBuffer::<VehicleEnt>::apply(sim);
             ParCommandBuffer::<HumanEnt>::apply(sim);
             ParCommandBuffer::<TrainEnt>::apply(sim);
             ParCommandBuffer::<WagonEnt>::apply(sim);
             ParCommandBuffer::<FreightStationEnt>::apply(sim);
             ParCommandBuffer::<CompanyEnt>::apply(sim);
+            apply_ms += 1000.0 * apply_start.elapsed().as_secs_f32();
 
             let elapsed = start.elapsed();
 
             h.add_value(1000.0 * elapsed.as_secs_f32());
+            self.last_times.push(1000.0 * elapsed.as_secs_f32());
         }
+        self.apply.add_value(apply_ms);
+    }
+
+    /// Average time spent per tick applying the command buffers (spawns, despawns and deferred
+    /// entity updates) over the last ticks, in milliseconds
+    pub fn apply_ms(&self) -> f32 {
+        self.apply.avg()
+    }
+
+    /// Reports the last tick if its systems took more than the budget, in milliseconds
+    pub fn slow_tick(&self, budget_ms: f32) -> Option<SlowTick> {
+        let total_ms: f32 = self.last_times.iter().sum();
+        if total_ms <= budget_ms {
+            return None;
+        }
+        let mut systems: Vec<SystemTiming> = self
+            .systems
+            .iter()
+            .zip(&self.last_times)
+            .map(|((s, h), &ms)| SystemTiming {
+                name: s.name(),
+                ms,
+                avg_ms: h.avg(),
+            })
+            .collect();
+        systems.sort_unstable_by_key(|s| OrderedFloat(-s.ms));
+        systems.truncate(SLOW_TICK_SYSTEMS);
+        Some(SlowTick {
+            tick: self.last_tick,
+            total_ms,
+            budget_ms,
+            systems,
+        })
     }
 
     pub fn times(&self) -> Vec<(String, f32)> {
@@ -61,6 +143,10 @@ impl SeqSchedule {
             .systems
             .iter()
             .map(|(s, h)| (s.name().to_string(), h.avg()))
+            .chain(std::iter::once((
+                "par_command_buffer::apply".to_string(),
+                self.apply_ms(),
+            )))
             .collect::<Vec<_>>();
         times.sort_unstable_by_key(|(_, t)| OrderedFloat(-*t));
         times
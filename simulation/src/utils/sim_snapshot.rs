@@ -0,0 +1,178 @@
+use crate::economy::{Government, ItemRegistry, Market, Milestones, MILESTONES};
+use crate::map::BuildingKind;
+use crate::map_dynamic::District;
+use crate::souls::health::HealthStats;
+use crate::utils::time::GameTime;
+use crate::Simulation;
+use geom::Vec2;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// SimSnapshot is a summary of the state of a run, meant to be exported as json so that
+/// external dashboards and notebooks can analyze runs without parsing the save files.
+/// Money is in bucks and distances in meters.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimSnapshot {
+    pub tick: u64,
+    /// Game time at the snapshot, formatted as day and hour
+    pub time: String,
+    pub day: i32,
+    pub money: i64,
+    pub milestone: String,
+
+    pub population: u32,
+    pub vehicles: u32,
+    pub trains: u32,
+    pub companies: u32,
+    pub houses: u32,
+    pub buildings: u32,
+    pub road_length: f32,
+
+    pub average_health: f32,
+    pub sick: u32,
+    pub deaths: u32,
+
+    /// Districts with anything in them, sorted by coordinates
+    pub districts: Vec<DistrictSnapshot>,
+    /// One entry per item, sorted by name
+    pub market: Vec<ItemMarketSnapshot>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DistrictSnapshot {
+    /// District coordinates, as shown in the game
+    pub district: String,
+    pub x: i32,
+    pub y: i32,
+    /// Humans living in the district
+    pub population: u32,
+    pub houses: u32,
+    pub companies: u32,
+    /// Jobs offered by the companies of the district
+    pub jobs: u32,
+    /// Vehicles currently in the district
+    pub vehicles: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ItemMarketSnapshot {
+    pub item: String,
+    /// Price on the external market
+    pub ext_value: i64,
+    pub buy_orders: u32,
+    pub buy_qty: u32,
+    pub sell_orders: u32,
+    pub sell_qty: u32,
+    /// Total quantity held by the souls of the city
+    pub stock: i64,
+}
+
+impl SimSnapshot {
+    pub fn take(sim: &Simulation) -> Self {
+        let world = sim.world();
+        let map = sim.map();
+        let time = sim.read::<GameTime>();
+        let health = sim.read::<HealthStats>();
+        let milestone = MILESTONES[sim.read::<Milestones>().reached()].name;
+
+        let mut districts = BTreeMap::<District, DistrictSnapshot>::new();
+        let mut district = |pos: Vec2| {
+            let d = District::of(pos);
+            districts.entry(d).or_insert_with(|| DistrictSnapshot {
+                district: d.to_string(),
+                x: d.0,
+                y: d.1,
+                ..Default::default()
+            })
+        };
+
+        for b in map.buildings().values() {
+            match b.kind {
+                BuildingKind::House => district(b.door_pos.xy()).houses += 1,
+                BuildingKind::GoodsCompany(_) => district(b.door_pos.xy()).companies += 1,
+                _ => {}
+            }
+        }
+        for h in world.humans.values() {
+            if let Some(b) = map.buildings().get(h.home.house) {
+                district(b.door_pos.xy()).population += 1;
+            }
+        }
+        for c in world.companies.values() {
+            district(c.trans.position.xy()).jobs += c.comp.max_workers.max(0) as u32;
+        }
+        for v in world.vehicles.values() {
+            district(v.trans.position.xy()).vehicles += 1;
+        }
+
+        let registry = sim.read::<ItemRegistry>();
+        let mut market: Vec<ItemMarketSnapshot> = sim
+            .read::<Market>()
+            .iter()
+            .map(|(&id, m)| ItemMarketSnapshot {
+                item: registry
+                    .get(id)
+                    .map_or_else(|| format!("{:?}", id), |x| x.name.clone()),
+                ext_value: m.ext_value.bucks(),
+                buy_orders: m.buy_orders().len() as u32,
+                buy_qty: m.buy_orders().values().map(|o| o.qty).sum(),
+                sell_orders: m.sell_orders().len() as u32,
+                sell_qty: m.sell_orders().values().map(|o| o.qty).sum(),
+                stock: m.capital_map().values().map(|&x| x as i64).sum(),
+            })
+            .collect();
+        market.sort_by(|a, b| a.item.cmp(&b.item));
+
+        Self {
+            tick: sim.get_tick(),
+            time: time.daytime.to_string(),
+            day: time.daytime.day,
+            money: sim.read::<Government>().money.bucks(),
+            milestone: milestone.to_string(),
+            population: world.humans.len() as u32,
+            vehicles: world.vehicles.len() as u32,
+            trains: world.trains.len() as u32,
+            companies: world.companies.len() as u32,
+            houses: map
+                .buildings()
+                .values()
+                .filter(|b| b.kind == BuildingKind::House)
+                .count() as u32,
+            buildings: map.buildings().len() as u32,
+            road_length: map.roads().values().map(|r| r.length()).sum(),
+            average_health: health.average,
+            sick: health.sick,
+            deaths: health.deaths,
+            districts: districts.into_values().collect(),
+            market,
+        }
+    }
+
+    /// Writes the snapshot as json in the save folder
+    pub fn save(&self, name: &str) -> Option<()> {
+        <common::saveload::JSONPretty as common::saveload::Encoder>::save_silent(self, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use common::saveload::{Encoder, JSON};
+    use geom::vec3;
+
+    #[test]
+    fn snapshot_roundtrips_as_json() {
+        let test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(200.0, 0.0, 0.0)]);
+
+        let snap = SimSnapshot::take(&test.g);
+        assert!(snap.road_length > 150.0);
+        assert_eq!(snap.milestone, MILESTONES[0].name);
+
+        let encoded = JSON::encode(&snap).unwrap();
+        let decoded: SimSnapshot = JSON::decode(&encoded).unwrap();
+        assert_eq!(decoded.market.len(), snap.market.len());
+        assert_eq!(decoded.road_length, snap.road_length);
+    }
+}
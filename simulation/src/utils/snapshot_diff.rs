@@ -0,0 +1,164 @@
+use crate::init::SAVELOAD_FUNCS;
+use crate::utils::replay::Replay;
+use crate::{Simulation, World};
+use common::flatten::{first_difference, flatten};
+use common::saveload::{Bincode, Encoder};
+use serde::Serialize;
+use slotmapd::{HopSlotMap, Key};
+use std::fmt::{Debug, Display, Formatter};
+
+/// Resources that record what happened instead of the state of the simulation
+const IGNORED_RESOURCES: &[&str] = &["replay"];
+
+/// A place where two simulations hold different values
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    /// The diverging entity or resource, e.g. `humans 3v1` or `resource map`
+    pub location: String,
+    /// Path of the diverging field inside it, e.g. `trans.position.x`
+    pub path: String,
+    pub left: String,
+    pub right: String,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}: {} != {}",
+            self.location, self.path, self.left, self.right
+        )
+    }
+}
+
+/// Compares two simulations entity by entity and resource by resource.
+/// Returns one divergence per diverging entity or resource, the entities first.
+pub fn diff_simulations(a: &Simulation, b: &Simulation) -> Vec<Divergence> {
+    let mut out = diff_world(a.world(), b.world());
+
+    unsafe {
+        for l in &SAVELOAD_FUNCS {
+            if IGNORED_RESOURCES.contains(&l.name) || (l.save)(a) == (l.save)(b) {
+                continue;
+            }
+            let location = format!("resource {}", l.name);
+            out.push(
+                diverging_field(location.clone(), &(l.flatten)(a), &(l.flatten)(b)).unwrap_or(
+                    Divergence {
+                        location,
+                        path: String::new(),
+                        left: "<encoded differently>".to_string(),
+                        right: "<encoded differently>".to_string(),
+                    },
+                ),
+            );
+        }
+    }
+
+    out
+}
+
+pub fn diff_world(a: &World, b: &World) -> Vec<Divergence> {
+    let mut out = vec![];
+    diff_storage(&mut out, "vehicles", &a.vehicles, &b.vehicles);
+    diff_storage(&mut out, "humans", &a.humans, &b.humans);
+    diff_storage(&mut out, "trains", &a.trains, &b.trains);
+    diff_storage(&mut out, "wagons", &a.wagons, &b.wagons);
+    diff_storage(
+        &mut out,
+        "freight_stations",
+        &a.freight_stations,
+        &b.freight_stations,
+    );
+    diff_storage(&mut out, "companies", &a.companies, &b.companies);
+    diff_storage(&mut out, "birds", &a.birds, &b.birds);
+    diff_storage(&mut out, "flocks", &a.flocks, &b.flocks);
+    diff_storage(&mut out, "buses", &a.buses, &b.buses);
+    out
+}
+
+fn diff_storage<K: Key + Debug, V: Serialize>(
+    out: &mut Vec<Divergence>,
+    name: &str,
+    a: &HopSlotMap<K, V>,
+    b: &HopSlotMap<K, V>,
+) {
+    let missing = |id: K, left: &str, right: &str| Divergence {
+        location: format!("{} {:?}", name, id),
+        path: String::new(),
+        left: left.to_string(),
+        right: right.to_string(),
+    };
+
+    for (id, va) in a.iter() {
+        let Some(vb) = b.get(id) else {
+            out.push(missing(id, "<exists>", "<none>"));
+            continue;
+        };
+        // encoding is much faster than flattening, only flatten the entities that differ
+        if Bincode::encode(va).ok() == Bincode::encode(vb).ok() {
+            continue;
+        }
+        let location = format!("{} {:?}", name, id);
+        if let Some(d) = diverging_field(location, &flatten(va), &flatten(vb)) {
+            out.push(d);
+        }
+    }
+    for (id, _) in b.iter() {
+        if !a.contains_key(id) {
+            out.push(missing(id, "<none>", "<exists>"));
+        }
+    }
+}
+
+fn diverging_field(
+    location: String,
+    a: &[(String, String)],
+    b: &[(String, String)],
+) -> Option<Divergence> {
+    let (path, left, right) = first_difference(a, b)?;
+    Some(Divergence {
+        location,
+        path,
+        left,
+        right,
+    })
+}
+
+/// Replays the commands from the start up to the tick of the simulation, and compares the
+/// replayed simulation with it. A divergence means the simulation isn't deterministic.
+pub fn diff_with_replay(sim: &Simulation, replay: Replay) -> Vec<Divergence> {
+    let target = sim.get_tick();
+    let (mut replayed, mut loader) = Simulation::from_replay(replay);
+    let mut schedule = Simulation::schedule();
+
+    let mut finished = false;
+    while replayed.get_tick() < target {
+        if finished {
+            replayed.tick(&mut schedule, &[]);
+            continue;
+        }
+        finished = loader.advance_tick(&mut replayed, &mut schedule);
+    }
+
+    diff_simulations(sim, &replayed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use geom::vec3;
+
+    #[test]
+    fn diff_finds_the_diverging_resource() {
+        let test = TestCtx::new();
+        let test2 = TestCtx::new();
+        assert!(diff_simulations(&test.g, &test2.g).is_empty());
+
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let diff = diff_simulations(&test.g, &test2.g);
+        assert!(!diff.is_empty());
+        assert!(diff.iter().any(|d| d.location == "resource map"));
+    }
+}
@@ -0,0 +1,304 @@
+//! Export of the road network and of the traffic to SUMO, so that traffic researchers can
+//! cross-validate the network in an established simulator. The import of SUMO networks lives
+//! in [`crate::map::procgen`].
+//!
+//! The network is written as SUMO plain xml, netconvert builds the network from it:
+//! `netconvert -n x.nod.xml -e x.edg.xml -x x.con.xml -o x.net.xml`.
+//! The routes are the vehicles on the road at the time of the export, all departing at 0.
+//! See <https://sumo.dlr.de/docs/Networks/PlainXML.html> for the format
+
+use crate::map::{LaneDirection, LaneID, LaneKind, Map, TrafficControl, TraverseKind, TurnKind};
+use crate::transportation::VehicleKind;
+use crate::Simulation;
+use common::FastMap;
+use slotmapd::{Key, KeyData};
+use std::fmt::Write;
+
+/// The xml files of the export, by extension
+pub struct SumoExport {
+    pub files: Vec<(&'static str, String)>,
+}
+
+/// The edge and the index of every exported lane, SUMO numbers the lanes from the right
+type LaneIndex = FastMap<LaneID, (String, usize)>;
+
+impl SumoExport {
+    pub fn new(sim: &Simulation) -> Self {
+        let map = sim.map();
+        let (edges, lanes) = edges_xml(&map);
+
+        Self {
+            files: vec![
+                ("nod", nodes_xml(&map)),
+                ("edg", edges),
+                ("con", connections_xml(&map, &lanes)),
+                ("rou", routes_xml(sim, &lanes)),
+            ],
+        }
+    }
+
+    /// Writes the files in the save folder, returns the paths that were written
+    pub fn save(&self, prefix: &str) -> Vec<String> {
+        let dir = common::saveload::save_dir();
+        let _ = std::fs::create_dir_all(&dir);
+        let mut written = vec![];
+        for (ext, xml) in &self.files {
+            let path = format!("{}/{}.{}.xml", dir, prefix, ext);
+            match std::fs::write(&path, xml) {
+                Ok(()) => written.push(path),
+                Err(e) => log::error!("could not write {}: {}", path, e),
+            }
+        }
+        written
+    }
+}
+
+/// Ids stay the same across exports of the same save, so that demand files can refer to them
+fn key(k: KeyData) -> String {
+    let ffi = k.as_ffi();
+    format!("{}v{}", ffi & 0xffff_ffff, ffi >> 32)
+}
+
+fn shape(points: impl Iterator<Item = geom::Vec3>) -> String {
+    points
+        .map(|p| format!("{:.2},{:.2},{:.2}", p.x, p.y, p.z))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The SUMO vehicle classes allowed on a lane, parking lanes are left out
+fn allowed(kind: LaneKind, electrified: bool) -> Option<&'static str> {
+    Some(match kind {
+        LaneKind::Driving => "passenger truck bus delivery",
+        LaneKind::Biking => "bicycle",
+        LaneKind::Bus => "bus",
+        LaneKind::Parking => return None,
+        LaneKind::Walking => "pedestrian",
+        LaneKind::Rail if electrified => "rail rail_electric",
+        LaneKind::Rail => "rail",
+        LaneKind::Water => "ship",
+        LaneKind::Tram => "tram",
+    })
+}
+
+fn nodes_xml(map: &Map) -> String {
+    let mut xml = String::from("<nodes>\n");
+    for inter in map.intersections().values() {
+        let controls: Vec<&TrafficControl> = inter
+            .roads
+            .iter()
+            .filter_map(|r| map.roads().get(*r))
+            .flat_map(|r| r.incoming_lanes_to(inter.id))
+            .filter_map(|(l, _)| map.lanes().get(*l))
+            .map(|l| &l.control)
+            .collect();
+        let kind = if inter.roads.len() == 1 {
+            "dead_end"
+        } else if controls
+            .iter()
+            .any(|c| matches!(c, TrafficControl::Light(_)))
+        {
+            "traffic_light"
+        } else if controls
+            .iter()
+            .any(|c| matches!(c, TrafficControl::StopSign | TrafficControl::Yield))
+        {
+            "priority_stop"
+        } else {
+            "priority"
+        };
+        let _ = writeln!(
+            xml,
+            r#"    <node id="i{}" x="{:.2}" y="{:.2}" z="{:.2}" type="{}"/>"#,
+            key(inter.id.data()),
+            inter.pos.x,
+            inter.pos.y,
+            inter.pos.z,
+            kind
+        );
+    }
+    xml.push_str("</nodes>\n");
+    xml
+}
+
+/// Every direction of a road is an edge, the backward one is prefixed with a minus like SUMO does
+fn edges_xml(map: &Map) -> (String, LaneIndex) {
+    let mut xml = String::from("<edges>\n");
+    let mut index = LaneIndex::default();
+    for road in map.roads().values() {
+        for side in [LaneDirection::Forward, LaneDirection::Backward] {
+            // from the curb to the middle of the road
+            let lanes: Vec<(LaneID, &'static str)> = road
+                .lanes_on(side)
+                .iter()
+                .rev()
+                .filter_map(|&(id, kind)| Some((id, allowed(kind, road.electrified)?)))
+                .collect();
+            if lanes.is_empty() {
+                continue;
+            }
+
+            let (id, from, to, points) = match side {
+                LaneDirection::Forward => (
+                    format!("r{}", key(road.id.data())),
+                    road.src,
+                    road.dst,
+                    shape(road.points().iter().copied()),
+                ),
+                LaneDirection::Backward => (
+                    format!("-r{}", key(road.id.data())),
+                    road.dst,
+                    road.src,
+                    shape(road.points().iter().rev().copied()),
+                ),
+            };
+            let speed = lanes
+                .iter()
+                .map(|(l, _)| map.lanes()[*l].speed_limit)
+                .fold(0.0, f32::max);
+            let _ = writeln!(
+                xml,
+                r#"    <edge id="{}" from="i{}" to="i{}" numLanes="{}" speed="{:.2}" shape="{}">"#,
+                id,
+                key(from.data()),
+                key(to.data()),
+                lanes.len(),
+                speed,
+                points
+            );
+            for (i, (l, allow)) in lanes.into_iter().enumerate() {
+                let lane = &map.lanes()[l];
+                let _ = writeln!(
+                    xml,
+                    r#"        <lane index="{}" allow="{}" speed="{:.2}" width="{:.2}"/>"#,
+                    i,
+                    allow,
+                    lane.speed_limit,
+                    lane.kind.width()
+                );
+                index.insert(l, (id.clone(), i));
+            }
+            xml.push_str("    </edge>\n");
+        }
+    }
+    xml.push_str("</edges>\n");
+    (xml, index)
+}
+
+/// The turns vehicles can take, pedestrian crossings are generated by netconvert
+fn connections_xml(map: &Map, lanes: &LaneIndex) -> String {
+    let mut xml = String::from("<connections>\n");
+    for inter in map.intersections().values() {
+        for turn in inter.turns() {
+            if !matches!(turn.kind, TurnKind::Driving | TurnKind::Rail) {
+                continue;
+            }
+            let (Some((from, from_lane)), Some((to, to_lane))) =
+                (lanes.get(&turn.id.src), lanes.get(&turn.id.dst))
+            else {
+                continue;
+            };
+            let _ = writeln!(
+                xml,
+                r#"    <connection from="{from}" to="{to}" fromLane="{from_lane}" toLane="{to_lane}"/>"#
+            );
+        }
+    }
+    xml.push_str("</connections>\n");
+    xml
+}
+
+fn routes_xml(sim: &Simulation, lanes: &LaneIndex) -> String {
+    let mut xml = String::from("<routes>\n");
+    for (id, class) in [
+        ("car", "passenger"),
+        ("truck", "truck"),
+        ("bus", "bus"),
+        ("tram", "tram"),
+    ] {
+        let _ = writeln!(xml, r#"    <vType id="{id}" vClass="{class}"/>"#);
+    }
+
+    for (id, v) in sim.world().vehicles.iter() {
+        let Some(route) = v.it.get_route() else {
+            continue;
+        };
+        let mut edges: Vec<&str> = vec![];
+        for travers in std::iter::once(&route.cur).chain(route.reversed_route.iter().rev()) {
+            let TraverseKind::Lane(l) = travers.kind else {
+                continue;
+            };
+            let Some((edge, _)) = lanes.get(&l) else {
+                continue;
+            };
+            if edges.last() != Some(&edge.as_str()) {
+                edges.push(edge);
+            }
+        }
+        if edges.is_empty() {
+            continue;
+        }
+        let kind = match v.vehicle.kind {
+            VehicleKind::Car => "car",
+            VehicleKind::Truck => "truck",
+            VehicleKind::Bus => "bus",
+            VehicleKind::Tram => "tram",
+        };
+        let _ = writeln!(
+            xml,
+            r#"    <vehicle id="v{}" type="{}" depart="0.00" departLane="best"><route edges="{}"/></vehicle>"#,
+            key(id.data()),
+            kind,
+            edges.join(" ")
+        );
+    }
+    xml.push_str("</routes>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::procgen::{OsmNetwork, OsmRoad};
+    use geom::vec2;
+
+    #[test]
+    fn network_to_sumo() {
+        let road = |src, dst, one_way| OsmRoad {
+            src,
+            dst,
+            n_lanes: 1,
+            one_way,
+            speed_limit: 10.0,
+            sidewalks: true,
+            parking: true,
+        };
+        let network = OsmNetwork {
+            nodes: vec![vec2(0.0, 0.0), vec2(100.0, 0.0), vec2(100.0, 100.0)],
+            roads: vec![road(0, 1, false), road(1, 2, true)],
+        };
+        let mut map = Map::empty();
+        network.build(&mut map);
+
+        let nodes = nodes_xml(&map);
+        assert_eq!(nodes.matches("<node ").count(), 3);
+        assert_eq!(nodes.matches(r#"type="dead_end""#).count(), 2);
+
+        let (edges, lanes) = edges_xml(&map);
+        // the sidewalk of the one way street going backward is an edge of its own
+        assert_eq!(edges.matches("<edge ").count(), 4);
+        assert_eq!(edges.matches(r#"id="-r"#).count(), 2);
+        // parking lanes are left out, the sidewalk is the rightmost lane
+        assert!(!edges.contains("parking"));
+        assert_eq!(
+            edges
+                .matches(r#"<lane index="0" allow="pedestrian""#)
+                .count(),
+            4
+        );
+        assert_eq!(lanes.len(), 7);
+
+        let connections = connections_xml(&map, &lanes);
+        assert!(connections.contains("<connection "));
+    }
+}
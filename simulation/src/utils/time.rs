@@ -7,6 +7,7 @@ pub const SECONDS_PER_HOUR: i32 = 3600;
 pub const HOURS_PER_DAY: i32 = 24;
 pub const SECONDS_PER_DAY: i32 = SECONDS_PER_HOUR * HOURS_PER_DAY;
 pub const TICKS_PER_SECOND: u64 = 50;
+pub const DAYS_PER_SEASON: i32 = 2;
 
 /// The amount of time the game was updated
 /// Used as a resource
@@ -50,6 +51,15 @@ pub struct DayTime {
     pub second: i32,
 }
 
+/// The season of the year, derived from the in-game day
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
 /// An interval of in-game time
 #[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct TimeInterval {
@@ -126,6 +136,19 @@ impl RecTimeInterval {
             0
         }
     }
+
+    /// Seconds until the interval next ends
+    pub fn dist_until_end(&self, t: DayTime) -> i32 {
+        let mut end_dt = DayTime {
+            day: t.day,
+            hour: self.end_hour,
+            second: self.end_second,
+        };
+        if end_dt < t {
+            end_dt.day += 1;
+        }
+        end_dt.gamesec() - t.gamesec()
+    }
 }
 
 impl DayTime {
@@ -150,6 +173,15 @@ impl DayTime {
     pub fn gamesec(&self) -> i32 {
         self.day * SECONDS_PER_DAY + self.daysec()
     }
+
+    pub fn season(&self) -> Season {
+        match (self.day / DAYS_PER_SEASON) % 4 {
+            0 => Season::Spring,
+            1 => Season::Summer,
+            2 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
 }
 
 impl GameTime {
@@ -187,6 +219,10 @@ impl GameTime {
     pub fn daysec(&self) -> f64 {
         self.timestamp % Self::DAY as f64
     }
+
+    pub fn season(&self) -> Season {
+        self.daytime.season()
+    }
 }
 
 impl GameInstant {
@@ -217,4 +253,15 @@ mod test {
     fn assert_up_dt_ticks_per_second_match() {
         assert!((1.0 / UP_DT.as_secs_f64() - super::TICKS_PER_SECOND as f64).abs() < 0.0001);
     }
+
+    #[test]
+    fn seasons_cycle() {
+        use super::{DayTime, Season, DAYS_PER_SEASON, SECONDS_PER_DAY};
+
+        let season = |day: i32| DayTime::new(day * SECONDS_PER_DAY).season();
+        assert_eq!(season(0), Season::Spring);
+        assert_eq!(season(DAYS_PER_SEASON), Season::Summer);
+        assert_eq!(season(3 * DAYS_PER_SEASON), Season::Winter);
+        assert_eq!(season(4 * DAYS_PER_SEASON), Season::Spring);
+    }
 }
@@ -0,0 +1,228 @@
+use crate::economy::{Government, Money};
+use crate::map::{BuildingKind, Map};
+use crate::utils::resources::Resources;
+use crate::utils::time::{GameInstant, GameTime, SECONDS_PER_HOUR};
+use crate::World;
+use geom::{Shape, Vec2, AABB};
+use serde::{Deserialize, Serialize};
+
+/// Side of the silhouette grid of a snapshot, in cells
+pub const SILHOUETTE_RES: usize = 128;
+/// Snapshots are first taken every 6 in-game hours
+const SNAPSHOT_PERIOD: u32 = 6 * SECONDS_PER_HOUR as u32;
+/// Past this many snapshots, every other one is dropped and the period doubles,
+/// so that the history always covers the whole game
+const MAX_SNAPSHOTS: usize = 512;
+/// Empty space kept around the city in the silhouette, in meters
+const MARGIN: f32 = 100.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SilhouetteCell {
+    Empty,
+    Road,
+    Rail,
+    House,
+    Company,
+    Other,
+}
+
+/// A compact picture of the city at some point in time, used to play back its growth
+#[derive(Serialize, Deserialize)]
+pub struct TimelapseSnapshot {
+    pub instant: GameInstant,
+    pub population: u32,
+    pub money: Money,
+    pub buildings: u32,
+    /// Total length of the roads, in meters
+    pub road_length: f32,
+    /// Square area covered by the silhouette, it grows with the city
+    pub bounds: AABB,
+    /// Run-length encoded silhouette, row by row from the lower left corner
+    silhouette: Vec<(u8, SilhouetteCell)>,
+}
+
+impl TimelapseSnapshot {
+    pub fn take(world: &World, map: &Map, money: Money, instant: GameInstant) -> Self {
+        let mut bounds: Option<AABB> = None;
+        let mut add = |b: AABB| bounds = Some(bounds.map_or(b, |x| x.union(b)));
+        for road in map.roads().values() {
+            for p in road.points().iter() {
+                add(AABB::new(p.xy(), p.xy()));
+            }
+        }
+        for b in map.buildings().values() {
+            add(b.obb.bbox());
+        }
+        let bounds = bounds.unwrap_or(AABB::zero()).expand(MARGIN);
+        let bounds = AABB::centered(bounds.center(), Vec2::splat(bounds.w().max(bounds.h())));
+
+        let mut snap = Self {
+            instant,
+            population: world.humans.len() as u32,
+            money,
+            buildings: map.buildings().len() as u32,
+            road_length: map.roads().values().map(|r| r.length()).sum(),
+            bounds,
+            silhouette: vec![],
+        };
+
+        let step = snap.cell_size() * 0.5;
+        let mut grid = vec![SilhouetteCell::Empty; SILHOUETTE_RES * SILHOUETTE_RES];
+        let mut mark = |p: Vec2, c: SilhouetteCell| {
+            if let Some(i) = snap.cell_index(p) {
+                grid[i] = c;
+            }
+        };
+
+        for b in map.buildings().values() {
+            let c = match b.kind {
+                BuildingKind::House => SilhouetteCell::House,
+                BuildingKind::GoodsCompany(_) => SilhouetteCell::Company,
+                _ => SilhouetteCell::Other,
+            };
+            mark(b.obb.center(), c);
+            let bbox = b.obb.bbox();
+            let mut y = bbox.ll.y;
+            while y < bbox.ur.y {
+                let mut x = bbox.ll.x;
+                while x < bbox.ur.x {
+                    let p = Vec2::new(x, y);
+                    if b.obb.contains(p) {
+                        mark(p, c);
+                    }
+                    x += step;
+                }
+                y += step;
+            }
+        }
+
+        // Roads are drawn over the buildings so that the network stays readable when zoomed out
+        for road in map.roads().values() {
+            let c = if road.is_rail() {
+                SilhouetteCell::Rail
+            } else {
+                SilhouetteCell::Road
+            };
+            for (p, _) in road.points().equipoints_dir(step, false) {
+                mark(p.xy(), c);
+            }
+        }
+
+        snap.silhouette = encode(&grid);
+        snap
+    }
+
+    /// Side of a cell of the silhouette, in meters
+    pub fn cell_size(&self) -> f32 {
+        self.bounds.w() / SILHOUETTE_RES as f32
+    }
+
+    /// Index in the silhouette of the cell containing the given position
+    pub fn cell_index(&self, p: Vec2) -> Option<usize> {
+        let c = (p - self.bounds.ll) / self.cell_size();
+        if c.x < 0.0 || c.y < 0.0 {
+            return None;
+        }
+        let (x, y) = (c.x as usize, c.y as usize);
+        (x < SILHOUETTE_RES && y < SILHOUETTE_RES).then_some(y * SILHOUETTE_RES + x)
+    }
+
+    /// Decodes the silhouette, `SILHOUETTE_RES * SILHOUETTE_RES` cells row by row
+    pub fn silhouette(&self) -> Vec<SilhouetteCell> {
+        self.silhouette
+            .iter()
+            .flat_map(|&(n, c)| std::iter::repeat(c).take(n as usize))
+            .collect()
+    }
+}
+
+fn encode(grid: &[SilhouetteCell]) -> Vec<(u8, SilhouetteCell)> {
+    let mut runs: Vec<(u8, SilhouetteCell)> = vec![];
+    for &c in grid {
+        match runs.last_mut() {
+            Some((n, last)) if *last == c && *n < u8::MAX => *n += 1,
+            _ => runs.push((1, c)),
+        }
+    }
+    runs
+}
+
+/// Timelapse records periodic snapshots of the city to play back its growth
+#[derive(Serialize, Deserialize)]
+pub struct Timelapse {
+    snapshots: Vec<TimelapseSnapshot>,
+    /// In-game seconds between two snapshots
+    period: u32,
+}
+
+impl Default for Timelapse {
+    fn default() -> Self {
+        Self {
+            snapshots: vec![],
+            period: SNAPSHOT_PERIOD,
+        }
+    }
+}
+
+impl Timelapse {
+    pub fn snapshots(&self) -> &[TimelapseSnapshot] {
+        &self.snapshots
+    }
+
+    fn push(&mut self, snap: TimelapseSnapshot) {
+        self.snapshots.push(snap);
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            let mut i = 0;
+            self.snapshots.retain(|_| {
+                i += 1;
+                i % 2 == 1
+            });
+            self.period *= 2;
+        }
+    }
+}
+
+pub fn timelapse_update(world: &mut World, res: &mut Resources) {
+    profiling::scope!("utils::timelapse_update");
+    let period = res.read::<Timelapse>().period;
+    let instant = {
+        let time = res.read::<GameTime>();
+        if !time.tick(period) {
+            return;
+        }
+        time.instant()
+    };
+
+    let money = res.read::<Government>().money;
+    let snap = TimelapseSnapshot::take(world, &res.read::<Map>(), money, instant);
+    res.write::<Timelapse>().push(snap);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestCtx;
+    use geom::vec3;
+
+    #[test]
+    fn snapshot_silhouette() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+
+        let snap = TimelapseSnapshot::take(
+            test.g.world(),
+            &test.g.map(),
+            Money::ZERO,
+            GameInstant { timestamp: 0.0 },
+        );
+        let cells = snap.silhouette();
+        assert_eq!(cells.len(), SILHOUETTE_RES * SILHOUETTE_RES);
+        assert!(snap.silhouette.len() < cells.len() / 10);
+
+        let on_road = snap.cell_index(Vec2::new(150.0, 0.0)).unwrap();
+        assert_eq!(cells[on_road], SilhouetteCell::Road);
+        let off_road = snap.cell_index(Vec2::new(150.0, 80.0)).unwrap();
+        assert_eq!(cells[off_road], SilhouetteCell::Empty);
+        assert!(snap.cell_index(Vec2::new(150.0, 1000.0)).is_none());
+    }
+}
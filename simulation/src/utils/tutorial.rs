@@ -0,0 +1,282 @@
+//! Tutorials are scenarios whose steps wait for the player to do something, such as building a
+//! road between two markers. The player's actions are detected from the commands they apply,
+//! the UI shows the current step and points at the tool to use.
+
+use crate::map::{Map, PathKind, Pathfinder, Traversable, TraverseDirection, TraverseKind};
+use crate::utils::scenario::{announce, Scenario};
+use crate::utils::time::{GameTime, Tick};
+use crate::world_command::WorldCommand;
+use crate::Simulation;
+use geom::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// How close to the markers the roads must be to connect them, in meters
+pub const MARKER_RADIUS: f32 = 25.0;
+
+/// A kind of player action, detected from the commands
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TutorialAction {
+    BuildRoad,
+    Bulldoze,
+    BuildSpecialBuilding,
+    ZoneLots,
+}
+
+impl TutorialAction {
+    pub fn matches(self, command: &WorldCommand) -> bool {
+        match self {
+            TutorialAction::BuildRoad => matches!(
+                command,
+                WorldCommand::MapMakeConnection { .. }
+                    | WorldCommand::MapMakeMultipleConnections(..)
+            ),
+            TutorialAction::Bulldoze => matches!(
+                command,
+                WorldCommand::MapRemoveRoad(_)
+                    | WorldCommand::MapRemoveIntersection(_)
+                    | WorldCommand::MapRemoveBuilding(_)
+            ),
+            TutorialAction::BuildSpecialBuilding => {
+                matches!(command, WorldCommand::MapBuildSpecialBuilding { .. })
+            }
+            TutorialAction::ZoneLots => matches!(command, WorldCommand::MapSetLotKind { .. }),
+        }
+    }
+}
+
+/// What the player must do to go to the next step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TutorialObjective {
+    /// Nothing, the step ends after the given game seconds
+    Wait(f64),
+    Action(TutorialAction),
+    /// Build roads connecting the two markers
+    ConnectPoints(Vec3, Vec3),
+}
+
+impl TutorialObjective {
+    /// Whether the command that was just applied completes the objective
+    fn done_by(&self, command: &WorldCommand, map: &Map) -> bool {
+        match *self {
+            TutorialObjective::Wait(_) => false,
+            TutorialObjective::Action(action) => action.matches(command),
+            TutorialObjective::ConnectPoints(a, b) => {
+                TutorialAction::BuildRoad.matches(command) && connected(map, a, b)
+            }
+        }
+    }
+
+    /// The points the UI marks on the map
+    pub fn markers(&self) -> Vec<Vec3> {
+        match *self {
+            TutorialObjective::ConnectPoints(a, b) => vec![a, b],
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TutorialStep {
+    pub text: String,
+    /// Name of the toolbox button the UI points at
+    #[serde(default)]
+    pub highlight: Option<String>,
+    pub objective: TutorialObjective,
+}
+
+impl TutorialStep {
+    fn new(text: &str, highlight: Option<&str>, objective: TutorialObjective) -> Self {
+        Self {
+            text: text.to_string(),
+            highlight: highlight.map(ToString::to_string),
+            objective,
+        }
+    }
+}
+
+/// Whether cars can drive between the roads near the two points
+pub fn connected(map: &Map, a: Vec3, b: Vec3) -> bool {
+    let lane = |p| map.nearest_driving_lane(p, Some(MARKER_RADIUS));
+    let (Some(la), Some(lb)) = (lane(a), lane(b)) else {
+        return false;
+    };
+    let path = |from, to| {
+        let start = Traversable::new(TraverseKind::Lane(from), TraverseDirection::Forward);
+        from == to || PathKind::Vehicle.path(map, Tick(0), start, to).is_some()
+    };
+    path(la, lb) || path(lb, la)
+}
+
+impl Scenario {
+    /// The tutorial teaching the basics of building a city, around the given position
+    pub fn tutorial(center: Vec3) -> Self {
+        let a = center + Vec3::x(-100.0);
+        let b = center + Vec3::x(100.0);
+        let c = center + Vec3::new(100.0, 150.0, 0.0);
+
+        let mut scenario = Scenario::new("Tutorial".to_string(), vec![]);
+        scenario.tutorial = vec![
+            TutorialStep::new(
+                "Welcome! This tutorial shows how to build a city.",
+                None,
+                TutorialObjective::Wait(30.0),
+            ),
+            TutorialStep::new(
+                "Pick the road tool and build a road between the two markers.",
+                Some("road"),
+                TutorialObjective::ConnectPoints(a, b),
+            ),
+            TutorialStep::new(
+                "Roads can branch off other roads: connect the new marker to your road.",
+                Some("road"),
+                TutorialObjective::ConnectPoints(b, c),
+            ),
+            TutorialStep::new(
+                "Houses and shops grow along the roads. Zone some lots with the lot brush.",
+                Some("housebrush"),
+                TutorialObjective::Action(TutorialAction::ZoneLots),
+            ),
+            TutorialStep::new(
+                "Companies give jobs to the people. Place one from the buildings tool.",
+                Some("buildings"),
+                TutorialObjective::Action(TutorialAction::BuildSpecialBuilding),
+            ),
+            TutorialStep::new(
+                "Mistakes happen: remove something with the bulldozer.",
+                Some("bulldozer"),
+                TutorialObjective::Action(TutorialAction::Bulldoze),
+            ),
+        ];
+        scenario
+    }
+}
+
+/// Goes to the next step when the command that was just applied completes the current one
+pub(crate) fn tutorial_command(sim: &mut Simulation, command: &WorldCommand) {
+    let done = {
+        let scenario = sim.read::<Scenario>();
+        let Some(step) = scenario.current_step() else {
+            return;
+        };
+        step.objective.done_by(command, &sim.map())
+    };
+    if done {
+        next_step(sim);
+    }
+}
+
+/// Ends the steps that only wait
+pub(crate) fn tutorial_update(sim: &mut Simulation, time: &GameTime) {
+    let waited = {
+        let scenario = sim.read::<Scenario>();
+        let Some(step) = scenario.current_step() else {
+            return;
+        };
+        match step.objective {
+            TutorialObjective::Wait(duration) => time.timestamp - scenario.step_started >= duration,
+            _ => false,
+        }
+    };
+    if waited {
+        next_step(sim);
+    }
+}
+
+fn next_step(sim: &mut Simulation) {
+    let time = *sim.read::<GameTime>();
+    let mut scenario = sim.write::<Scenario>();
+    scenario.step += 1;
+    scenario.step_started = time.timestamp;
+    if scenario.step < scenario.tutorial.len() {
+        return;
+    }
+    let text = format!("{} complete, well done!", scenario.name);
+    drop(scenario);
+    announce(sim, &time, text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economy::{ConstructionQueue, Money};
+    use crate::map::{LanePatternBuilder, MapProject, ProjectFilter};
+    use crate::tests::TestCtx;
+    use crate::utils::time::TICKS_PER_SECOND;
+    use geom::vec3;
+
+    #[test]
+    fn steps_advance_with_the_applied_commands() {
+        let mut test = TestCtx::new();
+        let (a, mid, b) = (
+            vec3(0.0, 0.0, 0.0),
+            vec3(100.0, 0.0, 0.0),
+            vec3(200.0, 0.0, 0.0),
+        );
+
+        let mut scenario = Scenario::new("test".to_string(), vec![]);
+        scenario.tutorial = vec![
+            TutorialStep::new("", None, TutorialObjective::ConnectPoints(a, b)),
+            TutorialStep::new(
+                "",
+                None,
+                TutorialObjective::Action(TutorialAction::Bulldoze),
+            ),
+        ];
+        scenario.start(0.0);
+        test.g.resources.insert(scenario);
+
+        let mut schedule = Simulation::schedule();
+        let mut run = |test: &mut TestCtx, commands: &[WorldCommand]| {
+            test.g.tick(&mut schedule, commands);
+        };
+
+        test.build_roads(&[a, mid]);
+        let first = test.g.map().roads().keys().next().unwrap();
+        let connect = WorldCommand::MapMakeConnection {
+            from: test.g.map().project(mid, 0.0, ProjectFilter::ALL),
+            to: MapProject::ground(b),
+            inter: None,
+            pat: LanePatternBuilder::default().build(),
+        };
+
+        // queued commands complete the objective once they are built
+        test.g.write::<ConstructionQueue>().enabled = true;
+        test.g.write::<ConstructionQueue>().daily_limit = Money::ZERO;
+        run(&mut test, &[connect]);
+        assert_eq!(test.g.read::<ConstructionQueue>().len(), 1);
+        assert_eq!(test.g.read::<Scenario>().step, 0);
+
+        test.g.write::<ConstructionQueue>().daily_limit = Money::new_bucks(1_000_000);
+        for _ in 0..=TICKS_PER_SECOND {
+            run(&mut test, &[]);
+        }
+        assert!(test.g.read::<ConstructionQueue>().is_empty());
+        assert!(connected(&test.g.map(), a, b));
+        assert_eq!(test.g.read::<Scenario>().step, 1);
+
+        run(&mut test, &[WorldCommand::MapRemoveRoad(first)]);
+        assert!(test.g.read::<Scenario>().is_finished());
+    }
+
+    #[test]
+    fn rejected_commands_dont_complete_the_steps() {
+        let mut test = TestCtx::new();
+        let mut scenario = Scenario::new("test".to_string(), vec![]);
+        scenario.tutorial = vec![TutorialStep::new(
+            "",
+            None,
+            TutorialObjective::Action(TutorialAction::Bulldoze),
+        )];
+        scenario.start(0.0);
+        test.g.resources.insert(scenario);
+
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let road = test.g.map().roads().keys().next().unwrap();
+        drop(test.g.map_mut().remove_road(road));
+
+        let mut schedule = Simulation::schedule();
+        test.g
+            .tick(&mut schedule, [&WorldCommand::MapRemoveRoad(road)]);
+        assert_eq!(test.g.read::<Scenario>().step, 0);
+    }
+}
@@ -1,16 +1,22 @@
-use crate::economy::{Bought, Market, Sold, Workers};
+use crate::economy::{Bought, Earnings, Market, Sold, Workers};
+use crate::map::BusStopID;
 use crate::map_dynamic::{
     DispatchID, Dispatcher, Itinerary, ItineraryFollower, ItineraryLeader, ParkingManagement,
     Router,
 };
 use crate::physics::{Collider, CollisionWorld, Speed};
-use crate::souls::desire::{BuyFood, Home, Work};
+use crate::souls::desire::{AttendEvent, BuyFood, GoOut, Home, Refuel, Work};
+use crate::souls::education::Education;
 use crate::souls::freight_station::FreightStation;
 use crate::souls::goods_company::GoodsCompany;
+use crate::souls::health::Health;
 use crate::souls::human::{HumanDecision, PersonalInfo};
+use crate::transportation::bus::{BusLineID, BusState};
+use crate::transportation::metro::MetroTrain;
 use crate::transportation::train::{Locomotive, LocomotiveReservation, RailWagon};
 use crate::transportation::{Location, Pedestrian, Vehicle, VehicleKind, VehicleState};
 use crate::utils::par_command_buffer::SimDrop;
+use crate::utils::pool::EntityPool;
 use crate::utils::resources::Resources;
 use crate::{impl_entity, impl_trans, SoulID};
 use derive_more::{From, TryInto};
@@ -29,6 +35,7 @@ new_key_type! {
     pub struct CompanyID;
     pub struct BirdID;
     pub struct FlockID;
+    pub struct BusID;
 }
 
 impl_entity!(VehicleID, VehicleEnt, vehicles);
@@ -39,6 +46,7 @@ impl_entity!(FreightStationID, FreightStationEnt, freight_stations);
 impl_entity!(CompanyID, CompanyEnt, companies);
 impl_entity!(BirdID, BirdEnt, birds);
 impl_entity!(FlockID, Flock, flocks);
+impl_entity!(BusID, BusEnt, buses);
 
 impl_trans!(HumanID);
 impl_trans!(VehicleID);
@@ -48,7 +56,9 @@ impl_trans!(FreightStationID);
 impl_trans!(CompanyID);
 impl_trans!(BirdID);
 
-#[derive(PartialEq, Eq, Copy, Clone, Debug, From, TryInto)]
+#[derive(
+    PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, From, TryInto, Serialize, Deserialize,
+)]
 pub enum AnyEntity {
     VehicleID(VehicleID),
     TrainID(TrainID),
@@ -101,22 +111,30 @@ pub struct HumanEnt {
     pub decision: HumanDecision,
     pub home: Home,
     pub food: BuyFood,
+    pub go_out: GoOut,
+    pub refuel: Refuel,
+    pub event: AttendEvent,
     pub bought: Bought,
     pub work: Option<Work>,
+    pub earnings: Earnings,
+    pub education: Education,
+    pub health: Health,
 
     pub personal_info: Box<PersonalInfo>,
 }
 
 impl SimDrop for HumanEnt {
     fn sim_drop(mut self, id: HumanID, res: &mut Resources) {
-        if let Some(collider) = self.collider {
+        if let Some(collider) = self.collider.take() {
             res.write::<CollisionWorld>().remove_maintain(collider.0);
         }
 
         res.write::<Market>().remove(SoulID::Human(id));
 
         self.router
-            .clear_steps(&mut res.write::<ParkingManagement>())
+            .clear_steps(&mut res.write::<ParkingManagement>());
+
+        res.write::<EntityPool>().recycle_human(self);
     }
 }
 
@@ -129,6 +147,9 @@ pub struct TrainEnt {
     pub res: LocomotiveReservation,
     #[inspect(skip)]
     pub leader: ItineraryLeader,
+    /// The metro line the train serves, if it is a metro train
+    #[inspect(skip)]
+    pub metro: Option<MetroTrain>,
 }
 
 impl SimDrop for TrainEnt {
@@ -198,6 +219,19 @@ pub struct Flock {
     pub bird_ids: Vec<BirdID>,
 }
 
+/// The driver of a bus of a line, the bus itself is a vehicle
+#[derive(Serialize, Deserialize)]
+pub struct BusEnt {
+    /// The passengers are located in it
+    pub vehicle: VehicleID,
+    pub line: BusLineID,
+    /// Index in the stops of the line of the stop the bus drives to or waits at
+    pub next_stop: usize,
+    pub state: BusState,
+    /// The passengers with the stop they get off at
+    pub passengers: Vec<(HumanID, BusStopID)>,
+}
+
 #[derive(Default, Serialize, Deserialize)]
 pub struct World {
     pub vehicles: HopSlotMap<VehicleID, VehicleEnt>,
@@ -208,6 +242,7 @@ pub struct World {
     pub companies: HopSlotMap<CompanyID, CompanyEnt>,
     pub birds: HopSlotMap<BirdID, BirdEnt>,
     pub flocks: HopSlotMap<FlockID, Flock>,
+    pub buses: HopSlotMap<BusID, BusEnt>,
 }
 
 impl World {
@@ -215,6 +250,10 @@ impl World {
         <<E as EntityID>::Entity as Entity>::storage(self).get(id)
     }
 
+    pub fn get_mut<E: EntityID>(&mut self, id: E) -> Option<&mut E::Entity> {
+        <<E as EntityID>::Entity as Entity>::storage_mut(self).get_mut(id)
+    }
+
     pub fn storage<E: Entity>(&self) -> &HopSlotMap<E::ID, E> {
         E::storage(self)
     }
@@ -259,6 +298,15 @@ impl World {
         }
     }
 
+    pub fn it_any_mut(&mut self, id: AnyEntity) -> Option<&mut Itinerary> {
+        match id {
+            AnyEntity::VehicleID(x) => Some(&mut self.get_mut(x)?.it),
+            AnyEntity::TrainID(x) => Some(&mut self.get_mut(x)?.it),
+            AnyEntity::HumanID(x) => Some(&mut self.get_mut(x)?.it),
+            _ => None,
+        }
+    }
+
     pub fn pos<E: WorldTransform>(&self, id: E) -> Option<Vec3> {
         self.get(id).map(|x| E::trans(x).position)
     }
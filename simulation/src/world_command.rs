@@ -1,27 +1,53 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{Display, Formatter};
 use std::time::Instant;
 
-use common::descriptions::BuildingGen;
+use common::descriptions::{BuildingGen, DoorLayout};
 use serde::{Deserialize, Serialize};
 
-use geom::{vec3, Vec2, Vec3, OBB};
+use geom::{vec3, PolyLine3, Vec2, Vec3, OBB};
 use WorldCommand::*;
 
-use crate::economy::Government;
-use crate::map::procgen::{load_parismap, load_testfield};
+use crate::economy::{
+    ConstructionID, ConstructionQueue, CrisisFrequency, EconomyCrises, Government, Milestones,
+    Money,
+};
+use crate::map::procgen::{
+    add_edge_connections, load_parismap, load_testfield, BuildingArchetypes, OsmNetwork,
+};
 use crate::map::{
-    BuildingID, BuildingKind, Environment, IntersectionID, LaneID, LanePattern, LanePatternBuilder,
-    LightPolicy, LotID, Map, MapProject, ProjectKind, RoadID, TerraformKind, TurnPolicy, Zone,
+    ApproachSign, BuildingID, BuildingKind, BusStop, BusStopID, CurbZone, Decoration, DecorationID,
+    DecorationSupport, Environment, IntersectionID, LaneID, LanePattern, LanePatternBuilder,
+    LightPolicy, LightProgram, LotID, LotKind, Map, MapProject, ProjectFilter, ProjectKind,
+    RoadFurniture, RoadID, TerraformKind, TurnPolicy, Zone, MIN_BUS_STOP_SPACING,
+};
+use crate::map_dynamic::{
+    BuildingInfos, District, MapMarker, MapMarkers, MarkerID, ParkingManagement, Policies, Policy,
+    Rezoning,
 };
-use crate::map_dynamic::{BuildingInfos, ParkingManagement};
 use crate::multiplayer::chat::Message;
 use crate::multiplayer::MultiplayerState;
+use crate::souls::freight_station::set_freight_service;
+use crate::souls::goods_company::{set_logistics, FreightMode};
+use crate::transportation::airport::check_airport_footprint;
+use crate::transportation::bus::{BusLine, BusLineID, BusLines};
+use crate::transportation::metro::{
+    check_metro_station_footprint, MetroLine, MetroLineID, MetroLines,
+};
+use crate::transportation::road_maintenance::{PlowPriority, RepairFunding, RoadMaintenance};
 use crate::transportation::testing_vehicles::RandomVehicles;
-use crate::transportation::train::{spawn_train, RailWagonKind};
+use crate::transportation::train::{
+    check_train_placement, spawn_train, LocomotiveKind, RailWagonKind,
+};
+use crate::transportation::waterway::{check_canal, check_harbor_footprint};
 use crate::transportation::{spawn_parked_vehicle_with_spot, unpark, VehicleKind};
+use crate::utils::chunked_save;
+use crate::utils::events::{SimEventKind, SimEvents};
 use crate::utils::rand_provider::RandProvider;
+use crate::utils::scenario::Scenario;
 use crate::utils::time::{GameTime, Tick};
-use crate::{Replay, Simulation, SimulationOptions};
+use crate::world::{CompanyID, FreightStationID};
+use crate::{GoodsCompanyRegistry, Replay, Simulation, SimulationOptions};
 
 #[derive(Clone, Default)]
 pub struct WorldCommands {
@@ -37,6 +63,42 @@ pub enum WorldCommand {
     MapRemoveRoad(RoadID),
     MapRemoveBuilding(BuildingID),
     MapBuildHouse(LotID),
+    MapSetLotKind {
+        lots: Vec<LotID>,
+        kind: LotKind,
+    },
+    /// Adds or removes the overhead lines of a rail road
+    MapSetRoadElectrified {
+        road: RoadID,
+        electrified: bool,
+    },
+    /// Designates a section of the curb of a road as a bus stop, a loading zone or no parking
+    MapSetRoadCurb {
+        road: RoadID,
+        zone: CurbZone,
+    },
+    /// Places a speed bump or a stop or yield sign along a road
+    MapAddRoadFurniture {
+        road: RoadID,
+        furniture: RoadFurniture,
+    },
+    /// Removes the road furniture close to the given distance along the road
+    MapRemoveRoadFurniture {
+        road: RoadID,
+        dist: f32,
+    },
+    /// Places a cosmetic decal or sign
+    MapAddDecoration(Decoration),
+    MapRemoveDecoration(DecorationID),
+    /// Places a bus stop on a sidewalk, see [`Map::snap_bus_stop`]
+    MapAddBusStop(BusStop),
+    MapRemoveBusStop(BusStopID),
+    /// Replaces the houses by lots of the given kind, immediately or progressively
+    MapRezoneBuildings {
+        buildings: Vec<BuildingID>,
+        kind: LotKind,
+        immediate: bool,
+    },
     Terraform {
         kind: TerraformKind,
         center: Vec2,
@@ -55,6 +117,8 @@ pub enum WorldCommand {
         dist: f32,
         n_wagons: u32,
         lane: LaneID,
+        #[serde(default)]
+        loco: LocomotiveKind,
     },
     MapMakeConnection {
         from: MapProject,
@@ -71,6 +135,12 @@ pub enum WorldCommand {
         turn: TurnPolicy,
         light: LightPolicy,
     },
+    /// Offsets of the light cycles of the intersections, `None` for their default one
+    MapSetLightOffsets(Vec<(IntersectionID, Option<u16>)>),
+    /// Custom timing of the lights of the intersection, `None` for the default one
+    MapSetLightProgram(IntersectionID, Option<LightProgram>),
+    /// Signs of the incoming roads of the intersection, see [`crate::map::ApproachSign`]
+    MapSetApproachSigns(IntersectionID, BTreeMap<RoadID, ApproachSign>),
     MapBuildSpecialBuilding {
         pos: OBB,
         kind: BuildingKind,
@@ -79,6 +149,8 @@ pub enum WorldCommand {
         zone: Option<Zone>,
     },
     MapLoadParis,
+    /// Builds the road network imported from an OpenStreetMap extract
+    MapLoadOsm(Box<OsmNetwork>),
     MapLoadTestField {
         pos: Vec2,
         size: u32,
@@ -89,6 +161,308 @@ pub enum WorldCommand {
         zone: Zone,
     },
     SetGameTime(GameTime),
+    SetRoadPlowPriority {
+        road: RoadID,
+        priority: PlowPriority,
+    },
+    SetCrisisFrequency(CrisisFrequency),
+    SetMilestonesEnabled(bool),
+    SetRoadRepairFunding(RepairFunding),
+    /// Replaces the running scenario, its events are run at their scheduled time
+    StartScenario(Box<Scenario>),
+    SetDistrictPolicy {
+        district: District,
+        policy: Policy,
+        enacted: bool,
+    },
+    /// Sets the building theme of a district, `None` to remove it
+    SetDistrictTheme {
+        district: District,
+        theme: Option<String>,
+    },
+    /// Sets how many delivery trucks a company wants and how it ships its goods
+    SetCompanyLogistics {
+        company: CompanyID,
+        fleet_size: u32,
+        mode: FreightMode,
+    },
+    /// Sets how many trains serve a freight station depending on its backlog
+    SetFreightService {
+        station: FreightStationID,
+        min_trains: u32,
+        max_trains: u32,
+    },
+    AddMapMarker(MapMarker),
+    UpdateMapMarker(MarkerID, MapMarker),
+    RemoveMapMarker(MarkerID),
+    AddBusLine(BusLine),
+    UpdateBusLine(BusLineID, BusLine),
+    RemoveBusLine(BusLineID),
+    AddMetroLine(MetroLine),
+    UpdateMetroLine(MetroLineID, MetroLine),
+    RemoveMetroLine(MetroLineID),
+    /// Queues the new public constructions instead of paying them at once,
+    /// spending at most `daily_limit` per day on the queue
+    SetConstructionBudget {
+        enabled: bool,
+        daily_limit: Money,
+    },
+    /// Moves a queued construction to the given place in the queue, 0 being funded first
+    MoveConstruction {
+        id: ConstructionID,
+        position: usize,
+    },
+    /// Removes a construction from the queue, what was already paid for it is refunded
+    CancelConstruction(ConstructionID),
+    /// Commands applied as a unit: each of them is validated against the state produced by the
+    /// earlier ones and the whole batch is rejected if one of them is invalid
+    Batch(Vec<WorldCommand>),
+}
+
+/// Why a command was rejected
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    LockedByMilestone(&'static str),
+    InvalidPlacement(&'static str),
+    /// The command refers to something that doesn't exist anymore
+    Outdated,
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::LockedByMilestone(m) => write!(f, "Requires the {m} milestone"),
+            CommandError::InvalidPlacement(reason) => f.write_str(reason),
+            CommandError::Outdated => f.write_str("The map changed in the meantime"),
+        }
+    }
+}
+
+/// Number of rejected commands kept for the UI
+const MAX_REJECTED: usize = 16;
+
+/// RejectedCommands keeps the last rejected commands so that the UI can tell the player
+/// why nothing happened.
+#[derive(Default)]
+pub struct RejectedCommands {
+    rejected: VecDeque<(Tick, CommandError)>,
+}
+
+impl RejectedCommands {
+    fn push(&mut self, tick: Tick, err: CommandError) {
+        if self.rejected.len() >= MAX_REJECTED {
+            self.rejected.pop_front();
+        }
+        self.rejected.push_back((tick, err));
+    }
+
+    /// Returns the commands rejected at or after the given tick
+    pub fn since(&self, tick: Tick) -> impl Iterator<Item = &(Tick, CommandError)> + '_ {
+        self.rejected.iter().filter(move |(t, _)| *t >= tick)
+    }
+}
+
+/// Number of undoable edits kept for the UI
+const MAX_UNDOABLE: usize = 64;
+
+/// UndoableEdits keeps the inverse of the last map edits, applying the inverse of an edit undoes
+/// it and records the inverse of the inverse so that the edit can be redone.
+#[derive(Default)]
+pub struct UndoableEdits {
+    next: u64,
+    edits: VecDeque<(u64, Inverse)>,
+}
+
+/// What undoes a map edit. The roads and buildings are recorded by their position, as a road
+/// split or a redo replaces them with new ones, and are looked up again by [`Inverse::resolve`].
+#[derive(Debug, Clone)]
+pub enum Inverse {
+    /// Builds again what the edit removed
+    Rebuild(WorldCommand),
+    /// Removes the roads along the points, the road that was built or the pieces it was split in
+    RemoveRoad(PolyLine3),
+    /// Removes the building centered on the position
+    RemoveBuilding(Vec2),
+    RemoveDecoration(Decoration),
+    /// Removes the bus stop whose passengers wait at the position
+    RemoveBusStop(Vec3),
+    /// Puts back the zone of the building centered on the position
+    UpdateZone(Vec2, Zone),
+    Batch(Vec<Inverse>),
+}
+
+impl Inverse {
+    fn batch(mut inverses: Vec<Inverse>) -> Inverse {
+        if inverses.len() == 1 {
+            return inverses.remove(0);
+        }
+        Inverse::Batch(inverses)
+    }
+
+    /// The command undoing the edit on the current map
+    pub fn resolve(&self, map: &Map) -> WorldCommand {
+        match *self {
+            Inverse::Rebuild(ref command) => resolve_ids(map, command),
+            Inverse::RemoveRoad(ref points) => {
+                WorldCommand::batch(roads_along(map, points).map(MapRemoveRoad).collect())
+            }
+            Inverse::RemoveBuilding(pos) => WorldCommand::batch(
+                building_at(map, pos)
+                    .map(MapRemoveBuilding)
+                    .into_iter()
+                    .collect(),
+            ),
+            Inverse::RemoveDecoration(ref decoration) => WorldCommand::batch(
+                map.decorations
+                    .iter()
+                    .find(|(_, d)| d.kind == decoration.kind && d.pos == decoration.pos)
+                    .map(|(id, _)| MapRemoveDecoration(id))
+                    .into_iter()
+                    .collect(),
+            ),
+            Inverse::RemoveBusStop(pos) => WorldCommand::batch(
+                map.bus_stops
+                    .iter()
+                    .find(|(_, s)| s.pos.is_close(pos, 0.5))
+                    .map(|(id, _)| MapRemoveBusStop(id))
+                    .into_iter()
+                    .collect(),
+            ),
+            Inverse::UpdateZone(pos, ref zone) => WorldCommand::batch(
+                building_at(map, pos)
+                    .map(|building| UpdateZone {
+                        building,
+                        zone: zone.clone(),
+                    })
+                    .into_iter()
+                    .collect(),
+            ),
+            Inverse::Batch(ref inverses) => {
+                WorldCommand::batch(inverses.iter().map(|x| x.resolve(map)).collect())
+            }
+        }
+    }
+}
+
+/// The roads lying along the points
+fn roads_along<'a>(map: &'a Map, points: &'a PolyLine3) -> impl Iterator<Item = RoadID> + 'a {
+    let on_points = |p: Vec3| points.project_dist2(p) < 1.0;
+    map.spatial_map
+        .query(points.flatten().bbox().expand(1.0), ProjectFilter::ROAD)
+        .filter_map(|kind| match kind {
+            ProjectKind::Road(id) => Some(id),
+            _ => None,
+        })
+        .filter(move |&id| {
+            let Some(r) = map.roads.get(id) else {
+                return false;
+            };
+            on_points(r.points.first())
+                && on_points(r.points.last())
+                && on_points(r.points.point_along(r.points.length() * 0.5))
+        })
+}
+
+/// The building centered on the position
+fn building_at(map: &Map, pos: Vec2) -> Option<BuildingID> {
+    map.spatial_map
+        .query_around(pos, 0.5, ProjectFilter::BUILDING)
+        .filter_map(|kind| kind.as_building())
+        .find(|&id| {
+            map.buildings
+                .get(id)
+                .map_or(false, |b| b.obb.center().is_close(pos, 0.5))
+        })
+}
+
+/// The road passing closest to the position
+fn road_near(map: &Map, pos: Vec3) -> Option<RoadID> {
+    map.spatial_map
+        .query_around(pos.xy(), 20.0, ProjectFilter::ROAD)
+        .filter_map(|kind| match kind {
+            ProjectKind::Road(id) => map.roads.get(id),
+            _ => None,
+        })
+        .min_by(|a, b| {
+            a.points
+                .project_dist2(pos)
+                .total_cmp(&b.points.project_dist2(pos))
+        })
+        .map(|r| r.id)
+}
+
+/// Replaces the intersections, roads and buildings of a rebuild that don't exist anymore
+/// by the ones at their position
+fn resolve_ids(map: &Map, command: &WorldCommand) -> WorldCommand {
+    let project = |mut proj: MapProject| {
+        if let ProjectKind::Inter(id) = proj.kind {
+            if !map
+                .intersections
+                .get(id)
+                .map_or(false, |i| i.pos.is_close(proj.pos, 1.0))
+            {
+                proj.kind = match map.project(proj.pos, 1.0, ProjectFilter::INTER).kind {
+                    kind @ ProjectKind::Inter(_) => kind,
+                    _ => ProjectKind::Ground,
+                };
+            }
+        }
+        proj
+    };
+    let mut command = command.clone();
+    match command {
+        MapMakeConnection {
+            ref mut from,
+            ref mut to,
+            ..
+        } => {
+            *from = project(*from);
+            *to = project(*to);
+        }
+        MapMakeMultipleConnections(ref mut projects, _) => {
+            for proj in projects {
+                *proj = project(*proj);
+            }
+        }
+        MapAddDecoration(ref mut decoration) => match decoration.on {
+            DecorationSupport::Road(ref mut id) if !map.roads.contains_key(*id) => {
+                *id = road_near(map, decoration.pos).unwrap_or(*id);
+            }
+            DecorationSupport::Building(ref mut id) if !map.buildings.contains_key(*id) => {
+                *id = map
+                    .spatial_map
+                    .query_around(decoration.pos.xy(), 0.5, ProjectFilter::BUILDING)
+                    .find_map(|kind| kind.as_building())
+                    .unwrap_or(*id);
+            }
+            _ => {}
+        },
+        MapAddBusStop(ref mut stop) if !map.roads.contains_key(stop.road) => {
+            stop.road = road_near(map, stop.pos).unwrap_or(stop.road);
+        }
+        _ => {}
+    }
+    command
+}
+
+impl UndoableEdits {
+    fn push(&mut self, inverse: Inverse) {
+        if self.edits.len() >= MAX_UNDOABLE {
+            self.edits.pop_front();
+        }
+        self.edits.push_back((self.next, inverse));
+        self.next += 1;
+    }
+
+    /// Index of the next recorded edit
+    pub fn next(&self) -> u64 {
+        self.next
+    }
+
+    /// Returns the inverses of the edits recorded at or after the given index
+    pub fn since(&self, idx: u64) -> impl Iterator<Item = &(u64, Inverse)> + '_ {
+        self.edits.iter().filter(move |(i, _)| *i >= idx)
+    }
 }
 
 impl AsRef<[WorldCommand]> for WorldCommands {
@@ -122,6 +496,10 @@ impl WorldCommands {
         self.commands.push(MapLoadParis)
     }
 
+    pub fn map_load_osm(&mut self, network: OsmNetwork) {
+        self.commands.push(MapLoadOsm(Box::new(network)))
+    }
+
     pub fn map_load_testfield(&mut self, pos: Vec2, size: u32, spacing: f32) {
         self.commands.push(MapLoadTestField { pos, size, spacing })
     }
@@ -130,11 +508,12 @@ impl WorldCommands {
         self.commands.push(SetGameTime(gt))
     }
 
-    pub fn add_train(&mut self, dist: f32, n_wagons: u32, laneid: LaneID) {
+    pub fn add_train(&mut self, dist: f32, n_wagons: u32, laneid: LaneID, loco: LocomotiveKind) {
         self.commands.push(AddTrain {
             dist,
             n_wagons,
             lane: laneid,
+            loco,
         })
     }
 
@@ -169,6 +548,40 @@ impl WorldCommands {
         self.commands.push(MapBuildHouse(id))
     }
 
+    pub fn map_set_lot_kind(&mut self, lots: Vec<LotID>, kind: LotKind) {
+        self.commands.push(MapSetLotKind { lots, kind })
+    }
+
+    pub fn map_set_road_electrified(&mut self, road: RoadID, electrified: bool) {
+        self.commands
+            .push(MapSetRoadElectrified { road, electrified })
+    }
+
+    pub fn map_set_road_curb(&mut self, road: RoadID, zone: CurbZone) {
+        self.commands.push(MapSetRoadCurb { road, zone })
+    }
+
+    pub fn map_add_road_furniture(&mut self, road: RoadID, furniture: RoadFurniture) {
+        self.commands.push(MapAddRoadFurniture { road, furniture })
+    }
+
+    pub fn map_remove_road_furniture(&mut self, road: RoadID, dist: f32) {
+        self.commands.push(MapRemoveRoadFurniture { road, dist })
+    }
+
+    pub fn map_rezone_buildings(
+        &mut self,
+        buildings: Vec<BuildingID>,
+        kind: LotKind,
+        immediate: bool,
+    ) {
+        self.commands.push(MapRezoneBuildings {
+            buildings,
+            kind,
+            immediate,
+        })
+    }
+
     pub fn map_make_connection(
         &mut self,
         from: MapProject,
@@ -184,6 +597,76 @@ impl WorldCommands {
         })
     }
 
+    pub fn set_road_plow_priority(&mut self, road: RoadID, priority: PlowPriority) {
+        self.commands.push(SetRoadPlowPriority { road, priority })
+    }
+
+    pub fn set_road_repair_funding(&mut self, funding: RepairFunding) {
+        self.commands.push(SetRoadRepairFunding(funding))
+    }
+
+    pub fn set_district_policy(&mut self, district: District, policy: Policy, enacted: bool) {
+        self.commands.push(SetDistrictPolicy {
+            district,
+            policy,
+            enacted,
+        })
+    }
+
+    pub fn set_district_theme(&mut self, district: District, theme: Option<String>) {
+        self.commands.push(SetDistrictTheme { district, theme })
+    }
+
+    pub fn set_company_logistics(
+        &mut self,
+        company: CompanyID,
+        fleet_size: u32,
+        mode: FreightMode,
+    ) {
+        self.commands.push(SetCompanyLogistics {
+            company,
+            fleet_size,
+            mode,
+        })
+    }
+
+    pub fn set_freight_service(
+        &mut self,
+        station: FreightStationID,
+        min_trains: u32,
+        max_trains: u32,
+    ) {
+        self.commands.push(SetFreightService {
+            station,
+            min_trains,
+            max_trains,
+        })
+    }
+
+    pub fn add_map_marker(&mut self, marker: MapMarker) {
+        self.commands.push(AddMapMarker(marker))
+    }
+
+    pub fn update_map_marker(&mut self, id: MarkerID, marker: MapMarker) {
+        self.commands.push(UpdateMapMarker(id, marker))
+    }
+
+    pub fn remove_map_marker(&mut self, id: MarkerID) {
+        self.commands.push(RemoveMapMarker(id))
+    }
+
+    pub fn set_crisis_frequency(&mut self, frequency: CrisisFrequency) {
+        self.commands.push(SetCrisisFrequency(frequency))
+    }
+
+    pub fn set_milestones_enabled(&mut self, enabled: bool) {
+        self.commands.push(SetMilestonesEnabled(enabled))
+    }
+
+    pub fn start_scenario(&mut self, scenario: Scenario) {
+        self.commands.push(StartScenario(Box::new(scenario)))
+    }
+
     pub fn map_update_intersection_policy(
         &mut self,
         id: IntersectionID,
@@ -196,23 +679,441 @@ impl WorldCommands {
             light: lp,
         })
     }
+
+    pub fn map_add_decoration(&mut self, decoration: Decoration) {
+        self.commands.push(MapAddDecoration(decoration))
+    }
+
+    pub fn map_remove_decoration(&mut self, id: DecorationID) {
+        self.commands.push(MapRemoveDecoration(id))
+    }
+
+    pub fn map_add_bus_stop(&mut self, stop: BusStop) {
+        self.commands.push(MapAddBusStop(stop))
+    }
+
+    pub fn map_remove_bus_stop(&mut self, id: BusStopID) {
+        self.commands.push(MapRemoveBusStop(id))
+    }
+
+    pub fn add_bus_line(&mut self, line: BusLine) {
+        self.commands.push(AddBusLine(line))
+    }
+
+    pub fn update_bus_line(&mut self, id: BusLineID, line: BusLine) {
+        self.commands.push(UpdateBusLine(id, line))
+    }
+
+    pub fn remove_bus_line(&mut self, id: BusLineID) {
+        self.commands.push(RemoveBusLine(id))
+    }
+
+    pub fn add_metro_line(&mut self, line: MetroLine) {
+        self.commands.push(AddMetroLine(line))
+    }
+
+    pub fn update_metro_line(&mut self, id: MetroLineID, line: MetroLine) {
+        self.commands.push(UpdateMetroLine(id, line))
+    }
+
+    pub fn remove_metro_line(&mut self, id: MetroLineID) {
+        self.commands.push(RemoveMetroLine(id))
+    }
+
+    pub fn set_construction_budget(&mut self, enabled: bool, daily_limit: Money) {
+        self.commands.push(SetConstructionBudget {
+            enabled,
+            daily_limit,
+        })
+    }
+
+    pub fn move_construction(&mut self, id: ConstructionID, position: usize) {
+        self.commands.push(MoveConstruction { id, position })
+    }
+
+    pub fn cancel_construction(&mut self, id: ConstructionID) {
+        self.commands.push(CancelConstruction(id))
+    }
+
+    pub fn map_set_light_offsets(&mut self, offsets: Vec<(IntersectionID, Option<u16>)>) {
+        self.commands.push(MapSetLightOffsets(offsets))
+    }
+
+    pub fn map_set_light_program(&mut self, id: IntersectionID, program: Option<LightProgram>) {
+        self.commands.push(MapSetLightProgram(id, program))
+    }
+
+    pub fn map_set_approach_signs(
+        &mut self,
+        id: IntersectionID,
+        signs: BTreeMap<RoadID, ApproachSign>,
+    ) {
+        self.commands.push(MapSetApproachSigns(id, signs))
+    }
 }
 
 impl WorldCommand {
+    /// Groups the commands so that they are applied as a unit
+    pub fn batch(mut commands: Vec<WorldCommand>) -> WorldCommand {
+        if commands.len() == 1 {
+            return commands.remove(0);
+        }
+        Batch(commands)
+    }
+
     /// Returns true if the command can be applied without any systems needed to be run afterward
     pub fn is_instant(&self) -> bool {
+        if let Batch(ref commands) = *self {
+            return commands.iter().all(WorldCommand::is_instant);
+        }
         matches!(
             self,
             MapBuildHouse(_)
+                | MapSetLotKind { .. }
+                | MapSetRoadElectrified { .. }
+                | MapSetRoadCurb { .. }
+                | MapAddRoadFurniture { .. }
+                | MapRemoveRoadFurniture { .. }
+                | MapAddDecoration(_)
+                | MapRemoveDecoration(_)
+                | MapAddBusStop(_)
+                | MapRemoveBusStop(_)
                 | MapUpdateIntersectionPolicy { .. }
+                | MapSetLightOffsets(_)
+                | MapSetLightProgram(..)
+                | MapSetApproachSigns(..)
                 | UpdateZone { .. }
                 | SetGameTime(_)
+                | SetRoadPlowPriority { .. }
+                | SetCrisisFrequency(_)
+                | SetMilestonesEnabled(_)
+                | SetRoadRepairFunding(_)
+                | SetDistrictPolicy { .. }
+                | SetDistrictTheme { .. }
+                | SetCompanyLogistics { .. }
+                | SetFreightService { .. }
+                | AddMapMarker(_)
+                | UpdateMapMarker(..)
+                | RemoveMapMarker(_)
+                | AddBusLine(_)
+                | UpdateBusLine(..)
+                | RemoveBusLine(_)
+                | AddMetroLine(_)
+                | UpdateMetroLine(..)
+                | RemoveMetroLine(_)
+                | SetConstructionBudget { .. }
+                | MoveConstruction { .. }
+                | CancelConstruction(_)
         )
     }
 
-    pub fn apply(&self, sim: &mut Simulation) {
+    /// What is built by the command if it is a public construction, those are queued when the
+    /// construction queue is enabled
+    pub fn construction_name(&self) -> Option<&'static str> {
+        match *self {
+            Batch(ref commands) => {
+                let mut names = commands.iter().map(WorldCommand::construction_name);
+                let first = names.next()??;
+                names.all(|n| n.is_some()).then_some(first)
+            }
+            MapMakeConnection { .. } => Some("Road"),
+            MapMakeMultipleConnections(..) => Some("Roads"),
+            MapBuildHouse(_) => Some("House"),
+            MapBuildSpecialBuilding { .. } => Some("Building"),
+            MapSetRoadElectrified {
+                electrified: true, ..
+            } => Some("Electrification"),
+            UpdateZone { .. } => Some("Zone"),
+            MapAddBusStop(_) => Some("Bus stop"),
+            _ => None,
+        }
+    }
+
+    /// What the money spent on the command is shown as in the expenses
+    pub fn expense_name(&self) -> &'static str {
+        if let Some(name) = self.construction_name() {
+            return name;
+        }
+        match *self {
+            Batch(_) => "Construction",
+            AddTrain { .. } => "Train",
+            AddBusLine(_) | UpdateBusLine(..) => "Bus line",
+            AddMetroLine(_) | UpdateMetroLine(..) => "Metro line",
+            SetCompanyLogistics { .. } => "Trucks",
+            _ => "Other",
+        }
+    }
+
+    /// Checks that the command can be applied to the current state of the simulation.
+    /// The commands of a batch can depend on the earlier ones, so the batch is tried on a fork
+    /// of the simulation and nothing is applied unless all of them go through
+    pub fn validate(&self, sim: &Simulation) -> Result<(), CommandError> {
+        let map = sim.map();
+        match *self {
+            Batch(ref commands) => {
+                drop(map);
+                let mut fork = chunked_save::fork(sim).map_err(|e| {
+                    log::error!("could not fork the simulation to check a batch: {}", e);
+                    CommandError::Outdated
+                })?;
+                for command in commands {
+                    command.validate(&fork)?;
+                    command.apply_validated(&mut fork, &mut vec![]);
+                }
+            }
+            MapRemoveIntersection(id) => {
+                if !map.intersections().contains_key(id) {
+                    return Err(CommandError::Outdated);
+                }
+            }
+            MapRemoveRoad(id) => {
+                if !map.roads().contains_key(id) {
+                    return Err(CommandError::Outdated);
+                }
+            }
+            MapRemoveBuilding(id) => {
+                if !map.buildings().contains_key(id) {
+                    return Err(CommandError::Outdated);
+                }
+            }
+            MapMakeConnection {
+                from, to, ref pat, ..
+            } => {
+                if !from.kind.check_valid(&map) || !to.kind.check_valid(&map) {
+                    return Err(CommandError::Outdated);
+                }
+                if pat.lanes().any(|(kind, _, _)| kind.is_water()) {
+                    check_canal(from.pos, to.pos).map_err(CommandError::InvalidPlacement)?;
+                }
+            }
+            MapMakeMultipleConnections(ref projects, ref links) => {
+                if projects.iter().any(|p| !p.kind.check_valid(&map))
+                    || links
+                        .iter()
+                        .any(|&(from, to, ..)| from >= projects.len() || to >= projects.len())
+                {
+                    return Err(CommandError::Outdated);
+                }
+                for (from, to, _, pat) in links {
+                    if pat.lanes().any(|(kind, _, _)| kind.is_water()) {
+                        check_canal(projects[*from].pos, projects[*to].pos)
+                            .map_err(CommandError::InvalidPlacement)?;
+                    }
+                }
+            }
+            MapBuildSpecialBuilding { pos, kind, .. } => {
+                if let Some(m) = sim
+                    .read::<Milestones>()
+                    .locked_by(kind, &sim.read::<GoodsCompanyRegistry>())
+                {
+                    return Err(CommandError::LockedByMilestone(m.name));
+                }
+                if kind == BuildingKind::Airport {
+                    check_airport_footprint(&map, &pos).map_err(CommandError::InvalidPlacement)?;
+                }
+                if kind == BuildingKind::Harbor {
+                    check_harbor_footprint(&map, &pos).map_err(CommandError::InvalidPlacement)?;
+                }
+                if kind == BuildingKind::MetroStation {
+                    check_metro_station_footprint(&map, &pos)
+                        .map_err(CommandError::InvalidPlacement)?;
+                }
+                if map.building_overlaps(pos) {
+                    return Err(CommandError::InvalidPlacement(
+                        "Intersecting with a building",
+                    ));
+                }
+                if map.environment.height(pos.center()).is_none() {
+                    return Err(CommandError::InvalidPlacement("Outside of the map"));
+                }
+            }
+            AddTrain {
+                dist,
+                n_wagons,
+                lane,
+                loco,
+            } => {
+                if !map.lanes().contains_key(lane) {
+                    return Err(CommandError::Outdated);
+                }
+                check_train_placement(&map, &sim.world().trains, lane, dist, n_wagons, loco)
+                    .map_err(CommandError::InvalidPlacement)?;
+            }
+            MapSetRoadElectrified { road, .. } => {
+                let Some(r) = map.roads().get(road) else {
+                    return Err(CommandError::Outdated);
+                };
+                if !r.is_rail() {
+                    return Err(CommandError::InvalidPlacement(
+                        "Only rails can be electrified",
+                    ));
+                }
+            }
+            MapSetLightOffsets(ref offsets) => {
+                if offsets
+                    .iter()
+                    .any(|&(id, _)| !map.intersections().contains_key(id))
+                {
+                    return Err(CommandError::Outdated);
+                }
+            }
+            MapSetLightProgram(id, _) => {
+                if !map.intersections().contains_key(id) {
+                    return Err(CommandError::Outdated);
+                }
+            }
+            MapSetApproachSigns(id, ref signs) => {
+                let Some(inter) = map.intersections().get(id) else {
+                    return Err(CommandError::Outdated);
+                };
+                if signs.keys().any(|r| !inter.roads.contains(r)) {
+                    return Err(CommandError::Outdated);
+                }
+            }
+            MapSetRoadCurb { road, zone } => {
+                let Some(r) = map.roads().get(road) else {
+                    return Err(CommandError::Outdated);
+                };
+                if r.is_rail() {
+                    return Err(CommandError::InvalidPlacement("Rails have no curb"));
+                }
+                if zone.start >= zone.end {
+                    return Err(CommandError::InvalidPlacement("Empty curb section"));
+                }
+            }
+            MapAddRoadFurniture { road, furniture } => {
+                let Some(r) = map.roads().get(road) else {
+                    return Err(CommandError::Outdated);
+                };
+                if r.is_rail() || r.is_canal() {
+                    return Err(CommandError::InvalidPlacement(
+                        "Furniture can only be placed on roads",
+                    ));
+                }
+                if furniture.dist < 0.0 || furniture.dist > r.length() {
+                    return Err(CommandError::InvalidPlacement("Outside of the road"));
+                }
+            }
+            MapRemoveRoadFurniture { road, .. } => {
+                if !map.roads().contains_key(road) {
+                    return Err(CommandError::Outdated);
+                }
+            }
+            MapAddDecoration(ref decoration) => {
+                decoration.check().map_err(CommandError::InvalidPlacement)?;
+                let exists = match decoration.on {
+                    DecorationSupport::Ground => true,
+                    DecorationSupport::Road(id) => map.roads().contains_key(id),
+                    DecorationSupport::Building(id) => map.buildings().contains_key(id),
+                };
+                if !exists {
+                    return Err(CommandError::Outdated);
+                }
+            }
+            MapRemoveDecoration(id) => {
+                if !map.decorations().contains_key(id) {
+                    return Err(CommandError::Outdated);
+                }
+            }
+            MapAddBusStop(ref stop) => {
+                stop.check().map_err(CommandError::InvalidPlacement)?;
+                if !map.roads().contains_key(stop.road) {
+                    return Err(CommandError::Outdated);
+                }
+                if map
+                    .bus_stops()
+                    .values()
+                    .any(|s| s.pos.is_close(stop.pos, MIN_BUS_STOP_SPACING))
+                {
+                    return Err(CommandError::InvalidPlacement(
+                        "Too close to another bus stop",
+                    ));
+                }
+            }
+            MapRemoveBusStop(id) => {
+                if !map.bus_stops().contains_key(id) {
+                    return Err(CommandError::Outdated);
+                }
+            }
+            AddBusLine(ref line) | UpdateBusLine(_, ref line) => {
+                line.check().map_err(CommandError::InvalidPlacement)?;
+                if !line.stops.iter().all(|&s| map.bus_stops().contains_key(s)) {
+                    return Err(CommandError::Outdated);
+                }
+                if let UpdateBusLine(id, _) = *self {
+                    if sim.read::<BusLines>().get(id).is_none() {
+                        return Err(CommandError::Outdated);
+                    }
+                }
+            }
+            AddMetroLine(ref line) | UpdateMetroLine(_, ref line) => {
+                line.check().map_err(CommandError::InvalidPlacement)?;
+                let is_station = |&b| {
+                    map.buildings()
+                        .get(b)
+                        .map_or(false, |b| b.kind == BuildingKind::MetroStation)
+                };
+                if !line.stations.iter().all(is_station) {
+                    return Err(CommandError::Outdated);
+                }
+                if let UpdateMetroLine(id, _) = *self {
+                    if sim.read::<MetroLines>().get(id).is_none() {
+                        return Err(CommandError::Outdated);
+                    }
+                }
+            }
+            SetCompanyLogistics { company, .. } => {
+                if !sim.world.companies.contains_key(company) {
+                    return Err(CommandError::Outdated);
+                }
+            }
+            SetFreightService { station, .. } => {
+                if !sim.world.freight_stations.contains_key(station) {
+                    return Err(CommandError::Outdated);
+                }
+            }
+            SetDistrictTheme {
+                theme: Some(ref theme),
+                ..
+            } => {
+                if sim.read::<BuildingArchetypes>().theme(theme).is_none() {
+                    return Err(CommandError::InvalidPlacement("Unknown building theme"));
+                }
+            }
+            UpdateZone { building, .. } => {
+                if !map.buildings().contains_key(building) {
+                    return Err(CommandError::Outdated);
+                }
+            }
+            SetConstructionBudget { daily_limit, .. } => {
+                if daily_limit < Money::ZERO {
+                    return Err(CommandError::InvalidPlacement(
+                        "The budget can't be negative",
+                    ));
+                }
+            }
+            MoveConstruction { id, .. } | CancelConstruction(id) => {
+                if !sim.read::<ConstructionQueue>().contains(id) {
+                    return Err(CommandError::Outdated);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Applies the command and charges its cost, returns whether it was applied now: false when
+    /// it was rejected or queued in the construction queue
+    pub fn apply(&self, sim: &mut Simulation) -> bool {
+        if let Err(err) = self.validate(sim) {
+            reject(sim, err);
+            return false;
+        }
+
         let cost = Government::action_cost(self, sim);
-        sim.write::<Government>().money -= cost;
+        let queued = cost > Money::ZERO
+            && self.construction_name().is_some()
+            && sim.read::<ConstructionQueue>().enabled;
 
         let mut rep = sim.resources.write::<Replay>();
         if rep.enabled {
@@ -221,14 +1122,120 @@ impl WorldCommand {
         }
         drop(rep);
 
+        if queued {
+            sim.write::<ConstructionQueue>().push(self.clone(), cost);
+            return false;
+        }
+        self.apply_recording_undo(sim);
+        let timestamp = sim.read::<GameTime>().timestamp;
+        sim.write::<Government>()
+            .spend(cost, self.expense_name(), timestamp);
+        true
+    }
+
+    /// Applies a construction that was paid through the construction queue
+    pub(crate) fn apply_paid(&self, sim: &mut Simulation) -> Result<(), CommandError> {
+        if let Err(err) = self.validate(sim) {
+            reject(sim, err);
+            return Err(err);
+        }
+        self.apply_recording_undo(sim);
+        Ok(())
+    }
+
+    fn apply_recording_undo(&self, sim: &mut Simulation) {
+        let mut undo = vec![];
+        self.apply_validated(sim, &mut undo);
+        if !undo.is_empty() {
+            undo.reverse();
+            sim.write::<UndoableEdits>().push(Inverse::batch(undo));
+        }
+    }
+
+    /// Applies the command without validating, charging its action cost or recording it.
+    /// The inverses undoing the map edits are pushed to `undo` in the order of the edits.
+    fn apply_validated(&self, sim: &mut Simulation, undo: &mut Vec<Inverse>) {
         match *self {
-            MapRemoveIntersection(id) => sim.map_mut().remove_intersection(id),
-            MapRemoveRoad(id) => drop(sim.map_mut().remove_road(id)),
-            MapRemoveBuilding(id) => drop(sim.map_mut().remove_building(id)),
+            Batch(ref commands) => {
+                for command in commands {
+                    command.apply_validated(sim, undo);
+                }
+            }
+            MapRemoveIntersection(id) => {
+                undo.extend(rebuild_intersection(&sim.map(), id).map(Inverse::Rebuild));
+                sim.map_mut().remove_intersection(id)
+            }
+            MapRemoveRoad(id) => {
+                undo.extend(rebuild_road(&sim.map(), id).map(Inverse::Rebuild));
+                drop(sim.map_mut().remove_road(id))
+            }
+            MapRemoveBuilding(id) => {
+                undo.extend(rebuild_building(&sim.map(), id).map(Inverse::Rebuild));
+                drop(sim.map_mut().remove_building(id))
+            }
             MapBuildHouse(id) => {
-                if let Some(build) = sim.map_mut().build_house(id) {
-                    let mut infos = sim.write::<BuildingInfos>();
-                    infos.insert(build);
+                let archetype = sim.map().lots().get(id).and_then(|lot| {
+                    let policies = sim.read::<Policies>();
+                    let theme = policies.theme(District::of(lot.shape.center()));
+                    sim.read::<BuildingArchetypes>().pick(lot, theme).cloned()
+                });
+                let build = sim.map_mut().build_house(id, archetype.as_ref());
+                if let Some(build) = build {
+                    insert_building_info(sim, build);
+                    push_construction_event(sim, build);
+                }
+            }
+            MapSetRoadElectrified { road, electrified } => {
+                sim.map_mut().set_road_electrified(road, electrified)
+            }
+            MapSetRoadCurb { road, zone } => sim.map_mut().set_road_curb(road, zone),
+            MapAddRoadFurniture { road, furniture } => {
+                sim.map_mut().add_road_furniture(road, furniture)
+            }
+            MapRemoveRoadFurniture { road, dist } => {
+                sim.map_mut().remove_road_furniture(road, dist)
+            }
+            MapAddDecoration(ref decoration) => {
+                if sim.map_mut().add_decoration(decoration.clone()).is_some() {
+                    undo.push(Inverse::RemoveDecoration(decoration.clone()));
+                }
+            }
+            MapAddBusStop(ref stop) => {
+                if sim.map_mut().add_bus_stop(stop.clone()).is_some() {
+                    undo.push(Inverse::RemoveBusStop(stop.pos));
+                }
+            }
+            MapRemoveBusStop(id) => {
+                if let Some(stop) = sim.map_mut().remove_bus_stop(id) {
+                    undo.push(Inverse::Rebuild(MapAddBusStop(stop)));
+                }
+            }
+            MapRemoveDecoration(id) => {
+                if let Some(decoration) = sim.map_mut().remove_decoration(id) {
+                    undo.push(Inverse::Rebuild(MapAddDecoration(decoration)));
+                }
+            }
+            MapSetLotKind { ref lots, kind } => {
+                let mut map = sim.map_mut();
+                for &lot in lots {
+                    map.set_lot_kind(lot, kind);
+                }
+            }
+            MapRezoneBuildings {
+                ref buildings,
+                kind,
+                immediate,
+            } => {
+                if immediate {
+                    let mut map = sim.map_mut();
+                    for &b in buildings {
+                        map.rezone_building(b, kind);
+                    }
+                } else {
+                    let mut rezoning = sim.write::<Rezoning>();
+                    for &b in buildings {
+                        rezoning.queue(b, kind);
+                    }
                 }
             }
             MapMakeConnection {
@@ -237,7 +1244,10 @@ impl WorldCommand {
                 inter,
                 ref pat,
             } => {
-                sim.write::<Map>().make_connection(from, to, inter, pat);
+                let mut map = sim.map_mut();
+                if let Some((_, r)) = map.make_connection(from, to, inter, pat) {
+                    undo.push(Inverse::RemoveRoad(map.roads[r].points.clone()));
+                }
             }
             MapMakeMultipleConnections(ref projects, ref links) => {
                 let mut map = sim.map_mut();
@@ -254,6 +1264,7 @@ impl WorldCommand {
                     }
 
                     if let Some((_, r)) = map.make_connection(fromproj, toproj, *interpoint, pat) {
+                        undo.push(Inverse::RemoveRoad(map.roads[r].points.clone()));
                         if fromproj.kind.is_ground() {
                             inters.insert(*from, map.roads[r].src);
                         }
@@ -271,28 +1282,123 @@ impl WorldCommand {
                 i.light_policy = lp;
                 i.turn_policy = tp;
             }),
+            MapSetLightOffsets(ref offsets) => {
+                let mut map = sim.map_mut();
+                for &(id, offset) in offsets {
+                    map.update_intersection(id, move |i| i.light_offset = offset);
+                }
+            }
+            MapSetLightProgram(id, ref program) => {
+                sim.map_mut()
+                    .update_intersection(id, |i| i.light_program = program.clone());
+            }
+            MapSetApproachSigns(id, ref signs) => {
+                sim.map_mut()
+                    .update_intersection(id, |i| i.approach_signs = signs.clone());
+            }
             MapBuildSpecialBuilding {
                 pos: obb,
                 kind,
                 gen,
                 ref zone,
             } => {
-                if let Some(id) =
+                let build =
                     sim.write::<Map>()
-                        .build_special_building(&obb, kind, gen, zone.clone())
-                {
-                    sim.write::<BuildingInfos>().insert(id);
+                        .build_special_building(&obb, kind, gen, zone.clone());
+                if let Some(id) = build {
+                    insert_building_info(sim, id);
+                    push_construction_event(sim, id);
+                    undo.push(Inverse::RemoveBuilding(obb.center()));
                 }
             }
             SetGameTime(gt) => *sim.write::<GameTime>() = gt,
+            SetRoadPlowPriority { road, priority } => {
+                sim.write::<RoadMaintenance>().set_priority(road, priority)
+            }
+            SetRoadRepairFunding(funding) => {
+                sim.write::<RoadMaintenance>().funding = funding;
+            }
+            SetDistrictPolicy {
+                district,
+                policy,
+                enacted,
+            } => {
+                sim.write::<Policies>().set(district, policy, enacted);
+            }
+            SetDistrictTheme {
+                district,
+                ref theme,
+            } => {
+                sim.write::<Policies>().set_theme(district, theme.clone());
+            }
+            SetCompanyLogistics {
+                company,
+                fleet_size,
+                mode,
+            } => set_logistics(sim, company, fleet_size, mode),
+            SetFreightService {
+                station,
+                min_trains,
+                max_trains,
+            } => set_freight_service(sim, station, min_trains, max_trains),
+            AddMapMarker(ref marker) => {
+                sim.write::<MapMarkers>().add(marker.clone());
+            }
+            UpdateMapMarker(id, ref marker) => {
+                sim.write::<MapMarkers>().update(id, marker.clone());
+            }
+            RemoveMapMarker(id) => sim.write::<MapMarkers>().remove(id),
+            AddBusLine(ref line) => {
+                sim.write::<BusLines>().add(line.clone());
+            }
+            UpdateBusLine(id, ref line) => {
+                sim.write::<BusLines>().update(id, line.clone());
+            }
+            RemoveBusLine(id) => sim.write::<BusLines>().remove(id),
+            AddMetroLine(ref line) => {
+                sim.write::<MetroLines>().add(line.clone());
+            }
+            UpdateMetroLine(id, ref line) => {
+                sim.write::<MetroLines>().update(id, line.clone());
+            }
+            RemoveMetroLine(id) => sim.write::<MetroLines>().remove(id),
+            SetConstructionBudget {
+                enabled,
+                daily_limit,
+            } => {
+                let mut queue = sim.write::<ConstructionQueue>();
+                queue.enabled = enabled;
+                queue.daily_limit = daily_limit;
+            }
+            MoveConstruction { id, position } => {
+                sim.write::<ConstructionQueue>().reorder(id, position);
+            }
+            CancelConstruction(id) => {
+                let refund = sim.write::<ConstructionQueue>().cancel(id);
+                sim.write::<Government>().money += refund;
+            }
+            StartScenario(ref scenario) => {
+                let mut scenario = Scenario::clone(scenario);
+                scenario.start(sim.read::<GameTime>().timestamp);
+                *sim.write::<RandProvider>() = RandProvider::new(scenario.seed);
+                sim.resources.insert::<Scenario>(scenario);
+            }
+            SetCrisisFrequency(frequency) => {
+                sim.write::<EconomyCrises>().frequency = frequency;
+            }
+            SetMilestonesEnabled(enabled) => {
+                sim.write::<Milestones>().enabled = enabled;
+            }
             AddTrain {
                 dist,
                 n_wagons,
                 lane,
+                loco,
             } => {
-                spawn_train(sim, dist, n_wagons, lane, RailWagonKind::Freight);
+                spawn_train(sim, dist, n_wagons, lane, RailWagonKind::Freight, loco);
             }
             MapLoadParis => load_parismap(&mut sim.map_mut()),
+            MapLoadOsm(ref network) => network.build(&mut sim.map_mut()),
             MapLoadTestField { pos, size, spacing } => {
                 load_testfield(&mut sim.map_mut(), pos, size, spacing)
             }
@@ -306,6 +1412,7 @@ impl WorldCommand {
 
                 if opts.terrain_size > 0 {
                     generate_terrain(sim, opts.terrain_size);
+                    add_edge_connections(&mut sim.map_mut(), opts.edge_connections);
                 }
 
                 sim.resources
@@ -314,6 +1421,12 @@ impl WorldCommand {
             UpdateZone { building, ref zone } => {
                 let mut map = sim.map_mut();
 
+                if let Some(b) = map.buildings.get(building) {
+                    if let Some(old) = b.zone.clone() {
+                        undo.push(Inverse::UpdateZone(b.obb.center(), old));
+                    }
+                }
+
                 map.update_zone(building, move |z| *z = zone.clone());
             }
             SpawnRandomCars { n_cars } => {
@@ -352,13 +1465,109 @@ impl WorldCommand {
                 slope,
             } => {
                 let tick = *sim.read::<Tick>();
-                sim.map_mut()
+                let volume = sim
+                    .map_mut()
                     .terraform(tick, kind, center, radius, amount, level, slope);
+                // the volume moved is only known once the terrain is modified
+                let timestamp = sim.read::<GameTime>().timestamp;
+                sim.write::<Government>().spend(
+                    Government::terraform_cost(volume),
+                    "Terraforming",
+                    timestamp,
+                );
             }
         }
     }
 }
 
+/// Records why a command was rejected so that the UI can tell the player
+fn reject(sim: &Simulation, err: CommandError) {
+    log::warn!("rejected command: {}", err);
+    let tick = *sim.read::<Tick>();
+    sim.write::<RejectedCommands>().push(tick, err);
+}
+
+/// Where a road ends once it is removed, the intersection disappears along with its last road
+fn road_end(map: &Map, id: IntersectionID) -> Option<MapProject> {
+    let inter = map.intersections.get(id)?;
+    Some(MapProject {
+        pos: inter.pos,
+        kind: if inter.roads.len() > 1 {
+            ProjectKind::Inter(id)
+        } else {
+            ProjectKind::Ground
+        },
+    })
+}
+
+/// The command building the road again once it is removed.
+/// The curbs and furniture of the road are not brought back.
+fn rebuild_road(map: &Map, id: RoadID) -> Option<WorldCommand> {
+    let road = map.roads.get(id)?;
+    let from = road_end(map, road.src)?;
+    let to = road_end(map, road.dst)?;
+    Some(MapMakeConnection {
+        from,
+        to,
+        inter: road.segment.elbow(from.pos.xy()),
+        pat: road.pattern(&map.lanes),
+    })
+}
+
+/// The command building the intersection and its roads again once it is removed
+fn rebuild_intersection(map: &Map, id: IntersectionID) -> Option<WorldCommand> {
+    let inter = map.intersections.get(id)?;
+    let mut projects = vec![MapProject {
+        pos: inter.pos,
+        kind: ProjectKind::Ground,
+    }];
+    let mut links = vec![];
+    for &r in &inter.roads {
+        let road = unwrap_cont!(map.roads.get(r));
+        let other = unwrap_cont!(road.other_end(id));
+        let proj = unwrap_cont!(road_end(map, other));
+        projects.push(proj);
+
+        let (from, to) = if road.src == id {
+            (0, projects.len() - 1)
+        } else {
+            (projects.len() - 1, 0)
+        };
+        let elbow = road.segment.elbow(projects[from].pos.xy());
+        links.push((from, to, elbow, road.pattern(&map.lanes)));
+    }
+    Some(MapMakeMultipleConnections(projects, links))
+}
+
+/// The command building the building again once it is removed
+fn rebuild_building(map: &Map, id: BuildingID) -> Option<WorldCommand> {
+    let b = map.buildings.get(id)?;
+    Some(MapBuildSpecialBuilding {
+        pos: b.obb,
+        kind: b.kind,
+        gen: b.gen?,
+        zone: b.zone.clone(),
+    })
+}
+
+pub(crate) fn insert_building_info(sim: &Simulation, id: BuildingID) {
+    let map = sim.map();
+    let Some(b) = map.buildings().get(id) else {
+        return;
+    };
+    sim.write::<BuildingInfos>()
+        .insert(b, &sim.read::<GoodsCompanyRegistry>());
+}
+
+pub(crate) fn push_construction_event(sim: &Simulation, id: BuildingID) {
+    let Some(pos) = sim.map().buildings().get(id).map(|b| b.door_pos) else {
+        return;
+    };
+    let tick = *sim.read::<Tick>();
+    sim.write::<SimEvents>()
+        .push(tick, pos, SimEventKind::Construction);
+}
+
 fn generate_terrain(sim: &mut Simulation, size: u16) {
     info!("generating terrain..");
     let t = Instant::now();
@@ -387,6 +1596,7 @@ fn generate_terrain(sim: &mut Simulation, size: u16) {
             BuildingKind::ExternalTrading,
             BuildingGen::NoWalkway {
                 door_pos: Vec2::ZERO,
+                doors: DoorLayout::NONE,
             },
             None,
         )
@@ -418,3 +1628,384 @@ impl From<&WorldCommands> for Vec<WorldCommand> {
         x.commands.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{CurbKind, DecorationKind, FurnitureKind, LaneDirection, RoadSegmentKind};
+    use crate::tests::TestCtx;
+    use geom::{vec2, Color};
+
+    #[test]
+    fn invalid_batch_is_fully_rejected() {
+        let mut test = TestCtx::new();
+
+        test.build_roads(&[vec3(0., 0., 0.), vec3(100., 0., 0.)]);
+        let house = test.build_house_near(vec2(50.0, 50.0));
+        let house_obb = test.g.map().buildings()[house].obb;
+        let n_roads = test.g.map().roads().len();
+
+        test.apply(&[WorldCommand::batch(vec![
+            MapMakeConnection {
+                from: MapProject::ground(vec3(0.0, 200.0, 0.0)),
+                to: MapProject::ground(vec3(100.0, 200.0, 0.0)),
+                inter: None,
+                pat: LanePatternBuilder::new().rail(true).build(),
+            },
+            MapBuildSpecialBuilding {
+                pos: house_obb,
+                kind: BuildingKind::RailFreightStation,
+                gen: BuildingGen::NoWalkway {
+                    door_pos: Vec2::ZERO,
+                    doors: DoorLayout::NONE,
+                },
+                zone: None,
+            },
+        ])]);
+
+        assert_eq!(test.g.map().roads().len(), n_roads);
+        let rejected: Vec<_> = test
+            .g
+            .read::<RejectedCommands>()
+            .since(Tick(0))
+            .map(|x| x.1)
+            .collect();
+        assert_eq!(
+            rejected,
+            vec![CommandError::InvalidPlacement(
+                "Intersecting with a building"
+            )]
+        );
+    }
+
+    #[test]
+    fn batch_commands_are_checked_against_the_earlier_ones() {
+        let mut test = TestCtx::new();
+
+        test.build_roads(&[vec3(0., 0., 0.), vec3(100., 0., 0.)]);
+        let n_buildings = test.g.map().buildings().len();
+
+        let station = MapBuildSpecialBuilding {
+            pos: OBB::new(vec2(50.0, 60.0), Vec2::X, 40.0, 40.0),
+            kind: BuildingKind::RailFreightStation,
+            gen: BuildingGen::NoWalkway {
+                door_pos: Vec2::ZERO,
+                doors: DoorLayout::NONE,
+            },
+            zone: None,
+        };
+        // both are valid on their own but the second one overlaps the first one once it is built
+        test.apply(&[WorldCommand::batch(vec![station.clone(), station])]);
+
+        assert_eq!(test.g.map().buildings().len(), n_buildings);
+        let rejected: Vec<_> = test
+            .g
+            .read::<RejectedCommands>()
+            .since(Tick(0))
+            .map(|x| x.1)
+            .collect();
+        assert_eq!(
+            rejected,
+            vec![CommandError::InvalidPlacement(
+                "Intersecting with a building"
+            )]
+        );
+    }
+
+    #[test]
+    fn failed_batch_leaves_no_split_behind() {
+        let mut test = TestCtx::new();
+
+        test.build_roads(&[vec3(0., 0., 0.), vec3(100., 0., 0.)]);
+        let road = test.g.map().roads().keys().next().unwrap();
+        let inter = test.g.map().roads()[road].src;
+        let n_roads = test.g.map().roads().len();
+        let n_inters = test.g.map().intersections().len();
+
+        test.apply(&[WorldCommand::batch(vec![
+            MapSetLightOffsets(vec![(inter, Some(7))]),
+            // splits the road in two
+            MapMakeConnection {
+                from: MapProject {
+                    pos: vec3(50.0, 0.0, 0.0),
+                    kind: ProjectKind::Road(road),
+                },
+                to: MapProject::ground(vec3(50.0, 100.0, 0.0)),
+                inter: None,
+                pat: LanePatternBuilder::new().build(),
+            },
+            // the road doesn't exist anymore once it is split
+            MapRemoveRoad(road),
+            MapSetLightOffsets(vec![(inter, Some(3))]),
+        ])]);
+
+        let map = test.g.map();
+        assert_eq!(map.roads().len(), n_roads);
+        assert_eq!(map.intersections().len(), n_inters);
+        assert!(map.roads().contains_key(road));
+        assert_eq!(map.intersections()[inter].light_offset, None);
+        drop(map);
+        let rejected: Vec<_> = test
+            .g
+            .read::<RejectedCommands>()
+            .since(Tick(0))
+            .map(|x| x.1)
+            .collect();
+        assert_eq!(rejected, vec![CommandError::Outdated]);
+    }
+
+    #[test]
+    fn road_edits_can_be_undone_and_redone() {
+        let mut test = TestCtx::new();
+        let last_inverse = |test: &TestCtx| {
+            let edits = test.g.read::<UndoableEdits>();
+            let inverse = &edits.since(edits.next() - 1).last().unwrap().1;
+            inverse.resolve(&test.g.map())
+        };
+
+        test.apply(&[MapMakeConnection {
+            from: MapProject::ground(vec3(0.0, 0.0, 0.0)),
+            to: MapProject::ground(vec3(100.0, 0.0, 0.0)),
+            inter: Some(vec2(50.0, 30.0)),
+            pat: LanePatternBuilder::new().build(),
+        }]);
+        assert_eq!(test.g.map().roads().len(), 1);
+
+        let undo = last_inverse(&test);
+        assert!(matches!(undo, MapRemoveRoad(_)));
+        test.apply(&[undo]);
+        assert_eq!(test.g.map().roads().len(), 0);
+        assert_eq!(test.g.map().intersections().len(), 0);
+
+        let redo = last_inverse(&test);
+        test.apply(&[redo]);
+        let map = test.g.map();
+        let (_, road) = map.roads().iter().next().unwrap();
+        assert!(road.points.first().xy().is_close(vec2(0.0, 0.0), 0.5));
+        assert!(road.points.last().xy().is_close(vec2(100.0, 0.0), 0.5));
+        assert!(matches!(road.segment, RoadSegmentKind::Curved(_)));
+    }
+
+    #[test]
+    fn undo_finds_the_roads_replaced_by_a_split() {
+        let mut test = TestCtx::new();
+        // applies the command and returns what undoes it
+        let apply = |test: &mut TestCtx, command: WorldCommand| {
+            test.apply(&[command]);
+            let edits = test.g.read::<UndoableEdits>();
+            edits.since(edits.next() - 1).last().unwrap().1.clone()
+        };
+        let resolve = |test: &TestCtx, inverse: &Inverse| inverse.resolve(&test.g.map());
+        let connection = |from, to| MapMakeConnection {
+            from: MapProject::ground(from),
+            to: MapProject::ground(to),
+            inter: None,
+            pat: LanePatternBuilder::new().build(),
+        };
+
+        let first = apply(
+            &mut test,
+            connection(vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)),
+        );
+        assert!(matches!(resolve(&test, &first), MapRemoveRoad(_)));
+
+        // splits the first road in two
+        let split = test
+            .g
+            .map()
+            .project(vec3(50.0, 0.0, 0.0), 1.0, ProjectFilter::ROAD);
+        let second = apply(
+            &mut test,
+            MapMakeConnection {
+                from: split,
+                to: MapProject::ground(vec3(50.0, 80.0, 0.0)),
+                inter: None,
+                pat: LanePatternBuilder::new().build(),
+            },
+        );
+        assert_eq!(test.g.map().roads().len(), 3);
+
+        // undo, redo and undo the second road, the split stays
+        let undo = resolve(&test, &second);
+        let redo = apply(&mut test, undo);
+        assert_eq!(test.g.map().roads().len(), 2);
+        let redo = resolve(&test, &redo);
+        let second = apply(&mut test, redo);
+        assert_eq!(test.g.map().roads().len(), 3);
+        assert_eq!(test.g.map().intersections().len(), 4);
+        let undo = resolve(&test, &second);
+        test.apply(&[undo]);
+        assert_eq!(test.g.map().roads().len(), 2);
+
+        // the first road is both halves now
+        let undo_first = resolve(&test, &first);
+        assert!(matches!(undo_first, Batch(ref v) if v.len() == 2));
+        test.apply(&[undo_first]);
+        let map = test.g.map();
+        assert_eq!(map.roads().len(), 0);
+        assert_eq!(map.intersections().len(), 0);
+    }
+
+    #[test]
+    fn decorations_go_away_with_their_road() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0., 0., 0.), vec3(100., 0., 0.)]);
+        let road = *test.g.map().roads().keys().next().unwrap();
+        let sign = |on| Decoration {
+            kind: DecorationKind::Sign("Main street".to_string()),
+            pos: vec3(50.0, 0.0, 0.0),
+            dir: Vec2::X,
+            size: 3.0,
+            color: Color::WHITE,
+            on,
+        };
+
+        test.apply(&[
+            MapAddDecoration(sign(DecorationSupport::Road(road))),
+            MapAddDecoration(sign(DecorationSupport::Ground)),
+            MapAddDecoration(Decoration {
+                kind: DecorationKind::Sign(String::new()),
+                ..sign(DecorationSupport::Ground)
+            }),
+        ]);
+        assert_eq!(test.g.map().decorations().len(), 2);
+
+        test.apply(&[MapRemoveRoad(road)]);
+        let map = test.g.map();
+        assert_eq!(map.decorations().len(), 1);
+        assert!(map
+            .decorations()
+            .values()
+            .all(|d| d.on == DecorationSupport::Ground));
+    }
+
+    #[test]
+    fn electric_trains_need_electrified_rails() {
+        let mut test = TestCtx::new();
+
+        test.apply(&[MapMakeConnection {
+            from: MapProject::ground(vec3(0.0, 0.0, 0.0)),
+            to: MapProject::ground(vec3(300.0, 0.0, 0.0)),
+            inter: None,
+            pat: LanePatternBuilder::new().rail(true).one_way(true).build(),
+        }]);
+
+        let (road, lane) = {
+            let map = test.g.map();
+            let (_, r) = map.roads().iter().next().unwrap();
+            (r.id, r.lanes_iter().next().unwrap().0)
+        };
+        let add_electric = AddTrain {
+            dist: 200.0,
+            n_wagons: 2,
+            lane,
+            loco: LocomotiveKind::Electric,
+        };
+
+        test.apply(&[add_electric.clone()]);
+        assert_eq!(test.g.world().trains.len(), 0);
+
+        test.apply(&[
+            MapSetRoadElectrified {
+                road,
+                electrified: true,
+            },
+            add_electric,
+        ]);
+        assert!(test.g.map().roads()[road].electrified);
+        assert_eq!(test.g.world().trains.len(), 1);
+    }
+
+    #[test]
+    fn trains_cannot_overlap() {
+        let mut test = TestCtx::new();
+
+        test.apply(&[MapMakeConnection {
+            from: MapProject::ground(vec3(0.0, 0.0, 0.0)),
+            to: MapProject::ground(vec3(300.0, 0.0, 0.0)),
+            inter: None,
+            pat: LanePatternBuilder::new().rail(true).one_way(true).build(),
+        }]);
+
+        let lane = test.g.map().lanes().keys().next().unwrap();
+        let add = |dist| AddTrain {
+            dist,
+            n_wagons: 2,
+            lane,
+            loco: LocomotiveKind::Diesel,
+        };
+
+        test.apply(&[add(100.0)]);
+        assert_eq!(test.g.world().trains.len(), 1);
+
+        test.apply(&[add(120.0)]);
+        assert_eq!(test.g.world().trains.len(), 1);
+
+        test.apply(&[add(10.0)]);
+        assert_eq!(test.g.world().trains.len(), 1);
+
+        test.apply(&[add(250.0)]);
+        assert_eq!(test.g.world().trains.len(), 2);
+    }
+
+    #[test]
+    fn curb_designations() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(200.0, 0.0, 0.0)]);
+        let road = test.g.map().roads().keys().next().unwrap();
+        let n_spots = test.g.map().parking.all_spots().count();
+        assert!(n_spots > 0);
+
+        let designate = |kind| {
+            [LaneDirection::Forward, LaneDirection::Backward].map(|side| MapSetRoadCurb {
+                road,
+                zone: CurbZone {
+                    side,
+                    start: 0.0,
+                    end: 100.0,
+                    kind,
+                },
+            })
+        };
+        test.apply(&designate(CurbKind::NoParking));
+        let n_left = test.g.map().parking.all_spots().count();
+        assert!(n_left > 0 && n_left < n_spots);
+
+        test.apply(&designate(CurbKind::LoadingZone));
+        let map = test.g.map();
+        assert_eq!(map.parking.all_spots().count(), n_spots);
+
+        let mut pm = ParkingManagement::default();
+        let near = vec3(50.0, 0.0, 0.0);
+        let car = pm.reserve_near(near, &map).unwrap();
+        assert_eq!(car.get(&map.parking).unwrap().curb, CurbKind::Parking);
+        let truck = pm.reserve_loading_near(near, &map).unwrap();
+        assert_eq!(truck.get(&map.parking).unwrap().curb, CurbKind::LoadingZone);
+    }
+
+    #[test]
+    fn road_furniture() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(200.0, 0.0, 0.0)]);
+        let road = test.g.map().roads().keys().next().unwrap();
+
+        let sign = |dist| MapAddRoadFurniture {
+            road,
+            furniture: RoadFurniture {
+                kind: FurnitureKind::StopSign,
+                side: LaneDirection::Forward,
+                dist,
+            },
+        };
+        assert!(sign(1000.0).validate(&test.g).is_err());
+
+        test.apply(&[sign(50.0), sign(150.0)]);
+        assert_eq!(test.g.map().roads()[road].furniture.items().len(), 2);
+
+        test.apply(&[MapRemoveRoadFurniture { road, dist: 52.0 }]);
+        let map = test.g.map();
+        let items = map.roads()[road].furniture.items();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].dist, 150.0);
+    }
+}